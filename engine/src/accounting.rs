@@ -0,0 +1,210 @@
+//! 按策略隔离的盈亏核算
+//!
+//! 每次执行结果按策略累计毛利、净利、手续费、执行次数与盈利次数 (胜率 = wins / executions)。
+//! 实时累计写入 Redis `metrics:strategy:{strategy_id}` (HINCRBY / HINCRBYFLOAT，引擎重启后继续累加)，
+//! 历史明细写入 Postgres `pnl_records`，并累加 `strategy_configs.total_trades / total_profit`。
+//! 写入目标由 ENGINE_STRATEGY_PNL_SINKS 配置 (默认 `redis,postgres`，设为空或 `none` 关闭)。
+
+use anyhow::Result;
+use redis::aio::ConnectionManager;
+use serde::Serialize;
+use sqlx::PgPool;
+use std::collections::BTreeMap;
+
+use crate::executor::ExecutionResult;
+use crate::strategy::StrategyType;
+
+/// Redis key 前缀
+const KEY_PREFIX: &str = "metrics:strategy:";
+
+/// 单个策略的累计盈亏
+#[derive(Debug, Clone, Serialize)]
+pub struct StrategyTotals {
+    pub strategy_type: StrategyType,
+    pub executions: u64,
+    pub wins: u64,
+    pub gross_profit: f64,
+    pub net_profit: f64,
+    pub fees: f64,
+    pub win_rate: f64,
+}
+
+/// 写入目标
+#[derive(Debug, Clone, Copy)]
+pub struct PnlSinks {
+    pub redis: bool,
+    pub postgres: bool,
+}
+
+impl PnlSinks {
+    pub fn from_env() -> Self {
+        let raw = std::env::var("ENGINE_STRATEGY_PNL_SINKS").unwrap_or_else(|_| "redis,postgres".to_string());
+        let sinks: Vec<String> = raw.split(',').map(|s| s.trim().to_lowercase()).collect();
+        Self {
+            redis: sinks.iter().any(|s| s == "redis"),
+            postgres: sinks.iter().any(|s| s == "postgres"),
+        }
+    }
+}
+
+/// 按策略的盈亏账本
+#[derive(Debug, Default)]
+pub struct StrategyLedger {
+    totals: BTreeMap<String, StrategyTotals>,
+}
+
+impl StrategyLedger {
+    /// 记录一次执行结果 (没有任何成交回报的执行不计入)
+    pub fn record(&mut self, result: &ExecutionResult) -> Option<&StrategyTotals> {
+        if result.orders.is_empty() {
+            return None;
+        }
+        let totals = self
+            .totals
+            .entry(result.signal.strategy_id.clone())
+            .or_insert_with(|| StrategyTotals {
+                strategy_type: result.signal.strategy_type,
+                executions: 0,
+                wins: 0,
+                gross_profit: 0.0,
+                net_profit: 0.0,
+                fees: 0.0,
+                win_rate: 0.0,
+            });
+        totals.executions += 1;
+        if result.net_profit > 0.0 {
+            totals.wins += 1;
+        }
+        totals.gross_profit += result.net_profit + result.total_fee;
+        totals.net_profit += result.net_profit;
+        totals.fees += result.total_fee;
+        totals.win_rate = totals.wins as f64 / totals.executions as f64;
+        Some(totals)
+    }
+
+    /// 测试用: 读取某个策略的累计
+    #[cfg(test)]
+    pub(crate) fn get(&self, strategy_id: &str) -> Option<&StrategyTotals> {
+        self.totals.get(strategy_id)
+    }
+}
+
+/// 单次执行的累加量写入 Redis
+pub async fn publish_redis(conn: &mut ConnectionManager, result: &ExecutionResult) -> Result<()> {
+    let key = format!("{}{}", KEY_PREFIX, result.signal.strategy_id);
+    let mut pipe = redis::pipe();
+    pipe.hincr(&key, "executions", 1i64).ignore();
+    pipe.hincr(&key, "wins", i64::from(result.net_profit > 0.0)).ignore();
+    pipe.hincr(&key, "gross_profit", result.net_profit + result.total_fee).ignore();
+    pipe.hincr(&key, "net_profit", result.net_profit).ignore();
+    pipe.hincr(&key, "fees", result.total_fee).ignore();
+    pipe.query_async::<()>(conn).await?;
+    Ok(())
+}
+
+/// 单次执行写入 Postgres: pnl_records 明细 + strategy_configs 累计
+pub async fn persist_postgres(pool: &PgPool, result: &ExecutionResult, elapsed_ms: u64) -> Result<()> {
+    let exchange = serde_json::to_value(result.signal.exchange)?;
+    let strategy_type = serde_json::to_value(result.signal.strategy_type)?;
+    let mut tx = pool.begin().await?;
+    sqlx::query(
+        "INSERT INTO pnl_records \
+             (strategy_id, strategy_type, exchange_id, path, gross_profit, fees, net_profit, profit_rate, execution_time_ms) \
+         VALUES ($1::uuid, $2::strategy_type, $3, $4, $5::numeric, $6::numeric, $7::numeric, $8::numeric, $9)",
+    )
+    .bind(&result.signal.strategy_id)
+    .bind(strategy_type.as_str())
+    .bind(exchange.as_str())
+    .bind(&result.signal.path)
+    .bind(result.net_profit + result.total_fee)
+    .bind(result.total_fee)
+    .bind(result.net_profit)
+    .bind(result.signal.profit_rate)
+    .bind(elapsed_ms.min(i32::MAX as u64) as i32)
+    .execute(&mut *tx)
+    .await?;
+    sqlx::query(
+        "UPDATE strategy_configs \
+         SET total_trades = total_trades + 1, total_profit = total_profit + $2::numeric, \
+             last_run_at = NOW(), updated_at = NOW() \
+         WHERE id = $1::uuid",
+    )
+    .bind(&result.signal.strategy_id)
+    .bind(result.net_profit)
+    .execute(&mut *tx)
+    .await?;
+    tx.commit().await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fake_redis::FakeRedis;
+    use crate::exchange::ExchangeId;
+    use crate::executor::{OrderResponse, OrderSide, OrderStatus};
+    use crate::strategy::Signal;
+
+    /// 单笔成交的执行结果
+    fn execution(strategy_id: &str, strategy_type: StrategyType, gross_profit: f64, fee: f64) -> ExecutionResult {
+        let signal = Signal::new(strategy_id, strategy_type, ExchangeId::Binance, 0.01, 1.0, 0.9, "BTC/USDT", 0);
+        let order = OrderResponse {
+            order_id: format!("{}-o", strategy_id),
+            exchange: ExchangeId::Binance,
+            symbol: "BTC/USDT".to_string(),
+            side: OrderSide::Buy,
+            status: OrderStatus::Filled,
+            filled_amount: 1.0,
+            avg_price: 100.0,
+            fee,
+            latency_ms: 10,
+            other_fees: vec![],
+        };
+        ExecutionResult::from_orders(signal, vec![order], gross_profit, false)
+    }
+
+    #[test]
+    fn fees_and_profit_are_isolated_per_strategy() {
+        let mut ledger = StrategyLedger::default();
+        ledger.record(&execution("grid-1", StrategyType::Grid, 2.0, 0.5));
+        ledger.record(&execution("grid-1", StrategyType::Grid, 0.2, 0.4));
+        ledger.record(&execution("tri-1", StrategyType::Triangular, 5.0, 1.0));
+        // 没有成交回报的执行不计入
+        let mut empty = execution("tri-1", StrategyType::Triangular, 0.0, 0.0);
+        empty.orders.clear();
+        assert!(ledger.record(&empty).is_none());
+
+        let grid = ledger.get("grid-1").unwrap();
+        assert_eq!((grid.executions, grid.wins), (2, 1));
+        assert!((grid.gross_profit - 2.2).abs() < 1e-9);
+        assert!((grid.fees - 0.9).abs() < 1e-9);
+        assert!((grid.net_profit - 1.3).abs() < 1e-9);
+        assert_eq!(grid.win_rate, 0.5);
+
+        let tri = ledger.get("tri-1").unwrap();
+        assert!(matches!(tri.strategy_type, StrategyType::Triangular));
+        assert_eq!((tri.executions, tri.wins), (1, 1));
+        assert!((tri.fees - 1.0).abs() < 1e-9);
+        assert!((tri.net_profit - 4.0).abs() < 1e-9);
+        assert!(ledger.get("pair-1").is_none());
+    }
+
+    #[tokio::test]
+    async fn redis_totals_are_keyed_by_strategy() {
+        let redis = FakeRedis::start().await;
+        let mut conn = redis.connection().await;
+        publish_redis(&mut conn, &execution("grid-1", StrategyType::Grid, 2.0, 0.5)).await.unwrap();
+        publish_redis(&mut conn, &execution("grid-1", StrategyType::Grid, 0.2, 0.4)).await.unwrap();
+        publish_redis(&mut conn, &execution("tri-1", StrategyType::Triangular, 5.0, 1.0)).await.unwrap();
+
+        let field = |id: &str, field: &str| -> f64 {
+            redis.hget(&format!("{}{}", KEY_PREFIX, id), field).unwrap().parse().unwrap()
+        };
+        assert_eq!(field("grid-1", "executions"), 2.0);
+        assert_eq!(field("grid-1", "wins"), 1.0);
+        assert!((field("grid-1", "fees") - 0.9).abs() < 1e-9);
+        assert!((field("grid-1", "net_profit") - 1.3).abs() < 1e-9);
+        assert_eq!(field("tri-1", "executions"), 1.0);
+        assert!((field("tri-1", "gross_profit") - 5.0).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,795 @@
+//! 引擎 HTTP 服务
+//!
+//! 提供状态快照 (/status、/metrics、/tickers、/signals、/positions、/tick-rates)、
+//! 带 Token 的已加载策略生效阈值与就绪状态 (/strategies)、
+//! 带 Bearer Token 的控制接口 (暂停策略、熔断开关、重载策略、紧急平仓 /admin/flatten)、
+//! 决策流的 SSE 推送 (/events/decisions，支持 Last-Event-ID 断线续传)，
+//! 仅限本机访问的调试页面 (/debug)，以及带 Token 的内部状态快照 (/debug/snapshot，事后排障用)。
+
+use axum::extract::{Path, Query, State};
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::sse::{Event, KeepAlive, Sse};
+use axum::response::{Html, IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use serde::{Deserialize, Serialize};
+use futures_util::stream::{self, Stream, StreamExt};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex, RwLock, TryLockError};
+use std::time::Duration;
+use tokio::sync::{broadcast, mpsc, oneshot};
+use tracing::{info, warn};
+
+use crate::accounting::StrategyTotals;
+use crate::exchange::{ExchangeId, Ticker};
+use crate::positions::Position;
+use crate::strategy::{Signal, StrategyType};
+use crate::symbols::same_symbol;
+use crate::tick_rate::TickRateStat;
+
+/// 保留的最近信号 / 成交条数
+const RECENT_LIMIT: usize = 50;
+/// 决策流保留的事件数 (用于 Last-Event-ID 续传)
+const DECISION_REPLAY_LIMIT: usize = 200;
+/// 调试页面
+const DEBUG_PAGE: &str = include_str!("debug_ui.html");
+/// 等待引擎主循环生成快照的超时
+const SNAPSHOT_TIMEOUT: Duration = Duration::from_secs(2);
+/// 等待紧急平仓完成的超时 (逐个持仓市价下单)
+const FLATTEN_TIMEOUT: Duration = Duration::from_secs(30);
+/// 读取共享状态时 try-lock 的重试次数
+const SNAPSHOT_LOCK_RETRIES: usize = 20;
+
+/// HTTP 服务配置
+#[derive(Debug, Clone)]
+pub struct ApiConfig {
+    pub addr: SocketAddr,
+    /// 控制接口的 Bearer Token (未配置时控制接口不可用)
+    pub token: Option<String>,
+    pub debug_ui: bool,
+    /// 调试页面默认展示的交易对
+    pub watchlist: Vec<String>,
+    /// 是否提供 /events/decisions
+    pub sse_decisions: bool,
+    /// 是否提供 /debug/snapshot (需要 Token)
+    pub debug_snapshot: bool,
+    /// 是否提供 /strategies
+    pub strategies: bool,
+    /// 是否提供紧急平仓 /admin/flatten (需要 Token)
+    pub flatten: bool,
+}
+
+impl ApiConfig {
+    /// 从环境变量读取 (ENGINE_HTTP_ADDR 为空时不启动 HTTP 服务)
+    pub fn from_env() -> Option<Self> {
+        let addr = std::env::var("ENGINE_HTTP_ADDR").unwrap_or_else(|_| "127.0.0.1:9810".to_string());
+        if addr.trim().is_empty() {
+            return None;
+        }
+        let addr = match addr.parse() {
+            Ok(addr) => addr,
+            Err(e) => {
+                warn!("ENGINE_HTTP_ADDR 无效 ({}): {}", addr, e);
+                return None;
+            }
+        };
+        Some(Self {
+            addr,
+            token: std::env::var("ENGINE_API_TOKEN").ok().filter(|v| !v.is_empty()),
+            debug_ui: std::env::var("ENGINE_DEBUG_UI")
+                .map(|v| matches!(v.as_str(), "1" | "true" | "True"))
+                .unwrap_or(false),
+            watchlist: std::env::var("ENGINE_DEBUG_WATCHLIST")
+                .unwrap_or_else(|_| "BTC/USDT,ETH/USDT".to_string())
+                .split(',')
+                .map(|s| s.trim().to_string())
+                .filter(|s| !s.is_empty())
+                .collect(),
+            sse_decisions: std::env::var("ENGINE_SSE_DECISIONS")
+                .map(|v| !matches!(v.as_str(), "0" | "false" | "False"))
+                .unwrap_or(true),
+            debug_snapshot: std::env::var("ENGINE_DEBUG_SNAPSHOT")
+                .map(|v| !matches!(v.as_str(), "0" | "false" | "False"))
+                .unwrap_or(true),
+            strategies: std::env::var("ENGINE_API_STRATEGIES")
+                .map(|v| !matches!(v.as_str(), "0" | "false" | "False"))
+                .unwrap_or(true),
+            flatten: std::env::var("ENGINE_API_FLATTEN")
+                .map(|v| !matches!(v.as_str(), "0" | "false" | "False"))
+                .unwrap_or(true),
+        })
+    }
+}
+
+/// 控制指令 (HTTP -> 引擎)
+#[derive(Debug)]
+pub enum ControlCommand {
+    PauseStrategy(String),
+    ResumeStrategy(String),
+    KillSwitch(bool),
+    Reload,
+    /// 在主循环内生成引擎内部状态快照
+    Snapshot(oneshot::Sender<serde_json::Value>),
+    /// 在主循环内导出已加载策略的生效阈值与就绪状态
+    Strategies(oneshot::Sender<serde_json::Value>),
+    /// 开启熔断并市价平掉所有持仓，回传逐个持仓的平仓结果
+    Flatten(oneshot::Sender<serde_json::Value>),
+}
+
+/// 交易所连接状态
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ExchangeHealth {
+    pub connected: bool,
+    /// WebSocket 断线重连成功次数
+    pub reconnects: u64,
+    pub last_ticker_at: i64,
+    pub tickers: u64,
+}
+
+/// 策略状态
+#[derive(Debug, Clone, Serialize)]
+pub struct StrategyStatus {
+    pub id: String,
+    pub strategy_type: StrategyType,
+    pub active: bool,
+    pub paused: bool,
+    pub panicked: bool,
+}
+
+/// 最近信号及其处理结果
+#[derive(Debug, Clone, Serialize)]
+pub struct SignalRecord {
+    pub correlation_id: String,
+    pub strategy_id: String,
+    pub strategy_type: StrategyType,
+    pub exchange: ExchangeId,
+    pub path: String,
+    pub profit_rate: f64,
+    pub edge_bps: f64,
+    pub confidence: f64,
+    pub priority: i32,
+    pub timestamp: i64,
+    /// queued / evicted / queue_full / duplicate / risk_blocked / observe_only / executed / failed
+    pub verdict: String,
+}
+
+/// 成交流水 (模拟模式下即纸面账本)
+#[derive(Debug, Clone, Serialize)]
+pub struct LedgerEntry {
+    pub correlation_id: String,
+    pub strategy_id: String,
+    pub path: String,
+    pub net_profit: f64,
+    pub simulated: bool,
+    pub timestamp: i64,
+}
+
+/// 引擎状态快照
+#[derive(Debug, Default)]
+pub struct EngineState {
+    pub mode: String,
+    pub started_at: i64,
+    pub halted: bool,
+    pub exchanges: BTreeMap<String, ExchangeHealth>,
+    pub strategies: Vec<StrategyStatus>,
+    pub tickers: HashMap<(ExchangeId, String), Ticker>,
+    pub signals: VecDeque<SignalRecord>,
+    pub positions: Vec<Position>,
+    pub ledger: VecDeque<LedgerEntry>,
+    pub metrics: BTreeMap<String, i64>,
+    /// 各交易对行情频率 (退化的排在前面)
+    pub tick_rates: Vec<TickRateStat>,
+    /// 按策略隔离的盈亏累计
+    pub strategy_pnl: BTreeMap<String, StrategyTotals>,
+}
+
+impl EngineState {
+    pub fn record_ticker(&mut self, ticker: &Ticker) {
+        let health = self.exchanges.entry(ticker.exchange.key().to_string()).or_default();
+        health.last_ticker_at = ticker.timestamp;
+        health.tickers += 1;
+        self.tickers
+            .insert((ticker.exchange, ticker.symbol.clone()), ticker.clone());
+    }
+
+    pub fn record_signal(&mut self, signal: &Signal, verdict: &str) {
+        if self.signals.len() == RECENT_LIMIT {
+            self.signals.pop_front();
+        }
+        self.signals.push_back(SignalRecord {
+            correlation_id: signal.correlation_id.clone(),
+            strategy_id: signal.strategy_id.clone(),
+            strategy_type: signal.strategy_type,
+            exchange: signal.exchange,
+            path: signal.path.clone(),
+            profit_rate: signal.profit_rate,
+            edge_bps: signal.edge_bps,
+            confidence: signal.confidence,
+            priority: signal.priority,
+            timestamp: signal.timestamp,
+            verdict: verdict.to_string(),
+        });
+    }
+
+    /// 更新已记录信号的处理结果
+    pub fn set_verdict(&mut self, correlation_id: &str, verdict: &str) {
+        if let Some(record) = self
+            .signals
+            .iter_mut()
+            .rev()
+            .find(|r| r.correlation_id == correlation_id)
+        {
+            record.verdict = verdict.to_string();
+        }
+    }
+
+    pub fn record_ledger(&mut self, entry: LedgerEntry) {
+        if self.ledger.len() == RECENT_LIMIT {
+            self.ledger.pop_front();
+        }
+        self.ledger.push_back(entry);
+    }
+}
+
+/// 决策事件 (id 单调递增)
+#[derive(Debug, Clone)]
+pub struct DecisionEvent {
+    pub id: u64,
+    pub payload: serde_json::Value,
+}
+
+/// 决策事件总线: 广播给 SSE 订阅者，并保留最近事件供断线续传
+struct DecisionFeed {
+    tx: broadcast::Sender<DecisionEvent>,
+    /// 最近事件与下一个事件 id
+    recent: Mutex<(VecDeque<DecisionEvent>, u64)>,
+}
+
+impl Default for DecisionFeed {
+    fn default() -> Self {
+        let (tx, _) = broadcast::channel(DECISION_REPLAY_LIMIT);
+        Self {
+            tx,
+            recent: Mutex::new((VecDeque::new(), 1)),
+        }
+    }
+}
+
+/// 引擎与 HTTP 服务共享的状态
+#[derive(Clone, Default)]
+pub struct SharedState {
+    state: Arc<RwLock<EngineState>>,
+    decisions: Arc<DecisionFeed>,
+}
+
+impl SharedState {
+    pub fn new(mode: &str) -> Self {
+        let state = EngineState {
+            mode: mode.to_string(),
+            started_at: chrono::Utc::now().timestamp_millis(),
+            ..Default::default()
+        };
+        Self {
+            state: Arc::new(RwLock::new(state)),
+            decisions: Arc::default(),
+        }
+    }
+
+    /// 修改状态 (锁中毒时忽略，调试状态不应影响交易)
+    pub fn update(&self, f: impl FnOnce(&mut EngineState)) {
+        if let Ok(mut state) = self.state.write() {
+            f(&mut state);
+        }
+    }
+
+    pub fn read<T>(&self, f: impl FnOnce(&EngineState) -> T) -> Option<T> {
+        self.state.read().ok().map(|state| f(&state))
+    }
+
+    /// 非阻塞读取: 写锁被占用时短暂让出后重试，仍拿不到锁返回 None
+    pub async fn try_read<T>(&self, f: impl FnOnce(&EngineState) -> T) -> Option<T> {
+        let mut f = Some(f);
+        for _ in 0..SNAPSHOT_LOCK_RETRIES {
+            // 锁结果不能跨 await 持有
+            let blocked = match self.state.try_read() {
+                Ok(state) => return f.take().map(|f| f(&state)),
+                Err(TryLockError::WouldBlock) => true,
+                Err(TryLockError::Poisoned(_)) => false,
+            };
+            if !blocked {
+                return None;
+            }
+            tokio::task::yield_now().await;
+        }
+        None
+    }
+
+    /// 发布决策事件
+    pub fn publish_decision(&self, payload: serde_json::Value) {
+        let Ok(mut recent) = self.decisions.recent.lock() else {
+            return;
+        };
+        let (events, next_id) = &mut *recent;
+        let event = DecisionEvent { id: *next_id, payload };
+        *next_id += 1;
+        if events.len() == DECISION_REPLAY_LIMIT {
+            events.pop_front();
+        }
+        events.push_back(event.clone());
+        // 在锁内发送，保证订阅时的补发与实时事件不重不漏
+        let _ = self.decisions.tx.send(event);
+    }
+
+    /// 订阅决策事件，返回 id 大于 last_event_id 的已保留事件与实时接收端
+    pub(crate) fn subscribe_decisions(&self, last_event_id: Option<u64>) -> (Vec<DecisionEvent>, broadcast::Receiver<DecisionEvent>) {
+        let recent = match self.decisions.recent.lock() {
+            Ok(recent) => recent,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        let rx = self.decisions.tx.subscribe();
+        let replay = match last_event_id {
+            Some(last) => recent.0.iter().filter(|e| e.id > last).cloned().collect(),
+            None => vec![],
+        };
+        (replay, rx)
+    }
+}
+
+#[derive(Clone)]
+struct AppState {
+    state: SharedState,
+    control: mpsc::UnboundedSender<ControlCommand>,
+    config: ApiConfig,
+}
+
+/// 构建路由
+pub fn router(config: ApiConfig, state: SharedState, control: mpsc::UnboundedSender<ControlCommand>) -> Router {
+    let mut app = Router::new()
+        .route("/status", get(status))
+        .route("/metrics", get(metrics))
+        .route("/tickers", get(tickers))
+        .route("/signals", get(signals))
+        .route("/positions", get(positions))
+        .route("/tick-rates", get(tick_rates))
+        .route("/control/strategies/:id/pause", post(pause_strategy))
+        .route("/control/strategies/:id/resume", post(resume_strategy))
+        .route("/control/kill", post(kill_switch))
+        .route("/control/reload", post(reload));
+    if config.sse_decisions {
+        app = app.route("/events/decisions", get(decision_events));
+    }
+    if config.debug_snapshot {
+        app = app.route("/debug/snapshot", get(debug_snapshot));
+    }
+    if config.strategies {
+        app = app.route("/strategies", get(strategies));
+    }
+    if config.flatten {
+        app = app.route("/admin/flatten", post(flatten));
+    }
+    if config.debug_ui {
+        if config.addr.ip().is_loopback() {
+            app = app.route("/debug", get(debug_page));
+        } else {
+            warn!("调试页面仅允许监听本机地址，当前 {}，已禁用", config.addr);
+        }
+    }
+    app.with_state(AppState { state, control, config })
+}
+
+/// 启动 HTTP 服务
+pub async fn serve(
+    config: ApiConfig,
+    state: SharedState,
+    control: mpsc::UnboundedSender<ControlCommand>,
+) -> anyhow::Result<()> {
+    let listener = tokio::net::TcpListener::bind(config.addr).await?;
+    info!("HTTP 服务监听 {}", config.addr);
+    axum::serve(listener, router(config, state, control)).await?;
+    Ok(())
+}
+
+async fn status(State(app): State<AppState>) -> Response {
+    let now = chrono::Utc::now().timestamp_millis();
+    let body = app.state.read(|s| {
+        serde_json::json!({
+            "mode": s.mode,
+            "uptimeMs": now - s.started_at,
+            "halted": s.halted,
+            "exchanges": s.exchanges,
+            "strategies": s.strategies,
+            "strategyPnl": s.strategy_pnl,
+        })
+    });
+    json_or_unavailable(body)
+}
+
+async fn metrics(State(app): State<AppState>) -> Response {
+    json_or_unavailable(app.state.read(|s| serde_json::json!(s.metrics)))
+}
+
+#[derive(Deserialize)]
+struct TickerQuery {
+    /// 逗号分隔的交易对，缺省使用配置的 watchlist
+    symbols: Option<String>,
+}
+
+async fn tickers(State(app): State<AppState>, Query(query): Query<TickerQuery>) -> Response {
+    let watchlist: Vec<String> = match query.symbols {
+        Some(symbols) => symbols.split(',').map(|s| s.trim().to_string()).collect(),
+        None => app.config.watchlist.clone(),
+    };
+    let body = app.state.read(|s| {
+        let mut out: Vec<&Ticker> = s
+            .tickers
+            .values()
+            .filter(|t| watchlist.iter().any(|w| same_symbol(w, &t.symbol)))
+            .collect();
+        out.sort_by_key(|t| (t.symbol.clone(), t.exchange.key()));
+        serde_json::json!({ "watchlist": watchlist, "tickers": out })
+    });
+    json_or_unavailable(body)
+}
+
+async fn signals(State(app): State<AppState>) -> Response {
+    json_or_unavailable(app.state.read(|s| {
+        let recent: Vec<&SignalRecord> = s.signals.iter().rev().collect();
+        serde_json::json!({ "signals": recent })
+    }))
+}
+
+async fn positions(State(app): State<AppState>) -> Response {
+    json_or_unavailable(app.state.read(|s| {
+        let ledger: Vec<&LedgerEntry> = s.ledger.iter().rev().collect();
+        serde_json::json!({ "positions": s.positions, "ledger": ledger })
+    }))
+}
+
+async fn tick_rates(State(app): State<AppState>) -> Response {
+    json_or_unavailable(app.state.read(|s| {
+        let degraded = s.tick_rates.iter().filter(|r| r.degraded).count();
+        serde_json::json!({ "degraded": degraded, "symbols": s.tick_rates })
+    }))
+}
+
+/// 决策流 SSE: 带 Last-Event-ID 重连时先补发之后的事件
+async fn decision_events(
+    State(app): State<AppState>,
+    headers: HeaderMap,
+) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    let last_event_id = headers
+        .get("last-event-id")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.trim().parse().ok());
+    let (replay, rx) = app.state.subscribe_decisions(last_event_id);
+    let live = stream::unfold(rx, |mut rx| async move {
+        loop {
+            match rx.recv().await {
+                Ok(event) => return Some((event, rx)),
+                Err(broadcast::error::RecvError::Lagged(n)) => warn!("SSE 订阅者滞后，丢弃 {} 条决策事件", n),
+                Err(broadcast::error::RecvError::Closed) => return None,
+            }
+        }
+    });
+    let events = stream::iter(replay).chain(live).map(|event| {
+        Ok(Event::default()
+            .id(event.id.to_string())
+            .event("decision")
+            .data(event.payload.to_string()))
+    });
+    Sse::new(events).keep_alive(KeepAlive::default())
+}
+
+async fn pause_strategy(State(app): State<AppState>, headers: HeaderMap, Path(id): Path<String>) -> Response {
+    send_control(&app, &headers, ControlCommand::PauseStrategy(id))
+}
+
+async fn resume_strategy(State(app): State<AppState>, headers: HeaderMap, Path(id): Path<String>) -> Response {
+    send_control(&app, &headers, ControlCommand::ResumeStrategy(id))
+}
+
+#[derive(Deserialize)]
+struct KillRequest {
+    enabled: bool,
+}
+
+async fn kill_switch(State(app): State<AppState>, headers: HeaderMap, Json(req): Json<KillRequest>) -> Response {
+    send_control(&app, &headers, ControlCommand::KillSwitch(req.enabled))
+}
+
+async fn reload(State(app): State<AppState>, headers: HeaderMap) -> Response {
+    send_control(&app, &headers, ControlCommand::Reload)
+}
+
+async fn debug_page() -> Html<&'static str> {
+    Html(DEBUG_PAGE)
+}
+
+/// 已加载策略: 类型、名称、生效阈值 (含默认值) 与就绪状态
+async fn strategies(State(app): State<AppState>, headers: HeaderMap) -> Response {
+    if !authorized(app.config.token.as_deref(), &headers) {
+        return (StatusCode::UNAUTHORIZED, Json(serde_json::json!({ "success": false, "error": "unauthorized" })))
+            .into_response();
+    }
+    let (tx, rx) = oneshot::channel();
+    if app.control.send(ControlCommand::Strategies(tx)).is_err() {
+        return json_or_unavailable(None);
+    }
+    match tokio::time::timeout(SNAPSHOT_TIMEOUT, rx).await {
+        Ok(Ok(body)) => Json(body).into_response(),
+        _ => {
+            warn!("引擎主循环未在 {:?} 内响应策略列表请求", SNAPSHOT_TIMEOUT);
+            json_or_unavailable(None)
+        }
+    }
+}
+
+/// 紧急平仓: 开启熔断并市价平掉所有交易所的持仓，返回逐个持仓的结果；重复调用是安全的
+async fn flatten(State(app): State<AppState>, headers: HeaderMap) -> Response {
+    if !authorized(app.config.token.as_deref(), &headers) {
+        return (StatusCode::UNAUTHORIZED, Json(serde_json::json!({ "success": false, "error": "unauthorized" })))
+            .into_response();
+    }
+    warn!("收到紧急平仓请求");
+    let (tx, rx) = oneshot::channel();
+    if app.control.send(ControlCommand::Flatten(tx)).is_err() {
+        return json_or_unavailable(None);
+    }
+    match tokio::time::timeout(FLATTEN_TIMEOUT, rx).await {
+        Ok(Ok(result)) => {
+            let success = result["failed"].as_u64() == Some(0);
+            let mut body = serde_json::json!({ "success": success });
+            if let (Some(body), Some(result)) = (body.as_object_mut(), result.as_object()) {
+                body.extend(result.iter().filter(|(k, _)| *k != "event").map(|(k, v)| (k.clone(), v.clone())));
+            }
+            Json(body).into_response()
+        }
+        _ => {
+            warn!("引擎主循环未在 {:?} 内完成紧急平仓", FLATTEN_TIMEOUT);
+            json_or_unavailable(None)
+        }
+    }
+}
+
+/// 内部状态快照: 引擎部分 (已加载策略、阈值与策略内部缓存、队列) 由主循环生成，
+/// 共享状态部分 (行情缓存、持仓、指标) 用 try-lock 读取，均不阻塞行情处理
+async fn debug_snapshot(State(app): State<AppState>, headers: HeaderMap) -> Response {
+    if !authorized(app.config.token.as_deref(), &headers) {
+        return (StatusCode::UNAUTHORIZED, Json(serde_json::json!({ "success": false, "error": "unauthorized" })))
+            .into_response();
+    }
+    let (tx, rx) = oneshot::channel();
+    if app.control.send(ControlCommand::Snapshot(tx)).is_err() {
+        return json_or_unavailable(None);
+    }
+    let engine = match tokio::time::timeout(SNAPSHOT_TIMEOUT, rx).await {
+        Ok(Ok(engine)) => engine,
+        _ => {
+            warn!("引擎主循环未在 {:?} 内响应快照请求", SNAPSHOT_TIMEOUT);
+            return json_or_unavailable(None);
+        }
+    };
+    let state = app
+        .state
+        .try_read(|s| {
+            let mut tickers: Vec<&Ticker> = s.tickers.values().collect();
+            tickers.sort_by_key(|t| (t.exchange.key(), t.symbol.clone()));
+            serde_json::json!({
+                "mode": s.mode,
+                "halted": s.halted,
+                "exchanges": s.exchanges,
+                "tickers": tickers,
+                "positions": s.positions,
+                "metrics": s.metrics,
+                "tickRates": s.tick_rates,
+                "strategyPnl": s.strategy_pnl,
+            })
+        })
+        .await;
+    Json(serde_json::json!({
+        "timestamp": chrono::Utc::now().timestamp_millis(),
+        "engine": engine,
+        "state": state,
+    }))
+    .into_response()
+}
+
+fn send_control(app: &AppState, headers: &HeaderMap, command: ControlCommand) -> Response {
+    if !authorized(app.config.token.as_deref(), headers) {
+        return (StatusCode::UNAUTHORIZED, Json(serde_json::json!({ "success": false, "error": "unauthorized" })))
+            .into_response();
+    }
+    info!("收到控制指令: {:?}", command);
+    if app.control.send(command).is_err() {
+        return json_or_unavailable(None);
+    }
+    Json(serde_json::json!({ "success": true })).into_response()
+}
+
+/// 校验 Bearer Token；未配置 Token 时拒绝所有控制请求
+fn authorized(token: Option<&str>, headers: &HeaderMap) -> bool {
+    let Some(token) = token else {
+        return false;
+    };
+    headers
+        .get("authorization")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .map(|v| v == token)
+        .unwrap_or(false)
+}
+
+fn json_or_unavailable(body: Option<serde_json::Value>) -> Response {
+    match body {
+        Some(body) => Json(body).into_response(),
+        None => (
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(serde_json::json!({ "success": false, "error": "engine state unavailable" })),
+        )
+            .into_response(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn config() -> ApiConfig {
+        ApiConfig {
+            addr: "127.0.0.1:0".parse().unwrap(),
+            token: Some("secret".to_string()),
+            debug_ui: true,
+            watchlist: vec![],
+            sse_decisions: true,
+            debug_snapshot: true,
+            strategies: true,
+            flatten: true,
+        }
+    }
+
+    /// 在随机端口启动 HTTP 服务，返回地址与控制指令接收端
+    async fn serve_api(state: SharedState) -> (SocketAddr, mpsc::UnboundedReceiver<ControlCommand>) {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let _ = axum::serve(listener, router(config(), state, tx)).await;
+        });
+        (addr, rx)
+    }
+
+    async fn get_json(addr: SocketAddr, path: &str) -> serde_json::Value {
+        let response = reqwest::get(format!("http://{}{}", addr, path)).await.unwrap();
+        assert_eq!(response.status().as_u16(), 200, "{}", path);
+        response.json().await.unwrap()
+    }
+
+    /// 断言对象含有全部字段
+    fn assert_fields(value: &serde_json::Value, fields: &[&str]) {
+        for field in fields {
+            assert!(value.get(field).is_some(), "缺少字段 {}: {}", field, value);
+        }
+    }
+
+    /// 从 SSE 响应中读取事件，直到收到 `count` 个事件 id
+    async fn read_event_ids(response: &mut reqwest::Response, count: usize) -> Vec<u64> {
+        let mut buffer = String::new();
+        let mut ids = vec![];
+        while ids.len() < count {
+            let chunk = tokio::time::timeout(Duration::from_secs(2), response.chunk())
+                .await
+                .expect("等待 SSE 事件超时")
+                .unwrap()
+                .expect("SSE 连接提前关闭");
+            buffer.push_str(&String::from_utf8_lossy(&chunk));
+            while let Some(end) = buffer.find("\n\n") {
+                let frame: String = buffer.drain(..end + 2).collect();
+                if !frame.contains("event: decision") {
+                    continue;
+                }
+                let id = frame.lines().find_map(|l| l.strip_prefix("id: ")).expect("事件缺少 id");
+                ids.push(id.trim().parse().unwrap());
+            }
+        }
+        ids
+    }
+
+    #[tokio::test]
+    async fn sse_streams_decisions_with_incrementing_ids() {
+        let state = SharedState::new("simulation");
+        let (addr, _control) = serve_api(state.clone()).await;
+        let mut response = reqwest::get(format!("http://{}/events/decisions", addr)).await.unwrap();
+        assert_eq!(response.status().as_u16(), 200);
+        assert!(response.headers()["content-type"].to_str().unwrap().starts_with("text/event-stream"));
+        // 订阅在处理请求时建立，响应头返回后发布的事件都能收到
+        state.publish_decision(serde_json::json!({"path": "USDT->BTC->ETH->USDT"}));
+        state.publish_decision(serde_json::json!({"path": "USDT->ETH->BTC->USDT"}));
+        assert_eq!(read_event_ids(&mut response, 2).await, vec![1, 2]);
+    }
+
+    #[tokio::test]
+    async fn sse_reconnect_replays_after_last_event_id() {
+        let state = SharedState::new("simulation");
+        for i in 0..3 {
+            state.publish_decision(serde_json::json!({ "seq": i }));
+        }
+        let (addr, _control) = serve_api(state.clone()).await;
+        let mut response = reqwest::Client::new()
+            .get(format!("http://{}/events/decisions", addr))
+            .header("Last-Event-ID", "1")
+            .send()
+            .await
+            .unwrap();
+        state.publish_decision(serde_json::json!({ "seq": 3 }));
+        assert_eq!(read_event_ids(&mut response, 3).await, vec![2, 3, 4]);
+    }
+
+    #[tokio::test]
+    async fn debug_ui_endpoints_return_documented_state_shape() {
+        let state = SharedState::new("simulation");
+        state.update(|s| {
+            let ticker: Ticker = serde_json::from_value(serde_json::json!({
+                "exchange": "binance", "symbol": "BTC/USDT", "bid": 99.9, "ask": 100.1,
+                "last": 100.0, "volume": 1000.0, "timestamp": 1_000,
+            }))
+            .unwrap();
+            s.record_ticker(&ticker);
+            s.strategies.push(StrategyStatus {
+                id: "grid-1".to_string(),
+                strategy_type: StrategyType::Grid,
+                active: true,
+                paused: false,
+                panicked: false,
+            });
+            let signal = Signal::new("grid-1", StrategyType::Grid, ExchangeId::Binance, 0.002, 0.2, 0.8, "BTC/USDT", 1_000);
+            s.record_signal(&signal, "queued");
+            s.set_verdict(&signal.correlation_id, "executed");
+            s.positions.push(Position {
+                exchange: ExchangeId::Binance,
+                symbol: "BTC/USDT".to_string(),
+                quantity: 0.5,
+                avg_price: 100.0,
+            });
+            s.record_ledger(LedgerEntry {
+                correlation_id: signal.correlation_id.clone(),
+                strategy_id: "grid-1".to_string(),
+                path: "BTC/USDT".to_string(),
+                net_profit: 0.15,
+                simulated: true,
+                timestamp: 1_050,
+            });
+            s.metrics.insert("signals_stale".to_string(), 2);
+        });
+        let (addr, _control) = serve_api(state).await;
+
+        let page = reqwest::get(format!("http://{}/debug", addr)).await.unwrap().text().await.unwrap();
+        for path in ["'/status'", "'/metrics'", "'/tickers'", "'/signals'", "'/positions'"] {
+            assert!(page.contains(path), "调试页面未请求 {}", path);
+        }
+
+        let status = get_json(addr, "/status").await;
+        assert_fields(&status, &["mode", "uptimeMs", "halted", "exchanges", "strategies", "strategyPnl"]);
+        assert_eq!(status["mode"], "simulation");
+        assert_fields(&status["exchanges"]["binance"], &["connected", "reconnects", "last_ticker_at", "tickers"]);
+        assert_eq!(status["exchanges"]["binance"]["tickers"], 1);
+        assert_fields(&status["strategies"][0], &["id", "strategy_type", "active", "paused", "panicked"]);
+
+        assert_eq!(get_json(addr, "/metrics").await["signals_stale"], 2);
+
+        let tickers = get_json(addr, "/tickers?symbols=BTCUSDT").await;
+        assert_eq!(tickers["watchlist"], serde_json::json!(["BTCUSDT"]));
+        assert_fields(&tickers["tickers"][0], &["exchange", "symbol", "bid", "ask", "last", "timestamp"]);
+
+        let signals = get_json(addr, "/signals").await;
+        let signal = &signals["signals"][0];
+        assert_fields(signal, &["timestamp", "strategy_id", "exchange", "path", "profit_rate", "confidence", "priority"]);
+        assert_eq!(signal["verdict"], "executed");
+
+        let positions = get_json(addr, "/positions").await;
+        assert_fields(&positions["positions"][0], &["exchange", "symbol", "quantity", "avg_price"]);
+        assert_fields(&positions["ledger"][0], &["timestamp", "strategy_id", "path", "net_profit", "simulated"]);
+    }
+}
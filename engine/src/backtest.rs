@@ -0,0 +1,299 @@
+//! 回测回放
+//!
+//! 按录制的行情流驱动引擎: 回放时钟随行情时间戳推进，每条行情走与实盘相同的处理路径
+//! (故障注入 → 行情健康度 → 策略分发 → 陈旧 / 缺失报价检查 → 入队)，
+//! 因此陈旧行情保护、调度窗口、故障注入计划在回测中与实盘行为一致。
+//!
+//! 回放文件为 JSONL，每行一条 `Ticker`，或一条合成的停机事件:
+//! ```json
+//! {"event": "gap", "exchange": "okx", "duration_ms": 30000}
+//! {"event": "gap", "exchange": "okx", "symbol": "ETH/USDT", "duration_ms": 30000}
+//! {"event": "disconnect", "exchange": "okx"}
+//! ```
+//! `gap` 从当前回放时间起冻结该交易所 (给出 symbol 时仅该交易对) duration_ms 内的行情，模拟行情中断
+//! (作为 `feed_freeze` 故障注入，与故障计划中的行情冻结走同一条丢弃路径)；
+//! `disconnect` 标记交易所断线，其全部交易对视为陈旧，直到回放中出现该交易所的下一笔行情。
+//!
+//! `ENGINE_MODE=backtest` 时引擎不连接交易所，按 ENGINE_BACKTEST_FILE 回放一遍后输出汇总 (信号数、预期收益合计与按策略明细) 并退出；
+//! 策略取自 ENGINE_BACKTEST_STRATEGIES (StrategyConfig 数组的 JSON 文件)，未设置时从数据库加载已启用的策略。
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::Arc;
+use tracing::info;
+
+use crate::engine::{Clock, Engine};
+use crate::exchange::{ExchangeId, Ticker};
+use crate::faults::{FaultInjector, FaultKind, FaultPlan};
+use crate::report::StrategyReport;
+use crate::strategy::StrategyConfig;
+
+/// 回放时钟 (由回放过程推进)
+#[derive(Debug, Default)]
+pub struct ReplayClock {
+    now_ms: AtomicI64,
+}
+
+impl ReplayClock {
+    pub fn new(start_ms: i64) -> Self {
+        Self {
+            now_ms: AtomicI64::new(start_ms),
+        }
+    }
+
+    /// 推进到指定时间 (不回退)
+    pub fn advance_to(&self, ts: i64) {
+        self.now_ms.fetch_max(ts, Ordering::Relaxed);
+    }
+}
+
+impl Clock for ReplayClock {
+    fn now_ms(&self) -> i64 {
+        self.now_ms.load(Ordering::Relaxed)
+    }
+}
+
+/// 合成的停机事件
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum DowntimeEvent {
+    /// 行情中断 (symbol 为空时整个交易所)
+    Gap {
+        exchange: ExchangeId,
+        #[serde(default)]
+        symbol: Option<String>,
+        duration_ms: i64,
+    },
+    /// 交易所断线
+    Disconnect { exchange: ExchangeId },
+}
+
+/// 回放流中的一条记录
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum ReplayEvent {
+    Downtime(DowntimeEvent),
+    Ticker(Ticker),
+}
+
+/// 回放结果
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReplaySummary {
+    pub tickers: u64,
+    /// 因行情中断被丢弃的行情
+    pub tickers_dropped: u64,
+    pub gaps: u64,
+    pub disconnects: u64,
+    /// 等待执行的信号
+    pub signals_queued: usize,
+    /// 因行情陈旧被抑制的信号
+    pub signals_suppressed_stale: u64,
+    pub stale_symbols: BTreeSet<String>,
+    /// 策略产生的信号 (不含调度窗口外被抑制的)
+    pub signals: u64,
+    /// 信号预期收益合计
+    pub expected_profit: f64,
+    /// 按策略明细
+    pub strategies: Vec<StrategyReport>,
+}
+
+/// 读取 JSONL 回放文件 (忽略空行)
+pub fn load_events(path: &Path) -> Result<Vec<ReplayEvent>> {
+    let raw = std::fs::read_to_string(path).with_context(|| format!("读取回放文件 {} 失败", path.display()))?;
+    raw.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| {
+            serde_json::from_str(line).with_context(|| format!("解析回放文件 {} 第 {} 行失败", path.display(), i + 1))
+        })
+        .collect()
+}
+
+/// 按顺序回放事件。引擎须以同一个 `ReplayClock` 创建；
+/// 未启用故障注入时挂载一个空计划的注入器，用于注入行情中断与统计陈旧抑制。
+pub async fn replay(
+    engine: &mut Engine,
+    clock: &ReplayClock,
+    events: impl IntoIterator<Item = ReplayEvent>,
+) -> ReplaySummary {
+    let faults = match engine.fault_injector() {
+        Some(faults) => faults,
+        None => {
+            let faults = Arc::new(FaultInjector::new(FaultPlan::default()));
+            engine.set_fault_injector(faults.clone());
+            faults
+        }
+    };
+    let dropped_before = faults.report().ticks_dropped;
+    let mut summary = ReplaySummary::default();
+    for event in events {
+        match event {
+            ReplayEvent::Ticker(ticker) => {
+                clock.advance_to(ticker.timestamp);
+                summary.tickers += 1;
+                engine.replay_ticker(&ticker).await;
+            }
+            ReplayEvent::Downtime(DowntimeEvent::Gap {
+                exchange,
+                symbol,
+                duration_ms,
+            }) => {
+                info!("回放行情中断: {:?} {:?} {} 毫秒", exchange, symbol, duration_ms);
+                summary.gaps += 1;
+                faults.inject(
+                    FaultKind::FeedFreeze { exchange, symbol },
+                    clock.now_ms(),
+                    duration_ms,
+                );
+            }
+            ReplayEvent::Downtime(DowntimeEvent::Disconnect { exchange }) => {
+                summary.disconnects += 1;
+                engine.replay_disconnect(exchange);
+            }
+        }
+    }
+    summary.signals_queued = engine.queued_signals();
+    let report = faults.report();
+    // 行情中断与故障计划中的冻结丢弃的行情不算送达
+    summary.tickers_dropped = report.ticks_dropped - dropped_before;
+    summary.tickers -= summary.tickers_dropped;
+    summary.signals_suppressed_stale = report.signals_suppressed_stale;
+    summary.stale_symbols = report.stale_symbols;
+    if let Some(report) = engine.sim_report() {
+        summary.signals = report.strategies.iter().map(|s| s.signals).sum();
+        summary.expected_profit = report.strategies.iter().map(|s| s.expected_profit).sum();
+        summary.strategies = report.strategies;
+    }
+    summary
+}
+
+/// 读取 JSONL 行情文件并回放，返回汇总
+pub async fn replay_from_jsonl(path: &Path, engine: &mut Engine, clock: &ReplayClock) -> Result<ReplaySummary> {
+    let events = load_events(path)?;
+    info!("回放 {}: {} 条记录", path.display(), events.len());
+    Ok(replay(engine, clock, events).await)
+}
+
+/// 回放文件 (ENGINE_BACKTEST_FILE)
+pub fn replay_file_from_env() -> Option<PathBuf> {
+    std::env::var("ENGINE_BACKTEST_FILE")
+        .ok()
+        .filter(|v| !v.is_empty())
+        .map(PathBuf::from)
+}
+
+/// 回测策略配置文件 (ENGINE_BACKTEST_STRATEGIES，未设置时为 None)
+pub fn load_strategies_from_env() -> Result<Option<Vec<StrategyConfig>>> {
+    let Some(path) = std::env::var("ENGINE_BACKTEST_STRATEGIES").ok().filter(|v| !v.is_empty()) else {
+        return Ok(None);
+    };
+    let raw = std::fs::read_to_string(&path).with_context(|| format!("读取回测策略文件 {} 失败", path))?;
+    let configs = serde_json::from_str(&raw).with_context(|| format!("解析回测策略文件 {} 失败", path))?;
+    Ok(Some(configs))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::executor::OrderExecutor;
+
+    fn grid(id: &str, symbol: &str) -> StrategyConfig {
+        serde_json::from_value(serde_json::json!({
+            "id": id,
+            "strategy_type": "grid",
+            "name": id,
+            "is_enabled": true,
+            "priority": 1,
+            "config": {"symbol": symbol, "lower_price": 90.0, "upper_price": 110.0, "grid_count": 20, "include_funding": false},
+        }))
+        .unwrap()
+    }
+
+    /// 每秒一笔、每笔都穿越一条格线的行情
+    fn ticks(symbol: &str, from_s: i64, to_s: i64) -> Vec<ReplayEvent> {
+        (from_s..to_s)
+            .map(|s| {
+                let price = if s % 2 == 0 { 100.5 } else { 101.5 };
+                ReplayEvent::Ticker(
+                    serde_json::from_value(serde_json::json!({
+                        "exchange": "binance", "symbol": symbol, "bid": price - 0.01, "ask": price + 0.01,
+                        "last": price, "volume": 1000.0, "timestamp": s * 1_000,
+                    }))
+                    .unwrap(),
+                )
+            })
+            .collect()
+    }
+
+    fn sim_engine(clock: &Arc<ReplayClock>) -> Engine {
+        let mut executor = OrderExecutor::new(Default::default(), None);
+        executor.set_simulation_mode(true);
+        Engine::new(executor, None, clock.clone())
+    }
+
+    #[tokio::test]
+    async fn strategy_stays_inert_until_active_from() {
+        let clock = Arc::new(ReplayClock::new(0));
+        let mut engine = sim_engine(&clock);
+        let mut config = grid("delayed", "BTC/USDT");
+        config.active_from = Some(10_000);
+        engine.load_strategies(vec![config]);
+
+        // 生效前照常接收行情，但不产生信号
+        let before = replay(&mut engine, &clock, ticks("BTC/USDT", 0, 10)).await;
+        assert_eq!(before.tickers, 10);
+        assert_eq!(before.signals, 0);
+        assert_eq!(before.signals_queued, 0);
+
+        // 时钟越过 active_from 后开始产生信号
+        let after = replay(&mut engine, &clock, ticks("BTC/USDT", 10, 20)).await;
+        assert!(after.signals > 0, "{:?}", after);
+    }
+
+    #[tokio::test]
+    async fn gap_drops_ticks_and_suppresses_signals_on_stale_legs() {
+        let dir = std::env::temp_dir().join(format!("inarbit-downtime-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("downtime.jsonl");
+        // ETH/BTC 中断 30 秒: 中断期间的 ETH/BTC 行情被丢弃，ETH/USDT 照常送达
+        let lines = [
+            r#"{"exchange":"binance","symbol":"BTC/USDT","bid":30000.0,"ask":30001.0,"last":30000.5,"volume":1200.0,"timestamp":0}"#,
+            r#"{"exchange":"binance","symbol":"ETH/BTC","bid":0.05198,"ask":0.05200,"last":0.05199,"volume":9000.0,"timestamp":100}"#,
+            r#"{"exchange":"binance","symbol":"ETH/USDT","bid":1559.0,"ask":1559.5,"last":1559.2,"volume":25000.0,"timestamp":200}"#,
+            r#"{"event":"gap","exchange":"binance","symbol":"ETH/BTC","duration_ms":30000}"#,
+            r#"{"exchange":"binance","symbol":"ETH/BTC","bid":0.04998,"ask":0.05000,"last":0.04999,"volume":9100.0,"timestamp":10000}"#,
+            r#"{"exchange":"binance","symbol":"BTC/USDT","bid":30000.0,"ask":30001.0,"last":30000.5,"volume":1200.0,"timestamp":10050}"#,
+            r#"{"exchange":"binance","symbol":"ETH/USDT","bid":1570.0,"ask":1570.5,"last":1570.2,"volume":25100.0,"timestamp":10100}"#,
+            r#"{"event":"disconnect","exchange":"binance"}"#,
+        ];
+        std::fs::write(&path, lines.join("\n")).unwrap();
+
+        let clock = Arc::new(ReplayClock::new(0));
+        let mut engine = sim_engine(&clock);
+        let strategy: StrategyConfig = serde_json::from_value(serde_json::json!({
+            "id": "tri",
+            "strategy_type": "triangular",
+            "name": "tri",
+            "is_enabled": true,
+            "priority": 1,
+            "config": {"quote_currency": "USDT", "base_currencies": ["BTC", "ETH"], "min_profit_rate": 0.001,
+                       "trade_amount": 100},
+        }))
+        .unwrap();
+        engine.load_strategies(vec![strategy]);
+        let summary = replay_from_jsonl(&path, &mut engine, &clock).await.unwrap();
+
+        assert_eq!((summary.gaps, summary.disconnects), (1, 1));
+        assert_eq!((summary.tickers, summary.tickers_dropped), (5, 1));
+        // ETH/USDT 上涨后按旧的 ETH/BTC 报价算出的机会因该腿陈旧被抑制，不进入队列
+        assert!(summary.signals_suppressed_stale > 0, "{:?}", summary);
+        assert!(summary.stale_symbols.contains("Binance:ETH/BTC"), "{:?}", summary.stale_symbols);
+        assert_eq!(summary.signals_queued, 0);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
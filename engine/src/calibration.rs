@@ -0,0 +1,312 @@
+//! 信号置信度校准
+//!
+//! 按策略类型记录 (原始置信度, 实际是否盈利) 样本，分 10 个区间统计经验成功率，
+//! 向原始值做贝叶斯平滑后再用 PAV (保序回归) 保证单调。样本不足时为恒等映射。
+//! 校准表写入 Redis `calibration:{strategy_type}`，已写入的策略类型记在集合 `calibration:index`，
+//! 加载时按集合读取，不用 KEYS 扫描整个库。
+
+use anyhow::Result;
+use redis::aio::ConnectionManager;
+use redis::AsyncCommands;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::strategy::StrategyType;
+
+/// 分箱数量
+const BINS: usize = 10;
+/// 每个分箱的先验权重 (平滑到原始置信度)
+const PRIOR_WEIGHT: f64 = 2.0;
+/// Redis key 前缀
+const KEY_PREFIX: &str = "calibration:";
+/// 已持久化校准表的策略类型集合
+const INDEX_KEY: &str = "calibration:index";
+
+/// 单个分箱的统计
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct BinStats {
+    pub count: u64,
+    pub successes: u64,
+}
+
+/// 单个策略类型的校准表
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CalibrationTable {
+    pub bins: Vec<BinStats>,
+    /// 拟合后的各分箱校准值 (样本不足时为空)
+    pub fitted: Vec<f64>,
+}
+
+impl Default for CalibrationTable {
+    fn default() -> Self {
+        Self {
+            bins: vec![BinStats::default(); BINS],
+            fitted: vec![],
+        }
+    }
+}
+
+impl CalibrationTable {
+    fn total(&self) -> u64 {
+        self.bins.iter().map(|b| b.count).sum()
+    }
+
+    /// 拟合单调校准映射
+    fn fit(&mut self, min_samples: u64) {
+        if self.total() < min_samples {
+            self.fitted.clear();
+            return;
+        }
+        // 平滑后的分箱均值与权重
+        let mut blocks: Vec<(f64, f64, usize)> = self
+            .bins
+            .iter()
+            .enumerate()
+            .map(|(i, b)| {
+                let prior = bin_midpoint(i);
+                let weight = b.count as f64 + PRIOR_WEIGHT;
+                let mean = (b.successes as f64 + prior * PRIOR_WEIGHT) / weight;
+                (mean, weight, 1)
+            })
+            .collect();
+
+        // PAV: 合并相邻违反单调性的块
+        let mut i = 0;
+        while i + 1 < blocks.len() {
+            if blocks[i].0 > blocks[i + 1].0 {
+                let (m1, w1, n1) = blocks[i];
+                let (m2, w2, n2) = blocks.remove(i + 1);
+                blocks[i] = ((m1 * w1 + m2 * w2) / (w1 + w2), w1 + w2, n1 + n2);
+                i = i.saturating_sub(1);
+            } else {
+                i += 1;
+            }
+        }
+
+        self.fitted = blocks
+            .iter()
+            .flat_map(|(mean, _, n)| std::iter::repeat_n(*mean, *n))
+            .collect();
+    }
+
+    fn calibrate(&self, raw: f64) -> f64 {
+        if self.fitted.len() != BINS {
+            return raw;
+        }
+        self.fitted[bin_index(raw)]
+    }
+}
+
+fn bin_index(confidence: f64) -> usize {
+    ((confidence.clamp(0.0, 1.0) * BINS as f64) as usize).min(BINS - 1)
+}
+
+fn bin_midpoint(i: usize) -> f64 {
+    (i as f64 + 0.5) / BINS as f64
+}
+
+/// 置信度校准器
+pub struct Calibrator {
+    tables: HashMap<StrategyType, CalibrationTable>,
+    min_samples: u64,
+}
+
+impl Calibrator {
+    /// 创建校准器 (ENGINE_CALIBRATION_MIN_SAMPLES 指定启用校准所需样本数，默认 50)
+    pub fn from_env() -> Self {
+        let min_samples = std::env::var("ENGINE_CALIBRATION_MIN_SAMPLES")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(50);
+        Self {
+            tables: HashMap::new(),
+            min_samples,
+        }
+    }
+
+    /// 记录一次执行结果
+    pub fn record(&mut self, strategy_type: StrategyType, raw_confidence: f64, success: bool) {
+        let table = self.tables.entry(strategy_type).or_default();
+        let bin = &mut table.bins[bin_index(raw_confidence)];
+        bin.count += 1;
+        if success {
+            bin.successes += 1;
+        }
+    }
+
+    /// 重新拟合所有校准表
+    pub fn refit(&mut self) {
+        for table in self.tables.values_mut() {
+            table.fit(self.min_samples);
+        }
+    }
+
+    /// 原始置信度 -> 校准后置信度
+    pub fn calibrate(&self, strategy_type: StrategyType, raw_confidence: f64) -> f64 {
+        self.tables
+            .get(&strategy_type)
+            .map(|t| t.calibrate(raw_confidence))
+            .unwrap_or(raw_confidence)
+    }
+
+    /// 从 Redis 加载已持久化的校准表
+    pub async fn load(&mut self, redis: &redis::Client) -> Result<()> {
+        for (strategy_type, table) in load_tables(redis).await? {
+            self.tables.insert(strategy_type, table);
+        }
+        self.refit();
+        Ok(())
+    }
+
+    /// 持久化校准表到 Redis (`calibration:<strategy_type>`)，并登记到 `calibration:index`
+    pub async fn persist(&self, conn: &mut ConnectionManager) -> Result<()> {
+        for (strategy_type, table) in &self.tables {
+            let name = strategy_key(*strategy_type);
+            let _: () = conn.set(format!("{}{}", KEY_PREFIX, name), serde_json::to_string(table)?).await?;
+            let _: () = conn.sadd(INDEX_KEY, name).await?;
+        }
+        Ok(())
+    }
+}
+
+fn strategy_key(strategy_type: StrategyType) -> String {
+    serde_json::to_value(strategy_type)
+        .ok()
+        .and_then(|v| v.as_str().map(str::to_string))
+        .unwrap_or_default()
+}
+
+/// 读取 `calibration:index` 登记的所有校准表
+pub async fn load_tables(redis: &redis::Client) -> Result<Vec<(StrategyType, CalibrationTable)>> {
+    let mut conn = redis.get_multiplexed_async_connection().await?;
+    let mut names: Vec<String> = conn.smembers(INDEX_KEY).await?;
+    names.sort();
+    let mut out = vec![];
+    for name in names {
+        let Ok(strategy_type) = serde_json::from_value::<StrategyType>(serde_json::Value::String(name.clone())) else {
+            continue;
+        };
+        let raw: Option<String> = conn.get(format!("{}{}", KEY_PREFIX, name)).await?;
+        if let Some(table) = raw.and_then(|r| serde_json::from_str::<CalibrationTable>(&r).ok()) {
+            out.push((strategy_type, table));
+        }
+    }
+    Ok(out)
+}
+
+/// CLI: 打印校准表
+pub async fn dump(redis: &redis::Client) -> Result<()> {
+    let tables = load_tables(redis).await?;
+    if tables.is_empty() {
+        println!("no calibration tables found");
+        return Ok(());
+    }
+    for (strategy_type, table) in tables {
+        println!("== {} ({} samples) ==", strategy_key(strategy_type), table.total());
+        println!("{:>12} {:>8} {:>10} {:>12}", "bin", "count", "empirical", "calibrated");
+        for (i, bin) in table.bins.iter().enumerate() {
+            let empirical = if bin.count > 0 {
+                format!("{:.3}", bin.successes as f64 / bin.count as f64)
+            } else {
+                "-".to_string()
+            };
+            let calibrated = table
+                .fitted
+                .get(i)
+                .map(|v| format!("{:.3}", v))
+                .unwrap_or_else(|| "identity".to_string());
+            println!(
+                "{:>5.1}-{:<6.1} {:>8} {:>10} {:>12}",
+                i as f64 / BINS as f64,
+                (i + 1) as f64 / BINS as f64,
+                bin.count,
+                empirical,
+                calibrated
+            );
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fake_redis::FakeRedis;
+
+    fn calibrator(min_samples: u64) -> Calibrator {
+        Calibrator {
+            tables: HashMap::new(),
+            min_samples,
+        }
+    }
+
+    fn record_many(calibrator: &mut Calibrator, strategy_type: StrategyType, raw: f64, total: u64, successes: u64) {
+        for i in 0..total {
+            calibrator.record(strategy_type, raw, i < successes);
+        }
+    }
+
+    #[test]
+    fn realized_outcomes_shift_calibrated_confidence() {
+        let mut calibrator = calibrator(20);
+        // 三角套利的高置信度信号实际多数亏损，网格的低置信度信号实际全部盈利
+        record_many(&mut calibrator, StrategyType::Triangular, 0.85, 20, 5);
+        record_many(&mut calibrator, StrategyType::Grid, 0.15, 20, 20);
+
+        // 拟合前为恒等映射
+        assert_eq!(calibrator.calibrate(StrategyType::Triangular, 0.85), 0.85);
+
+        calibrator.refit();
+        let high = calibrator.calibrate(StrategyType::Triangular, 0.85);
+        let low = calibrator.calibrate(StrategyType::Grid, 0.15);
+        assert!(high < 0.5, "overconfident bin should be pulled down, got {}", high);
+        assert!(low > 0.5, "underconfident bin should be pulled up, got {}", low);
+
+        // 保序: 校准值随原始置信度单调不减
+        let curve: Vec<f64> = (0..BINS)
+            .map(|i| calibrator.calibrate(StrategyType::Triangular, bin_midpoint(i)))
+            .collect();
+        assert!(curve.windows(2).all(|w| w[0] <= w[1] + 1e-12), "{:?}", curve);
+
+        // 没有样本的策略类型不受影响
+        assert_eq!(calibrator.calibrate(StrategyType::Pair, 0.85), 0.85);
+    }
+
+    #[test]
+    fn overconfident_signals_converge_to_the_empirical_rate() {
+        // 原始置信度 0.9 的信号实际只有一半盈利，样本越多校准值越接近 0.5
+        let mut calibrator = calibrator(20);
+        let mut errors = vec![];
+        for total in [20, 200, 2_000] {
+            calibrator.tables.clear();
+            record_many(&mut calibrator, StrategyType::Triangular, 0.9, total, total / 2);
+            calibrator.refit();
+            errors.push((calibrator.calibrate(StrategyType::Triangular, 0.9) - 0.5).abs());
+        }
+        assert!(errors.windows(2).all(|w| w[1] < w[0]), "{:?}", errors);
+        assert!(errors[2] < 0.005, "{:?}", errors);
+    }
+
+    #[tokio::test]
+    async fn persisted_tables_load_through_the_index_without_keys() {
+        let redis = FakeRedis::start().await;
+        let mut calibrator = calibrator(20);
+        record_many(&mut calibrator, StrategyType::Triangular, 0.9, 20, 10);
+        record_many(&mut calibrator, StrategyType::Grid, 0.5, 20, 10);
+        calibrator.persist(&mut redis.connection().await).await.unwrap();
+
+        let tables = load_tables(&redis.client()).await.unwrap();
+        let types: Vec<StrategyType> = tables.iter().map(|(t, _)| *t).collect();
+        assert_eq!(types, vec![StrategyType::Grid, StrategyType::Triangular]);
+        assert_eq!(tables[1].1.total(), 20);
+        assert!(redis.commands().iter().all(|c| !c[0].eq_ignore_ascii_case("KEYS")));
+    }
+
+    #[test]
+    fn too_few_samples_keep_identity_mapping() {
+        let mut calibrator = calibrator(50);
+        record_many(&mut calibrator, StrategyType::Triangular, 0.85, 20, 0);
+        calibrator.refit();
+        assert_eq!(calibrator.calibrate(StrategyType::Triangular, 0.85), 0.85);
+    }
+}
@@ -4,7 +4,8 @@ use anyhow::Result;
 use serde::Deserialize;
 use std::env;
 
-use crate::exchange::ExchangeConfig;
+use crate::exchange::{validate_endpoint, ExchangeConfig};
+use crate::signing::KeyType;
 
 /// 应用配置
 #[derive(Debug, Deserialize)]
@@ -103,6 +104,11 @@ fn load_exchange_configs() -> Vec<ExchangeConfig> {
                 api_secret: env::var("BINANCE_API_SECRET").unwrap_or_default(),
                 passphrase: None,
                 enabled: true,
+                execution_enabled: execution_enabled("BINANCE"),
+                key_type: key_type("BINANCE"),
+                ws_url: None,
+                rest_url: None,
+                symbols: vec![],
             });
         }
     }
@@ -116,6 +122,11 @@ fn load_exchange_configs() -> Vec<ExchangeConfig> {
                 api_secret: env::var("OKX_API_SECRET").unwrap_or_default(),
                 passphrase: env::var("OKX_PASSPHRASE").ok(),
                 enabled: true,
+                execution_enabled: execution_enabled("OKX"),
+                key_type: key_type("OKX"),
+                ws_url: None,
+                rest_url: None,
+                symbols: vec![],
             });
         }
     }
@@ -129,6 +140,11 @@ fn load_exchange_configs() -> Vec<ExchangeConfig> {
                 api_secret: env::var("BYBIT_API_SECRET").unwrap_or_default(),
                 passphrase: None,
                 enabled: true,
+                execution_enabled: execution_enabled("BYBIT"),
+                key_type: key_type("BYBIT"),
+                ws_url: None,
+                rest_url: None,
+                symbols: vec![],
             });
         }
     }
@@ -142,9 +158,132 @@ fn load_exchange_configs() -> Vec<ExchangeConfig> {
                 api_secret: env::var("GATE_API_SECRET").unwrap_or_default(),
                 passphrase: None,
                 enabled: true,
+                execution_enabled: execution_enabled("GATE"),
+                key_type: key_type("GATE"),
+                ws_url: None,
+                rest_url: None,
+                symbols: vec![],
             });
         }
     }
 
+    // Bitget
+    if let Ok(key) = env::var("BITGET_API_KEY") {
+        if !key.is_empty() {
+            configs.push(ExchangeConfig {
+                id: ExchangeId::Bitget,
+                api_key: key,
+                api_secret: env::var("BITGET_API_SECRET").unwrap_or_default(),
+                passphrase: env::var("BITGET_PASSPHRASE").ok(),
+                enabled: true,
+                execution_enabled: execution_enabled("BITGET"),
+                key_type: key_type("BITGET"),
+                ws_url: None,
+                rest_url: None,
+                symbols: vec![],
+            });
+        }
+    }
+
+    // MEXC
+    if let Ok(key) = env::var("MEXC_API_KEY") {
+        if !key.is_empty() {
+            configs.push(ExchangeConfig {
+                id: ExchangeId::Mexc,
+                api_key: key,
+                api_secret: env::var("MEXC_API_SECRET").unwrap_or_default(),
+                passphrase: None,
+                enabled: true,
+                execution_enabled: execution_enabled("MEXC"),
+                key_type: key_type("MEXC"),
+                ws_url: None,
+                rest_url: None,
+                symbols: vec![],
+            });
+        }
+    }
+
+    // 只订阅行情、不下单的交易所 (无需 API Key)
+    if let Ok(list) = env::var("ENGINE_OBSERVE_EXCHANGES") {
+        for name in list.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+            let Ok(id) = serde_json::from_value::<ExchangeId>(serde_json::Value::String(name.to_lowercase())) else {
+                continue;
+            };
+            match configs.iter_mut().find(|c| c.id == id) {
+                Some(config) => config.execution_enabled = false,
+                None => configs.push(ExchangeConfig {
+                    id,
+                    api_key: String::new(),
+                    api_secret: String::new(),
+                    passphrase: None,
+                    enabled: true,
+                    execution_enabled: false,
+                    key_type: KeyType::Hmac,
+                    ws_url: None,
+                    rest_url: None,
+                    symbols: vec![],
+                }),
+            }
+        }
+    }
+
+    // 接入地址覆盖 (区域节点 / 托管机房节点 / 代理) 与订阅的交易对
+    for config in configs.iter_mut() {
+        let prefix = format!("{:?}", config.id).to_uppercase();
+        config.ws_url = endpoint_override(&prefix, "WS_URL", "wss");
+        config.rest_url = endpoint_override(&prefix, "REST_URL", "https");
+        config.symbols = subscribed_symbols(&prefix);
+    }
+
     configs
 }
+
+/// 读取 `{PREFIX}_EXECUTION_ENABLED` (默认允许下单)
+fn execution_enabled(prefix: &str) -> bool {
+    env::var(format!("{}_EXECUTION_ENABLED", prefix))
+        .map(|v| !matches!(v.as_str(), "0" | "false" | "False"))
+        .unwrap_or(true)
+}
+
+/// 读取 `{PREFIX}_KEY_TYPE` (hmac / ed25519，默认 hmac)
+fn key_type(prefix: &str) -> KeyType {
+    env::var(format!("{}_KEY_TYPE", prefix))
+        .ok()
+        .and_then(|v| KeyType::parse(&v))
+        .unwrap_or_default()
+}
+
+/// 未配置时订阅的交易对 (覆盖内置三角套利所需的主流币种)
+const DEFAULT_SYMBOLS: &[&str] = &[
+    "BTC/USDT", "ETH/USDT", "ETH/BTC", "BNB/USDT", "BNB/BTC", "SOL/USDT", "SOL/BTC", "XRP/USDT", "XRP/BTC",
+];
+
+/// 读取 `{PREFIX}_SYMBOLS`，未设置时取 `ENGINE_SYMBOLS`，均未设置时使用内置默认列表 (逗号分隔，如 `BTC/USDT,ETH/USDT`)
+fn subscribed_symbols(prefix: &str) -> Vec<String> {
+    let parse = |raw: String| -> Vec<String> {
+        raw.split(',')
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .map(str::to_uppercase)
+            .collect()
+    };
+    env::var(format!("{}_SYMBOLS", prefix))
+        .ok()
+        .map(parse)
+        .filter(|s| !s.is_empty())
+        .or_else(|| env::var("ENGINE_SYMBOLS").ok().map(parse).filter(|s| !s.is_empty()))
+        .unwrap_or_else(|| DEFAULT_SYMBOLS.iter().map(|s| s.to_string()).collect())
+}
+
+/// 读取 `{PREFIX}_{KEY}` 地址覆盖；不是合法的 `scheme://` 地址时告警并使用内置默认
+fn endpoint_override(prefix: &str, key: &str, scheme: &str) -> Option<String> {
+    let name = format!("{}_{}", prefix, key);
+    let raw = env::var(&name).ok().filter(|v| !v.trim().is_empty())?;
+    match validate_endpoint(&raw, scheme) {
+        Ok(url) => Some(url),
+        Err(e) => {
+            tracing::warn!("{} 无效，使用内置默认地址: {}", name, e);
+            None
+        }
+    }
+}
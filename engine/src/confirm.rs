@@ -0,0 +1,249 @@
+//! 实盘执行前的二次确认
+//!
+//! 大额信号在下单前向独立价格源 (交易所 REST 盘口快照) 重新取价，按各腿的最新盘口重算收益率，
+//! 收益率低于配置的安全边际时拒绝执行。只对携带腿信息 (`legs`) 的信号生效，
+//! 其他信号没有可比对的成交价格，直接放行。
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::info;
+
+use crate::exchange::{ExchangeId, RestEndpoints};
+use crate::executor::OrderSide;
+use crate::strategies::split_symbol;
+use crate::strategy::Signal;
+
+/// 二次确认配置
+#[derive(Debug, Clone)]
+pub struct ConfirmConfig {
+    /// 预期收益 (计价币) 达到该值才做二次确认
+    pub min_expected_profit: f64,
+    /// 重算后收益率下限
+    pub margin: f64,
+}
+
+impl ConfirmConfig {
+    /// 从环境变量读取 (ENGINE_CONFIRM_ENABLED 未开启时返回 None)
+    pub fn from_env() -> Option<Self> {
+        let enabled = std::env::var("ENGINE_CONFIRM_ENABLED")
+            .map(|v| matches!(v.as_str(), "1" | "true" | "True"))
+            .unwrap_or(false);
+        if !enabled {
+            return None;
+        }
+        let parse = |key: &str, default: f64| {
+            std::env::var(key)
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(default)
+        };
+        Some(Self {
+            min_expected_profit: parse("ENGINE_CONFIRM_MIN_PROFIT", 0.0),
+            margin: parse("ENGINE_CONFIRM_MARGIN", 0.0005),
+        })
+    }
+}
+
+/// 独立价格源
+#[async_trait]
+pub trait PriceSource: Send + Sync {
+    /// 返回 (买一, 卖一)
+    async fn fetch_quote(&self, exchange: ExchangeId, symbol: &str) -> Result<(f64, f64)>;
+}
+
+/// 交易所 REST 盘口快照
+pub struct RestPriceSource {
+    client: reqwest::Client,
+    endpoints: RestEndpoints,
+}
+
+impl RestPriceSource {
+    pub fn new(endpoints: RestEndpoints) -> Self {
+        Self {
+            endpoints,
+            client: reqwest::Client::builder()
+                .timeout(Duration::from_secs(3))
+                .build()
+                .unwrap_or_else(|_| reqwest::Client::new()),
+        }
+    }
+
+    async fn get_json(&self, url: String) -> Result<serde_json::Value> {
+        Ok(self
+            .client
+            .get(url)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?)
+    }
+}
+
+#[async_trait]
+impl PriceSource for RestPriceSource {
+    async fn fetch_quote(&self, exchange: ExchangeId, symbol: &str) -> Result<(f64, f64)> {
+        let (base, quote) = split_symbol(symbol).with_context(|| format!("无法解析交易对 {}", symbol))?;
+        let base_url = self.endpoints.base_url(exchange);
+        let (body, bid_field, ask_field) = match exchange {
+            ExchangeId::Binance => {
+                let url = format!("{}/api/v3/ticker/bookTicker?symbol={}{}", base_url, base, quote);
+                (self.get_json(url).await?, "bidPrice", "askPrice")
+            }
+            ExchangeId::Okx => {
+                let url = format!("{}/api/v5/market/ticker?instId={}-{}", base_url, base, quote);
+                let body = self.get_json(url).await?;
+                let first = body
+                    .get("data")
+                    .and_then(|v| v.get(0))
+                    .cloned()
+                    .context("ticker 缺少 data")?;
+                (first, "bidPx", "askPx")
+            }
+            other => return Err(anyhow::anyhow!("{:?} 暂不支持 REST 盘口快照", other)),
+        };
+        let field = |name: &str| {
+            body.get(name)
+                .and_then(|v| v.as_str())
+                .and_then(|v| v.parse::<f64>().ok())
+                .filter(|v| *v > 0.0)
+                .with_context(|| format!("{:?} {} 盘口缺少 {}", exchange, symbol, name))
+        };
+        Ok((field(bid_field)?, field(ask_field)?))
+    }
+}
+
+/// 执行前二次确认
+pub struct PreExecutionCheck {
+    config: ConfirmConfig,
+    source: Arc<dyn PriceSource>,
+}
+
+impl PreExecutionCheck {
+    pub fn new(config: ConfirmConfig, source: Arc<dyn PriceSource>) -> Self {
+        Self { config, source }
+    }
+
+    /// 按最新盘口重算收益率，返回重算结果；收益已不足安全边际时返回错误
+    pub async fn confirm(&self, signal: &Signal) -> Result<f64> {
+        if signal.legs.is_empty() || signal.expected_profit < self.config.min_expected_profit {
+            return Ok(signal.profit_rate);
+        }
+        let mut growth = 1.0 + signal.profit_rate;
+        for leg in &signal.legs {
+            let (bid, ask) = self
+                .source
+                .fetch_quote(leg.exchange, &leg.symbol)
+                .await
+                .with_context(|| format!("二次确认取价失败 [{}]", signal.correlation_id))?;
+            growth *= match leg.side {
+                OrderSide::Buy => leg.price / ask,
+                OrderSide::Sell => bid / leg.price,
+            };
+        }
+        let profit_rate = growth - 1.0;
+        if profit_rate < self.config.margin {
+            return Err(anyhow::anyhow!(
+                "二次确认未通过 [{}]: 收益率 {:.4}% -> {:.4}%，低于安全边际 {:.4}%",
+                signal.correlation_id,
+                signal.profit_rate * 100.0,
+                profit_rate * 100.0,
+                self.config.margin * 100.0
+            ));
+        }
+        info!(
+            "二次确认通过 [{}]: 收益率 {:.4}% -> {:.4}%",
+            signal.correlation_id,
+            signal.profit_rate * 100.0,
+            profit_rate * 100.0
+        );
+        Ok(profit_rate)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::strategy::{SignalLeg, StrategyType};
+    use std::collections::HashMap;
+
+    /// 固定盘口的价格源 (未配置的交易对返回错误)
+    struct StubSource(HashMap<(ExchangeId, String), (f64, f64)>);
+
+    #[async_trait]
+    impl PriceSource for StubSource {
+        async fn fetch_quote(&self, exchange: ExchangeId, symbol: &str) -> Result<(f64, f64)> {
+            self.0
+                .get(&(exchange, symbol.to_string()))
+                .copied()
+                .with_context(|| format!("no quote for {}", symbol))
+        }
+    }
+
+    fn check(quotes: &[(ExchangeId, &str, f64, f64)]) -> PreExecutionCheck {
+        let quotes = quotes
+            .iter()
+            .map(|(exchange, symbol, bid, ask)| ((*exchange, symbol.to_string()), (*bid, *ask)))
+            .collect();
+        PreExecutionCheck::new(
+            ConfirmConfig {
+                min_expected_profit: 0.5,
+                margin: 0.001,
+            },
+            Arc::new(StubSource(quotes)),
+        )
+    }
+
+    /// Binance 按 100 买入、OKX 按 101 卖出 (收益率 1%)
+    fn signal() -> Signal {
+        let mut signal = Signal::new("cross", StrategyType::Triangular, ExchangeId::Binance, 0.01, 1.0, 0.5, "BTC/USDT", 0);
+        signal.legs = vec![
+            SignalLeg {
+                exchange: ExchangeId::Binance,
+                symbol: "BTC/USDT".to_string(),
+                side: OrderSide::Buy,
+                price: 100.0,
+            },
+            SignalLeg {
+                exchange: ExchangeId::Okx,
+                symbol: "BTC/USDT".to_string(),
+                side: OrderSide::Sell,
+                price: 101.0,
+            },
+        ];
+        signal
+    }
+
+    #[tokio::test]
+    async fn confirmation_passes_when_fresh_quotes_hold_the_edge() {
+        // 卖一略涨: 1.01 * 100 / 100.2 - 1 ≈ 0.8%，仍高于 0.1% 安全边际
+        let check = check(&[(ExchangeId::Binance, "BTC/USDT", 99.9, 100.2), (ExchangeId::Okx, "BTC/USDT", 101.0, 101.1)]);
+        let rate = check.confirm(&signal()).await.unwrap();
+        assert!((rate - (1.01 * 100.0 / 100.2 - 1.0)).abs() < 1e-12);
+    }
+
+    #[tokio::test]
+    async fn confirmation_rejects_when_edge_has_gone() {
+        // OKX 买一跌到 100.1: 1.01 * 100.1 / 101 - 1 ≈ 0.1%，低于安全边际
+        let moved = check(&[(ExchangeId::Binance, "BTC/USDT", 99.9, 100.0), (ExchangeId::Okx, "BTC/USDT", 100.0, 100.1)]);
+        let err = moved.confirm(&signal()).await.unwrap_err();
+        assert!(err.to_string().contains("二次确认未通过"), "{}", err);
+
+        // 取价失败同样拒绝执行
+        let err = check(&[]).confirm(&signal()).await.unwrap_err();
+        assert!(err.to_string().contains("二次确认取价失败"), "{}", err);
+    }
+
+    #[tokio::test]
+    async fn small_or_legless_signals_skip_confirmation() {
+        let check = check(&[]);
+        let mut small = signal();
+        small.expected_profit = 0.1;
+        assert_eq!(check.confirm(&small).await.unwrap(), 0.01);
+        let mut legless = signal();
+        legless.legs.clear();
+        assert_eq!(check.confirm(&legless).await.unwrap(), 0.01);
+    }
+}
@@ -1,10 +1,19 @@
 //! 数据库连接模块
 
 use anyhow::Result;
+use async_trait::async_trait;
+use redis::aio::{ConnectionManager, ConnectionManagerConfig};
 use sqlx::postgres::PgPoolOptions;
-use sqlx::PgPool;
+use sqlx::{PgPool, Row};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::OnceCell;
 
 use crate::config::{DatabaseConfig, RedisConfig};
+use crate::executor::ExecutionResult;
+use crate::schedule::parse_timestamp;
+use crate::strategy::StrategyConfig;
 
 /// 创建 PostgreSQL 连接池
 pub async fn create_pool(config: &DatabaseConfig) -> Result<PgPool> {
@@ -25,3 +34,159 @@ pub fn create_redis_client(config: &RedisConfig) -> Result<redis::Client> {
     tracing::info!("Redis 客户端已创建");
     Ok(client)
 }
+
+/// 复用的 Redis 连接: 首次使用时建立一条多路复用连接，之后各次读写克隆同一句柄 (克隆开销很小)；
+/// 连接断开后该次命令返回错误，并在后台重新建立 (建立失败时下次使用再试)
+#[derive(Clone)]
+pub struct SharedRedis {
+    client: redis::Client,
+    conn: Arc<OnceCell<ConnectionManager>>,
+    /// 建立连接的次数
+    connects: Arc<AtomicU64>,
+}
+
+impl SharedRedis {
+    pub fn new(client: redis::Client) -> Self {
+        Self {
+            client,
+            conn: Arc::default(),
+            connects: Arc::default(),
+        }
+    }
+
+    /// 底层客户端 (低频任务仍可自行建立连接)
+    pub fn client(&self) -> &redis::Client {
+        &self.client
+    }
+
+    /// 共享连接的句柄；尚未建立时建立 (只重试一次，避免 Redis 不可用时阻塞热路径)
+    pub async fn connection(&self) -> redis::RedisResult<ConnectionManager> {
+        self.conn
+            .get_or_try_init(|| {
+                self.connects.fetch_add(1, Ordering::Relaxed);
+                let config = ConnectionManagerConfig::new()
+                    .set_number_of_retries(1)
+                    .set_connection_timeout(Duration::from_secs(2))
+                    .set_response_timeout(Duration::from_secs(2));
+                ConnectionManager::new_with_config(self.client.clone(), config)
+            })
+            .await
+            .cloned()
+    }
+
+    /// 建立连接的次数
+    pub fn connects(&self) -> u64 {
+        self.connects.load(Ordering::Relaxed)
+    }
+}
+
+/// 加载已启用的策略配置 (按优先级降序)
+pub async fn load_strategy_configs(pool: &PgPool, user_id: Option<&str>) -> Result<Vec<StrategyConfig>> {
+    let rows = sqlx::query(
+        "SELECT id::text AS id, strategy_type::text AS strategy_type, name, is_enabled, \
+                priority, config::text AS config \
+         FROM strategy_configs \
+         WHERE is_enabled = true AND ($1::uuid IS NULL OR user_id = $1::uuid) \
+         ORDER BY priority DESC",
+    )
+    .bind(user_id)
+    .fetch_all(pool)
+    .await?;
+
+    let mut configs = vec![];
+    for row in rows {
+        let strategy_type: String = row.try_get("strategy_type")?;
+        let Ok(strategy_type) = serde_json::from_value(serde_json::Value::String(strategy_type.clone())) else {
+            tracing::warn!("跳过不支持的策略类型: {}", strategy_type);
+            continue;
+        };
+        let config: String = row.try_get("config")?;
+        let config: serde_json::Value = serde_json::from_str(&config).unwrap_or_default();
+        let name: String = row.try_get("name")?;
+        // 生效 / 失效时间写在策略参数中 (RFC 3339 或毫秒时间戳)
+        let time = |key: &str| {
+            let value = config.get(key).filter(|v| !v.is_null())?;
+            let parsed = parse_timestamp(value);
+            if parsed.is_none() {
+                tracing::warn!("策略 {} 的 `{}` 无法解析为时间: {}，已忽略", name, key, value);
+            }
+            parsed
+        };
+        let (active_from, active_until) = (time("active_from"), time("active_until"));
+        configs.push(StrategyConfig {
+            id: row.try_get("id")?,
+            strategy_type,
+            name,
+            is_enabled: row.try_get("is_enabled")?,
+            priority: row.try_get::<Option<i32>, _>("priority")?.unwrap_or(5),
+            config,
+            active_from,
+            active_until,
+        });
+    }
+    Ok(configs)
+}
+
+/// 执行审计的存储
+#[async_trait]
+pub trait ExecutionStore: Send + Sync {
+    /// 写入一次执行尝试 (error 为执行失败的原因)
+    async fn record_execution(&self, result: &ExecutionResult, error: Option<&str>) -> Result<()>;
+}
+
+#[async_trait]
+impl ExecutionStore for PgPool {
+    async fn record_execution(&self, result: &ExecutionResult, error: Option<&str>) -> Result<()> {
+        record_execution(self, result, error).await
+    }
+}
+
+/// 写入一次执行的审计记录 (表 `executions`，见 server/db/migration_v8_engine_executions.sql)
+pub async fn record_execution(pool: &PgPool, result: &ExecutionResult, error: Option<&str>) -> Result<()> {
+    let signal = &result.signal;
+    let text = |value: serde_json::Value| value.as_str().unwrap_or_default().to_string();
+    sqlx::query(
+        "INSERT INTO executions \
+             (correlation_id, strategy_id, strategy_type, exchange_id, path, action, profit_rate, expected_profit, \
+              confidence, signal_at, orders, total_fee, net_profit, success, error) \
+         VALUES ($1, $2, $3, $4, $5, $6, $7::numeric, $8::numeric, $9::numeric, to_timestamp($10::double precision / 1000.0), \
+                 $11::jsonb, $12::numeric, $13::numeric, $14, $15)",
+    )
+    .bind(&signal.correlation_id)
+    .bind(&signal.strategy_id)
+    .bind(text(serde_json::to_value(signal.strategy_type)?))
+    .bind(text(serde_json::to_value(signal.exchange)?))
+    .bind(&signal.path)
+    .bind(text(serde_json::to_value(signal.action)?))
+    .bind(signal.profit_rate)
+    .bind(signal.expected_profit)
+    .bind(signal.confidence)
+    .bind(signal.timestamp as f64)
+    .bind(serde_json::to_string(&result.orders)?)
+    .bind(result.total_fee)
+    .bind(result.net_profit)
+    .bind(result.success)
+    .bind(error)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fake_redis::FakeRedis;
+
+    #[tokio::test]
+    async fn shared_connection_is_established_once_for_many_publishes() {
+        let redis = FakeRedis::start().await;
+        let shared = SharedRedis::new(redis.client());
+        for i in 0..5 {
+            let mut conn = shared.connection().await.unwrap();
+            let _: () = redis::cmd("PUBLISH").arg("engine:status").arg(i).query_async(&mut conn).await.unwrap();
+        }
+        assert_eq!(shared.connects(), 1);
+        let publishes = redis.commands().iter().filter(|c| c[0] == "PUBLISH").count();
+        assert_eq!(publishes, 5);
+    }
+}
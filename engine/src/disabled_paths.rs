@@ -0,0 +1,132 @@
+//! 运行时禁用的套利路径
+//!
+//! 某条三角 / 环路异常时 (某条腿下架、反复成交不佳)，运维只需禁用这一条路径而不必停掉整个策略。
+//! 禁用列表保存在 Redis 集合 `config:disabled_paths` (如 `SADD config:disabled_paths USDT->BTC->ETH->USDT`)，
+//! 引擎每 5 秒刷新一次；三角套利与图搜索策略发信号前跳过禁用路径，继续评估其余路径。
+//!
+//! 路径按环路比较: 起点不同的同一环路 (`BTC->ETH->USDT->BTC` 与 `USDT->BTC->ETH->USDT`) 视为同一条，
+//! 方向相反的环路是不同的路径。
+
+use anyhow::Result;
+use redis::aio::ConnectionManager;
+use redis::AsyncCommands;
+use std::collections::HashSet;
+use std::sync::RwLock;
+use tracing::info;
+
+/// Redis 集合
+pub const REDIS_KEY: &str = "config:disabled_paths";
+
+/// 禁用路径集合 (环路规范形式)
+#[derive(Debug, Default)]
+pub struct DisabledPaths {
+    paths: RwLock<HashSet<String>>,
+}
+
+lazy_static::lazy_static! {
+    pub static ref DISABLED_PATHS: DisabledPaths = DisabledPaths::default();
+}
+
+impl DisabledPaths {
+    /// 路径是否被禁用
+    pub fn is_disabled(&self, path: &str) -> bool {
+        let Ok(paths) = self.paths.read() else {
+            return false;
+        };
+        !paths.is_empty() && paths.contains(&canonical_path(path))
+    }
+
+    /// 替换禁用列表，返回是否有变化
+    pub fn replace<I, S>(&self, paths: I) -> bool
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let next: HashSet<String> = paths
+            .into_iter()
+            .map(|p| canonical_path(p.as_ref()))
+            .filter(|p| !p.is_empty())
+            .collect();
+        let Ok(mut current) = self.paths.write() else {
+            return false;
+        };
+        if *current == next {
+            return false;
+        }
+        *current = next;
+        true
+    }
+
+    pub fn len(&self) -> usize {
+        self.paths.read().map(|p| p.len()).unwrap_or(0)
+    }
+
+    /// 从 Redis 刷新禁用列表
+    pub async fn refresh(&self, conn: &mut ConnectionManager) -> Result<()> {
+        let paths: Vec<String> = conn.smembers(REDIS_KEY).await?;
+        if self.replace(&paths) {
+            info!("禁用路径已更新: {} 条 {:?}", paths.len(), paths);
+        }
+        Ok(())
+    }
+}
+
+/// 环路规范形式: 币种大写，去掉首尾重复的起点后旋转到字典序最小的币种开头，再闭合
+pub fn canonical_path(path: &str) -> String {
+    let mut nodes: Vec<String> = path
+        .split("->")
+        .map(|n| n.trim().to_uppercase())
+        .filter(|n| !n.is_empty())
+        .collect();
+    if nodes.len() > 1 && nodes.first() == nodes.last() {
+        nodes.pop();
+    }
+    let Some(start) = nodes
+        .iter()
+        .enumerate()
+        .min_by(|a, b| a.1.cmp(b.1))
+        .map(|(i, _)| i)
+    else {
+        return String::new();
+    };
+    nodes.rotate_left(start);
+    let first = nodes[0].clone();
+    nodes.push(first);
+    nodes.join("->")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fake_redis::FakeRedis;
+
+    #[test]
+    fn rotations_match_but_reversed_cycle_does_not() {
+        assert_eq!(canonical_path("USDT->BTC->ETH->USDT"), "BTC->ETH->USDT->BTC");
+        assert_eq!(canonical_path(" eth -> usdt -> btc -> eth "), "BTC->ETH->USDT->BTC");
+        assert_eq!(canonical_path("USDT->ETH->BTC->USDT"), "BTC->USDT->ETH->BTC");
+        assert_eq!(canonical_path(""), "");
+    }
+
+    #[tokio::test]
+    async fn cycle_is_disabled_and_re_enabled_at_runtime() {
+        let redis = FakeRedis::start().await;
+        let mut conn = redis.connection().await;
+        let disabled = DisabledPaths::default();
+        disabled.refresh(&mut conn).await.unwrap();
+        assert!(!disabled.is_disabled("USDT->BTC->ETH->USDT"));
+
+        let _: () = conn.sadd(REDIS_KEY, "BTC->ETH->USDT->BTC").await.unwrap();
+        disabled.refresh(&mut conn).await.unwrap();
+        assert_eq!(disabled.len(), 1);
+        // 起点不同的同一环路同样禁用，反向环路不受影响
+        assert!(disabled.is_disabled("USDT->BTC->ETH->USDT"));
+        assert!(disabled.is_disabled("ETH->USDT->BTC->ETH"));
+        assert!(!disabled.is_disabled("USDT->ETH->BTC->USDT"));
+
+        let _: () = conn.srem(REDIS_KEY, "BTC->ETH->USDT->BTC").await.unwrap();
+        disabled.refresh(&mut conn).await.unwrap();
+        assert!(!disabled.is_disabled("USDT->BTC->ETH->USDT"));
+        assert!(!disabled.replace(Vec::<String>::new()), "列表未变化");
+    }
+}
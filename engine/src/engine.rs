@@ -0,0 +1,2680 @@
+//! 策略引擎: 将行情分发给策略，并把信号交给风控与执行
+
+use anyhow::Result;
+use futures_util::future::BoxFuture;
+use futures_util::FutureExt;
+use redis::aio::ConnectionManager;
+use redis::AsyncCommands;
+use sqlx::PgPool;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::panic::AssertUnwindSafe;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+use tokio::sync::{broadcast, mpsc};
+use tracing::{error, info, warn};
+
+use crate::accounting::{self, PnlSinks, StrategyLedger};
+use crate::api::{ControlCommand, LedgerEntry, SharedState, StrategyStatus};
+use crate::calibration::Calibrator;
+use crate::db::{load_strategy_configs, SharedRedis};
+use crate::disabled_paths::DISABLED_PATHS;
+use crate::exchange::{ExchangeConnection, ExchangeId, FundingRateUpdate, Kline, OrderBook, Ticker, Trade};
+use crate::faults::{FaultInjector, FaultKind, FaultReport, Store};
+use crate::forwarder::{LagPolicy, TickerForwarder};
+use crate::funding::{FundingRate, FUNDING_CACHE};
+use crate::health::FeedHealth;
+use crate::heatmap::{Heatmap, HeatmapConfig};
+use crate::latency::LatencyTracker;
+use crate::metrics::METRICS;
+use crate::executor::{parse_symbols_from_path, AmendResult, ExecutionResult, OrderExecutor, OrderSide};
+use crate::positions::{base_asset, OpenOrder, PositionBook};
+use crate::queue::{PushOutcome, SignalQueue};
+use crate::report::{ReportCollector, SimReport};
+use crate::risk::{persist_kill_switch, ExchangeCapitalCaps, InventoryLimits, RiskCheck, RiskManager, GLOBAL_RISK_MANAGER};
+use crate::schedule::StrategySchedule;
+use crate::scorecard::{Scorecard, VenueVerdict, DEPRIORITIZED_PENALTY};
+use crate::strategies::build_strategy;
+use crate::stops::{StopConfig, StopManager, StopOrder, StopPlacement};
+use crate::strategy::{NearMiss, Signal, SignalAction, Strategy, StrategyConfig, StrategyType};
+use crate::supervisor::{flatten_on_panic_enabled, PanicEvent, Supervisor, TaskKind};
+use crate::symbols::normalize_symbol;
+use crate::telemetry::SignalTrace;
+use crate::tick_rate::TickRateTracker;
+
+/// 引擎状态频道
+pub(crate) const STATUS_CHANNEL: &str = "engine:status";
+/// 引擎指标 Hash
+const METRICS_KEY: &str = "metrics:engine";
+/// 接近成交机会列表 (含盈亏平衡费率)
+const NEAR_MISS_KEY: &str = "opportunities:near_miss";
+/// 接近成交机会列表保留条数
+const NEAR_MISS_KEEP: isize = 1000;
+/// 平仓信号的队列优先级 (高于任何策略配置)
+const CLOSE_PRIORITY: i32 = 100;
+/// 队列高水位比例
+const QUEUE_HIGH_WATERMARK: f64 = 0.8;
+/// 高水位持续多久后告警 (毫秒)
+const QUEUE_HIGH_WATERMARK_MS: i64 = 10_000;
+
+/// 时钟 (回测时注入回放时钟，保证调度与实盘一致)
+pub trait Clock: Send + Sync {
+    /// 当前时间 (毫秒)
+    fn now_ms(&self) -> i64;
+}
+
+/// 系统时钟
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now_ms(&self) -> i64 {
+        chrono::Utc::now().timestamp_millis()
+    }
+}
+
+/// 已加载的策略
+struct StrategySlot {
+    strategy: Box<dyn Strategy>,
+    name: String,
+    schedule: Option<StrategySchedule>,
+    active: bool,
+    priority: i32,
+    stop_config: StopConfig,
+    /// 策略回调曾 panic，已停用
+    panicked: bool,
+    /// 通过控制接口手动暂停
+    paused: bool,
+    /// 是否接收未收盘的 K 线 (策略参数 partial_klines，默认只接收已收盘的 K 线)
+    partial_klines: bool,
+}
+
+/// 执行任务的结果
+struct ExecutionOutcome {
+    correlation_id: String,
+    /// 信号来源策略 (执行出错、没有执行结果时报告按此归属)
+    strategy_id: String,
+    strategy_type: StrategyType,
+    exchange: ExchangeId,
+    /// 决策时刻路径上的行情快照 (评分卡计算滑点)
+    decision: Vec<Ticker>,
+    /// 执行耗时 (毫秒)
+    elapsed_ms: u64,
+    /// 被风控拦截时为 None
+    result: Option<Result<ExecutionResult>>,
+    /// 生命周期链路追踪
+    trace: Option<SignalTrace>,
+}
+
+/// 后台订单任务的结果，由主循环应用到仓位簿
+enum OrderUpdate {
+    /// 挂单改价: 原挂单、目标价与改单结果
+    Requoted {
+        order: OpenOrder,
+        price: f64,
+        result: Result<AmendResult>,
+    },
+    /// 监视器止损的市价平仓结果
+    StopFilled {
+        stop: StopOrder,
+        result: Result<crate::executor::OrderResponse>,
+    },
+}
+
+/// 策略引擎
+pub struct Engine {
+    strategies: Vec<StrategySlot>,
+    executor: Arc<OrderExecutor>,
+    redis: Option<SharedRedis>,
+    clock: Arc<dyn Clock>,
+    stops: StopManager,
+    positions: PositionBook,
+    /// 组合层面的单币种净持仓上限
+    inventory_limits: InventoryLimits,
+    /// 单个交易所的资金部署上限
+    exchange_caps: ExchangeCapitalCaps,
+    calibrator: Calibrator,
+    queue: SignalQueue,
+    /// 正在执行的信号数 (达到上限时其余信号在队列中等待)
+    in_flight: usize,
+    /// 同时执行的信号数上限 (ENGINE_MAX_IN_FLIGHT，默认 1)，限制多腿执行未完成期间的总裸露敞口
+    max_in_flight: usize,
+    /// 挂单偏离同侧最优价超过该基点数时改价到最优价 (ENGINE_REQUOTE_BPS，默认 0 即不改价)
+    requote_bps: f64,
+    /// 改价任务未完成的挂单 (避免后续行情重复改价)
+    requoting: HashSet<String>,
+    order_tx: mpsc::UnboundedSender<OrderUpdate>,
+    order_rx: Option<mpsc::UnboundedReceiver<OrderUpdate>>,
+    /// 队列越过高水位的起始时间
+    high_watermark_since: Option<i64>,
+    high_watermark_alerted: bool,
+    supervisor: Supervisor,
+    panic_rx: Option<mpsc::UnboundedReceiver<PanicEvent>>,
+    /// 供 HTTP 服务读取的状态快照
+    state: SharedState,
+    control_tx: mpsc::UnboundedSender<ControlCommand>,
+    control_rx: Option<mpsc::UnboundedReceiver<ControlCommand>>,
+    /// 熔断: 停止执行新信号
+    halted: bool,
+    /// 风控管理器 (默认为全局实例)
+    risk: RiskManager,
+    /// 策略来源 (重载策略时使用)
+    strategy_source: Option<(PgPool, Option<String>)>,
+    /// 行情连接 (主循环启动后可用，按策略追加订阅交易对)
+    feeds: HashMap<ExchangeId, Arc<ExchangeConnection>>,
+    /// 为策略追加订阅的交易对 (重载后不再需要的退订；启动时配置的交易对不在其中)
+    strategy_symbols: HashMap<ExchangeId, HashSet<String>>,
+    health: FeedHealth,
+    /// 各交易对行情频率
+    tick_rates: TickRateTracker,
+    /// 各交易所行情延迟
+    latency: LatencyTracker,
+    /// 故障注入 (仅模拟 / 回测)
+    faults: Option<Arc<FaultInjector>>,
+    /// 交易所执行质量评分卡
+    scorecard: Scorecard,
+    /// 模拟运行报告
+    report: ReportCollector,
+    /// 按策略隔离的盈亏累计
+    strategy_pnl: StrategyLedger,
+    pnl_sinks: PnlSinks,
+    /// 机会热力图 (ENGINE_HEATMAP，默认关闭)
+    heatmap: HeatmapConfig,
+    /// 信号生命周期链路导出 (ENGINE_OTLP_ENDPOINT，默认关闭)
+    tracer: Option<opentelemetry_sdk::trace::Tracer>,
+}
+
+impl Engine {
+    /// 创建引擎
+    pub fn new(mut executor: OrderExecutor, redis: Option<redis::Client>, clock: Arc<dyn Clock>) -> Self {
+        let (supervisor, panic_rx) = Supervisor::new();
+        let (control_tx, control_rx) = mpsc::unbounded_channel();
+        let (order_tx, order_rx) = mpsc::unbounded_channel();
+        let state = SharedState::new(if executor.is_simulation() { "simulation" } else { "live" });
+        executor.set_decision_feed(state.clone());
+        executor.set_clock(clock.clone());
+        let report = ReportCollector::new(clock.now_ms());
+        Self {
+            strategies: vec![],
+            executor: Arc::new(executor),
+            redis: redis.map(SharedRedis::new),
+            clock,
+            stops: StopManager::new(),
+            positions: PositionBook::new(),
+            inventory_limits: InventoryLimits::from_env(),
+            exchange_caps: ExchangeCapitalCaps::from_env(),
+            calibrator: Calibrator::from_env(),
+            queue: SignalQueue::from_env(),
+            in_flight: 0,
+            max_in_flight: max_in_flight_from_env(),
+            requote_bps: requote_bps_from_env(),
+            requoting: HashSet::new(),
+            order_tx,
+            order_rx: Some(order_rx),
+            high_watermark_since: None,
+            high_watermark_alerted: false,
+            supervisor,
+            panic_rx: Some(panic_rx),
+            state,
+            control_tx,
+            control_rx: Some(control_rx),
+            halted: false,
+            risk: GLOBAL_RISK_MANAGER.clone(),
+            strategy_source: None,
+            feeds: HashMap::new(),
+            strategy_symbols: HashMap::new(),
+            health: FeedHealth::from_env(),
+            tick_rates: TickRateTracker::from_env(),
+            latency: LatencyTracker::from_env(),
+            faults: None,
+            scorecard: Scorecard::from_env(),
+            report,
+            strategy_pnl: StrategyLedger::default(),
+            pnl_sinks: PnlSinks::from_env(),
+            heatmap: HeatmapConfig::from_env(),
+            tracer: None,
+        }
+    }
+
+    /// 启用信号生命周期链路导出
+    pub fn set_tracer(&mut self, tracer: opentelemetry_sdk::trace::Tracer) {
+        self.tracer = Some(tracer);
+    }
+
+    /// 启用故障注入 (需与执行器共用同一个注入器)
+    pub fn set_fault_injector(&mut self, faults: Arc<FaultInjector>) {
+        self.faults = Some(faults);
+    }
+
+    /// 已启用的故障注入器
+    pub fn fault_injector(&self) -> Option<Arc<FaultInjector>> {
+        self.faults.clone()
+    }
+
+    /// 故障注入报告 (未启用故障注入时为 None)
+    pub fn fault_report(&self) -> Option<FaultReport> {
+        self.faults.as_ref().map(|f| f.report())
+    }
+
+    /// 模拟运行报告 (实盘模式为 None)
+    pub fn sim_report(&self) -> Option<SimReport> {
+        self.executor
+            .is_simulation()
+            .then(|| self.report.report(self.clock.now_ms()))
+    }
+
+    /// HTTP 服务所需的状态快照与控制通道
+    pub fn api_handles(&self) -> (SharedState, mpsc::UnboundedSender<ControlCommand>) {
+        (self.state.clone(), self.control_tx.clone())
+    }
+
+    /// 从数据库加载已启用的策略
+    pub async fn load_enabled_strategies(&mut self, pool: &PgPool, user_id: Option<&str>) -> Result<()> {
+        self.strategy_source = Some((pool.clone(), user_id.map(str::to_string)));
+        self.load_strategies(load_strategy_configs(pool, user_id).await?);
+        self.sync_strategy_symbols().await;
+        Ok(())
+    }
+
+    /// 向各交易所连接追加订阅策略依赖的交易对，并退订此前为策略追加、当前策略已不再需要的交易对
+    /// (主循环启动前只加载策略，启动时再统一订阅)
+    async fn sync_strategy_symbols(&mut self) {
+        let wanted: BTreeSet<String> = self.strategies.iter().flat_map(|s| s.strategy.symbols()).collect();
+        for (id, conn) in self.feeds.clone() {
+            let needed: Vec<String> = wanted.iter().map(|s| normalize_symbol(id, s)).collect();
+            let tracked = self.strategy_symbols.entry(id).or_default();
+            let stale: Vec<String> = tracked.iter().filter(|s| !needed.contains(s)).cloned().collect();
+            if !stale.is_empty() {
+                match conn.unsubscribe_symbols(&stale).await {
+                    Ok(()) => tracked.retain(|s| !stale.contains(s)),
+                    Err(e) => warn!("{:?} 退订策略交易对失败: {}", id, e),
+                }
+            }
+            if needed.is_empty() {
+                continue;
+            }
+            match conn.subscribe_symbols(&needed).await {
+                Ok(added) => tracked.extend(added),
+                Err(e) => warn!("{:?} 追加订阅策略交易对失败: {}", id, e),
+            }
+        }
+    }
+
+    /// 按配置构建并加载策略 (不支持或配置无效的跳过)
+    pub fn load_strategies(&mut self, configs: Vec<StrategyConfig>) {
+        for config in configs {
+            let Some(strategy) = build_strategy(&config) else {
+                warn!("策略 {} ({:?}) 暂不支持，跳过", config.name, config.strategy_type);
+                continue;
+            };
+            if let Err(e) = self.add_strategy(strategy, &config) {
+                error!("加载策略 {} 失败: {}", config.name, e);
+            }
+        }
+    }
+
+    /// 是否已加载该类型的策略 (决定是否需要额外的行情，如资金费率轮询)
+    pub fn has_strategy_type(&self, strategy_type: StrategyType) -> bool {
+        self.strategies.iter().any(|slot| slot.strategy.strategy_type() == strategy_type)
+    }
+
+    /// 加载策略 (解析调度窗口)
+    pub fn add_strategy(&mut self, mut strategy: Box<dyn Strategy>, config: &StrategyConfig) -> Result<()> {
+        let schedule = StrategySchedule::from_config(config)?;
+        strategy.set_clock(self.clock.clone());
+        strategy.set_venue_ranking(&self.venue_ranking());
+        let active = schedule
+            .as_ref()
+            .map(|s| s.is_active(self.clock.now_ms()))
+            .unwrap_or(true);
+        info!(
+            "加载策略 {} ({:?}), 调度: {}",
+            config.name,
+            config.strategy_type,
+            if schedule.is_some() { "按时间窗口" } else { "始终活跃" }
+        );
+        self.strategies.push(StrategySlot {
+            strategy,
+            name: config.name.clone(),
+            schedule,
+            active,
+            priority: config.priority,
+            stop_config: StopConfig::from_config(&config.config),
+            panicked: false,
+            paused: false,
+            partial_klines: config
+                .config
+                .get("partial_klines")
+                .and_then(|v| v.as_bool())
+                .unwrap_or(false),
+        });
+        self.sync_strategies();
+        Ok(())
+    }
+
+    /// 启动对账: 从 OMS 拉取持仓与未完成订单写入本地仓位簿
+    pub async fn recover_state(&mut self) -> Result<()> {
+        if !self.executor.has_oms() {
+            info!("未配置 OMS，跳过启动对账");
+            return Ok(());
+        }
+        let (positions, orders) = self.executor.fetch_open_state().await?;
+        for position in positions {
+            info!(
+                "恢复持仓: {:?} {} 数量 {} 均价 {}",
+                position.exchange, position.symbol, position.quantity, position.avg_price
+            );
+            self.positions.seed_position(position);
+        }
+        for order in orders {
+            self.positions.seed_open_order(order);
+        }
+        self.sync_positions();
+        info!(
+            "启动对账完成: {} 个持仓, {} 个未完成订单",
+            self.positions.position_count(),
+            self.positions.open_order_count()
+        );
+        Ok(())
+    }
+
+    /// 从 Redis 加载置信度校准表
+    pub async fn load_calibration(&mut self) {
+        let Some(redis) = self.redis_client().cloned() else {
+            return;
+        };
+        if let Err(e) = self.calibrator.load(&redis).await {
+            warn!("加载置信度校准表失败: {}", e);
+        }
+    }
+
+    /// 定期重新拟合并持久化校准表
+    async fn refit_calibration(&mut self) {
+        self.calibrator.refit();
+        if let Some(mut conn) = self.redis_conn().await {
+            if let Err(e) = self.calibrator.persist(&mut conn).await {
+                warn!("持久化置信度校准表失败: {}", e);
+            }
+        }
+    }
+
+    /// 运行引擎主循环
+    pub async fn run(&mut self, connections: &HashMap<ExchangeId, Arc<ExchangeConnection>>) -> Result<()> {
+        let (tx, mut rx) = mpsc::channel::<Ticker>(4096);
+
+        self.feeds = connections.clone();
+        self.sync_strategy_symbols().await;
+
+        let lag_policy = LagPolicy::from_env();
+        for (id, conn) in connections {
+            let forwarder = TickerForwarder::new(conn.clone(), tx.clone(), lag_policy.clone());
+            let task = format!("ticker_forwarder:{:?}", id);
+            self.supervisor.spawn(task, TaskKind::Feed, forwarder.run());
+        }
+        drop(tx);
+
+        // 订单簿快照 (仅订阅了深度频道的交易所有推送)；快照是全量的，滞后丢弃不影响一致性
+        let (book_tx, mut book_rx) = mpsc::channel::<OrderBook>(1024);
+        for (id, conn) in connections {
+            self.spawn_forwarder(format!("orderbook_forwarder:{:?}", id), conn.subscribe_orderbooks(), book_tx.clone());
+        }
+        drop(book_tx);
+
+        // 逐笔成交 (仅订阅了成交频道的交易所有推送)；滞后时丢弃，不补发
+        let (trade_tx, mut trade_rx) = mpsc::channel::<Trade>(4096);
+        for (id, conn) in connections {
+            self.spawn_forwarder(format!("trade_forwarder:{:?}", id), conn.subscribe_trades(), trade_tx.clone());
+        }
+        drop(trade_tx);
+
+        // K 线 (仅订阅了 K 线频道的交易所有推送)；滞后时丢弃，不补发
+        let (kline_tx, mut kline_rx) = mpsc::channel::<Kline>(4096);
+        for (id, conn) in connections {
+            self.spawn_forwarder(format!("kline_forwarder:{:?}", id), conn.subscribe_klines(), kline_tx.clone());
+        }
+        drop(kline_tx);
+
+        // 资金费率 (仅开始轮询的交易所有推送)
+        let (funding_tx, mut funding_rx) = mpsc::channel::<FundingRateUpdate>(1024);
+        for (id, conn) in connections {
+            self.spawn_forwarder(format!("funding_forwarder:{:?}", id), conn.subscribe_funding_rates(), funding_tx.clone());
+        }
+        drop(funding_tx);
+
+        let (result_tx, mut result_rx) = mpsc::unbounded_channel::<ExecutionOutcome>();
+        let mut panic_rx = self
+            .panic_rx
+            .take()
+            .ok_or_else(|| anyhow::anyhow!("引擎已在运行"))?;
+        let mut schedule_tick = tokio::time::interval(Duration::from_secs(1));
+        let mut calibration_tick = tokio::time::interval(Duration::from_secs(60));
+        let mut metrics_tick = tokio::time::interval(Duration::from_secs(5));
+        let mut heatmap_tick = tokio::time::interval(self.heatmap.interval);
+        let mut latency_tick = tokio::time::interval(Duration::from_secs(10));
+        let mut control_rx = self
+            .control_rx
+            .take()
+            .ok_or_else(|| anyhow::anyhow!("引擎已在运行"))?;
+        let mut order_rx = self
+            .order_rx
+            .take()
+            .ok_or_else(|| anyhow::anyhow!("引擎已在运行"))?;
+        loop {
+            self.fill_execution_slots(&result_tx).await;
+            tokio::select! {
+                ticker = rx.recv() => {
+                    let Some(ticker) = ticker else {
+                        warn!("所有行情通道已关闭，引擎退出");
+                        return Ok(());
+                    };
+                    self.on_market_ticker(&ticker, SystemTime::now(), connections).await;
+                }
+                Some(book) = book_rx.recv() => {
+                    self.dispatch_orderbook(&book);
+                }
+                Some(trade) = trade_rx.recv() => {
+                    self.dispatch_event(&trade, |_| true, |strategy, trade| strategy.on_trade(trade)).await;
+                }
+                Some(kline) = kline_rx.recv() => {
+                    // 未收盘的 K 线只发给设置了 partial_klines 的策略
+                    self.dispatch_event(&kline, |slot| kline.closed || slot.partial_klines, |strategy, kline| {
+                        strategy.on_kline(kline)
+                    })
+                    .await;
+                }
+                Some(funding) = funding_rx.recv() => {
+                    self.dispatch_funding_rate(&funding).await;
+                }
+                Some(command) = control_rx.recv() => {
+                    self.handle_control(command).await;
+                }
+                Some(event) = panic_rx.recv() => {
+                    self.handle_panic(event).await;
+                }
+                Some(outcome) = result_rx.recv() => {
+                    self.in_flight = self.in_flight.saturating_sub(1);
+                    self.finish_execution(outcome).await;
+                }
+                Some(update) = order_rx.recv() => {
+                    self.in_flight = self.in_flight.saturating_sub(1);
+                    self.apply_order_update(update).await;
+                }
+                _ = schedule_tick.tick() => {
+                    self.tick_faults();
+                    self.update_schedules().await;
+                    self.check_queue_watermark().await;
+                    self.sync_exchange_health(connections).await;
+                }
+                _ = calibration_tick.tick() => {
+                    self.refit_calibration().await;
+                    self.publish_scorecard().await;
+                }
+                _ = metrics_tick.tick() => {
+                    self.publish_queue_metrics().await;
+                    self.publish_tick_rates().await;
+                    self.refresh_disabled_paths().await;
+                }
+                _ = heatmap_tick.tick(), if self.heatmap.enabled => {
+                    self.publish_heatmap().await;
+                }
+                _ = latency_tick.tick() => {
+                    self.publish_latency().await;
+                }
+            }
+        }
+    }
+
+    /// 把交易所广播的事件转发到主循环；滞后时丢弃，不补发
+    fn spawn_forwarder<T>(&self, task: String, mut events: broadcast::Receiver<T>, tx: mpsc::Sender<T>)
+    where
+        T: Clone + Send + 'static,
+    {
+        self.supervisor.spawn(task, TaskKind::Feed, async move {
+            loop {
+                match events.recv().await {
+                    Ok(event) => {
+                        if tx.send(event).await.is_err() {
+                            return;
+                        }
+                    }
+                    Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                    Err(broadcast::error::RecvError::Closed) => return,
+                }
+            }
+        });
+    }
+
+    /// 停机: 并发关闭所有交易所连接 (各自等待读循环退出) 并发布停止事件
+    pub async fn shutdown(&self, connections: &HashMap<ExchangeId, Arc<ExchangeConnection>>) {
+        let started = std::time::Instant::now();
+        futures_util::future::join_all(connections.values().map(|conn| conn.stop())).await;
+        info!("已关闭 {} 个交易所连接 ({} 毫秒)", connections.len(), started.elapsed().as_millis());
+        self.publish_status(&serde_json::json!({
+            "event": "stopped",
+            "timestamp": chrono::Utc::now().timestamp_millis(),
+        }))
+        .await;
+    }
+
+    /// 处理一条行情: 故障注入丢弃 → 行情健康度 / 频率 → 报价检查 → 调度 → 分发给策略
+    async fn on_market_ticker(
+        &mut self,
+        ticker: &Ticker,
+        received_at: SystemTime,
+        connections: &HashMap<ExchangeId, Arc<ExchangeConnection>>,
+    ) {
+        let now = self.clock.now_ms();
+        if self.faults.as_ref().is_some_and(|f| f.drop_ticker(ticker, now)) {
+            return;
+        }
+        self.health.record(ticker, now);
+        self.tick_rates.record(ticker.exchange, &ticker.symbol, now);
+        self.scorecard.record_tick(ticker.exchange, now);
+        if !self.check_quote(ticker, connections).await {
+            return;
+        }
+        self.update_schedules().await;
+        self.latency.record(ticker, chrono::Utc::now().timestamp_millis());
+        METRICS.ticker_processed(ticker.exchange);
+        self.dispatch(ticker, received_at).await;
+    }
+
+    /// 回测: 按实盘同一条路径处理一条回放行情 (时钟由调用方推进)
+    pub async fn replay_ticker(&mut self, ticker: &Ticker) {
+        self.tick_faults();
+        self.on_market_ticker(ticker, SystemTime::now(), &HashMap::new()).await;
+    }
+
+    /// 回测: 交易所断线，直到回放中出现该交易所的下一笔行情
+    pub fn replay_disconnect(&mut self, exchange: ExchangeId) {
+        warn!("{:?} 连接断开 (回放)，等待重连", exchange);
+        self.health.on_disconnect(exchange);
+    }
+
+    /// 等待执行的信号数
+    pub fn queued_signals(&self) -> usize {
+        self.queue.len()
+    }
+
+    /// 检查行情是否带有可成交的买一卖一；缺失时不分发给策略，并补订阅最优报价频道
+    async fn check_quote(
+        &mut self,
+        ticker: &Ticker,
+        connections: &HashMap<ExchangeId, Arc<ExchangeConnection>>,
+    ) -> bool {
+        let Some(first) = self.health.record_quote(ticker) else {
+            return true;
+        };
+        if !first {
+            return false;
+        }
+        warn!(
+            "{:?} {} 行情缺失买一卖一 (bid={} ask={} last={})，不再分发给策略",
+            ticker.exchange, ticker.symbol, ticker.bid, ticker.ask, ticker.last
+        );
+        self.incr_metrics(&[("ticker_missing_quote", 1)]).await;
+        if self.health.take_quote_request(ticker.exchange, &ticker.symbol) {
+            if let Some(conn) = connections.get(&ticker.exchange).cloned() {
+                let symbol = ticker.symbol.clone();
+                tokio::spawn(async move {
+                    if let Err(e) = conn.request_quotes(std::slice::from_ref(&symbol)).await {
+                        warn!("{:?} {} 补订阅最优报价频道失败: {}", conn.id, symbol, e);
+                    }
+                });
+            }
+        }
+        false
+    }
+
+    /// 将 Ticker 分发给所有策略；不在活跃窗口内的策略照常接收行情，但信号被抑制
+    async fn dispatch(&mut self, ticker: &Ticker, received_at: SystemTime) {
+        let traced = self.tracer.is_some();
+        let mut signals = vec![];
+        let mut suppressed = 0;
+        let mut panicked = false;
+        let mut near_misses = vec![];
+        self.state.update(|s| s.record_ticker(ticker));
+        for slot in self.strategies.iter_mut() {
+            if slot.panicked {
+                continue;
+            }
+            // 策略回调 panic 只停用该策略，不拖垮引擎主循环
+            let detect_start = SystemTime::now();
+            let produced = match AssertUnwindSafe(slot.strategy.on_ticker(ticker)).catch_unwind().await {
+                Ok(signal) => signal,
+                Err(payload) => {
+                    slot.panicked = true;
+                    let task = format!("strategy:{}", slot.strategy.id());
+                    self.supervisor.report(task, TaskKind::Strategy, payload.as_ref());
+                    panicked = true;
+                    continue;
+                }
+            };
+            near_misses.extend(slot.strategy.take_near_misses());
+            if let Some(mut signal) = produced {
+                if slot.paused {
+                    continue;
+                }
+                if slot.active {
+                    self.report.record_signal(&signal);
+                    METRICS.signal_generated(signal.exchange);
+                    signal.priority = slot.priority;
+                    if traced {
+                        signal.trace = Some(SignalTrace::detected(&signal, &ticker.symbol, received_at, detect_start));
+                    }
+                    signals.push(signal);
+                } else {
+                    suppressed += 1;
+                }
+            }
+        }
+        if panicked {
+            self.sync_strategies();
+        }
+        self.publish_near_misses(&near_misses).await;
+        // 同一行情事件的指标合并为一次 Redis 管道写入
+        let mut metrics = vec![];
+        if suppressed > 0 {
+            metrics.push(("schedule_inactive", suppressed));
+        }
+        let (stale, unquoted) = self.admit_signals(signals).await;
+        if stale > 0 {
+            metrics.push(("signals_stale", stale));
+        }
+        if unquoted > 0 {
+            metrics.push(("signals_missing_quote", unquoted));
+        }
+        self.incr_metrics(&metrics).await;
+        for stop in self.stops.on_ticker(ticker) {
+            self.trigger_stop(stop);
+        }
+        self.requote_orders(ticker);
+    }
+
+    /// 未完成的限价挂单偏离同侧最优价 (买单对买一、卖单对卖一) 超过 `requote_bps` 时改价到最优价，
+    /// 在独立任务中经 `OrderExecutor::amend_order` 原生改单或撤单重挂，结果由主循环应用到仓位簿
+    fn requote_orders(&mut self, ticker: &Ticker) {
+        if self.requote_bps <= 0.0 {
+            return;
+        }
+        for order in self.positions.open_orders_for(ticker.exchange, &ticker.symbol) {
+            let touch = match order.side {
+                OrderSide::Buy => ticker.bid,
+                OrderSide::Sell => ticker.ask,
+            };
+            if order.price <= 0.0 || touch <= 0.0 || order.amount <= 0.0 {
+                continue;
+            }
+            if (touch - order.price).abs() / order.price * 10_000.0 < self.requote_bps
+                || self.requoting.contains(&order.order_id)
+            {
+                continue;
+            }
+            self.requoting.insert(order.order_id.clone());
+            self.in_flight += 1;
+            let executor = self.executor.clone();
+            let order_tx = self.order_tx.clone();
+            let task = format!("requote:{}", order.order_id);
+            self.supervisor.spawn(task, TaskKind::Execution, async move {
+                let result = executor.amend_order(&order, touch, order.amount).await;
+                let _ = order_tx.send(OrderUpdate::Requoted { order, price: touch, result });
+            });
+        }
+    }
+
+    /// 应用后台订单任务的结果
+    async fn apply_order_update(&mut self, update: OrderUpdate) {
+        match update {
+            OrderUpdate::Requoted { order, price, result } => {
+                self.requoting.remove(&order.order_id);
+                match result {
+                    Ok(amended) => {
+                        let response = amended.order;
+                        self.positions.remove_open_order(&order.order_id);
+                        if response.filled_amount > 0.0 {
+                            self.positions.apply_fill(
+                                response.exchange,
+                                &response.symbol,
+                                response.side,
+                                response.filled_amount,
+                                response.avg_price,
+                            );
+                            self.sync_positions();
+                        }
+                        let remaining = order.amount - response.filled_amount;
+                        if !response.status.is_terminal() && remaining > 0.0 {
+                            self.positions.seed_open_order(OpenOrder {
+                                order_id: response.order_id,
+                                amount: remaining,
+                                price,
+                                ..order
+                            });
+                        }
+                        self.incr_metric("orders_requoted", 1).await;
+                    }
+                    Err(e) => warn!(
+                        "挂单改价失败 {:?} {} {}: {}",
+                        order.exchange, order.symbol, order.order_id, e
+                    ),
+                }
+            }
+            OrderUpdate::StopFilled { stop, result } => match result {
+                Ok(fill) => {
+                    self.positions
+                        .apply_fill(stop.exchange, &stop.symbol, stop.side, fill.filled_amount, fill.avg_price);
+                    self.sync_positions();
+                    let pnl = stop.realized_pnl(fill.avg_price);
+                    self.incr_metric("stop_triggered", 1).await;
+                    self.publish_status(&serde_json::json!({
+                        "event": "stop_triggered",
+                        "strategyId": stop.strategy_id,
+                        "positionId": stop.position_id,
+                        "exchange": stop.exchange,
+                        "symbol": stop.symbol,
+                        "exitPrice": fill.avg_price,
+                        "realizedPnl": pnl,
+                        "timestamp": self.clock.now_ms(),
+                    }))
+                    .await;
+                }
+                Err(e) => error!("止损平仓失败 [{}]: {}", stop.position_id, e),
+            },
+        }
+    }
+
+    /// 行情检查通过的信号入队: 路径上有缺失报价或陈旧行情的交易对时抑制，返回 (陈旧, 缺失报价) 抑制数
+    async fn admit_signals(&mut self, signals: Vec<Signal>) -> (i64, i64) {
+        let now = self.clock.now_ms();
+        let mut stale = 0;
+        let mut unquoted = 0;
+        for signal in signals {
+            if let Some(symbol) = self.health.unquoted_in_path(signal.exchange, &signal.path) {
+                warn!(
+                    "行情缺失买一卖一，抑制信号 [{}]: {:?} {} ({})",
+                    signal.correlation_id, signal.exchange, symbol, signal.path
+                );
+                unquoted += 1;
+                continue;
+            }
+            if let Some(symbol) = self.health.stale_in_path(signal.exchange, &signal.path, now) {
+                warn!(
+                    "行情陈旧，抑制信号 [{}]: {:?} {} ({})",
+                    signal.correlation_id, signal.exchange, symbol, signal.path
+                );
+                if let Some(faults) = &self.faults {
+                    faults.record_stale_suppression(signal.exchange, &symbol);
+                }
+                self.scorecard.record_stale(signal.exchange, now);
+                stale += 1;
+                continue;
+            }
+            self.enqueue(signal).await;
+        }
+        (stale, unquoted)
+    }
+
+    /// 将成交 / K 线 / 资金费率等事件分发给 `accepts` 接收的策略；信号与行情信号走同一套活跃窗口 / 陈旧行情检查后入队
+    async fn dispatch_event<E, F>(&mut self, event: &E, accepts: impl Fn(&StrategySlot) -> bool, mut on_event: F)
+    where
+        F: for<'a> FnMut(&'a mut Box<dyn Strategy>, &'a E) -> BoxFuture<'a, Option<Signal>>,
+    {
+        let mut signals = vec![];
+        let mut suppressed = 0;
+        let mut panicked = false;
+        for slot in self.strategies.iter_mut() {
+            if slot.panicked || !accepts(slot) {
+                continue;
+            }
+            let produced = match AssertUnwindSafe(on_event(&mut slot.strategy, event)).catch_unwind().await {
+                Ok(signal) => signal,
+                Err(payload) => {
+                    slot.panicked = true;
+                    let task = format!("strategy:{}", slot.strategy.id());
+                    self.supervisor.report(task, TaskKind::Strategy, payload.as_ref());
+                    panicked = true;
+                    continue;
+                }
+            };
+            let Some(mut signal) = produced else {
+                continue;
+            };
+            if slot.paused {
+                continue;
+            }
+            if slot.active {
+                self.report.record_signal(&signal);
+                METRICS.signal_generated(signal.exchange);
+                signal.priority = slot.priority;
+                signals.push(signal);
+            } else {
+                suppressed += 1;
+            }
+        }
+        self.admit_event_signals(signals, suppressed, panicked).await;
+    }
+
+    /// 更新资金费率缓存 (配对、网格估算持仓资金费) 并分发给所有策略
+    async fn dispatch_funding_rate(&mut self, funding: &FundingRateUpdate) {
+        FUNDING_CACHE.update(
+            funding.exchange,
+            &funding.symbol,
+            FundingRate {
+                rate: funding.rate,
+                next_funding_time: funding.next_funding_time,
+                ..Default::default()
+            },
+        );
+        self.dispatch_event(funding, |_| true, |strategy, funding| strategy.on_funding_rate(funding))
+            .await;
+    }
+
+    /// 成交 / K 线 / 资金费率回调产生的信号入队，并记录被调度窗口抑制、陈旧与缺少报价的数量
+    async fn admit_event_signals(&mut self, signals: Vec<Signal>, suppressed: i64, panicked: bool) {
+        if panicked {
+            self.sync_strategies();
+        }
+        let mut metrics = vec![];
+        if suppressed > 0 {
+            metrics.push(("schedule_inactive", suppressed));
+        }
+        let (stale, unquoted) = self.admit_signals(signals).await;
+        if stale > 0 {
+            metrics.push(("signals_stale", stale));
+        }
+        if unquoted > 0 {
+            metrics.push(("signals_missing_quote", unquoted));
+        }
+        self.incr_metrics(&metrics).await;
+    }
+
+    /// 将订单簿快照分发给所有策略
+    fn dispatch_orderbook(&mut self, book: &OrderBook) {
+        let mut panicked = false;
+        for slot in self.strategies.iter_mut().filter(|slot| !slot.panicked) {
+            let strategy = &mut slot.strategy;
+            if let Err(payload) = std::panic::catch_unwind(AssertUnwindSafe(|| strategy.on_orderbook(book))) {
+                slot.panicked = true;
+                let task = format!("strategy:{}", slot.strategy.id());
+                self.supervisor.report(task, TaskKind::Strategy, payload.as_ref());
+                panicked = true;
+            }
+        }
+        if panicked {
+            self.sync_strategies();
+        }
+    }
+
+    /// 根据时钟更新各策略的活跃状态，并发布状态变化
+    async fn update_schedules(&mut self) {
+        let now = self.clock.now_ms();
+        let mut transitions = vec![];
+        let mut close_signals = vec![];
+        for slot in self.strategies.iter_mut() {
+            let Some(schedule) = &slot.schedule else {
+                continue;
+            };
+            let active = schedule.is_active(now);
+            if active == slot.active {
+                continue;
+            }
+            slot.active = active;
+            info!(
+                "策略 {} {}",
+                slot.strategy.id(),
+                if active { "进入活跃窗口" } else { "离开活跃窗口，暂停信号" }
+            );
+            if !active && schedule.close_on_end {
+                close_signals.extend(slot.strategy.close_signals(now));
+            }
+            transitions.push(serde_json::json!({
+                "event": "schedule",
+                "strategyId": slot.strategy.id(),
+                "strategyType": slot.strategy.strategy_type(),
+                "active": active,
+                "timestamp": now,
+            }));
+        }
+        if !transitions.is_empty() {
+            self.sync_strategies();
+        }
+        for payload in transitions {
+            self.publish_status(&payload).await;
+        }
+        for mut signal in close_signals {
+            signal.priority = CLOSE_PRIORITY;
+            self.enqueue(signal).await;
+        }
+    }
+
+    /// 信号入队；队列满时按优先级淘汰并记录原因
+    async fn enqueue(&mut self, mut signal: Signal) {
+        signal.raw_confidence = signal.confidence;
+        signal.confidence = self
+            .calibrator
+            .calibrate(signal.strategy_type, signal.raw_confidence);
+        // 近期拒单率偏高的交易所降级排队
+        if signal.action == SignalAction::Open
+            && self.scorecard.verdict(signal.exchange, self.clock.now_ms()) != VenueVerdict::Preferred
+        {
+            signal.priority -= DEPRIORITIZED_PENALTY;
+        }
+        self.state.update(|s| s.record_signal(&signal, "queued"));
+        match self.queue.push(signal, self.clock.now_ms()) {
+            PushOutcome::Queued => {}
+            PushOutcome::Evicted(old) => {
+                self.state.update(|s| s.set_verdict(&old.correlation_id, "evicted"));
+                warn!(
+                    "信号队列已满，淘汰低优先级信号 [{}]: {} {} (优先级 {})",
+                    old.correlation_id, old.strategy_id, old.path, old.priority
+                );
+                self.incr_metric("signal_queue_evicted_priority", 1).await;
+            }
+            PushOutcome::Rejected(new) => {
+                self.state.update(|s| s.set_verdict(&new.correlation_id, "queue_full"));
+                warn!(
+                    "信号队列已满，新信号优先级不足被丢弃 [{}]: {} {} (优先级 {})",
+                    new.correlation_id, new.strategy_id, new.path, new.priority
+                );
+                self.incr_metric("signal_queue_rejected", 1).await;
+            }
+        }
+    }
+
+    /// 在同时执行数上限 (ENGINE_MAX_IN_FLIGHT) 内依次启动队首信号，其余信号留在队列中等待
+    async fn fill_execution_slots(&mut self, result_tx: &mpsc::UnboundedSender<ExecutionOutcome>) {
+        while self.in_flight < self.max_in_flight && !self.halted {
+            if !self.start_next_execution(result_tx).await {
+                break;
+            }
+        }
+    }
+
+    /// 取出队首信号，本地检查通过后在独立任务中执行，避免执行器阻塞行情分发 (队列中没有可执行的信号时返回 false)
+    async fn start_next_execution(&mut self, result_tx: &mpsc::UnboundedSender<ExecutionOutcome>) -> bool {
+        while let Some(mut signal) = self.queue.pop(self.clock.now_ms()) {
+            let mut trace = signal.trace.take();
+            if let Some(trace) = &mut trace {
+                trace.risk_started();
+            }
+            let Some(signal) = self.route_observe_only(signal).await else {
+                continue;
+            };
+            if self.is_duplicate_open(&signal) {
+                self.state.update(|s| s.set_verdict(&signal.correlation_id, "duplicate"));
+                continue;
+            }
+            if let Err(reason) = self.check_inventory(&signal) {
+                warn!("组合净持仓超限，跳过信号 [{}]: {}", signal.correlation_id, reason);
+                self.state.update(|s| s.set_verdict(&signal.correlation_id, "inventory_limit"));
+                self.export_trace(trace, "inventory_limit", &[]);
+                self.incr_metric("signals_inventory_blocked", 1).await;
+                continue;
+            }
+            if let Err(reason) = self.check_exchange_capital(&signal) {
+                warn!("交易所资金部署超限，跳过信号 [{}]: {}", signal.correlation_id, reason);
+                self.state.update(|s| s.set_verdict(&signal.correlation_id, "exchange_capital_limit"));
+                self.export_trace(trace, "exchange_capital_limit", &[]);
+                self.incr_metric("signals_exchange_capital_blocked", 1).await;
+                continue;
+            }
+            if signal.action == SignalAction::Open
+                && self.scorecard.verdict(signal.exchange, self.clock.now_ms()) == VenueVerdict::Blocked
+            {
+                warn!(
+                    "交易所拒单率超过拦截阈值，跳过开仓 [{}]: {:?} {}",
+                    signal.correlation_id, signal.exchange, signal.path
+                );
+                self.state.update(|s| s.set_verdict(&signal.correlation_id, "venue_blocked"));
+                self.export_trace(trace, "venue_blocked", &[]);
+                self.incr_metric("signals_venue_blocked", 1).await;
+                continue;
+            }
+            self.state.update(|s| s.set_verdict(&signal.correlation_id, "executing"));
+            self.in_flight += 1;
+            let exchange = signal.exchange;
+            let decision = self.decision_snapshot(&signal);
+            let executor = self.executor.clone();
+            let risk = self.risk.clone();
+            let result_tx = result_tx.clone();
+            let task = format!("execution:{}", signal.correlation_id);
+            self.supervisor.spawn(task, TaskKind::Execution, async move {
+                let correlation_id = signal.correlation_id.clone();
+                let passed = risk.evaluate_risk(&signal).await;
+                if let Some(trace) = &mut trace {
+                    trace.risk_finished();
+                }
+                if !passed {
+                    warn!(
+                        "信号被风控拦截 [{}]: {} {}",
+                        signal.correlation_id, signal.strategy_id, signal.path
+                    );
+                    let _ = result_tx.send(ExecutionOutcome {
+                        correlation_id,
+                        strategy_id: signal.strategy_id.clone(),
+                        strategy_type: signal.strategy_type,
+                        exchange,
+                        decision,
+                        elapsed_ms: 0,
+                        result: None,
+                        trace,
+                    });
+                    return;
+                }
+                if let Some(trace) = &mut trace {
+                    trace.execution_started();
+                }
+                let started = std::time::Instant::now();
+                let (strategy_id, strategy_type) = (signal.strategy_id.clone(), signal.strategy_type);
+                let result = executor.execute(signal).await;
+                if let Some(trace) = &mut trace {
+                    trace.execution_finished();
+                }
+                let _ = result_tx.send(ExecutionOutcome {
+                    correlation_id,
+                    strategy_id,
+                    strategy_type,
+                    exchange,
+                    decision,
+                    elapsed_ms: started.elapsed().as_millis() as u64,
+                    result: Some(result),
+                    trace,
+                });
+            });
+            return true;
+        }
+        false
+    }
+
+    /// 将落在只读行情交易所 (observe-only) 上的腿改派到可下单交易所中价格最优者，
+    /// 无可用交易所或改派后收益不为正时抑制信号
+    async fn route_observe_only(&self, mut signal: Signal) -> Option<Signal> {
+        let executable = |e: &ExchangeId| self.executor.can_execute_on(*e);
+        if executable(&signal.exchange) && signal.legs.iter().all(|leg| executable(&leg.exchange)) {
+            return Some(signal);
+        }
+        let all_tickers: Vec<Ticker> = self
+            .state
+            .read(|s| s.tickers.values().cloned().collect())
+            .unwrap_or_default();
+        let tickers: Vec<&Ticker> = all_tickers.iter().filter(|t| executable(&t.exchange)).collect();
+
+        let original = signal.exchange;
+        let mut growth = 1.0 + signal.profit_rate;
+        let mut routed = true;
+        if signal.legs.is_empty() {
+            // 单交易所信号: 按各可下单交易所的缓存行情重新定价，改派到收益最高者
+            let side = match signal.action {
+                SignalAction::Open => OrderSide::Buy,
+                SignalAction::Close => OrderSide::Sell,
+            };
+            let touch = |exchange: ExchangeId, symbol: &str| {
+                all_tickers
+                    .iter()
+                    .find(|t| t.exchange == exchange && normalize_symbol(t.exchange, &t.symbol) == symbol)
+                    .map(|t| match side {
+                        OrderSide::Buy => t.ask,
+                        OrderSide::Sell => t.bid,
+                    })
+                    .filter(|p| *p > 0.0)
+            };
+            let symbols: Vec<String> = parse_symbols_from_path(&signal.path)
+                .iter()
+                .map(|s| normalize_symbol(original, s))
+                .collect();
+            let reference: Option<Vec<f64>> = symbols.iter().map(|sym| touch(original, sym)).collect();
+            let mut venues: Vec<ExchangeId> = tickers.iter().map(|t| t.exchange).collect();
+            venues.sort_by_key(|e| format!("{:?}", e));
+            venues.dedup();
+            let best = reference.and_then(|reference| {
+                venues
+                    .into_iter()
+                    .filter_map(|venue| {
+                        let mut venue_growth = growth;
+                        for (sym, reference) in symbols.iter().zip(&reference) {
+                            let price = touch(venue, sym)?;
+                            venue_growth *= match side {
+                                OrderSide::Buy => reference / price,
+                                OrderSide::Sell => price / reference,
+                            };
+                        }
+                        Some((venue, venue_growth))
+                    })
+                    .max_by(|a, b| a.1.total_cmp(&b.1))
+            });
+            match best {
+                Some((exchange, venue_growth)) => {
+                    signal.exchange = exchange;
+                    growth = venue_growth;
+                }
+                None => routed = false,
+            }
+        } else {
+            for leg in signal.legs.iter_mut().filter(|leg| !executable(&leg.exchange)) {
+                let symbol = normalize_symbol(leg.exchange, &leg.symbol);
+                let candidates = tickers
+                    .iter()
+                    .filter(|t| normalize_symbol(t.exchange, &t.symbol) == symbol);
+                let best = match leg.side {
+                    OrderSide::Buy => candidates
+                        .filter(|t| t.ask > 0.0)
+                        .min_by(|a, b| a.ask.total_cmp(&b.ask))
+                        .map(|t| (t.exchange, t.ask)),
+                    OrderSide::Sell => candidates
+                        .filter(|t| t.bid > 0.0)
+                        .max_by(|a, b| a.bid.total_cmp(&b.bid))
+                        .map(|t| (t.exchange, t.bid)),
+                };
+                let Some((exchange, price)) = best else {
+                    routed = false;
+                    break;
+                };
+                growth *= match leg.side {
+                    OrderSide::Buy => leg.price / price,
+                    OrderSide::Sell => price / leg.price,
+                };
+                leg.exchange = exchange;
+                leg.price = price;
+            }
+            signal.exchange = signal.legs[0].exchange;
+        }
+
+        let profit_rate = growth - 1.0;
+        if !routed || profit_rate <= 0.0 {
+            warn!(
+                "信号落在只读行情交易所且无可改派的交易所或改派后无收益，已抑制 [{}]: {:?} {}",
+                signal.correlation_id, original, signal.path
+            );
+            self.state.update(|s| s.set_verdict(&signal.correlation_id, "observe_only"));
+            self.incr_metric("signals_observe_only", 1).await;
+            return None;
+        }
+        if signal.profit_rate != 0.0 {
+            signal.expected_profit *= profit_rate / signal.profit_rate;
+        }
+        signal.set_profit_rate(profit_rate);
+        info!(
+            "信号从只读行情交易所改派 [{}]: {:?} -> {:?}",
+            signal.correlation_id, original, signal.exchange
+        );
+        Some(signal)
+    }
+
+    /// 信号路径上各交易对的最新行情
+    fn decision_snapshot(&self, signal: &Signal) -> Vec<Ticker> {
+        let symbols: Vec<String> = parse_symbols_from_path(&signal.path)
+            .iter()
+            .map(|s| normalize_symbol(signal.exchange, s))
+            .collect();
+        self.state
+            .read(|s| {
+                s.tickers
+                    .iter()
+                    .filter(|((exchange, symbol), _)| {
+                        *exchange == signal.exchange && symbols.contains(&normalize_symbol(*exchange, symbol))
+                    })
+                    .map(|(_, ticker)| ticker.clone())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// 方向性信号执行后组合净持仓是否超过单币种上限
+    ///
+    /// 只对单交易对的方向性信号估算变动 (开仓买入、平仓卖出，名义金额取执行器实际下单的金额，
+    /// 预期收益为负的信号同样计入)；期现、三角等多腿信号各腿相互对冲，不改变净持仓。
+    fn check_inventory(&self, signal: &Signal) -> Result<(), String> {
+        if self.inventory_limits.is_empty() || !signal.strategy_type.is_directional() {
+            return Ok(());
+        }
+        let symbols = parse_symbols_from_path(&signal.path);
+        let [symbol] = symbols.as_slice() else {
+            return Ok(());
+        };
+        let Some(asset) = base_asset(symbol) else {
+            return Ok(());
+        };
+        // 推算不出下单金额的信号执行器会拒绝执行
+        let Some(notional) = self.executor.order_notional(signal) else {
+            return Ok(());
+        };
+        let delta = match signal.action {
+            SignalAction::Open => notional,
+            SignalAction::Close => -notional,
+        };
+        self.inventory_limits
+            .check(&asset, self.positions.net_inventory(&asset), delta)
+    }
+
+    /// 开仓信号投入的资金是否使所涉交易所的资金部署超过上限
+    ///
+    /// 投入金额取执行器实际下单的名义金额；多腿信号按各腿成交的交易所分别检查。
+    fn check_exchange_capital(&self, signal: &Signal) -> Result<(), String> {
+        if self.exchange_caps.is_empty() || signal.action != SignalAction::Open {
+            return Ok(());
+        }
+        let Some(notional) = self.executor.order_notional(signal) else {
+            return Ok(());
+        };
+        let mut exchanges: Vec<ExchangeId> = vec![];
+        for exchange in signal.legs.iter().map(|leg| leg.exchange).chain(std::iter::once(signal.exchange)) {
+            if !exchanges.contains(&exchange) {
+                exchanges.push(exchange);
+            }
+        }
+        for exchange in exchanges {
+            self.exchange_caps
+                .check(exchange, self.positions.deployed_capital(exchange), notional)?;
+        }
+        Ok(())
+    }
+
+    /// 方向性开仓是否重复: 同一策略已在该交易对的同一价格档位持仓 (网格不同格线可分批买入)，
+    /// 或交易对上有不是引擎开出的持仓 / 挂单 (启动时对账恢复，无法判断归属)
+    fn is_duplicate_open(&self, signal: &Signal) -> bool {
+        if signal.action != SignalAction::Open || !signal.strategy_type.is_directional() {
+            return false;
+        }
+        for symbol in parse_symbols_from_path(&signal.path) {
+            if self.positions.has_lot(signal.exchange, &symbol, &signal.strategy_id, signal.level) {
+                warn!(
+                    "跳过重复开仓 [{}]: {} 已在 {:?} {} 的档位 {:?} 持仓",
+                    signal.correlation_id, signal.strategy_id, signal.exchange, symbol, signal.level
+                );
+                return true;
+            }
+            if self.positions.has_untracked_exposure(signal.exchange, &symbol) {
+                warn!(
+                    "跳过重复开仓 [{}]: {:?} {} 已有对账恢复的持仓或挂单",
+                    signal.correlation_id, signal.exchange, symbol
+                );
+                return true;
+            }
+        }
+        false
+    }
+
+    /// 处理执行结果: 校准样本、仓位簿与止损
+    async fn finish_execution(&mut self, outcome: ExecutionOutcome) {
+        let ExecutionOutcome {
+            correlation_id,
+            strategy_id,
+            strategy_type,
+            exchange,
+            decision,
+            elapsed_ms,
+            result,
+            trace,
+        } = outcome;
+        let Some(result) = result else {
+            METRICS.signal_blocked("risk", 1);
+            self.state.update(|s| s.set_verdict(&correlation_id, "risk_blocked"));
+            self.export_trace(trace, "risk_blocked", &[]);
+            return;
+        };
+        let now = self.clock.now_ms();
+        match &result {
+            Ok(result) => self.scorecard.record_execution(result, &decision, now),
+            Err(_) => self.scorecard.record_failure(exchange, now),
+        }
+        self.update_venue_ranking();
+        let verdict = match &result {
+            Ok(result) if result.success => "executed",
+            _ => "failed",
+        };
+        METRICS.execution(exchange, verdict == "executed");
+        self.state.update(|s| s.set_verdict(&correlation_id, verdict));
+        let orders = result.as_ref().map(|r| r.orders.as_slice()).unwrap_or_default();
+        self.export_trace(trace, verdict, orders);
+        if let Ok(result) = &result {
+            let entry = LedgerEntry {
+                correlation_id: correlation_id.clone(),
+                strategy_id: result.signal.strategy_id.clone(),
+                path: result.signal.path.clone(),
+                net_profit: result.net_profit,
+                simulated: self.executor.is_simulation(),
+                timestamp: self.clock.now_ms(),
+            };
+            self.state.update(|s| s.record_ledger(entry));
+            self.record_strategy_pnl(result, elapsed_ms).await;
+        }
+        match result {
+            Ok(result) if result.success => {
+                self.report.record_execution(
+                    &result.signal.strategy_id,
+                    result.signal.strategy_type,
+                    result.net_profit + result.total_fee,
+                    result.net_profit,
+                    elapsed_ms,
+                    true,
+                );
+                self.calibrator.record(
+                    result.signal.strategy_type,
+                    result.signal.raw_confidence,
+                    result.net_profit > 0.0,
+                );
+                self.positions.apply_execution(&result.signal, &result.orders);
+                self.sync_positions();
+                self.manage_stops(&result.signal, &result.orders).await;
+            }
+            Ok(result) => {
+                self.report.record_execution(
+                    &strategy_id,
+                    strategy_type,
+                    result.net_profit + result.total_fee,
+                    result.net_profit,
+                    elapsed_ms,
+                    false,
+                );
+                self.calibrator
+                    .record(result.signal.strategy_type, result.signal.raw_confidence, false);
+            }
+            Err(e) => {
+                error!("信号执行失败 [{}]: {}", correlation_id, e);
+                self.report
+                    .record_execution(&strategy_id, strategy_type, 0.0, 0.0, elapsed_ms, false);
+            }
+        }
+    }
+
+    /// 导出信号生命周期链路 (未开启时忽略)
+    fn export_trace(&self, trace: Option<SignalTrace>, verdict: &str, orders: &[crate::executor::OrderResponse]) {
+        if let (Some(tracer), Some(mut trace)) = (&self.tracer, trace) {
+            if verdict != "executed" && verdict != "failed" && verdict != "risk_blocked" {
+                trace.risk_finished();
+            }
+            trace.export(tracer, verdict, orders);
+        }
+    }
+
+    /// 按策略累计盈亏，写入 Redis 实时累计与 Postgres 历史
+    async fn record_strategy_pnl(&mut self, result: &ExecutionResult, elapsed_ms: u64) {
+        let Some(totals) = self.strategy_pnl.record(result) else {
+            return;
+        };
+        let totals = totals.clone();
+        let strategy_id = result.signal.strategy_id.clone();
+        self.state.update(|s| {
+            s.strategy_pnl.insert(strategy_id, totals);
+        });
+        if self.pnl_sinks.redis {
+            if let Some(mut conn) = self.redis_conn().await {
+                if let Err(e) = accounting::publish_redis(&mut conn, result).await {
+                    warn!("写入策略盈亏到 Redis 失败 [{}]: {}", result.signal.correlation_id, e);
+                }
+            }
+        }
+        if !self.pnl_sinks.postgres || self.store_down(Store::Db) {
+            return;
+        }
+        let Some((pool, _)) = &self.strategy_source else {
+            return;
+        };
+        // 数据库写入不阻塞主循环
+        let pool = pool.clone();
+        let result = result.clone();
+        tokio::spawn(async move {
+            if let Err(e) = accounting::persist_postgres(&pool, &result, elapsed_ms).await {
+                warn!("写入策略盈亏到 Postgres 失败 [{}]: {}", result.signal.correlation_id, e);
+            }
+        });
+    }
+
+    /// 开仓后挂保护性止损，平仓后撤销被平掉的止损
+    async fn manage_stops(&mut self, signal: &Signal, orders: &[crate::executor::OrderResponse]) {
+        if signal.action == SignalAction::Close {
+            for stop in self.stops.cancel_for_fills(&signal.strategy_id, orders) {
+                info!("仓位已平，撤销止损 {} ({} @ {})", stop.id, stop.symbol, stop.stop_price);
+                self.cancel_native_stop(&stop).await;
+            }
+            return;
+        }
+        if !signal.strategy_type.is_directional() {
+            return;
+        }
+        let Some(config) = self
+            .strategies
+            .iter()
+            .find(|s| s.strategy.id() == signal.strategy_id)
+            .map(|s| s.stop_config.clone())
+        else {
+            return;
+        };
+        if !config.enabled {
+            return;
+        }
+        for mut stop in StopManager::build_stops(signal, orders, &config) {
+            match self.executor.place_stop_order(&stop).await {
+                Ok(order_id) => {
+                    stop.placement = StopPlacement::Native;
+                    stop.exchange_order_id = Some(order_id);
+                }
+                Err(e) => info!("止损由引擎侧监视器托管 ({}): {}", stop.symbol, e),
+            }
+            info!(
+                "登记止损 [{}]: {} {:?} {} @ {:.8}",
+                stop.position_id, stop.symbol, stop.side, stop.amount, stop.stop_price
+            );
+            self.stops.register(stop);
+        }
+    }
+
+    /// 撤销交易所侧的原生止损单 (失败只记录日志)
+    async fn cancel_native_stop(&self, stop: &StopOrder) {
+        if let Err(e) = self.executor.cancel_stop_order(stop).await {
+            warn!("撤销原生止损单 {} ({:?} {}) 失败: {}", stop.id, stop.exchange, stop.symbol, e);
+        }
+    }
+
+    /// 监视器触发止损: 在独立任务中市价平仓，成交由主循环计入持仓并归因盈亏
+    fn trigger_stop(&mut self, stop: StopOrder) {
+        warn!(
+            "止损触发 [{}]: {:?} {} 止损价 {:.8}",
+            stop.position_id, stop.exchange, stop.symbol, stop.stop_price
+        );
+        self.in_flight += 1;
+        let executor = self.executor.clone();
+        let order_tx = self.order_tx.clone();
+        let task = format!("stop:{}", stop.position_id);
+        self.supervisor.spawn(task, TaskKind::Execution, async move {
+            let result = executor
+                .market_order(stop.exchange, &stop.symbol, stop.side, stop.amount)
+                .await;
+            let _ = order_tx.send(OrderUpdate::StopFilled { stop, result });
+        });
+    }
+
+    /// 任务 panic: 严重告警；执行任务 panic 时释放执行槽位并按配置平仓
+    async fn handle_panic(&mut self, event: PanicEvent) {
+        self.incr_metric("task_panic", 1).await;
+        self.publish_status(&serde_json::json!({
+            "event": "alert",
+            "level": "critical",
+            "reason": "task_panic",
+            "task": event.task,
+            "kind": event.kind,
+            "message": event.message,
+            "timestamp": event.timestamp,
+        }))
+        .await;
+        if event.kind != TaskKind::Execution {
+            return;
+        }
+        self.in_flight = self.in_flight.saturating_sub(1);
+        if flatten_on_panic_enabled() {
+            self.flatten_all().await;
+        }
+    }
+
+    /// 市价平掉仓位簿中的所有持仓，并撤销所有止损；返回逐个持仓的平仓结果
+    async fn flatten_all(&mut self) -> Vec<serde_json::Value> {
+        for stop in self.stops.cancel_all() {
+            info!("平仓前撤销止损 {} ({} @ {})", stop.id, stop.symbol, stop.stop_price);
+            self.cancel_native_stop(&stop).await;
+        }
+        let mut results = Vec::new();
+        for position in self.positions.positions() {
+            let side = if position.quantity > 0.0 {
+                OrderSide::Sell
+            } else {
+                OrderSide::Buy
+            };
+            let amount = position.quantity.abs();
+            warn!("紧急平仓: {:?} {} {:?} {}", position.exchange, position.symbol, side, amount);
+            let mut result = serde_json::json!({
+                "exchange": position.exchange,
+                "symbol": position.symbol,
+                "side": side,
+                "amount": amount,
+            });
+            match self
+                .executor
+                .market_order(position.exchange, &position.symbol, side, amount)
+                .await
+            {
+                Ok(fill) => {
+                    self.positions
+                        .apply_fill(position.exchange, &position.symbol, side, fill.filled_amount, fill.avg_price);
+                    result["success"] = true.into();
+                    result["orderId"] = fill.order_id.into();
+                    result["filledAmount"] = fill.filled_amount.into();
+                    result["avgPrice"] = fill.avg_price.into();
+                }
+                Err(e) => {
+                    error!("紧急平仓失败 {:?} {}: {}", position.exchange, position.symbol, e);
+                    result["success"] = false.into();
+                    result["error"] = e.to_string().into();
+                }
+            }
+            results.push(result);
+        }
+        self.sync_positions();
+        results
+    }
+
+    /// 切换熔断开关并广播；同时写入风控的紧急停止开关 (Redis `engine:kill_switch`)，避免轮询把它覆盖回去
+    async fn set_kill_switch(&mut self, enabled: bool) {
+        warn!("熔断开关 {}", if enabled { "开启，停止执行新信号" } else { "关闭，恢复执行" });
+        self.halted = enabled;
+        self.state.update(|s| s.halted = enabled);
+        self.risk.set_kill_switch(enabled);
+        if let Some(mut conn) = self.redis_conn().await {
+            if let Err(err) = persist_kill_switch(&mut conn, enabled).await {
+                warn!("写入紧急停止开关失败: {}", err);
+            }
+        }
+        self.publish_status(&serde_json::json!({
+            "event": "kill_switch",
+            "enabled": enabled,
+            "timestamp": self.clock.now_ms(),
+        }))
+        .await;
+    }
+
+    /// 紧急平仓: 先开启熔断阻止新信号，再市价平掉所有持仓；重复调用时已平的持仓不再下单
+    async fn emergency_flatten(&mut self) -> serde_json::Value {
+        if !self.halted || !self.risk.kill_switch_engaged() {
+            self.set_kill_switch(true).await;
+        }
+        let positions = self.flatten_all().await;
+        let failed = positions
+            .iter()
+            .filter(|p| p["success"].as_bool() != Some(true))
+            .count();
+        let summary = serde_json::json!({
+            "event": "flatten",
+            "halted": self.halted,
+            "count": positions.len(),
+            "failed": failed,
+            "positions": positions,
+            "timestamp": self.clock.now_ms(),
+        });
+        self.publish_status(&summary).await;
+        summary
+    }
+
+    /// 处理 HTTP 控制指令
+    async fn handle_control(&mut self, command: ControlCommand) {
+        match command {
+            ControlCommand::PauseStrategy(id) | ControlCommand::ResumeStrategy(id)
+                if !self.strategies.iter().any(|s| s.strategy.id() == id) =>
+            {
+                warn!("控制指令: 策略 {} 不存在", id);
+            }
+            ControlCommand::PauseStrategy(id) => self.set_paused(&id, true),
+            ControlCommand::ResumeStrategy(id) => self.set_paused(&id, false),
+            ControlCommand::KillSwitch(enabled) => self.set_kill_switch(enabled).await,
+            ControlCommand::Reload => self.reload_strategies().await,
+            ControlCommand::Snapshot(reply) => {
+                let _ = reply.send(self.debug_snapshot());
+            }
+            ControlCommand::Strategies(reply) => {
+                let _ = reply.send(self.strategy_inventory());
+            }
+            ControlCommand::Flatten(reply) => {
+                let _ = reply.send(self.emergency_flatten().await);
+            }
+        }
+    }
+
+    /// 已加载的策略及其生效阈值与就绪状态
+    fn strategy_inventory(&self) -> serde_json::Value {
+        let strategies: Vec<serde_json::Value> = self
+            .strategies
+            .iter()
+            .map(|slot| {
+                serde_json::json!({
+                    "id": slot.strategy.id(),
+                    "name": slot.name,
+                    "strategyType": slot.strategy.strategy_type(),
+                    "priority": slot.priority,
+                    "active": slot.active,
+                    "paused": slot.paused,
+                    "panicked": slot.panicked,
+                    "ready": slot.strategy.is_ready(),
+                    "thresholds": slot.strategy.thresholds(),
+                })
+            })
+            .collect();
+        serde_json::json!({ "count": strategies.len(), "strategies": strategies })
+    }
+
+    /// 引擎内部状态快照 (在主循环内生成，保证各部分一致)
+    fn debug_snapshot(&self) -> serde_json::Value {
+        let strategies: Vec<serde_json::Value> = self
+            .strategies
+            .iter()
+            .map(|slot| {
+                serde_json::json!({
+                    "id": slot.strategy.id(),
+                    "strategyType": slot.strategy.strategy_type(),
+                    "active": slot.active,
+                    "paused": slot.paused,
+                    "panicked": slot.panicked,
+                    "priority": slot.priority,
+                    "state": slot.strategy.debug_state(),
+                })
+            })
+            .collect();
+        let queue = self.queue.stats();
+        serde_json::json!({
+            "now": self.clock.now_ms(),
+            "simulation": self.executor.is_simulation(),
+            "halted": self.halted,
+            "redisConnects": self.redis.as_ref().map(SharedRedis::connects),
+            "inFlight": self.in_flight,
+            "maxInFlight": self.max_in_flight,
+            "risk": {
+                "openExposure": self.risk.open_exposure(),
+                "exposureLimit": self.risk.config.exposure_limit,
+                "drawdown": self.risk.drawdown(),
+                "maxDrawdown": self.risk.config.max_drawdown,
+                "killSwitch": self.risk.kill_switch_engaged(),
+            },
+            "queue": {
+                "depth": queue.depth,
+                "capacity": self.queue.capacity(),
+                "evicted": queue.evicted,
+                "expired": queue.expired,
+            },
+            "strategies": strategies,
+        })
+    }
+
+    fn set_paused(&mut self, strategy_id: &str, paused: bool) {
+        for slot in self.strategies.iter_mut().filter(|s| s.strategy.id() == strategy_id) {
+            slot.paused = paused;
+            info!("策略 {} {}", strategy_id, if paused { "已暂停" } else { "已恢复" });
+        }
+        self.sync_strategies();
+    }
+
+    /// 从数据库重新加载策略 (保留手动暂停状态)
+    async fn reload_strategies(&mut self) {
+        let Some((pool, user_id)) = self.strategy_source.clone() else {
+            warn!("未配置数据库，无法重载策略");
+            return;
+        };
+        let paused: Vec<String> = self
+            .strategies
+            .iter()
+            .filter(|s| s.paused)
+            .map(|s| s.strategy.id().to_string())
+            .collect();
+        let previous = std::mem::take(&mut self.strategies);
+        let result = if self.store_down(Store::Db) {
+            Err(anyhow::anyhow!("数据库不可用"))
+        } else {
+            self.load_enabled_strategies(&pool, user_id.as_deref()).await
+        };
+        if let Err(e) = result {
+            error!("重载策略失败，保留原有策略: {}", e);
+            self.strategies = previous;
+            return;
+        }
+        for id in paused {
+            self.set_paused(&id, true);
+        }
+        info!("已重载 {} 个策略", self.strategies.len());
+        self.sync_strategies();
+    }
+
+    fn sync_strategies(&self) {
+        let strategies: Vec<StrategyStatus> = self
+            .strategies
+            .iter()
+            .map(|s| StrategyStatus {
+                id: s.strategy.id().to_string(),
+                strategy_type: s.strategy.strategy_type(),
+                active: s.active,
+                paused: s.paused,
+                panicked: s.panicked,
+            })
+            .collect();
+        self.state.update(|s| s.strategies = strategies);
+    }
+
+    fn sync_positions(&self) {
+        let positions = self.positions.positions();
+        self.state.update(|s| s.positions = positions);
+    }
+
+    async fn sync_exchange_health(&mut self, connections: &HashMap<ExchangeId, Arc<ExchangeConnection>>) {
+        for (id, conn) in connections {
+            let connected = conn.is_active().await && conn.is_connected() && self.health.is_connected(*id);
+            let (_, reconnects) = conn.reconnect_counts();
+            self.scorecard
+                .record_connectivity(*id, connected, self.clock.now_ms());
+            let key = serde_json::to_value(id)
+                .ok()
+                .and_then(|v| v.as_str().map(str::to_string))
+                .unwrap_or_default();
+            self.state.update(|s| {
+                let health = s.exchanges.entry(key).or_default();
+                health.connected = connected;
+                health.reconnects = reconnects;
+            });
+        }
+    }
+
+    /// 高水位持续超过阈值时告警 (执行能力成为瓶颈)，每次越线只告警一次
+    async fn check_queue_watermark(&mut self) {
+        let now = self.clock.now_ms();
+        let threshold = (self.queue.capacity() as f64 * QUEUE_HIGH_WATERMARK).ceil() as usize;
+        if self.queue.len() < threshold {
+            self.high_watermark_since = None;
+            self.high_watermark_alerted = false;
+            return;
+        }
+        let since = *self.high_watermark_since.get_or_insert(now);
+        if self.high_watermark_alerted || now - since < QUEUE_HIGH_WATERMARK_MS {
+            return;
+        }
+        self.high_watermark_alerted = true;
+        warn!(
+            "信号队列持续高水位 {}ms: 深度 {}/{}，执行能力不足",
+            now - since,
+            self.queue.len(),
+            self.queue.capacity()
+        );
+        self.publish_status(&serde_json::json!({
+            "event": "alert",
+            "level": "warning",
+            "reason": "signal_queue_high_watermark",
+            "depth": self.queue.len(),
+            "capacity": self.queue.capacity(),
+            "timestamp": now,
+        }))
+        .await;
+    }
+
+    /// 写入信号队列指标
+    async fn publish_queue_metrics(&self) {
+        let stats = self.queue.stats();
+        let fields = [
+            ("signal_queue_depth", stats.depth as i64),
+            ("signal_queue_evicted", stats.evicted as i64),
+            ("signal_queue_expired", stats.expired as i64),
+            ("signal_queue_wait_p50_ms", stats.wait_p50_ms),
+            ("signal_queue_wait_p95_ms", stats.wait_p95_ms),
+            ("signal_queue_wait_p99_ms", stats.wait_p99_ms),
+        ];
+        self.state.update(|s| {
+            for (field, value) in fields {
+                s.metrics.insert(field.to_string(), value);
+            }
+        });
+        if let Some(mut conn) = self.redis_conn().await {
+            let _ = conn.hset_multiple::<_, _, _, ()>(METRICS_KEY, &fields).await;
+        }
+    }
+
+    /// 更新并发布各交易对行情频率
+    async fn publish_tick_rates(&mut self) {
+        let stats = self.tick_rates.update(self.clock.now_ms());
+        let degraded = stats.iter().filter(|s| s.degraded).count() as i64;
+        self.state.update(|s| {
+            s.metrics.insert("tick_rate_degraded".to_string(), degraded);
+            s.tick_rates = stats.clone();
+        });
+        let Some(mut conn) = self.redis_conn().await else {
+            return;
+        };
+        let _ = conn.hset::<_, _, _, ()>(METRICS_KEY, "tick_rate_degraded", degraded).await;
+        if let Err(e) = self.tick_rates.publish(&mut conn, &stats).await {
+            warn!("发布行情频率统计失败: {}", e);
+        }
+    }
+
+    /// 发布各交易所行情延迟分位数，p95 超过预算时告警
+    async fn publish_latency(&mut self) {
+        let stats = self.latency.snapshot(chrono::Utc::now().timestamp_millis());
+        self.latency.check_budget(&stats);
+        let Some(mut conn) = self.redis_conn().await else {
+            return;
+        };
+        if let Err(e) = self.latency.publish(&mut conn, &stats).await {
+            warn!("发布行情延迟统计失败: {}", e);
+        }
+    }
+
+    /// 各策略所有监控路径的当前收益
+    pub fn opportunity_heatmap(&self) -> Heatmap {
+        let mut heatmap = Heatmap::new(self.clock.now_ms());
+        for slot in self.strategies.iter().filter(|s| !s.panicked) {
+            heatmap.insert(slot.strategy.id(), slot.strategy.strategy_type(), slot.strategy.path_edges());
+        }
+        heatmap
+    }
+
+    /// 计算并发布机会热力图
+    async fn publish_heatmap(&mut self) {
+        let heatmap = self.opportunity_heatmap();
+        let paths = heatmap.len() as i64;
+        self.state.update(|s| {
+            s.metrics.insert("heatmap_paths".to_string(), paths);
+        });
+        let Some(mut conn) = self.redis_conn().await else {
+            return;
+        };
+        if let Err(e) = heatmap.publish(&mut conn).await {
+            warn!("发布机会热力图失败: {}", e);
+        }
+    }
+
+    /// 从 Redis 刷新运行时禁用的套利路径
+    async fn refresh_disabled_paths(&mut self) {
+        let Some(mut conn) = self.redis_conn().await else {
+            return;
+        };
+        if let Err(e) = DISABLED_PATHS.refresh(&mut conn).await {
+            warn!("刷新禁用路径失败: {}", e);
+        }
+        let disabled = DISABLED_PATHS.len() as i64;
+        self.state.update(|s| {
+            s.metrics.insert("disabled_paths".to_string(), disabled);
+        });
+    }
+
+    /// 评分卡对已连接 / 有记录的交易所的排序
+    fn venue_ranking(&self) -> Vec<(ExchangeId, VenueVerdict)> {
+        let mut candidates: Vec<ExchangeId> = self.feeds.keys().copied().collect();
+        for venue in self.scorecard.venues() {
+            if !candidates.contains(&venue) {
+                candidates.push(venue);
+            }
+        }
+        self.scorecard.rank_venues(&candidates, self.clock.now_ms())
+    }
+
+    /// 把评分卡排序同步给策略 (按腿选择交易所时使用)
+    fn update_venue_ranking(&mut self) {
+        let ranking = self.venue_ranking();
+        for slot in self.strategies.iter_mut() {
+            slot.strategy.set_venue_ranking(&ranking);
+        }
+    }
+
+    /// 发布交易所评分卡
+    async fn publish_scorecard(&mut self) {
+        let now = self.clock.now_ms();
+        self.scorecard.prune(now);
+        self.update_venue_ranking();
+        let Some(mut conn) = self.redis_conn().await else {
+            return;
+        };
+        if let Err(e) = self.scorecard.publish(&mut conn, now).await {
+            warn!("发布交易所评分卡失败: {}", e);
+        }
+    }
+
+    /// 推进故障注入计划；注入的断线交给行情健康度处理，并把当前打开的熔断记入故障报告
+    fn tick_faults(&mut self) {
+        let Some(faults) = &self.faults else {
+            return;
+        };
+        let now = self.clock.now_ms();
+        for fault in faults.tick(now) {
+            if let FaultKind::Reconnect { exchange } = fault.kind {
+                warn!("{:?} 连接断开 (注入)，等待重连", exchange);
+                self.health.on_disconnect(exchange);
+            }
+        }
+        let mut open = vec![];
+        if self.halted {
+            open.push(("kill_switch".to_string(), "engine".to_string()));
+        }
+        if self.risk.kill_switch_engaged() {
+            open.push(("risk_kill_switch".to_string(), "engine".to_string()));
+        }
+        for exchange in self.scorecard.blocked_venues(now) {
+            open.push(("venue_blocked".to_string(), exchange.key().to_string()));
+        }
+        faults.observe_breakers(&open, now);
+    }
+
+    fn store_down(&self, store: Store) -> bool {
+        self.faults
+            .as_ref()
+            .is_some_and(|f| f.store_down(store, self.clock.now_ms()))
+    }
+
+    /// 可用的 Redis 客户端 (未配置或处于不可用窗口时为 None，调用方按降级处理)
+    fn redis_client(&self) -> Option<&redis::Client> {
+        self.shared_redis().map(SharedRedis::client)
+    }
+
+    /// 高频写入 (指标 / 状态事件) 复用的 Redis 连接
+    async fn redis_conn(&self) -> Option<ConnectionManager> {
+        self.shared_redis()?.connection().await.ok()
+    }
+
+    fn shared_redis(&self) -> Option<&SharedRedis> {
+        if self.store_down(Store::Redis) {
+            return None;
+        }
+        self.redis.as_ref()
+    }
+
+    async fn publish_status(&self, payload: &serde_json::Value) {
+        if let Some(mut conn) = self.redis_conn().await {
+            let _ = conn.publish::<_, _, ()>(STATUS_CHANNEL, payload.to_string()).await;
+        }
+    }
+
+    /// 记录并发布接近成交的机会 (扣费前有利可图，附盈亏平衡费率)
+    async fn publish_near_misses(&self, near_misses: &[NearMiss]) {
+        if near_misses.is_empty() {
+            return;
+        }
+        for miss in near_misses {
+            info!(
+                "接近成交 [{}] {:?} {}: 毛收益 {:.4}% 净收益 {:.4}% 盈亏平衡费率 {:.4}%",
+                miss.strategy_id,
+                miss.exchange,
+                miss.path,
+                miss.gross_rate * 100.0,
+                miss.net_rate * 100.0,
+                miss.breakeven_fee_rate * 100.0
+            );
+        }
+        let Some(mut conn) = self.redis_conn().await else {
+            return;
+        };
+        let mut pipe = redis::pipe();
+        for miss in near_misses {
+            if let Ok(payload) = serde_json::to_string(miss) {
+                pipe.lpush(NEAR_MISS_KEY, payload).ignore();
+            }
+        }
+        pipe.ltrim(NEAR_MISS_KEY, 0, NEAR_MISS_KEEP - 1).ignore();
+        let _ = pipe.query_async::<()>(&mut conn).await;
+    }
+
+    async fn incr_metric(&self, field: &str, delta: i64) {
+        self.incr_metrics(&[(field, delta)]).await;
+    }
+
+    /// 批量累加指标，一次管道写入 Redis
+    async fn incr_metrics(&self, deltas: &[(&str, i64)]) {
+        if deltas.is_empty() {
+            return;
+        }
+        self.state.update(|s| {
+            for (field, delta) in deltas {
+                *s.metrics.entry(field.to_string()).or_default() += delta;
+            }
+        });
+        for (field, delta) in deltas {
+            METRICS.observe(field, *delta);
+        }
+        if let Some(mut conn) = self.redis_conn().await {
+            let _ = metrics_pipeline(deltas).query_async::<()>(&mut conn).await;
+        }
+    }
+}
+
+/// 同时执行的信号数上限 (ENGINE_MAX_IN_FLIGHT，默认 1 即串行执行)
+fn max_in_flight_from_env() -> usize {
+    std::env::var("ENGINE_MAX_IN_FLIGHT")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .filter(|v| *v > 0)
+        .unwrap_or(1)
+}
+
+/// 挂单改价阈值 (ENGINE_REQUOTE_BPS，默认 0 即不改价)
+fn requote_bps_from_env() -> f64 {
+    std::env::var("ENGINE_REQUOTE_BPS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .filter(|v: &f64| v.is_finite() && *v > 0.0)
+        .unwrap_or(0.0)
+}
+
+/// 指标累加管道 (每个字段一条 HINCRBY)
+fn metrics_pipeline(deltas: &[(&str, i64)]) -> redis::Pipeline {
+    let mut pipe = redis::pipe();
+    for (field, delta) in deltas {
+        pipe.hincr(METRICS_KEY, *field, *delta).ignore();
+    }
+    pipe
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::{self, ApiConfig};
+    use crate::backtest::ReplayClock;
+    use crate::fake_redis::FakeRedis;
+    use crate::exchange::testing::ticker;
+    use crate::risk::RiskConfig;
+    use crate::strategy::StrategyType;
+
+    fn grid(id: &str) -> StrategyConfig {
+        serde_json::from_value(serde_json::json!({
+            "id": id,
+            "strategy_type": "grid",
+            "name": id,
+            "is_enabled": true,
+            "priority": 1,
+            "config": {"symbol": "BTC/USDT", "lower_price": 90.0, "upper_price": 110.0, "grid_count": 20},
+        }))
+        .unwrap()
+    }
+
+    /// 模拟模式引擎 (Binance 与 OKX 连接不实际联网，缓存 BTC/USDT 100、ETH/USDT 10 的盘口供模拟成交)
+    async fn sim_engine() -> Engine {
+        let mut connections = HashMap::new();
+        for id in [ExchangeId::Binance, ExchangeId::Okx] {
+            let connection = ExchangeConnection::new(id).await.unwrap();
+            for (symbol, price) in [("BTC/USDT", 100.0), ("ETH/USDT", 10.0)] {
+                let mut quote = ticker(symbol).price(price).at(1_000).build();
+                quote.exchange = id;
+                connection.publish_ticker(quote);
+            }
+            connections.insert(id, Arc::new(connection));
+        }
+        let mut executor = OrderExecutor::new(connections, None);
+        executor.set_simulation_mode(true);
+        let mut engine = Engine::new(executor, None, Arc::new(ReplayClock::new(1_000)));
+        // 独立的风控实例，避免紧急停止开关影响其他测试
+        engine.risk = RiskManager::new(RiskConfig::default());
+        engine
+    }
+
+    /// 桩 OMS: 一个持仓 (外加一个已平的空仓) 与三笔订单，只有未完成的应被恢复
+    async fn mock_oms_state() -> String {
+        use axum::routing::get;
+        use axum::Json;
+
+        let app = axum::Router::new()
+            .route(
+                "/api/v1/bot/positions",
+                get(|| async {
+                    Json(serde_json::json!({"success": true, "data": [
+                        {"exchange_id": "binance", "instrument": "BTC/USDT", "quantity": "0.5", "avg_price": "100"},
+                        {"exchange_id": "okx", "instrument": "BTC/USDT", "quantity": "0", "avg_price": "0"}
+                    ]}))
+                }),
+            )
+            .route(
+                "/api/v1/oms/orders",
+                get(|| async {
+                    Json(serde_json::json!({"success": true, "orders": [
+                        {"id": "o-1", "exchange_id": "okx", "symbol": "ETH/USDT", "side": "buy",
+                         "status": "partially_filled", "quantity": "2", "filled_quantity": "0.5", "price": "9.9"},
+                        {"id": "o-2", "exchange_id": "okx", "symbol": "ETH/USDT", "side": "sell",
+                         "status": "filled", "quantity": "1", "filled_quantity": "1", "price": "10.1"},
+                        {"id": "o-3", "exchange_id": "binance", "symbol": "ETH/USDT", "side": "sell",
+                         "status": "cancelled", "quantity": "1", "filled_quantity": "0", "price": "10.2"}
+                    ]}))
+                }),
+            );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        format!("http://{}", addr)
+    }
+
+    #[tokio::test]
+    async fn startup_recovers_in_flight_state_from_oms() {
+        let mut engine = sim_engine().await;
+        Arc::get_mut(&mut engine.executor)
+            .unwrap()
+            .set_oms_base(mock_oms_state().await);
+
+        engine.recover_state().await.unwrap();
+
+        assert_eq!(engine.positions.position_count(), 1);
+        let position = engine.positions.get(ExchangeId::Binance, "BTC/USDT").unwrap();
+        assert_eq!(position.quantity, 0.5);
+        assert_eq!(position.avg_price, 100.0);
+
+        // 已成交 / 已撤销的订单不恢复；部分成交的只恢复剩余数量
+        assert_eq!(engine.positions.open_order_count(), 1);
+        let orders = engine.positions.open_orders_for(ExchangeId::Okx, "ETH/USDT");
+        assert_eq!(orders.len(), 1);
+        assert_eq!(orders[0].order_id, "o-1");
+        assert_eq!(orders[0].side, OrderSide::Buy);
+        assert_eq!(orders[0].amount, 1.5);
+        assert_eq!(orders[0].price, 9.9);
+
+        // 恢复的敞口参与重复开仓判断
+        assert!(engine.positions.has_exposure(ExchangeId::Binance, "BTC/USDT"));
+        assert!(engine.positions.has_exposure(ExchangeId::Okx, "ETH/USDT"));
+        assert!(!engine.positions.has_exposure(ExchangeId::Binance, "ETH/USDT"));
+    }
+
+    #[tokio::test]
+    async fn grid_rungs_open_separately_but_a_held_rung_is_not_reopened() {
+        let mut engine = sim_engine().await;
+        let (result_tx, mut result_rx) = mpsc::unbounded_channel();
+        let rung = |level: i64, action: SignalAction| {
+            Signal::new("grid-btc", StrategyType::Grid, ExchangeId::Binance, 0.01, 1.0, 0.5, "BTC/USDT", 1_000)
+                .with_action(action)
+                .with_level(level)
+        };
+
+        // 第 5 格买入成交后，价格再下一格，第 4 格照常买入
+        for level in [5, 4] {
+            engine.enqueue(rung(level, SignalAction::Open)).await;
+            assert!(engine.start_next_execution(&result_tx).await);
+            let outcome = result_rx.recv().await.unwrap();
+            engine.in_flight -= 1;
+            engine.finish_execution(outcome).await;
+        }
+        let held = engine.positions.get(ExchangeId::Binance, "BTC/USDT").unwrap().quantity;
+        assert!(engine.positions.has_lot(ExchangeId::Binance, "BTC/USDT", "grid-btc", Some(4)));
+
+        // 仍持有的第 5 格不重复买入
+        engine.enqueue(rung(5, SignalAction::Open)).await;
+        assert!(!engine.start_next_execution(&result_tx).await);
+        assert_eq!(engine.in_flight, 0);
+
+        // 第 5 格卖出平掉一半持仓，之后可再次买入
+        engine.enqueue(rung(5, SignalAction::Close)).await;
+        assert!(engine.start_next_execution(&result_tx).await);
+        let outcome = result_rx.recv().await.unwrap();
+        engine.in_flight -= 1;
+        engine.finish_execution(outcome).await;
+        let remaining = engine.positions.get(ExchangeId::Binance, "BTC/USDT").unwrap().quantity;
+        assert!((remaining - held / 2.0).abs() < 1e-9, "{} / {}", remaining, held);
+        assert!(!engine.positions.has_lot(ExchangeId::Binance, "BTC/USDT", "grid-btc", Some(5)));
+        engine.enqueue(rung(5, SignalAction::Open)).await;
+        assert!(engine.start_next_execution(&result_tx).await);
+
+        // 对账恢复的持仓无法判断归属，任何档位都不开仓
+        engine.positions.seed_position(crate::positions::Position {
+            exchange: ExchangeId::Okx,
+            symbol: "BTC/USDT".to_string(),
+            quantity: 0.5,
+            avg_price: 100.0,
+        });
+        let mut signal = rung(3, SignalAction::Open);
+        signal.exchange = ExchangeId::Okx;
+        engine.enqueue(signal).await;
+        assert!(!engine.start_next_execution(&result_tx).await);
+    }
+
+    #[tokio::test]
+    async fn sim_run_report_file_counts_failed_executions() {
+        let mut engine = sim_engine().await;
+        // DOGE/USDT 没有盘口缓存，模拟成交被拒
+        let doge: StrategyConfig = serde_json::from_value(serde_json::json!({
+            "id": "grid-doge", "strategy_type": "grid", "name": "grid-doge", "is_enabled": true, "priority": 1,
+            "config": {"symbol": "DOGE/USDT", "lower_price": 90.0, "upper_price": 110.0, "grid_count": 20},
+        }))
+        .unwrap();
+        engine.load_strategies(vec![grid("grid-btc"), doge]);
+        let (result_tx, mut result_rx) = mpsc::unbounded_channel();
+        for (i, price) in [100.5, 99.5, 100.5].into_iter().enumerate() {
+            for symbol in ["BTC/USDT", "DOGE/USDT"] {
+                engine.replay_ticker(&ticker(symbol).price(price).at(1_000 + i as i64).build()).await;
+            }
+            while engine.start_next_execution(&result_tx).await {
+                let outcome = result_rx.recv().await.unwrap();
+                engine.in_flight -= 1;
+                engine.finish_execution(outcome).await;
+            }
+        }
+
+        let path = std::env::temp_dir().join(format!("inarbit-sim-report-{}.csv", uuid::Uuid::new_v4()));
+        engine.sim_report().unwrap().write_to(&path).unwrap();
+        let csv = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let mut lines = csv.lines();
+        let header: Vec<&str> = lines.next().unwrap().split(',').collect();
+        let rows: HashMap<String, HashMap<&str, String>> = lines
+            .map(|line| {
+                let row: HashMap<&str, String> =
+                    header.iter().copied().zip(line.split(',').map(str::to_string)).collect();
+                (row["strategy_id"].clone(), row)
+            })
+            .collect();
+        assert_eq!(rows.len(), 2, "{}", csv);
+        let field = |id: &str, name: &str| rows[id][name].clone();
+
+        // 网格买入后卖出，两笔都成交
+        assert_eq!((field("grid-btc", "signals"), field("grid-btc", "executions")), ("2".into(), "2".into()));
+        assert_eq!(field("grid-btc", "failures"), "0");
+        // 买入、卖出都因缺少盘口被拒: 计入执行次数与失败次数，胜率为 0
+        assert_eq!((field("grid-doge", "signals"), field("grid-doge", "executions")), ("2".into(), "2".into()));
+        assert_eq!(field("grid-doge", "failures"), "2");
+        assert_eq!((field("grid-doge", "wins"), field("grid-doge", "win_rate")), ("0".into(), "0.0000".into()));
+    }
+
+    #[tokio::test]
+    async fn execution_panic_flattens_positions_and_raises_alert() {
+        let redis = FakeRedis::start().await;
+        let mut engine = sim_engine().await;
+        engine.redis = Some(SharedRedis::new(redis.client()));
+        engine.positions.apply_fill(ExchangeId::Binance, "BTC/USDT", OrderSide::Buy, 0.5, 100.0);
+        engine.in_flight = 1;
+
+        let supervisor = engine.supervisor.clone();
+        supervisor
+            .spawn("execution:c1", TaskKind::Execution, async { panic!("leg send failed") })
+            .await
+            .unwrap();
+        let event = engine.panic_rx.as_mut().unwrap().recv().await.unwrap();
+        engine.handle_panic(event).await;
+
+        assert_eq!(engine.in_flight, 0);
+        assert_eq!(engine.positions.position_count(), 0);
+        let alert = redis
+            .commands()
+            .into_iter()
+            .find(|c| c[0] == "PUBLISH" && c[2].contains("task_panic"))
+            .expect("panic alert published");
+        let payload: serde_json::Value = serde_json::from_str(&alert[2]).unwrap();
+        assert_eq!(payload["level"], "critical");
+        assert_eq!(payload["task"], "execution:c1");
+        assert_eq!(payload["kind"], "execution");
+        assert_eq!(payload["message"], "leg send failed");
+    }
+
+    #[tokio::test]
+    async fn watcher_stop_exits_the_position_at_market() {
+        let mut engine = sim_engine().await;
+        engine.positions.apply_fill(ExchangeId::Binance, "BTC/USDT", OrderSide::Buy, 0.5, 102.0);
+        let signal = Signal::new("grid-1", StrategyType::Grid, ExchangeId::Binance, 0.01, 1.0, 0.9, "BTC/USDT", 0);
+        let entry = crate::executor::OrderResponse {
+            order_id: "o1".to_string(),
+            exchange: ExchangeId::Binance,
+            symbol: "BTC/USDT".to_string(),
+            side: OrderSide::Buy,
+            status: crate::executor::OrderStatus::Filled,
+            filled_amount: 0.5,
+            avg_price: 102.0,
+            fee: 0.0,
+            latency_ms: 10,
+            other_fees: vec![],
+        };
+        for stop in StopManager::build_stops(&signal, &[entry], &StopConfig::default()) {
+            engine.stops.register(stop);
+        }
+
+        let mut quote = ticker("BTC/USDT").price(101.0).at(1_000).build();
+        engine.replay_ticker(&quote).await;
+        assert_eq!(engine.positions.position_count(), 1);
+        // 买一跌破 102 * 0.98 = 99.96: 市价卖出平仓
+        quote = ticker("BTC/USDT").price(99.9).at(2_000).build();
+        engine.replay_ticker(&quote).await;
+        let update = engine.order_rx.as_mut().unwrap().recv().await.unwrap();
+        engine.apply_order_update(update).await;
+        assert_eq!(engine.positions.position_count(), 0);
+        assert!(engine.stops.cancel_all().is_empty());
+    }
+
+    #[tokio::test]
+    async fn per_event_metrics_are_written_as_one_pipeline() {
+        let deltas = [("signals_stale", 2), ("schedule_inactive", 3)];
+        // 两条 HINCRBY 打包进同一个写缓冲，一次往返
+        let packed = metrics_pipeline(&deltas).get_packed_pipeline();
+        let separate: Vec<u8> = deltas
+            .iter()
+            .flat_map(|(field, delta)| redis::cmd("HINCRBY").arg(METRICS_KEY).arg(*field).arg(*delta).get_packed_command())
+            .collect();
+        assert_eq!(packed, separate);
+
+        let redis = FakeRedis::start().await;
+        let mut engine = sim_engine().await;
+        engine.redis = Some(SharedRedis::new(redis.client()));
+        engine.incr_metrics(&deltas).await;
+        engine.incr_metrics(&[("signals_stale", 1)]).await;
+        engine.incr_metrics(&[]).await;
+
+        // 键与值与逐条 HINCRBY 时一致，且不包成 MULTI / EXEC 事务 (CLIENT 为连接握手)
+        assert_eq!(redis.hget(METRICS_KEY, "signals_stale").as_deref(), Some("3"));
+        assert_eq!(redis.hget(METRICS_KEY, "schedule_inactive").as_deref(), Some("3"));
+        let commands: Vec<String> = redis.commands().into_iter().map(|c| c[0].clone()).filter(|c| c != "CLIENT").collect();
+        assert_eq!(commands, vec!["HINCRBY"; 3]);
+        assert_eq!(engine.state.read(|s| s.metrics.get("signals_stale").copied()), Some(Some(3)));
+    }
+
+    #[tokio::test]
+    async fn strategies_jointly_breaching_net_inventory_block_the_next_signal() {
+        let mut engine = sim_engine().await;
+        engine.inventory_limits = InventoryLimits::parse("BTC:1000");
+        // 网格与配对各自持有 BTC 多头，合计 900
+        engine.positions.apply_fill(ExchangeId::Binance, "BTC/USDT", OrderSide::Buy, 4.0, 100.0);
+        engine.positions.apply_fill(ExchangeId::Okx, "BTC/USDT", OrderSide::Buy, 5.0, 100.0);
+
+        // 名义金额 = 预期收益 / 收益率 = 200
+        let open = |strategy_type, path: &str| Signal::new("s", strategy_type, ExchangeId::Binance, 0.01, 2.0, 0.9, path, 0);
+        let err = engine.check_inventory(&open(StrategyType::Grid, "BTC/USDT")).unwrap_err();
+        assert!(err.contains("超过上限"), "{}", err);
+        assert!(engine.check_inventory(&open(StrategyType::Pair, "BTC/USDT")).is_err());
+
+        // 平仓、其他币种与对冲的多腿信号不受影响
+        let close = open(StrategyType::Grid, "BTC/USDT").with_action(SignalAction::Close);
+        assert!(engine.check_inventory(&close).is_ok());
+        assert!(engine.check_inventory(&open(StrategyType::Grid, "ETH/USDT")).is_ok());
+        assert!(engine.check_inventory(&open(StrategyType::Triangular, "USDT->BTC->ETH->USDT")).is_ok());
+
+        // 预期收益为负的开仓同样按实际下单金额 200 计入
+        let losing = Signal::new("s", StrategyType::Grid, ExchangeId::Binance, -0.01, -2.0, 0.9, "BTC/USDT", 0);
+        assert!(engine.check_inventory(&losing).is_err());
+        // 推算不出金额时按模拟默认金额 100 计入: 900 + 100 未超过上限
+        let no_size = Signal::new("s", StrategyType::Grid, ExchangeId::Binance, 0.0, 0.0, 0.9, "BTC/USDT", 0);
+        assert!(engine.check_inventory(&no_size).is_ok());
+    }
+
+    #[tokio::test]
+    async fn exchange_capital_uses_the_order_notional_even_for_losing_opens() {
+        let mut engine = sim_engine().await;
+        engine.exchange_caps = ExchangeCapitalCaps::parse("binance:1000");
+        engine.positions.apply_fill(ExchangeId::Binance, "BTC/USDT", OrderSide::Buy, 9.0, 100.0);
+
+        let open = |profit_rate: f64, expected: f64| {
+            Signal::new("s", StrategyType::Grid, ExchangeId::Binance, profit_rate, expected, 0.9, "BTC/USDT", 0)
+        };
+        // 名义金额 200 (收益为正或为负都一样) 超过剩余 100 额度
+        assert!(engine.check_exchange_capital(&open(0.01, 2.0)).is_err());
+        assert!(engine.check_exchange_capital(&open(-0.01, -2.0)).is_err());
+        // 模拟模式下推算不出金额时按默认 100 下单，恰好用满额度
+        assert!(engine.check_exchange_capital(&open(0.0, 0.0)).is_ok());
+        assert!(engine.check_exchange_capital(&open(-0.01, -2.0).with_action(SignalAction::Close)).is_ok());
+    }
+
+    #[tokio::test]
+    async fn ticker_without_bid_ask_produces_no_signal() {
+        let mut engine = sim_engine().await;
+        engine.load_strategies(vec![grid("grid-btc")]);
+        let signals = |engine: &Engine| engine.sim_report().unwrap().strategies.iter().map(|s| s.signals).sum::<u64>();
+
+        // 报价字段为 0 或直接填成最新成交价，价格穿越格线也不产生信号
+        for (i, price) in [100.5, 101.5, 100.5, 101.5].into_iter().enumerate() {
+            let mut quote = ticker("BTC/USDT").price(price).at(1_000 + i as i64).build();
+            if i % 2 == 0 {
+                (quote.bid, quote.ask) = (0.0, 0.0);
+            } else {
+                (quote.bid, quote.ask) = (price, price);
+            }
+            engine.replay_ticker(&quote).await;
+        }
+        assert_eq!(signals(&engine), 0);
+        assert_eq!(engine.queued_signals(), 0);
+        // 已为该交易对请求过补订阅报价频道
+        assert!(!engine.health.take_quote_request(ExchangeId::Binance, "BTC/USDT"));
+
+        // 恢复可成交报价后照常产生信号
+        for (i, price) in [100.5, 101.5, 100.5].into_iter().enumerate() {
+            engine.replay_ticker(&ticker("BTC/USDT").price(price).at(2_000 + i as i64).build()).await;
+        }
+        assert!(signals(&engine) > 0);
+    }
+
+    /// 在随机端口启动 HTTP 服务，返回服务地址
+    async fn serve_api(engine: &Engine) -> std::net::SocketAddr {
+        let (state, control) = engine.api_handles();
+        let config = ApiConfig {
+            addr: "127.0.0.1:0".parse().unwrap(),
+            token: Some("secret".to_string()),
+            debug_ui: false,
+            watchlist: vec![],
+            sse_decisions: true,
+            debug_snapshot: true,
+            strategies: true,
+            flatten: true,
+        };
+        let listener = tokio::net::TcpListener::bind(config.addr).await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let _ = axum::serve(listener, api::router(config, state, control)).await;
+        });
+        addr
+    }
+
+    /// 发送请求并在当前任务中充当引擎主循环处理控制指令，返回响应 JSON
+    async fn call(engine: &mut Engine, request: reqwest::RequestBuilder) -> (u16, serde_json::Value) {
+        let mut control = engine.control_rx.take().unwrap();
+        let mut response = tokio::spawn(async move {
+            let response = request.send().await.unwrap();
+            (response.status().as_u16(), response.json::<serde_json::Value>().await.unwrap())
+        });
+        let response = loop {
+            tokio::select! {
+                result = &mut response => break result.unwrap(),
+                Some(command) = control.recv() => engine.handle_control(command).await,
+            }
+        };
+        engine.control_rx = Some(control);
+        response
+    }
+
+    #[tokio::test]
+    async fn debug_snapshot_contains_strategy_and_cached_price() {
+        let mut engine = sim_engine().await;
+        engine.load_strategies(vec![grid("grid-btc")]);
+        engine.replay_ticker(&ticker("BTC/USDT").price(100.5).at(1_000).build()).await;
+        let addr = serve_api(&engine).await;
+        let url = format!("http://{}/debug/snapshot", addr);
+
+        let (status, _) = call(&mut engine, reqwest::Client::new().get(&url)).await;
+        assert_eq!(status, 401);
+
+        let (status, body) = call(&mut engine, reqwest::Client::new().get(&url).bearer_auth("secret")).await;
+        assert_eq!(status, 200);
+        let strategies = body["engine"]["strategies"].as_array().unwrap();
+        assert_eq!(strategies.len(), 1);
+        assert_eq!(strategies[0]["id"], "grid-btc");
+        let tickers = body["state"]["tickers"].as_array().unwrap();
+        assert!(tickers.iter().any(|t| t["symbol"] == "BTC/USDT" && t["last"] == 100.5), "{}", body);
+    }
+
+    #[tokio::test]
+    async fn strategies_endpoint_reports_the_default_triangular_thresholds() {
+        let mut engine = sim_engine().await;
+        // 与 server/db/init.sql 初始化的三角套利默认配置一致
+        let default: StrategyConfig = serde_json::from_value(serde_json::json!({
+            "id": "triangular-default",
+            "strategy_type": "triangular",
+            "name": "三角套利",
+            "is_enabled": true,
+            "priority": 1,
+            "config": {
+                "min_profit_rate": 0.001,
+                "max_slippage": 0.0005,
+                "base_currencies": ["USDT", "BTC", "ETH"],
+                "scan_interval_ms": 100
+            },
+        }))
+        .unwrap();
+        engine.load_strategies(vec![default]);
+        let addr = serve_api(&engine).await;
+        let url = format!("http://{}/strategies", addr);
+
+        let (status, _) = call(&mut engine, reqwest::Client::new().get(&url)).await;
+        assert_eq!(status, 401);
+
+        let (status, body) = call(&mut engine, reqwest::Client::new().get(&url).bearer_auth("secret")).await;
+        assert_eq!(status, 200);
+        assert_eq!(body["count"], 1);
+        let strategy = &body["strategies"][0];
+        assert_eq!(strategy["id"], "triangular-default");
+        assert_eq!(strategy["strategyType"], "triangular");
+        let thresholds = &strategy["thresholds"];
+        assert_eq!(thresholds["minProfitRate"], 0.001, "{}", body);
+        assert_eq!(thresholds["feeRate"], 0.001, "{}", body);
+        assert_eq!(thresholds["tradeAmount"], 100.0, "{}", body);
+        assert_eq!(thresholds["corroborationWindowMs"], 0, "{}", body);
+        assert_eq!(thresholds["maxPriceAgeMs"], 0, "{}", body);
+        assert!(thresholds.get("quotes").is_none(), "{}", body);
+    }
+
+    #[tokio::test]
+    async fn flatten_closes_every_position_and_engages_kill_switch() {
+        let mut engine = sim_engine().await;
+        engine.positions.apply_fill(ExchangeId::Binance, "BTC/USDT", OrderSide::Buy, 0.5, 100.0);
+        engine.positions.apply_fill(ExchangeId::Okx, "ETH/USDT", OrderSide::Sell, 2.0, 10.0);
+        let addr = serve_api(&engine).await;
+        let flatten = || reqwest::Client::new().post(format!("http://{}/admin/flatten", addr));
+
+        let (status, _) = call(&mut engine, flatten()).await;
+        assert_eq!(status, 401);
+        assert_eq!(engine.positions.position_count(), 2);
+
+        let (status, body) = call(&mut engine, flatten().bearer_auth("secret")).await;
+        assert_eq!(status, 200);
+        assert_eq!(body["success"], true, "{}", body);
+        assert_eq!(body["halted"], true);
+        assert_eq!(body["count"], 2);
+        let orders = body["positions"].as_array().unwrap();
+        let side = |symbol: &str| {
+            let order = orders.iter().find(|p| p["symbol"] == symbol).unwrap();
+            assert_eq!(order["success"], true);
+            order["side"].clone()
+        };
+        assert_eq!(side("BTC/USDT"), serde_json::json!("Sell"));
+        assert_eq!(side("ETH/USDT"), serde_json::json!("Buy"));
+        assert!(engine.halted);
+        assert_eq!(engine.state.read(|s| s.halted), Some(true));
+        assert_eq!(engine.positions.position_count(), 0);
+
+        // 重复调用: 不再下单，熔断保持开启
+        let (status, body) = call(&mut engine, flatten().bearer_auth("secret")).await;
+        assert_eq!(status, 200);
+        assert_eq!(body["count"], 0);
+        assert_eq!(body["halted"], true);
+    }
+
+    #[tokio::test]
+    async fn flatten_persists_kill_switch_and_is_idempotent() {
+        let redis = FakeRedis::start().await;
+        let mut engine = sim_engine().await;
+        engine.redis = Some(SharedRedis::new(redis.client()));
+        engine.positions.apply_fill(ExchangeId::Binance, "BTC/USDT", OrderSide::Buy, 0.5, 100.0);
+
+        let first = engine.emergency_flatten().await;
+        assert_eq!(first["count"], 1);
+        assert_eq!(first["failed"], 0);
+        assert!(engine.halted);
+        assert!(engine.risk.kill_switch_engaged());
+        assert_eq!(redis.get("engine:kill_switch").as_deref(), Some("1"));
+
+        // 重复调用: 没有持仓可平，不下单，也不重复写开关
+        let writes = || redis.commands().iter().filter(|c| c[0] == "SET").count();
+        assert_eq!(writes(), 1);
+        let second = engine.emergency_flatten().await;
+        assert_eq!(second["count"], 0);
+        assert_eq!(second["positions"], serde_json::json!([]));
+        assert_eq!(writes(), 1);
+        assert!(engine.risk.kill_switch_engaged());
+
+        // 关闭熔断时清除持久化的开关
+        engine.set_kill_switch(false).await;
+        assert!(!engine.risk.kill_switch_engaged());
+        assert_eq!(redis.get("engine:kill_switch"), None);
+    }
+
+    #[tokio::test]
+    async fn drifted_resting_orders_are_requoted_via_amend() {
+        let mut engine = sim_engine().await;
+        engine.requote_bps = 10.0;
+        let resting = |order_id: &str, exchange, price| OpenOrder {
+            order_id: order_id.to_string(),
+            exchange,
+            symbol: "BTC/USDT".to_string(),
+            side: OrderSide::Buy,
+            amount: 0.5,
+            price,
+        };
+        engine.positions.seed_open_order(resting("okx-1", ExchangeId::Okx, 95.0));
+        engine.positions.seed_open_order(resting("binance-1", ExchangeId::Binance, 95.0));
+        engine.positions.seed_open_order(resting("okx-near", ExchangeId::Okx, 99.95));
+
+        for exchange in [ExchangeId::Okx, ExchangeId::Binance] {
+            let mut quote = ticker("BTC/USDT").price(100.0).at(1_000).build();
+            quote.exchange = exchange;
+            engine.replay_ticker(&quote).await;
+        }
+        // 改单在独立任务中进行，未完成前后续行情不重复改价
+        assert_eq!(engine.in_flight, 2);
+        engine.replay_ticker(&ticker("BTC/USDT").price(100.0).at(2_000).build()).await;
+        assert_eq!(engine.in_flight, 2);
+        for _ in 0..2 {
+            let update = engine.order_rx.as_mut().unwrap().recv().await.unwrap();
+            engine.in_flight -= 1;
+            engine.apply_order_update(update).await;
+        }
+
+        // OKX 原生改单: 订单号不变，价格改到买一；偏离不足阈值的挂单不动
+        let okx = engine.positions.open_orders_for(ExchangeId::Okx, "BTC/USDT");
+        let amended = okx.iter().find(|o| o.order_id == "okx-1").unwrap();
+        assert!((amended.price - 99.99).abs() < 1e-9);
+        assert_eq!(amended.amount, 0.5);
+        let near = okx.iter().find(|o| o.order_id == "okx-near").unwrap();
+        assert_eq!(near.price, 99.95);
+        // Binance 撤单重挂: 模拟按新价格立即成交，计入持仓
+        assert!(engine.positions.open_orders_for(ExchangeId::Binance, "BTC/USDT").is_empty());
+        let position = engine.positions.get(ExchangeId::Binance, "BTC/USDT").unwrap();
+        assert_eq!(position.quantity, 0.5);
+        assert!((position.avg_price - 99.99).abs() < 1e-9);
+    }
+
+    /// Okx 为只读行情交易所的模拟引擎，状态中缓存各交易所 BTC/USDT 的 (买一, 卖一)
+    async fn observe_only_engine(quotes: &[(ExchangeId, f64, f64)]) -> Engine {
+        let mut engine = sim_engine().await;
+        Arc::get_mut(&mut engine.executor)
+            .unwrap()
+            .set_observe_only(HashSet::from([ExchangeId::Okx]));
+        for (exchange, bid, ask) in quotes {
+            let quote = ticker("BTC/USDT").exchange(*exchange).quote(*bid, *ask).build();
+            engine.state.update(|s| s.record_ticker(&quote));
+        }
+        engine
+    }
+
+    #[tokio::test]
+    async fn observe_only_signal_is_repriced_on_the_best_executable_venue() {
+        let engine = observe_only_engine(&[
+            (ExchangeId::Okx, 99.9, 100.0),
+            (ExchangeId::Binance, 100.4, 100.5),
+            (ExchangeId::Bybit, 100.1, 100.2),
+        ])
+        .await;
+        let signal = Signal::new("grid", StrategyType::Grid, ExchangeId::Okx, 0.01, 1.0, 0.5, "BTC/USDT", 1_000);
+
+        let routed = engine.route_observe_only(signal).await.unwrap();
+        assert_eq!(routed.exchange, ExchangeId::Bybit);
+        assert!((routed.profit_rate - (1.01 * 100.0 / 100.2 - 1.0)).abs() < 1e-12, "{}", routed.profit_rate);
+    }
+
+    #[tokio::test]
+    async fn observe_only_signal_is_suppressed_when_no_venue_keeps_the_edge() {
+        let engine = observe_only_engine(&[(ExchangeId::Okx, 99.9, 100.0), (ExchangeId::Binance, 101.9, 102.0)]).await;
+        let signal = Signal::new("grid", StrategyType::Grid, ExchangeId::Okx, 0.01, 1.0, 0.5, "BTC/USDT", 1_000);
+        let correlation_id = signal.correlation_id.clone();
+        engine.state.update(|s| s.record_signal(&signal, "queued"));
+
+        assert!(engine.route_observe_only(signal).await.is_none());
+        let verdict = engine
+            .state
+            .read(|s| s.signals.iter().find(|r| r.correlation_id == correlation_id).map(|r| r.verdict.clone()))
+            .flatten();
+        assert_eq!(verdict.as_deref(), Some("observe_only"));
+    }
+
+    #[tokio::test]
+    async fn in_flight_cap_keeps_the_next_signal_queued_until_an_execution_completes() {
+        let mut engine = sim_engine().await;
+        engine.max_in_flight = 2;
+        let (result_tx, mut result_rx) = mpsc::unbounded_channel();
+        for i in 0..3 {
+            let id = format!("tri-{}", i);
+            let signal = Signal::new(id, StrategyType::Triangular, ExchangeId::Binance, 0.01, 1.0, 0.5, "BTC/USDT", 1_000);
+            engine.enqueue(signal).await;
+        }
+
+        engine.fill_execution_slots(&result_tx).await;
+        assert_eq!(engine.in_flight, 2);
+        assert_eq!(engine.queue.stats().depth, 1);
+
+        // 一笔执行完成后空出名额，排队的信号才开始执行
+        let outcome = result_rx.recv().await.unwrap();
+        engine.in_flight -= 1;
+        engine.finish_execution(outcome).await;
+        engine.fill_execution_slots(&result_tx).await;
+        assert_eq!(engine.in_flight, 2);
+        assert_eq!(engine.queue.stats().depth, 0);
+    }
+}
@@ -1,13 +1,63 @@
 //! 多交易所 WebSocket 连接模块
+//!
+//! 连接断开 (读到错误或流结束) 后在 `stop()` 之前自动重连: 按指数退避 (默认 1s、2s、4s…，上限 60s，
+//! 附加 ±20% 抖动) 重新拨号并按原交易对列表重新订阅，已补订阅的最优报价频道一并恢复。
+//! 退避参数由 ENGINE_WS_RECONNECT_INITIAL_MS / ENGINE_WS_RECONNECT_MAX_MS 配置；退避只在重连后收到消息时复位，
+//! 连续重连次数达到 ENGINE_WS_RECONNECT_MAX_ATTEMPTS 后该分片停止重连 (默认不限)。
+//! `health` 返回最近推送时间、推送频率、解析失败数与重连次数；连接仍在但行情静默时可用 `force_reconnect` 强制重连
+//! (由 `watchdog` 模块定期检查)。
+//!
+//! 交易对较多时可按 ENGINE_WS_SHARD_SIZE 把交易对拆分到多条连接 (分片)，各分片独立订阅、独立断线重连，
+//! 行情汇入同一个广播通道。
+//!
+//! 启动后可用 `subscribe_symbols` / `unsubscribe_symbols` 增减行情交易对 (发送增量订阅 / 退订帧)，
+//! 各连接记录当前订阅集合，断线重连时按该集合重新订阅。
+//! 订阅消息按 ENGINE_WS_SUBSCRIBE_CHUNK 拆分为多条 (默认 Binance 50、OKX 20 个交易对一条)，
+//! 以 ENGINE_WS_SUBSCRIBE_DELAY_MS 的间隔依次发送；Binance 的订阅确认 (`{"result":null,"id":N}`) 按请求 ID 核对，
+//! 被拒绝或超时未确认的块记录日志后重发 (最多 3 次)。
+//!
+//! 推送中的交易对在发布前统一转换为 `BASE/QUOTE` (见 `symbols` 模块，结构化形式为 [`Symbol`]，`Ticker::pair` 获取)，
+//! 订阅消息再按交易所写法转换回去。
+//!
+//! 服务端的 Ping 帧原样回 Pong (Binance / Bybit 长时间收不到 Pong 会断开)；服务端发来 Close 时回复 Close 后按断线重连。
+//! 要求应用层心跳的交易所 (OKX `ping`、Gate `spot.ping`、MEXC `PING`) 由读循环按间隔发送。
+//!
+//! `request_orderbooks` 追加订阅深度频道 (Binance depth20@100ms / OKX books5)，
+//! 每个交易对在本地维护最新订单簿，经 `subscribe_orderbooks` 广播一致的快照。
+//! `request_trades` 追加订阅逐笔成交频道 (Binance `<symbol>@trade` / OKX trades / Gate `spot.trades` / MEXC `spot@public.deals`)，
+//! 成交经 `subscribe_trades` 广播。
+//! `request_klines` 追加订阅 1 分钟 K 线 (Binance `<symbol>@kline_1m` / OKX candle1m)，K 线经 `subscribe_klines` 广播
+//! (未收盘的 K 线同样广播，由 `closed` 区分)。
+//! `request_funding_rates` 按 ENGINE_FUNDING_POLL_SECS 定期轮询永续合约资金费率 (Binance / OKX REST，见 `funding` 模块)，
+//! 经 `subscribe_funding_rates` 广播。
+//! 需要完整深度时用 `request_full_orderbooks` 订阅增量频道 (Binance `<symbol>@depth@100ms` / Gate `spot.order_book_update` /
+//! MEXC `spot@public.increase.depth`)，以 REST 快照 + 增量在本地维护 (见 `orderbook` 模块)，增量不连续或重连后自动重新拉取快照。
 
 use anyhow::Result;
 use futures_util::{SinkExt, StreamExt};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use futures_util::stream::SplitSink;
+use futures_util::stream::SplitStream;
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicBool, AtomicI64, AtomicU64, Ordering};
 use std::sync::Arc;
-use tokio::sync::{broadcast, RwLock};
-use tokio_tungstenite::{connect_async, tungstenite::Message};
-use tracing::{error, info, warn};
+use std::time::Duration;
+use tokio::net::TcpStream;
+use tokio::sync::{broadcast, Mutex, Notify, RwLock};
+use tokio::task::JoinHandle;
+use tokio_tungstenite::{connect_async, tungstenite::Message, MaybeTlsStream, WebSocketStream};
+use tracing::{debug, error, info, warn};
+
+use crate::executor::OrderSide;
+use crate::funding::{contract_key, fetch_funding_rates};
+use crate::health::has_quote;
+use crate::orderbook::{
+    fetch_depth_snapshot, parse_depth_diff, parse_levels, supports_depth_sync, DepthUpdate, OrderBookManager, SyncAction,
+};
+use crate::signing::KeyType;
+use crate::symbols::{denormalize_symbol, normalize_symbol};
+pub use crate::symbols::Symbol;
+use crate::timestamps::normalize_timestamp;
 
 /// 交易所 ID
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
@@ -23,6 +73,18 @@ pub enum ExchangeId {
 
 #[allow(dead_code)]
 impl ExchangeId {
+    /// 小写名称 (与序列化形式一致)，用作 Redis Key、指标标签与状态接口的交易所键
+    pub fn key(&self) -> &'static str {
+        match self {
+            ExchangeId::Binance => "binance",
+            ExchangeId::Okx => "okx",
+            ExchangeId::Bybit => "bybit",
+            ExchangeId::Gate => "gate",
+            ExchangeId::Bitget => "bitget",
+            ExchangeId::Mexc => "mexc",
+        }
+    }
+
     /// 获取 WebSocket URL
     pub fn ws_url(&self) -> &'static str {
         match self {
@@ -34,6 +96,92 @@ impl ExchangeId {
             ExchangeId::Mexc => "wss://wbs.mexc.com/ws",
         }
     }
+
+    /// 获取 REST 基础地址
+    pub fn rest_url(&self) -> &'static str {
+        match self {
+            ExchangeId::Binance => "https://api.binance.com",
+            ExchangeId::Okx => "https://www.okx.com",
+            ExchangeId::Bybit => "https://api.bybit.com",
+            ExchangeId::Gate => "https://api.gateio.ws",
+            ExchangeId::Bitget => "https://api.bitget.com",
+            ExchangeId::Mexc => "https://api.mexc.com",
+        }
+    }
+
+    /// 永续合约 REST 基础地址 (资金费率轮询；不支持的交易所为 None)
+    pub fn futures_rest_url(&self) -> Option<&'static str> {
+        match self {
+            ExchangeId::Binance => Some("https://fapi.binance.com"),
+            ExchangeId::Okx => Some("https://www.okx.com"),
+            _ => None,
+        }
+    }
+
+    /// 应用层心跳消息 (不需要的交易所为 None)。
+    /// OKX 回复 "pong"、Gate 回复 spot.pong、MEXC 回复 {"msg":"PONG"}，行情解析均忽略
+    pub fn keepalive_message(&self) -> Option<String> {
+        let msg = match self {
+            // OKX 30 秒内无数据即断开，需发送纯文本 "ping"
+            ExchangeId::Okx => return Some("ping".to_string()),
+            ExchangeId::Gate => serde_json::json!({
+                "time": chrono::Utc::now().timestamp(),
+                "channel": "spot.ping"
+            }),
+            ExchangeId::Mexc => serde_json::json!({ "method": "PING" }),
+            _ => return None,
+        };
+        Some(msg.to_string())
+    }
+
+    /// 应用层心跳间隔 (仅 `keepalive_message` 非空时使用)
+    pub fn keepalive_interval(&self) -> Duration {
+        match self {
+            // OKX 30 秒无数据断开，留 5 秒余量
+            ExchangeId::Okx => Duration::from_secs(25),
+            // Gate v4 约 30 秒无消息可能断开，留足余量
+            ExchangeId::Gate => Duration::from_secs(15),
+            // MEXC 要求客户端每 30 秒内发送一次 PING，否则约 60 秒后断开
+            ExchangeId::Mexc => Duration::from_secs(20),
+            _ => Duration::from_secs(30),
+        }
+    }
+
+    /// 订阅请求是否会收到按 ID 对应的确认回复 (Binance: `{"result":null,"id":N}`)
+    pub fn subscribe_acks(&self) -> bool {
+        matches!(self, ExchangeId::Binance)
+    }
+}
+
+/// 校验自定义接入地址: 必须是指定协议 (wss / https) 且带主机名的合法 URL，返回去掉末尾 `/` 的地址
+pub fn validate_endpoint(raw: &str, scheme: &str) -> Result<String> {
+    let url = reqwest::Url::parse(raw.trim()).map_err(|e| anyhow::anyhow!("地址 {} 无效: {}", raw, e))?;
+    if url.scheme() != scheme {
+        return Err(anyhow::anyhow!("地址 {} 必须使用 {}:// 协议", raw, scheme));
+    }
+    if url.host_str().is_none_or(str::is_empty) {
+        return Err(anyhow::anyhow!("地址 {} 缺少主机名", raw));
+    }
+    Ok(raw.trim().trim_end_matches('/').to_string())
+}
+
+/// 各交易所 REST 基础地址 (配置覆盖优先，否则使用内置默认)
+#[derive(Debug, Clone, Default)]
+pub struct RestEndpoints(HashMap<ExchangeId, String>);
+
+impl RestEndpoints {
+    pub fn from_configs(configs: &[ExchangeConfig]) -> Self {
+        Self(
+            configs
+                .iter()
+                .filter_map(|c| Some((c.id, c.rest_url.clone()?)))
+                .collect(),
+        )
+    }
+
+    pub fn base_url(&self, exchange: ExchangeId) -> &str {
+        self.0.get(&exchange).map(String::as_str).unwrap_or(exchange.rest_url())
+    }
 }
 
 /// Ticker 数据
@@ -43,17 +191,414 @@ pub struct Ticker {
     pub symbol: String,
     pub bid: f64,
     pub ask: f64,
+    /// 买一挂单量 (base)
+    #[serde(default)]
+    pub bid_size: f64,
+    /// 卖一挂单量 (base)
+    #[serde(default)]
+    pub ask_size: f64,
     pub last: f64,
     pub volume: f64,
     pub timestamp: i64,
+    /// 本地解析时间 (毫秒，0 为未知，如回放行情)，用于统计交易所延迟与管道延迟
+    #[serde(default)]
+    pub received_at: i64,
+}
+
+impl Ticker {
+    /// 结构化交易对 (行情发布前已统一为 `BASE/QUOTE`)
+    pub fn pair(&self) -> Option<Symbol> {
+        Symbol::parse(self.exchange, &self.symbol)
+    }
+}
+
+/// 逐笔成交
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Trade {
+    pub exchange: ExchangeId,
+    pub symbol: String,
+    pub price: f64,
+    pub qty: f64,
+    /// 主动成交方向 (taker 买入为 Buy)
+    pub side: OrderSide,
+    pub timestamp: i64,
+}
+
+/// K 线 (OHLCV)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Kline {
+    pub exchange: ExchangeId,
+    pub symbol: String,
+    /// 周期 (如 "1m")
+    pub interval: String,
+    /// 开盘时间 (毫秒)
+    pub open_time: i64,
+    pub open: f64,
+    pub high: f64,
+    pub low: f64,
+    pub close: f64,
+    /// 成交量 (基础币)
+    pub volume: f64,
+    /// 是否已收盘 (未收盘的 K 线会随成交持续更新)
+    pub closed: bool,
+}
+
+/// 永续合约资金费率
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FundingRateUpdate {
+    pub exchange: ExchangeId,
+    /// 合约 (BASE/QUOTE:MARGIN，如 `BTC/USDT:USDT`)
+    pub symbol: String,
+    /// 单期资金费率
+    pub rate: f64,
+    /// 下次结算时间 (毫秒，未知时为 0)
+    pub next_funding_time: i64,
+    /// 标记价格 (接口未提供时为 None)
+    pub mark_price: Option<f64>,
+    pub timestamp: i64,
+}
+
+/// 最优报价频道推送的买一卖一
+#[derive(Debug, Clone)]
+struct Quote {
+    symbol: String,
+    bid: f64,
+    bid_size: f64,
+    ask: f64,
+    ask_size: f64,
+    timestamp: Option<i64>,
+}
+
+/// 盘口档位
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct PriceLevel {
+    pub price: f64,
+    pub size: f64,
+}
+
+/// 订单簿快照 (买盘按价格从高到低，卖盘按价格从低到高)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OrderBook {
+    pub exchange: ExchangeId,
+    pub symbol: String,
+    pub bids: Vec<PriceLevel>,
+    pub asks: Vec<PriceLevel>,
+    /// 交易所推送的序列号 (Binance lastUpdateId / OKX seqId)，用于丢弃乱序快照
+    pub sequence: u64,
+    pub timestamp: i64,
+}
+
+impl OrderBook {
+    pub fn best_bid(&self) -> Option<PriceLevel> {
+        self.bids.first().copied()
+    }
+
+    pub fn best_ask(&self) -> Option<PriceLevel> {
+        self.asks.first().copied()
+    }
+
+    /// 排序并去掉数量为 0 的档位
+    fn normalize(&mut self) {
+        self.bids.retain(|l| l.size > 0.0 && l.price > 0.0);
+        self.asks.retain(|l| l.size > 0.0 && l.price > 0.0);
+        self.bids.sort_by(|a, b| b.price.total_cmp(&a.price));
+        self.asks.sort_by(|a, b| a.price.total_cmp(&b.price));
+    }
+
+    /// 买一不低于卖一 (快照不一致)
+    fn is_crossed(&self) -> bool {
+        matches!((self.best_bid(), self.best_ask()), (Some(bid), Some(ask)) if bid.price >= ask.price)
+    }
+}
+
+type WsWriter = SplitSink<WebSocketStream<MaybeTlsStream<TcpStream>>, Message>;
+type WsReader = SplitStream<WebSocketStream<MaybeTlsStream<TcpStream>>>;
+
+/// 断线重连的退避参数
+#[derive(Debug, Clone, Copy)]
+pub struct ReconnectPolicy {
+    pub initial: Duration,
+    pub max: Duration,
+    /// 连续重连 (期间未收到任何消息) 的最大次数，用尽后该分片停止重连；None 为不限
+    pub max_reconnect_attempts: Option<usize>,
+}
+
+impl Default for ReconnectPolicy {
+    fn default() -> Self {
+        Self {
+            initial: Duration::from_secs(1),
+            max: Duration::from_secs(60),
+            max_reconnect_attempts: None,
+        }
+    }
+}
+
+impl ReconnectPolicy {
+    /// 从 ENGINE_WS_RECONNECT_INITIAL_MS / ENGINE_WS_RECONNECT_MAX_MS / ENGINE_WS_RECONNECT_MAX_ATTEMPTS 读取
+    pub fn from_env() -> Self {
+        let mut policy = Self::default();
+        let env_ms = |name: &str| std::env::var(name).ok().and_then(|v| v.parse::<u64>().ok());
+        if let Some(ms) = env_ms("ENGINE_WS_RECONNECT_INITIAL_MS") {
+            policy.initial = Duration::from_millis(ms.max(1));
+        }
+        if let Some(ms) = env_ms("ENGINE_WS_RECONNECT_MAX_MS") {
+            policy.max = Duration::from_millis(ms);
+        }
+        if let Some(n) = env_ms("ENGINE_WS_RECONNECT_MAX_ATTEMPTS").filter(|n| *n > 0) {
+            policy.max_reconnect_attempts = Some(n as usize);
+        }
+        policy.max = policy.max.max(policy.initial);
+        policy
+    }
+
+    /// 第 attempt 次 (从 0 开始) 重连前的等待: 指数退避封顶后附加 ±20% 抖动
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let base = self
+            .initial
+            .saturating_mul(2u32.saturating_pow(attempt.min(16)))
+            .min(self.max);
+        let jitter = (uuid::Uuid::new_v4().as_u128() % 1_000) as f64 / 1_000.0 * 0.4 - 0.2;
+        base.mul_f64(1.0 + jitter)
+    }
+}
+
+/// 重连计数
+#[derive(Debug, Default)]
+struct ReconnectStats {
+    attempts: AtomicU64,
+    successes: AtomicU64,
+}
+
+/// 行情推送统计 (各分片共用)
+#[derive(Debug, Default)]
+struct FeedStats {
+    /// 最近一条推送的本地接收时间 (毫秒，0 为尚未收到)
+    last_message_at: AtomicI64,
+    /// 连接启动 / 最近一次强制重连的时间 (毫秒)，尚未收到推送时据此计算静默时长
+    since: AtomicI64,
+    messages: AtomicU64,
+    /// 无法解析的推送 (非 JSON 且不是心跳回复)
+    parse_failures: AtomicU64,
+    /// 上次计算频率时的 (时间, 累计条数, 频率)
+    rate: std::sync::Mutex<(i64, u64, f64)>,
+}
+
+impl FeedStats {
+    fn record_message(&self, text: &str) {
+        self.last_message_at
+            .store(chrono::Utc::now().timestamp_millis(), Ordering::Relaxed);
+        self.messages.fetch_add(1, Ordering::Relaxed);
+        if serde_json::from_str::<serde_json::Value>(text).is_err() && !text.trim().eq_ignore_ascii_case("pong") {
+            self.parse_failures.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// 距上次计算至少 1 秒时按区间内的条数更新频率 (条/秒)
+    fn messages_per_sec(&self, now: i64) -> f64 {
+        let total = self.messages.load(Ordering::Relaxed);
+        let Ok(mut rate) = self.rate.lock() else {
+            return 0.0;
+        };
+        let (at, count, value) = *rate;
+        if at == 0 {
+            *rate = (now, total, 0.0);
+        } else if now - at >= 1_000 {
+            *rate = (now, total, total.saturating_sub(count) as f64 * 1_000.0 / (now - at) as f64);
+        } else {
+            return value;
+        }
+        rate.2
+    }
+}
+
+/// 各交易所最近一次由连接任务发布行情的本地时间 (毫秒)，多个连接共用同一份
+#[derive(Debug, Clone, Default)]
+pub struct TickerClock {
+    last: Arc<std::sync::RwLock<HashMap<ExchangeId, i64>>>,
+}
+
+impl TickerClock {
+    pub fn record(&self, exchange: ExchangeId, at: i64) {
+        if let Ok(mut last) = self.last.write() {
+            last.insert(exchange, at);
+        }
+    }
+
+    pub fn snapshot(&self) -> HashMap<ExchangeId, i64> {
+        self.last.read().map(|last| last.clone()).unwrap_or_default()
+    }
+}
+
+/// 行情连接健康状态
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FeedHealth {
+    pub exchange: ExchangeId,
+    pub active: bool,
+    pub connected: bool,
+    pub shards: usize,
+    /// 最近一条推送的时间 (毫秒)
+    pub last_message_at: Option<i64>,
+    /// 已静默的时长 (毫秒；尚未收到推送时从连接启动 / 最近一次强制重连算起)
+    pub silent_ms: i64,
+    pub messages_per_sec: f64,
+    pub messages: u64,
+    pub parse_failures: u64,
+    pub reconnect_attempts: u64,
+    pub reconnect_successes: u64,
+    pub timestamp: i64,
+}
+
+/// 资金费率轮询间隔 (ENGINE_FUNDING_POLL_SECS，默认 60 秒)
+fn funding_poll_interval() -> Duration {
+    let secs = std::env::var("ENGINE_FUNDING_POLL_SECS")
+        .ok()
+        .and_then(|v| v.parse::<u64>().ok())
+        .filter(|v| *v > 0)
+        .unwrap_or(60);
+    Duration::from_secs(secs)
+}
+
+/// 每条连接最多订阅的交易对数 (ENGINE_WS_SHARD_SIZE，如 `200` 或 `binance:200,okx:100,*:150`；0 / 未设置为不分片)
+pub fn shard_size_from_env(exchange: ExchangeId) -> usize {
+    std::env::var("ENGINE_WS_SHARD_SIZE")
+        .ok()
+        .and_then(|raw| parse_per_exchange(&raw, exchange))
+        .unwrap_or(0)
+}
+
+/// 单条订阅消息最多包含的交易对数 (ENGINE_WS_SUBSCRIBE_CHUNK，格式同 ENGINE_WS_SHARD_SIZE)。
+/// 默认 Binance 50 (单条 SUBSCRIBE 的 params 过多会被拒绝)、OKX 20、Bybit 10 (单次请求的 args 有上限)、其他 50
+pub fn subscribe_chunk_from_env(exchange: ExchangeId) -> usize {
+    std::env::var("ENGINE_WS_SUBSCRIBE_CHUNK")
+        .ok()
+        .and_then(|raw| parse_per_exchange(&raw, exchange))
+        .filter(|size| *size > 0)
+        .unwrap_or(match exchange {
+            ExchangeId::Okx => 20,
+            ExchangeId::Bybit => 10,
+            _ => 50,
+        })
+}
+
+/// 分块发送订阅消息的间隔 (ENGINE_WS_SUBSCRIBE_DELAY_MS，默认 250；Binance 单连接每秒最多 5 条入站消息)
+fn subscribe_delay_from_env() -> Duration {
+    Duration::from_millis(
+        std::env::var("ENGINE_WS_SUBSCRIBE_DELAY_MS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(250),
+    )
+}
+
+/// 解析按交易所配置的数值: `200` 或 `binance:200,okx:100,*:150`
+fn parse_per_exchange(raw: &str, exchange: ExchangeId) -> Option<usize> {
+    let mut fallback = None;
+    for item in raw.split(',').map(str::trim).filter(|i| !i.is_empty()) {
+        match item.split_once(':') {
+            None => fallback = item.parse().ok(),
+            Some(("*", size)) => fallback = size.trim().parse().ok(),
+            Some((name, size)) => {
+                let id: Option<ExchangeId> =
+                    serde_json::from_value(serde_json::Value::String(name.trim().to_lowercase())).ok();
+                if id == Some(exchange) {
+                    return size.trim().parse().ok();
+                }
+            }
+        }
+    }
+    fallback
+}
+
+/// 交易对规范形式 (去掉分隔符并大写)，用于在分片间匹配不同写法的同一交易对
+fn symbol_key(symbol: &str) -> String {
+    symbol.replace(['/', '-', '_'], "").to_uppercase()
+}
+
+/// 单条 WebSocket 连接 (分片)
+#[derive(Default)]
+struct Shard {
+    index: usize,
+    /// 该分片当前订阅的交易对 (运行时可增减，重连时按此重新订阅)
+    symbols: std::sync::RwLock<Vec<String>>,
+    /// 规范形式的交易对 (路由补订阅用)
+    keys: std::sync::RwLock<HashSet<String>>,
+    /// WebSocket 写端 (连接后用于追加订阅与回复 Pong)
+    writer: Mutex<Option<WsWriter>>,
+    /// 当前是否连通 (断线重连期间为 false)
+    connected: AtomicBool,
+    /// 通知读循环断开并重连 (行情静默时由看门狗触发)
+    reconnect: Notify,
+    /// 通知读循环退出 (`stop` 时触发；用 `notify_one` 保留许可，读循环正忙时也不会错过)
+    stop: Notify,
+    /// 已发送、等待交易所确认的订阅块 (按请求 ID)
+    acks: std::sync::Mutex<HashMap<u64, PendingAck>>,
+    next_ack_id: AtomicU64,
+}
+
+/// 等待确认的订阅块
+struct PendingAck {
+    symbols: Vec<String>,
+    attempts: u32,
+    sent_at: std::time::Instant,
 }
 
+/// 订阅请求 ID 起始值 (避开深度、成交、K 线等频道使用的固定 ID)
+const ACK_ID_BASE: u64 = 100;
+/// 订阅块未收到确认时的重发等待
+const SUBSCRIBE_ACK_TIMEOUT: Duration = Duration::from_secs(10);
+/// 单个订阅块最多发送次数 (含首次)
+const SUBSCRIBE_MAX_ATTEMPTS: u32 = 3;
+/// `stop` 等待读循环退出的上限，超时后强制中止
+const STOP_TIMEOUT: Duration = Duration::from_secs(1);
+
 /// 交易所连接
 #[allow(dead_code)]
 pub struct ExchangeConnection {
     pub id: ExchangeId,
     pub ticker_tx: broadcast::Sender<Ticker>,
     active: Arc<RwLock<bool>>,
+    /// WebSocket 地址 (默认内置地址，可按交易所配置覆盖)
+    ws_url: String,
+    /// 每个交易对最近一条行情 (消费方滞后丢消息时据此补发)
+    latest: Arc<std::sync::RwLock<HashMap<String, Ticker>>>,
+    /// 各分片连接 (未分片时只有一条)
+    shards: std::sync::RwLock<Vec<Arc<Shard>>>,
+    /// 每条连接最多订阅的交易对数 (0 为不分片)
+    shard_size: usize,
+    /// 单条订阅消息最多包含的交易对数
+    subscribe_chunk: usize,
+    /// 分块发送订阅消息的间隔
+    subscribe_delay: Duration,
+    /// 已补订阅最优报价频道的交易对；其 ticker 缺失的买一卖一由报价频道填充
+    quoted: Arc<std::sync::RwLock<HashSet<String>>>,
+    pub orderbook_tx: broadcast::Sender<OrderBook>,
+    /// 已订阅深度频道的交易对
+    depth_symbols: Arc<std::sync::RwLock<HashSet<String>>>,
+    /// 各交易对本地维护的订单簿
+    books: Arc<std::sync::RwLock<HashMap<String, OrderBook>>>,
+    /// Binance 全量订单簿同步 (快照 + 增量)
+    depth_sync: DepthSync,
+    pub trade_tx: broadcast::Sender<Trade>,
+    /// 已订阅逐笔成交频道的交易对
+    trade_symbols: Arc<std::sync::RwLock<HashSet<String>>>,
+    pub kline_tx: broadcast::Sender<Kline>,
+    /// 已订阅 K 线频道的交易对
+    kline_symbols: Arc<std::sync::RwLock<HashSet<String>>>,
+    pub funding_tx: broadcast::Sender<FundingRateUpdate>,
+    /// 轮询资金费率的合约 (BASE/QUOTE:MARGIN)
+    funding_symbols: Arc<std::sync::RwLock<HashSet<String>>>,
+    /// 资金费率轮询任务是否在运行
+    funding_polling: Arc<AtomicBool>,
+    /// 永续合约 REST 基础地址
+    futures_rest_url: Option<String>,
+    reconnect_policy: ReconnectPolicy,
+    reconnects: Arc<ReconnectStats>,
+    feed: Arc<FeedStats>,
+    /// 最近行情时间 (健康检查用)
+    ticker_clock: TickerClock,
+    /// 各分片读循环任务 (`stop` 时等待退出)
+    tasks: std::sync::Mutex<Vec<JoinHandle<()>>>,
 }
 
 #[allow(dead_code)]
@@ -61,89 +606,982 @@ impl ExchangeConnection {
     /// 创建新连接
     pub async fn new(id: ExchangeId) -> Result<Self> {
         let (ticker_tx, _) = broadcast::channel(1000);
-        
+        let (orderbook_tx, _) = broadcast::channel(1000);
+        let (trade_tx, _) = broadcast::channel(1000);
+        let (kline_tx, _) = broadcast::channel(1000);
+        let (funding_tx, _) = broadcast::channel(1000);
+        let books: Arc<std::sync::RwLock<HashMap<String, OrderBook>>> = Arc::default();
+        let depth_sync = DepthSync {
+            exchange: id,
+            manager: Arc::new(std::sync::Mutex::new(OrderBookManager::new(id))),
+            client: reqwest::Client::builder().timeout(Duration::from_secs(10)).build()?,
+            rest_url: id.rest_url().to_string(),
+            orderbook_tx: orderbook_tx.clone(),
+            books: books.clone(),
+        };
+
         Ok(Self {
             id,
             ticker_tx,
             active: Arc::new(RwLock::new(false)),
+            ws_url: id.ws_url().to_string(),
+            latest: Arc::default(),
+            shards: Default::default(),
+            shard_size: shard_size_from_env(id),
+            subscribe_chunk: subscribe_chunk_from_env(id),
+            subscribe_delay: subscribe_delay_from_env(),
+            quoted: Arc::default(),
+            orderbook_tx,
+            depth_symbols: Arc::default(),
+            books,
+            depth_sync,
+            trade_tx,
+            trade_symbols: Arc::default(),
+            kline_tx,
+            kline_symbols: Arc::default(),
+            funding_tx,
+            funding_symbols: Arc::default(),
+            funding_polling: Arc::default(),
+            futures_rest_url: id.futures_rest_url().map(str::to_string),
+            reconnect_policy: ReconnectPolicy::from_env(),
+            reconnects: Arc::default(),
+            feed: Arc::default(),
+            ticker_clock: TickerClock::default(),
+            tasks: Default::default(),
         })
     }
 
+    /// 发布行情: 先更新最新行情快照，再广播
+    pub fn publish_ticker(&self, ticker: Ticker) {
+        publish_ticker(&self.ticker_tx, &self.latest, ticker);
+    }
+
+    /// 各交易对最近一条行情
+    pub fn latest_tickers(&self) -> Vec<Ticker> {
+        self.latest
+            .read()
+            .map(|latest| latest.values().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// 使用自定义 WebSocket 地址 (区域节点 / 托管机房节点 / 代理)
+    pub fn with_ws_url(mut self, url: impl Into<String>) -> Self {
+        self.ws_url = url.into();
+        self
+    }
+
+    pub fn ws_url(&self) -> &str {
+        &self.ws_url
+    }
+
+    /// 使用自定义 REST 基础地址 (拉取深度快照)
+    pub fn with_rest_url(mut self, url: impl Into<String>) -> Self {
+        self.depth_sync.rest_url = url.into();
+        self
+    }
+
+    /// 使用自定义永续合约 REST 基础地址 (轮询资金费率)
+    pub fn with_futures_rest_url(mut self, url: impl Into<String>) -> Self {
+        self.futures_rest_url = Some(url.into());
+        self
+    }
+
     /// 订阅 Ticker
     pub fn subscribe_tickers(&self) -> broadcast::Receiver<Ticker> {
         self.ticker_tx.subscribe()
     }
 
-    /// 启动 WebSocket 连接
+    /// 订阅订单簿快照 (需先通过 `request_orderbooks` 订阅深度频道)
+    pub fn subscribe_orderbooks(&self) -> broadcast::Receiver<OrderBook> {
+        self.orderbook_tx.subscribe()
+    }
+
+    /// 订阅逐笔成交 (需先通过 `request_trades` 订阅成交频道)
+    pub fn subscribe_trades(&self) -> broadcast::Receiver<Trade> {
+        self.trade_tx.subscribe()
+    }
+
+    /// 订阅 K 线 (需先通过 `request_klines` 订阅 K 线频道)
+    pub fn subscribe_klines(&self) -> broadcast::Receiver<Kline> {
+        self.kline_tx.subscribe()
+    }
+
+    /// 订阅资金费率 (需先通过 `request_funding_rates` 开始轮询)
+    pub fn subscribe_funding_rates(&self) -> broadcast::Receiver<FundingRateUpdate> {
+        self.funding_tx.subscribe()
+    }
+
+    /// 交易对最近一份订单簿快照
+    pub fn latest_orderbook(&self, symbol: &str) -> Option<OrderBook> {
+        self.books.read().ok()?.get(&symbol_key(symbol)).cloned()
+    }
+
+    /// 连接是否活跃
+    pub async fn is_active(&self) -> bool {
+        *self.active.read().await
+    }
+
+    /// WebSocket 当前是否连通 (任一分片断线重连期间为 false)
+    pub fn is_connected(&self) -> bool {
+        self.shards.read().is_ok_and(|shards| {
+            !shards.is_empty() && shards.iter().all(|s| s.connected.load(Ordering::Relaxed))
+        })
+    }
+
+    /// 当前订阅行情的交易对数 (各分片合计)
+    pub fn subscribed_count(&self) -> usize {
+        self.shards
+            .read()
+            .map(|shards| shards.iter().map(|s| s.symbols().len()).sum())
+            .unwrap_or(0)
+    }
+
+    /// 当前连接 (分片) 数
+    pub fn shard_count(&self) -> usize {
+        self.shards.read().map(|shards| shards.len()).unwrap_or(0)
+    }
+
+    /// 重连尝试次数与成功次数
+    pub fn reconnect_counts(&self) -> (u64, u64) {
+        (
+            self.reconnects.attempts.load(Ordering::Relaxed),
+            self.reconnects.successes.load(Ordering::Relaxed),
+        )
+    }
+
+    /// 本地订单簿因增量缺口 / 乱序重新拉取快照的次数
+    pub fn orderbook_resyncs(&self) -> u64 {
+        self.depth_sync.manager.lock().map(|m| m.resync_count()).unwrap_or(0)
+    }
+
+    /// 行情推送健康状态: 最近推送时间、推送频率、解析失败数与重连次数
+    pub fn health(&self) -> FeedHealth {
+        let now = chrono::Utc::now().timestamp_millis();
+        let last = self.feed.last_message_at.load(Ordering::Relaxed);
+        let since = self.feed.since.load(Ordering::Relaxed);
+        let (reconnect_attempts, reconnect_successes) = self.reconnect_counts();
+        FeedHealth {
+            exchange: self.id,
+            active: self.active.try_read().map(|a| *a).unwrap_or(true),
+            connected: self.is_connected(),
+            shards: self.shard_count(),
+            last_message_at: (last > 0).then_some(last),
+            silent_ms: (now - last.max(since)).max(0),
+            messages_per_sec: self.feed.messages_per_sec(now),
+            messages: self.feed.messages.load(Ordering::Relaxed),
+            parse_failures: self.feed.parse_failures.load(Ordering::Relaxed),
+            reconnect_attempts,
+            reconnect_successes,
+            timestamp: now,
+        }
+    }
+
+    /// 强制所有分片断开并按当前订阅集合重连 (连接仍在但行情静默时使用)
+    pub fn force_reconnect(&self) {
+        self.feed
+            .since
+            .store(chrono::Utc::now().timestamp_millis(), Ordering::Relaxed);
+        if let Ok(shards) = self.shards.read() {
+            for shard in shards.iter() {
+                shard.reconnect.notify_waiters();
+            }
+        }
+    }
+
+    /// 与其他连接共用最近行情时间
+    pub fn with_ticker_clock(mut self, clock: TickerClock) -> Self {
+        self.ticker_clock = clock;
+        self
+    }
+
+    pub fn ticker_clock(&self) -> &TickerClock {
+        &self.ticker_clock
+    }
+
+    /// 设置断线重连的退避参数
+    pub fn with_reconnect_policy(mut self, policy: ReconnectPolicy) -> Self {
+        self.reconnect_policy = policy;
+        self
+    }
+
+    /// 设置每条连接最多订阅的交易对数 (0 为不分片)
+    pub fn with_shard_size(mut self, size: usize) -> Self {
+        self.shard_size = size;
+        self
+    }
+
+    /// 设置单条订阅消息的交易对数与分块发送间隔
+    pub fn with_subscribe_chunk(mut self, size: usize, delay: Duration) -> Self {
+        self.subscribe_chunk = size.max(1);
+        self.subscribe_delay = delay;
+        self
+    }
+
+    /// 启动 WebSocket 连接 (首次连接失败直接返回错误；此后各分片断线自动重连，直到 `stop()`)
     pub async fn start(&self, symbols: Vec<String>) -> Result<()> {
-        let url = self.id.ws_url();
-        info!("正在连接 {:?}: {}", self.id, url);
+        let url = self.ws_url.clone();
+        let chunk = if self.shard_size == 0 { symbols.len().max(1) } else { self.shard_size };
+        let groups: Vec<Vec<String>> = if symbols.is_empty() {
+            vec![Vec::new()]
+        } else {
+            symbols.chunks(chunk).map(|c| c.to_vec()).collect()
+        };
+        info!("正在连接 {:?}: {} ({} 条连接)", self.id, url, groups.len());
+        self.feed
+            .since
+            .store(chrono::Utc::now().timestamp_millis(), Ordering::Relaxed);
 
-        let (ws_stream, _) = connect_async(url).await?;
-        let (mut write, mut read) = ws_stream.split();
+        let mut streams = Vec::with_capacity(groups.len());
+        for (index, group) in groups.into_iter().enumerate() {
+            let shard = Arc::new(Shard::new(index, group));
+            let read = self.connect_shard(&shard).await?;
+            streams.push((shard, read));
+        }
+        if let Ok(mut shards) = self.shards.write() {
+            *shards = streams.iter().map(|(shard, _)| shard.clone()).collect();
+        }
+        info!("{:?} 已订阅 {} 个交易对", self.id, symbols.len());
 
         // 设置为活跃
         *self.active.write().await = true;
 
-        // 发送订阅消息
-        let subscribe_msg = self.build_subscribe_message(&symbols);
-        write.send(Message::Text(subscribe_msg)).await?;
-        info!("{:?} 已订阅 {} 个交易对", self.id, symbols.len());
+        for (shard, read) in streams {
+            self.spawn_shard(shard, url.clone(), read);
+        }
+
+        Ok(())
+    }
+
+    /// 启动分片读循环并保存任务句柄
+    fn spawn_shard(&self, shard: Arc<Shard>, url: String, read: WsReader) {
+        let handle = tokio::spawn(self.run_shard(shard, url, read));
+        if let Ok(mut tasks) = self.tasks.lock() {
+            tasks.retain(|task| !task.is_finished());
+            tasks.push(handle);
+        }
+    }
+
+    /// 为分片拨号并分块订阅其当前交易对，返回读端 (写端存入分片)
+    async fn connect_shard(&self, shard: &Shard) -> Result<WsReader> {
+        let frames = shard.ticker_frames(self.id, &shard.symbols(), true, self.subscribe_chunk);
+        let (write, read) = dial(&self.ws_url, frames, self.subscribe_delay).await?;
+        *shard.writer.lock().await = Some(write);
+        shard.connected.store(true, Ordering::Relaxed);
+        Ok(read)
+    }
 
-        // 读取消息
+    /// 单个分片的读循环: 断线后按退避重连并重新订阅该分片的交易对
+    fn run_shard(
+        &self,
+        shard: Arc<Shard>,
+        url: String,
+        mut read: WsReader,
+    ) -> impl std::future::Future<Output = ()> + Send + 'static {
         let ticker_tx = self.ticker_tx.clone();
+        let latest = self.latest.clone();
+        let quoted = self.quoted.clone();
+        let orderbook_tx = self.orderbook_tx.clone();
+        let depth_symbols = self.depth_symbols.clone();
+        let books = self.books.clone();
+        let depth_sync = self.depth_sync.clone();
+        let trade_tx = self.trade_tx.clone();
+        let trade_symbols = self.trade_symbols.clone();
+        let kline_tx = self.kline_tx.clone();
+        let kline_symbols = self.kline_symbols.clone();
         let exchange_id = self.id;
         let active = self.active.clone();
+        let policy = self.reconnect_policy;
+        let stats = self.reconnects.clone();
+        let feed = self.feed.clone();
+        let ticker_clock = self.ticker_clock.clone();
+        let subscribe_chunk = self.subscribe_chunk;
+        let subscribe_delay = self.subscribe_delay;
 
-        tokio::spawn(async move {
-            while *active.read().await {
-                match read.next().await {
-                    Some(Ok(Message::Text(text))) => {
-                        if let Some(ticker) = Self::parse_ticker(exchange_id, &text) {
-                            let _ = ticker_tx.send(ticker);
+        async move {
+            let writer = &shard.writer;
+            let connected = &shard.connected;
+            let index = shard.index;
+            // 应用层心跳 (OKX "ping"、Gate spot.ping 等)；不需要的交易所不触发，连接停止后随读循环退出
+            let keepalive_enabled = exchange_id.keepalive_message().is_some();
+            let keepalive_every = exchange_id.keepalive_interval();
+            // 连续重连次数: 只在重连后收到消息时清零，避免连上即断时退避一直停留在起始等待
+            let mut attempt = 0u32;
+            let mut keepalive = tokio::time::interval_at(
+                tokio::time::Instant::now() + keepalive_every,
+                keepalive_every,
+            );
+            // 订阅确认超时检查 (只对会回复确认的交易所启用)
+            let acks_enabled = exchange_id.subscribe_acks();
+            let mut ack_check = tokio::time::interval_at(
+                tokio::time::Instant::now() + SUBSCRIBE_ACK_TIMEOUT,
+                SUBSCRIBE_ACK_TIMEOUT / 2,
+            );
+            loop {
+                while *active.read().await {
+                    let next = tokio::select! {
+                        _ = shard.stop.notified() => break,
+                        next = read.next() => next,
+                        _ = shard.reconnect.notified() => {
+                            warn!("{:?}#{} 行情静默，强制重连", exchange_id, index);
+                            break;
+                        }
+                        _ = keepalive.tick(), if keepalive_enabled => {
+                            if let (Some(msg), Some(write)) =
+                                (exchange_id.keepalive_message(), writer.lock().await.as_mut())
+                            {
+                                if let Err(e) = write.send(Message::Text(msg)).await {
+                                    warn!("{:?}#{} 发送心跳失败: {}", exchange_id, index, e);
+                                    break;
+                                }
+                            }
+                            continue;
+                        }
+                        _ = ack_check.tick(), if acks_enabled => {
+                            let frames = shard.retry_expired(exchange_id);
+                            if let (false, Some(write)) = (frames.is_empty(), writer.lock().await.as_mut()) {
+                                if let Err(e) = send_frames(write, frames, subscribe_delay).await {
+                                    warn!("{:?}#{} 重发订阅失败: {}", exchange_id, index, e);
+                                    break;
+                                }
+                            }
+                            continue;
+                        }
+                    };
+                    match next {
+                        Some(Ok(Message::Text(text))) => {
+                            attempt = 0;
+                            feed.record_message(&text);
+                            if let Some((id, outcome)) = parse_subscribe_ack(exchange_id, &text) {
+                                if let Some(frame) = shard.on_ack(exchange_id, id, outcome) {
+                                    if let Some(write) = writer.lock().await.as_mut() {
+                                        if let Err(e) = write.send(Message::Text(frame)).await {
+                                            warn!("{:?}#{} 重发订阅失败: {}", exchange_id, index, e);
+                                            break;
+                                        }
+                                    }
+                                }
+                                continue;
+                            }
+                            // 交易对统一转换为 BASE/QUOTE 后再发布
+                            if let Some(mut ticker) = Self::parse_ticker(exchange_id, &text) {
+                                ticker.symbol = normalize_symbol(exchange_id, &ticker.symbol);
+                                fill_quote(&latest, &quoted, &mut ticker);
+                                ticker_clock.record(exchange_id, ticker.received_at);
+                                publish_ticker(&ticker_tx, &latest, ticker);
+                            } else if let Some(mut quote) = Self::parse_quote(exchange_id, &text) {
+                                quote.symbol = normalize_symbol(exchange_id, &quote.symbol);
+                                ticker_clock.record(exchange_id, chrono::Utc::now().timestamp_millis());
+                                publish_quote(&ticker_tx, &latest, quote);
+                            } else if let Some(trades) = Self::parse_trades(exchange_id, &text) {
+                                for mut trade in trades {
+                                    trade.symbol = normalize_symbol(exchange_id, &trade.symbol);
+                                    let _ = trade_tx.send(trade);
+                                }
+                            } else if let Some(klines) = Self::parse_klines(exchange_id, &text) {
+                                for mut kline in klines {
+                                    kline.symbol = normalize_symbol(exchange_id, &kline.symbol);
+                                    let _ = kline_tx.send(kline);
+                                }
+                            } else if let Some(book) = Self::parse_orderbook(exchange_id, &text) {
+                                publish_orderbook(&orderbook_tx, &books, book);
+                            } else if let Some(update) = parse_depth_diff(exchange_id, &text) {
+                                depth_sync.on_update(update);
+                            }
+                        }
+                        Some(Ok(Message::Ping(data))) => {
+                            // Binance / Bybit 要求原样回 Pong，否则约 10 分钟后断开连接
+                            debug!("{:?}#{} 收到 Ping，回复 Pong", exchange_id, index);
+                            if let Some(write) = writer.lock().await.as_mut() {
+                                if let Err(e) = write.send(Message::Pong(data)).await {
+                                    warn!("{:?}#{} 回复 Pong 失败: {}", exchange_id, index, e);
+                                    break;
+                                }
+                            }
+                        }
+                        Some(Ok(Message::Close(frame))) => {
+                            // 服务端主动关闭 (维护 / 24 小时强制断开)，按断线处理并重连
+                            match frame {
+                                Some(frame) => warn!(
+                                    "{:?}#{} 服务端关闭连接: code={} reason={}",
+                                    exchange_id, index, frame.code, frame.reason
+                                ),
+                                None => warn!("{:?}#{} 服务端关闭连接", exchange_id, index),
+                            }
+                            // 回复 Close 完成关闭握手
+                            if let Some(write) = writer.lock().await.as_mut() {
+                                let _ = write.close().await;
+                            }
+                            break;
+                        }
+                        Some(Err(e)) => {
+                            error!("{:?}#{} WebSocket 错误: {}", exchange_id, index, e);
+                            break;
+                        }
+                        None => break,
+                        _ => {}
+                    }
+                }
+                connected.store(false, Ordering::Relaxed);
+                *writer.lock().await = None;
+                if !*active.read().await {
+                    info!("{:?}#{} WebSocket 连接已停止", exchange_id, index);
+                    return;
+                }
+                warn!("{:?}#{} WebSocket 连接已断开，开始重连", exchange_id, index);
+
+                let (write, next_read) = loop {
+                    if policy
+                        .max_reconnect_attempts
+                        .is_some_and(|max| attempt as usize >= max)
+                    {
+                        error!(
+                            "{:?}#{} 连续 {} 次重连后仍未恢复，停止重连",
+                            exchange_id, index, attempt
+                        );
+                        return;
+                    }
+                    let delay = policy.delay(attempt);
+                    tokio::select! {
+                        _ = tokio::time::sleep(delay) => {}
+                        _ = shard.stop.notified() => {}
+                    }
+                    if !*active.read().await {
+                        info!("{:?}#{} 重连期间连接已停止", exchange_id, index);
+                        return;
+                    }
+                    attempt += 1;
+                    stats.attempts.fetch_add(1, Ordering::Relaxed);
+                    // 按当前订阅集合分块重新订阅 (包含运行时追加、不含已退订的交易对)
+                    shard.clear_acks();
+                    let frames = shard.ticker_frames(exchange_id, &shard.symbols(), true, subscribe_chunk);
+                    match dial(&url, frames, subscribe_delay).await {
+                        Ok(stream) => break stream,
+                        Err(e) => warn!(
+                            "{:?}#{} 第 {} 次重连失败 (等待 {} 毫秒后): {}",
+                            exchange_id,
+                            index,
+                            attempt,
+                            delay.as_millis(),
+                            e
+                        ),
+                    }
+                };
+                read = next_read;
+                *writer.lock().await = Some(write);
+                connected.store(true, Ordering::Relaxed);
+                stats.successes.fetch_add(1, Ordering::Relaxed);
+                info!(
+                    "{:?}#{} 第 {} 次尝试重连成功，已重新订阅 {} 个交易对",
+                    exchange_id,
+                    index,
+                    attempt,
+                    shard.symbols().len()
+                );
+
+                // 恢复该分片已补订阅的最优报价频道
+                let quoted_symbols: Vec<String> = quoted
+                    .read()
+                    .map(|q| q.iter().filter(|s| shard.owns(s)).cloned().collect())
+                    .unwrap_or_default();
+                if let Some(msg) = Self::build_quote_subscribe_message(exchange_id, &quoted_symbols)
+                    .filter(|_| !quoted_symbols.is_empty())
+                {
+                    if let Some(write) = writer.lock().await.as_mut() {
+                        if let Err(e) = write.send(Message::Text(msg)).await {
+                            warn!("{:?}#{} 重连后恢复最优报价频道失败: {}", exchange_id, index, e);
                         }
                     }
-                    Some(Ok(Message::Ping(_data))) => {
-                        // 自动处理 ping/pong（忽略 ping payload，避免未使用告警）
-                        info!("{:?} 收到 Ping", exchange_id);
+                }
+
+                // 恢复该分片已订阅的深度频道
+                let depth: Vec<String> = depth_symbols
+                    .read()
+                    .map(|d| d.iter().filter(|s| shard.owns(s)).cloned().collect())
+                    .unwrap_or_default();
+                if !depth.is_empty() {
+                    if let Some(write) = writer.lock().await.as_mut() {
+                        for msg in Self::build_depth_subscribe_messages(exchange_id, &depth).unwrap_or_default() {
+                            if let Err(e) = write.send(Message::Text(msg)).await {
+                                warn!("{:?}#{} 重连后恢复深度频道失败: {}", exchange_id, index, e);
+                                break;
+                            }
+                        }
                     }
-                    Some(Err(e)) => {
-                        error!("{:?} WebSocket 错误: {}", exchange_id, e);
-                        break;
+                }
+
+                // 恢复该分片已订阅的逐笔成交频道
+                let traded: Vec<String> = trade_symbols
+                    .read()
+                    .map(|t| t.iter().filter(|s| shard.owns(s)).cloned().collect())
+                    .unwrap_or_default();
+                if let Some(msg) = Self::build_trade_subscribe_message(exchange_id, &traded).filter(|_| !traded.is_empty()) {
+                    if let Some(write) = writer.lock().await.as_mut() {
+                        if let Err(e) = write.send(Message::Text(msg)).await {
+                            warn!("{:?}#{} 重连后恢复逐笔成交频道失败: {}", exchange_id, index, e);
+                        }
+                    }
+                }
+
+                // 恢复该分片已订阅的 K 线频道
+                let candled: Vec<String> = kline_symbols
+                    .read()
+                    .map(|k| k.iter().filter(|s| shard.owns(s)).cloned().collect())
+                    .unwrap_or_default();
+                if let Some(msg) = Self::build_kline_subscribe_message(exchange_id, &candled).filter(|_| !candled.is_empty()) {
+                    if let Some(write) = writer.lock().await.as_mut() {
+                        if let Err(e) = write.send(Message::Text(msg)).await {
+                            warn!("{:?}#{} 重连后恢复 K 线频道失败: {}", exchange_id, index, e);
+                        }
+                    }
+                }
+
+                // 恢复全量深度增量: 断线期间的增量已丢失，丢弃本地订单簿后重新拉取快照
+                let full: Vec<String> = depth_sync
+                    .tracked()
+                    .into_iter()
+                    .filter(|s| shard.owns(s))
+                    .collect();
+                if !full.is_empty() {
+                    if let Some(write) = writer.lock().await.as_mut() {
+                        for msg in build_diff_subscribe_messages(exchange_id, &full) {
+                            if let Err(e) = write.send(Message::Text(msg)).await {
+                                warn!("{:?}#{} 重连后恢复深度增量频道失败: {}", exchange_id, index, e);
+                                break;
+                            }
+                        }
+                    }
+                    for symbol in full {
+                        if depth_sync.restart(&symbol) {
+                            depth_sync.fetch_snapshot(symbol);
+                        }
                     }
-                    None => break,
-                    _ => {}
                 }
             }
-            warn!("{:?} WebSocket 连接已断开", exchange_id);
-        });
+        }
+    }
+
+    /// 运行时追加订阅行情频道，返回新增的交易对 (已订阅的跳过)。
+    /// 追加到最后一条连接，分片已满时按分片大小新建连接；断线期间只记录，重连时一并订阅
+    pub async fn subscribe_symbols(&self, symbols: &[String]) -> Result<Vec<String>> {
+        let shards: Vec<Arc<Shard>> = self.shards.read().map(|s| s.clone()).unwrap_or_default();
+        let Some(last) = shards.last().cloned() else {
+            anyhow::bail!("{:?} 尚未连接", self.id);
+        };
+        if !*self.active.read().await {
+            anyhow::bail!("{:?} 连接已停止", self.id);
+        }
+        let mut added: Vec<String> = vec![];
+        for symbol in symbols {
+            let key = symbol_key(symbol);
+            if !shards.iter().any(|s| s.subscribed(symbol)) && !added.iter().any(|a| symbol_key(a) == key) {
+                added.push(symbol.clone());
+            }
+        }
+        if added.is_empty() {
+            return Ok(added);
+        }
+        let room = if self.shard_size == 0 {
+            added.len()
+        } else {
+            self.shard_size.saturating_sub(last.symbols().len())
+        };
+        let (head, rest) = added.split_at(room.min(added.len()));
+        if !head.is_empty() {
+            last.add(head);
+            if let Some(write) = last.writer.lock().await.as_mut() {
+                let frames = last.ticker_frames(self.id, head, true, self.subscribe_chunk);
+                send_frames(write, frames, self.subscribe_delay).await?;
+            }
+        }
+        for (offset, group) in rest.chunks(self.shard_size.max(1)).enumerate() {
+            let shard = Arc::new(Shard::new(shards.len() + offset, group.to_vec()));
+            let read = self.connect_shard(&shard).await?;
+            if let Ok(mut all) = self.shards.write() {
+                all.push(shard.clone());
+            }
+            self.spawn_shard(shard, self.ws_url.clone(), read);
+        }
+        info!("{:?} 已追加订阅 {} 个交易对: {:?}", self.id, added.len(), added);
+        Ok(added)
+    }
 
+    /// 运行时退订行情频道 (未订阅的跳过)，并清除这些交易对的最新行情缓存
+    pub async fn unsubscribe_symbols(&self, symbols: &[String]) -> Result<()> {
+        let shards: Vec<Arc<Shard>> = self.shards.read().map(|s| s.clone()).unwrap_or_default();
+        if shards.is_empty() {
+            anyhow::bail!("{:?} 尚未连接", self.id);
+        }
+        let mut removed = 0;
+        for shard in shards {
+            let owned = shard.remove(symbols);
+            if owned.is_empty() {
+                continue;
+            }
+            removed += owned.len();
+            if let Some(write) = shard.writer.lock().await.as_mut() {
+                let frames = shard.ticker_frames(self.id, &owned, false, self.subscribe_chunk);
+                send_frames(write, frames, self.subscribe_delay).await?;
+            }
+        }
+        if let Ok(mut latest) = self.latest.write() {
+            for symbol in symbols {
+                latest.remove(&normalize_symbol(self.id, symbol));
+            }
+        }
+        if removed > 0 {
+            info!("{:?} 已退订 {} 个交易对", self.id, removed);
+        }
         Ok(())
     }
 
-    /// 构建订阅消息 (不同交易所格式不同)
-    fn build_subscribe_message(&self, symbols: &[String]) -> String {
-        match self.id {
-            ExchangeId::Binance => {
-                // Binance 格式: {"method":"SUBSCRIBE","params":["btcusdt@ticker"],"id":1}
+    /// 补订阅最优报价频道 (ticker 频道缺失买一卖一时的回退)
+    pub async fn request_quotes(&self, symbols: &[String]) -> Result<()> {
+        let Some(msg) = Self::build_quote_subscribe_message(self.id, symbols) else {
+            anyhow::bail!("{:?} 不支持最优报价频道", self.id);
+        };
+        let shard = self.shards.read().ok().and_then(|shards| {
+            shards
+                .iter()
+                .find(|s| symbols.iter().all(|symbol| s.owns(symbol)))
+                .or(shards.first())
+                .cloned()
+        });
+        let Some(shard) = shard else {
+            anyhow::bail!("{:?} 尚未连接", self.id);
+        };
+        let mut writer = shard.writer.lock().await;
+        let Some(write) = writer.as_mut() else {
+            anyhow::bail!("{:?} 尚未连接", self.id);
+        };
+        write.send(Message::Text(msg)).await?;
+        if let Ok(mut quoted) = self.quoted.write() {
+            quoted.extend(symbols.iter().map(|s| normalize_symbol(self.id, s)));
+        }
+        info!("{:?} 已补订阅 {} 个交易对的最优报价频道", self.id, symbols.len());
+        Ok(())
+    }
+
+    /// 订阅深度频道 (Binance depth20@100ms / OKX books5)，快照经 `subscribe_orderbooks` 广播
+    pub async fn request_orderbooks(&self, symbols: &[String]) -> Result<()> {
+        if Self::build_depth_subscribe_messages(self.id, symbols).is_none() {
+            anyhow::bail!("{:?} 不支持深度频道", self.id);
+        }
+        self.send_to_owning_shards(symbols, |owned| {
+            Self::build_depth_subscribe_messages(self.id, owned).unwrap_or_default()
+        })
+        .await?;
+        if let Ok(mut depth) = self.depth_symbols.write() {
+            depth.extend(symbols.iter().cloned());
+        }
+        info!("{:?} 已订阅 {} 个交易对的深度频道", self.id, symbols.len());
+        Ok(())
+    }
+
+    /// 订阅逐笔成交频道 (Binance `<symbol>@trade` / OKX trades / Gate spot.trades / MEXC deals)，成交经 `subscribe_trades` 广播
+    pub async fn request_trades(&self, symbols: &[String]) -> Result<()> {
+        if Self::build_trade_subscribe_message(self.id, symbols).is_none() {
+            anyhow::bail!("{:?} 不支持逐笔成交频道", self.id);
+        }
+        self.send_to_owning_shards(symbols, |owned| {
+            Self::build_trade_subscribe_message(self.id, owned).into_iter().collect()
+        })
+        .await?;
+        if let Ok(mut traded) = self.trade_symbols.write() {
+            traded.extend(symbols.iter().cloned());
+        }
+        info!("{:?} 已订阅 {} 个交易对的逐笔成交频道", self.id, symbols.len());
+        Ok(())
+    }
+
+    /// 订阅 1 分钟 K 线频道 (Binance `<symbol>@kline_1m` / OKX candle1m)，K 线经 `subscribe_klines` 广播
+    pub async fn request_klines(&self, symbols: &[String]) -> Result<()> {
+        if Self::build_kline_subscribe_message(self.id, symbols).is_none() {
+            anyhow::bail!("{:?} 不支持 K 线频道", self.id);
+        }
+        self.send_to_owning_shards(symbols, |owned| {
+            Self::build_kline_subscribe_message(self.id, owned).into_iter().collect()
+        })
+        .await?;
+        if let Ok(mut candled) = self.kline_symbols.write() {
+            candled.extend(symbols.iter().cloned());
+        }
+        info!("{:?} 已订阅 {} 个交易对的 K 线频道", self.id, symbols.len());
+        Ok(())
+    }
+
+    /// 定期轮询永续合约资金费率 (symbols 为现货或合约写法，均按以计价币为保证金的合约处理)，
+    /// 结果经 `subscribe_funding_rates` 广播；轮询任务在 `stop()` 后退出
+    pub async fn request_funding_rates(&self, symbols: &[String]) -> Result<()> {
+        let Some(base_url) = self.futures_rest_url.clone() else {
+            anyhow::bail!("{:?} 不支持资金费率轮询", self.id);
+        };
+        if let Ok(mut contracts) = self.funding_symbols.write() {
+            contracts.extend(symbols.iter().filter_map(|s| contract_key(&normalize_symbol(self.id, s))));
+        }
+        info!("{:?} 已开始轮询 {} 个合约的资金费率", self.id, symbols.len());
+        if self.funding_polling.swap(true, Ordering::SeqCst) {
+            return Ok(());
+        }
+        let exchange = self.id;
+        let client = self.depth_sync.client.clone();
+        let contracts = self.funding_symbols.clone();
+        let funding_tx = self.funding_tx.clone();
+        let active = self.active.clone();
+        let polling = self.funding_polling.clone();
+        let interval = funding_poll_interval();
+        tokio::spawn(async move {
+            while *active.read().await {
+                let wanted = contracts.read().map(|c| c.clone()).unwrap_or_default();
+                match fetch_funding_rates(&client, exchange, &base_url, &wanted).await {
+                    Ok(updates) => {
+                        for update in updates {
+                            let _ = funding_tx.send(update);
+                        }
+                    }
+                    Err(e) => warn!("{:?} 拉取资金费率失败: {}", exchange, e),
+                }
+                tokio::time::sleep(interval).await;
+            }
+            polling.store(false, Ordering::SeqCst);
+        });
+        Ok(())
+    }
+
+    /// 构建 K 线频道订阅消息
+    fn build_kline_subscribe_message(exchange: ExchangeId, symbols: &[String]) -> Option<String> {
+        let msg = match exchange {
+            ExchangeId::Binance => {
+                let streams: Vec<String> = symbols
+                    .iter()
+                    .map(|s| format!("{}@kline_1m", denormalize_symbol(ExchangeId::Binance, s).to_lowercase()))
+                    .collect();
+                serde_json::json!({
+                    "method": "SUBSCRIBE",
+                    "params": streams,
+                    "id": 7
+                })
+            }
+            ExchangeId::Okx => {
+                let args: Vec<serde_json::Value> = symbols
+                    .iter()
+                    .map(|s| serde_json::json!({"channel": "candle1m", "instId": denormalize_symbol(ExchangeId::Okx, s)}))
+                    .collect();
+                serde_json::json!({
+                    "op": "subscribe",
+                    "args": args
+                })
+            }
+            _ => return None,
+        };
+        Some(msg.to_string())
+    }
+
+    /// 构建逐笔成交频道订阅消息
+    fn build_trade_subscribe_message(exchange: ExchangeId, symbols: &[String]) -> Option<String> {
+        let msg = match exchange {
+            ExchangeId::Binance => {
+                let streams: Vec<String> = symbols
+                    .iter()
+                    .map(|s| format!("{}@trade", denormalize_symbol(ExchangeId::Binance, s).to_lowercase()))
+                    .collect();
+                serde_json::json!({
+                    "method": "SUBSCRIBE",
+                    "params": streams,
+                    "id": 6
+                })
+            }
+            ExchangeId::Okx => {
+                let args: Vec<serde_json::Value> = symbols
+                    .iter()
+                    .map(|s| serde_json::json!({"channel": "trades", "instId": denormalize_symbol(ExchangeId::Okx, s)}))
+                    .collect();
+                serde_json::json!({
+                    "op": "subscribe",
+                    "args": args
+                })
+            }
+            ExchangeId::Gate => {
+                let pairs: Vec<String> = symbols
+                    .iter()
+                    .map(|s| denormalize_symbol(ExchangeId::Gate, s))
+                    .collect();
+                serde_json::json!({
+                    "time": chrono::Utc::now().timestamp(),
+                    "channel": "spot.trades",
+                    "event": "subscribe",
+                    "payload": pairs
+                })
+            }
+            ExchangeId::Mexc => {
+                let channels: Vec<String> = symbols
+                    .iter()
+                    .map(|s| format!("spot@public.deals.v3.api@{}", denormalize_symbol(ExchangeId::Mexc, s)))
+                    .collect();
+                serde_json::json!({
+                    "method": "SUBSCRIPTION",
+                    "params": channels
+                })
+            }
+            _ => return None,
+        };
+        Some(msg.to_string())
+    }
+
+    /// 订阅全量深度增量 (Binance `<symbol>@depth@100ms` / Gate `spot.order_book_update` / MEXC `spot@public.increase.depth`)，
+    /// 以 REST 快照 + 增量在本地维护完整订单簿，经 `subscribe_orderbooks` 广播 (序列号为最后应用的增量 ID)
+    pub async fn request_full_orderbooks(&self, symbols: &[String]) -> Result<()> {
+        if !supports_depth_sync(self.id) {
+            anyhow::bail!("{:?} 不支持全量深度增量", self.id);
+        }
+        // 先登记再订阅: 快照返回前到达的增量进入缓存
+        let added: Vec<String> = symbols
+            .iter()
+            .filter(|s| self.depth_sync.begin(s))
+            .cloned()
+            .collect();
+        if added.is_empty() {
+            return Ok(());
+        }
+        self.send_to_owning_shards(&added, |owned| build_diff_subscribe_messages(self.id, owned))
+            .await?;
+        for symbol in &added {
+            self.depth_sync.fetch_snapshot(symbol.clone());
+        }
+        info!("{:?} 已订阅 {} 个交易对的深度增量", self.id, added.len());
+        Ok(())
+    }
+
+    /// 按分片拆分追加订阅，推送走订阅该交易对的连接 (不属于任何分片的交易对走第一条连接)
+    async fn send_to_owning_shards(
+        &self,
+        symbols: &[String],
+        build: impl Fn(&[String]) -> Vec<String>,
+    ) -> Result<()> {
+        let shards: Vec<Arc<Shard>> = self.shards.read().map(|s| s.clone()).unwrap_or_default();
+        if shards.is_empty() {
+            anyhow::bail!("{:?} 尚未连接", self.id);
+        }
+        for (i, shard) in shards.iter().enumerate() {
+            let owned: Vec<String> = symbols
+                .iter()
+                .filter(|s| shard.owns(s) || (i == 0 && !shards.iter().any(|other| other.owns(s))))
+                .cloned()
+                .collect();
+            if owned.is_empty() {
+                continue;
+            }
+            let messages = build(&owned);
+            let mut writer = shard.writer.lock().await;
+            let Some(write) = writer.as_mut() else {
+                anyhow::bail!("{:?}#{} 尚未连接", self.id, shard.index);
+            };
+            for msg in messages {
+                write.send(Message::Text(msg)).await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// 构建深度频道订阅消息。
+    /// Binance 的 depth20 推送不带交易对，先切换为 combined 格式 (`{"stream":..,"data":..}`) 以区分交易对
+    fn build_depth_subscribe_messages(exchange: ExchangeId, symbols: &[String]) -> Option<Vec<String>> {
+        let messages = match exchange {
+            ExchangeId::Binance => {
+                let streams: Vec<String> = symbols
+                    .iter()
+                    .map(|s| format!("{}@depth20@100ms", denormalize_symbol(ExchangeId::Binance, s).to_lowercase()))
+                    .collect();
+                vec![
+                    serde_json::json!({
+                        "method": "SET_PROPERTY",
+                        "params": ["combined", true],
+                        "id": 3
+                    }),
+                    serde_json::json!({
+                        "method": "SUBSCRIBE",
+                        "params": streams,
+                        "id": 4
+                    }),
+                ]
+            }
+            ExchangeId::Okx => {
+                let args: Vec<serde_json::Value> = symbols
+                    .iter()
+                    .map(|s| serde_json::json!({"channel": "books5", "instId": denormalize_symbol(ExchangeId::Okx, s)}))
+                    .collect();
+                vec![serde_json::json!({
+                    "op": "subscribe",
+                    "args": args
+                })]
+            }
+            _ => return None,
+        };
+        Some(messages.into_iter().map(|m| m.to_string()).collect())
+    }
+
+    /// 构建最优报价频道订阅消息 (Binance bookTicker / OKX bbo-tbt / Bybit orderbook.1)
+    fn build_quote_subscribe_message(exchange: ExchangeId, symbols: &[String]) -> Option<String> {
+        let msg = match exchange {
+            ExchangeId::Binance => {
                 let streams: Vec<String> = symbols
                     .iter()
-                    .map(|s| format!("{}@ticker", s.to_lowercase().replace("/", "")))
+                    .map(|s| format!("{}@bookTicker", denormalize_symbol(ExchangeId::Binance, s).to_lowercase()))
                     .collect();
                 serde_json::json!({
                     "method": "SUBSCRIBE",
                     "params": streams,
-                    "id": 1
+                    "id": 2
+                })
+            }
+            ExchangeId::Okx => {
+                let args: Vec<serde_json::Value> = symbols
+                    .iter()
+                    .map(|s| serde_json::json!({"channel": "bbo-tbt", "instId": denormalize_symbol(ExchangeId::Okx, s)}))
+                    .collect();
+                serde_json::json!({
+                    "op": "subscribe",
+                    "args": args
+                })
+            }
+            ExchangeId::Bybit => {
+                let topics: Vec<String> = symbols
+                    .iter()
+                    .map(|s| format!("orderbook.1.{}", denormalize_symbol(ExchangeId::Bybit, s)))
+                    .collect();
+                serde_json::json!({
+                    "op": "subscribe",
+                    "args": topics
+                })
+            }
+            _ => return None,
+        };
+        Some(msg.to_string())
+    }
+
+    /// 构建行情频道的订阅 / 退订消息 (不同交易所格式不同)
+    fn build_ticker_message(exchange: ExchangeId, symbols: &[String], subscribe: bool, id: u64) -> String {
+        let op = if subscribe { "subscribe" } else { "unsubscribe" };
+        match exchange {
+            ExchangeId::Binance => {
+                // Binance 格式: {"method":"SUBSCRIBE","params":["btcusdt@ticker"],"id":1}
+                let streams: Vec<String> = symbols
+                    .iter()
+                    .map(|s| format!("{}@ticker", denormalize_symbol(ExchangeId::Binance, s).to_lowercase()))
+                    .collect();
+                serde_json::json!({
+                    "method": op.to_uppercase(),
+                    "params": streams,
+                    "id": id
                 }).to_string()
             }
             ExchangeId::Okx => {
                 // OKX 格式
                 let args: Vec<serde_json::Value> = symbols
                     .iter()
-                    .map(|s| serde_json::json!({"channel": "tickers", "instId": s.replace("/", "-")}))
+                    .map(|s| serde_json::json!({"channel": "tickers", "instId": denormalize_symbol(ExchangeId::Okx, s)}))
                     .collect();
                 serde_json::json!({
-                    "op": "subscribe",
+                    "op": op,
                     "args": args
                 }).to_string()
             }
@@ -151,18 +1589,52 @@ impl ExchangeConnection {
                 // Bybit 格式
                 let topics: Vec<String> = symbols
                     .iter()
-                    .map(|s| format!("tickers.{}", s.replace("/", "")))
+                    .map(|s| format!("tickers.{}", denormalize_symbol(ExchangeId::Bybit, s)))
                     .collect();
                 serde_json::json!({
-                    "op": "subscribe",
+                    "op": op,
                     "args": topics
                 }).to_string()
             }
-            _ => {
-                // 默认格式
+            ExchangeId::Gate => {
+                // Gate v4 格式: {"time":..,"channel":"spot.tickers","event":"subscribe","payload":["BTC_USDT"]}
+                let pairs: Vec<String> = symbols
+                    .iter()
+                    .map(|s| denormalize_symbol(ExchangeId::Gate, s))
+                    .collect();
+                serde_json::json!({
+                    "time": chrono::Utc::now().timestamp(),
+                    "channel": "spot.tickers",
+                    "event": op,
+                    "payload": pairs
+                }).to_string()
+            }
+            ExchangeId::Bitget => {
+                // Bitget spot v1 格式: {"op":"subscribe","args":[{"instType":"sp","channel":"ticker","instId":"BTCUSDT"}]}
+                let args: Vec<serde_json::Value> = symbols
+                    .iter()
+                    .map(|s| {
+                        serde_json::json!({
+                            "instType": "sp",
+                            "channel": "ticker",
+                            "instId": denormalize_symbol(ExchangeId::Bitget, s)
+                        })
+                    })
+                    .collect();
+                serde_json::json!({
+                    "op": op,
+                    "args": args
+                }).to_string()
+            }
+            ExchangeId::Mexc => {
+                // MEXC v3 格式: {"method":"SUBSCRIPTION","params":["spot@public.bookTicker.v3.api@BTCUSDT"]}
+                let channels: Vec<String> = symbols
+                    .iter()
+                    .map(|s| format!("spot@public.bookTicker.v3.api@{}", denormalize_symbol(ExchangeId::Mexc, s)))
+                    .collect();
                 serde_json::json!({
-                    "type": "subscribe",
-                    "channels": symbols
+                    "method": if subscribe { "SUBSCRIPTION" } else { "UNSUBSCRIPTION" },
+                    "params": channels
                 }).to_string()
             }
         }
@@ -171,10 +1643,12 @@ impl ExchangeConnection {
     /// 解析 Ticker 消息 (不同交易所格式不同)
     fn parse_ticker(exchange: ExchangeId, msg: &str) -> Option<Ticker> {
         let json: serde_json::Value = serde_json::from_str(msg).ok()?;
+        let received_at = chrono::Utc::now().timestamp_millis();
         
         match exchange {
             ExchangeId::Binance => {
                 // Binance ticker 格式
+                let json = binance_payload(&json);
                 if json.get("e")?.as_str()? != "24hrTicker" {
                     return None;
                 }
@@ -183,9 +1657,12 @@ impl ExchangeConnection {
                     symbol: json.get("s")?.as_str()?.to_string(),
                     bid: json.get("b")?.as_str()?.parse().ok()?,
                     ask: json.get("a")?.as_str()?.parse().ok()?,
+                    bid_size: json.get("B").and_then(|v| v.as_str()).and_then(|v| v.parse().ok()).unwrap_or(0.0),
+                    ask_size: json.get("A").and_then(|v| v.as_str()).and_then(|v| v.parse().ok()).unwrap_or(0.0),
                     last: json.get("c")?.as_str()?.parse().ok()?,
                     volume: json.get("v")?.as_str()?.parse().ok()?,
-                    timestamp: json.get("E")?.as_i64()?,
+                    timestamp: normalize_timestamp(exchange, json.get("E")?)?,
+                    received_at,
                 })
             }
             ExchangeId::Okx => {
@@ -195,47 +1672,1500 @@ impl ExchangeConnection {
                     symbol: data.get("instId")?.as_str()?.to_string(),
                     bid: data.get("bidPx")?.as_str()?.parse().ok()?,
                     ask: data.get("askPx")?.as_str()?.parse().ok()?,
+                    bid_size: data.get("bidSz").and_then(|v| v.as_str()).and_then(|v| v.parse().ok()).unwrap_or(0.0),
+                    ask_size: data.get("askSz").and_then(|v| v.as_str()).and_then(|v| v.parse().ok()).unwrap_or(0.0),
                     last: data.get("last")?.as_str()?.parse().ok()?,
                     volume: data.get("vol24h")?.as_str()?.parse().ok()?,
-                    timestamp: data.get("ts")?.as_str()?.parse().ok()?,
+                    timestamp: normalize_timestamp(exchange, data.get("ts")?)?,
+                    received_at,
+                })
+            }
+            ExchangeId::Gate => {
+                // 只处理 spot.tickers 的推送 (event 为 update)，订阅确认帧 (event 为 subscribe) 直接忽略
+                if json.get("channel")?.as_str()? != "spot.tickers" || json.get("event")?.as_str()? != "update" {
+                    return None;
+                }
+                let result = json.get("result")?;
+                let num = |key: &str| result.get(key)?.as_str()?.parse::<f64>().ok();
+                Some(Ticker {
+                    exchange,
+                    symbol: result.get("currency_pair")?.as_str()?.to_string(),
+                    bid: num("highest_bid")?,
+                    ask: num("lowest_ask")?,
+                    bid_size: num("highest_size").unwrap_or(0.0),
+                    ask_size: num("lowest_size").unwrap_or(0.0),
+                    last: num("last")?,
+                    volume: num("base_volume")?,
+                    timestamp: normalize_timestamp(exchange, json.get("time_ms").or(json.get("time"))?)?,
+                    received_at,
+                })
+            }
+            ExchangeId::Bitget => {
+                // 订阅确认 ({"event":"subscribe",..}) 没有 data，心跳回复 "pong" 不是 JSON，均在此前返回 None
+                if json.get("arg")?.get("channel")?.as_str()? != "ticker" {
+                    return None;
+                }
+                let data = json.get("data")?.as_array()?.first()?;
+                let num = |key: &str| -> Option<f64> {
+                    match data.get(key)? {
+                        serde_json::Value::String(s) => s.parse().ok(),
+                        v => v.as_f64(),
+                    }
+                };
+                Some(Ticker {
+                    exchange,
+                    symbol: data.get("instId")?.as_str()?.to_string(),
+                    bid: num("bidPr")?,
+                    ask: num("askPr")?,
+                    bid_size: num("bidSz").unwrap_or(0.0),
+                    ask_size: num("askSz").unwrap_or(0.0),
+                    last: num("last")?,
+                    volume: num("baseVolume")?,
+                    timestamp: normalize_timestamp(exchange, json.get("ts").or(data.get("ts"))?)?,
+                    received_at,
+                })
+            }
+            ExchangeId::Mexc => {
+                // bookTicker 格式: {"c":"spot@public.bookTicker.v3.api@BTCUSDT","d":{"A":"34.7","B":"1.5","a":"20863.82","b":"20863.81"},"s":"BTCUSDT","t":1661932660144}
+                // 订阅确认 ({"id":0,"code":0,"msg":..}) 没有 c 字段
+                if !json.get("c")?.as_str()?.starts_with("spot@public.bookTicker") {
+                    return None;
+                }
+                let data = json.get("d")?;
+                let num = |key: &str| data.get(key)?.as_str()?.parse::<f64>().ok();
+                let bid = num("b")?;
+                let ask = num("a")?;
+                // bookTicker 不含最新成交价与 24 小时成交量: last 取买一卖一中间价，volume 记为 0
+                Some(Ticker {
+                    exchange,
+                    symbol: json.get("s")?.as_str()?.to_string(),
+                    bid,
+                    ask,
+                    bid_size: num("B").unwrap_or(0.0),
+                    ask_size: num("A").unwrap_or(0.0),
+                    last: (bid + ask) / 2.0,
+                    volume: 0.0,
+                    timestamp: normalize_timestamp(exchange, json.get("t")?)?,
+                    received_at,
                 })
             }
             _ => None,
         }
     }
 
-    /// 停止连接
-    pub async fn stop(&self) {
-        *self.active.write().await = false;
+    /// 解析最优报价频道消息
+    fn parse_quote(exchange: ExchangeId, msg: &str) -> Option<Quote> {
+        let json: serde_json::Value = serde_json::from_str(msg).ok()?;
+        let num = |v: &serde_json::Value| v.as_str()?.parse::<f64>().ok();
+        match exchange {
+            ExchangeId::Binance => {
+                // bookTicker 格式: {"u":400900217,"s":"BNBUSDT","b":"25.35","B":"31.21","a":"25.36","A":"40.66"}
+                let json = binance_payload(&json);
+                if json.get("e").is_some() {
+                    return None;
+                }
+                json.get("u")?;
+                Some(Quote {
+                    symbol: json.get("s")?.as_str()?.to_string(),
+                    bid: num(json.get("b")?)?,
+                    bid_size: json.get("B").and_then(num).unwrap_or(0.0),
+                    ask: num(json.get("a")?)?,
+                    ask_size: json.get("A").and_then(num).unwrap_or(0.0),
+                    timestamp: None,
+                })
+            }
+            ExchangeId::Okx => {
+                let arg = json.get("arg")?;
+                if arg.get("channel")?.as_str()? != "bbo-tbt" {
+                    return None;
+                }
+                let data = json.get("data")?.as_array()?.first()?;
+                let bid = data.get("bids")?.as_array()?.first()?.as_array()?;
+                let ask = data.get("asks")?.as_array()?.first()?.as_array()?;
+                Some(Quote {
+                    symbol: arg.get("instId")?.as_str()?.to_string(),
+                    bid: num(bid.first()?)?,
+                    bid_size: bid.get(1).and_then(num).unwrap_or(0.0),
+                    ask: num(ask.first()?)?,
+                    ask_size: ask.get(1).and_then(num).unwrap_or(0.0),
+                    timestamp: data.get("ts").and_then(|ts| normalize_timestamp(exchange, ts)),
+                })
+            }
+            ExchangeId::Bybit => {
+                if !json.get("topic")?.as_str()?.starts_with("orderbook.1.") {
+                    return None;
+                }
+                let data = json.get("data")?;
+                let bid = data.get("b")?.as_array()?.first()?.as_array()?;
+                let ask = data.get("a")?.as_array()?.first()?.as_array()?;
+                Some(Quote {
+                    symbol: data.get("s")?.as_str()?.to_string(),
+                    bid: num(bid.first()?)?,
+                    bid_size: bid.get(1).and_then(num).unwrap_or(0.0),
+                    ask: num(ask.first()?)?,
+                    ask_size: ask.get(1).and_then(num).unwrap_or(0.0),
+                    timestamp: json.get("ts").and_then(|ts| normalize_timestamp(exchange, ts)),
+                })
+            }
+            _ => None,
+        }
     }
-}
-
-/// 交易所配置
-#[derive(Debug, Clone, Deserialize)]
-#[allow(dead_code)]
-pub struct ExchangeConfig {
-    pub id: ExchangeId,
-    pub api_key: String,
-    pub api_secret: String,
-    pub passphrase: Option<String>,
-    pub enabled: bool,
-}
 
-/// 连接所有启用的交易所
-pub async fn connect_all(configs: &[ExchangeConfig]) -> Result<HashMap<ExchangeId, Arc<ExchangeConnection>>> {
-    let mut connections = HashMap::new();
+    /// 解析逐笔成交 (OKX / MEXC 一条推送可能包含多笔)
+    fn parse_trades(exchange: ExchangeId, msg: &str) -> Option<Vec<Trade>> {
+        let json: serde_json::Value = serde_json::from_str(msg).ok()?;
+        match exchange {
+            ExchangeId::Binance => {
+                // {"e":"trade","E":1672515782136,"s":"BNBBTC","t":12345,"p":"0.001","q":"100","T":1672515782136,"m":true}
+                let json = binance_payload(&json);
+                if json.get("e")?.as_str()? != "trade" {
+                    return None;
+                }
+                // m 为买方是挂单方，即主动卖出
+                let buyer_is_maker = json.get("m").and_then(|v| v.as_bool()).unwrap_or(false);
+                Some(vec![Trade {
+                    exchange,
+                    symbol: json.get("s")?.as_str()?.to_string(),
+                    price: json.get("p")?.as_str()?.parse().ok()?,
+                    qty: json.get("q")?.as_str()?.parse().ok()?,
+                    side: if buyer_is_maker { OrderSide::Sell } else { OrderSide::Buy },
+                    timestamp: normalize_timestamp(exchange, json.get("T").or(json.get("E"))?)?,
+                }])
+            }
+            ExchangeId::Okx => {
+                // {"arg":{"channel":"trades","instId":"BTC-USDT"},"data":[{"instId":"BTC-USDT","tradeId":"130639474","px":"42219.9","sz":"0.12060306","side":"buy","ts":"1630048897897"}]}
+                if json.get("arg")?.get("channel")?.as_str()? != "trades" {
+                    return None;
+                }
+                json.get("data")?
+                    .as_array()?
+                    .iter()
+                    .map(|data| {
+                        Some(Trade {
+                            exchange,
+                            symbol: data.get("instId")?.as_str()?.to_string(),
+                            price: data.get("px")?.as_str()?.parse().ok()?,
+                            qty: data.get("sz")?.as_str()?.parse().ok()?,
+                            side: match data.get("side")?.as_str()? {
+                                "sell" => OrderSide::Sell,
+                                _ => OrderSide::Buy,
+                            },
+                            timestamp: normalize_timestamp(exchange, data.get("ts")?)?,
+                        })
+                    })
+                    .collect()
+            }
+            ExchangeId::Gate => {
+                // {"time":1606292218,"time_ms":1606292218231,"channel":"spot.trades","event":"update",
+                //  "result":{"id":309143071,"create_time":1606292218,"create_time_ms":"1606292218213.4578","side":"sell",
+                //  "currency_pair":"GT_USDT","amount":"16.4700000000","price":"0.4705000000","range":"2390902-2390902"}}
+                if json.get("channel")?.as_str()? != "spot.trades" || json.get("event")?.as_str()? != "update" {
+                    return None;
+                }
+                let result = json.get("result")?;
+                // create_time_ms 为带小数的毫秒字符串，不能按通用规则当作秒解析
+                let timestamp = match result.get("create_time_ms").and_then(|v| v.as_str()) {
+                    Some(ms) => ms.parse::<f64>().ok()? as i64,
+                    None => normalize_timestamp(exchange, json.get("time_ms").or(result.get("create_time"))?)?,
+                };
+                Some(vec![Trade {
+                    exchange,
+                    symbol: result.get("currency_pair")?.as_str()?.to_string(),
+                    price: result.get("price")?.as_str()?.parse().ok()?,
+                    qty: result.get("amount")?.as_str()?.parse().ok()?,
+                    side: match result.get("side")?.as_str()? {
+                        "sell" => OrderSide::Sell,
+                        _ => OrderSide::Buy,
+                    },
+                    timestamp,
+                }])
+            }
+            ExchangeId::Mexc => {
+                // {"c":"spot@public.deals.v3.api@BTCUSDT","d":{"deals":[{"S":1,"p":"20233.84","t":1661927587825,"v":"0.001028"}],
+                //  "e":"spot@public.deals.v3.api"},"s":"BTCUSDT","t":1661927587836}
+                // S 为主动成交方向: 1 买入，2 卖出
+                if !json.get("c")?.as_str()?.starts_with("spot@public.deals") {
+                    return None;
+                }
+                let symbol = json.get("s")?.as_str()?;
+                json.get("d")?
+                    .get("deals")?
+                    .as_array()?
+                    .iter()
+                    .map(|deal| {
+                        Some(Trade {
+                            exchange,
+                            symbol: symbol.to_string(),
+                            price: deal.get("p")?.as_str()?.parse().ok()?,
+                            qty: deal.get("v")?.as_str()?.parse().ok()?,
+                            side: match deal.get("S")?.as_i64()? {
+                                2 => OrderSide::Sell,
+                                _ => OrderSide::Buy,
+                            },
+                            timestamp: normalize_timestamp(exchange, deal.get("t").or(json.get("t"))?)?,
+                        })
+                    })
+                    .collect()
+            }
+            _ => None,
+        }
+    }
 
-    for config in configs.iter().filter(|c| c.enabled) {
-        match ExchangeConnection::new(config.id).await {
-            Ok(conn) => {
-                info!("创建 {:?} 连接成功", config.id);
-                connections.insert(config.id, Arc::new(conn));
+    /// 解析 K 线 (OKX 一条推送可能包含多根)
+    fn parse_klines(exchange: ExchangeId, msg: &str) -> Option<Vec<Kline>> {
+        let json: serde_json::Value = serde_json::from_str(msg).ok()?;
+        let num = |v: &serde_json::Value| v.as_str()?.parse::<f64>().ok();
+        match exchange {
+            ExchangeId::Binance => {
+                // {"e":"kline","E":1672515782136,"s":"BNBBTC","k":{"t":1672515780000,"T":1672515839999,"s":"BNBBTC","i":"1m",
+                //  "o":"0.0010","c":"0.0020","h":"0.0025","l":"0.0015","v":"1000","x":false,...}}
+                let json = binance_payload(&json);
+                if json.get("e")?.as_str()? != "kline" {
+                    return None;
+                }
+                let k = json.get("k")?;
+                Some(vec![Kline {
+                    exchange,
+                    symbol: k.get("s").or(json.get("s"))?.as_str()?.to_string(),
+                    interval: k.get("i")?.as_str()?.to_string(),
+                    open_time: normalize_timestamp(exchange, k.get("t")?)?,
+                    open: num(k.get("o")?)?,
+                    high: num(k.get("h")?)?,
+                    low: num(k.get("l")?)?,
+                    close: num(k.get("c")?)?,
+                    volume: num(k.get("v")?)?,
+                    closed: k.get("x")?.as_bool()?,
+                }])
             }
-            Err(e) => {
-                error!("创建 {:?} 连接失败: {}", config.id, e);
+            ExchangeId::Okx => {
+                // {"arg":{"channel":"candle1m","instId":"BTC-USDT"},
+                //  "data":[["1597026383085","8533.02","8553.74","8527.17","8548.26","45247","529.5858061","529.5858061","0"]]}
+                // data: [ts, o, h, l, c, vol, volCcy, volCcyQuote, confirm]，confirm 为 "1" 表示已收盘
+                let arg = json.get("arg")?;
+                let interval = arg.get("channel")?.as_str()?.strip_prefix("candle")?.to_string();
+                let symbol = arg.get("instId")?.as_str()?;
+                json.get("data")?
+                    .as_array()?
+                    .iter()
+                    .map(|data| {
+                        let data = data.as_array()?;
+                        Some(Kline {
+                            exchange,
+                            symbol: symbol.to_string(),
+                            interval: interval.clone(),
+                            open_time: normalize_timestamp(exchange, data.first()?)?,
+                            open: num(data.get(1)?)?,
+                            high: num(data.get(2)?)?,
+                            low: num(data.get(3)?)?,
+                            close: num(data.get(4)?)?,
+                            volume: num(data.get(5)?)?,
+                            closed: data.get(8)?.as_str()? == "1",
+                        })
+                    })
+                    .collect()
             }
+            _ => None,
         }
     }
 
-    Ok(connections)
+    /// 解析深度频道快照 (Binance depth20 / OKX books5，均为前 N 档全量推送)
+    fn parse_orderbook(exchange: ExchangeId, msg: &str) -> Option<OrderBook> {
+        let json: serde_json::Value = serde_json::from_str(msg).ok()?;
+        match exchange {
+            ExchangeId::Binance => {
+                // combined 格式: {"stream":"btcusdt@depth20@100ms","data":{"lastUpdateId":160,"bids":[["0.0024","10"]],"asks":[..]}}
+                let stream = json.get("stream")?.as_str()?;
+                let (symbol, channel) = stream.split_once('@')?;
+                if !channel.starts_with("depth") {
+                    return None;
+                }
+                let data = json.get("data")?;
+                Some(OrderBook {
+                    exchange,
+                    symbol: symbol.to_uppercase(),
+                    bids: parse_levels(data.get("bids")?)?,
+                    asks: parse_levels(data.get("asks")?)?,
+                    sequence: data.get("lastUpdateId")?.as_u64()?,
+                    // 部分深度推送不带时间戳，取本地接收时间
+                    timestamp: chrono::Utc::now().timestamp_millis(),
+                })
+            }
+            ExchangeId::Okx => {
+                // books5 格式: {"arg":{"channel":"books5","instId":"BTC-USDT"},"data":[{"asks":[["8446","95","0","3"]],"bids":[..],"ts":"1597026383085","seqId":123}]}
+                let arg = json.get("arg")?;
+                if arg.get("channel")?.as_str()? != "books5" {
+                    return None;
+                }
+                let data = json.get("data")?.as_array()?.first()?;
+                Some(OrderBook {
+                    exchange,
+                    symbol: arg.get("instId")?.as_str()?.to_string(),
+                    bids: parse_levels(data.get("bids")?)?,
+                    asks: parse_levels(data.get("asks")?)?,
+                    sequence: data.get("seqId").and_then(|v| v.as_u64()).unwrap_or(0),
+                    timestamp: normalize_timestamp(exchange, data.get("ts")?)?,
+                })
+            }
+            _ => None,
+        }
+    }
+
+    /// 停止连接: 各分片发送 Close 帧并唤醒读循环，等待其退出 (最多 `STOP_TIMEOUT`，超时强制中止)
+    pub async fn stop(&self) {
+        *self.active.write().await = false;
+        self.depth_sync.clear();
+        let deadline = tokio::time::Instant::now() + STOP_TIMEOUT;
+        let shards: Vec<Arc<Shard>> = self.shards.read().map(|s| s.clone()).unwrap_or_default();
+        for shard in &shards {
+            // 发送 Close 帧关闭连接，并唤醒阻塞在读取 / 重连等待中的读循环
+            if let Some(write) = shard.writer.lock().await.as_mut() {
+                if let Err(e) = tokio::time::timeout_at(deadline, write.close()).await.unwrap_or(Ok(())) {
+                    debug!("{:?}#{} 发送 Close 帧失败: {}", self.id, shard.index, e);
+                }
+            }
+            shard.stop.notify_one();
+        }
+        let tasks: Vec<JoinHandle<()>> = self.tasks.lock().map(|mut t| t.drain(..).collect()).unwrap_or_default();
+        for mut task in tasks {
+            if tokio::time::timeout_at(deadline, &mut task).await.is_err() {
+                warn!("{:?} 读循环未在 {} 毫秒内退出，强制中止", self.id, STOP_TIMEOUT.as_millis());
+                task.abort();
+            }
+        }
+        info!("{:?} 连接已关闭", self.id);
+    }
+}
+
+impl Shard {
+    fn new(index: usize, symbols: Vec<String>) -> Self {
+        Self {
+            index,
+            keys: std::sync::RwLock::new(symbols.iter().map(|s| symbol_key(s)).collect()),
+            symbols: std::sync::RwLock::new(symbols),
+            ..Default::default()
+        }
+    }
+
+    /// 按块构建行情订阅 / 退订消息；会回复确认的交易所为每个订阅块分配请求 ID 并登记待确认
+    fn ticker_frames(&self, exchange: ExchangeId, symbols: &[String], subscribe: bool, chunk: usize) -> Vec<String> {
+        if symbols.is_empty() {
+            return vec![ExchangeConnection::build_ticker_message(exchange, symbols, subscribe, self.next_id())];
+        }
+        symbols
+            .chunks(chunk.max(1))
+            .map(|group| self.frame(exchange, group.to_vec(), subscribe, 1))
+            .collect()
+    }
+
+    fn frame(&self, exchange: ExchangeId, symbols: Vec<String>, subscribe: bool, attempts: u32) -> String {
+        let id = self.next_id();
+        let frame = ExchangeConnection::build_ticker_message(exchange, &symbols, subscribe, id);
+        if subscribe && exchange.subscribe_acks() {
+            if let Ok(mut acks) = self.acks.lock() {
+                acks.insert(
+                    id,
+                    PendingAck {
+                        symbols,
+                        attempts,
+                        sent_at: std::time::Instant::now(),
+                    },
+                );
+            }
+        }
+        frame
+    }
+
+    fn next_id(&self) -> u64 {
+        ACK_ID_BASE + self.next_ack_id.fetch_add(1, Ordering::Relaxed)
+    }
+
+    /// 处理订阅确认；失败且未超过次数上限时返回重发的消息
+    fn on_ack(&self, exchange: ExchangeId, id: u64, outcome: std::result::Result<(), String>) -> Option<String> {
+        let pending = self.acks.lock().ok()?.remove(&id)?;
+        match outcome {
+            Ok(()) => {
+                debug!("{:?}#{} 订阅确认 id={} ({} 个交易对)", exchange, self.index, id, pending.symbols.len());
+                None
+            }
+            Err(e) => {
+                warn!(
+                    "{:?}#{} 订阅被拒绝 id={} ({} 个交易对，第 {} 次): {}",
+                    exchange,
+                    self.index,
+                    id,
+                    pending.symbols.len(),
+                    pending.attempts,
+                    e
+                );
+                self.retry(exchange, pending)
+            }
+        }
+    }
+
+    /// 超时未确认的订阅块按原交易对重发
+    fn retry_expired(&self, exchange: ExchangeId) -> Vec<String> {
+        let expired: Vec<PendingAck> = match self.acks.lock() {
+            Ok(mut acks) => {
+                let ids: Vec<u64> = acks
+                    .iter()
+                    .filter(|(_, p)| p.sent_at.elapsed() >= SUBSCRIBE_ACK_TIMEOUT)
+                    .map(|(id, _)| *id)
+                    .collect();
+                ids.iter().filter_map(|id| acks.remove(id)).collect()
+            }
+            Err(_) => return vec![],
+        };
+        expired
+            .into_iter()
+            .filter_map(|pending| {
+                warn!(
+                    "{:?}#{} {} 个交易对的订阅 {} 秒内未确认 (第 {} 次)",
+                    exchange,
+                    self.index,
+                    pending.symbols.len(),
+                    SUBSCRIBE_ACK_TIMEOUT.as_secs(),
+                    pending.attempts
+                );
+                self.retry(exchange, pending)
+            })
+            .collect()
+    }
+
+    fn retry(&self, exchange: ExchangeId, pending: PendingAck) -> Option<String> {
+        if pending.attempts >= SUBSCRIBE_MAX_ATTEMPTS {
+            error!(
+                "{:?}#{} 订阅 {} 次仍未成功，放弃: {:?}",
+                exchange, self.index, pending.attempts, pending.symbols
+            );
+            return None;
+        }
+        Some(self.frame(exchange, pending.symbols, true, pending.attempts + 1))
+    }
+
+    /// 断线后丢弃待确认的订阅块 (重连时重新订阅)
+    fn clear_acks(&self) {
+        if let Ok(mut acks) = self.acks.lock() {
+            acks.clear();
+        }
+    }
+
+    /// 交易对是否由该分片订阅 (未分片时订阅全部)
+    fn owns(&self, symbol: &str) -> bool {
+        let Ok(keys) = self.keys.read() else {
+            return false;
+        };
+        (self.index == 0 && keys.is_empty()) || keys.contains(&symbol_key(symbol))
+    }
+
+    /// 当前订阅的交易对
+    fn symbols(&self) -> Vec<String> {
+        self.symbols.read().map(|s| s.clone()).unwrap_or_default()
+    }
+
+    /// 是否已订阅该交易对 (与 `owns` 不同，不把未分片的空连接视为订阅全部)
+    fn subscribed(&self, symbol: &str) -> bool {
+        self.keys.read().map(|k| k.contains(&symbol_key(symbol))).unwrap_or(false)
+    }
+
+    /// 记录追加订阅的交易对
+    fn add(&self, symbols: &[String]) {
+        if let (Ok(mut list), Ok(mut keys)) = (self.symbols.write(), self.keys.write()) {
+            for symbol in symbols {
+                if keys.insert(symbol_key(symbol)) {
+                    list.push(symbol.clone());
+                }
+            }
+        }
+    }
+
+    /// 移除已退订的交易对，返回该分片实际订阅过的部分
+    fn remove(&self, symbols: &[String]) -> Vec<String> {
+        let (Ok(mut list), Ok(mut keys)) = (self.symbols.write(), self.keys.write()) else {
+            return vec![];
+        };
+        let removed: Vec<String> = symbols.iter().filter(|s| keys.remove(&symbol_key(s))).cloned().collect();
+        let dropped: HashSet<String> = removed.iter().map(|s| symbol_key(s)).collect();
+        list.retain(|s| !dropped.contains(&symbol_key(s)));
+        removed
+    }
+}
+
+/// 拨号并依次发送订阅消息
+async fn dial(url: &str, frames: Vec<String>, delay: Duration) -> Result<(WsWriter, WsReader)> {
+    let (ws_stream, _) = connect_async(url).await?;
+    let (mut write, read) = ws_stream.split();
+    send_frames(&mut write, frames, delay).await?;
+    Ok((write, read))
+}
+
+/// 依次发送多条消息，相邻两条间隔 delay (交易所限制单连接的入站消息频率)
+async fn send_frames(write: &mut WsWriter, frames: Vec<String>, delay: Duration) -> Result<()> {
+    for (i, frame) in frames.into_iter().enumerate() {
+        if i > 0 && !delay.is_zero() {
+            tokio::time::sleep(delay).await;
+        }
+        write.send(Message::Text(frame)).await?;
+    }
+    Ok(())
+}
+
+/// 解析订阅确认: Binance 回复 `{"result":null,"id":N}`，失败时为 `{"error":{"code":2,"msg":"..."},"id":N}`
+fn parse_subscribe_ack(exchange: ExchangeId, msg: &str) -> Option<(u64, std::result::Result<(), String>)> {
+    if exchange != ExchangeId::Binance {
+        return None;
+    }
+    let json: serde_json::Value = serde_json::from_str(msg).ok()?;
+    let id = json.get("id")?.as_u64()?;
+    if let Some(error) = json.get("error") {
+        return Some((id, Err(error.to_string())));
+    }
+    json.get("result")?;
+    Some((id, Ok(())))
+}
+
+fn publish_ticker(
+    tx: &broadcast::Sender<Ticker>,
+    latest: &std::sync::RwLock<HashMap<String, Ticker>>,
+    ticker: Ticker,
+) {
+    if let Ok(mut latest) = latest.write() {
+        latest.insert(ticker.symbol.clone(), ticker.clone());
+    }
+    let _ = tx.send(ticker);
+}
+
+/// Binance combined 格式 (`{"stream":..,"data":..}`，订阅深度频道后启用) 取出 data
+fn binance_payload(json: &serde_json::Value) -> &serde_json::Value {
+    match (json.get("stream"), json.get("data")) {
+        (Some(_), Some(data)) => data,
+        _ => json,
+    }
+}
+
+/// 订单簿快照: 序列号不比本地新的 (乱序 / 重复) 与买卖盘交叉的丢弃，其余替换本地订单簿后广播
+fn publish_orderbook(
+    tx: &broadcast::Sender<OrderBook>,
+    books: &std::sync::RwLock<HashMap<String, OrderBook>>,
+    mut book: OrderBook,
+) {
+    book.normalize();
+    book.symbol = normalize_symbol(book.exchange, &book.symbol);
+    if book.is_crossed() {
+        debug!("{:?} {} 订单簿买卖盘交叉，丢弃", book.exchange, book.symbol);
+        return;
+    }
+    let Ok(mut books) = books.write() else {
+        return;
+    };
+    let key = symbol_key(&book.symbol);
+    if let Some(prev) = books.get(&key) {
+        if book.sequence != 0 && book.sequence <= prev.sequence {
+            return;
+        }
+    }
+    books.insert(key, book.clone());
+    drop(books);
+    let _ = tx.send(book);
+}
+
+/// 深度增量订阅消息 (Gate 每个交易对一条)
+fn build_diff_subscribe_messages(exchange: ExchangeId, symbols: &[String]) -> Vec<String> {
+    let messages = match exchange {
+        ExchangeId::Binance => {
+            let streams: Vec<String> = symbols
+                .iter()
+                .map(|s| format!("{}@depth@100ms", denormalize_symbol(ExchangeId::Binance, s).to_lowercase()))
+                .collect();
+            vec![serde_json::json!({
+                "method": "SUBSCRIBE",
+                "params": streams,
+                "id": 5
+            })]
+        }
+        ExchangeId::Gate => symbols
+            .iter()
+            .map(|s| {
+                serde_json::json!({
+                    "time": chrono::Utc::now().timestamp(),
+                    "channel": "spot.order_book_update",
+                    "event": "subscribe",
+                    "payload": [denormalize_symbol(ExchangeId::Gate, s), "100ms"]
+                })
+            })
+            .collect(),
+        ExchangeId::Mexc => {
+            let channels: Vec<String> = symbols
+                .iter()
+                .map(|s| format!("spot@public.increase.depth.v3.api@{}", denormalize_symbol(ExchangeId::Mexc, s)))
+                .collect();
+            vec![serde_json::json!({
+                "method": "SUBSCRIPTION",
+                "params": channels
+            })]
+        }
+        _ => vec![],
+    };
+    messages.into_iter().map(|m| m.to_string()).collect()
+}
+
+/// 快照拉取失败 / 与缓存增量对不上时的重试间隔
+const SNAPSHOT_RETRY_DELAY: Duration = Duration::from_secs(1);
+
+/// 全量订单簿同步 (Binance / Gate / MEXC): 增量交给 `OrderBookManager` 维护，需要时在后台拉取 REST 快照
+#[derive(Clone)]
+struct DepthSync {
+    exchange: ExchangeId,
+    manager: Arc<std::sync::Mutex<OrderBookManager>>,
+    client: reqwest::Client,
+    rest_url: String,
+    orderbook_tx: broadcast::Sender<OrderBook>,
+    books: Arc<std::sync::RwLock<HashMap<String, OrderBook>>>,
+}
+
+impl DepthSync {
+    fn begin(&self, symbol: &str) -> bool {
+        self.manager.lock().is_ok_and(|mut m| m.begin_sync(symbol))
+    }
+
+    fn restart(&self, symbol: &str) -> bool {
+        self.manager.lock().is_ok_and(|mut m| m.restart_sync(symbol))
+    }
+
+    fn tracked(&self) -> Vec<String> {
+        self.manager.lock().map(|m| m.tracked()).unwrap_or_default()
+    }
+
+    fn clear(&self) {
+        if let Ok(mut m) = self.manager.lock() {
+            m.clear();
+        }
+    }
+
+    fn on_update(&self, update: DepthUpdate) {
+        let symbol = update.symbol.clone();
+        let Ok(action) = self.manager.lock().map(|mut m| m.on_update(update)) else {
+            return;
+        };
+        self.handle(symbol, action);
+    }
+
+    fn handle(&self, symbol: String, action: SyncAction) {
+        match action {
+            SyncAction::Publish(book) => publish_orderbook(&self.orderbook_tx, &self.books, book),
+            SyncAction::Resync => self.fetch_snapshot(symbol),
+            SyncAction::None => {}
+        }
+    }
+
+    /// 后台拉取快照；失败或快照早于缓存增量时等待后重试，直到同步完成或不再跟踪该交易对
+    fn fetch_snapshot(&self, symbol: String) {
+        let this = self.clone();
+        tokio::spawn(async move {
+            loop {
+                match fetch_depth_snapshot(&this.client, this.exchange, &this.rest_url, &symbol).await {
+                    Ok(snapshot) => {
+                        let Ok(action) = this.manager.lock().map(|mut m| m.on_snapshot(&symbol, snapshot)) else {
+                            return;
+                        };
+                        if !matches!(action, SyncAction::Resync) {
+                            this.handle(symbol, action);
+                            return;
+                        }
+                    }
+                    Err(e) => warn!("{:?} {} 拉取深度快照失败: {}", this.exchange, symbol, e),
+                }
+                tokio::time::sleep(SNAPSHOT_RETRY_DELAY).await;
+                if !this.manager.lock().is_ok_and(|m| m.is_syncing(&symbol)) {
+                    return;
+                }
+            }
+        });
+    }
+}
+
+/// 已补订阅报价频道的交易对: ticker 缺失的买一卖一沿用报价频道的最新值
+fn fill_quote(
+    latest: &std::sync::RwLock<HashMap<String, Ticker>>,
+    quoted: &std::sync::RwLock<HashSet<String>>,
+    ticker: &mut Ticker,
+) {
+    if has_quote(ticker) {
+        return;
+    }
+    if !quoted.read().is_ok_and(|quoted| quoted.contains(&ticker.symbol)) {
+        return;
+    }
+    if let Some(prev) = latest.read().ok().and_then(|latest| latest.get(&ticker.symbol).cloned()) {
+        ticker.bid = prev.bid;
+        ticker.ask = prev.ask;
+        ticker.bid_size = prev.bid_size;
+        ticker.ask_size = prev.ask_size;
+    }
+}
+
+/// 报价频道更新: 以该交易对最近一条 ticker 为基础更新买一卖一后广播 (尚无 ticker 时等待)
+fn publish_quote(
+    tx: &broadcast::Sender<Ticker>,
+    latest: &std::sync::RwLock<HashMap<String, Ticker>>,
+    quote: Quote,
+) {
+    let Some(mut ticker) = latest.read().ok().and_then(|latest| latest.get(&quote.symbol).cloned()) else {
+        return;
+    };
+    ticker.bid = quote.bid;
+    ticker.ask = quote.ask;
+    ticker.bid_size = quote.bid_size;
+    ticker.ask_size = quote.ask_size;
+    ticker.timestamp = quote
+        .timestamp
+        .unwrap_or_else(|| chrono::Utc::now().timestamp_millis())
+        .max(ticker.timestamp);
+    ticker.received_at = chrono::Utc::now().timestamp_millis();
+    publish_ticker(tx, latest, ticker);
+}
+
+/// 交易所配置
+#[derive(Debug, Clone, Deserialize)]
+#[allow(dead_code)]
+pub struct ExchangeConfig {
+    pub id: ExchangeId,
+    pub api_key: String,
+    pub api_secret: String,
+    pub passphrase: Option<String>,
+    pub enabled: bool,
+    /// 是否允许在该交易所下单；关闭时只订阅行情供策略参考 (observe-only)
+    #[serde(default = "default_execution_enabled")]
+    pub execution_enabled: bool,
+    /// API Key 类型 (决定请求签名方式)
+    #[serde(default)]
+    pub key_type: KeyType,
+    /// 覆盖内置 WebSocket 地址 (wss)
+    #[serde(default)]
+    pub ws_url: Option<String>,
+    /// 覆盖内置 REST 基础地址 (https)
+    #[serde(default)]
+    pub rest_url: Option<String>,
+    /// 启动时订阅的交易对
+    #[serde(default)]
+    pub symbols: Vec<String>,
+}
+
+impl ExchangeConfig {
+    /// 实际使用的 WebSocket 地址
+    pub fn ws_endpoint(&self) -> &str {
+        self.ws_url.as_deref().unwrap_or(self.id.ws_url())
+    }
+}
+
+fn default_execution_enabled() -> bool {
+    true
+}
+
+/// 启动时首次连接的策略
+#[derive(Debug, Clone, Copy)]
+pub struct ConnectPolicy {
+    /// 每个交易所首次连接的尝试次数 (间隔按 `ReconnectPolicy` 退避)
+    pub attempts: u32,
+    /// 任一启用的交易所连接失败即中止启动；关闭时跳过失败的交易所 (全部失败仍中止)
+    pub require_all: bool,
+}
+
+impl Default for ConnectPolicy {
+    fn default() -> Self {
+        Self {
+            attempts: 3,
+            require_all: false,
+        }
+    }
+}
+
+impl ConnectPolicy {
+    /// 从 ENGINE_WS_CONNECT_ATTEMPTS / ENGINE_WS_REQUIRE_ALL 读取
+    pub fn from_env() -> Self {
+        let mut policy = Self::default();
+        if let Some(n) = std::env::var("ENGINE_WS_CONNECT_ATTEMPTS").ok().and_then(|v| v.parse::<u32>().ok()) {
+            policy.attempts = n.max(1);
+        }
+        if let Ok(v) = std::env::var("ENGINE_WS_REQUIRE_ALL") {
+            policy.require_all = matches!(v.as_str(), "1" | "true" | "True");
+        }
+        policy
+    }
+}
+
+/// 连接所有启用的交易所: 创建连接并按配置的交易对启动 WebSocket，各连接的最近行情时间记入 `clock`。
+/// 首次连接失败时按退避重试；仍失败的交易所记错误后跳过 (`require_all` 时中止)，没有任何交易所连上时返回错误
+pub async fn connect_all(
+    configs: &[ExchangeConfig],
+    clock: &TickerClock,
+) -> Result<HashMap<ExchangeId, Arc<ExchangeConnection>>> {
+    connect_all_with(configs, ConnectPolicy::from_env(), clock).await
+}
+
+pub async fn connect_all_with(
+    configs: &[ExchangeConfig],
+    policy: ConnectPolicy,
+    clock: &TickerClock,
+) -> Result<HashMap<ExchangeId, Arc<ExchangeConnection>>> {
+    let mut connections = HashMap::new();
+    let mut failed = Vec::new();
+
+    for config in configs.iter().filter(|c| c.enabled) {
+        let conn = match ExchangeConnection::new(config.id).await {
+            Ok(conn) => conn,
+            Err(e) => {
+                error!("创建 {:?} 连接失败: {}", config.id, e);
+                failed.push(config.id);
+                continue;
+            }
+        };
+        let mut conn = conn.with_ws_url(config.ws_endpoint()).with_ticker_clock(clock.clone());
+        if let Some(rest_url) = &config.rest_url {
+            conn = conn.with_rest_url(rest_url.clone());
+        }
+        info!("创建 {:?} 连接成功 ({})", config.id, conn.ws_url());
+        let mut attempt = 0;
+        loop {
+            match conn.start(config.symbols.clone()).await {
+                Ok(()) => {
+                    connections.insert(config.id, Arc::new(conn));
+                    break;
+                }
+                Err(e) if attempt + 1 < policy.attempts => {
+                    let delay = conn.reconnect_policy.delay(attempt);
+                    warn!(
+                        "{:?} WebSocket 第 {} 次连接失败，{} 毫秒后重试: {}",
+                        config.id,
+                        attempt + 1,
+                        delay.as_millis(),
+                        e
+                    );
+                    tokio::time::sleep(delay).await;
+                    attempt += 1;
+                }
+                Err(e) => {
+                    error!("{:?} WebSocket 连接 {} 次均失败，跳过该交易所: {}", config.id, attempt + 1, e);
+                    failed.push(config.id);
+                    break;
+                }
+            }
+        }
+    }
+
+    if policy.require_all && !failed.is_empty() {
+        for conn in connections.values() {
+            conn.stop().await;
+        }
+        anyhow::bail!("交易所 {:?} 连接失败 (ENGINE_WS_REQUIRE_ALL 已开启)", failed);
+    }
+    if connections.is_empty() && !failed.is_empty() {
+        anyhow::bail!("所有启用的交易所均连接失败: {:?}", failed);
+    }
+    Ok(connections)
+}
+
+/// 测试用的行情构造器与本地 WebSocket 服务
+#[cfg(test)]
+pub mod testing {
+    use super::{ExchangeId, Ticker};
+    use futures_util::{SinkExt, StreamExt};
+    use tokio_tungstenite::tungstenite::Message;
+
+    /// 默认 Binance、成交量 1000、时间戳 1000，报价由 `price` 或 `quote` 设置
+    pub struct TickerBuilder(Ticker);
+
+    pub fn ticker(symbol: &str) -> TickerBuilder {
+        TickerBuilder(Ticker {
+            exchange: ExchangeId::Binance,
+            symbol: symbol.to_string(),
+            bid: 0.0,
+            ask: 0.0,
+            bid_size: 0.0,
+            ask_size: 0.0,
+            last: 0.0,
+            volume: 1000.0,
+            timestamp: 1_000,
+            received_at: 0,
+        })
+    }
+
+    impl TickerBuilder {
+        pub fn exchange(mut self, exchange: ExchangeId) -> Self {
+            self.0.exchange = exchange;
+            self
+        }
+
+        /// 最新价为 `price`，买一 / 卖一各偏离 0.01
+        pub fn price(mut self, price: f64) -> Self {
+            self.0.bid = price - 0.01;
+            self.0.ask = price + 0.01;
+            self.0.last = price;
+            self
+        }
+
+        /// 买一 / 卖一，最新价取买一
+        pub fn quote(mut self, bid: f64, ask: f64) -> Self {
+            self.0.bid = bid;
+            self.0.ask = ask;
+            self.0.last = bid;
+            self
+        }
+
+        /// 买一 / 卖一挂单量
+        pub fn size(mut self, size: f64) -> Self {
+            self.0.bid_size = size;
+            self.0.ask_size = size;
+            self
+        }
+
+        pub fn volume(mut self, volume: f64) -> Self {
+            self.0.volume = volume;
+            self
+        }
+
+        pub fn at(mut self, timestamp: i64) -> Self {
+            self.0.timestamp = timestamp;
+            self
+        }
+
+        pub fn build(self) -> Ticker {
+            self.0
+        }
+    }
+
+    /// 本地 WebSocket 服务: 接受任意条连接，把每条连接收到的文本消息按 (连接序号, 消息) 转发出来；
+    /// 每条连接收到第一条消息 (订阅) 后依次推送 `push`
+    pub async fn recording_server(push: Vec<String>) -> (String, tokio::sync::mpsc::UnboundedReceiver<(usize, String)>) {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+        tokio::spawn(async move {
+            let mut index = 0;
+            while let Ok((stream, _)) = listener.accept().await {
+                let tx = tx.clone();
+                let mut push = push.clone();
+                tokio::spawn(async move {
+                    let mut ws = tokio_tungstenite::accept_async(stream).await.unwrap();
+                    while let Some(Ok(msg)) = ws.next().await {
+                        if let Message::Text(text) = msg {
+                            let _ = tx.send((index, text.to_string()));
+                            for frame in push.drain(..) {
+                                ws.send(Message::Text(frame)).await.unwrap();
+                            }
+                        }
+                    }
+                });
+                index += 1;
+            }
+        });
+        (format!("ws://{}", addr), rx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use testing::recording_server;
+
+    fn exchange_config(id: ExchangeId, ws_url: Option<String>) -> ExchangeConfig {
+        ExchangeConfig {
+            id,
+            api_key: String::new(),
+            api_secret: String::new(),
+            passphrase: None,
+            enabled: true,
+            execution_enabled: false,
+            key_type: KeyType::Hmac,
+            ws_url,
+            rest_url: None,
+            symbols: vec!["BTC/USDT".to_string()],
+        }
+    }
+
+    #[test]
+    fn ws_url_override_is_validated_and_preferred() {
+        assert_eq!(
+            validate_endpoint(" wss://ws.okx.example:8443/ws/v5/public/ ", "wss").unwrap(),
+            "wss://ws.okx.example:8443/ws/v5/public"
+        );
+        assert!(validate_endpoint("https://ws.okx.example", "wss").is_err());
+        assert!(validate_endpoint("ws://ws.okx.example", "wss").is_err());
+        assert!(validate_endpoint("wss://", "wss").is_err());
+        assert!(validate_endpoint("not a url", "wss").is_err());
+
+        let default = exchange_config(ExchangeId::Okx, None);
+        assert_eq!(default.ws_endpoint(), ExchangeId::Okx.ws_url());
+        let overridden = exchange_config(ExchangeId::Okx, Some("wss://okx.colo.example/ws".to_string()));
+        assert_eq!(overridden.ws_endpoint(), "wss://okx.colo.example/ws");
+    }
+
+    #[tokio::test]
+    async fn connect_all_dials_the_overridden_ws_url() {
+        let (url, _closed) = quiet_server(true).await;
+        let policy = ConnectPolicy {
+            attempts: 1,
+            require_all: true,
+        };
+        let configs = [exchange_config(ExchangeId::Okx, Some(url.clone()))];
+        let connections = connect_all_with(&configs, policy, &TickerClock::default()).await.unwrap();
+        let conn = &connections[&ExchangeId::Okx];
+        assert_eq!(conn.ws_url(), url);
+        assert!(conn.is_connected());
+        conn.stop().await;
+    }
+
+    #[test]
+    fn reconnect_backoff_doubles_up_to_the_cap_with_jitter() {
+        let policy = ReconnectPolicy {
+            initial: Duration::from_millis(100),
+            max: Duration::from_millis(1_000),
+            max_reconnect_attempts: None,
+        };
+        // 100 -> 200 -> 400 -> 800 -> 1000 (封顶)，每次附加 ±20% 抖动
+        for (attempt, base) in [(0, 100.0), (1, 200.0), (2, 400.0), (3, 800.0), (4, 1_000.0), (40, 1_000.0)] {
+            let delays: Vec<f64> = (0..50).map(|_| policy.delay(attempt).as_secs_f64() * 1_000.0).collect();
+            assert!(
+                delays.iter().all(|d| *d >= base * 0.8 - 1e-6 && *d <= base * 1.2 + 1e-6),
+                "attempt {}: {:?}",
+                attempt,
+                delays
+            );
+            assert!(delays.iter().any(|d| (d - delays[0]).abs() > 1e-6), "attempt {} 没有抖动", attempt);
+        }
+    }
+
+    fn message(frame: &str) -> serde_json::Value {
+        serde_json::from_str(frame).unwrap()
+    }
+
+    #[test]
+    fn gate_subscribe_uses_spot_tickers_channel() {
+        let symbols = vec!["BTC/USDT".to_string(), "ETH/USDT".to_string()];
+        let frame = message(&ExchangeConnection::build_ticker_message(ExchangeId::Gate, &symbols, true, 1));
+        assert_eq!(frame["channel"], "spot.tickers");
+        assert_eq!(frame["event"], "subscribe");
+        assert_eq!(frame["payload"], serde_json::json!(["BTC_USDT", "ETH_USDT"]));
+        assert!(frame["time"].as_i64().unwrap() > 0);
+    }
+
+    #[test]
+    fn gate_ticker_frame_is_parsed_and_ack_ignored() {
+        let update = r#"{"time":1606292218,"time_ms":1606292218231,"channel":"spot.tickers","event":"update","result":{"currency_pair":"BTC_USDT","last":"19106.55","lowest_ask":"19108.71","highest_bid":"19106.55","change_percentage":"3.66","base_volume":"2811.3042155865","quote_volume":"53441606.52","high_24h":"19417.74","low_24h":"18434.21"}}"#;
+        let ticker = ExchangeConnection::parse_ticker(ExchangeId::Gate, update).unwrap();
+        assert_eq!(ticker.symbol, "BTC_USDT");
+        assert_eq!(normalize_symbol(ExchangeId::Gate, &ticker.symbol), "BTC/USDT");
+        assert_eq!(ticker.bid, 19106.55);
+        assert_eq!(ticker.ask, 19108.71);
+        assert_eq!(ticker.last, 19106.55);
+        assert_eq!(ticker.volume, 2811.3042155865);
+        assert_eq!(ticker.timestamp, 1606292218231);
+
+        let ack = r#"{"time":1606292218,"time_ms":1606292218231,"channel":"spot.tickers","event":"subscribe","result":{"status":"success"}}"#;
+        assert!(ExchangeConnection::parse_ticker(ExchangeId::Gate, ack).is_none());
+    }
+
+    #[test]
+    fn bitget_subscribe_lists_one_arg_per_symbol() {
+        let symbols = vec!["BTC/USDT".to_string(), "ETH/USDT".to_string()];
+        let frame = message(&ExchangeConnection::build_ticker_message(ExchangeId::Bitget, &symbols, true, 1));
+        assert_eq!(
+            frame,
+            serde_json::json!({"op": "subscribe", "args": [
+                {"instType": "sp", "channel": "ticker", "instId": "BTCUSDT"},
+                {"instType": "sp", "channel": "ticker", "instId": "ETHUSDT"},
+            ]})
+        );
+    }
+
+    #[test]
+    fn bitget_ticker_round_trips_and_acks_are_ignored() {
+        let push = r#"{"action":"snapshot","arg":{"instType":"sp","channel":"ticker","instId":"BTCUSDT"},"data":[{"instId":"BTCUSDT","last":"34560.25","bidPr":"34560.2","askPr":"34560.3","bidSz":"0.51","askSz":"1.2","baseVolume":"1234.5","quoteVolume":"42662000.1","ts":"1625115030001"}],"ts":1625115030000}"#;
+        let ticker = ExchangeConnection::parse_ticker(ExchangeId::Bitget, push).unwrap();
+        assert_eq!(normalize_symbol(ExchangeId::Bitget, &ticker.symbol), "BTC/USDT");
+        assert_eq!((ticker.bid, ticker.ask, ticker.last), (34560.2, 34560.3, 34560.25));
+        assert_eq!((ticker.bid_size, ticker.ask_size), (0.51, 1.2));
+        assert_eq!(ticker.volume, 1234.5);
+        assert_eq!(ticker.timestamp, 1625115030000);
+
+        let ack = r#"{"event":"subscribe","arg":{"instType":"sp","channel":"ticker","instId":"BTCUSDT"}}"#;
+        assert!(ExchangeConnection::parse_ticker(ExchangeId::Bitget, ack).is_none());
+        assert!(ExchangeConnection::parse_ticker(ExchangeId::Bitget, "pong").is_none());
+    }
+
+    #[tokio::test]
+    async fn symbols_are_sharded_across_connections() {
+        assert_eq!(parse_per_exchange("binance:200,okx:2,*:150", ExchangeId::Okx), Some(2));
+        assert_eq!(parse_per_exchange("binance:200,*:150", ExchangeId::Okx), Some(150));
+        assert_eq!(parse_per_exchange("300", ExchangeId::Okx), Some(300));
+
+        let (url, mut frames) = recording_server(vec![]).await;
+        let conn = ExchangeConnection::new(ExchangeId::Okx).await.unwrap().with_ws_url(url).with_shard_size(2);
+        let symbols: Vec<String> = ["BTC/USDT", "ETH/USDT", "SOL/USDT", "XRP/USDT", "BNB/USDT"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        conn.start(symbols).await.unwrap();
+        assert_eq!(conn.shard_count(), 3);
+        assert_eq!(conn.subscribed_count(), 5);
+        assert!(conn.is_connected());
+
+        // 每条连接只订阅自己分到的交易对: 2 + 2 + 1
+        let mut per_connection: HashMap<usize, Vec<String>> = HashMap::new();
+        while per_connection.values().map(Vec::len).sum::<usize>() < 5 {
+            let (index, text) = tokio::time::timeout(Duration::from_secs(1), frames.recv()).await.unwrap().unwrap();
+            let frame: serde_json::Value = serde_json::from_str(&text).unwrap();
+            if frame["op"] != "subscribe" {
+                continue;
+            }
+            for arg in frame["args"].as_array().unwrap() {
+                per_connection.entry(index).or_default().push(arg["instId"].as_str().unwrap().to_string());
+            }
+        }
+        let mut sizes: Vec<usize> = per_connection.values().map(Vec::len).collect();
+        sizes.sort();
+        assert_eq!(sizes, [1, 2, 2]);
+        let mut all: Vec<String> = per_connection.into_values().flatten().collect();
+        all.sort();
+        assert_eq!(all, ["BNB-USDT", "BTC-USDT", "ETH-USDT", "SOL-USDT", "XRP-USDT"]);
+        conn.stop().await;
+    }
+
+    #[test]
+    fn gate_unsubscribe_and_second_sample_payload() {
+        let symbols = vec!["ETH/USDT".to_string()];
+        let frame = message(&ExchangeConnection::build_ticker_message(ExchangeId::Gate, &symbols, false, 1));
+        assert_eq!(frame["channel"], "spot.tickers");
+        assert_eq!(frame["event"], "unsubscribe");
+        assert_eq!(frame["payload"], serde_json::json!(["ETH_USDT"]));
+
+        // Gate 文档中的另一条推送样例 (小数位更多、成交额为 0)
+        let update = r#"{"time":1669107766,"time_ms":1669107766406,"channel":"spot.tickers","event":"update","result":{"currency_pair":"ETH_USDT","last":"1162.83","lowest_ask":"1162.84","highest_bid":"1162.8","change_percentage":"-0.7461","base_volume":"0","quote_volume":"0","high_24h":"1185.67","low_24h":"1130.07"}}"#;
+        let ticker = ExchangeConnection::parse_ticker(ExchangeId::Gate, update).unwrap();
+        assert_eq!(normalize_symbol(ExchangeId::Gate, &ticker.symbol), "ETH/USDT");
+        assert_eq!((ticker.bid, ticker.ask, ticker.last), (1162.8, 1162.84, 1162.83));
+        assert_eq!(ticker.volume, 0.0);
+        assert_eq!(ticker.timestamp, 1669107766406);
+
+        // 心跳回复与错误回复都不是行情
+        let pong = r#"{"time":1669107766,"time_ms":1669107766406,"channel":"spot.pong","event":"","result":null}"#;
+        assert!(ExchangeConnection::parse_ticker(ExchangeId::Gate, pong).is_none());
+        let error = r#"{"time":1669107766,"channel":"spot.tickers","event":"subscribe","error":{"code":2,"message":"unknown currency pair BAD_USDT"},"result":null}"#;
+        assert!(ExchangeConnection::parse_ticker(ExchangeId::Gate, error).is_none());
+    }
+
+    #[tokio::test]
+    async fn gate_connection_subscribes_and_publishes_parsed_tickers() {
+        let update = r#"{"time":1606292218,"time_ms":1606292218231,"channel":"spot.tickers","event":"update","result":{"currency_pair":"BTC_USDT","last":"19106.55","lowest_ask":"19108.71","highest_bid":"19106.55","base_volume":"2811.3042155865"}}"#;
+        let ack = r#"{"time":1606292218,"channel":"spot.tickers","event":"subscribe","result":{"status":"success"}}"#;
+        let (url, mut frames) = recording_server(vec![ack.to_string(), update.to_string()]).await;
+        let conn = ExchangeConnection::new(ExchangeId::Gate).await.unwrap().with_ws_url(url);
+        let mut tickers = conn.subscribe_tickers();
+        conn.start(vec!["BTC/USDT".to_string()]).await.unwrap();
+
+        let (_, subscribe) = tokio::time::timeout(Duration::from_secs(1), frames.recv()).await.unwrap().unwrap();
+        let subscribe = message(&subscribe);
+        assert_eq!(subscribe["event"], "subscribe");
+        assert_eq!(subscribe["payload"], serde_json::json!(["BTC_USDT"]));
+
+        // 订阅确认被忽略，只发布一条已规范化交易对的行情
+        let ticker = tokio::time::timeout(Duration::from_secs(1), tickers.recv()).await.unwrap().unwrap();
+        assert_eq!(ticker.exchange, ExchangeId::Gate);
+        assert_eq!(ticker.symbol, "BTC/USDT");
+        assert_eq!((ticker.bid, ticker.ask), (19106.55, 19108.71));
+        assert!(tickers.try_recv().is_err());
+        conn.stop().await;
+    }
+
+    #[test]
+    fn mexc_subscribe_uses_book_ticker_channels() {
+        let symbols = vec!["BTC/USDT".to_string(), "1000SHIB/USDT".to_string()];
+        let frame = message(&ExchangeConnection::build_ticker_message(ExchangeId::Mexc, &symbols, true, 1));
+        assert_eq!(
+            frame,
+            serde_json::json!({"method": "SUBSCRIPTION", "params": [
+                "spot@public.bookTicker.v3.api@BTCUSDT",
+                "spot@public.bookTicker.v3.api@1000SHIBUSDT",
+            ]})
+        );
+        let frame = message(&ExchangeConnection::build_ticker_message(ExchangeId::Mexc, &symbols[..1], false, 2));
+        assert_eq!(frame["method"], "UNSUBSCRIPTION");
+    }
+
+    #[test]
+    fn mexc_book_ticker_uses_mid_price_and_zero_volume() {
+        let push = r#"{"c":"spot@public.bookTicker.v3.api@BTCUSDT","d":{"A":"34.7","B":"1.5","a":"20863.82","b":"20863.80"},"s":"BTCUSDT","t":1661932660144}"#;
+        let ticker = ExchangeConnection::parse_ticker(ExchangeId::Mexc, push).unwrap();
+        assert_eq!(normalize_symbol(ExchangeId::Mexc, &ticker.symbol), "BTC/USDT");
+        assert_eq!((ticker.bid, ticker.ask), (20863.80, 20863.82));
+        assert_eq!((ticker.bid_size, ticker.ask_size), (1.5, 34.7));
+        assert!((ticker.last - 20863.81).abs() < 1e-9);
+        assert_eq!(ticker.volume, 0.0);
+        assert_eq!(ticker.timestamp, 1661932660144);
+
+        let ack = r#"{"id":0,"code":0,"msg":"spot@public.bookTicker.v3.api@BTCUSDT"}"#;
+        assert!(ExchangeConnection::parse_ticker(ExchangeId::Mexc, ack).is_none());
+        assert!(ExchangeConnection::parse_ticker(ExchangeId::Mexc, r#"{"id":0,"code":0,"msg":"PONG"}"#).is_none());
+    }
+
+    #[tokio::test]
+    async fn reconnect_stops_after_the_attempt_bound() {
+        // 只接受一条连接: 收到订阅后关闭，之后端口不再监听，重连全部失败
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            drop(listener);
+            let mut ws = tokio_tungstenite::accept_async(stream).await.unwrap();
+            let _ = ws.next().await;
+            let _ = ws.close(None).await;
+        });
+        let conn = ExchangeConnection::new(ExchangeId::Okx)
+            .await
+            .unwrap()
+            .with_ws_url(url)
+            .with_reconnect_policy(ReconnectPolicy {
+                initial: Duration::from_millis(10),
+                max: Duration::from_millis(20),
+                max_reconnect_attempts: Some(3),
+            });
+        conn.start(vec!["BTC/USDT".to_string()]).await.unwrap();
+
+        let deadline = tokio::time::Instant::now() + Duration::from_secs(3);
+        while !conn.tasks.lock().unwrap().iter().all(|t| t.is_finished()) {
+            assert!(tokio::time::Instant::now() < deadline, "分片应在重连次数用尽后退出");
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert_eq!(conn.reconnect_counts(), (3, 0));
+        assert!(!conn.is_connected());
+    }
+
+    #[test]
+    fn mexc_keepalive_pings_within_thirty_seconds() {
+        assert_eq!(message(&ExchangeId::Mexc.keepalive_message().unwrap()), serde_json::json!({"method": "PING"}));
+        assert!(ExchangeId::Mexc.keepalive_interval() < Duration::from_secs(30));
+    }
+
+    #[tokio::test]
+    async fn mexc_only_config_streams_book_tickers() {
+        let push = r#"{"c":"spot@public.bookTicker.v3.api@BTCUSDT","d":{"A":"34.7","B":"1.5","a":"20863.82","b":"20863.80"},"s":"BTCUSDT","t":1661932660144}"#;
+        let (url, mut frames) = recording_server(vec![push.to_string()]).await;
+        // 与只设置 MEXC_API_KEY / MEXC_API_SECRET 时 load_exchange_configs 产出的配置一致
+        let mut config = exchange_config(ExchangeId::Mexc, Some(url));
+        config.api_key = "mexc-key".to_string();
+        config.api_secret = "mexc-secret".to_string();
+        config.execution_enabled = true;
+        let policy = ConnectPolicy {
+            attempts: 1,
+            require_all: true,
+        };
+        let connections = connect_all_with(&[config], policy, &TickerClock::default()).await.unwrap();
+        let conn = &connections[&ExchangeId::Mexc];
+
+        let (_, frame) = tokio::time::timeout(Duration::from_secs(2), frames.recv()).await.unwrap().unwrap();
+        assert_eq!(
+            message(&frame),
+            serde_json::json!({"method": "SUBSCRIPTION", "params": ["spot@public.bookTicker.v3.api@BTCUSDT"]})
+        );
+        let ticker = tokio::time::timeout(Duration::from_secs(2), async {
+            loop {
+                if let Some(ticker) = conn.latest_tickers().pop() {
+                    return ticker;
+                }
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .unwrap();
+        assert_eq!(ticker.exchange, ExchangeId::Mexc);
+        assert_eq!(ticker.symbol, "BTC/USDT");
+        assert_eq!((ticker.bid, ticker.ask), (20863.80, 20863.82));
+        conn.stop().await;
+    }
+
+    #[test]
+    fn depth_frames_share_one_order_book_shape() {
+        let binance = r#"{"stream":"btcusdt@depth20@100ms","data":{"lastUpdateId":160,"bids":[["29999.0","0"],["30000.0","1.5"],["29998.0","2"]],"asks":[["30002.0","3"],["30001.0","0.5"]]}}"#;
+        let okx = r#"{"arg":{"channel":"books5","instId":"BTC-USDT"},"data":[{"asks":[["30001.5","0.8","0","3"]],"bids":[["30000.5","1.2","0","2"]],"ts":"1597026383085","seqId":123}]}"#;
+        let (tx, mut rx) = broadcast::channel(8);
+
+        // 两家交易所的深度推送解析为同一 OrderBook: 交易对统一为 BASE/QUOTE，档位排序并去掉数量为 0 的档位
+        // 每条连接各自保存最新订单簿
+        let mut latest = HashMap::new();
+        for (id, frame) in [(ExchangeId::Binance, binance), (ExchangeId::Okx, okx)] {
+            let books = latest.entry(id).or_insert_with(|| std::sync::RwLock::new(HashMap::new()));
+            let book = ExchangeConnection::parse_orderbook(id, frame).unwrap();
+            publish_orderbook(&tx, books, book);
+            let book = rx.try_recv().unwrap();
+            assert_eq!((book.exchange, book.symbol.as_str()), (id, "BTC/USDT"));
+            assert!(book.bids.windows(2).all(|w| w[0].price > w[1].price));
+            assert!(book.asks.windows(2).all(|w| w[0].price < w[1].price));
+            assert!(book.bids.iter().chain(&book.asks).all(|l| l.size > 0.0));
+        }
+        let books = &latest[&ExchangeId::Okx];
+        let book = latest_book(books, "BTC/USDT");
+        assert_eq!(book.exchange, ExchangeId::Okx);
+        assert_eq!((book.best_bid().unwrap().price, book.best_ask().unwrap().price), (30000.5, 30001.5));
+        assert_eq!((book.sequence, book.timestamp), (123, 1597026383085));
+
+        // 序列号不大于上一份的快照与买卖盘交叉的快照都被丢弃
+        let mut older = book.clone();
+        older.sequence = 100;
+        older.bids[0].price = 30000.9;
+        publish_orderbook(&tx, books, older);
+        let mut crossed = book.clone();
+        crossed.sequence = 200;
+        crossed.bids[0].price = 30002.0;
+        publish_orderbook(&tx, books, crossed);
+        assert!(rx.try_recv().is_err());
+        assert_eq!(latest_book(books, "BTC-USDT").sequence, 123);
+
+        // 非深度频道不解析为订单簿
+        let ticker = r#"{"stream":"btcusdt@bookTicker","data":{"u":1,"s":"BTCUSDT","b":"1","B":"1","a":"2","A":"1"}}"#;
+        assert!(ExchangeConnection::parse_orderbook(ExchangeId::Binance, ticker).is_none());
+    }
+
+    fn latest_book(books: &std::sync::RwLock<HashMap<String, OrderBook>>, symbol: &str) -> OrderBook {
+        books.read().unwrap().get(&symbol_key(symbol)).cloned().unwrap()
+    }
+
+    fn many_symbols(count: usize) -> Vec<String> {
+        (0..count).map(|i| format!("C{}/USDT", i)).collect()
+    }
+
+    #[test]
+    fn three_hundred_symbols_are_split_into_chunked_frames() {
+        let symbols = many_symbols(300);
+        let shard = Shard::new(0, symbols.clone());
+
+        let frames: Vec<serde_json::Value> =
+            shard.ticker_frames(ExchangeId::Binance, &symbols, true, 50).iter().map(|f| message(f)).collect();
+        assert_eq!(frames.len(), 6);
+        let mut ids = HashSet::new();
+        let mut streams = vec![];
+        for frame in &frames {
+            assert_eq!(frame["method"], "SUBSCRIBE");
+            let params = frame["params"].as_array().unwrap();
+            assert_eq!(params.len(), 50);
+            streams.extend(params.iter().map(|p| p.as_str().unwrap().to_string()));
+            ids.insert(frame["id"].as_u64().unwrap());
+        }
+        assert_eq!(ids.len(), 6, "每个订阅块使用不同的请求 ID");
+        assert_eq!(streams.first().unwrap(), "c0usdt@ticker");
+        assert_eq!(streams.len(), 300);
+        assert_eq!(shard.acks.lock().unwrap().len(), 6);
+
+        let frames = shard.ticker_frames(ExchangeId::Okx, &symbols, true, 20);
+        assert_eq!(frames.len(), 15);
+        assert!(frames.iter().all(|f| message(f)["args"].as_array().unwrap().len() == 20));
+    }
+
+    #[test]
+    fn rejected_chunk_is_retried_until_the_attempt_limit() {
+        let symbols = many_symbols(3);
+        let shard = Shard::new(0, symbols.clone());
+        let frame = message(&shard.ticker_frames(ExchangeId::Binance, &symbols, true, 50)[0]);
+        let mut id = frame["id"].as_u64().unwrap();
+
+        for _ in 1..SUBSCRIBE_MAX_ATTEMPTS {
+            let retry = message(&shard.on_ack(ExchangeId::Binance, id, Err("too many params".to_string())).unwrap());
+            assert_eq!(retry["params"], frame["params"]);
+            assert_ne!(retry["id"].as_u64().unwrap(), id);
+            id = retry["id"].as_u64().unwrap();
+        }
+        assert!(shard.on_ack(ExchangeId::Binance, id, Err("too many params".to_string())).is_none());
+        assert!(shard.acks.lock().unwrap().is_empty());
+
+        let frame = message(&shard.ticker_frames(ExchangeId::Binance, &symbols, true, 50)[0]);
+        assert!(shard.on_ack(ExchangeId::Binance, frame["id"].as_u64().unwrap(), Ok(())).is_none());
+        assert!(shard.acks.lock().unwrap().is_empty());
+    }
+
+    /// 本地 WebSocket 服务: 从不推送行情；`drain` 为 true 时读取客户端消息 (收到 Close 后结束并通知)，否则完全不读
+    async fn quiet_server(drain: bool) -> (String, tokio::sync::oneshot::Receiver<()>) {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (closed_tx, closed_rx) = tokio::sync::oneshot::channel();
+        tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut ws = tokio_tungstenite::accept_async(stream).await.unwrap();
+            if drain {
+                while let Some(Ok(msg)) = ws.next().await {
+                    if msg.is_close() {
+                        break;
+                    }
+                }
+                let _ = closed_tx.send(());
+            } else {
+                tokio::time::sleep(Duration::from_secs(30)).await;
+                drop((ws, closed_tx));
+            }
+        });
+        (format!("ws://{}", addr), closed_rx)
+    }
+
+    async fn connect_quiet(url: String) -> ExchangeConnection {
+        let conn = ExchangeConnection::new(ExchangeId::Okx).await.unwrap().with_ws_url(url);
+        conn.start(vec!["BTC/USDT".to_string()]).await.unwrap();
+        assert!(conn.is_connected());
+        conn
+    }
+
+    #[tokio::test]
+    async fn stop_closes_quiet_socket_within_a_second() {
+        let (url, closed) = quiet_server(true).await;
+        let conn = connect_quiet(url).await;
+        let started = std::time::Instant::now();
+        conn.stop().await;
+        assert!(started.elapsed() < Duration::from_secs(1), "stop 用时 {:?}", started.elapsed());
+        assert!(conn.tasks.lock().unwrap().is_empty());
+        // 服务端收到 Close 帧
+        tokio::time::timeout(Duration::from_secs(1), closed).await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn stop_does_not_wait_for_an_unresponsive_peer() {
+        let (url, _closed) = quiet_server(false).await;
+        let conn = connect_quiet(url).await;
+        let started = std::time::Instant::now();
+        conn.stop().await;
+        assert!(started.elapsed() <= STOP_TIMEOUT + Duration::from_millis(200), "stop 用时 {:?}", started.elapsed());
+        assert!(conn.tasks.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn binance_kline_push_parses_every_field() {
+        let push = r#"{"stream":"btcusdt@kline_1m","data":{"e":"kline","E":1672515782136,"s":"BTCUSDT","k":{"t":1672515780000,"T":1672515839999,"s":"BTCUSDT","i":"1m","f":100,"L":200,"o":"16500.10","c":"16510.20","h":"16512.00","l":"16499.50","v":"12.345","n":101,"x":false,"q":"203800.1","V":"6.1","Q":"100700.2","B":"0"}}}"#;
+        let klines = ExchangeConnection::parse_klines(ExchangeId::Binance, push).unwrap();
+        assert_eq!(klines.len(), 1);
+        let kline = &klines[0];
+        assert_eq!((kline.symbol.as_str(), kline.interval.as_str()), ("BTCUSDT", "1m"));
+        assert_eq!(kline.open_time, 1672515780000);
+        assert_eq!((kline.open, kline.high, kline.low, kline.close), (16500.10, 16512.00, 16499.50, 16510.20));
+        assert_eq!(kline.volume, 12.345);
+        assert!(!kline.closed);
+
+        let closed = push.replace(r#""x":false"#, r#""x":true"#);
+        assert!(ExchangeConnection::parse_klines(ExchangeId::Binance, &closed).unwrap()[0].closed);
+        // 同一连接上的行情推送不是 K 线
+        let ticker = r#"{"stream":"btcusdt@bookTicker","data":{"u":400900217,"s":"BTCUSDT","b":"25.35190000","B":"31.21000000","a":"25.36520000","A":"40.66000000"}}"#;
+        assert!(ExchangeConnection::parse_klines(ExchangeId::Binance, ticker).is_none());
+    }
+
+    #[test]
+    fn okx_candle_push_parses_each_candle() {
+        let push = r#"{"arg":{"channel":"candle1m","instId":"BTC-USDT"},"data":[["1597026383085","8533.02","8553.74","8527.17","8548.26","45247","529.5858061","529.5858061","1"],["1597026443085","8548.26","8560.00","8540.10","8555.55","120","1.02","8721.3","0"]]}"#;
+        let klines = ExchangeConnection::parse_klines(ExchangeId::Okx, push).unwrap();
+        assert_eq!(klines.len(), 2);
+        let first = &klines[0];
+        assert_eq!((first.symbol.as_str(), first.interval.as_str()), ("BTC-USDT", "1m"));
+        assert_eq!(first.open_time, 1597026383085);
+        assert_eq!((first.open, first.high, first.low, first.close), (8533.02, 8553.74, 8527.17, 8548.26));
+        assert_eq!(first.volume, 45247.0);
+        assert!(first.closed);
+        assert_eq!(klines[1].open_time, 1597026443085);
+        assert!(!klines[1].closed);
+
+        let ack = r#"{"event":"subscribe","arg":{"channel":"candle1m","instId":"BTC-USDT"},"connId":"a4d3ae55"}"#;
+        assert!(ExchangeConnection::parse_klines(ExchangeId::Okx, ack).is_none());
+    }
 }
@@ -2,17 +2,34 @@
 
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
-use tracing::{error, info};
+use std::time::Duration;
+use tracing::{error, info, warn};
 
+use crate::api::SharedState;
+use crate::binance_rest::BinanceRestClient;
+use crate::confirm::PreExecutionCheck;
+use crate::db::{ExecutionStore, SharedRedis};
+use crate::engine::{Clock, SystemClock};
 use crate::exchange::{ExchangeConnection, ExchangeId};
-use crate::strategy::Signal;
+use crate::faults::FaultInjector;
+use crate::fills::FillTracker;
+use crate::instruments::{InstrumentRegistry, SharedInstruments};
+use crate::metrics::METRICS;
+use crate::netting::OrderNetter;
+use crate::okx_rest::OkxRestClient;
+use crate::positions::{OpenOrder, Position};
+use crate::rejections::ExecutionError;
+use crate::risk::{trace_requests_from_env, GLOBAL_RISK_MANAGER};
+use crate::stops::{native_stop_params, supports_native_stop, StopOrder, StopPlacement};
+use crate::strategy::{Signal, SignalAction, CORRELATION_HEADER};
+use crate::symbols::{denormalize_symbol, normalize_symbol, same_symbol, Symbol};
 use redis::AsyncCommands;
 use reqwest::Client;
 
 /// 订单方向
-#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum OrderSide {
     Buy,
     Sell,
@@ -20,7 +37,6 @@ pub enum OrderSide {
 
 /// 订单类型
 #[derive(Debug, Clone, Copy, Serialize, Deserialize)]
-#[allow(dead_code)]
 pub enum OrderType {
     Market,
     Limit,
@@ -28,14 +44,16 @@ pub enum OrderType {
 
 /// 订单请求
 #[derive(Debug, Clone, Serialize)]
-#[allow(dead_code)]
 pub struct OrderRequest {
     pub exchange: ExchangeId,
     pub symbol: String,
     pub side: OrderSide,
     pub order_type: OrderType,
     pub amount: f64,
+    /// 限价单的限价；信号腿的市价单为信号价 (只用于最小名义金额校验，不随单发送)
     pub price: Option<f64>,
+    /// 客户端订单号 (信号腿由 correlation_id 与腿序号生成)；超时 / 5xx 后据此查询订单是否已受理，避免重复下单
+    pub client_order_id: Option<String>,
 }
 
 /// 订单响应
@@ -48,8 +66,12 @@ pub struct OrderResponse {
     pub status: OrderStatus,
     pub filled_amount: f64,
     pub avg_price: f64,
+    /// 手续费 (计价币)
     pub fee: f64,
     pub latency_ms: u64,
+    /// 以计价币、基础币之外的币种 (如 BNB) 抵扣的手续费: (币种, 数量)，未折算、不计入 `fee`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub other_fees: Vec<(String, f64)>,
 }
 
 /// 订单状态
@@ -62,6 +84,63 @@ pub enum OrderStatus {
     Failed,
 }
 
+impl OrderStatus {
+    /// 是否为终态 (完全成交 / 撤单 / 失败)
+    pub fn is_terminal(self) -> bool {
+        matches!(self, Self::Filled | Self::Cancelled | Self::Failed)
+    }
+}
+
+/// 改单方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AmendMethod {
+    /// 交易所原生改单接口 (保留订单号，价格不变时保留队列位置)
+    Native,
+    /// 撤单后按新价格 / 数量重新挂单
+    CancelReplace,
+}
+
+/// 改单结果
+#[derive(Debug, Clone, Serialize)]
+pub struct AmendResult {
+    pub method: AmendMethod,
+    /// 改单后的订单 (撤单重挂时为新订单)
+    pub order: OrderResponse,
+}
+
+/// 交易所是否支持原生改单 (价格与数量均可修改)。
+/// Binance 现货的保留优先级改单只能减少数量，统一走撤单重挂。
+pub fn supports_native_amend(exchange: ExchangeId) -> bool {
+    matches!(exchange, ExchangeId::Okx | ExchangeId::Bybit | ExchangeId::Gate)
+}
+
+/// 将改单翻译为交易所原生改单参数
+pub fn native_amend_params(order: &OpenOrder, new_price: f64, new_amount: f64) -> Option<serde_json::Value> {
+    match order.exchange {
+        ExchangeId::Okx => Some(serde_json::json!({
+            "instId": denormalize_symbol(ExchangeId::Okx, &order.symbol),
+            "ordId": order.order_id,
+            "newPx": new_price.to_string(),
+            "newSz": new_amount.to_string(),
+        })),
+        ExchangeId::Bybit => Some(serde_json::json!({
+            "category": "spot",
+            "symbol": denormalize_symbol(ExchangeId::Bybit, &order.symbol),
+            "orderId": order.order_id,
+            "price": new_price.to_string(),
+            "qty": new_amount.to_string(),
+        })),
+        ExchangeId::Gate => Some(serde_json::json!({
+            "currency_pair": denormalize_symbol(ExchangeId::Gate, &order.symbol),
+            "order_id": order.order_id,
+            "price": new_price.to_string(),
+            "amount": new_amount.to_string(),
+        })),
+        _ => None,
+    }
+}
+
 /// 执行结果
 #[derive(Debug, Clone, Serialize)]
 pub struct ExecutionResult {
@@ -72,15 +151,97 @@ pub struct ExecutionResult {
     pub success: bool,
 }
 
+impl ExecutionResult {
+    /// 由订单回报汇总执行结果 (手续费按去重后的回报累计)
+    pub fn from_orders(signal: Signal, orders: Vec<OrderResponse>, gross_profit: f64, dedupe: bool) -> Self {
+        let orders = if dedupe { dedupe_orders(orders) } else { orders };
+        let total_fee: f64 = orders.iter().map(|o| o.fee).sum();
+        let success = !orders.is_empty()
+            && orders
+                .iter()
+                .all(|o| matches!(o.status, OrderStatus::Filled | OrderStatus::PartialFilled));
+        Self {
+            signal,
+            orders,
+            total_fee,
+            net_profit: gross_profit - total_fee,
+            success,
+        }
+    }
+}
+
+/// 按 (交易所, 订单号) 合并重复的订单回报
+///
+/// 重试时交易所可能把同一订单回报两次，直接累加会重复计算成交量与收益。
+/// 同一订单保留成交量最大 (最新) 的一条回报；订单号为空的回报不合并。
+pub fn dedupe_orders(orders: Vec<OrderResponse>) -> Vec<OrderResponse> {
+    let mut out: Vec<OrderResponse> = Vec::with_capacity(orders.len());
+    let mut index: HashMap<(ExchangeId, String), usize> = HashMap::new();
+    for order in orders {
+        if order.order_id.is_empty() {
+            out.push(order);
+            continue;
+        }
+        match index.get(&(order.exchange, order.order_id.clone())) {
+            Some(&i) => {
+                if order.filled_amount > out[i].filled_amount {
+                    out[i] = order;
+                }
+            }
+            None => {
+                index.insert((order.exchange, order.order_id.clone()), out.len());
+                out.push(order);
+            }
+        }
+    }
+    out
+}
+
 /// 订单执行器
 pub struct OrderExecutor {
     #[allow(dead_code)]
     exchanges: HashMap<ExchangeId, Arc<ExchangeConnection>>,
     // 可选: 模拟模式
     simulation_mode: bool,
-    redis: Option<redis::Client>,
+    /// 实盘下单开关 (ENGINE_EXECUTE_SIGNALS=1 且 ENGINE_LIVE_CONFIRM=CONFIRM_LIVE，启动时读取一次)
+    live_enabled: bool,
+    redis: Option<SharedRedis>,
     oms_client: Option<OmsClient>,
     user_id: Option<String>,
+    /// 交易对精度 / 最小下单量
+    instruments: SharedInstruments,
+    /// 故障注入 (模拟执行延迟尖峰)
+    faults: Option<Arc<FaultInjector>>,
+    /// 故障窗口判断用的时钟 (与引擎一致，回测时为回放时钟)
+    clock: Arc<dyn Clock>,
+    /// 按订单号合并重复回报 (ENGINE_ORDER_DEDUPE，默认开启)
+    dedupe_orders: bool,
+    /// 只提供行情、不允许下单的交易所
+    observe_only: HashSet<ExchangeId>,
+    /// 实盘下单前的二次确认
+    confirmation: Option<Arc<PreExecutionCheck>>,
+    /// 支持时优先使用原生改单 (ENGINE_NATIVE_AMEND，默认开启)
+    native_amend: bool,
+    /// 下单前的订单轧差 (ENGINE_ORDER_NETTING，默认关闭)
+    netting: Option<Arc<OrderNetter>>,
+    /// 单笔订单遇到临时错误时的重试次数 (ENGINE_LEG_RETRIES，默认 2)，与整笔执行的重试相互独立
+    leg_retries: u32,
+    /// 单笔订单首次重试前的等待，此后每次翻倍 (ENGINE_LEG_RETRY_BACKOFF_MS，默认 100)
+    leg_retry_backoff: Duration,
+    /// 决策评分扣除的每腿手续费 (ENGINE_DECISION_FEE_RATES，未设置时按策略上报的收益率评分)
+    decision_fees: Option<DecisionFees>,
+    /// Binance 签名 REST 客户端 (配置了 API Key 时实盘直接下单)
+    binance: Option<Arc<BinanceRestClient>>,
+    /// OKX 签名 REST 客户端 (配置了 API Key 与 Passphrase 时实盘直接下单)
+    okx: Option<Arc<OkxRestClient>>,
+    /// 私有成交推送的累计结果 (实盘市价单回报未到终态时据此等待最终成交)
+    fills: Option<Arc<FillTracker>>,
+    /// 模拟成交的滑点与手续费模型
+    simulation_model: SimulationModel,
+    /// 执行审计写入的数据库 (ENGINE_PERSIST_EXECUTIONS，默认开启)
+    execution_store: Option<Arc<dyn ExecutionStore>>,
+    /// 决策流 SSE (与 decisions:latest 在同一处发布)
+    decision_feed: Option<SharedState>,
 }
 
 impl OrderExecutor {
@@ -92,25 +253,182 @@ impl OrderExecutor {
         Self {
             exchanges,
             simulation_mode: true, // 默认模拟模式
-            redis,
+            live_enabled: std::env::var("ENGINE_EXECUTE_SIGNALS")
+                .map(|v| matches!(v.as_str(), "1" | "true" | "True"))
+                .unwrap_or(false)
+                && std::env::var("ENGINE_LIVE_CONFIRM").is_ok_and(|v| v == "CONFIRM_LIVE"),
+            redis: redis.map(SharedRedis::new),
             oms_client: OmsClient::from_env(),
             user_id: std::env::var("ENGINE_USER_ID").ok().filter(|v| !v.is_empty()),
+            instruments: Arc::new(std::sync::RwLock::new(InstrumentRegistry::default())),
+            faults: None,
+            clock: Arc::new(SystemClock),
+            dedupe_orders: std::env::var("ENGINE_ORDER_DEDUPE")
+                .map(|v| !matches!(v.as_str(), "0" | "false" | "False"))
+                .unwrap_or(true),
+            observe_only: HashSet::new(),
+            confirmation: None,
+            native_amend: std::env::var("ENGINE_NATIVE_AMEND")
+                .map(|v| !matches!(v.as_str(), "0" | "false" | "False"))
+                .unwrap_or(true),
+            netting: OrderNetter::from_env().map(Arc::new),
+            leg_retries: std::env::var("ENGINE_LEG_RETRIES")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(2),
+            leg_retry_backoff: Duration::from_millis(
+                std::env::var("ENGINE_LEG_RETRY_BACKOFF_MS")
+                    .ok()
+                    .and_then(|v| v.parse().ok())
+                    .unwrap_or(100),
+            ),
+            decision_fees: DecisionFees::from_env(),
+            binance: None,
+            okx: None,
+            fills: None,
+            simulation_model: SimulationModel::from_env(),
+            execution_store: None,
+            decision_feed: None,
         }
     }
 
+    /// 通过风控与确认的决策同时推送到 SSE 决策流
+    pub fn set_decision_feed(&mut self, state: SharedState) {
+        self.decision_feed = Some(state);
+    }
+
+    /// 使用引擎的时钟 (回测时故障窗口按回放时间生效)
+    pub fn set_clock(&mut self, clock: Arc<dyn Clock>) {
+        self.clock = clock;
+    }
+
+    /// 启用故障注入
+    pub fn set_fault_injector(&mut self, faults: Arc<FaultInjector>) {
+        self.faults = Some(faults);
+    }
+
+    /// 设置交易对元数据 (下单前按步长取整并校验最小下单量)
+    pub fn set_instruments(&mut self, instruments: SharedInstruments) {
+        self.instruments = instruments;
+    }
+
+    /// 设置 Binance 签名 REST 客户端
+    pub fn set_binance_client(&mut self, client: Arc<BinanceRestClient>) {
+        self.binance = Some(client);
+    }
+
+    /// 设置 OKX 签名 REST 客户端
+    pub fn set_okx_client(&mut self, client: Arc<OkxRestClient>) {
+        self.okx = Some(client);
+    }
+
+    /// 设置私有成交推送的累计结果
+    pub fn set_fill_tracker(&mut self, tracker: Arc<FillTracker>) {
+        self.fills = Some(tracker);
+    }
+
+    /// 设置执行审计写入的数据库 (每次执行完成后写入 `executions`)
+    pub fn set_execution_store(&mut self, store: Arc<dyn ExecutionStore>) {
+        self.execution_store = Some(store);
+    }
+
+    /// 启用实盘下单前的二次确认
+    pub fn set_confirmation(&mut self, check: Arc<PreExecutionCheck>) {
+        self.confirmation = Some(check);
+    }
+
+    /// 设置只提供行情、不允许下单的交易所
+    pub fn set_observe_only(&mut self, exchanges: HashSet<ExchangeId>) {
+        self.observe_only = exchanges;
+    }
+
+    /// 交易所是否允许下单
+    pub fn can_execute_on(&self, exchange: ExchangeId) -> bool {
+        !self.observe_only.contains(&exchange)
+    }
+
+    /// 设置模拟成交的滑点与手续费模型 (运行时由 ENGINE_SIM_* 在创建时读取)
+    #[cfg(test)]
+    pub fn set_simulation_model(&mut self, model: SimulationModel) {
+        self.simulation_model = model;
+    }
+
     /// 设置模拟模式
     pub fn set_simulation_mode(&mut self, enabled: bool) {
         self.simulation_mode = enabled;
     }
 
-    /// 执行套利信号
+    /// 测试中直接打开实盘下单开关，不经环境变量
+    #[cfg(test)]
+    pub(crate) fn set_live_enabled(&mut self, enabled: bool) {
+        self.live_enabled = enabled;
+    }
+
+    /// 是否为模拟模式
+    pub fn is_simulation(&self) -> bool {
+        self.simulation_mode
+    }
+
+    /// 信号实际下单的名义金额: 由预期收益 / 收益率推算，推算不出时模拟模式按默认金额成交，实盘拒绝执行 (None)
+    pub fn order_notional(&self, signal: &Signal) -> Option<f64> {
+        signal
+            .notional()
+            .or_else(|| self.simulation_mode.then_some(DEFAULT_SIM_NOTIONAL))
+    }
+
+    /// 执行套利信号，完成后写入执行审计记录 (失败的尝试同样记录；写入失败只记录日志，不影响交易)
     pub async fn execute(&self, signal: Signal) -> Result<ExecutionResult> {
+        let correlation_id = signal.correlation_id.clone();
+        let attempt = self.execution_store.as_ref().map(|_| signal.clone());
+        let result = match self.execute_signal(signal).await {
+            Ok(result) => result,
+            Err(e) => {
+                GLOBAL_RISK_MANAGER.release_exposure(&correlation_id);
+                if let Some(signal) = attempt {
+                    let failed = ExecutionResult {
+                        signal,
+                        orders: vec![],
+                        total_fee: 0.0,
+                        net_profit: 0.0,
+                        success: false,
+                    };
+                    self.record_execution(&failed, Some(&e.to_string())).await;
+                }
+                return Err(e);
+            }
+        };
+        GLOBAL_RISK_MANAGER.record_pnl(result.net_profit);
+        GLOBAL_RISK_MANAGER.settle_exposure(&result.signal, result.success);
+        self.record_execution(&result, None).await;
+        Ok(result)
+    }
+
+    async fn record_execution(&self, result: &ExecutionResult, error: Option<&str>) {
+        if let Some(store) = &self.execution_store {
+            // 审计写入失败不影响交易
+            if let Err(e) = store.record_execution(result, error).await {
+                warn!("写入执行记录失败 [{}]: {}", result.signal.correlation_id, e);
+            }
+        }
+    }
+
+    /// 按模式执行信号 (模拟成交；实盘经 OMS 执行，未配置 OMS 时按腿直接下单)
+    async fn execute_signal(&self, signal: Signal) -> Result<ExecutionResult> {
         info!(
-            "执行信号: {:?} @ {:?}, 预期收益: {:.4}%",
-            signal.strategy_type, signal.exchange, signal.profit_rate * 100.0
+            "执行信号 [{}]: {:?} @ {:?}, 预期收益: {:.4}%",
+            signal.correlation_id, signal.strategy_type, signal.exchange, signal.profit_rate * 100.0
         );
 
+        if let Some(exchange) = std::iter::once(signal.exchange)
+            .chain(signal.legs.iter().map(|leg| leg.exchange))
+            .find(|e| !self.can_execute_on(*e))
+        {
+            return Err(anyhow::anyhow!("{:?} 为只读行情交易所 (observe-only)，拒绝下单", exchange));
+        }
+
         if self.simulation_mode {
+            // 模拟模式不写 decisions:latest (OMS 从中取单)，只推送决策流
+            self.stream_decision(&self.build_decision_payload(&signal));
             return self.simulate_execution(signal).await;
         }
 
@@ -120,56 +438,106 @@ impl OrderExecutor {
             ));
         }
 
+        if let Some(check) = &self.confirmation {
+            check.confirm(&signal).await?;
+        }
+
         let decision_payload = self.build_decision_payload(&signal);
         self.publish_signal(&signal, &decision_payload).await;
         self.publish_decision(&decision_payload).await?;
 
         if let Some(client) = &self.oms_client {
             let idempotency_key = format!("engine:{}:{}", signal.strategy_id, signal.timestamp);
-            let success = client
-                .execute_latest(idempotency_key, self.simulation_mode)
+            let orders = client
+                .execute_latest(idempotency_key, &signal.correlation_id, self.simulation_mode, signal.exchange)
                 .await?;
-            return Ok(ExecutionResult {
-                signal,
-                orders: vec![],
-                total_fee: 0.0,
-                net_profit: 0.0,
-                success,
-            });
+            return Ok(self.oms_result(signal, orders));
         }
 
-        Err(anyhow::anyhow!("OMS client not configured (ENGINE_OMS_BASE/ENGINE_OMS_TOKEN)"))
+        if signal.legs.is_empty() {
+            return Err(anyhow::anyhow!(
+                "信号没有按腿信息，且 OMS client not configured (ENGINE_OMS_BASE/ENGINE_OMS_TOKEN)"
+            ));
+        }
+        self.execute_legs(signal).await
     }
 
-    /// 模拟执行
-    async fn simulate_execution(&self, signal: Signal) -> Result<ExecutionResult> {
-        let simulated_order = OrderResponse {
-            order_id: uuid::Uuid::new_v4().to_string(),
-            exchange: signal.exchange,
-            symbol: "SIMULATED".to_string(),
-            side: OrderSide::Buy,
-            status: OrderStatus::Filled,
-            filled_amount: 100.0,
-            avg_price: 1.0,
-            fee: 0.1,
-            latency_ms: 50,
+    /// OMS 执行结果按回报的成交计算盈亏: 名义金额无法由预期收益推算时取首笔成交额，
+    /// OMS 未回报手续费的订单按决策评分的费率估算；与其他执行路径一样按成交判定成功，没有成交时盈亏为 0 且记为失败
+    fn oms_result(&self, signal: Signal, mut orders: Vec<OrderResponse>) -> ExecutionResult {
+        if let Some(fees) = &self.decision_fees {
+            for order in orders.iter_mut().filter(|o| o.fee == 0.0) {
+                order.fee = order.filled_amount * order.avg_price * fees.rate(order.exchange);
+            }
+        }
+        let notional = signal
+            .notional()
+            .or_else(|| orders.first().map(|o| o.filled_amount * o.avg_price))
+            .unwrap_or(0.0);
+        let gross_profit = if orders.is_empty() {
+            0.0
+        } else {
+            realized_gross(&signal, &orders, notional)
         };
+        ExecutionResult::from_orders(signal, orders, gross_profit, self.dedupe_orders)
+    }
 
-        let result = ExecutionResult {
-            signal: signal.clone(),
-            orders: vec![simulated_order],
-            total_fee: 0.1,
-            net_profit: signal.expected_profit - 0.1,
-            success: true,
+    /// 按腿下单执行信号: 各腿按信号名义金额 / 信号价折算数量，以市价单经 `send_order` (精度取整、订单轧差、
+    /// 单笔重试) 并发发送，各腿所需币种须已有库存；毛收益按各腿成交均价相对信号价的偏离修正，有腿未成交时记为失败
+    async fn execute_legs(&self, signal: Signal) -> Result<ExecutionResult> {
+        let Some(notional) = self.order_notional(&signal) else {
+            anyhow::bail!("信号 [{}] 无法由预期收益推算下单金额", signal.correlation_id);
         };
+        let requests = leg_requests(&signal, notional)?;
+        let legs = requests.len();
+        let orders = self.execute_batch(requests).await?;
+        let gross_profit = realized_gross(&signal, &orders, notional);
+        let mut result = ExecutionResult::from_orders(signal, orders, gross_profit, self.dedupe_orders);
+        if result.orders.len() < legs {
+            warn!(
+                "执行 [{}] 只有 {}/{} 腿下单成功",
+                result.signal.correlation_id,
+                result.orders.len(),
+                legs
+            );
+            result.success = false;
+        }
+        info!(
+            "按腿执行完成 [{}]: {} 腿，净收益 ${:.4}",
+            result.signal.correlation_id, legs, result.net_profit
+        );
+        Ok(result)
+    }
+
+    /// 模拟执行: 多腿信号逐腿经 `send_order` 按滑点 / 手续费模型成交 (与实盘同一路径: 精度取整、订单轧差、
+    /// 单笔重试与故障注入)，没有按腿信息的信号按路径整体模拟
+    async fn simulate_execution(&self, signal: Signal) -> Result<ExecutionResult> {
+        let extra_latency = self
+            .faults
+            .as_ref()
+            .map(|f| f.extra_latency_ms(self.clock.now_ms()))
+            .unwrap_or(0);
+        if extra_latency > 0 {
+            tokio::time::sleep(std::time::Duration::from_millis(extra_latency)).await;
+        }
+        if !signal.legs.is_empty() {
+            return self.execute_legs(signal).await;
+        }
+        let (orders, gross_profit) =
+            self.simulation_model
+                .fill(&signal, 50 + extra_latency, |exchange, symbol| self.cached_quote(exchange, symbol))?;
+        let expected_profit = signal.expected_profit;
+        let result = ExecutionResult::from_orders(signal, orders, gross_profit, self.dedupe_orders);
+
+        info!(
+            "模拟执行完成 [{}]: 预期收益 ${:.4}，按滑点 / 手续费模型净收益 ${:.4}",
+            result.signal.correlation_id, expected_profit, result.net_profit
+        );
 
-        info!("模拟执行完成: 净收益 ${:.4}", result.net_profit);
-        
         Ok(result)
     }
 
     /// 执行市价单
-    #[allow(dead_code)]
     pub async fn market_order(
         &self,
         exchange: ExchangeId,
@@ -184,13 +552,13 @@ impl OrderExecutor {
             order_type: OrderType::Market,
             amount,
             price: None,
+            client_order_id: None,
         };
 
         self.send_order(request).await
     }
 
     /// 执行限价单
-    #[allow(dead_code)]
     pub async fn limit_order(
         &self,
         exchange: ExchangeId,
@@ -206,48 +574,361 @@ impl OrderExecutor {
             order_type: OrderType::Limit,
             amount,
             price: Some(price),
+            client_order_id: None,
         };
 
         self.send_order(request).await
     }
 
     /// 发送订单到交易所
-    #[allow(dead_code)]
     async fn send_order(&self, request: OrderRequest) -> Result<OrderResponse> {
         let _conn = self.exchanges.get(&request.exchange)
             .ok_or_else(|| anyhow::anyhow!("交易所 {:?} 未连接", request.exchange))?;
+        let request = self.apply_precision(request)?;
+        if let Some(netting) = &self.netting {
+            let mark_price = self.mark_price(request.exchange, &request.symbol);
+            return netting
+                .submit(request, mark_price, |request| self.submit_with_retry(request))
+                .await;
+        }
+        self.submit_with_retry(request).await
+    }
+
+    /// 发送单笔订单并按错误类型退避重试: 连接失败与限频时请求未被受理，直接重发；超时、5xx 等结果未知的错误
+    /// 先按客户端订单号查询，订单已受理则以查询结果为准，确认未受理才重发 (无法查询时不重试)。其余错误直接返回
+    async fn submit_with_retry(&self, request: OrderRequest) -> Result<OrderResponse> {
+        let mut attempt = 0;
+        loop {
+            let err = match self.submit_order(request.clone()).await {
+                Ok(response) => return Ok(response),
+                Err(e) => e,
+            };
+            if let Some(rejection) = err.downcast_ref::<ExecutionError>() {
+                METRICS.order_rejected(request.exchange, rejection.reason());
+            }
+            let Some(kind) = retry_kind(&err).filter(|_| attempt < self.leg_retries) else {
+                return Err(err);
+            };
+            let backoff = self.leg_retry_backoff * 2u32.saturating_pow(attempt);
+            attempt += 1;
+            warn!(
+                "{:?} {} 下单临时失败，{} 毫秒后第 {}/{} 次重试: {}",
+                request.exchange,
+                request.symbol,
+                backoff.as_millis(),
+                attempt,
+                self.leg_retries,
+                err
+            );
+            tokio::time::sleep(backoff).await;
+            if kind == RetryKind::QueryFirst {
+                match self.find_order(&request).await {
+                    Ok(Some(response)) => {
+                        info!(
+                            "{:?} {} 订单 {} 已被受理，不再重发",
+                            request.exchange, request.symbol, response.order_id
+                        );
+                        return Ok(response);
+                    }
+                    Ok(None) => {}
+                    Err(e) => {
+                        warn!(
+                            "{:?} {} 无法确认订单是否已受理，放弃重试: {}",
+                            request.exchange, request.symbol, e
+                        );
+                        return Err(err);
+                    }
+                }
+            }
+        }
+    }
+
+    /// 按客户端订单号查询订单是否已被交易所受理，未受理时返回 None (模拟模式下失败的下单不会留下订单)
+    async fn find_order(&self, request: &OrderRequest) -> Result<Option<OrderResponse>> {
+        if self.simulation_mode {
+            return Ok(None);
+        }
+        let client_order_id = request
+            .client_order_id
+            .as_deref()
+            .ok_or_else(|| anyhow::anyhow!("订单没有客户端订单号"))?;
+        match request.exchange {
+            ExchangeId::Binance => match &self.binance {
+                Some(client) => client.query_client_order(&request.symbol, client_order_id).await,
+                None => Err(anyhow::anyhow!("Binance 未配置 API Key，无法查询订单")),
+            },
+            ExchangeId::Okx => match &self.okx {
+                Some(client) => client.query_client_order(&request.symbol, client_order_id).await,
+                None => Err(anyhow::anyhow!("OKX 未配置 API Key / Passphrase，无法查询订单")),
+            },
+            exchange => Err(anyhow::anyhow!("{:?} 订单查询未实现", exchange)),
+        }
+    }
+
+    /// 交易对当前中间价 (订单轧差时内部相抵部分的成交价)
+    fn mark_price(&self, exchange: ExchangeId, symbol: &str) -> Option<f64> {
+        self.cached_quote(exchange, symbol).map(|(bid, ask)| (bid + ask) / 2.0)
+    }
 
-        // TODO: 实现真实的订单发送
-        // 1. 使用交易所 REST API 发送订单
-        // 2. 等待订单确认
-        // 3. 返回执行结果
+    /// 连接缓存的最新买一 / 卖一
+    fn cached_quote(&self, exchange: ExchangeId, symbol: &str) -> Option<(f64, f64)> {
+        let symbol = normalize_symbol(exchange, symbol);
+        self.exchanges
+            .get(&exchange)?
+            .latest_tickers()
+            .into_iter()
+            .find(|t| normalize_symbol(exchange, &t.symbol) == symbol && t.bid > 0.0 && t.ask > 0.0)
+            .map(|t| (t.bid, t.ask))
+    }
 
+    /// 发送单笔订单 (实盘目前支持 Binance、OKX REST 直连)
+    async fn submit_order(&self, request: OrderRequest) -> Result<OrderResponse> {
+        if self.simulation_mode {
+            let now = self.clock.now_ms();
+            if self
+                .faults
+                .as_ref()
+                .is_some_and(|f| f.order_error(request.exchange, &request.symbol, now))
+            {
+                return Err(ExecutionError::Transient {
+                    exchange: request.exchange,
+                    message: "injected order error".to_string(),
+                }
+                .into());
+            }
+            let quote = self.cached_quote(request.exchange, &request.symbol);
+            return self.simulation_model.fill_order(&request, quote, 30);
+        }
+
+        if !self.live_enabled() {
+            return Err(anyhow::anyhow!(
+                "live execution blocked: require ENGINE_EXECUTE_SIGNALS=1 and ENGINE_LIVE_CONFIRM=CONFIRM_LIVE"
+            ));
+        }
+
+        // 先订阅成交推送再下单，避免成交先于下单回报到达而漏掉
+        let fills = self.fills.as_ref().map(|tracker| (tracker, tracker.subscribe()));
+        let response = match request.exchange {
+            ExchangeId::Binance => match &self.binance {
+                Some(client) => client.new_order(&request).await,
+                None => Err(anyhow::anyhow!("Binance 未配置 API Key，无法实盘下单")),
+            },
+            ExchangeId::Okx => match &self.okx {
+                Some(client) => client.new_order(&request).await,
+                None => Err(anyhow::anyhow!("OKX 未配置 API Key / Passphrase，无法实盘下单")),
+            },
+            exchange => Err(anyhow::anyhow!("{:?} 订单发送未实现", exchange)),
+        }?;
+        match fills {
+            // 限价单可能长期挂单，只等待市价单的最终成交
+            Some((tracker, updates))
+                if matches!(request.order_type, OrderType::Market) && !response.status.is_terminal() =>
+            {
+                Ok(tracker.settle(updates, response).await)
+            }
+            _ => Ok(response),
+        }
+    }
+
+    /// 改单方式: 交易所支持且未关闭原生改单时用原生接口，否则撤单重挂
+    pub fn amend_method(&self, exchange: ExchangeId) -> AmendMethod {
+        if self.native_amend && supports_native_amend(exchange) {
+            AmendMethod::Native
+        } else {
+            AmendMethod::CancelReplace
+        }
+    }
+
+    /// 修改挂单的价格与数量 (引擎对偏离盘口的挂单重新报价时调用，见 `Engine::requote_orders`)
+    ///
+    /// 原生改单延迟更低，并尽量保留队列位置；不支持的交易所撤单后按新价格重新挂限价单。
+    /// 原生改单失败 (未接入该交易所的改单接口、交易所拒绝等) 时同样退回撤单重挂。
+    pub async fn amend_order(&self, order: &OpenOrder, new_price: f64, new_amount: f64) -> Result<AmendResult> {
+        let mut method = self.amend_method(order.exchange);
+        let native = match method {
+            AmendMethod::Native => Some(self.send_amend(order, new_price, new_amount).await),
+            AmendMethod::CancelReplace => None,
+        };
+        let response = match native {
+            Some(Ok(response)) => response,
+            native => {
+                if let Some(Err(e)) = native {
+                    warn!(
+                        "{:?} {} 原生改单失败，改为撤单重挂: {}",
+                        order.exchange, order.order_id, e
+                    );
+                    method = AmendMethod::CancelReplace;
+                }
+                self.cancel_order(order.exchange, &order.symbol, &order.order_id).await?;
+                self.limit_order(order.exchange, &order.symbol, order.side, new_amount, new_price)
+                    .await?
+            }
+        };
+        info!(
+            "改单 {:?} {} {} -> {} ({:?}): 价格 {} 数量 {}",
+            order.exchange, order.symbol, order.order_id, response.order_id, method, new_price, new_amount
+        );
+        Ok(AmendResult { method, order: response })
+    }
+
+    /// 发送原生改单请求 (实盘目前支持 OKX REST 直连)
+    async fn send_amend(&self, order: &OpenOrder, new_price: f64, new_amount: f64) -> Result<OrderResponse> {
+        let request = self.apply_precision(OrderRequest {
+            exchange: order.exchange,
+            symbol: order.symbol.clone(),
+            side: order.side,
+            order_type: OrderType::Limit,
+            amount: new_amount,
+            price: Some(new_price),
+            client_order_id: None,
+        })?;
         if self.simulation_mode {
             return Ok(OrderResponse {
-                order_id: uuid::Uuid::new_v4().to_string(),
+                order_id: order.order_id.clone(),
                 exchange: request.exchange,
                 symbol: request.symbol,
                 side: request.side,
-                status: OrderStatus::Filled,
-                filled_amount: request.amount,
-                avg_price: request.price.unwrap_or(1.0),
-                fee: request.amount * 0.001,
-                latency_ms: 30,
+                status: OrderStatus::Pending,
+                filled_amount: 0.0,
+                avg_price: new_price,
+                fee: 0.0,
+                latency_ms: 20,
+                other_fees: vec![],
             });
         }
+        if !self.live_enabled() {
+            return Err(anyhow::anyhow!(
+                "live execution blocked: require ENGINE_EXECUTE_SIGNALS=1 and ENGINE_LIVE_CONFIRM=CONFIRM_LIVE"
+            ));
+        }
+        let params = native_amend_params(order, request.price.unwrap_or(new_price), request.amount)
+            .ok_or_else(|| anyhow::anyhow!("{:?} 改单参数构建失败", order.exchange))?;
+
+        match order.exchange {
+            ExchangeId::Okx => match &self.okx {
+                Some(client) => client.amend_order(&order.symbol, &params).await,
+                None => Err(anyhow::anyhow!("OKX 未配置 API Key / Passphrase，无法实盘改单")),
+            },
+            exchange => Err(anyhow::anyhow!("{:?} 原生改单发送未实现", exchange)),
+        }
+    }
+
+    /// 撤销挂单
+    pub async fn cancel_order(&self, exchange: ExchangeId, symbol: &str, order_id: &str) -> Result<()> {
+        if !self.exchanges.contains_key(&exchange) {
+            return Err(anyhow::anyhow!("交易所 {:?} 未连接", exchange));
+        }
+        if self.simulation_mode {
+            info!("模拟撤单 {:?} {} {}", exchange, symbol, order_id);
+            return Ok(());
+        }
+        if !self.live_enabled() {
+            return Err(anyhow::anyhow!(
+                "live execution blocked: require ENGINE_EXECUTE_SIGNALS=1 and ENGINE_LIVE_CONFIRM=CONFIRM_LIVE"
+            ));
+        }
 
+        match exchange {
+            ExchangeId::Binance => match &self.binance {
+                Some(client) => client.cancel_order(symbol, order_id).await.map(|_| ()),
+                None => Err(anyhow::anyhow!("Binance 未配置 API Key，无法实盘撤单")),
+            },
+            ExchangeId::Okx => match &self.okx {
+                Some(client) => client.cancel_order(symbol, order_id).await,
+                None => Err(anyhow::anyhow!("OKX 未配置 API Key / Passphrase，无法实盘撤单")),
+            },
+            exchange => Err(anyhow::anyhow!("{:?} 撤单发送未实现", exchange)),
+        }
+    }
+
+    /// 挂出交易所原生止损单，返回交易所订单 ID (实盘目前支持 Binance、OKX REST 直连)
+    pub async fn place_stop_order(&self, stop: &StopOrder) -> Result<String> {
+        if self.simulation_mode {
+            return Err(anyhow::anyhow!("模拟模式下止损由引擎侧监视器托管"));
+        }
+        if !supports_native_stop(stop.exchange) {
+            return Err(anyhow::anyhow!("{:?} 不支持原生止损单", stop.exchange));
+        }
         if !self.live_enabled() {
             return Err(anyhow::anyhow!(
                 "live execution blocked: require ENGINE_EXECUTE_SIGNALS=1 and ENGINE_LIVE_CONFIRM=CONFIRM_LIVE"
             ));
         }
+        let params = native_stop_params(stop)
+            .ok_or_else(|| anyhow::anyhow!("{:?} 止损参数构建失败", stop.exchange))?;
 
-        Err(anyhow::anyhow!("订单发送未实现"))
+        match stop.exchange {
+            ExchangeId::Binance => match &self.binance {
+                Some(client) => client.new_stop_order(&params).await,
+                None => Err(anyhow::anyhow!("Binance 未配置 API Key，无法挂原生止损单")),
+            },
+            ExchangeId::Okx => match &self.okx {
+                Some(client) => client.new_algo_order(&params).await,
+                None => Err(anyhow::anyhow!("OKX 未配置 API Key / Passphrase，无法挂原生止损单")),
+            },
+            exchange => Err(anyhow::anyhow!("{:?} 不支持原生止损单", exchange)),
+        }
+    }
+
+    /// 撤销交易所原生止损单 (引擎侧监视器托管的止损无需撤销)
+    pub async fn cancel_stop_order(&self, stop: &StopOrder) -> Result<()> {
+        let Some(order_id) = stop.exchange_order_id.as_deref() else {
+            return Ok(());
+        };
+        if stop.placement != StopPlacement::Native || self.simulation_mode {
+            return Ok(());
+        }
+        match stop.exchange {
+            ExchangeId::Binance => match &self.binance {
+                Some(client) => client.cancel_order(&stop.symbol, order_id).await.map(|_| ()),
+                None => Err(anyhow::anyhow!("Binance 未配置 API Key，无法撤销原生止损单")),
+            },
+            ExchangeId::Okx => match &self.okx {
+                Some(client) => client.cancel_algo_order(&stop.symbol, order_id).await,
+                None => Err(anyhow::anyhow!("OKX 未配置 API Key / Passphrase，无法撤销原生止损单")),
+            },
+            exchange => Err(anyhow::anyhow!("{:?} 不支持原生止损单", exchange)),
+        }
+    }
+
+    /// 从 OMS 拉取当前持仓与未完成订单 (启动对账)
+    pub async fn fetch_open_state(&self) -> Result<(Vec<Position>, Vec<OpenOrder>)> {
+        let client = self
+            .oms_client
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("OMS client not configured (ENGINE_OMS_BASE/ENGINE_OMS_TOKEN)"))?;
+        let positions = client.fetch_positions().await?;
+        let orders = client.fetch_open_orders(self.simulation_mode).await?;
+        Ok((positions, orders))
+    }
+
+    /// 是否配置了 OMS
+    pub fn has_oms(&self) -> bool {
+        self.oms_client.is_some()
+    }
+
+    /// 测试用: 指向桩 OMS 服务
+    #[cfg(test)]
+    pub(crate) fn set_oms_base(&mut self, base_url: String) {
+        self.oms_client = Some(OmsClient {
+            base_url,
+            token: "token".to_string(),
+            http: Client::new(),
+            trace_requests: false,
+        });
     }
 
     fn build_decision_payload(&self, signal: &Signal) -> serde_json::Value {
         let symbols = parse_symbols_from_path(&signal.path);
         let symbol = symbols.first().cloned().unwrap_or_default();
+        // 策略给出的收益率已扣除策略自身的费率: 配置了决策费率时改从毛收益率扣除，避免重复扣费
+        let (fee_rate, net_profit_rate) = match &self.decision_fees {
+            Some(fees) => {
+                let fee_rate = fees.total(signal, &symbols);
+                (fee_rate, signal.gross_profit_rate() - fee_rate)
+            }
+            None => (signal.fee_rate, signal.profit_rate),
+        };
         serde_json::json!({
             "strategyType": format!("{:?}", signal.strategy_type).to_lowercase(),
             "exchange": format!("{:?}", signal.exchange).to_lowercase(),
@@ -255,13 +936,19 @@ impl OrderExecutor {
             "direction": "neutral",
             "expectedProfit": signal.expected_profit,
             "expectedProfitRate": signal.profit_rate,
+            "grossProfitRate": signal.gross_profit_rate(),
+            "feeRate": fee_rate,
+            "netProfitRate": net_profit_rate,
+            "edgeBps": signal.edge_bps,
             "estimatedExposure": 0.0,
-            "riskScore": calc_risk_score(signal.profit_rate),
+            "riskScore": calc_risk_score(net_profit_rate),
             "confidence": signal.confidence,
             "timestamp": signal.timestamp,
+            "correlationId": signal.correlation_id,
             "rawOpportunity": {
                 "path": signal.path,
                 "symbols": symbols,
+                "legs": signal.legs,
             }
         })
     }
@@ -273,7 +960,7 @@ impl OrderExecutor {
         let Some(user_id) = &self.user_id else {
             return;
         };
-        if let Ok(mut conn) = redis.get_multiplexed_async_connection().await {
+        if let Ok(mut conn) = redis.connection().await {
             let channel = format!(
                 "signal:{}:{}",
                 user_id,
@@ -283,7 +970,14 @@ impl OrderExecutor {
         }
     }
 
+    fn stream_decision(&self, payload: &serde_json::Value) {
+        if let Some(feed) = &self.decision_feed {
+            feed.publish_decision(payload.clone());
+        }
+    }
+
     async fn publish_decision(&self, payload: &serde_json::Value) -> Result<()> {
+        self.stream_decision(payload);
         let Some(redis) = &self.redis else {
             return Ok(());
         };
@@ -291,24 +985,22 @@ impl OrderExecutor {
             .get("riskScore")
             .and_then(|v| v.as_f64())
             .unwrap_or(1.0);
-        let mut conn = redis.get_multiplexed_async_connection().await?;
-        let _: () = conn
+        let mut conn = redis.connection().await?;
+        let _: () = redis::pipe()
             .zadd("decisions:latest", payload.to_string(), risk_score)
+            .ignore()
+            .expire("decisions:latest", 10)
+            .ignore()
+            .query_async(&mut conn)
             .await?;
-        let _: () = conn.expire("decisions:latest", 10).await?;
         Ok(())
     }
 
     fn live_enabled(&self) -> bool {
-        let execute_signals = std::env::var("ENGINE_EXECUTE_SIGNALS")
-            .map(|v| matches!(v.as_str(), "1" | "true" | "True"))
-            .unwrap_or(false);
-        let live_confirm = std::env::var("ENGINE_LIVE_CONFIRM").unwrap_or_default();
-        execute_signals && live_confirm == "CONFIRM_LIVE"
+        self.live_enabled
     }
 
-    /// 批量执行订单 (原子性套利)
-    #[allow(dead_code)]
+    /// 并发发送一组订单 (多腿套利)，失败的订单只记录日志、不在结果中
     pub async fn execute_batch(&self, orders: Vec<OrderRequest>) -> Result<Vec<OrderResponse>> {
         // 并发执行所有订单
         let mut handles = vec![];
@@ -329,19 +1021,83 @@ impl OrderExecutor {
             }
         }
 
+        if self.dedupe_orders {
+            results = dedupe_orders(results);
+        }
         Ok(results)
     }
 
     /// 为异步任务克隆自身
-    #[allow(dead_code)]
     fn clone_for_task(&self) -> Self {
         Self {
             exchanges: self.exchanges.clone(),
             simulation_mode: self.simulation_mode,
+            live_enabled: self.live_enabled,
             redis: self.redis.clone(),
             oms_client: self.oms_client.clone(),
             user_id: self.user_id.clone(),
+            instruments: self.instruments.clone(),
+            faults: self.faults.clone(),
+            clock: self.clock.clone(),
+            dedupe_orders: self.dedupe_orders,
+            observe_only: self.observe_only.clone(),
+            confirmation: self.confirmation.clone(),
+            native_amend: self.native_amend,
+            netting: self.netting.clone(),
+            leg_retries: self.leg_retries,
+            leg_retry_backoff: self.leg_retry_backoff,
+            decision_fees: self.decision_fees.clone(),
+            simulation_model: self.simulation_model.clone(),
+            binance: self.binance.clone(),
+            okx: self.okx.clone(),
+            fills: self.fills.clone(),
+            execution_store: self.execution_store.clone(),
+            decision_feed: self.decision_feed.clone(),
+        }
+    }
+
+    /// 按交易对精度取整下单数量与限价 (限价取整后不劣于原价)，并校验交易状态、最小下单量 / 最小名义金额；
+    /// 实盘模式下元数据超过硬性过期上限时拒绝下单
+    fn apply_precision(&self, mut request: OrderRequest) -> Result<OrderRequest> {
+        if !self.can_execute_on(request.exchange) {
+            return Err(anyhow::anyhow!("{:?} 为只读行情交易所 (observe-only)，拒绝下单", request.exchange));
+        }
+        let registry = self
+            .instruments
+            .read()
+            .map_err(|_| anyhow::anyhow!("交易对元数据不可用"))?;
+        if !self.simulation_mode && registry.is_expired(request.exchange, chrono::Utc::now().timestamp_millis()) {
+            return Err(anyhow::anyhow!(
+                "{:?} 交易对元数据已超过最大缓存时长，拒绝实盘下单",
+                request.exchange
+            ));
+        }
+        let Some(info) = registry.get(request.exchange, &request.symbol) else {
+            return Ok(request);
+        };
+        if !info.is_trading() {
+            return Err(anyhow::anyhow!(
+                "{:?} {} 当前不可交易 ({})",
+                request.exchange, request.symbol, info.status
+            ));
         }
+        request.amount = info.round_qty(request.amount);
+        request.price = request.price.map(|price| info.round_price_for(request.side, price));
+        if request.amount <= 0.0 || request.amount < info.min_qty {
+            return Err(anyhow::anyhow!(
+                "{:?} {} 下单数量 {} 低于最小下单量 {}",
+                request.exchange, request.symbol, request.amount, info.min_qty
+            ));
+        }
+        if let Some(price) = request.price {
+            if !info.meets_min_notional(request.amount, price) {
+                return Err(anyhow::anyhow!(
+                    "{:?} {} 下单金额 {} 低于最小名义金额 {}",
+                    request.exchange, request.symbol, request.amount * price, info.min_notional
+                ));
+            }
+        }
+        Ok(request)
     }
 }
 
@@ -350,6 +1106,7 @@ struct OmsClient {
     base_url: String,
     token: String,
     http: Client,
+    trace_requests: bool,
 }
 
 impl OmsClient {
@@ -363,15 +1120,27 @@ impl OmsClient {
             base_url: base.trim_end_matches('/').to_string(),
             token,
             http: Client::new(),
+            trace_requests: trace_requests_from_env(),
         })
     }
 
-    async fn execute_latest(&self, idempotency_key: String, simulation_mode: bool) -> Result<bool> {
+    /// 执行最新决策，返回 OMS 回报的成交 (未注明交易所的订单记在信号交易所)
+    async fn execute_latest(
+        &self,
+        idempotency_key: String,
+        correlation_id: &str,
+        simulation_mode: bool,
+        exchange: ExchangeId,
+    ) -> Result<Vec<OrderResponse>> {
         let trading_mode = if simulation_mode { "paper" } else { "live" };
-        let resp = self
+        let mut req = self
             .http
             .post(format!("{}/api/v1/oms/execute_latest", self.base_url))
-            .bearer_auth(&self.token)
+            .bearer_auth(&self.token);
+        if self.trace_requests && !correlation_id.is_empty() {
+            req = req.header(CORRELATION_HEADER, correlation_id);
+        }
+        let resp = req
             .json(&serde_json::json!({
                 "trading_mode": trading_mode,
                 "confirm_live": !simulation_mode,
@@ -382,13 +1151,158 @@ impl OmsClient {
             .await?;
         let payload: serde_json::Value = resp.json().await?;
         if !payload.get("success").and_then(|v| v.as_bool()).unwrap_or(false) {
-            return Err(anyhow::anyhow!("OMS execute_latest failed: {:?}", payload));
+            return Err(anyhow::anyhow!(
+                "OMS execute_latest failed [{}]: {:?}",
+                correlation_id, payload
+            ));
+        }
+        let rows = payload.get("orders").and_then(|v| v.as_array()).cloned().unwrap_or_default();
+        Ok(rows.iter().filter_map(|row| parse_oms_fill(row, exchange)).collect())
+    }
+
+    async fn get_json(&self, url: String) -> Result<serde_json::Value> {
+        let payload: serde_json::Value = self
+            .http
+            .get(url)
+            .bearer_auth(&self.token)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        if !payload.get("success").and_then(|v| v.as_bool()).unwrap_or(false) {
+            return Err(anyhow::anyhow!("OMS request failed: {:?}", payload));
         }
-        Ok(true)
+        Ok(payload)
+    }
+
+    async fn fetch_positions(&self) -> Result<Vec<Position>> {
+        let payload = self
+            .get_json(format!("{}/api/v1/bot/positions", self.base_url))
+            .await?;
+        let rows = payload.get("data").and_then(|v| v.as_array()).cloned().unwrap_or_default();
+        Ok(rows
+            .iter()
+            .filter_map(|row| {
+                Some(Position {
+                    exchange: parse_exchange_id(row.get("exchange_id")?.as_str()?)?,
+                    symbol: row.get("instrument")?.as_str()?.to_string(),
+                    quantity: json_f64(row.get("quantity"))?,
+                    avg_price: json_f64(row.get("avg_price")).unwrap_or(0.0),
+                })
+            })
+            .filter(|p| p.quantity != 0.0)
+            .collect())
     }
+
+    async fn fetch_open_orders(&self, simulation_mode: bool) -> Result<Vec<OpenOrder>> {
+        let trading_mode = if simulation_mode { "paper" } else { "live" };
+        let payload = self
+            .get_json(format!(
+                "{}/api/v1/oms/orders?trading_mode={}&limit=500",
+                self.base_url, trading_mode
+            ))
+            .await?;
+        let rows = payload.get("orders").and_then(|v| v.as_array()).cloned().unwrap_or_default();
+        Ok(rows
+            .iter()
+            .filter(|row| {
+                matches!(
+                    row.get("status").and_then(|v| v.as_str()),
+                    Some("pending") | Some("partially_filled")
+                )
+            })
+            .filter_map(|row| {
+                let quantity = json_f64(row.get("quantity"))?;
+                let filled = json_f64(row.get("filled_quantity")).unwrap_or(0.0);
+                Some(OpenOrder {
+                    order_id: row.get("id")?.as_str()?.to_string(),
+                    exchange: parse_exchange_id(row.get("exchange_id")?.as_str()?)?,
+                    symbol: row.get("symbol")?.as_str()?.to_string(),
+                    side: match row.get("side")?.as_str()? {
+                        "buy" => OrderSide::Buy,
+                        _ => OrderSide::Sell,
+                    },
+                    amount: (quantity - filled).max(0.0),
+                    price: json_f64(row.get("price")).unwrap_or(0.0),
+                })
+            })
+            .collect())
+    }
+}
+
+/// 下单失败后的重试方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RetryKind {
+    /// 请求未被交易所受理 (连接失败、明确限频)，直接重发
+    Resend,
+    /// 结果未知 (超时、5xx、交易所临时故障)，先按客户端订单号查询，确认未受理才重发
+    QueryFirst,
 }
 
-fn parse_symbols_from_path(path: &str) -> Vec<String> {
+/// 下单错误的重试方式，None 表示不重试 (余额不足、过滤器等拒单重试仍会失败)
+fn retry_kind(err: &anyhow::Error) -> Option<RetryKind> {
+    if let Some(e) = err.downcast_ref::<ExecutionError>() {
+        return match e {
+            ExecutionError::RateLimited { .. } => Some(RetryKind::Resend),
+            e if e.is_retryable() => Some(RetryKind::QueryFirst),
+            _ => None,
+        };
+    }
+    let e = err.downcast_ref::<reqwest::Error>()?;
+    if e.is_connect() {
+        Some(RetryKind::Resend)
+    } else if e.is_timeout() || e.status().is_some_and(|s| s.is_server_error()) {
+        Some(RetryKind::QueryFirst)
+    } else {
+        None
+    }
+}
+
+/// OMS 执行回报中的一笔成交:
+/// `{"order_id":"...","symbol":"BTC/USDT","side":"buy","quantity":"0.01","average_price":"30000"}`
+/// (`fee` / `exchange_id` 可选；`kind` 为快照等非订单条目时跳过)
+fn parse_oms_fill(row: &serde_json::Value, exchange: ExchangeId) -> Option<OrderResponse> {
+    let filled_amount = json_f64(row.get("quantity"))?;
+    Some(OrderResponse {
+        order_id: row.get("order_id")?.as_str()?.to_string(),
+        exchange: row
+            .get("exchange_id")
+            .and_then(|v| v.as_str())
+            .and_then(parse_exchange_id)
+            .unwrap_or(exchange),
+        symbol: row.get("symbol")?.as_str()?.to_string(),
+        side: match row.get("side")?.as_str()? {
+            "sell" | "SELL" => OrderSide::Sell,
+            _ => OrderSide::Buy,
+        },
+        status: if filled_amount > 0.0 {
+            OrderStatus::Filled
+        } else {
+            OrderStatus::Pending
+        },
+        filled_amount,
+        avg_price: json_f64(row.get("average_price")).unwrap_or(0.0),
+        fee: json_f64(row.get("fee")).unwrap_or(0.0),
+        latency_ms: 0,
+        other_fees: vec![],
+    })
+}
+
+fn parse_exchange_id(value: &str) -> Option<ExchangeId> {
+    serde_json::from_value(serde_json::Value::String(value.to_lowercase())).ok()
+}
+
+/// 兼容数字与字符串形式的金额字段 (Decimal 可能被序列化为字符串)
+fn json_f64(value: Option<&serde_json::Value>) -> Option<f64> {
+    match value? {
+        serde_json::Value::Number(n) => n.as_f64(),
+        serde_json::Value::String(s) => s.parse().ok(),
+        _ => None,
+    }
+}
+
+pub(crate) fn parse_symbols_from_path(path: &str) -> Vec<String> {
     if path.is_empty() {
         return vec![];
     }
@@ -405,7 +1319,1057 @@ fn parse_symbols_from_path(path: &str) -> Vec<String> {
     out
 }
 
+/// 决策评分使用的每腿手续费率 (如 `binance:0.001,okx:0.0008,*:0.001`；`*` 为未列出交易所的费率，默认 0)
+#[derive(Debug, Clone, Default)]
+struct DecisionFees {
+    rates: HashMap<ExchangeId, f64>,
+    default_rate: f64,
+}
+
+impl DecisionFees {
+    fn from_env() -> Option<Self> {
+        let raw = std::env::var("ENGINE_DECISION_FEE_RATES").ok()?;
+        Self::parse("ENGINE_DECISION_FEE_RATES", &raw)
+    }
+
+    fn parse(key: &str, raw: &str) -> Option<Self> {
+        let mut fees = Self::default();
+        for item in raw.split(',').map(str::trim).filter(|i| !i.is_empty()) {
+            let Some((exchange, rate)) = item.split_once(':') else {
+                warn!("{} 项 `{}` 格式应为 交易所:费率，已忽略", key, item);
+                continue;
+            };
+            let Ok(rate) = rate.trim().parse::<f64>() else {
+                warn!("{} 项 `{}` 费率无效，已忽略", key, item);
+                continue;
+            };
+            if exchange.trim() == "*" {
+                fees.default_rate = rate.max(0.0);
+                continue;
+            }
+            match serde_json::from_value(serde_json::Value::String(exchange.trim().to_lowercase())) {
+                Ok(id) => {
+                    fees.rates.insert(id, rate.max(0.0));
+                }
+                Err(_) => warn!("{} 未知交易所 `{}`，已忽略", key, exchange),
+            }
+        }
+        (!fees.rates.is_empty() || fees.default_rate > 0.0).then_some(fees)
+    }
+
+    fn rate(&self, exchange: ExchangeId) -> f64 {
+        self.rates.get(&exchange).copied().unwrap_or(self.default_rate)
+    }
+
+    /// 信号各腿手续费之和: 有按腿交易所时逐腿计费，否则按路径腿数在信号交易所计费
+    /// (首尾相同的币种环路 `USDT->BTC->ETH->USDT` 为 3 腿)
+    fn total(&self, signal: &Signal, symbols: &[String]) -> f64 {
+        if !signal.legs.is_empty() {
+            return signal.legs.iter().map(|leg| self.rate(leg.exchange)).sum();
+        }
+        self.rate(signal.exchange) * path_leg_count(symbols) as f64
+    }
+}
+
+/// 路径腿数 (首尾相同的币种环路少算一腿)
+fn path_leg_count(symbols: &[String]) -> usize {
+    match (symbols.first(), symbols.last()) {
+        (Some(first), Some(last)) if symbols.len() > 1 && first == last => symbols.len() - 1,
+        _ => symbols.len().max(1),
+    }
+}
+
+/// 信号未给出可推算名义金额时的模拟下单金额 (计价币)
+const DEFAULT_SIM_NOTIONAL: f64 = 100.0;
+
+/// 模拟成交模型
+///
+/// 单笔市价单按缓存盘口吃单 (买入取卖一、卖出取买一，无缓存时取订单的参考价)，成交价再向不利方向偏移
+/// `slippage_bps`；限价单按限价成交。多腿信号逐腿按此成交，盘口相对信号价的变动与滑点一起折算进收益。
+/// 没有按腿信息的信号按路径腿数逐腿扣除滑点，只为路径中的交易对按信号动作 (开仓买入、平仓卖出) 生成成交。
+/// 缓存中没有所需盘口、也没有参考价时拒绝成交，不编造价格。
+/// 手续费按各腿交易所费率 × 名义金额计，净收益 = 模型毛收益 - 手续费，而不是直接采用策略上报的预期收益。
+#[derive(Debug, Clone)]
+pub struct SimulationModel {
+    /// 每腿不利滑点 (基点)
+    pub slippage_bps: f64,
+    fees: DecisionFees,
+}
+
+impl Default for SimulationModel {
+    fn default() -> Self {
+        Self::new(0.0, 0.001)
+    }
+}
+
+impl SimulationModel {
+    /// 统一费率的模型 (按交易所的费率由 ENGINE_SIM_FEE_RATES 配置，见 `from_env`)
+    pub fn new(slippage_bps: f64, fee_rate: f64) -> Self {
+        Self {
+            slippage_bps: slippage_bps.max(0.0),
+            fees: DecisionFees {
+                rates: HashMap::new(),
+                default_rate: fee_rate.max(0.0),
+            },
+        }
+    }
+
+    /// ENGINE_SIM_SLIPPAGE_BPS (默认 0) 与 ENGINE_SIM_FEE_RATES (格式同 ENGINE_DECISION_FEE_RATES，默认 `*:0.001`)
+    pub fn from_env() -> Self {
+        let slippage_bps = std::env::var("ENGINE_SIM_SLIPPAGE_BPS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(0.0);
+        let mut model = Self::new(slippage_bps, 0.001);
+        if let Some(fees) = std::env::var("ENGINE_SIM_FEE_RATES")
+            .ok()
+            .and_then(|raw| DecisionFees::parse("ENGINE_SIM_FEE_RATES", &raw))
+        {
+            model.fees = fees;
+        }
+        model
+    }
+
+    pub fn fee_rate(&self, exchange: ExchangeId) -> f64 {
+        self.fees.rate(exchange)
+    }
+
+    /// 模拟单笔订单成交；市价单既没有盘口缓存也没有参考价、或限价单缺少限价时拒绝成交
+    pub fn fill_order(&self, request: &OrderRequest, quote: Option<(f64, f64)>, latency_ms: u64) -> Result<OrderResponse> {
+        let price = match request.order_type {
+            OrderType::Limit => request.price.filter(|p| *p > 0.0),
+            OrderType::Market => quote
+                .map(|quote| touch(quote, request.side))
+                .filter(|p| *p > 0.0)
+                .or(request.price.filter(|p| *p > 0.0))
+                .map(|market| self.slipped(request.side, market)),
+        };
+        let Some(price) = price else {
+            anyhow::bail!(
+                "模拟成交缺少 {:?} {} 的盘口缓存与参考价，拒绝成交",
+                request.exchange, request.symbol
+            );
+        };
+        Ok(OrderResponse {
+            order_id: uuid::Uuid::new_v4().to_string(),
+            exchange: request.exchange,
+            symbol: request.symbol.clone(),
+            side: request.side,
+            status: OrderStatus::Filled,
+            filled_amount: request.amount,
+            avg_price: price,
+            fee: request.amount * price * self.fee_rate(request.exchange),
+            latency_ms,
+            other_fees: vec![],
+        })
+    }
+
+    /// 成交价向不利方向偏移滑点
+    fn slipped(&self, side: OrderSide, price: f64) -> f64 {
+        let slip = self.slippage_bps / 10_000.0;
+        match side {
+            OrderSide::Buy => price * (1.0 + slip),
+            OrderSide::Sell => price * (1.0 - slip),
+        }
+    }
+
+    /// 按模型生成没有按腿信息的信号的成交回报，返回 (回报, 扣除手续费前的毛收益)；需要盘口而缓存中没有时拒绝成交
+    ///
+    /// 只有能解析为交易对的路径元素 (如网格的 `BTC/USDT`) 能确定下单标的，方向取信号动作；
+    /// 币种环路 (`USDT->BTC->ETH->USDT`) 无法确定交易对与方向，只扣滑点不记成交
+    pub fn fill(
+        &self,
+        signal: &Signal,
+        latency_ms: u64,
+        quote: impl Fn(ExchangeId, &str) -> Option<(f64, f64)>,
+    ) -> Result<(Vec<OrderResponse>, f64)> {
+        let notional = signal.notional().unwrap_or(DEFAULT_SIM_NOTIONAL);
+        let symbols = parse_symbols_from_path(&signal.path);
+        let growth = (1.0 + signal.profit_rate) * (1.0 - self.slippage_bps / 10_000.0).powi(path_leg_count(&symbols) as i32);
+        let side = match signal.action {
+            SignalAction::Open => OrderSide::Buy,
+            SignalAction::Close => OrderSide::Sell,
+        };
+        let mut orders = vec![];
+        for symbol in symbols.iter().filter_map(|s| Symbol::parse(signal.exchange, s)) {
+            let symbol = symbol.to_string();
+            let Some(market) = quote(signal.exchange, &symbol).map(|q| touch(q, side)).filter(|p| *p > 0.0) else {
+                anyhow::bail!("模拟成交缺少 {:?} {} 的盘口缓存，拒绝成交", signal.exchange, symbol);
+            };
+            let price = self.slipped(side, market);
+            orders.push(OrderResponse {
+                order_id: uuid::Uuid::new_v4().to_string(),
+                exchange: signal.exchange,
+                symbol,
+                side,
+                status: OrderStatus::Filled,
+                filled_amount: notional / price,
+                avg_price: price,
+                fee: notional * self.fee_rate(signal.exchange),
+                latency_ms,
+                other_fees: vec![],
+            });
+        }
+        Ok((orders, notional * (growth - 1.0)))
+    }
+}
+
+/// 按方向取盘口对手价 (买入取卖一、卖出取买一)
+fn touch((bid, ask): (f64, f64), side: OrderSide) -> f64 {
+    match side {
+        OrderSide::Buy => ask,
+        OrderSide::Sell => bid,
+    }
+}
+
+/// 信号各腿的市价单: 数量为名义金额 / 信号价 (基础币)，客户端订单号由 correlation_id 与腿序号确定
+fn leg_requests(signal: &Signal, notional: f64) -> Result<Vec<OrderRequest>> {
+    signal
+        .legs
+        .iter()
+        .enumerate()
+        .map(|(index, leg)| {
+            if leg.price <= 0.0 {
+                anyhow::bail!("信号 [{}] 的 {:?} {} 腿缺少信号价", signal.correlation_id, leg.exchange, leg.symbol);
+            }
+            Ok(OrderRequest {
+                exchange: leg.exchange,
+                symbol: leg.symbol.clone(),
+                side: leg.side,
+                order_type: OrderType::Market,
+                amount: notional / leg.price,
+                price: Some(leg.price),
+                client_order_id: Some(leg_client_order_id(&signal.correlation_id, index)),
+            })
+        })
+        .collect()
+}
+
+/// 信号腿的客户端订单号: correlation_id 的字母数字部分加腿序号，不超过 32 个字符
+/// (OKX clOrdId 只允许字母数字且最长 32 位，Binance newClientOrderId 最长 36 位)
+fn leg_client_order_id(correlation_id: &str, index: usize) -> String {
+    let suffix = format!("L{}", index);
+    let prefix: String = correlation_id
+        .chars()
+        .filter(char::is_ascii_alphanumeric)
+        .take(32 - suffix.len())
+        .collect();
+    format!("{}{}", prefix, suffix)
+}
+
+/// 按腿成交的毛收益: 信号收益率按各腿成交均价相对信号价的偏离修正 (买贵 / 卖便宜都降低收益)
+fn realized_gross(signal: &Signal, orders: &[OrderResponse], notional: f64) -> f64 {
+    let mut growth = 1.0 + signal.profit_rate;
+    for order in orders.iter().filter(|o| o.avg_price > 0.0) {
+        let Some(leg) = signal.legs.iter().find(|leg| {
+            leg.exchange == order.exchange && leg.side == order.side && same_symbol(&leg.symbol, &order.symbol)
+        }) else {
+            continue;
+        };
+        growth *= match leg.side {
+            OrderSide::Buy => leg.price / order.avg_price,
+            OrderSide::Sell => order.avg_price / leg.price,
+        };
+    }
+    notional * (growth - 1.0)
+}
+
 fn calc_risk_score(profit_rate: f64) -> f64 {
     let base = (1.0 - profit_rate).max(0.01);
     (base * 1000.0).min(1000.0)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::instruments::InstrumentInfo;
+    use crate::positions::PositionBook;
+    use crate::strategy::{SignalAction, StrategyType};
+
+    fn grid_signal(action: SignalAction) -> Signal {
+        Signal::new("grid", StrategyType::Grid, ExchangeId::Binance, 0.01, 1.0, 0.5, "BTC/USDT", 0).with_action(action)
+    }
+
+    #[test]
+    fn sim_round_trip_leaves_position_flat() {
+        let model = SimulationModel::new(5.0, 0.001);
+        let mut book = PositionBook::new();
+
+        let open = grid_signal(SignalAction::Open);
+        let (orders, _) = model.fill(&open, 0, |_, _| Some((100.0, 100.1))).unwrap();
+        assert_eq!(orders.len(), 1);
+        assert!(matches!(orders[0].side, OrderSide::Buy));
+        book.apply_execution(&open, &orders);
+        assert!(book.has_exposure(ExchangeId::Binance, "BTC/USDT"));
+
+        let close = grid_signal(SignalAction::Close);
+        let (orders, _) = model.fill(&close, 0, |_, _| Some((105.0, 105.1))).unwrap();
+        assert!(matches!(orders[0].side, OrderSide::Sell));
+        book.apply_execution(&close, &orders);
+        assert_eq!(book.position_count(), 0);
+        assert_eq!(book.deployed_capital(ExchangeId::Binance), 0.0);
+    }
+
+    #[test]
+    fn sim_fill_without_quote_is_rejected() {
+        let model = SimulationModel::default();
+        assert!(model.fill(&grid_signal(SignalAction::Open), 0, |_, _| None).is_err());
+    }
+
+    #[test]
+    fn sim_asset_cycle_books_nothing() {
+        let model = SimulationModel::default();
+        let signal = Signal::new("graph", StrategyType::Graph, ExchangeId::Binance, 0.01, 1.0, 0.5, "USDT->BTC->ETH->USDT", 0);
+        let (orders, gross) = model.fill(&signal, 0, |_, _| Some((100.0, 100.1))).unwrap();
+        assert!(orders.is_empty());
+        assert!(gross > 0.0);
+    }
+
+    /// 模拟 OKX 交易接口: 改单按 `amend_ok` 成功或被拒，撤单成功，下单返回新订单号 `new-1`，策略委托返回 `algo-1`
+    async fn mock_okx(amend_ok: bool) -> String {
+        use axum::extract::Query;
+        use axum::routing::post;
+        use axum::Json;
+        use serde_json::json;
+
+        let app = axum::Router::new()
+            .route(
+                "/api/v5/trade/amend-order",
+                post(move || async move {
+                    Json(if amend_ok {
+                        json!({"code": "0", "msg": "", "data": [{"ordId": "old-1", "sCode": "0", "sMsg": ""}]})
+                    } else {
+                        json!({"code": "1", "msg": "", "data": [{"ordId": "old-1", "sCode": "51503", "sMsg": "amend failed"}]})
+                    })
+                }),
+            )
+            .route(
+                "/api/v5/trade/order-algo",
+                post(|| async { Json(json!({"code": "0", "msg": "", "data": [{"algoId": "algo-1", "sCode": "0"}]})) }),
+            )
+            .route(
+                "/api/v5/trade/cancel-algos",
+                post(|| async { Json(json!({"code": "0", "msg": "", "data": [{"algoId": "algo-1", "sCode": "0"}]})) }),
+            )
+            .route(
+                "/api/v5/trade/cancel-order",
+                post(|| async { Json(json!({"code": "0", "msg": "", "data": [{"ordId": "old-1", "sCode": "0"}]})) }),
+            )
+            .route(
+                "/api/v5/trade/order",
+                post(|| async { Json(json!({"code": "0", "msg": "", "data": [{"ordId": "new-1", "sCode": "0"}]})) }).get(
+                    |Query(query): Query<HashMap<String, String>>| async move {
+                        Json(json!({"code": "0", "msg": "", "data": [{
+                            "instId": "BTC-USDT", "ordId": query["ordId"], "state": "live", "side": "buy",
+                            "accFillSz": "0", "avgPx": "", "px": "101", "fee": "0", "feeCcy": "USDT"
+                        }]}))
+                    },
+                ),
+            );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        format!("http://{}", addr)
+    }
+
+    async fn live_okx_executor(amend_ok: bool) -> OrderExecutor {
+        let connection = Arc::new(ExchangeConnection::new(ExchangeId::Okx).await.unwrap());
+        let mut executor = OrderExecutor::new(HashMap::from([(ExchangeId::Okx, connection)]), None);
+        executor.set_simulation_mode(false);
+        executor.set_live_enabled(true);
+        let client = OkxRestClient::new(mock_okx(amend_ok).await, "key", "secret", "pass").unwrap();
+        executor.set_okx_client(Arc::new(client));
+        executor
+    }
+
+    /// 模拟 Binance 下单接口: 按请求数量以 100.5 全部成交 (手续费 0.01 USDT)，记录收到的查询串
+    async fn mock_binance(queries: Arc<std::sync::Mutex<Vec<String>>>) -> String {
+        use axum::extract::RawQuery;
+        use axum::routing::post;
+        use axum::Json;
+
+        let app = axum::Router::new().route(
+            "/api/v3/order",
+            post(move |RawQuery(query): RawQuery| async move {
+                let query = query.unwrap_or_default();
+                queries.lock().unwrap().push(query.clone());
+                let param = |key: &str| {
+                    query
+                        .split('&')
+                        .find_map(|kv| kv.strip_prefix(&format!("{}=", key)))
+                        .unwrap_or_default()
+                        .to_string()
+                };
+                let qty: f64 = param("quantity").parse().unwrap_or(0.0);
+                Json(serde_json::json!({
+                    "symbol": param("symbol"), "orderId": 28, "status": "FILLED", "side": param("side"),
+                    "executedQty": qty.to_string(), "cummulativeQuoteQty": (qty * 100.5).to_string(),
+                    "fills": [{"price": "100.5", "qty": qty.to_string(), "commission": "0.01", "commissionAsset": "USDT"}]
+                }))
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        format!("http://{}", addr)
+    }
+
+    #[tokio::test]
+    async fn live_signal_leg_is_placed_through_binance_rest() {
+        let queries = Arc::new(std::sync::Mutex::new(vec![]));
+        let connection = Arc::new(ExchangeConnection::new(ExchangeId::Binance).await.unwrap());
+        let mut executor = OrderExecutor::new(HashMap::from([(ExchangeId::Binance, connection)]), None);
+        executor.set_simulation_mode(false);
+        executor.set_live_enabled(true);
+        let signer = crate::signing::Signer::new(crate::signing::KeyType::Hmac, "secret").unwrap();
+        let client = BinanceRestClient::new(mock_binance(queries.clone()).await, "key", signer).unwrap();
+        executor.set_binance_client(Arc::new(client));
+
+        // 名义金额 100 (预期收益 1 / 收益率 1%)，信号价 100 买入 1 BTC
+        let mut signal = Signal::new("pair", StrategyType::Pair, ExchangeId::Binance, 0.01, 1.0, 0.5, "BTC/USDT", 0);
+        signal.legs = vec![crate::strategy::SignalLeg {
+            exchange: ExchangeId::Binance,
+            symbol: "BTC/USDT".to_string(),
+            side: OrderSide::Buy,
+            price: 100.0,
+        }];
+        let result = executor.execute(signal).await.unwrap();
+
+        assert!(result.success);
+        assert_eq!(result.orders.len(), 1);
+        assert_eq!(result.orders[0].order_id, "28");
+        assert_eq!(result.orders[0].filled_amount, 1.0);
+        assert!((result.orders[0].avg_price - 100.5).abs() < 1e-9);
+        // 按成交价修正: 100 * (1.01 * 100 / 100.5 - 1) - 0.01
+        let expected = 100.0 * (1.01 * 100.0 / 100.5 - 1.0) - 0.01;
+        assert!((result.net_profit - expected).abs() < 1e-9);
+
+        let queries = queries.lock().unwrap();
+        assert_eq!(queries.len(), 1);
+        for param in ["symbol=BTCUSDT", "side=BUY", "type=MARKET", "quantity=1", "signature="] {
+            assert!(queries[0].contains(param), "{} 缺少 {}", queries[0], param);
+        }
+        assert!(!queries[0].contains("price="));
+    }
+
+    /// 模拟下单超时 (首次下单返回 503) 的 Binance: `accepted` 时订单实际已受理，可按客户端订单号查到；
+    /// 否则查询返回订单不存在。记录收到的请求方法与查询串
+    async fn flaky_binance(accepted: bool, requests: Arc<std::sync::Mutex<Vec<(String, String)>>>) -> String {
+        use axum::extract::RawQuery;
+        use axum::http::{Method, StatusCode};
+        use axum::response::IntoResponse;
+        use axum::routing::any;
+        use axum::Json;
+
+        let app = axum::Router::new().route(
+            "/api/v3/order",
+            any(move |method: Method, RawQuery(query): RawQuery| async move {
+                let query = query.unwrap_or_default();
+                let posts = {
+                    let mut requests = requests.lock().unwrap();
+                    requests.push((method.to_string(), query.clone()));
+                    requests.iter().filter(|(m, _)| m == "POST").count()
+                };
+                let filled = Json(serde_json::json!({
+                    "symbol": "BTCUSDT", "orderId": 28, "status": "FILLED", "side": "BUY",
+                    "executedQty": "1", "cummulativeQuoteQty": "100.5",
+                    "fills": [{"price": "100.5", "qty": "1", "commission": "0.01", "commissionAsset": "USDT"}]
+                }));
+                match method {
+                    Method::POST if posts == 1 => {
+                        (StatusCode::SERVICE_UNAVAILABLE, Json(serde_json::json!({"code": -1001, "msg": "timeout"})))
+                            .into_response()
+                    }
+                    Method::GET if !accepted => (
+                        StatusCode::BAD_REQUEST,
+                        Json(serde_json::json!({"code": -2013, "msg": "Order does not exist."})),
+                    )
+                        .into_response(),
+                    _ => filled.into_response(),
+                }
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        format!("http://{}", addr)
+    }
+
+    #[tokio::test]
+    async fn timed_out_leg_is_looked_up_by_client_order_id_before_resending() {
+        let mut signal = Signal::new("pair", StrategyType::Pair, ExchangeId::Binance, 0.01, 1.0, 0.5, "BTC/USDT", 0);
+        signal.legs = vec![crate::strategy::SignalLeg {
+            exchange: ExchangeId::Binance,
+            symbol: "BTC/USDT".to_string(),
+            side: OrderSide::Buy,
+            price: 100.0,
+        }];
+        let client_order_id = leg_client_order_id(&signal.correlation_id, 0);
+
+        for accepted in [true, false] {
+            let requests = Arc::new(std::sync::Mutex::new(vec![]));
+            let connection = Arc::new(ExchangeConnection::new(ExchangeId::Binance).await.unwrap());
+            let mut executor = OrderExecutor::new(HashMap::from([(ExchangeId::Binance, connection)]), None);
+            executor.set_simulation_mode(false);
+            executor.set_live_enabled(true);
+            executor.leg_retries = 2;
+            executor.leg_retry_backoff = Duration::from_millis(1);
+            let signer = crate::signing::Signer::new(crate::signing::KeyType::Hmac, "secret").unwrap();
+            let client = BinanceRestClient::new(flaky_binance(accepted, requests.clone()).await, "key", signer).unwrap();
+            executor.set_binance_client(Arc::new(client));
+
+            let result = executor.execute(signal.clone()).await.unwrap();
+            assert!(result.success);
+            assert_eq!(result.orders[0].order_id, "28");
+
+            // 超时后先按客户端订单号查询: 已受理则不再重发，查不到才以同一客户端订单号重发
+            let requests = requests.lock().unwrap();
+            let methods: Vec<&str> = requests.iter().map(|(m, _)| m.as_str()).collect();
+            let expected = if accepted { vec!["POST", "GET"] } else { vec!["POST", "GET", "POST"] };
+            assert_eq!(methods, expected);
+            assert!(requests[0].1.contains(&format!("newClientOrderId={}", client_order_id)));
+            assert!(requests[1].1.contains(&format!("origClientOrderId={}", client_order_id)));
+            if !accepted {
+                assert!(requests[2].1.contains(&format!("newClientOrderId={}", client_order_id)));
+            }
+        }
+        // 每次拒单按原因计数
+        let rendered = METRICS.render(&HashMap::new());
+        assert!(rendered.contains(r#"inarbit_orders_rejected_total{exchange="binance",reason="transient"}"#));
+    }
+
+    #[test]
+    fn leg_client_order_id_is_alphanumeric_and_fits_okx() {
+        let id = leg_client_order_id("7d444840-9dc0-11d1-b245-5ffdce74fad2", 12);
+        assert_eq!(id, "7d4448409dc011d1b2455ffdce74fL12");
+        assert!(id.len() <= 32 && id.chars().all(|c| c.is_ascii_alphanumeric()));
+        assert_eq!(leg_client_order_id("c-1", 0), "c1L0");
+    }
+
+    fn resting_order() -> OpenOrder {
+        OpenOrder {
+            order_id: "old-1".to_string(),
+            exchange: ExchangeId::Okx,
+            symbol: "BTC/USDT".to_string(),
+            side: OrderSide::Buy,
+            amount: 0.01,
+            price: 100.0,
+        }
+    }
+
+    #[tokio::test]
+    async fn native_amend_keeps_order_id() {
+        let executor = live_okx_executor(true).await;
+        let result = executor.amend_order(&resting_order(), 101.0, 0.02).await.unwrap();
+        assert!(matches!(result.method, AmendMethod::Native));
+        assert_eq!(result.order.order_id, "old-1");
+    }
+
+    #[tokio::test]
+    async fn rejected_amend_falls_back_to_cancel_replace() {
+        let executor = live_okx_executor(false).await;
+        let result = executor.amend_order(&resting_order(), 101.0, 0.02).await.unwrap();
+        assert!(matches!(result.method, AmendMethod::CancelReplace));
+        assert_eq!(result.order.order_id, "new-1");
+    }
+
+    #[tokio::test]
+    async fn pending_market_order_settles_from_private_fill_pushes() {
+        use crate::fills::{FillAggregator, FillTracker, FillUpdate};
+
+        // 模拟 OKX 的订单查询始终为挂单中，最终成交只经私有推送到达
+        let mut executor = live_okx_executor(true).await;
+        let tracker = Arc::new(FillTracker::new(FillAggregator::default(), Duration::from_secs(2)));
+        executor.set_fill_tracker(tracker.clone());
+        let pushes = tracker.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            for (trade_id, qty, price, status) in [
+                ("t1", 0.004, 100.0, OrderStatus::PartialFilled),
+                ("t2", 0.004, 101.0, OrderStatus::PartialFilled),
+                ("t3", 0.002, 102.0, OrderStatus::Filled),
+            ] {
+                pushes.on_fill(FillUpdate {
+                    exchange: ExchangeId::Okx,
+                    order_id: "new-1".to_string(),
+                    symbol: "BTC/USDT".to_string(),
+                    side: OrderSide::Buy,
+                    trade_id: Some(trade_id.to_string()),
+                    qty,
+                    price,
+                    fee: qty * price * 0.001,
+                    status,
+                });
+            }
+        });
+
+        let response = executor
+            .market_order(ExchangeId::Okx, "BTC/USDT", OrderSide::Buy, 0.01)
+            .await
+            .unwrap();
+        assert_eq!(response.order_id, "new-1");
+        assert!(matches!(response.status, OrderStatus::Filled));
+        assert!((response.filled_amount - 0.01).abs() < 1e-12);
+        assert!((response.avg_price - 100.8).abs() < 1e-9);
+        assert!((response.fee - 0.01 * 100.8 * 0.001).abs() < 1e-12);
+    }
+
+    #[tokio::test]
+    async fn native_stop_is_placed_and_cancelled_through_rest() {
+        let executor = live_okx_executor(true).await;
+        let mut stop = StopOrder {
+            id: uuid::Uuid::new_v4().to_string(),
+            position_id: "pos-1".to_string(),
+            strategy_id: "grid".to_string(),
+            exchange: ExchangeId::Okx,
+            symbol: "BTC/USDT".to_string(),
+            side: OrderSide::Sell,
+            amount: 0.01,
+            entry_price: 100.0,
+            stop_price: 98.0,
+            placement: StopPlacement::Watcher,
+            exchange_order_id: None,
+        };
+        let order_id = executor.place_stop_order(&stop).await.unwrap();
+        assert_eq!(order_id, "algo-1");
+        stop.placement = StopPlacement::Native;
+        stop.exchange_order_id = Some(order_id);
+        executor.cancel_stop_order(&stop).await.unwrap();
+
+        stop.exchange = ExchangeId::Gate;
+        assert!(executor.place_stop_order(&stop).await.is_err());
+    }
+
+    /// 模拟模式执行器: Binance 缓存 BTC/USDT 买一卖一 100 / 100.1，OKX 缓存 101 / 101.1
+    async fn sim_executor(model: SimulationModel) -> OrderExecutor {
+        let mut connections = HashMap::new();
+        for (id, bid) in [(ExchangeId::Binance, 100.0), (ExchangeId::Okx, 101.0)] {
+            let connection = ExchangeConnection::new(id).await.unwrap();
+            connection.publish_ticker(
+                serde_json::from_value(serde_json::json!({
+                    "exchange": id, "symbol": "BTC/USDT", "bid": bid, "ask": bid + 0.1,
+                    "last": bid, "volume": 1000.0, "timestamp": 0,
+                }))
+                .unwrap(),
+            );
+            connections.insert(id, Arc::new(connection));
+        }
+        let mut executor = OrderExecutor::new(connections, None);
+        executor.set_simulation_mode(true);
+        executor.set_simulation_model(model);
+        executor
+    }
+
+    /// 跨所套利: Binance 按 100.1 买入、OKX 按 101 卖出 (每腿名义金额 100.1)
+    fn cross_venue_signal() -> Signal {
+        let rate = 101.0 / 100.1 - 1.0;
+        let mut signal = Signal::new("cross", StrategyType::Triangular, ExchangeId::Binance, rate, rate * 100.1, 0.5, "BTC/USDT", 0);
+        signal.legs = vec![
+            crate::strategy::SignalLeg {
+                exchange: ExchangeId::Binance,
+                symbol: "BTC/USDT".to_string(),
+                side: OrderSide::Buy,
+                price: 100.1,
+            },
+            crate::strategy::SignalLeg {
+                exchange: ExchangeId::Okx,
+                symbol: "BTC/USDT".to_string(),
+                side: OrderSide::Sell,
+                price: 101.0,
+            },
+        ];
+        signal
+    }
+
+    /// 模拟 OMS: execute_latest 回报两笔成交 (只有第二笔注明交易所与手续费) 与一条决策快照
+    async fn mock_oms() -> String {
+        use axum::routing::post;
+        use axum::Json;
+
+        let app = axum::Router::new().route(
+            "/api/v1/oms/execute_latest",
+            post(|| async {
+                Json(serde_json::json!({"success": true, "decision": {}, "orders": [
+                    {"order_id": "1", "symbol": "BTC/USDT", "side": "buy", "quantity": "0.999", "average_price": "100.2"},
+                    {"order_id": "2", "exchange_id": "okx", "symbol": "BTC/USDT", "side": "sell", "quantity": "0.999",
+                     "average_price": "101", "fee": "0.05"},
+                    {"kind": "opportunity_snapshot", "decision": {}}
+                ]}))
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        format!("http://{}", addr)
+    }
+
+    fn oms_client(base_url: String) -> OmsClient {
+        OmsClient {
+            base_url,
+            token: "token".to_string(),
+            http: Client::new(),
+            trace_requests: false,
+        }
+    }
+
+    #[tokio::test]
+    async fn oms_execution_records_pnl_from_reported_fills() {
+        let mut executor = sim_executor(SimulationModel::new(0.0, 0.0)).await;
+        executor.set_simulation_mode(false);
+        executor.set_live_enabled(true);
+        executor.oms_client = Some(oms_client(mock_oms().await));
+        executor.decision_fees = DecisionFees::parse("ENGINE_DECISION_FEE_RATES", "binance:0.001");
+
+        let signal = cross_venue_signal();
+        let rate = signal.profit_rate;
+        let result = executor.execute_signal(signal).await.unwrap();
+        assert!(result.success);
+        assert_eq!(result.orders.len(), 2);
+        assert_eq!(result.orders[1].exchange, ExchangeId::Okx);
+
+        // 买入腿成交价 100.2 高于信号价 100.1，收益按偏离修正；未回报手续费的按费率估算
+        let gross = 100.1 * ((1.0 + rate) * 100.1 / 100.2 - 1.0);
+        let fees = 0.999 * 100.2 * 0.001 + 0.05;
+        assert!((result.total_fee - fees).abs() < 1e-9);
+        assert!((result.net_profit - (gross - fees)).abs() < 1e-9);
+        assert!(result.net_profit < result.signal.expected_profit);
+
+        // OMS 没有回报成交时不算执行成功
+        let empty = executor.oms_result(cross_venue_signal(), vec![]);
+        assert!(!empty.success);
+        assert_eq!(empty.net_profit, 0.0);
+    }
+
+    #[tokio::test]
+    async fn decision_ranking_deducts_configured_fees_from_the_gross_edge() {
+        let redis = crate::fake_redis::FakeRedis::start().await;
+        let mut executor = OrderExecutor::new(HashMap::new(), Some(redis.client()));
+        executor.decision_fees = DecisionFees::parse("ENGINE_DECISION_FEE_RATES", "binance:0.001,okx:0.0002");
+
+        // 毛收益率都是 1%: 策略按自身费率扣费后 Binance 腿的信号收益率更高，但按交易所实际费率 OKX 腿的更划算
+        let signal = |id: &str, exchange: ExchangeId, strategy_fee: f64| {
+            let mut signal = Signal::new(id, StrategyType::Pair, exchange, 0.01 - strategy_fee, 1.0, 0.5, "BTC/USDT->ETH/USDT", 0)
+                .with_fee_rate(strategy_fee);
+            signal.legs = ["BTC/USDT", "ETH/USDT"]
+                .into_iter()
+                .map(|symbol| crate::strategy::SignalLeg {
+                    exchange,
+                    symbol: symbol.to_string(),
+                    side: OrderSide::Buy,
+                    price: 100.0,
+                })
+                .collect();
+            signal
+        };
+        let binance = signal("binance-legs", ExchangeId::Binance, 0.001);
+        let okx = signal("okx-legs", ExchangeId::Okx, 0.002);
+        assert!(binance.profit_rate > okx.profit_rate);
+
+        for signal in [&binance, &okx] {
+            let payload = executor.build_decision_payload(signal);
+            assert!((payload["grossProfitRate"].as_f64().unwrap() - 0.01).abs() < 1e-12);
+            executor.publish_decision(&payload).await.unwrap();
+        }
+        let mut conn = redis.connection().await;
+        let ranked: Vec<String> = conn.zrange("decisions:latest", 0, -1).await.unwrap();
+        let ranked: Vec<serde_json::Value> = ranked.iter().map(|p| serde_json::from_str(p).unwrap()).collect();
+        // 风险分越低越靠前: OKX 腿的信号排在前面
+        assert_eq!(ranked[0]["correlationId"], okx.correlation_id.as_str());
+        assert_eq!(ranked[1]["correlationId"], binance.correlation_id.as_str());
+        assert!((ranked[0]["netProfitRate"].as_f64().unwrap() - (0.01 - 0.0004)).abs() < 1e-12);
+        assert!((ranked[1]["netProfitRate"].as_f64().unwrap() - (0.01 - 0.002)).abs() < 1e-12);
+    }
+
+    #[tokio::test]
+    async fn sim_legs_fill_at_cached_touch_with_configured_fees() {
+        let executor = sim_executor(SimulationModel::new(0.0, 0.001)).await;
+        let result = executor.execute(cross_venue_signal()).await.unwrap();
+        assert!(result.success);
+        let price = |exchange| result.orders.iter().find(|o| o.exchange == exchange).unwrap().avg_price;
+        assert_eq!(price(ExchangeId::Binance), 100.1);
+        assert_eq!(price(ExchangeId::Okx), 101.0);
+        // 每腿名义金额相同 (数量 = 名义金额 / 信号价)
+        assert!((result.total_fee - 2.0 * 100.1 * 0.001).abs() < 1e-9);
+        assert!((result.net_profit - (0.9 - result.total_fee)).abs() < 1e-9);
+
+        // 市价单没有盘口缓存也没有参考价时拒绝成交，而不是编造价格
+        assert!(executor
+            .market_order(ExchangeId::Binance, "ETH/USDT", OrderSide::Buy, 1.0)
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn higher_slippage_strictly_reduces_net_profit() {
+        let mut previous = f64::INFINITY;
+        for slippage_bps in [0.0, 5.0, 20.0] {
+            let executor = sim_executor(SimulationModel::new(slippage_bps, 0.001)).await;
+            let result = executor.execute(cross_venue_signal()).await.unwrap();
+            assert!(result.success);
+            assert!(
+                result.net_profit < previous,
+                "滑点 {} bps 净收益 {} 不低于 {}",
+                slippage_bps,
+                result.net_profit,
+                previous
+            );
+            previous = result.net_profit;
+        }
+    }
+
+    #[tokio::test]
+    async fn latency_spike_window_follows_the_injected_clock() {
+        let plan: crate::faults::FaultPlan = serde_json::from_value(serde_json::json!({"faults": [
+            {"kind": "latency_spike", "start_ms": 0, "duration_ms": 1000, "extra_ms": 30}
+        ]}))
+        .unwrap();
+        let faults = Arc::new(FaultInjector::new(plan));
+        faults.tick(0);
+        let clock = Arc::new(crate::backtest::ReplayClock::new(500));
+        let mut executor = sim_executor(SimulationModel::new(0.0, 0.001)).await;
+        executor.set_fault_injector(faults.clone());
+        executor.set_clock(clock.clone());
+
+        // 回放时间在故障窗口内: 按回放时钟注入延迟 (系统时间远在窗口之后)
+        assert!(executor.execute(cross_venue_signal()).await.unwrap().success);
+        assert_eq!(faults.report().latency_injected_ms, 30);
+
+        clock.advance_to(1_500);
+        assert!(executor.execute(cross_venue_signal()).await.unwrap().success);
+        assert_eq!(faults.report().latency_injected_ms, 30);
+    }
+
+    /// OKX 的 BTC/USDT 下单首次返回临时错误
+    fn okx_fails_once() -> Arc<FaultInjector> {
+        let plan: crate::faults::FaultPlan = serde_json::from_value(serde_json::json!({"faults": [
+            {"kind": "order_error", "exchange": "okx", "symbol": "BTC/USDT", "failures": 1, "start_ms": 0, "duration_ms": 600000}
+        ]}))
+        .unwrap();
+        let faults = Arc::new(FaultInjector::new(plan));
+        faults.tick(chrono::Utc::now().timestamp_millis());
+        faults
+    }
+
+    #[tokio::test]
+    async fn leg_two_fails_transiently_once_then_the_arb_completes() {
+        let mut executor = sim_executor(SimulationModel::new(0.0, 0.001)).await;
+        let faults = okx_fails_once();
+        executor.set_fault_injector(faults.clone());
+        executor.leg_retries = 2;
+        executor.leg_retry_backoff = Duration::from_millis(1);
+
+        let result = executor.execute(cross_venue_signal()).await.unwrap();
+        assert!(result.success);
+        assert_eq!(result.orders.len(), 2);
+        assert!(result.orders.iter().any(|o| o.exchange == ExchangeId::Okx));
+        assert_eq!(faults.report().order_errors_injected, 1);
+
+        // 不重试时第二腿失败，整笔执行记为失败
+        let mut executor = sim_executor(SimulationModel::new(0.0, 0.001)).await;
+        executor.set_fault_injector(okx_fails_once());
+        executor.leg_retries = 0;
+        let result = executor.execute(cross_venue_signal()).await.unwrap();
+        assert!(!result.success);
+        assert_eq!(result.orders.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn opposite_legs_from_two_strategies_are_netted() {
+        let mut executor = sim_executor(SimulationModel::new(0.0, 0.001)).await;
+        executor.netting = Some(Arc::new(OrderNetter::new(Duration::from_millis(20))));
+        let single_leg = |strategy: &str, side: OrderSide, price: f64, notional: f64| {
+            let mut signal = Signal::new(strategy, StrategyType::Pair, ExchangeId::Binance, 0.01, notional * 0.01, 0.5, "BTC/USDT", 0);
+            signal.legs = vec![crate::strategy::SignalLeg {
+                exchange: ExchangeId::Binance,
+                symbol: "BTC/USDT".to_string(),
+                side,
+                price,
+            }];
+            signal
+        };
+        // 策略 A 买入 1 BTC，策略 B 同时卖出 0.4 BTC
+        let (buy, sell) = tokio::join!(
+            executor.execute(single_leg("a", OrderSide::Buy, 100.1, 100.1)),
+            executor.execute(single_leg("b", OrderSide::Sell, 100.0, 40.0)),
+        );
+        let (buy, sell) = (buy.unwrap(), sell.unwrap());
+
+        // 卖单在内部按中间价相抵，不付手续费；买单只有净额 0.6 按卖一成交并付费
+        assert!(sell.success);
+        assert_eq!(sell.orders[0].avg_price, 100.05);
+        assert_eq!(sell.total_fee, 0.0);
+        assert!(buy.success);
+        assert!((buy.orders[0].filled_amount - 1.0).abs() < 1e-12);
+        assert!((buy.total_fee - 0.6 * 100.1 * 0.001).abs() < 1e-12);
+    }
+
+    fn filled(order_id: &str, filled_amount: f64, fee: f64) -> OrderResponse {
+        OrderResponse {
+            order_id: order_id.to_string(),
+            exchange: ExchangeId::Binance,
+            symbol: "BTC/USDT".to_string(),
+            side: OrderSide::Buy,
+            status: OrderStatus::Filled,
+            filled_amount,
+            avg_price: 100.0,
+            fee,
+            latency_ms: 0,
+            other_fees: vec![],
+        }
+    }
+
+    #[test]
+    fn duplicate_order_response_is_counted_once() {
+        let orders = vec![filled("1", 0.5, 0.05), filled("1", 0.5, 0.05), filled("2", 0.5, 0.05)];
+        let signal = grid_signal(SignalAction::Open);
+
+        let result = ExecutionResult::from_orders(signal.clone(), orders.clone(), 1.0, true);
+        assert_eq!(result.orders.len(), 2);
+        assert!((result.total_fee - 0.1).abs() < 1e-12);
+        assert!((result.net_profit - 0.9).abs() < 1e-12);
+
+        let result = ExecutionResult::from_orders(signal, orders, 1.0, false);
+        assert_eq!(result.orders.len(), 3);
+        assert!((result.total_fee - 0.15).abs() < 1e-12);
+    }
+
+    #[test]
+    fn dedupe_keeps_the_most_filled_report() {
+        let mut partial = filled("1", 0.2, 0.02);
+        partial.status = OrderStatus::PartialFilled;
+        let orders = dedupe_orders(vec![partial, filled("1", 0.5, 0.05), filled("", 0.1, 0.0), filled("", 0.1, 0.0)]);
+        assert_eq!(orders.len(), 3);
+        assert_eq!(orders[0].filled_amount, 0.5);
+        assert!(matches!(orders[0].status, OrderStatus::Filled));
+    }
+
+    #[tokio::test]
+    async fn orders_are_rounded_and_checked_against_instrument_filters() {
+        let info: InstrumentInfo = serde_json::from_value(serde_json::json!({
+            "exchange": "binance", "symbol": "BTC/USDT", "exchange_symbol": "BTCUSDT", "base": "BTC", "quote": "USDT",
+            "price_tick": 0.01, "qty_step": 0.00001, "min_qty": 0.00001, "min_notional": 5.0,
+        }))
+        .unwrap();
+        let mut registry = InstrumentRegistry::new(0);
+        registry.replace(ExchangeId::Binance, vec![info], 0);
+        let connection = Arc::new(ExchangeConnection::new(ExchangeId::Binance).await.unwrap());
+        let mut executor = OrderExecutor::new(HashMap::from([(ExchangeId::Binance, connection)]), None);
+        executor.set_simulation_mode(true);
+        executor.set_instruments(Arc::new(std::sync::RwLock::new(registry)));
+
+        let fill = executor
+            .limit_order(ExchangeId::Binance, "BTCUSDT", OrderSide::Buy, 0.123456789, 100.019)
+            .await
+            .unwrap();
+        assert_eq!(fill.filled_amount, 0.12345);
+        assert_eq!(fill.avg_price, 100.01);
+
+        // 数量取整后低于最小名义金额 / 最小下单量时拒单
+        assert!(executor
+            .limit_order(ExchangeId::Binance, "BTC/USDT", OrderSide::Sell, 0.049999, 100.0)
+            .await
+            .is_err());
+        assert!(executor
+            .market_order(ExchangeId::Binance, "BTC/USDT", OrderSide::Sell, 0.000009)
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn oms_requests_carry_the_correlation_id_header() {
+        use axum::http::HeaderMap;
+        use axum::routing::post;
+        use axum::Json;
+
+        let seen: Arc<std::sync::Mutex<Vec<Option<String>>>> = Arc::default();
+        let record = seen.clone();
+        let app = axum::Router::new().route(
+            "/api/v1/oms/execute_latest",
+            post(move |headers: HeaderMap| {
+                let record = record.clone();
+                async move {
+                    let id = headers.get(CORRELATION_HEADER).and_then(|v| v.to_str().ok()).map(str::to_string);
+                    record.lock().unwrap().push(id);
+                    Json(serde_json::json!({"success": true, "orders": []}))
+                }
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        let mut client = oms_client(base);
+        client.trace_requests = true;
+        client.execute_latest("k1".to_string(), "corr-1", true, ExchangeId::Binance).await.unwrap();
+        client.trace_requests = false;
+        client.execute_latest("k2".to_string(), "corr-2", true, ExchangeId::Binance).await.unwrap();
+        assert_eq!(*seen.lock().unwrap(), vec![Some("corr-1".to_string()), None]);
+    }
+
+    #[tokio::test]
+    async fn observe_only_venue_in_any_leg_is_rejected() {
+        let mut executor = sim_executor(SimulationModel::new(0.0, 0.001)).await;
+
+        // 信号交易所可下单，但第二条腿在只读交易所
+        executor.set_observe_only(HashSet::from([ExchangeId::Okx]));
+        let err = executor.execute_signal(cross_venue_signal()).await.unwrap_err();
+        assert!(err.to_string().contains("Okx"), "{}", err);
+        assert!(err.to_string().contains("observe-only"), "{}", err);
+
+        // 信号交易所本身只读
+        executor.set_observe_only(HashSet::from([ExchangeId::Binance]));
+        let err = executor.execute_signal(cross_venue_signal()).await.unwrap_err();
+        assert!(err.to_string().contains("Binance"), "{}", err);
+
+        // 只读交易所不在任何腿上时照常执行
+        executor.set_observe_only(HashSet::from([ExchangeId::Bybit]));
+        assert!(executor.execute_signal(cross_venue_signal()).await.unwrap().success);
+    }
+
+    #[tokio::test]
+    async fn decision_stream_only_carries_signals_past_the_gates() {
+        let mut executor = sim_executor(SimulationModel::new(0.0, 0.001)).await;
+        let state = SharedState::new("simulation");
+        executor.set_decision_feed(state.clone());
+
+        executor.set_observe_only(HashSet::from([ExchangeId::Okx]));
+        assert!(executor.execute(cross_venue_signal()).await.is_err());
+        assert!(state.subscribe_decisions(Some(0)).0.is_empty());
+
+        executor.set_observe_only(HashSet::new());
+        executor.execute(cross_venue_signal()).await.unwrap();
+        let (events, _) = state.subscribe_decisions(Some(0));
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].payload["symbol"], "BTC/USDT");
+    }
+
+    /// 记录写入的执行尝试 (成功标记, 失败原因)
+    #[derive(Default)]
+    struct RecordingStore(std::sync::Mutex<Vec<(bool, Option<String>)>>);
+
+    #[async_trait::async_trait]
+    impl ExecutionStore for RecordingStore {
+        async fn record_execution(&self, result: &ExecutionResult, error: Option<&str>) -> Result<()> {
+            self.0.lock().unwrap().push((result.success, error.map(str::to_string)));
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn failed_execution_attempts_are_persisted() {
+        let mut executor = sim_executor(SimulationModel::new(0.0, 0.001)).await;
+        let store = Arc::new(RecordingStore::default());
+        executor.set_execution_store(store.clone());
+
+        executor.set_observe_only(HashSet::from([ExchangeId::Okx]));
+        assert!(executor.execute(cross_venue_signal()).await.is_err());
+        executor.set_observe_only(HashSet::new());
+        assert!(executor.execute(cross_venue_signal()).await.unwrap().success);
+
+        let records = store.0.lock().unwrap().clone();
+        assert_eq!(records.len(), 2);
+        assert!(!records[0].0);
+        assert!(records[0].1.as_deref().is_some_and(|e| e.contains("observe-only")), "{:?}", records[0]);
+        assert_eq!(records[1], (true, None));
+    }
+}
@@ -0,0 +1,194 @@
+//! 测试用的最小 Redis (RESP2) 服务
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+
+/// 支持 GET / SET / DEL / MGET / INCRBY / HINCRBY / HINCRBYFLOAT / SADD / SREM / SMEMBERS / ZADD / ZRANGE (整个集合，按分数升序)
+/// 与 MULTI / EXEC，其余命令回复 OK；记录收到的命令
+#[derive(Clone)]
+pub struct FakeRedis {
+    pub url: String,
+    data: Arc<Mutex<HashMap<String, String>>>,
+    commands: Arc<Mutex<Vec<Vec<String>>>>,
+}
+
+impl FakeRedis {
+    pub async fn start() -> Self {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let server = Self {
+            url: format!("redis://{}/", listener.local_addr().unwrap()),
+            data: Arc::default(),
+            commands: Arc::default(),
+        };
+        let accept = server.clone();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let conn = accept.clone();
+                tokio::spawn(async move { conn.serve(stream).await });
+            }
+        });
+        server
+    }
+
+    pub fn client(&self) -> redis::Client {
+        redis::Client::open(self.url.as_str()).unwrap()
+    }
+
+    /// 与引擎共享连接相同的连接类型
+    pub async fn connection(&self) -> redis::aio::ConnectionManager {
+        self.client().get_connection_manager().await.unwrap()
+    }
+
+    pub fn get(&self, key: &str) -> Option<String> {
+        self.data.lock().unwrap().get(key).cloned()
+    }
+
+    pub fn hget(&self, key: &str, field: &str) -> Option<String> {
+        self.get(&hash_key(key, field))
+    }
+
+    /// 收到的命令 (命令名大写)
+    pub fn commands(&self) -> Vec<Vec<String>> {
+        self.commands.lock().unwrap().clone()
+    }
+
+    async fn serve(&self, stream: tokio::net::TcpStream) {
+        let (read, mut write) = stream.into_split();
+        let mut read = BufReader::new(read);
+        let mut queued: Option<Vec<String>> = None;
+        while let Some(command) = read_command(&mut read).await {
+            let name = command.first().map(|c| c.to_uppercase()).unwrap_or_default();
+            let mut logged = command.clone();
+            if let Some(first) = logged.first_mut() {
+                *first = name.clone();
+            }
+            self.commands.lock().unwrap().push(logged);
+            let reply = match (name.as_str(), queued.as_mut()) {
+                ("MULTI", _) => {
+                    queued = Some(vec![]);
+                    "+OK\r\n".to_string()
+                }
+                ("EXEC", Some(_)) => {
+                    let replies = queued.take().unwrap_or_default();
+                    format!("*{}\r\n{}", replies.len(), replies.concat())
+                }
+                (_, Some(replies)) => {
+                    replies.push(self.execute(&name, &command[1..]));
+                    "+QUEUED\r\n".to_string()
+                }
+                (_, None) => self.execute(&name, &command[1..]),
+            };
+            if write.write_all(reply.as_bytes()).await.is_err() {
+                return;
+            }
+        }
+    }
+
+    fn execute(&self, name: &str, args: &[String]) -> String {
+        let mut data = self.data.lock().unwrap();
+        match name {
+            "PING" => "+PONG\r\n".to_string(),
+            "GET" => bulk(args.first().and_then(|k| data.get(k))),
+            "MGET" => format!("*{}\r\n{}", args.len(), args.iter().map(|k| bulk(data.get(k))).collect::<String>()),
+            "SET" if args.len() >= 2 => {
+                data.insert(args[0].clone(), args[1].clone());
+                "+OK\r\n".to_string()
+            }
+            "DEL" => format!(":{}\r\n", args.iter().filter(|k| data.remove(*k).is_some()).count()),
+            "INCR" | "INCRBY" => {
+                let by: i64 = args.get(1).and_then(|v| v.parse().ok()).unwrap_or(1);
+                let value = data.get(&args[0]).and_then(|v| v.parse::<i64>().ok()).unwrap_or(0) + by;
+                data.insert(args[0].clone(), value.to_string());
+                format!(":{}\r\n", value)
+            }
+            "HINCRBY" if args.len() >= 3 => {
+                let key = hash_key(&args[0], &args[1]);
+                let value = data.get(&key).and_then(|v| v.parse::<i64>().ok()).unwrap_or(0)
+                    + args[2].parse::<i64>().unwrap_or(0);
+                data.insert(key, value.to_string());
+                format!(":{}\r\n", value)
+            }
+            "HINCRBYFLOAT" if args.len() >= 3 => {
+                let key = hash_key(&args[0], &args[1]);
+                let value = data.get(&key).and_then(|v| v.parse::<f64>().ok()).unwrap_or(0.0)
+                    + args[2].parse::<f64>().unwrap_or(0.0);
+                let value = value.to_string();
+                data.insert(key, value.clone());
+                bulk(Some(&value))
+            }
+            "SADD" if !args.is_empty() => {
+                let added = args[1..]
+                    .iter()
+                    .filter(|m| data.insert(hash_key(&args[0], m), String::new()).is_none())
+                    .count();
+                format!(":{}\r\n", added)
+            }
+            "SREM" if !args.is_empty() => {
+                let removed = args[1..].iter().filter(|m| data.remove(&hash_key(&args[0], m)).is_some()).count();
+                format!(":{}\r\n", removed)
+            }
+            "SMEMBERS" if !args.is_empty() => {
+                let prefix = hash_key(&args[0], "");
+                let members: Vec<&str> = data.keys().filter_map(|k| k.strip_prefix(&prefix)).collect();
+                let body: String = members.iter().map(|m| format!("${}\r\n{}\r\n", m.len(), m)).collect();
+                format!("*{}\r\n{}", members.len(), body)
+            }
+            "ZADD" if !args.is_empty() => {
+                let added = args[1..]
+                    .chunks(2)
+                    .filter(|pair| pair.len() == 2)
+                    .filter(|pair| data.insert(hash_key(&args[0], &pair[1]), pair[0].clone()).is_none())
+                    .count();
+                format!(":{}\r\n", added)
+            }
+            "ZRANGE" if !args.is_empty() => {
+                let prefix = hash_key(&args[0], "");
+                let mut members: Vec<(f64, &str)> = data
+                    .iter()
+                    .filter_map(|(k, score)| Some((score.parse().ok()?, k.strip_prefix(&prefix)?)))
+                    .collect();
+                members.sort_by(|a, b| a.0.total_cmp(&b.0));
+                let body: String = members.iter().map(|(_, m)| format!("${}\r\n{}\r\n", m.len(), m)).collect();
+                format!("*{}\r\n{}", members.len(), body)
+            }
+            "PUBLISH" => ":0\r\n".to_string(),
+            _ => "+OK\r\n".to_string(),
+        }
+    }
+}
+
+/// 哈希字段、集合成员与普通 key 存在同一张表里
+fn hash_key(key: &str, field: &str) -> String {
+    format!("{}#{}", key, field)
+}
+
+fn bulk(value: Option<&String>) -> String {
+    match value {
+        Some(v) => format!("${}\r\n{}\r\n", v.len(), v),
+        None => "$-1\r\n".to_string(),
+    }
+}
+
+async fn read_line<R: AsyncBufReadExt + Unpin>(read: &mut R) -> Option<String> {
+    let mut line = String::new();
+    if read.read_line(&mut line).await.ok()? == 0 {
+        return None;
+    }
+    Some(line.trim_end().to_string())
+}
+
+/// 读取一条 RESP 数组形式的命令
+async fn read_command<R: AsyncBufReadExt + Unpin>(read: &mut R) -> Option<Vec<String>> {
+    let header = read_line(read).await?;
+    let count: usize = header.strip_prefix('*')?.parse().ok()?;
+    let mut args = Vec::with_capacity(count);
+    for _ in 0..count {
+        let len: usize = read_line(read).await?.strip_prefix('$')?.parse().ok()?;
+        let mut buf = vec![0; len + 2];
+        read.read_exact(&mut buf).await.ok()?;
+        buf.truncate(len);
+        args.push(String::from_utf8_lossy(&buf).to_string());
+    }
+    Some(args)
+}
@@ -0,0 +1,581 @@
+//! 故障注入 (仅模拟 / 回测模式)
+//!
+//! 按计划或按概率注入: 行情冻结 (交易所或单个交易对)、断线重连、模拟执行延迟尖峰、
+//! 下单临时失败、Redis / 数据库不可用窗口。注入点都是真实故障走的同一条路径 —— 丢弃行情交给
+//! 行情健康度判断陈旧、断线交给健康度与连接状态、下单失败走单笔订单重试、存储不可用走引擎的降级分支 ——
+//! 因此验证的是实际的恢复逻辑。运行结束时输出注入记录与引擎观测到的行为 (因陈旧被抑制的信号、
+//! 期间打开过的熔断: 引擎熔断开关、Redis 紧急停止、交易所拒单率拦截)。
+//!
+//! 配置文件 (ENGINE_FAULTS_FILE，JSON):
+//! ```json
+//! {"seed": 42, "faults": [
+//!   {"kind": "feed_freeze", "exchange": "binance", "symbol": "ETH/USDT", "start_ms": 60000, "duration_ms": 90000},
+//!   {"kind": "feed_freeze", "exchange": "okx", "probability_per_min": 0.2, "duration_ms": 30000},
+//!   {"kind": "reconnect", "exchange": "binance", "start_ms": 120000, "duration_ms": 3000},
+//!   {"kind": "latency_spike", "start_ms": 30000, "duration_ms": 10000, "extra_ms": 800},
+//!   {"kind": "order_error", "exchange": "binance", "symbol": "ETH/USDT", "failures": 1, "start_ms": 0, "duration_ms": 60000},
+//!   {"kind": "store_outage", "store": "redis", "start_ms": 45000, "duration_ms": 20000}
+//! ]}
+//! ```
+//! `start_ms` 相对引擎启动时间；给出 `probability_per_min` 时按概率触发，忽略 `start_ms`。
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::sync::Mutex;
+use tracing::{info, warn};
+
+use crate::exchange::{ExchangeId, Ticker};
+use crate::symbols::same_symbol;
+
+/// 存储类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Store {
+    Redis,
+    Db,
+}
+
+/// 故障类型
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum FaultKind {
+    /// 行情冻结 (symbol 为空时冻结整个交易所)
+    FeedFreeze {
+        exchange: ExchangeId,
+        #[serde(default)]
+        symbol: Option<String>,
+    },
+    /// 断线重连: 窗口内交易所断开、无行情
+    Reconnect { exchange: ExchangeId },
+    /// 模拟执行路径的额外延迟
+    LatencySpike { extra_ms: u64 },
+    /// 下单返回临时错误 (窗口内前 failures 笔匹配的订单失败，symbol 为空时匹配整个交易所)
+    OrderError {
+        exchange: ExchangeId,
+        #[serde(default)]
+        symbol: Option<String>,
+        #[serde(default = "default_order_failures")]
+        failures: u32,
+    },
+    /// 存储不可用
+    StoreOutage { store: Store },
+}
+
+/// 单条故障配置
+#[derive(Debug, Clone, Deserialize)]
+pub struct FaultSpec {
+    #[serde(flatten)]
+    pub kind: FaultKind,
+    #[serde(default)]
+    pub start_ms: i64,
+    pub duration_ms: i64,
+    /// 每分钟触发概率 (0-1)
+    #[serde(default)]
+    pub probability_per_min: Option<f64>,
+}
+
+/// 故障计划
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct FaultPlan {
+    #[serde(default)]
+    pub seed: u64,
+    #[serde(default)]
+    pub faults: Vec<FaultSpec>,
+}
+
+impl FaultPlan {
+    /// 从 ENGINE_FAULTS_FILE 加载，未配置时返回 None
+    pub fn from_env() -> Result<Option<Self>> {
+        let Ok(path) = std::env::var("ENGINE_FAULTS_FILE") else {
+            return Ok(None);
+        };
+        if path.is_empty() {
+            return Ok(None);
+        }
+        let raw = std::fs::read_to_string(&path).with_context(|| format!("读取故障计划 {} 失败", path))?;
+        let plan = serde_json::from_str(&raw).with_context(|| format!("解析故障计划 {} 失败", path))?;
+        Ok(Some(plan))
+    }
+}
+
+/// 已注入的故障
+#[derive(Debug, Clone, Serialize)]
+pub struct InjectedFault {
+    #[serde(flatten)]
+    pub kind: FaultKind,
+    pub start: i64,
+    pub end: i64,
+}
+
+/// 故障注入报告
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct FaultReport {
+    pub injected: Vec<InjectedFault>,
+    pub ticks_dropped: u64,
+    pub reconnects: u64,
+    pub latency_injected_ms: u64,
+    pub order_errors_injected: u64,
+    pub store_outage_hits: u64,
+    /// 因行情陈旧被抑制的信号
+    pub signals_suppressed_stale: u64,
+    /// 曾被判定为陈旧的交易对
+    pub stale_symbols: BTreeSet<String>,
+    /// 打开过的熔断
+    pub circuit_breakers: Vec<BreakerEvent>,
+}
+
+/// 熔断打开记录
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct BreakerEvent {
+    /// `kill_switch` (引擎熔断开关)、`risk_kill_switch` (Redis 紧急停止)、`venue_blocked` (交易所拒单率拦截)
+    pub breaker: String,
+    /// 作用对象 (`engine` 或交易所)
+    pub target: String,
+    pub opened_at: i64,
+    /// 仍处于打开状态时为 None
+    pub closed_at: Option<i64>,
+}
+
+struct InjectorState {
+    started_at: Option<i64>,
+    /// 已激活的故障窗口 (含计划与概率触发)
+    active: Vec<InjectedFault>,
+    /// 已激活的计划故障下标
+    scheduled_fired: Vec<bool>,
+    /// 概率故障的下一次掷骰时间
+    next_roll: Vec<i64>,
+    rng: u64,
+    report: FaultReport,
+}
+
+/// 故障注入器
+pub struct FaultInjector {
+    plan: FaultPlan,
+    state: Mutex<InjectorState>,
+}
+
+impl FaultInjector {
+    pub fn new(plan: FaultPlan) -> Self {
+        let n = plan.faults.len();
+        let rng = plan.seed.max(1);
+        Self {
+            plan,
+            state: Mutex::new(InjectorState {
+                started_at: None,
+                active: vec![],
+                scheduled_fired: vec![false; n],
+                next_roll: vec![0; n],
+                rng,
+                report: FaultReport::default(),
+            }),
+        }
+    }
+
+    /// 推进时间: 激活到期的计划故障，并为概率故障掷骰 (引擎每秒调用)。
+    /// 返回本次新激活的故障。
+    pub fn tick(&self, now: i64) -> Vec<InjectedFault> {
+        let Ok(mut state) = self.state.lock() else {
+            return vec![];
+        };
+        let started_at = *state.started_at.get_or_insert(now);
+        let mut fired = vec![];
+        for (i, spec) in self.plan.faults.iter().enumerate() {
+            match spec.probability_per_min {
+                Some(p) => {
+                    if now < state.next_roll[i] {
+                        continue;
+                    }
+                    state.next_roll[i] = now + 1_000;
+                    // 每秒掷一次
+                    let roll = next_random(&mut state.rng);
+                    let already_active = state
+                        .active
+                        .iter()
+                        .any(|f| same_fault(&f.kind, &spec.kind) && f.end > now);
+                    if roll >= p / 60.0 || already_active {
+                        continue;
+                    }
+                }
+                None => {
+                    if state.scheduled_fired[i] || now < started_at + spec.start_ms {
+                        continue;
+                    }
+                    state.scheduled_fired[i] = true;
+                }
+            }
+            let fault = InjectedFault {
+                kind: spec.kind.clone(),
+                start: now,
+                end: now + spec.duration_ms,
+            };
+            info!("注入故障 {:?} ({} ~ {})", fault.kind, fault.start, fault.end);
+            if matches!(fault.kind, FaultKind::Reconnect { .. }) {
+                state.report.reconnects += 1;
+            }
+            state.report.injected.push(fault.clone());
+            state.active.push(fault.clone());
+            fired.push(fault);
+        }
+        state.active.retain(|f| f.end > now);
+        fired
+    }
+
+    /// 立即注入一个持续 `duration_ms` 的故障窗口 (回测中的合成停机事件)，与计划内故障走同一判断路径
+    pub fn inject(&self, kind: FaultKind, now: i64, duration_ms: i64) {
+        let Ok(mut state) = self.state.lock() else {
+            return;
+        };
+        let fault = InjectedFault {
+            kind,
+            start: now,
+            end: now + duration_ms,
+        };
+        info!("注入故障 {:?} ({} ~ {})", fault.kind, fault.start, fault.end);
+        state.report.injected.push(fault.clone());
+        state.active.push(fault);
+    }
+
+    fn with_active<T>(&self, now: i64, f: impl FnOnce(&mut InjectorState, Vec<FaultKind>) -> T) -> Option<T> {
+        let mut state = self.state.lock().ok()?;
+        let active: Vec<FaultKind> = state
+            .active
+            .iter()
+            .filter(|fault| fault.start <= now && now < fault.end)
+            .map(|fault| fault.kind.clone())
+            .collect();
+        Some(f(&mut state, active))
+    }
+
+    /// 行情是否被冻结 / 断线丢弃
+    pub fn drop_ticker(&self, ticker: &Ticker, now: i64) -> bool {
+        self.with_active(now, |state, active| {
+            let dropped = active.iter().any(|kind| match kind {
+                FaultKind::FeedFreeze { exchange, symbol } => {
+                    *exchange == ticker.exchange
+                        && symbol
+                            .as_deref()
+                            .map(|s| same_symbol(s, &ticker.symbol))
+                            .unwrap_or(true)
+                }
+                FaultKind::Reconnect { exchange } => *exchange == ticker.exchange,
+                _ => false,
+            });
+            if dropped {
+                state.report.ticks_dropped += 1;
+            }
+            dropped
+        })
+        .unwrap_or(false)
+    }
+
+    /// 模拟执行的额外延迟 (毫秒)
+    pub fn extra_latency_ms(&self, now: i64) -> u64 {
+        self.with_active(now, |state, active| {
+            let extra = active
+                .iter()
+                .map(|kind| match kind {
+                    FaultKind::LatencySpike { extra_ms } => *extra_ms,
+                    _ => 0,
+                })
+                .max()
+                .unwrap_or(0);
+            state.report.latency_injected_ms += extra;
+            extra
+        })
+        .unwrap_or(0)
+    }
+
+    /// 本次下单是否注入临时错误 (每个故障窗口内按 failures 计数)
+    pub fn order_error(&self, exchange: ExchangeId, symbol: &str, now: i64) -> bool {
+        let Ok(mut state) = self.state.lock() else {
+            return false;
+        };
+        let hit = state.active.iter_mut().any(|fault| {
+            if fault.start > now || now >= fault.end {
+                return false;
+            }
+            match &mut fault.kind {
+                FaultKind::OrderError {
+                    exchange: e,
+                    symbol: s,
+                    failures,
+                } if *e == exchange
+                    && *failures > 0
+                    && s.as_deref().map(|s| same_symbol(s, symbol)).unwrap_or(true) =>
+                {
+                    *failures -= 1;
+                    true
+                }
+                _ => false,
+            }
+        });
+        if hit {
+            state.report.order_errors_injected += 1;
+        }
+        hit
+    }
+
+    /// 存储是否处于不可用窗口
+    pub fn store_down(&self, store: Store, now: i64) -> bool {
+        self.with_active(now, |state, active| {
+            let down = active
+                .iter()
+                .any(|kind| matches!(kind, FaultKind::StoreOutage { store: s } if *s == store));
+            if down {
+                state.report.store_outage_hits += 1;
+            }
+            down
+        })
+        .unwrap_or(false)
+    }
+
+    /// 记录因行情陈旧被抑制的信号
+    pub fn record_stale_suppression(&self, exchange: ExchangeId, symbol: &str) {
+        if let Ok(mut state) = self.state.lock() {
+            state.report.signals_suppressed_stale += 1;
+            state
+                .report
+                .stale_symbols
+                .insert(format!("{:?}:{}", exchange, symbol));
+        }
+    }
+
+    /// 同步当前打开的熔断 (breaker, target): 新打开的追加记录，不再打开的记下关闭时间
+    pub fn observe_breakers(&self, open: &[(String, String)], now: i64) {
+        let Ok(mut state) = self.state.lock() else {
+            return;
+        };
+        let events = &mut state.report.circuit_breakers;
+        for event in events.iter_mut().filter(|e| e.closed_at.is_none()) {
+            if !open.iter().any(|(breaker, target)| *breaker == event.breaker && *target == event.target) {
+                info!("熔断关闭: {} {}", event.breaker, event.target);
+                event.closed_at = Some(now);
+            }
+        }
+        for (breaker, target) in open {
+            if events
+                .iter()
+                .any(|e| e.closed_at.is_none() && e.breaker == *breaker && e.target == *target)
+            {
+                continue;
+            }
+            info!("熔断打开: {} {}", breaker, target);
+            events.push(BreakerEvent {
+                breaker: breaker.clone(),
+                target: target.clone(),
+                opened_at: now,
+                closed_at: None,
+            });
+        }
+    }
+
+    pub fn report(&self) -> FaultReport {
+        self.state
+            .lock()
+            .map(|s| s.report.clone())
+            .unwrap_or_default()
+    }
+}
+
+/// 模拟 / 回测模式下加载故障计划；实盘模式拒绝注入
+pub fn injector_from_env(live: bool) -> Result<Option<FaultInjector>> {
+    let Some(plan) = FaultPlan::from_env()? else {
+        return Ok(None);
+    };
+    if live {
+        warn!("实盘模式忽略故障注入计划");
+        return Ok(None);
+    }
+    info!("已加载故障注入计划: {} 条", plan.faults.len());
+    Ok(Some(FaultInjector::new(plan)))
+}
+
+fn default_order_failures() -> u32 {
+    1
+}
+
+fn same_fault(a: &FaultKind, b: &FaultKind) -> bool {
+    serde_json::to_value(a).ok() == serde_json::to_value(b).ok()
+}
+
+/// xorshift64，保证同一 seed 的概率故障可复现
+fn next_random(state: &mut u64) -> f64 {
+    let mut x = *state;
+    x ^= x << 13;
+    x ^= x >> 7;
+    x ^= x << 17;
+    *state = x;
+    (x >> 11) as f64 / (1u64 << 53) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backtest::{replay, ReplayClock, ReplayEvent};
+    use crate::engine::Engine;
+    use crate::exchange::testing::ticker;
+    use crate::executor::OrderExecutor;
+    use crate::health::FeedHealth;
+    use crate::strategy::StrategyConfig;
+    use std::collections::HashMap;
+    use std::sync::Arc;
+
+    fn plan(faults: serde_json::Value) -> FaultPlan {
+        serde_json::from_value(serde_json::json!({ "faults": faults })).unwrap()
+    }
+
+    #[test]
+    fn ninety_second_freeze_suppresses_only_the_frozen_symbol_until_it_ends() {
+        let injector = FaultInjector::new(plan(serde_json::json!([
+            {"kind": "feed_freeze", "exchange": "binance", "symbol": "BTC/USDT", "start_ms": 10_000, "duration_ms": 90_000}
+        ])));
+        let mut health = FeedHealth::from_env();
+        let mut suppressed = vec![];
+        // 与引擎主循环相同: 每秒推进计划，被冻结的行情直接丢弃，其余行情记入健康度
+        for second in 0..120 {
+            let now = second * 1_000;
+            injector.tick(now);
+            for symbol in ["BTC/USDT", "ETH/USDT"] {
+                let ticker = ticker(symbol).price(100.0).at(now).build();
+                if !injector.drop_ticker(&ticker, now) {
+                    health.record(&ticker, now);
+                }
+            }
+            assert!(health.stale_in_path(ExchangeId::Binance, "ETH/USDT", now).is_none());
+            if health.stale_in_path(ExchangeId::Binance, "BTC/USDT", now).is_some() {
+                suppressed.push(second);
+            }
+        }
+
+        // 冻结从 10s 开始，超过 5s 陈旧阈值后到冻结结束的信号全部被抑制，之后恢复
+        assert_eq!(suppressed, (15..100).collect::<Vec<_>>());
+        let report = injector.report();
+        assert_eq!(report.injected.len(), 1);
+        assert_eq!((report.injected[0].start, report.injected[0].end), (10_000, 100_000));
+        assert_eq!(report.ticks_dropped, 90);
+    }
+
+    #[test]
+    fn reconnect_drops_every_symbol_of_the_exchange() {
+        let injector = FaultInjector::new(plan(serde_json::json!([
+            {"kind": "reconnect", "exchange": "okx", "start_ms": 0, "duration_ms": 3_000}
+        ])));
+        let fired = injector.tick(0);
+        assert!(matches!(fired[0].kind, FaultKind::Reconnect { exchange: ExchangeId::Okx }));
+
+        assert!(injector.drop_ticker(&ticker("BTC/USDT").exchange(ExchangeId::Okx).price(100.0).at(1_000).build(), 1_000));
+        assert!(injector.drop_ticker(&ticker("ETH/USDT").exchange(ExchangeId::Okx).price(100.0).at(2_000).build(), 2_000));
+        assert!(!injector.drop_ticker(&ticker("BTC/USDT").price(100.0).at(2_000).build(), 2_000));
+        injector.tick(3_000);
+        assert!(!injector.drop_ticker(&ticker("BTC/USDT").exchange(ExchangeId::Okx).price(100.0).at(3_000).build(), 3_000));
+
+        let report = injector.report();
+        assert_eq!((report.reconnects, report.ticks_dropped), (1, 2));
+    }
+
+    #[test]
+    fn latency_spike_and_store_outage_apply_only_inside_their_windows() {
+        let injector = FaultInjector::new(plan(serde_json::json!([
+            {"kind": "latency_spike", "start_ms": 1_000, "duration_ms": 1_000, "extra_ms": 800},
+            {"kind": "store_outage", "store": "redis", "start_ms": 1_000, "duration_ms": 1_000}
+        ])));
+        injector.tick(0);
+        assert_eq!(injector.extra_latency_ms(500), 0);
+        assert!(!injector.store_down(Store::Redis, 500));
+
+        injector.tick(1_000);
+        assert_eq!(injector.extra_latency_ms(1_500), 800);
+        assert!(injector.store_down(Store::Redis, 1_500));
+        assert!(!injector.store_down(Store::Db, 1_500));
+
+        injector.tick(2_000);
+        assert_eq!(injector.extra_latency_ms(2_000), 0);
+        assert!(!injector.store_down(Store::Redis, 2_000));
+
+        let report = injector.report();
+        assert_eq!((report.latency_injected_ms, report.store_outage_hits), (800, 1));
+    }
+
+    fn grid(id: &str, symbol: &str) -> StrategyConfig {
+        serde_json::from_value(serde_json::json!({
+            "id": id,
+            "strategy_type": "grid",
+            "name": id,
+            "is_enabled": true,
+            "priority": 1,
+            "config": {"symbol": symbol, "lower_price": 90.0, "upper_price": 110.0, "grid_count": 20, "include_funding": false},
+        }))
+        .unwrap()
+    }
+
+    /// 每秒一笔、每笔都穿越一条格线的行情
+    fn ticks(symbol: &str, from_s: i64, to_s: i64) -> Vec<ReplayEvent> {
+        (from_s..to_s)
+            .map(|s| {
+                let price = if s % 2 == 0 { 100.5 } else { 101.5 };
+                ReplayEvent::Ticker(
+                    serde_json::from_value(serde_json::json!({
+                        "exchange": "binance", "symbol": symbol, "bid": price - 0.01, "ask": price + 0.01,
+                        "last": price, "volume": 1000.0, "timestamp": s * 1_000,
+                    }))
+                    .unwrap(),
+                )
+            })
+            .collect()
+    }
+
+    fn interleave(a: Vec<ReplayEvent>, b: Vec<ReplayEvent>) -> Vec<ReplayEvent> {
+        a.into_iter().zip(b).flat_map(|(x, y)| [x, y]).collect()
+    }
+
+    fn signals(engine: &Engine) -> HashMap<String, u64> {
+        let report = engine.sim_report().unwrap();
+        report.strategies.into_iter().map(|s| (s.strategy_id, s.signals)).collect()
+    }
+
+    #[tokio::test]
+    async fn ninety_second_freeze_produces_no_signals_then_resumes() {
+        let plan: FaultPlan = serde_json::from_value(serde_json::json!({"faults": [
+            {"kind": "feed_freeze", "exchange": "binance", "symbol": "BTC/USDT", "start_ms": 10_000, "duration_ms": 90_000}
+        ]}))
+        .unwrap();
+        let clock = Arc::new(ReplayClock::new(0));
+        let mut executor = OrderExecutor::new(Default::default(), None);
+        executor.set_simulation_mode(true);
+        let mut engine = Engine::new(executor, None, clock.clone());
+        engine.set_fault_injector(Arc::new(FaultInjector::new(plan)));
+        engine.load_strategies(vec![grid("btc", "BTC/USDT"), grid("eth", "ETH/USDT")]);
+
+        replay(&mut engine, &clock, interleave(ticks("BTC/USDT", 0, 10), ticks("ETH/USDT", 0, 10))).await;
+        let before = signals(&engine);
+        assert!(before["btc"] > 0);
+
+        replay(&mut engine, &clock, interleave(ticks("BTC/USDT", 10, 100), ticks("ETH/USDT", 10, 100))).await;
+        let frozen = signals(&engine);
+        assert_eq!(frozen["btc"], before["btc"], "冻结期间受影响交易对不应产生信号");
+        assert!(frozen["eth"] > before["eth"], "未受影响的交易对照常产生信号");
+
+        replay(&mut engine, &clock, interleave(ticks("BTC/USDT", 100, 110), ticks("ETH/USDT", 100, 110))).await;
+        assert!(signals(&engine)["btc"] > frozen["btc"], "冻结结束后恢复产生信号");
+
+        let report = engine.fault_report().unwrap();
+        assert_eq!(report.injected.len(), 1);
+        assert_eq!(report.ticks_dropped, 90);
+    }
+
+    #[test]
+    fn breakers_are_reported_when_opened_and_closed() {
+        let injector = FaultInjector::new(FaultPlan::default());
+        let venue = vec![("venue_blocked".to_string(), "okx".to_string())];
+        injector.observe_breakers(&venue, 1_000);
+        injector.observe_breakers(&venue, 2_000);
+        injector.observe_breakers(&[], 3_000);
+        injector.observe_breakers(&venue, 4_000);
+
+        let breakers = injector.report().circuit_breakers;
+        assert_eq!(breakers.len(), 2);
+        assert_eq!((breakers[0].opened_at, breakers[0].closed_at), (1_000, Some(3_000)));
+        assert_eq!((breakers[1].opened_at, breakers[1].closed_at), (4_000, None));
+    }
+}
@@ -0,0 +1,640 @@
+//! 成交回报累计
+//!
+//! 私有成交推送中同一订单常分多条部分成交消息到达，最后才是终态。各交易所的数量口径不同:
+//! 有的推送本次成交量 (增量，如 Binance `l` / OKX `fillSz`)，有的只推送累计成交量与累计均价。
+//! 直接相加或覆盖都会算错总量，这里按 (交易所, 订单号) 累计成交量、成交额与手续费，
+//! 计算成交量加权均价，收到终态 (完全成交 / 撤单 / 失败) 时结算并移除。
+//!
+//! 增量口径按成交 ID 去重 (断线重连后交易所可能重推)；累计口径忽略比已记录更小的累计量 (乱序到达)。
+//! 口径由 ENGINE_FILL_QTY_MODES 按交易所配置 (如 `okx:cumulative,gate:incremental`，默认增量)；
+//! Binance executionReport 不含累计手续费，固定按增量口径解析。
+//!
+//! 实盘模式下 Binance 用户数据流 (listenKey) 与 OKX 私有 orders 频道的推送经 [`FillTracker`] 累计后广播，
+//! 执行器对下单回报尚未到终态的市价单等待最终成交 (ENGINE_FILL_WAIT_MS，默认 3000)，以累计结果计算成交与手续费。
+
+use anyhow::Result;
+use futures_util::{SinkExt, StreamExt};
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::broadcast::{self, error::RecvError};
+use tokio::task::JoinHandle;
+use tokio::time::Instant;
+use tokio_tungstenite::{connect_async, tungstenite::Message};
+use tracing::{info, warn};
+
+use crate::binance_rest::{self, BinanceRestClient};
+use crate::exchange::ExchangeId;
+use crate::executor::{OrderResponse, OrderSide, OrderStatus};
+use crate::okx_rest::{self, OkxRestClient};
+use crate::symbols::{normalize_symbol, split_symbol};
+
+/// 默认等待市价单最终成交的时长 (毫秒)
+const DEFAULT_FILL_WAIT_MS: u64 = 3_000;
+
+/// 私有成交流断线后的重连间隔
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+/// Binance listenKey 续期间隔 (60 分钟未续期即失效)
+const LISTEN_KEY_KEEPALIVE: Duration = Duration::from_secs(30 * 60);
+
+/// OKX 私有连接的应用层心跳间隔 (30 秒无消息即被断开)
+const OKX_PING_INTERVAL: Duration = Duration::from_secs(25);
+
+/// 成交消息的数量口径
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FillQtyMode {
+    /// 每条消息为本次成交量 / 成交价 / 手续费
+    Incremental,
+    /// 每条消息为累计成交量 / 累计均价 / 累计手续费
+    Cumulative,
+}
+
+impl FillQtyMode {
+    fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "incremental" | "delta" => Some(Self::Incremental),
+            "cumulative" | "total" => Some(Self::Cumulative),
+            _ => None,
+        }
+    }
+}
+
+/// 单条成交推送
+#[derive(Debug, Clone)]
+pub struct FillUpdate {
+    pub exchange: ExchangeId,
+    pub order_id: String,
+    pub symbol: String,
+    pub side: OrderSide,
+    /// 成交 ID (增量口径去重用)
+    pub trade_id: Option<String>,
+    pub qty: f64,
+    pub price: f64,
+    pub fee: f64,
+    pub status: OrderStatus,
+}
+
+/// 单个订单的累计成交
+#[derive(Debug, Default)]
+struct OrderFill {
+    filled: f64,
+    notional: f64,
+    fee: f64,
+    trade_ids: HashSet<String>,
+}
+
+/// 按订单累计部分成交
+#[derive(Debug, Default)]
+pub struct FillAggregator {
+    modes: HashMap<ExchangeId, FillQtyMode>,
+    orders: HashMap<(ExchangeId, String), OrderFill>,
+}
+
+impl FillAggregator {
+    pub fn from_env() -> Self {
+        let modes = std::env::var("ENGINE_FILL_QTY_MODES")
+            .map(|raw| parse_modes(&raw))
+            .unwrap_or_default();
+        Self {
+            modes,
+            orders: HashMap::new(),
+        }
+    }
+
+    /// 交易所的数量口径 (Binance 固定为增量)
+    pub fn mode(&self, exchange: ExchangeId) -> FillQtyMode {
+        if exchange == ExchangeId::Binance {
+            return FillQtyMode::Incremental;
+        }
+        self.modes
+            .get(&exchange)
+            .copied()
+            .unwrap_or(FillQtyMode::Incremental)
+    }
+
+    /// 处理一条成交推送，返回订单当前的累计成交 (终态时为最终结果，并移除该订单)
+    pub fn on_fill(&mut self, update: FillUpdate) -> OrderResponse {
+        let mode = self.mode(update.exchange);
+        let key = (update.exchange, update.order_id.clone());
+        let order = self.orders.entry(key.clone()).or_default();
+        match mode {
+            FillQtyMode::Incremental => {
+                let duplicate = update
+                    .trade_id
+                    .as_ref()
+                    .is_some_and(|id| !order.trade_ids.insert(id.clone()));
+                if !duplicate && update.qty > 0.0 {
+                    order.filled += update.qty;
+                    order.notional += update.qty * update.price;
+                    order.fee += update.fee;
+                }
+            }
+            FillQtyMode::Cumulative => {
+                if update.qty >= order.filled {
+                    order.filled = update.qty;
+                    order.notional = update.qty * update.price;
+                    order.fee = update.fee;
+                }
+            }
+        }
+        let avg_price = if order.filled > 0.0 {
+            order.notional / order.filled
+        } else {
+            0.0
+        };
+        let status = match update.status {
+            OrderStatus::Pending if order.filled > 0.0 => OrderStatus::PartialFilled,
+            status => status,
+        };
+        let response = OrderResponse {
+            order_id: update.order_id,
+            exchange: update.exchange,
+            symbol: update.symbol,
+            side: update.side,
+            status,
+            filled_amount: order.filled,
+            avg_price,
+            fee: order.fee,
+            latency_ms: 0,
+            other_fees: vec![],
+        };
+        if status.is_terminal() {
+            self.orders.remove(&key);
+        }
+        response
+    }
+}
+
+/// 私有成交推送的累计与分发: 推送经 [`FillAggregator`] 累计后广播给等待订单结果的执行器
+pub struct FillTracker {
+    aggregator: Mutex<FillAggregator>,
+    updates: broadcast::Sender<OrderResponse>,
+    /// 等待市价单最终成交的最长时间
+    wait: Duration,
+}
+
+impl FillTracker {
+    pub fn new(aggregator: FillAggregator, wait: Duration) -> Self {
+        Self {
+            aggregator: Mutex::new(aggregator),
+            updates: broadcast::channel(1024).0,
+            wait,
+        }
+    }
+
+    pub fn from_env() -> Self {
+        let wait_ms = std::env::var("ENGINE_FILL_WAIT_MS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(DEFAULT_FILL_WAIT_MS);
+        Self::new(FillAggregator::from_env(), Duration::from_millis(wait_ms))
+    }
+
+    pub fn mode(&self, exchange: ExchangeId) -> FillQtyMode {
+        self.aggregator.lock().unwrap().mode(exchange)
+    }
+
+    /// 累计一条成交推送并广播订单当前的累计成交
+    pub fn on_fill(&self, update: FillUpdate) -> OrderResponse {
+        let response = self.aggregator.lock().unwrap().on_fill(update);
+        let _ = self.updates.send(response.clone());
+        response
+    }
+
+    /// 订阅累计成交 (须在下单前订阅，避免成交推送先于下单回报到达)
+    pub fn subscribe(&self) -> broadcast::Receiver<OrderResponse> {
+        self.updates.subscribe()
+    }
+
+    /// 等待下单回报未到终态的订单的最终成交；超时返回已收到的最新累计 (没有推送时原样返回下单回报)
+    pub async fn settle(&self, mut updates: broadcast::Receiver<OrderResponse>, response: OrderResponse) -> OrderResponse {
+        let deadline = tokio::time::sleep(self.wait);
+        tokio::pin!(deadline);
+        let mut latest = response;
+        loop {
+            tokio::select! {
+                _ = &mut deadline => {
+                    warn!(
+                        "{:?} 订单 {} 在 {} 毫秒内未收到最终成交，按已成交 {} 处理",
+                        latest.exchange,
+                        latest.order_id,
+                        self.wait.as_millis(),
+                        latest.filled_amount
+                    );
+                    return latest;
+                }
+                update = updates.recv() => match update {
+                    Ok(update) if update.exchange == latest.exchange && update.order_id == latest.order_id => {
+                        if update.filled_amount >= latest.filled_amount || update.status.is_terminal() {
+                            latest = OrderResponse {
+                                latency_ms: latest.latency_ms,
+                                ..update
+                            };
+                        }
+                        if latest.status.is_terminal() {
+                            return latest;
+                        }
+                    }
+                    Ok(_) | Err(RecvError::Lagged(_)) => {}
+                    Err(RecvError::Closed) => return latest,
+                }
+            }
+        }
+    }
+}
+
+fn str_f64(value: Option<&serde_json::Value>) -> Option<f64> {
+    value?.as_str()?.parse().ok()
+}
+
+/// 以基础币扣除的手续费按成交价折算为计价币 (与 REST 下单回报口径一致)
+fn quote_fee(symbol: &str, fee: f64, fee_asset: &str, price: f64) -> f64 {
+    let (base, quote) = split_symbol(symbol).unwrap_or_default();
+    if !base.is_empty() && fee_asset == base && fee_asset != quote {
+        fee * price
+    } else {
+        fee
+    }
+}
+
+/// 解析 Binance 用户数据流的 executionReport (增量口径: `l` / `L` 为本次成交量与成交价，`n` / `N` 为本次手续费):
+/// `{"e":"executionReport","s":"BTCUSDT","S":"BUY","X":"PARTIALLY_FILLED","i":4293153,"t":12,"l":"0.2","L":"100","n":"0.02","N":"USDT"}`
+pub fn parse_binance_execution_report(msg: &serde_json::Value) -> Option<FillUpdate> {
+    if msg.get("e")?.as_str()? != "executionReport" {
+        return None;
+    }
+    let symbol = normalize_symbol(ExchangeId::Binance, msg.get("s")?.as_str()?);
+    let price = str_f64(msg.get("L")).unwrap_or(0.0);
+    let fee_asset = msg.get("N").and_then(|a| a.as_str()).unwrap_or_default();
+    let fee = quote_fee(&symbol, str_f64(msg.get("n")).unwrap_or(0.0), fee_asset, price);
+    Some(FillUpdate {
+        exchange: ExchangeId::Binance,
+        order_id: msg.get("i")?.to_string(),
+        side: match msg.get("S")?.as_str()? {
+            "SELL" => OrderSide::Sell,
+            _ => OrderSide::Buy,
+        },
+        // 非成交事件的成交 ID 为 -1
+        trade_id: msg.get("t").and_then(|t| t.as_i64()).filter(|t| *t >= 0).map(|t| t.to_string()),
+        qty: str_f64(msg.get("l")).unwrap_or(0.0),
+        price,
+        fee,
+        status: binance_rest::order_status(msg.get("X")?.as_str()?),
+        symbol,
+    })
+}
+
+/// 解析 OKX 私有 orders 频道的单条订单推送: 增量口径取 `fillSz` / `fillPx` / `fillFee`，
+/// 累计口径取 `accFillSz` / `avgPx` / `fee` (手续费为负数表示扣除)
+pub fn parse_okx_order_push(item: &serde_json::Value, mode: FillQtyMode) -> Option<FillUpdate> {
+    let symbol = normalize_symbol(ExchangeId::Okx, item.get("instId")?.as_str()?);
+    let (qty, price, fee, fee_ccy) = match mode {
+        FillQtyMode::Incremental => ("fillSz", "fillPx", "fillFee", "fillFeeCcy"),
+        FillQtyMode::Cumulative => ("accFillSz", "avgPx", "fee", "feeCcy"),
+    };
+    let price = str_f64(item.get(price)).unwrap_or(0.0);
+    let fee_ccy = item.get(fee_ccy).and_then(|c| c.as_str()).unwrap_or_default();
+    let fee = quote_fee(&symbol, -str_f64(item.get(fee)).unwrap_or(0.0), fee_ccy, price);
+    Some(FillUpdate {
+        exchange: ExchangeId::Okx,
+        order_id: item.get("ordId")?.as_str()?.to_string(),
+        side: match item.get("side")?.as_str()? {
+            "sell" => OrderSide::Sell,
+            _ => OrderSide::Buy,
+        },
+        trade_id: item
+            .get("tradeId")
+            .and_then(|t| t.as_str())
+            .filter(|t| !t.is_empty())
+            .map(str::to_string),
+        qty: str_f64(item.get(qty)).unwrap_or(0.0),
+        price,
+        fee,
+        status: okx_rest::order_status(item.get("state")?.as_str()?),
+        symbol,
+    })
+}
+
+/// 持续接收 Binance 用户数据流 (`ws_base` 后拼接 listenKey)，断线或 listenKey 过期后重新申请并重连
+pub fn spawn_binance_user_stream(
+    client: Arc<BinanceRestClient>,
+    ws_base: String,
+    tracker: Arc<FillTracker>,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        loop {
+            if let Err(err) = run_binance_user_stream(&client, &ws_base, &tracker).await {
+                warn!("Binance 用户数据流断开，{} 秒后重连: {}", RECONNECT_DELAY.as_secs(), err);
+            }
+            tokio::time::sleep(RECONNECT_DELAY).await;
+        }
+    })
+}
+
+async fn run_binance_user_stream(client: &BinanceRestClient, ws_base: &str, tracker: &FillTracker) -> Result<()> {
+    let listen_key = client.start_user_stream().await?;
+    let (ws, _) = connect_async(format!("{}/{}", ws_base.trim_end_matches('/'), listen_key)).await?;
+    info!("Binance 用户数据流已连接");
+    let (mut write, mut read) = ws.split();
+    let mut keepalive = tokio::time::interval_at(Instant::now() + LISTEN_KEY_KEEPALIVE, LISTEN_KEY_KEEPALIVE);
+    loop {
+        tokio::select! {
+            _ = keepalive.tick() => client.keepalive_user_stream(&listen_key).await?,
+            message = read.next() => match message {
+                Some(Ok(Message::Text(text))) => {
+                    let msg: serde_json::Value = serde_json::from_str(&text).unwrap_or_default();
+                    if msg.get("e").and_then(|e| e.as_str()) == Some("listenKeyExpired") {
+                        anyhow::bail!("listenKey 已过期");
+                    }
+                    if let Some(update) = parse_binance_execution_report(&msg) {
+                        tracker.on_fill(update);
+                    }
+                }
+                Some(Ok(Message::Ping(data))) => write.send(Message::Pong(data)).await?,
+                Some(Ok(Message::Close(_))) | None => anyhow::bail!("连接已关闭"),
+                Some(Err(err)) => return Err(err.into()),
+                Some(Ok(_)) => {}
+            }
+        }
+    }
+}
+
+/// 持续接收 OKX 私有 orders 频道 (登录后订阅现货订单推送)，断线后重新登录并重连
+pub fn spawn_okx_private_stream(client: Arc<OkxRestClient>, url: String, tracker: Arc<FillTracker>) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        loop {
+            if let Err(err) = run_okx_private_stream(&client, &url, &tracker).await {
+                warn!("OKX 私有订单频道断开，{} 秒后重连: {}", RECONNECT_DELAY.as_secs(), err);
+            }
+            tokio::time::sleep(RECONNECT_DELAY).await;
+        }
+    })
+}
+
+async fn run_okx_private_stream(client: &OkxRestClient, url: &str, tracker: &FillTracker) -> Result<()> {
+    let (ws, _) = connect_async(url).await?;
+    let (mut write, mut read) = ws.split();
+    write.send(Message::Text(client.ws_login_frame())).await?;
+    let mode = tracker.mode(ExchangeId::Okx);
+    let mut ping = tokio::time::interval_at(Instant::now() + OKX_PING_INTERVAL, OKX_PING_INTERVAL);
+    loop {
+        tokio::select! {
+            _ = ping.tick() => write.send(Message::Text("ping".to_string())).await?,
+            message = read.next() => match message {
+                Some(Ok(Message::Text(text))) if text == "pong" => {}
+                Some(Ok(Message::Text(text))) => {
+                    let msg: serde_json::Value = serde_json::from_str(&text).unwrap_or_default();
+                    match msg.get("event").and_then(|e| e.as_str()) {
+                        Some("login") => {
+                            let subscribe = serde_json::json!({
+                                "op": "subscribe",
+                                "args": [{"channel": "orders", "instType": "SPOT"}],
+                            });
+                            write.send(Message::Text(subscribe.to_string())).await?;
+                            info!("OKX 私有订单频道已登录");
+                        }
+                        Some("error") => anyhow::bail!("OKX 私有连接错误: {}", text),
+                        _ if msg.pointer("/arg/channel").and_then(|c| c.as_str()) == Some("orders") => {
+                            for item in msg.get("data").and_then(|d| d.as_array()).into_iter().flatten() {
+                                if let Some(update) = parse_okx_order_push(item, mode) {
+                                    tracker.on_fill(update);
+                                }
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+                Some(Ok(Message::Ping(data))) => write.send(Message::Pong(data)).await?,
+                Some(Ok(Message::Close(_))) | None => anyhow::bail!("连接已关闭"),
+                Some(Err(err)) => return Err(err.into()),
+                Some(Ok(_)) => {}
+            }
+        }
+    }
+}
+
+fn parse_modes(raw: &str) -> HashMap<ExchangeId, FillQtyMode> {
+    raw.split(',')
+        .filter_map(|item| {
+            let (exchange, mode) = item.split_once(':')?;
+            let exchange =
+                serde_json::from_value(serde_json::Value::String(exchange.trim().to_lowercase())).ok()?;
+            Some((exchange, FillQtyMode::parse(mode)?))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn update(trade_id: &str, qty: f64, price: f64, fee: f64, status: OrderStatus) -> FillUpdate {
+        FillUpdate {
+            exchange: ExchangeId::Okx,
+            order_id: "1".to_string(),
+            symbol: "BTC/USDT".to_string(),
+            side: OrderSide::Buy,
+            trade_id: Some(trade_id.to_string()),
+            qty,
+            price,
+            fee,
+            status,
+        }
+    }
+
+    #[test]
+    fn three_incremental_partial_fills_accumulate_qty_and_vwap() {
+        let mut aggregator = FillAggregator::default();
+        aggregator.on_fill(update("t1", 0.2, 100.0, 0.02, OrderStatus::PartialFilled));
+        aggregator.on_fill(update("t2", 0.3, 101.0, 0.03, OrderStatus::PartialFilled));
+        // 重连后重推的成交按成交 ID 去重
+        let resent = aggregator.on_fill(update("t2", 0.3, 101.0, 0.03, OrderStatus::PartialFilled));
+        assert!((resent.filled_amount - 0.5).abs() < 1e-12);
+        let last = aggregator.on_fill(update("t3", 0.5, 102.0, 0.05, OrderStatus::Filled));
+
+        assert!(matches!(last.status, OrderStatus::Filled));
+        assert!((last.filled_amount - 1.0).abs() < 1e-12);
+        assert!((last.avg_price - (0.2 * 100.0 + 0.3 * 101.0 + 0.5 * 102.0)).abs() < 1e-9);
+        assert!((last.fee - 0.1).abs() < 1e-12);
+        // 终态后移除，同一订单号重新开始累计
+        let fresh = aggregator.on_fill(update("t4", 0.1, 99.0, 0.0, OrderStatus::PartialFilled));
+        assert!((fresh.filled_amount - 0.1).abs() < 1e-12);
+    }
+
+    #[test]
+    fn three_cumulative_partial_fills_keep_the_latest_total() {
+        let mut aggregator = FillAggregator {
+            modes: parse_modes("okx:cumulative"),
+            ..Default::default()
+        };
+        // 累计口径: 数量为累计成交量，价格为累计均价，手续费为累计手续费
+        aggregator.on_fill(update("", 0.2, 100.0, 0.02, OrderStatus::PartialFilled));
+        aggregator.on_fill(update("", 0.5, 100.6, 0.05, OrderStatus::PartialFilled));
+        // 乱序到达的旧累计量被忽略
+        let stale = aggregator.on_fill(update("", 0.2, 100.0, 0.02, OrderStatus::PartialFilled));
+        assert!((stale.filled_amount - 0.5).abs() < 1e-12);
+        assert!(matches!(stale.status, OrderStatus::PartialFilled));
+        let last = aggregator.on_fill(update("", 1.0, 101.3, 0.1, OrderStatus::Filled));
+
+        assert!(matches!(last.status, OrderStatus::Filled));
+        assert!((last.filled_amount - 1.0).abs() < 1e-12);
+        assert!((last.avg_price - 101.3).abs() < 1e-9);
+        assert!((last.fee - 0.1).abs() < 1e-12);
+    }
+
+    #[test]
+    fn modes_parse_per_exchange_and_binance_stays_incremental() {
+        let aggregator = FillAggregator {
+            modes: parse_modes("okx:cumulative, gate:delta, binance:total, bogus:cumulative, bybit:weird"),
+            ..Default::default()
+        };
+        assert_eq!(aggregator.mode(ExchangeId::Okx), FillQtyMode::Cumulative);
+        assert_eq!(aggregator.mode(ExchangeId::Gate), FillQtyMode::Incremental);
+        assert_eq!(aggregator.mode(ExchangeId::Bybit), FillQtyMode::Incremental);
+        assert_eq!(aggregator.mode(ExchangeId::Binance), FillQtyMode::Incremental);
+    }
+
+    #[test]
+    fn binance_execution_report_is_parsed_incrementally() {
+        let msg = json!({
+            "e": "executionReport", "s": "BTCUSDT", "S": "SELL", "X": "PARTIALLY_FILLED", "x": "TRADE",
+            "i": 4293153, "t": 12, "l": "0.2", "L": "100", "z": "0.5", "Z": "50.3", "n": "0.0002", "N": "BTC"
+        });
+        let update = parse_binance_execution_report(&msg).unwrap();
+        assert_eq!(update.order_id, "4293153");
+        assert_eq!(update.symbol, "BTC/USDT");
+        assert_eq!(update.side, OrderSide::Sell);
+        assert_eq!(update.trade_id.as_deref(), Some("12"));
+        assert!((update.qty - 0.2).abs() < 1e-12);
+        // 以基础币扣除的手续费折算为计价币
+        assert!((update.fee - 0.02).abs() < 1e-12);
+        assert!(matches!(update.status, OrderStatus::PartialFilled));
+
+        let accepted = json!({
+            "e": "executionReport", "s": "BTCUSDT", "S": "BUY", "X": "NEW", "i": 1, "t": -1, "l": "0", "L": "0", "n": "0", "N": null
+        });
+        assert_eq!(parse_binance_execution_report(&accepted).unwrap().trade_id, None);
+        assert!(parse_binance_execution_report(&json!({"e": "outboundAccountPosition"})).is_none());
+    }
+
+    #[test]
+    fn okx_order_push_follows_the_configured_mode() {
+        let item = json!({
+            "instId": "BTC-USDT", "ordId": "312269865356374016", "side": "buy", "state": "partially_filled",
+            "tradeId": "7", "fillSz": "0.3", "fillPx": "101", "fillFee": "-0.101", "fillFeeCcy": "USDT",
+            "accFillSz": "0.5", "avgPx": "100.6", "fee": "-0.1", "feeCcy": "USDT"
+        });
+        let incremental = parse_okx_order_push(&item, FillQtyMode::Incremental).unwrap();
+        assert!((incremental.qty - 0.3).abs() < 1e-12);
+        assert!((incremental.price - 101.0).abs() < 1e-12);
+        assert!((incremental.fee - 0.101).abs() < 1e-12);
+        assert_eq!(incremental.trade_id.as_deref(), Some("7"));
+
+        let cumulative = parse_okx_order_push(&item, FillQtyMode::Cumulative).unwrap();
+        assert_eq!(cumulative.symbol, "BTC/USDT");
+        assert!((cumulative.qty - 0.5).abs() < 1e-12);
+        assert!((cumulative.price - 100.6).abs() < 1e-12);
+        assert!((cumulative.fee - 0.1).abs() < 1e-12);
+        assert!(matches!(cumulative.status, OrderStatus::PartialFilled));
+    }
+
+    fn pending(order_id: &str) -> OrderResponse {
+        OrderResponse {
+            order_id: order_id.to_string(),
+            exchange: ExchangeId::Okx,
+            symbol: "BTC/USDT".to_string(),
+            side: OrderSide::Buy,
+            status: OrderStatus::Pending,
+            filled_amount: 0.0,
+            avg_price: 0.0,
+            fee: 0.0,
+            latency_ms: 42,
+            other_fees: vec![],
+        }
+    }
+
+    #[tokio::test]
+    async fn settle_times_out_with_the_latest_partial_fill() {
+        let tracker = FillTracker::new(FillAggregator::default(), Duration::from_millis(200));
+        let updates = tracker.subscribe();
+        // 其他订单的推送不影响结果
+        let mut other = update("x", 5.0, 1.0, 0.0, OrderStatus::Filled);
+        other.order_id = "2".to_string();
+        tracker.on_fill(other);
+        tracker.on_fill(update("t1", 0.2, 100.0, 0.02, OrderStatus::PartialFilled));
+
+        let settled = tracker.settle(updates, pending("1")).await;
+        assert!(matches!(settled.status, OrderStatus::PartialFilled));
+        assert!((settled.filled_amount - 0.2).abs() < 1e-12);
+        assert_eq!(settled.latency_ms, 42);
+
+        // 没有任何推送时原样返回下单回报
+        let settled = tracker.settle(tracker.subscribe(), pending("3")).await;
+        assert!(matches!(settled.status, OrderStatus::Pending));
+    }
+
+    /// 本地 Binance: REST 发放 listenKey，WebSocket 依次推送三条部分成交 (最后一条为完全成交) 后保持连接
+    async fn binance_user_stream_server() -> (String, String) {
+        use axum::routing::post;
+        use axum::Json;
+
+        let app = axum::Router::new().route(
+            "/api/v3/userDataStream",
+            post(|| async { Json(json!({"listenKey": "test-listen-key"})) }),
+        );
+        let rest = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let rest_addr = rest.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(rest, app).await });
+
+        let ws = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let ws_addr = ws.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (stream, _) = ws.accept().await.unwrap();
+            let mut path = String::new();
+            #[allow(clippy::result_large_err)]
+            let mut socket = tokio_tungstenite::accept_hdr_async(
+                stream,
+                |request: &tokio_tungstenite::tungstenite::handshake::server::Request, response| {
+                    path = request.uri().path().to_string();
+                    Ok(response)
+                },
+            )
+            .await
+            .unwrap();
+            assert_eq!(path, "/ws/test-listen-key");
+            for (trade_id, qty, price, status) in [
+                (1, "0.2", "100", "PARTIALLY_FILLED"),
+                (2, "0.3", "101", "PARTIALLY_FILLED"),
+                (3, "0.5", "102", "FILLED"),
+            ] {
+                let msg = json!({
+                    "e": "executionReport", "s": "BTCUSDT", "S": "BUY", "X": status, "i": 99,
+                    "t": trade_id, "l": qty, "L": price, "n": "0", "N": "USDT"
+                });
+                socket.send(Message::Text(msg.to_string())).await.unwrap();
+            }
+            tokio::time::sleep(Duration::from_secs(30)).await;
+        });
+        (format!("http://{}", rest_addr), format!("ws://{}/ws", ws_addr))
+    }
+
+    #[tokio::test]
+    async fn binance_user_stream_feeds_the_tracker() {
+        let (rest_url, ws_url) = binance_user_stream_server().await;
+        let signer = crate::signing::Signer::new(crate::signing::KeyType::Hmac, "secret").unwrap();
+        let client = Arc::new(BinanceRestClient::new(rest_url, "key", signer).unwrap());
+        let tracker = Arc::new(FillTracker::new(FillAggregator::default(), Duration::from_secs(3)));
+        let updates = tracker.subscribe();
+        let stream = spawn_binance_user_stream(client, ws_url, tracker.clone());
+
+        let mut placed = pending("99");
+        placed.exchange = ExchangeId::Binance;
+        let settled = tracker.settle(updates, placed).await;
+        stream.abort();
+        assert!(matches!(settled.status, OrderStatus::Filled));
+        assert!((settled.filled_amount - 1.0).abs() < 1e-12);
+        assert!((settled.avg_price - 101.3).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,258 @@
+//! 行情转发 (交易所广播通道 -> 引擎主循环)
+//!
+//! 引擎处理跟不上行情突发时，广播通道会丢弃最旧的消息 (`Lagged`)，被丢弃区间内
+//! 某些交易对的最新价格可能就此丢失。转发器在每次滞后后从连接的最新行情快照补发各交易对的最新价，
+//! 并丢弃此后到达的、比已补发行情更旧的消息。短时间内反复滞后时切换到合并模式:
+//! 每轮取空通道内积压的消息，每个交易对只转发最新一条；持续一段时间不再滞后后恢复逐条转发。
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::{broadcast, mpsc};
+use tracing::{info, warn};
+
+use crate::exchange::{ExchangeConnection, Ticker};
+
+/// 滞后处理配置
+#[derive(Debug, Clone)]
+pub struct LagPolicy {
+    /// 是否允许切换到合并模式
+    pub coalesce: bool,
+    /// 窗口内滞后次数达到该值时进入合并模式
+    pub lag_events: usize,
+    pub lag_window: Duration,
+    /// 合并模式下持续该时长未滞后则恢复逐条转发
+    pub quiet_period: Duration,
+}
+
+impl Default for LagPolicy {
+    fn default() -> Self {
+        Self {
+            coalesce: true,
+            lag_events: 3,
+            lag_window: Duration::from_secs(10),
+            quiet_period: Duration::from_secs(30),
+        }
+    }
+}
+
+impl LagPolicy {
+    /// 从环境变量读取:
+    /// ENGINE_TICKER_COALESCE、ENGINE_TICKER_LAG_EVENTS、ENGINE_TICKER_LAG_WINDOW_SECS、ENGINE_TICKER_COALESCE_QUIET_SECS
+    pub fn from_env() -> Self {
+        let mut policy = Self::default();
+        let env = |name: &str| std::env::var(name).ok().filter(|v| !v.trim().is_empty());
+        if let Some(v) = env("ENGINE_TICKER_COALESCE") {
+            policy.coalesce = !matches!(v.as_str(), "0" | "false" | "False");
+        }
+        if let Some(v) = env("ENGINE_TICKER_LAG_EVENTS").and_then(|v| v.parse::<usize>().ok()) {
+            policy.lag_events = v.max(1);
+        }
+        if let Some(v) = env("ENGINE_TICKER_LAG_WINDOW_SECS").and_then(|v| v.parse().ok()) {
+            policy.lag_window = Duration::from_secs(v);
+        }
+        if let Some(v) = env("ENGINE_TICKER_COALESCE_QUIET_SECS").and_then(|v| v.parse().ok()) {
+            policy.quiet_period = Duration::from_secs(v);
+        }
+        policy
+    }
+}
+
+/// 单个交易所的行情转发器
+pub struct TickerForwarder {
+    conn: Arc<ExchangeConnection>,
+    rx: broadcast::Receiver<Ticker>,
+    tx: mpsc::Sender<Ticker>,
+    policy: LagPolicy,
+    /// 最近的滞后时间
+    lags: VecDeque<Instant>,
+    coalescing: bool,
+    /// 滞后后已补发的各交易对行情时间，早于它的积压消息不再转发
+    replayed: HashMap<String, i64>,
+}
+
+impl TickerForwarder {
+    pub fn new(conn: Arc<ExchangeConnection>, tx: mpsc::Sender<Ticker>, policy: LagPolicy) -> Self {
+        let rx = conn.subscribe_tickers();
+        Self {
+            conn,
+            rx,
+            tx,
+            policy,
+            lags: VecDeque::new(),
+            coalescing: false,
+            replayed: HashMap::new(),
+        }
+    }
+
+    /// 持续转发，直到任一侧通道关闭
+    pub async fn run(mut self) {
+        loop {
+            let ticker = match self.rx.recv().await {
+                Ok(ticker) => ticker,
+                Err(broadcast::error::RecvError::Lagged(n)) => {
+                    if !self.on_lag(n).await {
+                        return;
+                    }
+                    continue;
+                }
+                Err(broadcast::error::RecvError::Closed) => return,
+            };
+            let delivered = if self.coalescing {
+                self.forward_coalesced(ticker).await
+            } else {
+                self.forward(ticker).await
+            };
+            if !delivered {
+                return;
+            }
+        }
+    }
+
+    /// 转发一条行情 (比已补发行情更旧的跳过)；返回 false 表示引擎侧通道已关闭
+    async fn forward(&mut self, ticker: Ticker) -> bool {
+        if let Some(ts) = self.replayed.get(&ticker.symbol) {
+            if ticker.timestamp <= *ts {
+                return true;
+            }
+            self.replayed.remove(&ticker.symbol);
+        }
+        self.tx.send(ticker).await.is_ok()
+    }
+
+    /// 合并模式: 取空积压消息，每个交易对只转发最新一条
+    async fn forward_coalesced(&mut self, first: Ticker) -> bool {
+        let mut batch: HashMap<String, Ticker> = HashMap::new();
+        batch.insert(first.symbol.clone(), first);
+        let mut lagged = false;
+        loop {
+            match self.rx.try_recv() {
+                Ok(ticker) => {
+                    batch.insert(ticker.symbol.clone(), ticker);
+                }
+                Err(broadcast::error::TryRecvError::Lagged(n)) => {
+                    warn!("{:?} ticker 消费滞后 (合并模式)，丢弃 {} 条", self.conn.id, n);
+                    self.lags.push_back(Instant::now());
+                    lagged = true;
+                }
+                Err(_) => break,
+            }
+        }
+        let mut snapshot = vec![];
+        if lagged {
+            // 丢弃区间内的最新价以快照为准
+            snapshot = self.conn.latest_tickers();
+            for ticker in &snapshot {
+                batch.insert(ticker.symbol.clone(), ticker.clone());
+            }
+        } else if self
+            .lags
+            .back()
+            .is_none_or(|last| last.elapsed() >= self.policy.quiet_period)
+        {
+            info!("{:?} 行情消费恢复，退出合并模式", self.conn.id);
+            self.coalescing = false;
+        }
+        for ticker in batch.into_values() {
+            if !self.forward(ticker).await {
+                return false;
+            }
+        }
+        for ticker in snapshot {
+            self.replayed.insert(ticker.symbol, ticker.timestamp);
+        }
+        true
+    }
+
+    /// 滞后: 补发各交易对最新行情，必要时进入合并模式；返回 false 表示引擎侧通道已关闭
+    async fn on_lag(&mut self, dropped: u64) -> bool {
+        warn!("{:?} ticker 消费滞后，丢弃 {} 条，补发各交易对最新行情", self.conn.id, dropped);
+        let now = Instant::now();
+        self.lags.push_back(now);
+        while self
+            .lags
+            .front()
+            .is_some_and(|t| now.duration_since(*t) > self.policy.lag_window)
+        {
+            self.lags.pop_front();
+        }
+        if self.policy.coalesce && !self.coalescing && self.lags.len() >= self.policy.lag_events {
+            warn!(
+                "{:?} {} 秒内滞后 {} 次，切换为按交易对合并的行情转发",
+                self.conn.id,
+                self.policy.lag_window.as_secs(),
+                self.lags.len()
+            );
+            self.coalescing = true;
+        }
+        for ticker in self.conn.latest_tickers() {
+            self.replayed.insert(ticker.symbol.clone(), ticker.timestamp);
+            if self.tx.send(ticker).await.is_err() {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exchange::testing::ticker;
+    use crate::exchange::ExchangeId;
+
+    /// 取出已转发的行情，直到一段时间内没有新消息
+    async fn drain(rx: &mut mpsc::Receiver<Ticker>) -> Vec<Ticker> {
+        let mut out = vec![];
+        while let Ok(Some(ticker)) = tokio::time::timeout(Duration::from_millis(200), rx.recv()).await {
+            out.push(ticker);
+        }
+        out
+    }
+
+    #[tokio::test]
+    async fn latest_price_per_symbol_survives_lag() {
+        let conn = Arc::new(ExchangeConnection::new(ExchangeId::Binance).await.unwrap());
+        let (tx, mut rx) = mpsc::channel(10_000);
+        let forwarder = TickerForwarder::new(conn.clone(), tx, LagPolicy::default());
+        // ETH 只在突发开头推送一次，随后被 1500 条 BTC 挤出广播通道 (容量 1000)
+        conn.publish_ticker(ticker("ETH/USDT").price(10.0).at(1).build());
+        for i in 0..1_500 {
+            conn.publish_ticker(ticker("BTC/USDT").price(100.0 + i as f64 * 0.01).at(2 + i).build());
+        }
+        tokio::spawn(forwarder.run());
+
+        let received = drain(&mut rx).await;
+        assert!(received.len() < 1_501, "通道应发生滞后");
+        let last = |symbol: &str| received.iter().rfind(|t| t.symbol == symbol).map(|t| t.timestamp);
+        assert_eq!(last("ETH/USDT"), Some(1));
+        assert_eq!(last("BTC/USDT"), Some(1_501));
+        // 补发后不再转发比它更旧的积压行情
+        for symbol in ["ETH/USDT", "BTC/USDT"] {
+            let ts: Vec<i64> = received.iter().filter(|t| t.symbol == symbol).map(|t| t.timestamp).collect();
+            assert!(ts.windows(2).all(|w| w[0] <= w[1]), "{} 行情乱序: {:?}", symbol, ts);
+        }
+    }
+
+    #[tokio::test]
+    async fn coalescing_forwards_only_the_latest_tick_per_symbol() {
+        let conn = Arc::new(ExchangeConnection::new(ExchangeId::Binance).await.unwrap());
+        let (tx, mut rx) = mpsc::channel(100);
+        let mut forwarder = TickerForwarder::new(conn.clone(), tx, LagPolicy::default());
+        forwarder.coalescing = true;
+        forwarder.lags.push_back(Instant::now());
+        for i in 0..10 {
+            conn.publish_ticker(ticker("BTC/USDT").price(100.0 + i as f64).at(i).build());
+            conn.publish_ticker(ticker("ETH/USDT").price(10.0 + i as f64).at(i).build());
+        }
+
+        let first = forwarder.rx.recv().await.unwrap();
+        assert!(forwarder.forward_coalesced(first).await);
+        let mut received = drain(&mut rx).await;
+        received.sort_by(|a, b| a.symbol.cmp(&b.symbol));
+        let latest: Vec<(&str, i64)> = received.iter().map(|t| (t.symbol.as_str(), t.timestamp)).collect();
+        assert_eq!(latest, [("BTC/USDT", 9), ("ETH/USDT", 9)]);
+        // 最近刚滞后过，仍保持合并模式
+        assert!(forwarder.coalescing);
+    }
+}
@@ -0,0 +1,287 @@
+//! 永续合约资金费率缓存
+//!
+//! 按 (交易所, 合约 BASE/QUOTE:MARGIN) 缓存最新资金费率与下次结算时间，供持仓类策略 (配对、网格)
+//! 估算持仓期内的资金费: 资金费率为正时多头支付、空头收取。
+//!
+//! 资金费率由连接定期轮询 REST 接口获取 (见 `ExchangeConnection::request_funding_rates`):
+//! Binance U 本位合约 `/fapi/v1/premiumIndex` (一次返回全部合约，含标记价格)；
+//! OKX `/api/v5/public/funding-rate` (逐个合约) 与 `/api/v5/public/mark-price` (全部永续合约的标记价格)。
+//! 只支持以计价币为保证金的 U 本位合约。
+
+use anyhow::{Context, Result};
+use std::collections::{HashMap, HashSet};
+use std::sync::RwLock;
+
+use crate::exchange::{ExchangeId, FundingRateUpdate};
+use crate::executor::OrderSide;
+use crate::strategies::split_symbol;
+
+/// 未知结算间隔时的默认值 (小时)
+const DEFAULT_INTERVAL_HOURS: f64 = 8.0;
+
+/// 单个合约的资金费率
+#[derive(Debug, Clone, Copy)]
+pub struct FundingRate {
+    /// 单期资金费率
+    pub rate: f64,
+    /// 下次结算时间 (epoch 毫秒，未知时为 0)
+    pub next_funding_time: i64,
+    /// 结算间隔 (小时)
+    pub interval_hours: f64,
+}
+
+impl Default for FundingRate {
+    fn default() -> Self {
+        Self {
+            rate: 0.0,
+            next_funding_time: 0,
+            interval_hours: DEFAULT_INTERVAL_HOURS,
+        }
+    }
+}
+
+impl FundingRate {
+    /// 持仓 [now, now + hold_ms] 内的结算次数；下次结算时间未知时按间隔折算为期望次数
+    pub fn settlements(&self, now: i64, hold_ms: i64) -> f64 {
+        let interval_ms = self.interval_hours.max(0.1) * 3_600_000.0;
+        if self.next_funding_time <= 0 {
+            return hold_ms as f64 / interval_ms;
+        }
+        let until = now + hold_ms;
+        let mut next = self.next_funding_time as f64;
+        // 缓存的下次结算时间已过去时顺延
+        while next < now as f64 {
+            next += interval_ms;
+        }
+        if next > until as f64 {
+            return 0.0;
+        }
+        1.0 + ((until as f64 - next) / interval_ms).floor()
+    }
+}
+
+/// 资金费率缓存
+#[derive(Debug, Default)]
+pub struct FundingCache {
+    rates: RwLock<HashMap<(ExchangeId, String), FundingRate>>,
+}
+
+lazy_static::lazy_static! {
+    pub static ref FUNDING_CACHE: FundingCache = FundingCache::default();
+}
+
+impl FundingCache {
+    /// 更新合约资金费率 (symbol 不带保证金后缀时视为以计价币为保证金)
+    pub fn update(&self, exchange: ExchangeId, symbol: &str, funding: FundingRate) {
+        let Some(key) = contract_key(symbol) else {
+            return;
+        };
+        if let Ok(mut rates) = self.rates.write() {
+            rates.insert((exchange, key), funding);
+        }
+    }
+
+    pub fn get(&self, exchange: ExchangeId, symbol: &str) -> Option<FundingRate> {
+        let key = contract_key(symbol)?;
+        self.rates.read().ok()?.get(&(exchange, key)).copied()
+    }
+
+    /// 按方向持有合约 hold_ms 毫秒的预计资金费 (占名义金额比例，正数为支出、负数为收入)。
+    /// 现货交易对或没有缓存费率的合约返回 0。
+    pub fn projected_cost(&self, exchange: ExchangeId, symbol: &str, side: OrderSide, now: i64, hold_ms: i64) -> f64 {
+        if !is_perpetual(symbol) || hold_ms <= 0 {
+            return 0.0;
+        }
+        let Some(funding) = self.get(exchange, symbol) else {
+            return 0.0;
+        };
+        let paid = funding.rate * funding.settlements(now, hold_ms);
+        match side {
+            OrderSide::Buy => paid,
+            OrderSide::Sell => -paid,
+        }
+    }
+}
+
+/// 是否为永续合约交易对 (`BTC/USDT:USDT` 形式)
+pub fn is_perpetual(symbol: &str) -> bool {
+    symbol.contains(':')
+}
+
+/// 永续合约键 BASE/QUOTE:MARGIN (未带保证金后缀时以计价币为保证金)
+pub fn contract_key(symbol: &str) -> Option<String> {
+    let (market, margin) = match symbol.split_once(':') {
+        Some((market, margin)) => (market, Some(margin.to_uppercase())),
+        None => (symbol, None),
+    };
+    let (base, quote) = split_symbol(market)?;
+    let margin = margin.unwrap_or_else(|| quote.clone());
+    Some(format!("{}/{}:{}", base, quote, margin))
+}
+
+/// 拉取一轮资金费率 (contracts 为合约键 BASE/QUOTE:MARGIN)
+pub async fn fetch_funding_rates(
+    client: &reqwest::Client,
+    exchange: ExchangeId,
+    base_url: &str,
+    contracts: &HashSet<String>,
+) -> Result<Vec<FundingRateUpdate>> {
+    let now = chrono::Utc::now().timestamp_millis();
+    match exchange {
+        ExchangeId::Binance => {
+            let body: serde_json::Value = client
+                .get(format!("{}/fapi/v1/premiumIndex", base_url))
+                .send()
+                .await?
+                .error_for_status()?
+                .json()
+                .await?;
+            parse_binance_premium_index(&body, contracts, now).context("premiumIndex 格式错误")
+        }
+        ExchangeId::Okx => {
+            let marks: serde_json::Value = client
+                .get(format!("{}/api/v5/public/mark-price", base_url))
+                .query(&[("instType", "SWAP")])
+                .send()
+                .await?
+                .error_for_status()?
+                .json()
+                .await?;
+            let marks = parse_okx_mark_prices(&marks).unwrap_or_default();
+            let mut out = vec![];
+            for contract in contracts {
+                let Some(inst_id) = okx_swap_inst_id(contract) else {
+                    continue;
+                };
+                let body: serde_json::Value = client
+                    .get(format!("{}/api/v5/public/funding-rate", base_url))
+                    .query(&[("instId", inst_id.as_str())])
+                    .send()
+                    .await?
+                    .error_for_status()?
+                    .json()
+                    .await?;
+                let mut update = parse_okx_funding_rate(&body, now).with_context(|| format!("{} 资金费率格式错误", inst_id))?;
+                update.mark_price = marks.get(&inst_id).copied();
+                out.push(update);
+            }
+            Ok(out)
+        }
+        _ => anyhow::bail!("{:?} 不支持资金费率轮询", exchange),
+    }
+}
+
+/// 解析 Binance `/fapi/v1/premiumIndex`:
+/// `[{"symbol":"BTCUSDT","markPrice":"11793.63","lastFundingRate":"0.00038246","nextFundingTime":1597392000000,"time":1597370495002}]`
+pub fn parse_binance_premium_index(
+    body: &serde_json::Value,
+    contracts: &HashSet<String>,
+    now: i64,
+) -> Option<Vec<FundingRateUpdate>> {
+    Some(
+        body.as_array()?
+            .iter()
+            .filter_map(|item| {
+                let (base, quote) = split_symbol(item.get("symbol")?.as_str()?)?;
+                let symbol = format!("{}/{}:{}", base, quote, quote);
+                if !contracts.contains(&symbol) {
+                    return None;
+                }
+                Some(FundingRateUpdate {
+                    exchange: ExchangeId::Binance,
+                    symbol,
+                    rate: item.get("lastFundingRate")?.as_str()?.parse().ok()?,
+                    next_funding_time: item.get("nextFundingTime").and_then(|v| v.as_i64()).unwrap_or(0),
+                    mark_price: item.get("markPrice").and_then(|v| v.as_str()).and_then(|v| v.parse().ok()),
+                    timestamp: item.get("time").and_then(|v| v.as_i64()).unwrap_or(now),
+                })
+            })
+            .collect(),
+    )
+}
+
+/// 解析 OKX `/api/v5/public/funding-rate`:
+/// `{"code":"0","data":[{"instId":"BTC-USDT-SWAP","fundingRate":"0.0001","fundingTime":"1703088000000","ts":"1703070685309"}]}`
+/// (fundingTime 为下次结算时间)
+pub fn parse_okx_funding_rate(body: &serde_json::Value, now: i64) -> Option<FundingRateUpdate> {
+    let data = body.get("data")?.as_array()?.first()?;
+    let inst_id = data.get("instId")?.as_str()?;
+    let (base, quote) = split_symbol(inst_id.strip_suffix("-SWAP")?)?;
+    let millis = |key: &str| data.get(key)?.as_str()?.parse::<i64>().ok();
+    Some(FundingRateUpdate {
+        exchange: ExchangeId::Okx,
+        symbol: format!("{}/{}:{}", base, quote, quote),
+        rate: data.get("fundingRate")?.as_str()?.parse().ok()?,
+        next_funding_time: millis("fundingTime").unwrap_or(0),
+        mark_price: None,
+        timestamp: millis("ts").unwrap_or(now),
+    })
+}
+
+/// 解析 OKX `/api/v5/public/mark-price`: instId -> 标记价格
+fn parse_okx_mark_prices(body: &serde_json::Value) -> Option<HashMap<String, f64>> {
+    Some(
+        body.get("data")?
+            .as_array()?
+            .iter()
+            .filter_map(|item| Some((item.get("instId")?.as_str()?.to_string(), item.get("markPx")?.as_str()?.parse().ok()?)))
+            .collect(),
+    )
+}
+
+/// 合约键 → OKX 永续合约 instId (`BTC/USDT:USDT` → `BTC-USDT-SWAP`)；币本位合约返回 None
+fn okx_swap_inst_id(contract: &str) -> Option<String> {
+    let (market, margin) = contract.split_once(':')?;
+    let (base, quote) = split_symbol(market)?;
+    (margin == quote).then(|| format!("{}-{}-SWAP", base, quote))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn usdt_and_usdc_margined_perps_are_separate_contracts() {
+        assert_eq!(contract_key("BTC/USDT:USDT").as_deref(), Some("BTC/USDT:USDT"));
+        assert_eq!(contract_key("BTC/USDC:usdc").as_deref(), Some("BTC/USDC:USDC"));
+        // 不带后缀时以计价币为保证金
+        assert_eq!(contract_key("BTC/USDC").as_deref(), Some("BTC/USDC:USDC"));
+
+        let cache = FundingCache::default();
+        let rate = |rate| FundingRate {
+            rate,
+            ..Default::default()
+        };
+        cache.update(ExchangeId::Binance, "BTC/USDT:USDT", rate(0.0001));
+        cache.update(ExchangeId::Binance, "BTC/USDC:USDC", rate(0.0003));
+        assert_eq!(cache.get(ExchangeId::Binance, "BTC/USDT:USDT").unwrap().rate, 0.0001);
+        assert_eq!(cache.get(ExchangeId::Binance, "BTC/USDC").unwrap().rate, 0.0003);
+        assert!(cache.get(ExchangeId::Okx, "BTC/USDC:USDC").is_none());
+
+        // 多头按各自合约的费率计资金费
+        let usdc = cache.projected_cost(ExchangeId::Binance, "BTC/USDC:USDC", OrderSide::Buy, 0, 8 * 3_600_000);
+        let usdt = cache.projected_cost(ExchangeId::Binance, "BTC/USDT:USDT", OrderSide::Sell, 0, 8 * 3_600_000);
+        assert!((usdc - 0.0003).abs() < 1e-12);
+        assert!((usdt + 0.0001).abs() < 1e-12);
+    }
+
+    #[test]
+    fn premium_index_rows_split_by_margin_asset() {
+        let body = serde_json::json!([
+            {"symbol": "BTCUSDT", "markPrice": "100.0", "lastFundingRate": "0.0001", "nextFundingTime": 8, "time": 1},
+            {"symbol": "BTCUSDC", "markPrice": "100.2", "lastFundingRate": "0.0003", "nextFundingTime": 8, "time": 1},
+            {"symbol": "ETHUSDC", "markPrice": "5.0", "lastFundingRate": "0.0002", "nextFundingTime": 8, "time": 1}
+        ]);
+        let contracts: HashSet<String> = ["BTC/USDT:USDT", "BTC/USDC:USDC"].iter().map(|s| s.to_string()).collect();
+        let updates = parse_binance_premium_index(&body, &contracts, 0).unwrap();
+        let rates: Vec<(&str, f64, Option<f64>)> =
+            updates.iter().map(|u| (u.symbol.as_str(), u.rate, u.mark_price)).collect();
+        assert_eq!(
+            rates,
+            vec![("BTC/USDT:USDT", 0.0001, Some(100.0)), ("BTC/USDC:USDC", 0.0003, Some(100.2))]
+        );
+
+        assert_eq!(okx_swap_inst_id("BTC/USDC:USDC").as_deref(), Some("BTC-USDC-SWAP"));
+        assert_eq!(okx_swap_inst_id("BTC/USD:BTC"), None);
+    }
+}
@@ -0,0 +1,151 @@
+//! 行情健康度跟踪
+//!
+//! 记录每个交易对最近一次收到行情的时间。超过阈值未更新的交易对视为陈旧，
+//! 涉及陈旧交易对的信号会被抑制；交易所断线期间其全部交易对视为陈旧，
+//! 直到重连后收到第一笔行情。
+//!
+//! 部分交易所 / 频道的行情不带买一卖一 (为 0，或直接填成最新成交价)，策略据此计算出的
+//! 价差并不可成交。缺失报价的行情默认不分发给策略，涉及该交易对的信号同样被抑制，
+//! 并向交易所补订阅最优报价频道 (ENGINE_MISSING_QUOTE / ENGINE_MISSING_QUOTE_RESUBSCRIBE)。
+
+use std::collections::{HashMap, HashSet};
+
+use crate::exchange::{ExchangeId, Symbol, Ticker};
+use crate::executor::parse_symbols_from_path;
+use crate::symbols::normalize_symbol;
+
+/// 行情健康度
+pub struct FeedHealth {
+    last_tick: HashMap<(ExchangeId, String), i64>,
+    /// 已断线、尚未收到重连后行情的交易所
+    disconnected: HashSet<ExchangeId>,
+    stale_after_ms: i64,
+    /// 缺失报价的行情是否抑制
+    suppress_missing_quote: bool,
+    /// 缺失报价时是否补订阅最优报价频道
+    resubscribe_quotes: bool,
+    /// 最近一条行情缺失报价的交易对
+    unquoted: HashSet<(ExchangeId, String)>,
+    /// 已请求补订阅报价频道的交易对
+    quote_requested: HashSet<(ExchangeId, String)>,
+}
+
+impl FeedHealth {
+    /// ENGINE_STALE_TICKER_MS 指定陈旧阈值 (默认 5000 毫秒)
+    pub fn from_env() -> Self {
+        let stale_after_ms = std::env::var("ENGINE_STALE_TICKER_MS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .filter(|v| *v > 0)
+            .unwrap_or(5_000);
+        // ENGINE_MISSING_QUOTE: suppress (默认) 抑制缺失报价的行情；allow 按原样分发
+        let suppress_missing_quote = match std::env::var("ENGINE_MISSING_QUOTE") {
+            Ok(v) if v.trim().eq_ignore_ascii_case("allow") => false,
+            Ok(v) if !v.trim().is_empty() && !v.trim().eq_ignore_ascii_case("suppress") => {
+                tracing::warn!("ENGINE_MISSING_QUOTE 取值 {} 无效，使用 suppress", v);
+                true
+            }
+            _ => true,
+        };
+        let resubscribe_quotes = std::env::var("ENGINE_MISSING_QUOTE_RESUBSCRIBE")
+            .map(|v| !matches!(v.as_str(), "0" | "false" | "False"))
+            .unwrap_or(true);
+        Self {
+            last_tick: HashMap::new(),
+            disconnected: HashSet::new(),
+            stale_after_ms,
+            suppress_missing_quote,
+            resubscribe_quotes,
+            unquoted: HashSet::new(),
+            quote_requested: HashSet::new(),
+        }
+    }
+
+    /// 记录行情 (使用本地接收时间，避免交易所时钟偏差)
+    pub fn record(&mut self, ticker: &Ticker, now: i64) {
+        self.disconnected.remove(&ticker.exchange);
+        self.last_tick
+            .insert((ticker.exchange, normalize_symbol(ticker.exchange, &ticker.symbol)), now);
+    }
+
+    /// 记录行情的报价状态；返回 None 表示报价可用 (或未开启抑制)，
+    /// Some(true) 表示该交易对刚开始缺失报价，Some(false) 表示持续缺失
+    pub fn record_quote(&mut self, ticker: &Ticker) -> Option<bool> {
+        if !self.suppress_missing_quote {
+            return None;
+        }
+        let key = (ticker.exchange, normalize_symbol(ticker.exchange, &ticker.symbol));
+        if has_quote(ticker) {
+            self.unquoted.remove(&key);
+            return None;
+        }
+        Some(self.unquoted.insert(key))
+    }
+
+    /// 是否需要为该交易对补订阅报价频道 (每个交易对只请求一次)
+    pub fn take_quote_request(&mut self, exchange: ExchangeId, symbol: &str) -> bool {
+        self.resubscribe_quotes && self.quote_requested.insert((exchange, normalize_symbol(exchange, symbol)))
+    }
+
+    /// 交易所断线
+    pub fn on_disconnect(&mut self, exchange: ExchangeId) {
+        self.disconnected.insert(exchange);
+    }
+
+    pub fn is_connected(&self, exchange: ExchangeId) -> bool {
+        !self.disconnected.contains(&exchange)
+    }
+
+    /// 交易对是否陈旧 (从未收到行情的交易对不视为陈旧)
+    pub fn is_stale(&self, exchange: ExchangeId, symbol: &str, now: i64) -> bool {
+        match self.last_tick.get(&(exchange, normalize_symbol(exchange, symbol))) {
+            Some(last) => self.disconnected.contains(&exchange) || now - last > self.stale_after_ms,
+            None => false,
+        }
+    }
+
+    /// 返回信号路径中第一个陈旧的交易对。
+    /// 路径既可以是交易对序列 (BTC/USDT->ETH/USDT)，也可以是币种序列 (USDT->BTC->ETH->USDT)，
+    /// 后者按相邻币种推断交易对 (BTC/USDT 或 USDT/BTC，取已收到过行情的一个)。
+    pub fn stale_in_path(&self, exchange: ExchangeId, path: &str, now: i64) -> Option<String> {
+        self.path_symbols(exchange, path)
+            .into_iter()
+            .find(|s| self.is_stale(exchange, s, now))
+    }
+
+    /// 返回信号路径中第一个缺失报价的交易对
+    pub fn unquoted_in_path(&self, exchange: ExchangeId, path: &str) -> Option<String> {
+        if self.unquoted.is_empty() {
+            return None;
+        }
+        self.path_symbols(exchange, path)
+            .into_iter()
+            .find(|s| self.unquoted.contains(&(exchange, normalize_symbol(exchange, s))))
+    }
+
+    /// 路径涉及的、已收到过行情的交易对
+    fn path_symbols(&self, exchange: ExchangeId, path: &str) -> Vec<String> {
+        let parts = parse_symbols_from_path(path);
+        let known = |s: &str| self.last_tick.contains_key(&(exchange, normalize_symbol(exchange, s)));
+        let mut symbols: Vec<String> = parts.iter().filter(|p| known(p)).cloned().collect();
+        for pair in parts.windows(2) {
+            let forward = Symbol::new(&pair[0], &pair[1]).to_string();
+            let backward = Symbol::new(&pair[1], &pair[0]).to_string();
+            if known(&forward) {
+                symbols.push(forward);
+            } else if known(&backward) {
+                symbols.push(backward);
+            }
+        }
+        symbols
+    }
+}
+
+/// 行情是否带有可成交的买一卖一: 任一侧为 0、买卖倒挂，或买一卖一都等于最新成交价
+/// (交易所以成交价填充报价字段) 均视为缺失
+pub fn has_quote(ticker: &Ticker) -> bool {
+    ticker.bid > 0.0
+        && ticker.ask > 0.0
+        && ticker.bid <= ticker.ask
+        && !(ticker.bid == ticker.ask && ticker.bid == ticker.last)
+}
@@ -0,0 +1,151 @@
+//! 机会热力图
+//!
+//! 除了越过阈值发出信号的路径，交易员还需要实时看到所有监控中的三角形 / 配对离盈利还有多远。
+//! 开启后引擎按固定间隔向各策略取当前每条监控路径的扣费后收益 (基点，与信号的 `edge_bps` 同口径)，
+//! 整体写入 Redis `metrics:opportunity_heatmap` (JSON，按策略分组的 `路径 -> edge_bps`，
+//! 行情不全的路径为 null) 供看板展示。
+//!
+//! 遍历所有路径有一定开销，默认关闭: ENGINE_HEATMAP 开启，ENGINE_HEATMAP_INTERVAL_MS 指定计算间隔 (默认 1000)。
+
+use anyhow::Result;
+use redis::aio::ConnectionManager;
+use redis::AsyncCommands;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use crate::strategy::StrategyType;
+
+/// Redis key
+const REDIS_KEY: &str = "metrics:opportunity_heatmap";
+
+/// 热力图配置
+#[derive(Debug, Clone)]
+pub struct HeatmapConfig {
+    pub enabled: bool,
+    pub interval: Duration,
+}
+
+impl HeatmapConfig {
+    pub fn from_env() -> Self {
+        let enabled = std::env::var("ENGINE_HEATMAP")
+            .map(|v| matches!(v.as_str(), "1" | "true" | "True"))
+            .unwrap_or(false);
+        let interval_ms = std::env::var("ENGINE_HEATMAP_INTERVAL_MS")
+            .ok()
+            .and_then(|v| v.parse::<u64>().ok())
+            .unwrap_or(1_000)
+            .max(100);
+        Self {
+            enabled,
+            interval: Duration::from_millis(interval_ms),
+        }
+    }
+}
+
+/// 单个策略的路径收益
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StrategyHeat {
+    pub strategy_type: StrategyType,
+    /// 路径 -> 当前收益 (基点，保留两位小数；行情不全时为 None)
+    pub paths: BTreeMap<String, Option<f64>>,
+}
+
+/// 机会热力图
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Heatmap {
+    pub timestamp: i64,
+    /// 策略 ID -> 路径收益
+    pub strategies: BTreeMap<String, StrategyHeat>,
+}
+
+impl Heatmap {
+    pub fn new(timestamp: i64) -> Self {
+        Self {
+            timestamp,
+            strategies: BTreeMap::new(),
+        }
+    }
+
+    /// 加入一个策略的路径收益 (收益率，内部换算为基点)；没有监控路径的策略不加入
+    pub fn insert(&mut self, strategy_id: &str, strategy_type: StrategyType, edges: Vec<(String, Option<f64>)>) {
+        if edges.is_empty() {
+            return;
+        }
+        let paths = edges
+            .into_iter()
+            .map(|(path, rate)| (path, rate.map(|r| (r * 1_000_000.0).round() / 100.0)))
+            .collect();
+        self.strategies
+            .insert(strategy_id.to_string(), StrategyHeat { strategy_type, paths });
+    }
+
+    /// 监控路径总数
+    pub fn len(&self) -> usize {
+        self.strategies.values().map(|s| s.paths.len()).sum()
+    }
+
+    pub async fn publish(&self, conn: &mut ConnectionManager) -> Result<()> {
+        let payload = serde_json::to_string(self)?;
+        let _: () = conn.set(REDIS_KEY, payload).await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fake_redis::FakeRedis;
+    use crate::strategy::StrategyConfig;
+
+    #[test]
+    fn edges_are_converted_to_rounded_bps() {
+        let mut heatmap = Heatmap::new(1_000);
+        heatmap.insert(
+            "tri",
+            StrategyType::Triangular,
+            vec![("USDT->BTC->ETH->USDT".to_string(), Some(0.00123456)), ("USDT->BNB->BTC->USDT".to_string(), Some(-0.0005))],
+        );
+        heatmap.insert("pair", StrategyType::Pair, vec![("BTC/USDT->ETH/USDT".to_string(), None)]);
+        // 没有监控路径的策略不加入
+        heatmap.insert("grid", StrategyType::Grid, vec![]);
+
+        assert_eq!(heatmap.len(), 3);
+        assert!(!heatmap.strategies.contains_key("grid"));
+        let tri = &heatmap.strategies["tri"].paths;
+        assert_eq!(tri["USDT->BTC->ETH->USDT"], Some(12.35));
+        assert_eq!(tri["USDT->BNB->BTC->USDT"], Some(-5.0));
+        assert_eq!(heatmap.strategies["pair"].paths["BTC/USDT->ETH/USDT"], None);
+    }
+
+    #[tokio::test]
+    async fn strategy_paths_are_published_with_missing_quotes_as_null() {
+        let config: StrategyConfig = serde_json::from_value(serde_json::json!({
+            "id": "tri", "strategy_type": "triangular", "name": "tri", "is_enabled": true, "priority": 1,
+            "config": {"triangles": [["USDT", "BTC", "ETH"], ["USDT", "BTC", "BNB"]]},
+        }))
+        .unwrap();
+        let mut strategy = crate::strategies::build_strategy(&config).unwrap();
+        for (symbol, bid, ask) in [("BTC/USDT", 99.9, 100.0), ("ETH/BTC", 0.0499, 0.05), ("ETH/USDT", 5.1, 5.11)] {
+            let ticker = serde_json::from_value(serde_json::json!({
+                "exchange": "binance", "symbol": symbol, "bid": bid, "ask": ask,
+                "last": bid, "volume": 1000.0, "timestamp": 1_000,
+            }))
+            .unwrap();
+            strategy.on_ticker(&ticker).await;
+        }
+        let mut heatmap = Heatmap::new(1_000);
+        heatmap.insert(strategy.id(), strategy.strategy_type(), strategy.path_edges());
+
+        let redis = FakeRedis::start().await;
+        heatmap.publish(&mut redis.connection().await).await.unwrap();
+        let published: serde_json::Value = serde_json::from_str(&redis.get(REDIS_KEY).unwrap()).unwrap();
+        assert_eq!(published["timestamp"], 1_000);
+        assert_eq!(published["strategies"]["tri"]["strategyType"], "triangular");
+        let paths = &published["strategies"]["tri"]["paths"];
+        assert!(paths["USDT->BTC->ETH->USDT"].as_f64().unwrap() > 0.0, "{}", paths);
+        // BNB 没有行情
+        assert!(paths["USDT->BTC->BNB->USDT"].is_null(), "{}", paths);
+    }
+}
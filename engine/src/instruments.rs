@@ -0,0 +1,856 @@
+//! 交易对元数据 (价格精度 / 数量步长 / 最小下单量 / 交易状态 / 手续费 / 流动性快照)
+//!
+//! 从交易所 REST 接口 (Binance exchangeInfo 与 24hr ticker、OKX instruments 与 tickers) 拉取，按交易所写入本地 JSON 缓存
+//! 与 Redis (`instruments:{exchange}`)。启动时直接使用已有缓存 (即使略微过期)，
+//! 只有完全没有缓存时才同步拉取；之后在后台按交易所加随机抖动定期刷新，避免重启时集中请求。
+//! 每个交易所记录元数据的获取时间，超过硬性上限 (ENGINE_SYMBOL_CACHE_MAX_AGE_SECS) 时实盘下单拒绝使用。
+//! 损坏或版本不符的缓存直接丢弃并记录日志。
+//!
+//! 启动加载的索引同时放在全局 [`INSTRUMENTS`]，策略据此查询最小名义金额以确定信号规模。
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use redis::AsyncCommands;
+use std::path::PathBuf;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use tracing::{info, warn};
+
+use crate::db::SharedRedis;
+use crate::engine::{Clock, SystemClock};
+use crate::exchange::{ExchangeId, RestEndpoints};
+use crate::executor::OrderSide;
+
+/// 交易对元数据
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InstrumentInfo {
+    pub exchange: ExchangeId,
+    /// 统一格式 BASE/QUOTE
+    pub symbol: String,
+    /// 交易所原生交易对 (BTCUSDT / BTC-USDT)
+    pub exchange_symbol: String,
+    pub base: String,
+    pub quote: String,
+    pub price_tick: f64,
+    pub qty_step: f64,
+    pub min_qty: f64,
+    pub min_notional: f64,
+    /// 交易状态 (`trading` 表示可交易，其余为交易所原始状态)
+    #[serde(default = "default_status")]
+    pub status: String,
+    /// 挂单手续费率 (0 表示未知)
+    #[serde(default)]
+    pub maker_fee: f64,
+    /// 吃单手续费率 (0 表示未知)
+    #[serde(default)]
+    pub taker_fee: f64,
+    /// 获取元数据时的流动性快照 (行情接口失败时为空)
+    #[serde(default)]
+    pub liquidity: Option<LiquiditySnapshot>,
+}
+
+/// 流动性快照: 24 小时成交额与买一 / 卖一价
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct LiquiditySnapshot {
+    /// 24 小时成交额 (计价币)
+    pub quote_volume_24h: f64,
+    pub bid: f64,
+    pub ask: f64,
+}
+
+fn default_status() -> String {
+    "trading".to_string()
+}
+
+impl InstrumentInfo {
+    /// 数量按步长向下取整
+    pub fn round_qty(&self, qty: f64) -> f64 {
+        round_down(qty, self.qty_step)
+    }
+
+    pub fn is_trading(&self) -> bool {
+        self.status == "trading"
+    }
+
+    /// 限价按最小变动价位取整且不劣于原价: 买入向下取整，卖出向上取整
+    pub fn round_price_for(&self, side: OrderSide, price: f64) -> f64 {
+        match side {
+            OrderSide::Buy => round_down(price, self.price_tick),
+            OrderSide::Sell => round_up(price, self.price_tick),
+        }
+    }
+
+    /// 名义金额是否满足最小名义金额 (未知时视为满足)
+    pub fn meets_min_notional(&self, amount: f64, price: f64) -> bool {
+        self.min_notional <= 0.0 || amount * price >= self.min_notional * (1.0 - 1e-9)
+    }
+}
+
+fn round_down(value: f64, step: f64) -> f64 {
+    if step <= 0.0 {
+        return value;
+    }
+    // 加一个极小量，避免 0.3 / 0.1 = 2.9999999 这类浮点误差
+    snap((value / step + 1e-9).floor() * step, step)
+}
+
+fn round_up(value: f64, step: f64) -> f64 {
+    if step <= 0.0 {
+        return value;
+    }
+    snap((value / step - 1e-9).ceil() * step, step)
+}
+
+/// 按步长的小数位数四舍五入，去掉 3 * 0.1 = 0.30000000000000004 这类浮点尾数
+fn snap(value: f64, step: f64) -> f64 {
+    let decimals = format!("{}", step)
+        .split_once('.')
+        .map(|(_, frac)| frac.trim_end_matches('0').len() as i32)
+        .unwrap_or(0)
+        .min(12);
+    let scale = 10f64.powi(decimals);
+    (value * scale).round() / scale
+}
+
+/// 元数据来源
+#[async_trait]
+pub trait InstrumentSource: Send + Sync {
+    async fn fetch(&self, exchange: ExchangeId) -> Result<Vec<InstrumentInfo>>;
+}
+
+/// 交易所 REST 接口
+pub struct RestInstrumentSource {
+    client: reqwest::Client,
+    endpoints: RestEndpoints,
+}
+
+impl RestInstrumentSource {
+    pub fn new(endpoints: RestEndpoints) -> Self {
+        Self {
+            endpoints,
+            client: reqwest::Client::builder()
+                .timeout(Duration::from_secs(10))
+                .build()
+                .unwrap_or_else(|_| reqwest::Client::new()),
+        }
+    }
+
+    async fn get_json(&self, url: String) -> Result<serde_json::Value> {
+        Ok(self.client.get(url).send().await?.error_for_status()?.json().await?)
+    }
+
+    /// 按交易所原生交易对索引的流动性快照；行情接口失败时只记日志，不影响精度元数据
+    async fn liquidity(&self, exchange: ExchangeId) -> HashMap<String, LiquiditySnapshot> {
+        let base = self.endpoints.base_url(exchange);
+        let (url, list, symbol, volume, bid, ask) = match exchange {
+            ExchangeId::Binance => (
+                format!("{}/api/v3/ticker/24hr", base),
+                None,
+                "symbol",
+                "quoteVolume",
+                "bidPrice",
+                "askPrice",
+            ),
+            ExchangeId::Okx => (
+                format!("{}/api/v5/market/tickers?instType=SPOT", base),
+                Some("data"),
+                "instId",
+                "volCcy24h",
+                "bidPx",
+                "askPx",
+            ),
+            _ => return HashMap::new(),
+        };
+        let body = match self.get_json(url).await {
+            Ok(body) => body,
+            Err(e) => {
+                warn!("{:?} 拉取 24 小时行情失败，交易对缓存不含流动性快照: {}", exchange, e);
+                return HashMap::new();
+            }
+        };
+        let items = match list {
+            Some(field) => body.get(field),
+            None => Some(&body),
+        };
+        items
+            .and_then(|v| v.as_array())
+            .into_iter()
+            .flatten()
+            .filter_map(|item| {
+                let field = |name: &str| item.get(name).and_then(str_f64).unwrap_or(0.0);
+                Some((
+                    item.get(symbol)?.as_str()?.to_string(),
+                    LiquiditySnapshot {
+                        quote_volume_24h: field(volume),
+                        bid: field(bid),
+                        ask: field(ask),
+                    },
+                ))
+            })
+            .collect()
+    }
+
+    async fn fetch_binance(&self) -> Result<Vec<InstrumentInfo>> {
+        let body: serde_json::Value = self
+            .client
+            .get(format!("{}/api/v3/exchangeInfo", self.endpoints.base_url(ExchangeId::Binance)))
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        let symbols = body
+            .get("symbols")
+            .and_then(|v| v.as_array())
+            .context("exchangeInfo 缺少 symbols")?;
+        let liquidity = self.liquidity(ExchangeId::Binance).await;
+        let (maker_fee, taker_fee) = base_fees(ExchangeId::Binance);
+        let mut out = vec![];
+        for item in symbols {
+            let status = match item.get("status").and_then(|v| v.as_str()) {
+                Some("TRADING") => "trading".to_string(),
+                Some(other) => other.to_lowercase(),
+                None => continue,
+            };
+            let (Some(native), Some(base), Some(quote)) = (
+                item.get("symbol").and_then(|v| v.as_str()),
+                item.get("baseAsset").and_then(|v| v.as_str()),
+                item.get("quoteAsset").and_then(|v| v.as_str()),
+            ) else {
+                continue;
+            };
+            let filters = item.get("filters").and_then(|v| v.as_array());
+            let filter = |kind: &str, field: &str| -> f64 {
+                filters
+                    .and_then(|f| {
+                        f.iter()
+                            .find(|x| x.get("filterType").and_then(|v| v.as_str()) == Some(kind))
+                    })
+                    .and_then(|x| x.get(field))
+                    .and_then(str_f64)
+                    .unwrap_or(0.0)
+            };
+            let min_notional = match filter("NOTIONAL", "minNotional") {
+                v if v > 0.0 => v,
+                _ => filter("MIN_NOTIONAL", "minNotional"),
+            };
+            out.push(InstrumentInfo {
+                exchange: ExchangeId::Binance,
+                symbol: format!("{}/{}", base, quote),
+                exchange_symbol: native.to_string(),
+                base: base.to_string(),
+                quote: quote.to_string(),
+                price_tick: filter("PRICE_FILTER", "tickSize"),
+                qty_step: filter("LOT_SIZE", "stepSize"),
+                min_qty: filter("LOT_SIZE", "minQty"),
+                min_notional,
+                status,
+                maker_fee,
+                taker_fee,
+                liquidity: liquidity.get(native).copied(),
+            });
+        }
+        Ok(out)
+    }
+
+    async fn fetch_okx(&self) -> Result<Vec<InstrumentInfo>> {
+        let body: serde_json::Value = self
+            .client
+            .get(format!(
+                "{}/api/v5/public/instruments?instType=SPOT",
+                self.endpoints.base_url(ExchangeId::Okx)
+            ))
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        let data = body
+            .get("data")
+            .and_then(|v| v.as_array())
+            .context("instruments 缺少 data")?;
+        let liquidity = self.liquidity(ExchangeId::Okx).await;
+        let (maker_fee, taker_fee) = base_fees(ExchangeId::Okx);
+        let mut out = vec![];
+        for item in data {
+            let status = match item.get("state").and_then(|v| v.as_str()) {
+                Some("live") => "trading".to_string(),
+                Some(other) => other.to_lowercase(),
+                None => continue,
+            };
+            let (Some(native), Some(base), Some(quote)) = (
+                item.get("instId").and_then(|v| v.as_str()),
+                item.get("baseCcy").and_then(|v| v.as_str()),
+                item.get("quoteCcy").and_then(|v| v.as_str()),
+            ) else {
+                continue;
+            };
+            let field = |name: &str| item.get(name).and_then(str_f64).unwrap_or(0.0);
+            out.push(InstrumentInfo {
+                exchange: ExchangeId::Okx,
+                symbol: format!("{}/{}", base, quote),
+                exchange_symbol: native.to_string(),
+                base: base.to_string(),
+                quote: quote.to_string(),
+                price_tick: field("tickSz"),
+                qty_step: field("lotSz"),
+                min_qty: field("minSz"),
+                min_notional: 0.0,
+                status,
+                maker_fee,
+                taker_fee,
+                liquidity: liquidity.get(native).copied(),
+            });
+        }
+        Ok(out)
+    }
+}
+
+#[async_trait]
+impl InstrumentSource for RestInstrumentSource {
+    async fn fetch(&self, exchange: ExchangeId) -> Result<Vec<InstrumentInfo>> {
+        match exchange {
+            ExchangeId::Binance => self.fetch_binance().await,
+            ExchangeId::Okx => self.fetch_okx().await,
+            other => Err(anyhow::anyhow!("{:?} 暂不支持拉取交易对元数据", other)),
+        }
+    }
+}
+
+/// 交易所公开费率表的普通用户档位 (挂单, 吃单)；元数据接口不带费率，账户实际费率需签名接口查询
+fn base_fees(exchange: ExchangeId) -> (f64, f64) {
+    match exchange {
+        ExchangeId::Binance => (0.001, 0.001),
+        ExchangeId::Okx => (0.0008, 0.001),
+        _ => (0.0, 0.0),
+    }
+}
+
+fn str_f64(value: &serde_json::Value) -> Option<f64> {
+    value
+        .as_str()
+        .and_then(|s| s.parse().ok())
+        .or_else(|| value.as_f64())
+}
+
+/// 缓存格式版本 (字段变化时递增，旧版本缓存直接丢弃)
+const CACHE_SCHEMA_VERSION: u32 = 3;
+/// Redis key 前缀
+const REDIS_KEY_PREFIX: &str = "instruments:";
+
+/// 缓存内容 (本地文件与 Redis 相同)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CacheFile {
+    #[serde(default)]
+    pub schema_version: u32,
+    pub fetched_at: i64,
+    pub instruments: Vec<InstrumentInfo>,
+}
+
+impl CacheFile {
+    /// 解析缓存；损坏或版本不符时返回 None 并记录日志
+    fn parse(raw: &[u8], origin: &str) -> Option<Self> {
+        match serde_json::from_slice::<CacheFile>(raw) {
+            Ok(cache) if cache.schema_version == CACHE_SCHEMA_VERSION => Some(cache),
+            Ok(cache) => {
+                warn!(
+                    "交易对缓存 {} 版本 {} 与当前版本 {} 不符，丢弃",
+                    origin, cache.schema_version, CACHE_SCHEMA_VERSION
+                );
+                None
+            }
+            Err(e) => {
+                warn!("交易对缓存 {} 已损坏，丢弃: {}", origin, e);
+                None
+            }
+        }
+    }
+}
+
+/// 带本地文件 / Redis 缓存的元数据加载器
+pub struct InstrumentLoader {
+    source: Box<dyn InstrumentSource>,
+    /// 缓存目录 (None 表示不使用本地缓存)
+    cache_dir: Option<PathBuf>,
+    redis: Option<SharedRedis>,
+    ttl_ms: i64,
+    /// 后台刷新的最大随机抖动
+    jitter_ms: i64,
+    /// 元数据硬性过期上限
+    max_age_ms: i64,
+    /// 后台刷新判断缓存时长用的时钟
+    clock: Arc<dyn Clock>,
+    /// 后台刷新写入索引后通知
+    refreshed: tokio::sync::Notify,
+}
+
+impl InstrumentLoader {
+    pub fn new(source: Box<dyn InstrumentSource>, cache_dir: Option<PathBuf>, ttl: Duration) -> Self {
+        let ttl_ms = ttl.as_millis() as i64;
+        Self {
+            source,
+            cache_dir,
+            redis: None,
+            ttl_ms,
+            jitter_ms: 300_000,
+            max_age_ms: ttl_ms.saturating_mul(7),
+            clock: Arc::new(SystemClock),
+            refreshed: tokio::sync::Notify::new(),
+        }
+    }
+
+    /// 使用 REST 来源，缓存配置来自环境变量
+    /// (ENGINE_SYMBOL_CACHE_DIR 默认 .cache/instruments，置空关闭；ENGINE_SYMBOL_CACHE_TTL_SECS 默认 86400；
+    /// ENGINE_SYMBOL_REFRESH_JITTER_SECS 默认 300；ENGINE_SYMBOL_CACHE_MAX_AGE_SECS 默认 604800)
+    pub fn from_env(endpoints: RestEndpoints) -> Self {
+        let cache_dir = std::env::var("ENGINE_SYMBOL_CACHE_DIR")
+            .unwrap_or_else(|_| ".cache/instruments".to_string());
+        let secs = |name: &str, default: u64| -> u64 {
+            std::env::var(name)
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(default)
+        };
+        let mut loader = Self::new(
+            Box::new(RestInstrumentSource::new(endpoints)),
+            Some(cache_dir).filter(|d| !d.is_empty()).map(PathBuf::from),
+            Duration::from_secs(secs("ENGINE_SYMBOL_CACHE_TTL_SECS", 86_400)),
+        );
+        loader.jitter_ms = secs("ENGINE_SYMBOL_REFRESH_JITTER_SECS", 300) as i64 * 1_000;
+        loader.max_age_ms = secs("ENGINE_SYMBOL_CACHE_MAX_AGE_SECS", 604_800) as i64 * 1_000;
+        loader
+    }
+
+    /// 同时把缓存写入 Redis
+    pub fn with_redis(mut self, redis: Option<SharedRedis>) -> Self {
+        self.redis = redis;
+        self
+    }
+
+    pub fn max_age_ms(&self) -> i64 {
+        self.max_age_ms
+    }
+
+    fn cache_path(&self, exchange: ExchangeId) -> Option<PathBuf> {
+        Some(self.cache_dir.as_ref()?.join(format!("{}.json", exchange.key())))
+    }
+
+    fn read_file(&self, exchange: ExchangeId) -> Option<CacheFile> {
+        let path = self.cache_path(exchange)?;
+        let raw = std::fs::read(&path).ok()?;
+        CacheFile::parse(&raw, &path.display().to_string())
+    }
+
+    fn write_file(&self, exchange: ExchangeId, cache: &CacheFile) -> Result<()> {
+        let Some(path) = self.cache_path(exchange) else {
+            return Ok(());
+        };
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        // 先写临时文件再改名，避免中断时留下半个文件
+        let tmp = path.with_extension("json.tmp");
+        std::fs::write(&tmp, serde_json::to_vec(cache)?)?;
+        std::fs::rename(&tmp, &path)?;
+        Ok(())
+    }
+
+    async fn read_redis(&self, exchange: ExchangeId) -> Option<CacheFile> {
+        let redis = self.redis.as_ref()?;
+        let key = format!("{}{}", REDIS_KEY_PREFIX, exchange.key());
+        let mut conn = redis.connection().await.ok()?;
+        let raw: Option<Vec<u8>> = conn.get(&key).await.ok()?;
+        CacheFile::parse(&raw?, &key)
+    }
+
+    async fn write_redis(&self, exchange: ExchangeId, cache: &CacheFile) -> Result<()> {
+        let Some(redis) = &self.redis else {
+            return Ok(());
+        };
+        let mut conn = redis.connection().await?;
+        let _: () = conn
+            .set(format!("{}{}", REDIS_KEY_PREFIX, exchange.key()), serde_json::to_vec(cache)?)
+            .await?;
+        Ok(())
+    }
+
+    /// 读取已有缓存 (本地文件与 Redis 中较新的一份，不论是否过期)
+    pub async fn load_cached(&self, exchange: ExchangeId) -> Option<CacheFile> {
+        let file = self.read_file(exchange);
+        let redis = self.read_redis(exchange).await;
+        match (file, redis) {
+            (Some(a), Some(b)) => Some(if b.fetched_at > a.fetched_at { b } else { a }),
+            (a, b) => a.or(b),
+        }
+    }
+
+    /// 从交易所拉取并写入本地文件与 Redis
+    pub async fn refresh(&self, exchange: ExchangeId, now_ms: i64) -> Result<CacheFile> {
+        let instruments = self.source.fetch(exchange).await?;
+        let cache = CacheFile {
+            schema_version: CACHE_SCHEMA_VERSION,
+            fetched_at: now_ms,
+            instruments,
+        };
+        if let Err(e) = self.write_file(exchange, &cache) {
+            warn!("{:?} 交易对缓存写入失败: {}", exchange, e);
+        }
+        if let Err(e) = self.write_redis(exchange, &cache).await {
+            warn!("{:?} 交易对缓存写入 Redis 失败: {}", exchange, e);
+        }
+        info!("{:?} 已拉取交易对元数据 ({} 个)", exchange, cache.instruments.len());
+        Ok(cache)
+    }
+
+    /// 启动加载: 有缓存时立即使用 (即使已过期，由后台刷新)；没有缓存时同步拉取
+    pub async fn load(&self, exchange: ExchangeId, now_ms: i64) -> Result<CacheFile> {
+        if let Some(cache) = self.load_cached(exchange).await {
+            info!(
+                "{:?} 使用交易对缓存 ({} 个，已缓存 {} 秒)",
+                exchange,
+                cache.instruments.len(),
+                (now_ms - cache.fetched_at) / 1_000
+            );
+            return Ok(cache);
+        }
+        self.refresh(exchange, now_ms).await
+    }
+
+    /// 距下次刷新的等待时间: 缓存过期前等待，过期后立即刷新，均加随机抖动
+    fn next_refresh_delay(&self, age_ms: Option<i64>) -> Duration {
+        let base = match age_ms {
+            Some(age) if age < self.ttl_ms => self.ttl_ms - age,
+            _ => 0,
+        };
+        let jitter = if self.jitter_ms > 0 {
+            (uuid::Uuid::new_v4().as_u128() % self.jitter_ms as u128) as i64
+        } else {
+            0
+        };
+        Duration::from_millis((base + jitter).max(0) as u64)
+    }
+}
+
+/// 后台按交易所刷新元数据 (失败时一分钟后重试，期间继续使用旧数据)
+pub fn spawn_refresh(loader: Arc<InstrumentLoader>, registry: SharedInstruments, exchanges: Vec<ExchangeId>) {
+    for exchange in exchanges {
+        let loader = loader.clone();
+        let registry = registry.clone();
+        tokio::spawn(async move {
+            loop {
+                let now = loader.clock.now_ms();
+                let age = registry.read().ok().and_then(|r| r.age_ms(exchange, now));
+                tokio::time::sleep(loader.next_refresh_delay(age)).await;
+                let now = loader.clock.now_ms();
+                match loader.refresh(exchange, now).await {
+                    Ok(cache) => {
+                        if let Ok(mut registry) = registry.write() {
+                            registry.replace(exchange, cache.instruments, cache.fetched_at);
+                        }
+                        loader.refreshed.notify_one();
+                    }
+                    Err(e) => {
+                        warn!("{:?} 后台刷新交易对元数据失败，继续使用缓存: {}", exchange, e);
+                        tokio::time::sleep(Duration::from_secs(60)).await;
+                    }
+                }
+            }
+        });
+    }
+}
+
+/// 执行器与后台刷新任务共享的元数据索引
+pub type SharedInstruments = Arc<RwLock<InstrumentRegistry>>;
+
+lazy_static::lazy_static! {
+    /// 启动时加载的元数据索引 (后台刷新同步更新)
+    pub static ref INSTRUMENTS: SharedInstruments = Arc::default();
+}
+
+/// 交易对的最小名义金额 (以计价币计；未加载或交易所未提供时为 0)
+pub fn min_notional(exchange: ExchangeId, symbol: &str) -> f64 {
+    INSTRUMENTS
+        .read()
+        .ok()
+        .and_then(|registry| registry.get(exchange, symbol).map(|info| info.min_notional))
+        .unwrap_or(0.0)
+}
+
+/// 交易对元数据索引
+#[derive(Debug, Default, Clone)]
+pub struct InstrumentRegistry {
+    instruments: HashMap<(ExchangeId, String), InstrumentInfo>,
+    /// 各交易所元数据的获取时间
+    fetched_at: HashMap<ExchangeId, i64>,
+    /// 硬性过期上限 (0 表示不限制)
+    max_age_ms: i64,
+}
+
+impl InstrumentRegistry {
+    pub fn new(max_age_ms: i64) -> Self {
+        Self {
+            max_age_ms,
+            ..Default::default()
+        }
+    }
+
+    /// 替换某个交易所的全部元数据
+    pub fn replace(&mut self, exchange: ExchangeId, instruments: Vec<InstrumentInfo>, fetched_at: i64) {
+        self.instruments.retain(|(ex, _), _| *ex != exchange);
+        for info in instruments {
+            self.instruments
+                .insert((info.exchange, normalize_key(&info.symbol)), info);
+        }
+        self.fetched_at.insert(exchange, fetched_at);
+    }
+
+    /// 查询元数据 (BTC/USDT、BTC-USDT、BTCUSDT 均可)
+    pub fn get(&self, exchange: ExchangeId, symbol: &str) -> Option<&InstrumentInfo> {
+        self.instruments.get(&(exchange, normalize_key(symbol)))
+    }
+
+    /// 元数据已缓存时长
+    pub fn age_ms(&self, exchange: ExchangeId, now_ms: i64) -> Option<i64> {
+        self.fetched_at.get(&exchange).map(|t| now_ms - t)
+    }
+
+    /// 元数据是否超过硬性过期上限
+    pub fn is_expired(&self, exchange: ExchangeId, now_ms: i64) -> bool {
+        self.max_age_ms > 0
+            && self
+                .age_ms(exchange, now_ms)
+                .is_some_and(|age| age > self.max_age_ms)
+    }
+
+    pub fn len(&self) -> usize {
+        self.instruments.len()
+    }
+}
+
+fn normalize_key(symbol: &str) -> String {
+    symbol.replace(['/', '-', '_'], "").to_uppercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backtest::ReplayClock;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    const HOUR_MS: i64 = 3_600_000;
+
+    /// 记录拉取次数的元数据来源
+    struct CountingSource(Arc<AtomicUsize>);
+
+    #[async_trait]
+    impl InstrumentSource for CountingSource {
+        async fn fetch(&self, exchange: ExchangeId) -> Result<Vec<InstrumentInfo>> {
+            self.0.fetch_add(1, Ordering::SeqCst);
+            Ok(vec![btc_usdt(exchange)])
+        }
+    }
+
+    /// BTC/USDT: 价格精度 0.01，数量步长 0.00001，最小名义金额 5
+    fn btc_usdt(exchange: ExchangeId) -> InstrumentInfo {
+        InstrumentInfo {
+            exchange,
+            symbol: "BTC/USDT".to_string(),
+            exchange_symbol: "BTCUSDT".to_string(),
+            base: "BTC".to_string(),
+            quote: "USDT".to_string(),
+            price_tick: 0.01,
+            qty_step: 0.00001,
+            min_qty: 0.00001,
+            min_notional: 5.0,
+            status: default_status(),
+            maker_fee: 0.001,
+            taker_fee: 0.001,
+            liquidity: Some(LiquiditySnapshot {
+                quote_volume_24h: 1.5e9,
+                bid: 100.0,
+                ask: 100.01,
+            }),
+        }
+    }
+
+    /// 时钟固定在 `now` 的加载器
+    fn loader(dir: &std::path::Path, now: i64) -> (Arc<InstrumentLoader>, Arc<AtomicUsize>) {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let mut loader = InstrumentLoader::new(
+            Box::new(CountingSource(calls.clone())),
+            Some(dir.to_path_buf()),
+            Duration::from_millis(HOUR_MS as u64),
+        );
+        loader.jitter_ms = 0;
+        loader.clock = Arc::new(ReplayClock::new(now));
+        (Arc::new(loader), calls)
+    }
+
+    fn cache_dir() -> PathBuf {
+        std::env::temp_dir().join(format!("inarbit-instruments-{}", uuid::Uuid::new_v4()))
+    }
+
+    const NOW: i64 = 1_700_000_000_000;
+
+    /// 用 `cache_age_ms` 前写入的缓存启动，返回加载器、拉取计数与已填充缓存的索引
+    async fn start_with_cache(dir: &std::path::Path, cache_age_ms: i64) -> (Arc<InstrumentLoader>, Arc<AtomicUsize>, SharedInstruments) {
+        let (seed, _) = loader(dir, NOW);
+        seed.refresh(ExchangeId::Binance, NOW - cache_age_ms).await.unwrap();
+
+        let (loader, calls) = loader(dir, NOW);
+        let cache = loader.load(ExchangeId::Binance, NOW).await.unwrap();
+        assert_eq!(cache.fetched_at, NOW - cache_age_ms);
+        assert_eq!(calls.load(Ordering::SeqCst), 0, "有缓存时启动不应同步拉取");
+        let registry: SharedInstruments = Arc::new(RwLock::new(InstrumentRegistry::new(0)));
+        registry
+            .write()
+            .unwrap()
+            .replace(ExchangeId::Binance, cache.instruments, cache.fetched_at);
+        (loader, calls, registry)
+    }
+
+    #[tokio::test]
+    async fn fresh_cache_is_used_without_fetching() {
+        let dir = cache_dir();
+        let (loader, calls, registry) = start_with_cache(&dir, 60_000).await;
+        let _ = std::fs::remove_dir_all(&dir);
+        assert!(registry.read().unwrap().get(ExchangeId::Binance, "BTCUSDT").is_some());
+        // 后台刷新等到缓存过期才拉取
+        let age = registry.read().unwrap().age_ms(ExchangeId::Binance, NOW);
+        assert_eq!(loader.next_refresh_delay(age), Duration::from_millis((HOUR_MS - 60_000) as u64));
+        assert_eq!(calls.load(Ordering::SeqCst), 0);
+    }
+
+    #[tokio::test]
+    async fn stale_cache_triggers_refetch() {
+        let dir = cache_dir();
+        let (loader, calls, registry) = start_with_cache(&dir, 2 * HOUR_MS).await;
+        spawn_refresh(loader.clone(), registry.clone(), vec![ExchangeId::Binance]);
+        tokio::time::timeout(Duration::from_secs(5), loader.refreshed.notified()).await.expect("过期缓存应立即刷新");
+        let _ = std::fs::remove_dir_all(&dir);
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        assert_eq!(registry.read().unwrap().age_ms(ExchangeId::Binance, NOW), Some(0));
+    }
+
+    #[tokio::test]
+    async fn cache_round_trip_keeps_fees_and_liquidity() {
+        let dir = cache_dir();
+        let (seed, _) = loader(&dir, NOW);
+        seed.refresh(ExchangeId::Binance, NOW).await.unwrap();
+
+        let (loader, calls) = loader(&dir, NOW);
+        let cache = loader.load_cached(ExchangeId::Binance).await.unwrap();
+        let _ = std::fs::remove_dir_all(&dir);
+        assert_eq!(calls.load(Ordering::SeqCst), 0);
+        let info = &cache.instruments[0];
+        let expected = btc_usdt(ExchangeId::Binance);
+        assert_eq!((info.maker_fee, info.taker_fee), (expected.maker_fee, expected.taker_fee));
+        assert_eq!(info.liquidity, expected.liquidity);
+        assert_eq!((info.price_tick, info.min_notional, info.status.as_str()), (0.01, 5.0, "trading"));
+    }
+
+    #[tokio::test]
+    async fn binance_metadata_carries_fees_and_the_24h_liquidity_snapshot() {
+        use axum::routing::get;
+        use axum::Json;
+
+        let app = axum::Router::new()
+            .route(
+                "/api/v3/exchangeInfo",
+                get(|| async {
+                    Json(serde_json::json!({"symbols": [
+                        {"symbol": "BTCUSDT", "status": "TRADING", "baseAsset": "BTC", "quoteAsset": "USDT", "filters": [
+                            {"filterType": "PRICE_FILTER", "tickSize": "0.01"},
+                            {"filterType": "LOT_SIZE", "stepSize": "0.00001", "minQty": "0.00001"},
+                            {"filterType": "NOTIONAL", "minNotional": "5"}
+                        ]},
+                        {"symbol": "ETHBTC", "status": "BREAK", "baseAsset": "ETH", "quoteAsset": "BTC", "filters": []}
+                    ]}))
+                }),
+            )
+            .route(
+                "/api/v3/ticker/24hr",
+                get(|| async {
+                    Json(serde_json::json!([
+                        {"symbol": "BTCUSDT", "quoteVolume": "1500000000", "bidPrice": "100.00", "askPrice": "100.01"}
+                    ]))
+                }),
+            );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        let config: crate::exchange::ExchangeConfig = serde_json::from_value(serde_json::json!({
+            "id": "binance", "api_key": "", "api_secret": "", "passphrase": null, "enabled": true,
+            "rest_url": format!("http://{}", addr),
+        }))
+        .unwrap();
+        let source = RestInstrumentSource::new(RestEndpoints::from_configs(&[config]));
+
+        let instruments = source.fetch(ExchangeId::Binance).await.unwrap();
+        assert_eq!(instruments.len(), 2);
+        let btc = instruments.iter().find(|i| i.symbol == "BTC/USDT").unwrap();
+        assert_eq!((btc.maker_fee, btc.taker_fee), (0.001, 0.001));
+        assert_eq!(btc.liquidity, btc_usdt(ExchangeId::Binance).liquidity);
+        let eth = instruments.iter().find(|i| i.symbol == "ETH/BTC").unwrap();
+        assert!(!eth.is_trading());
+        assert_eq!(eth.liquidity, None, "24 小时行情中没有的交易对不带流动性快照");
+    }
+
+    #[tokio::test]
+    async fn missing_or_corrupt_cache_fetches_synchronously() {
+        let dir = cache_dir();
+        let (loader, calls) = loader(&dir, NOW);
+        loader.load(ExchangeId::Okx, 1_000).await.unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        std::fs::write(loader.cache_path(ExchangeId::Okx).unwrap(), b"{not json").unwrap();
+        loader.load(ExchangeId::Okx, 2_000).await.unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn registry_expires_after_max_age() {
+        let mut registry = InstrumentRegistry::new(HOUR_MS);
+        registry.replace(ExchangeId::Binance, vec![], 0);
+        assert!(!registry.is_expired(ExchangeId::Binance, HOUR_MS));
+        assert!(registry.is_expired(ExchangeId::Binance, HOUR_MS + 1));
+        assert!(!registry.is_expired(ExchangeId::Okx, HOUR_MS + 1));
+    }
+
+    #[test]
+    fn quantity_rounds_down_to_small_steps() {
+        let info = btc_usdt(ExchangeId::Binance);
+        assert_eq!(info.round_qty(0.123456789), 0.12345);
+        assert_eq!(info.round_qty(0.00001), 0.00001);
+        assert_eq!(info.round_qty(0.000019999), 0.00001);
+        assert_eq!(info.round_qty(0.000009), 0.0);
+        // 浮点误差不应把恰好落在步长上的数量舍掉一步
+        assert_eq!(info.round_qty(0.3), 0.3);
+        assert_eq!(round_down(0.3, 0.1), 0.3);
+        assert_eq!(round_down(1.23, 0.0), 1.23);
+    }
+
+    #[test]
+    fn price_rounds_toward_the_passive_side() {
+        let info = btc_usdt(ExchangeId::Binance);
+        assert_eq!(info.round_price_for(OrderSide::Buy, 100.019), 100.01);
+        assert_eq!(info.round_price_for(OrderSide::Sell, 100.011), 100.02);
+        assert_eq!(info.round_price_for(OrderSide::Buy, 100.01), 100.01);
+        assert_eq!(info.round_price_for(OrderSide::Sell, 100.01), 100.01);
+        assert_eq!(round_up(0.30000000000000004, 0.1), 0.3);
+    }
+
+    #[test]
+    fn min_notional_boundary() {
+        let info = btc_usdt(ExchangeId::Binance);
+        assert!(info.meets_min_notional(0.05, 100.0));
+        assert!(!info.meets_min_notional(0.04999, 100.0));
+        // 0.1 * 50 的浮点结果不应因误差被判为不足
+        assert!(info.meets_min_notional(0.1, 50.0));
+        let mut unknown = info.clone();
+        unknown.min_notional = 0.0;
+        assert!(unknown.meets_min_notional(0.00001, 1.0));
+    }
+}
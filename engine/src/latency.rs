@@ -0,0 +1,252 @@
+//! 行情延迟统计
+//!
+//! 每条行情记录两段延迟: 交易所延迟 (交易所时间戳 → 本地解析，含网络与两端时钟偏差) 与
+//! 管道延迟 (本地解析 → 分发给策略，含广播通道与主循环排队)。按交易所保留最近若干样本计算 p50/p95/p99，
+//! 定期写入 Redis `metrics:engine:latency:<exchange>`；p95 超过预算时记录告警。
+//! 回放行情没有本地解析时间 (`received_at` 为 0)，不参与统计。
+
+use anyhow::Result;
+use redis::aio::ConnectionManager;
+use redis::AsyncCommands;
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+use tracing::warn;
+
+use crate::exchange::{ExchangeId, Ticker};
+
+/// Redis Key 前缀 (后接交易所 ID)
+const KEY_PREFIX: &str = "metrics:engine:latency:";
+
+/// 延迟统计配置
+#[derive(Debug, Clone)]
+pub struct LatencyConfig {
+    /// 每个交易所保留的样本数
+    pub window: usize,
+    /// 交易所延迟 p95 预算 (毫秒)
+    pub exchange_budget_ms: i64,
+    /// 管道延迟 p95 预算 (毫秒)
+    pub pipeline_budget_ms: i64,
+}
+
+impl Default for LatencyConfig {
+    fn default() -> Self {
+        Self {
+            window: 2_048,
+            exchange_budget_ms: 500,
+            pipeline_budget_ms: 50,
+        }
+    }
+}
+
+impl LatencyConfig {
+    /// 从环境变量读取: ENGINE_LATENCY_WINDOW、ENGINE_LATENCY_BUDGET_MS、ENGINE_PIPELINE_LATENCY_BUDGET_MS
+    pub fn from_env() -> Self {
+        let mut config = Self::default();
+        let env = |name: &str| std::env::var(name).ok().filter(|v| !v.trim().is_empty());
+        if let Some(v) = env("ENGINE_LATENCY_WINDOW").and_then(|v| v.parse::<usize>().ok()) {
+            config.window = v.max(1);
+        }
+        if let Some(v) = env("ENGINE_LATENCY_BUDGET_MS").and_then(|v| v.parse().ok()) {
+            config.exchange_budget_ms = v;
+        }
+        if let Some(v) = env("ENGINE_PIPELINE_LATENCY_BUDGET_MS").and_then(|v| v.parse().ok()) {
+            config.pipeline_budget_ms = v;
+        }
+        config
+    }
+}
+
+/// 延迟分位数 (毫秒)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct Percentiles {
+    pub p50: i64,
+    pub p95: i64,
+    pub p99: i64,
+}
+
+/// 最近 `capacity` 个延迟样本，按需计算分位数
+#[derive(Debug, Clone)]
+pub struct LatencyHistogram {
+    samples: VecDeque<i64>,
+    capacity: usize,
+}
+
+impl LatencyHistogram {
+    pub fn new(capacity: usize) -> Self {
+        let capacity = capacity.max(1);
+        Self {
+            samples: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    /// 记录一个样本 (负值按 0 计，常见于两端时钟偏差)；超出容量时丢弃最早的样本
+    pub fn record(&mut self, latency_ms: i64) {
+        if self.samples.len() == self.capacity {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(latency_ms.max(0));
+    }
+
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+
+    /// p50 / p95 / p99 (最近邻取法)；没有样本时为 None
+    pub fn percentiles(&self) -> Option<Percentiles> {
+        if self.samples.is_empty() {
+            return None;
+        }
+        let mut sorted: Vec<i64> = self.samples.iter().copied().collect();
+        sorted.sort_unstable();
+        Some(Percentiles {
+            p50: pick(&sorted, 0.50),
+            p95: pick(&sorted, 0.95),
+            p99: pick(&sorted, 0.99),
+        })
+    }
+}
+
+fn pick(sorted: &[i64], q: f64) -> i64 {
+    let idx = ((sorted.len() as f64 * q.clamp(0.0, 1.0)).ceil() as usize).clamp(1, sorted.len()) - 1;
+    sorted[idx]
+}
+
+/// 单个交易所的延迟统计
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LatencyStat {
+    pub exchange: ExchangeId,
+    pub samples: usize,
+    pub exchange_latency_ms: Percentiles,
+    pub pipeline_latency_ms: Percentiles,
+    pub timestamp: i64,
+}
+
+/// 按交易所统计行情延迟
+pub struct LatencyTracker {
+    config: LatencyConfig,
+    exchanges: HashMap<ExchangeId, (LatencyHistogram, LatencyHistogram)>,
+}
+
+impl LatencyTracker {
+    pub fn new(config: LatencyConfig) -> Self {
+        Self {
+            config,
+            exchanges: HashMap::new(),
+        }
+    }
+
+    pub fn from_env() -> Self {
+        Self::new(LatencyConfig::from_env())
+    }
+
+    /// 记录一条分发给策略的行情 (`dispatched_at` 为本地毫秒时间)
+    pub fn record(&mut self, ticker: &Ticker, dispatched_at: i64) {
+        if ticker.received_at <= 0 {
+            return;
+        }
+        let window = self.config.window;
+        let (exchange, pipeline) = self
+            .exchanges
+            .entry(ticker.exchange)
+            .or_insert_with(|| (LatencyHistogram::new(window), LatencyHistogram::new(window)));
+        if ticker.timestamp > 0 {
+            exchange.record(ticker.received_at - ticker.timestamp);
+        }
+        pipeline.record(dispatched_at - ticker.received_at);
+    }
+
+    /// 各交易所当前的分位数
+    pub fn snapshot(&self, now: i64) -> Vec<LatencyStat> {
+        let mut out: Vec<LatencyStat> = self
+            .exchanges
+            .iter()
+            .filter(|(_, (_, pipeline))| !pipeline.is_empty())
+            .map(|(id, (exchange, pipeline))| LatencyStat {
+                exchange: *id,
+                samples: pipeline.len(),
+                exchange_latency_ms: exchange.percentiles().unwrap_or_default(),
+                pipeline_latency_ms: pipeline.percentiles().unwrap_or_default(),
+                timestamp: now,
+            })
+            .collect();
+        out.sort_by_key(|s| format!("{:?}", s.exchange));
+        out
+    }
+
+    /// p95 超过预算时记录告警
+    pub fn check_budget(&self, stats: &[LatencyStat]) {
+        for stat in stats {
+            if stat.exchange_latency_ms.p95 > self.config.exchange_budget_ms {
+                warn!(
+                    "{:?} 交易所行情延迟 p95 {}ms 超过预算 {}ms (p50 {}ms / p99 {}ms)",
+                    stat.exchange,
+                    stat.exchange_latency_ms.p95,
+                    self.config.exchange_budget_ms,
+                    stat.exchange_latency_ms.p50,
+                    stat.exchange_latency_ms.p99
+                );
+            }
+            if stat.pipeline_latency_ms.p95 > self.config.pipeline_budget_ms {
+                warn!(
+                    "{:?} 行情管道延迟 p95 {}ms 超过预算 {}ms (p50 {}ms / p99 {}ms)",
+                    stat.exchange,
+                    stat.pipeline_latency_ms.p95,
+                    self.config.pipeline_budget_ms,
+                    stat.pipeline_latency_ms.p50,
+                    stat.pipeline_latency_ms.p99
+                );
+            }
+        }
+    }
+
+    /// 写入 Redis
+    pub async fn publish(&self, conn: &mut ConnectionManager, stats: &[LatencyStat]) -> Result<()> {
+        if stats.is_empty() {
+            return Ok(());
+        }
+        for stat in stats {
+            let key = format!("{}{}", KEY_PREFIX, stat.exchange.key());
+            let _: () = conn.set(key, serde_json::to_string(stat)?).await?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn percentiles_of_a_known_set_use_nearest_rank() {
+        let mut histogram = LatencyHistogram::new(200);
+        // 乱序写入 1..=100
+        for i in (1..=100).rev() {
+            histogram.record(i);
+        }
+        assert_eq!(histogram.percentiles(), Some(Percentiles { p50: 50, p95: 95, p99: 99 }));
+    }
+
+    #[test]
+    fn window_evicts_the_oldest_samples() {
+        let mut histogram = LatencyHistogram::new(4);
+        for latency in [1_000, 1_000, 1_000, 1_000, 1, 2, 3, -5] {
+            histogram.record(latency);
+        }
+        assert_eq!(histogram.len(), 4);
+        // 早期的 1000ms 样本已被挤出，负值按 0 计
+        assert_eq!(histogram.percentiles(), Some(Percentiles { p50: 1, p95: 3, p99: 3 }));
+    }
+
+    #[test]
+    fn empty_histogram_has_no_percentiles() {
+        let histogram = LatencyHistogram::new(0);
+        assert!(histogram.is_empty());
+        assert_eq!(histogram.percentiles(), None);
+    }
+}
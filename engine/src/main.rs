@@ -1,30 +1,150 @@
+mod accounting;
+mod api;
+mod backtest;
+mod binance_rest;
+mod calibration;
 mod config;
+mod confirm;
 mod db;
+mod disabled_paths;
+mod engine;
 mod exchange;
 mod executor;
+#[cfg(test)]
+mod fake_redis;
+mod faults;
+mod fills;
+mod forwarder;
+mod funding;
+mod health;
+mod heatmap;
+mod instruments;
+mod latency;
+mod metrics;
+mod netting;
+mod okx_rest;
+mod orderbook;
+mod positions;
+mod probe;
+mod queue;
+mod rejections;
+mod report;
 mod risk;
+mod schedule;
+mod scorecard;
+mod shutdown;
+mod signing;
+mod stops;
+mod strategies;
 mod strategy;
+mod supervisor;
+mod symbols;
+mod telemetry;
+mod tick_rate;
+mod timestamps;
+mod watchdog;
 
-use std::time::Duration;
+use std::process::ExitCode;
+use std::sync::Arc;
 
 use anyhow::Result;
+use clap::{Parser, Subcommand};
 use tracing::{info, warn};
+use opentelemetry::trace::TracerProvider as _;
+use tracing_subscriber::prelude::*;
 use tracing_subscriber::EnvFilter;
 
 use crate::config::load_config;
 use crate::db::{create_pool, create_redis_client};
-use crate::exchange::connect_all;
+use crate::engine::{Engine, SystemClock};
+use crate::exchange::{connect_all, TickerClock};
 use crate::executor::OrderExecutor;
+use crate::shutdown::{config_error, ShutdownReason};
+
+/// 命令行参数
+#[derive(Parser)]
+#[command(name = "inarbit-engine", version, about = "高性能加密货币套利引擎")]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// 打印 Redis 中的置信度校准表
+    Calibration,
+    /// 打印 Redis 中的交易所执行质量评分卡
+    Scorecard,
+}
 
 #[tokio::main]
-async fn main() -> Result<()> {
-    tracing_subscriber::fmt()
-        .with_env_filter(EnvFilter::from_default_env())
+async fn main() -> ExitCode {
+    let telemetry = telemetry::TelemetryConfig::from_env().map(|c| (telemetry::init_provider(&c), c));
+    let provider = match &telemetry {
+        Some((Ok(provider), _)) => Some(provider.clone()),
+        _ => None,
+    };
+    tracing_subscriber::registry()
+        .with(EnvFilter::from_default_env())
+        .with(tracing_subscriber::fmt::layer())
+        .with(
+            provider
+                .as_ref()
+                .map(|p| tracing_opentelemetry::layer().with_tracer(p.tracer(telemetry::TRACER_NAME))),
+        )
         .init();
+    supervisor::install_panic_hook();
+    match &telemetry {
+        Some((Ok(_), config)) => info!("OTLP trace export enabled: {}", config.endpoint),
+        Some((Err(err), config)) => warn!("OTLP trace export to {} disabled: {}", config.endpoint, err),
+        None => {}
+    }
+
+    let cli = Cli::parse();
+    let mut redis = None;
+    let reason = match run(cli, provider.as_ref(), &mut redis).await {
+        Ok(reason) => reason,
+        Err(err) => ShutdownReason::from_error(&err),
+    };
+    let code = shutdown::record(reason, redis.as_ref()).await;
+    if let Some(provider) = provider {
+        if let Err(err) = provider.shutdown() {
+            warn!("flush OTLP traces failed: {}", err);
+        }
+    }
+    ExitCode::from(code)
+}
 
-    let config = load_config()?;
+/// 运行引擎直到停止，返回停机原因 (已创建的 Redis 客户端写回 `redis_out`，用于发布停机原因)
+async fn run(
+    cli: Cli,
+    tracer_provider: Option<&opentelemetry_sdk::trace::TracerProvider>,
+    redis_out: &mut Option<redis::Client>,
+) -> Result<ShutdownReason> {
+    let config = load_config().map_err(config_error)?;
 
-    let _pool = match create_pool(&config.database).await {
+    match cli.command {
+        Some(Command::Calibration) => {
+            let redis = create_redis_client(&config.redis)?;
+            calibration::dump(&redis).await?;
+            return Ok(ShutdownReason::Completed);
+        }
+        Some(Command::Scorecard) => {
+            let redis = create_redis_client(&config.redis)?;
+            scorecard::dump(&redis).await?;
+            return Ok(ShutdownReason::Completed);
+        }
+        None => {}
+    }
+
+    let risk_config = risk::RiskConfig::load().map_err(config_error)?;
+    info!(
+        "risk limits: max_drawdown={} exposure_limit={} initial_equity={}",
+        risk_config.max_drawdown, risk_config.exposure_limit, risk_config.initial_equity
+    );
+    risk::init_global(risk_config);
+
+    let pool = match create_pool(&config.database).await {
         Ok(pool) => Some(pool),
         Err(err) => {
             warn!("db connection failed, continue without postgres: {}", err);
@@ -32,6 +152,10 @@ async fn main() -> Result<()> {
         }
     };
 
+    if config.mode == "backtest" {
+        return run_backtest(pool.as_ref()).await;
+    }
+
     let redis = match create_redis_client(&config.redis) {
         Ok(client) => Some(client),
         Err(err) => {
@@ -39,14 +163,280 @@ async fn main() -> Result<()> {
             None
         }
     };
+    redis_out.clone_from(&redis);
 
-    let connections = connect_all(&config.exchanges).await?;
-    let mut executor = OrderExecutor::new(connections, redis);
+    if let Some(redis) = &redis {
+        risk::spawn_kill_switch_poller(
+            risk::GLOBAL_RISK_MANAGER.clone(),
+            redis.clone(),
+            std::env::var("ENGINE_USER_ID").ok(),
+            risk::kill_switch_poll_interval(),
+        );
+    }
+
+    let ticker_clock = TickerClock::default();
+    let probe = probe::ProbeConfig::from_env().map(|probe_config| {
+        let state = probe::ProbeState::new(
+            pool.clone(),
+            redis.clone().map(db::SharedRedis::new),
+            ticker_clock.clone(),
+            probe_config.ticker_window_ms,
+        );
+        let server = state.clone();
+        tokio::spawn(async move {
+            if let Err(err) = probe::serve(probe_config.addr, server).await {
+                warn!("health server stopped: {}", err);
+            }
+        });
+        state
+    });
+
+    let connections = connect_all(&config.exchanges, &ticker_clock).await?;
+    if let Some(probe) = &probe {
+        probe.set_connections(connections.clone());
+    }
+    for exchange in config.exchanges.iter().filter(|c| c.enabled) {
+        match connections.get(&exchange.id) {
+            Some(conn) => info!(
+                "{:?} feed up: {} symbols subscribed over {} connection(s)",
+                exchange.id,
+                conn.subscribed_count(),
+                conn.shard_count()
+            ),
+            None => warn!("{:?} feed unavailable, continuing without it", exchange.id),
+        }
+    }
+    watchdog::FeedWatchdog::new(
+        watchdog::WatchdogConfig::from_env(),
+        connections.clone(),
+        redis.clone().map(db::SharedRedis::new),
+    )
+    .spawn();
+    let mut executor = OrderExecutor::new(connections.clone(), redis.clone());
     executor.set_simulation_mode(config.mode != "live");
+    if let Some(pool) = pool.as_ref().filter(|_| persist_executions_enabled()) {
+        executor.set_execution_store(Arc::new(pool.clone()));
+    }
+    if let Some(confirm) = confirm::ConfirmConfig::from_env() {
+        executor.set_confirmation(Arc::new(confirm::PreExecutionCheck::new(
+            confirm,
+            Arc::new(confirm::RestPriceSource::new(exchange::RestEndpoints::from_configs(
+                &config.exchanges,
+            ))),
+        )));
+    }
+    executor.set_observe_only(
+        config
+            .exchanges
+            .iter()
+            .filter(|c| !c.execution_enabled)
+            .map(|c| c.id)
+            .collect(),
+    );
+    executor.set_instruments(load_instruments(&config.exchanges, redis.clone()).await);
+    // 实盘模式下订阅私有成交推送，市价单回报未到终态时按累计成交结算
+    let fill_tracker = (config.mode == "live").then(|| Arc::new(fills::FillTracker::from_env()));
+    if let Some(tracker) = &fill_tracker {
+        executor.set_fill_tracker(tracker.clone());
+    }
+    for exchange in config.exchanges.iter().filter(|c| c.enabled && c.execution_enabled) {
+        match binance_rest::BinanceRestClient::from_config(exchange) {
+            Ok(Some(client)) => {
+                // 启动时校验 API Key (签名、时钟偏差、权限) 并记录账户余额，失败只告警
+                match client.account_balances().await {
+                    Ok(balances) => info!(
+                        "binance account: {}",
+                        balances
+                            .iter()
+                            .map(|b| format!("{} {}+{}", b.asset, b.free, b.locked))
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    ),
+                    Err(err) => warn!("binance account check failed: {}", err),
+                }
+                let client = Arc::new(client);
+                if let Some(tracker) = &fill_tracker {
+                    fills::spawn_binance_user_stream(client.clone(), exchange.ws_endpoint().to_string(), tracker.clone());
+                }
+                executor.set_binance_client(client);
+            }
+            Ok(None) => {}
+            Err(err) => warn!("binance rest client disabled: {}", err),
+        }
+        match okx_rest::OkxRestClient::from_config(exchange) {
+            Ok(Some(client)) => {
+                let client = Arc::new(client);
+                if let Some(tracker) = &fill_tracker {
+                    let url = exchange.ws_endpoint().replace("/public", "/private");
+                    fills::spawn_okx_private_stream(client.clone(), url, tracker.clone());
+                }
+                executor.set_okx_client(client);
+            }
+            Ok(None) => {}
+            Err(err) => warn!("okx rest client disabled: {}", err),
+        }
+    }
+    let faults = faults::injector_from_env(config.mode == "live")
+        .map_err(config_error)?
+        .map(Arc::new);
+    if let Some(faults) = &faults {
+        executor.set_fault_injector(faults.clone());
+    }
+    let mut engine = Engine::new(executor, redis, Arc::new(SystemClock));
+    if let Some(faults) = faults {
+        engine.set_fault_injector(faults);
+    }
+    if let Some(provider) = tracer_provider {
+        engine.set_tracer(provider.tracer(telemetry::TRACER_NAME));
+    }
+    engine.load_calibration().await;
+    if let Some(pool) = &pool {
+        let user_id = std::env::var("ENGINE_USER_ID").ok().filter(|v| !v.is_empty());
+        if let Err(err) = engine.load_enabled_strategies(pool, user_id.as_deref()).await {
+            warn!("load strategies failed: {}", err);
+        }
+    }
+
+    // 期现套利依赖资金费率，只在加载了该策略时轮询
+    if engine.has_strategy_type(strategy::StrategyType::CashCarry) {
+        for config in config.exchanges.iter().filter(|c| c.id.futures_rest_url().is_some()) {
+            let Some(conn) = connections.get(&config.id) else {
+                continue;
+            };
+            if let Err(err) = conn.request_funding_rates(&config.symbols).await {
+                warn!("start funding rate polling for {:?} failed: {}", config.id, err);
+            }
+        }
+    }
+
+    if recover_state_enabled() {
+        if let Err(err) = engine.recover_state().await {
+            if config.mode == "live" {
+                return Err(err.context("startup reconciliation with OMS failed"));
+            }
+            warn!("startup reconciliation skipped: {}", err);
+        }
+    }
+
+    if let Some(addr) = metrics::addr_from_env() {
+        let connections = connections.clone();
+        tokio::spawn(async move {
+            if let Err(err) = metrics::serve(addr, connections).await {
+                warn!("metrics server stopped: {}", err);
+            }
+        });
+    }
+
+    if let Some(api_config) = api::ApiConfig::from_env() {
+        let (state, control) = engine.api_handles();
+        tokio::spawn(async move {
+            if let Err(err) = api::serve(api_config, state, control).await {
+                warn!("http server stopped: {}", err);
+            }
+        });
+    }
 
     info!("inarbit engine started (mode: {})", config.mode);
 
-    loop {
-        tokio::time::sleep(Duration::from_secs(60)).await;
+    let reason = tokio::select! {
+        res = engine.run(&connections) => ShutdownReason::from_engine_result(res),
+        _ = tokio::signal::ctrl_c() => {
+            info!("收到停止信号");
+            ShutdownReason::Signal
+        }
+    };
+    engine.shutdown(&connections).await;
+
+    if let Some(report) = engine.fault_report() {
+        write_fault_report(&report)?;
+    }
+    if let (Some(report), Some(path)) = (engine.sim_report(), report::report_path_from_env()) {
+        report.write_to(&path)?;
+        info!("simulation report written to {}", path.display());
+    }
+
+    Ok(reason)
+}
+
+/// 回测模式: 不连接交易所，回放 ENGINE_BACKTEST_FILE 后输出汇总
+async fn run_backtest(pool: Option<&sqlx::PgPool>) -> Result<ShutdownReason> {
+    let path = backtest::replay_file_from_env()
+        .ok_or_else(|| config_error(anyhow::anyhow!("ENGINE_MODE=backtest 需要设置 ENGINE_BACKTEST_FILE")))?;
+    let clock = Arc::new(backtest::ReplayClock::new(0));
+    let mut executor = OrderExecutor::new(Default::default(), None);
+    executor.set_simulation_mode(true);
+    let mut engine = Engine::new(executor, None, clock.clone());
+    if let Some(faults) = faults::injector_from_env(false).map_err(config_error)? {
+        engine.set_fault_injector(Arc::new(faults));
+    }
+    match backtest::load_strategies_from_env().map_err(config_error)? {
+        Some(configs) => engine.load_strategies(configs),
+        None => match pool {
+            Some(pool) => {
+                let user_id = std::env::var("ENGINE_USER_ID").ok().filter(|v| !v.is_empty());
+                engine.load_enabled_strategies(pool, user_id.as_deref()).await?;
+            }
+            None => warn!("backtest without ENGINE_BACKTEST_STRATEGIES or postgres: no strategies loaded"),
+        },
+    }
+
+    let summary = backtest::replay_from_jsonl(&path, &mut engine, &clock).await?;
+    info!("backtest summary:\n{}", serde_json::to_string_pretty(&summary)?);
+    if let (Some(report), Some(path)) = (engine.sim_report(), report::report_path_from_env()) {
+        report.write_to(&path)?;
+        info!("simulation report written to {}", path.display());
+    }
+    Ok(ShutdownReason::Completed)
+}
+
+/// 加载已启用交易所的交易对元数据 (优先使用本地 / Redis 缓存)，并启动后台刷新
+async fn load_instruments(
+    exchanges: &[exchange::ExchangeConfig],
+    redis: Option<redis::Client>,
+) -> instruments::SharedInstruments {
+    let endpoints = exchange::RestEndpoints::from_configs(exchanges);
+    let loader = instruments::InstrumentLoader::from_env(endpoints).with_redis(redis.map(db::SharedRedis::new));
+    let mut registry = instruments::InstrumentRegistry::new(loader.max_age_ms());
+    let now = chrono::Utc::now().timestamp_millis();
+    let enabled: Vec<exchange::ExchangeId> = exchanges.iter().filter(|c| c.enabled).map(|c| c.id).collect();
+    for id in &enabled {
+        match loader.load(*id, now).await {
+            Ok(cache) => registry.replace(*id, cache.instruments, cache.fetched_at),
+            Err(err) => warn!("load instruments for {:?} failed: {}", id, err),
+        }
+    }
+    info!("loaded {} instruments", registry.len());
+    if let Ok(mut global) = instruments::INSTRUMENTS.write() {
+        *global = registry;
     }
+    let registry = instruments::INSTRUMENTS.clone();
+    instruments::spawn_refresh(Arc::new(loader), registry.clone(), enabled);
+    registry
+}
+
+/// 输出故障注入报告 (ENGINE_FAULT_REPORT_FILE 指定时同时写入文件)
+fn write_fault_report(report: &faults::FaultReport) -> Result<()> {
+    let json = serde_json::to_string_pretty(report)?;
+    info!("fault injection report:\n{}", json);
+    if let Ok(path) = std::env::var("ENGINE_FAULT_REPORT_FILE") {
+        if !path.is_empty() {
+            std::fs::write(&path, json)?;
+            info!("fault injection report written to {}", path);
+        }
+    }
+    Ok(())
+}
+
+/// 启动时是否从 OMS 恢复持仓 (ENGINE_RECOVER_STATE=0 关闭，默认开启)
+fn recover_state_enabled() -> bool {
+    std::env::var("ENGINE_RECOVER_STATE")
+        .map(|v| !matches!(v.as_str(), "0" | "false" | "False"))
+        .unwrap_or(true)
+}
+
+/// 是否把每次执行写入 Postgres `executions` (ENGINE_PERSIST_EXECUTIONS，默认开启)
+fn persist_executions_enabled() -> bool {
+    std::env::var("ENGINE_PERSIST_EXECUTIONS")
+        .map(|v| !matches!(v.as_str(), "0" | "false" | "False"))
+        .unwrap_or(true)
 }
@@ -0,0 +1,181 @@
+//! Prometheus 指标导出
+//!
+//! 引擎指标原本只写入 Redis Hash (`metrics:engine`)，不便于用标准工具采集。
+//! 这里在同一批指标点上同步累加 Prometheus 计数器，设置 ENGINE_METRICS_ADDR 时启动独立 HTTP 服务，
+//! 以文本格式在 `/metrics` 导出: 生成的信号、被拦截的信号 (按原因)、执行成功 / 失败、交易所拒单 (按原因)、
+//! 各交易所处理的行情条数、WebSocket 重连次数与本地订单簿重新同步次数 (采集时从各连接读取)。
+
+use axum::extract::State;
+use axum::http::header;
+use axum::response::IntoResponse;
+use axum::routing::get;
+use axum::Router;
+use prometheus::{Encoder, IntCounterVec, Opts, Registry, TextEncoder};
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tracing::{info, warn};
+
+use crate::exchange::{ExchangeConnection, ExchangeId};
+
+/// 引擎计数器
+pub struct EngineMetrics {
+    registry: Registry,
+    signals_generated: IntCounterVec,
+    signals_blocked: IntCounterVec,
+    executions: IntCounterVec,
+    orders_rejected: IntCounterVec,
+    tickers_processed: IntCounterVec,
+    ws_reconnects: IntCounterVec,
+    orderbook_resyncs: IntCounterVec,
+}
+
+lazy_static::lazy_static! {
+    pub static ref METRICS: EngineMetrics = EngineMetrics::new();
+}
+
+impl EngineMetrics {
+    fn new() -> Self {
+        let registry = Registry::new_custom(Some("inarbit".to_string()), None).expect("metrics registry");
+        let counter = |name: &str, help: &str, labels: &[&str]| {
+            let counter = IntCounterVec::new(Opts::new(name, help), labels).expect("metric definition");
+            registry
+                .register(Box::new(counter.clone()))
+                .expect("metric registration");
+            counter
+        };
+        Self {
+            signals_generated: counter("signals_generated_total", "策略生成的信号数", &["exchange"]),
+            signals_blocked: counter("signals_blocked_total", "被拦截的信号数 (按原因)", &["reason"]),
+            executions: counter("executions_total", "信号执行次数 (按结果)", &["exchange", "result"]),
+            orders_rejected: counter("orders_rejected_total", "交易所拒单次数 (按原因，含重试前的每次拒单)", &["exchange", "reason"]),
+            tickers_processed: counter("tickers_processed_total", "分发给策略的行情条数", &["exchange"]),
+            ws_reconnects: counter("ws_reconnects_total", "WebSocket 重连成功次数", &["exchange"]),
+            orderbook_resyncs: counter("orderbook_resyncs_total", "本地订单簿因增量缺口重新拉取快照的次数", &["exchange"]),
+            registry,
+        }
+    }
+
+    pub fn signal_generated(&self, exchange: ExchangeId) {
+        self.signals_generated.with_label_values(&[exchange.key()]).inc();
+    }
+
+    pub fn signal_blocked(&self, reason: &str, count: u64) {
+        self.signals_blocked.with_label_values(&[reason]).inc_by(count);
+    }
+
+    pub fn execution(&self, exchange: ExchangeId, success: bool) {
+        let result = if success { "success" } else { "failed" };
+        self.executions.with_label_values(&[exchange.key(), result]).inc();
+    }
+
+    pub fn order_rejected(&self, exchange: ExchangeId, reason: &str) {
+        self.orders_rejected.with_label_values(&[exchange.key(), reason]).inc();
+    }
+
+    pub fn ticker_processed(&self, exchange: ExchangeId) {
+        self.tickers_processed.with_label_values(&[exchange.key()]).inc();
+    }
+
+    /// 引擎指标点 (`incr_metrics` 的字段名) 中属于信号拦截的部分计入 `signals_blocked_total`
+    pub fn observe(&self, field: &str, delta: i64) {
+        let reason = match field {
+            "signals_inventory_blocked" => "inventory",
+            "signals_exchange_capital_blocked" => "exchange_capital",
+            "signals_venue_blocked" => "venue",
+            "signals_observe_only" => "observe_only",
+            "signals_stale" => "stale",
+            "signals_missing_quote" => "missing_quote",
+            "signal_queue_rejected" => "queue_full",
+            _ => return,
+        };
+        if delta > 0 {
+            self.signal_blocked(reason, delta as u64);
+        }
+    }
+
+    /// 按各连接的累计值同步重连 / 订单簿重新同步计数
+    fn sync_reconnects(&self, connections: &HashMap<ExchangeId, Arc<ExchangeConnection>>) {
+        for (id, conn) in connections {
+            let counter = self.ws_reconnects.with_label_values(&[id.key()]);
+            let (_, successes) = conn.reconnect_counts();
+            counter.inc_by(successes.saturating_sub(counter.get()));
+            let counter = self.orderbook_resyncs.with_label_values(&[id.key()]);
+            counter.inc_by(conn.orderbook_resyncs().saturating_sub(counter.get()));
+        }
+    }
+
+    /// Prometheus 文本格式
+    pub fn render(&self, connections: &HashMap<ExchangeId, Arc<ExchangeConnection>>) -> String {
+        self.sync_reconnects(connections);
+        let mut buf = Vec::new();
+        if let Err(e) = TextEncoder::new().encode(&self.registry.gather(), &mut buf) {
+            warn!("导出 Prometheus 指标失败: {}", e);
+        }
+        String::from_utf8(buf).unwrap_or_default()
+    }
+}
+
+/// 指标服务监听地址 (ENGINE_METRICS_ADDR，如 `0.0.0.0:9811`；未设置时不启动)
+pub fn addr_from_env() -> Option<SocketAddr> {
+    let raw = std::env::var("ENGINE_METRICS_ADDR").ok().filter(|v| !v.trim().is_empty())?;
+    match raw.trim().parse() {
+        Ok(addr) => Some(addr),
+        Err(e) => {
+            warn!("ENGINE_METRICS_ADDR 无效 ({}): {}", raw, e);
+            None
+        }
+    }
+}
+
+pub fn router(connections: HashMap<ExchangeId, Arc<ExchangeConnection>>) -> Router {
+    Router::new()
+        .route("/metrics", get(export))
+        .with_state(Arc::new(connections))
+}
+
+/// 启动指标 HTTP 服务
+pub async fn serve(addr: SocketAddr, connections: HashMap<ExchangeId, Arc<ExchangeConnection>>) -> anyhow::Result<()> {
+    let listener = tokio::net::TcpListener::bind(addr).await?;
+    info!("Prometheus 指标服务监听 {}", addr);
+    axum::serve(listener, router(connections)).await?;
+    Ok(())
+}
+
+async fn export(State(connections): State<Arc<HashMap<ExchangeId, Arc<ExchangeConnection>>>>) -> impl IntoResponse {
+    (
+        [(header::CONTENT_TYPE, "text/plain; version=0.0.4; charset=utf-8")],
+        METRICS.render(&connections),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn metrics_endpoint_renders_counters() {
+        METRICS.signal_blocked("metrics_test", 3);
+        METRICS.execution(ExchangeId::Mexc, false);
+        METRICS.order_rejected(ExchangeId::Mexc, "metrics_test");
+        let connection = ExchangeConnection::new(ExchangeId::Bitget).await.unwrap();
+        let connections = HashMap::from([(ExchangeId::Bitget, Arc::new(connection))]);
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, router(connections)).await });
+        let response = reqwest::get(format!("http://{}/metrics", addr)).await.unwrap();
+        assert!(response.headers()["content-type"].to_str().unwrap().starts_with("text/plain"));
+        let body = response.text().await.unwrap();
+
+        for line in [
+            "inarbit_signals_blocked_total{reason=\"metrics_test\"} 3",
+            "inarbit_orders_rejected_total{exchange=\"mexc\",reason=\"metrics_test\"} 1",
+            "inarbit_ws_reconnects_total{exchange=\"bitget\"} 0",
+            "inarbit_orderbook_resyncs_total{exchange=\"bitget\"} 0",
+        ] {
+            assert!(body.lines().any(|l| l == line), "缺少指标行 {}:\n{}", line, body);
+        }
+        assert!(body.contains("inarbit_executions_total{exchange=\"mexc\",result=\"failed\"}"), "{}", body);
+    }
+}
@@ -0,0 +1,338 @@
+//! 下单前的订单轧差
+//!
+//! 不同策略可能在同一时刻对同一交易所的同一交易对下相反方向的单 (如跨所策略卖出 BTC、
+//! 三角套利买入 BTC)，分别成交会白白支付两次手续费。开启后，同一交易所、同一交易对的市价单
+//! 在一个短窗口内汇集，买卖相抵的部分在内部按标记价格成交，只把净额作为一笔订单发送
+//! (完全相抵时不下单)。限价单各自有价格约束，不参与轧差。
+//!
+//! 由 ENGINE_ORDER_NETTING 开启 (默认关闭)，ENGINE_ORDER_NETTING_WINDOW_MS 指定汇集窗口 (默认 50 毫秒)。
+
+use anyhow::Result;
+use std::collections::HashMap;
+use std::future::Future;
+use std::time::Duration;
+use tokio::sync::{oneshot, Mutex};
+use tracing::info;
+
+use crate::exchange::ExchangeId;
+use crate::executor::{OrderRequest, OrderResponse, OrderSide, OrderStatus, OrderType};
+use crate::symbols::normalize_symbol;
+
+/// 等待汇集的订单
+struct Pending {
+    request: OrderRequest,
+    mark_price: Option<f64>,
+    /// 窗口发起者自身没有回报通道
+    reply: Option<oneshot::Sender<Result<OrderResponse, String>>>,
+}
+
+/// 订单轧差器
+pub struct OrderNetter {
+    window: Duration,
+    pending: Mutex<HashMap<(ExchangeId, String), Vec<Pending>>>,
+}
+
+impl OrderNetter {
+    pub fn new(window: Duration) -> Self {
+        Self {
+            window,
+            pending: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// ENGINE_ORDER_NETTING 开启时创建
+    pub fn from_env() -> Option<Self> {
+        let enabled = std::env::var("ENGINE_ORDER_NETTING")
+            .map(|v| matches!(v.as_str(), "1" | "true" | "True"))
+            .unwrap_or(false);
+        if !enabled {
+            return None;
+        }
+        let window_ms = std::env::var("ENGINE_ORDER_NETTING_WINDOW_MS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(50);
+        Some(Self::new(Duration::from_millis(window_ms)))
+    }
+
+    /// 提交订单: 窗口内首个订单负责等待窗口结束、轧差并发送净额订单，其余订单等待分配结果。
+    /// `mark_price` 为内部相抵部分的成交价 (通常为当前中间价)，`send` 实际发送订单。
+    pub async fn submit<F, Fut>(&self, request: OrderRequest, mark_price: Option<f64>, send: F) -> Result<OrderResponse>
+    where
+        F: FnOnce(OrderRequest) -> Fut,
+        Fut: Future<Output = Result<OrderResponse>>,
+    {
+        if !matches!(request.order_type, OrderType::Market) {
+            return send(request).await;
+        }
+        let key = (request.exchange, normalize_symbol(request.exchange, &request.symbol));
+        {
+            let mut pending = self.pending.lock().await;
+            if let Some(group) = pending.get_mut(&key) {
+                let (tx, rx) = oneshot::channel();
+                group.push(Pending {
+                    request,
+                    mark_price,
+                    reply: Some(tx),
+                });
+                drop(pending);
+                return rx
+                    .await
+                    .map_err(|_| anyhow::anyhow!("订单轧差任务已中断"))?
+                    .map_err(|e| anyhow::anyhow!(e));
+            }
+            pending.insert(
+                key.clone(),
+                vec![Pending {
+                    request,
+                    mark_price,
+                    reply: None,
+                }],
+            );
+        }
+        tokio::time::sleep(self.window).await;
+        let group = self.pending.lock().await.remove(&key).unwrap_or_default();
+        let requests: Vec<OrderRequest> = group.iter().map(|p| p.request.clone()).collect();
+        let mark = group.iter().find_map(|p| p.mark_price);
+        let plan = net_orders(&requests);
+        if requests.len() > 1 {
+            info!(
+                "{:?} {} 轧差 {} 笔订单: 内部相抵 {}，净额 {}",
+                key.0,
+                key.1,
+                requests.len(),
+                plan.crossed,
+                plan.net.as_ref().map(|n| n.amount).unwrap_or(0.0)
+            );
+        }
+        let net_result = match plan.net.clone() {
+            Some(net) => Some(send(net).await),
+            None => None,
+        };
+        let mut own = None;
+        for (i, pending) in group.into_iter().enumerate() {
+            let response = allocate(&pending.request, &plan, i, net_result.as_ref(), mark);
+            match pending.reply {
+                Some(reply) => {
+                    let _ = reply.send(response);
+                }
+                None => own = Some(response),
+            }
+        }
+        own.unwrap_or_else(|| Err("订单轧差结果缺失".to_string()))
+            .map_err(|e| anyhow::anyhow!(e))
+    }
+}
+
+/// 轧差结果
+#[derive(Debug, Clone)]
+pub struct NetPlan {
+    /// 需要发送的净额订单 (完全相抵时为 None)
+    pub net: Option<OrderRequest>,
+    /// 买卖相抵的数量
+    pub crossed: f64,
+    /// 每笔订单在内部相抵部分中的数量
+    pub crossed_by_order: Vec<f64>,
+    /// 每笔订单在净额订单中的数量
+    pub net_by_order: Vec<f64>,
+}
+
+/// 对同一交易所、同一交易对的一组订单轧差。
+/// 少数一方全部在内部相抵，多数一方按数量比例分摊相抵部分，剩余部分合并为一笔净额订单。
+pub fn net_orders(orders: &[OrderRequest]) -> NetPlan {
+    let is_buy = |o: &OrderRequest| matches!(o.side, OrderSide::Buy);
+    let buys: f64 = orders.iter().filter(|o| is_buy(o)).map(|o| o.amount).sum();
+    let sells: f64 = orders.iter().filter(|o| !is_buy(o)).map(|o| o.amount).sum();
+    let crossed = buys.min(sells);
+    let net_buy = buys >= sells;
+    let (major, net_amount) = if net_buy { (buys, buys - sells) } else { (sells, sells - buys) };
+    let mut crossed_by_order = Vec::with_capacity(orders.len());
+    let mut net_by_order = Vec::with_capacity(orders.len());
+    for order in orders {
+        if is_buy(order) == net_buy && major > 0.0 {
+            let share = order.amount / major;
+            crossed_by_order.push(crossed * share);
+            net_by_order.push(net_amount * share);
+        } else {
+            crossed_by_order.push(order.amount);
+            net_by_order.push(0.0);
+        }
+    }
+    let net = orders
+        .iter()
+        .find(|o| is_buy(o) == net_buy)
+        .filter(|_| net_amount > f64::EPSILON * major.max(1.0))
+        .map(|template| OrderRequest {
+            side: if net_buy { OrderSide::Buy } else { OrderSide::Sell },
+            amount: net_amount,
+            ..template.clone()
+        });
+    NetPlan {
+        net,
+        crossed,
+        crossed_by_order,
+        net_by_order,
+    }
+}
+
+/// 按轧差结果生成单笔订单的回报: 内部相抵部分按标记价成交且不收手续费，
+/// 净额部分按比例分摊净额订单的成交量与手续费
+fn allocate(
+    request: &OrderRequest,
+    plan: &NetPlan,
+    index: usize,
+    net_result: Option<&Result<OrderResponse>>,
+    mark: Option<f64>,
+) -> Result<OrderResponse, String> {
+    let crossed = plan.crossed_by_order[index];
+    let net_share = plan.net_by_order[index];
+    let net_fill = match net_result {
+        Some(Ok(net)) if net_share > 0.0 => {
+            let ratio = net_share / plan.net.as_ref().map(|n| n.amount).unwrap_or(net_share);
+            Some((net, ratio))
+        }
+        Some(Err(e)) if net_share > 0.0 && crossed <= 0.0 => return Err(e.to_string()),
+        _ => None,
+    };
+    let cross_price = mark
+        .or(net_fill.map(|(net, _)| net.avg_price))
+        .unwrap_or(0.0);
+    let (net_filled, net_price, fee, other_fees, latency_ms, order_id, net_status) = match net_fill {
+        Some((net, ratio)) => (
+            net.filled_amount * ratio,
+            net.avg_price,
+            net.fee * ratio,
+            net.other_fees.iter().map(|(asset, amount)| (asset.clone(), amount * ratio)).collect(),
+            net.latency_ms,
+            // 同一净额订单分摊给多笔订单时加序号，避免被当作重复回报合并
+            if plan.net_by_order.len() > 1 {
+                format!("{}:{}", net.order_id, index)
+            } else {
+                net.order_id.clone()
+            },
+            Some(net.status),
+        ),
+        None => (0.0, 0.0, 0.0, vec![], 0, format!("netted-{}", uuid::Uuid::new_v4()), None),
+    };
+    let filled_amount = crossed + net_filled;
+    let avg_price = if filled_amount > 0.0 {
+        (crossed * cross_price + net_filled * net_price) / filled_amount
+    } else {
+        0.0
+    };
+    let status = if filled_amount + f64::EPSILON >= request.amount {
+        OrderStatus::Filled
+    } else if filled_amount > 0.0 {
+        OrderStatus::PartialFilled
+    } else {
+        net_status.unwrap_or(OrderStatus::Failed)
+    };
+    Ok(OrderResponse {
+        order_id,
+        exchange: request.exchange,
+        symbol: request.symbol.clone(),
+        side: request.side,
+        status,
+        filled_amount,
+        avg_price,
+        fee,
+        latency_ms,
+        other_fees,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex as StdMutex};
+
+    fn market(side: OrderSide, amount: f64) -> OrderRequest {
+        OrderRequest {
+            exchange: ExchangeId::Binance,
+            symbol: "BTC/USDT".to_string(),
+            side,
+            order_type: OrderType::Market,
+            amount,
+            price: None,
+            client_order_id: None,
+        }
+    }
+
+    /// 按 101 全部成交的下单函数，记录实际发送的订单
+    fn sender(
+        sent: Arc<StdMutex<Vec<OrderRequest>>>,
+    ) -> impl FnOnce(OrderRequest) -> std::future::Ready<Result<OrderResponse>> {
+        move |request| {
+            sent.lock().unwrap().push(request.clone());
+            std::future::ready(Ok(OrderResponse {
+                order_id: "net-1".to_string(),
+                exchange: request.exchange,
+                symbol: request.symbol,
+                side: request.side,
+                status: OrderStatus::Filled,
+                filled_amount: request.amount,
+                avg_price: 101.0,
+                fee: request.amount * 101.0 * 0.001,
+                latency_ms: 5,
+                other_fees: vec![],
+            }))
+        }
+    }
+
+    #[tokio::test]
+    async fn opposite_simultaneous_legs_net_to_one_residual_order() {
+        let netter = OrderNetter::new(Duration::from_millis(20));
+        let sent = Arc::new(StdMutex::new(vec![]));
+        // 两个策略同时对同一交易对下相反方向的市价单
+        let (buy, sell) = tokio::join!(
+            netter.submit(market(OrderSide::Buy, 1.0), Some(100.0), sender(sent.clone())),
+            netter.submit(market(OrderSide::Sell, 0.4), Some(100.0), sender(sent.clone())),
+        );
+        let (buy, sell) = (buy.unwrap(), sell.unwrap());
+
+        let sent = sent.lock().unwrap();
+        assert_eq!(sent.len(), 1);
+        assert!(matches!(sent[0].side, OrderSide::Buy));
+        assert!((sent[0].amount - 0.6).abs() < 1e-12);
+
+        // 卖单全部在内部按标记价相抵，不付手续费
+        assert!(matches!(sell.status, OrderStatus::Filled));
+        assert!((sell.filled_amount - 0.4).abs() < 1e-12);
+        assert_eq!(sell.avg_price, 100.0);
+        assert_eq!(sell.fee, 0.0);
+        // 买单 0.4 内部按 100 成交、0.6 随净额订单按 101 成交，只承担净额订单的手续费
+        assert!(matches!(buy.status, OrderStatus::Filled));
+        assert!((buy.filled_amount - 1.0).abs() < 1e-12);
+        assert!((buy.avg_price - 100.6).abs() < 1e-9);
+        assert!((buy.fee - 0.6 * 101.0 * 0.001).abs() < 1e-12);
+        assert!(buy.order_id.starts_with("net-1"));
+    }
+
+    #[tokio::test]
+    async fn fully_offsetting_orders_send_nothing() {
+        let netter = OrderNetter::new(Duration::from_millis(20));
+        let sent = Arc::new(StdMutex::new(vec![]));
+        let (buy, sell) = tokio::join!(
+            netter.submit(market(OrderSide::Buy, 0.5), Some(100.0), sender(sent.clone())),
+            netter.submit(market(OrderSide::Sell, 0.5), Some(100.0), sender(sent.clone())),
+        );
+        assert!(sent.lock().unwrap().is_empty());
+        for fill in [buy.unwrap(), sell.unwrap()] {
+            assert!(matches!(fill.status, OrderStatus::Filled));
+            assert_eq!(fill.avg_price, 100.0);
+            assert_eq!(fill.fee, 0.0);
+        }
+    }
+
+    #[tokio::test]
+    async fn limit_orders_are_not_netted() {
+        let netter = OrderNetter::new(Duration::from_millis(20));
+        let sent = Arc::new(StdMutex::new(vec![]));
+        let mut limit = market(OrderSide::Buy, 1.0);
+        limit.order_type = OrderType::Limit;
+        limit.price = Some(99.0);
+        let fill = netter.submit(limit, Some(100.0), sender(sent.clone())).await.unwrap();
+        assert_eq!(sent.lock().unwrap().len(), 1);
+        assert_eq!(fill.filled_amount, 1.0);
+    }
+}
@@ -0,0 +1,483 @@
+//! OKX 现货 REST 下单客户端
+//!
+//! 私有接口 (`/api/v5/trade/*`) 需要签名: 对 `timestamp + METHOD + requestPath + body` 做 HMAC-SHA256 并输出 base64，
+//! 连同 API Key、Passphrase 与 ISO 8601 毫秒时间戳放在 `OK-ACCESS-*` 请求头。GET 请求的查询串属于 requestPath，body 为空。
+//!
+//! 现货按 `tdMode=cash` 下单，市价单以 `tgtCcy=base_ccy` 按基础币数量下单 (OKX 市价买单默认按计价币金额)。
+//! 信号腿下单带 `clOrdId`，超时或 5xx 后调用方先按 `clOrdId` 查询订单是否已受理，再决定是否重发。
+//! 下单响应只有订单号，成交均价与手续费由随后的订单查询补全；手续费以计价币计 (以基础币扣除的按成交均价折算)。
+//! 响应统一为 `{"code":"0","msg":"","data":[...]}`，`code != "0"` 时优先取 `data[0].sCode` / `sMsg`，
+//! 经 [`RejectionMap`] 映射为结构化执行错误，错误信息保留交易所原文。
+
+use anyhow::{Context, Result};
+use reqwest::Method;
+use std::time::{Duration, Instant};
+use tracing::warn;
+
+use crate::exchange::{ExchangeConfig, ExchangeId};
+use crate::executor::{OrderRequest, OrderResponse, OrderSide, OrderStatus, OrderType};
+use crate::rejections::{ExecutionError, RejectionMap};
+use crate::signing::{KeyType, Signer};
+use crate::symbols::{denormalize_symbol, split_symbol};
+
+/// 订单不存在的错误码
+const UNKNOWN_ORDER: &str = "51603";
+
+/// OKX 现货签名 REST 客户端
+pub struct OkxRestClient {
+    client: reqwest::Client,
+    base_url: String,
+    api_key: String,
+    passphrase: String,
+    signer: Signer,
+    rejections: RejectionMap,
+}
+
+impl OkxRestClient {
+    pub fn new(
+        base_url: impl Into<String>,
+        api_key: impl Into<String>,
+        secret: &str,
+        passphrase: impl Into<String>,
+    ) -> Result<Self> {
+        Ok(Self {
+            client: reqwest::Client::builder().timeout(Duration::from_secs(10)).build()?,
+            base_url: base_url.into().trim_end_matches('/').to_string(),
+            api_key: api_key.into(),
+            passphrase: passphrase.into(),
+            signer: Signer::new(KeyType::Hmac, secret)?,
+            rejections: RejectionMap::from_env(),
+        })
+    }
+
+    /// 由交易所配置构建 (未配置 API Key / Secret / Passphrase 时返回 None)
+    pub fn from_config(config: &ExchangeConfig) -> Result<Option<Self>> {
+        let passphrase = config.passphrase.as_deref().unwrap_or_default();
+        if config.id != ExchangeId::Okx || config.api_key.is_empty() || config.api_secret.is_empty() || passphrase.is_empty()
+        {
+            return Ok(None);
+        }
+        if config.key_type != KeyType::Hmac {
+            anyhow::bail!("OKX 只支持 HMAC API Key");
+        }
+        let base_url = config.rest_url.as_deref().unwrap_or(ExchangeId::Okx.rest_url());
+        Self::new(base_url, config.api_key.clone(), &config.api_secret, passphrase).map(Some)
+    }
+
+    /// 请求签名 (base64)
+    pub fn sign(&self, timestamp: &str, method: &Method, request_path: &str, body: &str) -> String {
+        self.signer.sign_base64(&prehash(timestamp, method, request_path, body))
+    }
+
+    /// 私有 WebSocket 登录帧: 以秒级时间戳对 `GET /users/self/verify` 签名
+    pub fn ws_login_frame(&self) -> String {
+        let timestamp = chrono::Utc::now().timestamp().to_string();
+        serde_json::json!({
+            "op": "login",
+            "args": [{
+                "apiKey": self.api_key,
+                "passphrase": self.passphrase,
+                "timestamp": timestamp,
+                "sign": self.sign(&timestamp, &Method::GET, "/users/self/verify", ""),
+            }]
+        })
+        .to_string()
+    }
+
+    /// 下单，随后查询一次补全成交信息 (查询失败时按挂单中返回订单号)
+    pub async fn new_order(&self, request: &OrderRequest) -> Result<OrderResponse> {
+        let inst_id = denormalize_symbol(ExchangeId::Okx, &request.symbol);
+        let mut body = serde_json::json!({
+            "instId": inst_id,
+            "tdMode": "cash",
+            "side": side_param(request.side),
+            "sz": request.amount.to_string(),
+        });
+        if let Some(client_order_id) = &request.client_order_id {
+            body["clOrdId"] = client_order_id.as_str().into();
+        }
+        match (request.order_type, request.price) {
+            (OrderType::Limit, Some(price)) => {
+                body["ordType"] = "limit".into();
+                body["px"] = price.to_string().into();
+            }
+            (OrderType::Limit, None) => anyhow::bail!("OKX 限价单缺少价格: {}", request.symbol),
+            (OrderType::Market, _) => {
+                body["ordType"] = "market".into();
+                body["tgtCcy"] = "base_ccy".into();
+            }
+        }
+        let started = Instant::now();
+        let data = self.signed(Method::POST, "/api/v5/trade/order", Some(&body)).await?;
+        let order_id = first_order_id(&data).with_context(|| format!("OKX 下单响应缺少 ordId: {}", data))?;
+        match self.query_order(&request.symbol, &order_id).await {
+            Ok(mut response) => {
+                response.latency_ms = started.elapsed().as_millis() as u64;
+                Ok(response)
+            }
+            Err(err) => {
+                warn!("OKX 订单 {} 查询失败，按挂单中处理: {}", order_id, err);
+                Ok(OrderResponse {
+                    order_id,
+                    exchange: ExchangeId::Okx,
+                    symbol: request.symbol.clone(),
+                    side: request.side,
+                    status: OrderStatus::Pending,
+                    filled_amount: 0.0,
+                    avg_price: request.price.unwrap_or(0.0),
+                    fee: 0.0,
+                    latency_ms: started.elapsed().as_millis() as u64,
+                    other_fees: vec![],
+                })
+            }
+        }
+    }
+
+    /// 原生改单 (`native_amend_params` 构建的 `instId` / `ordId` / `newPx` / `newSz`)，随后查询一次返回改单后的订单
+    pub async fn amend_order(&self, symbol: &str, params: &serde_json::Value) -> Result<OrderResponse> {
+        let data = self.signed(Method::POST, "/api/v5/trade/amend-order", Some(params)).await?;
+        let order_id = first_order_id(&data).with_context(|| format!("OKX 改单响应缺少 ordId: {}", data))?;
+        self.query_order(symbol, &order_id).await
+    }
+
+    /// 挂策略委托 (`native_stop_params` 构建的 conditional 止损)，返回 algoId
+    pub async fn new_algo_order(&self, params: &serde_json::Value) -> Result<String> {
+        let data = self.signed(Method::POST, "/api/v5/trade/order-algo", Some(params)).await?;
+        data.get(0)
+            .and_then(|item| item.get("algoId"))
+            .and_then(|id| id.as_str())
+            .map(str::to_string)
+            .with_context(|| format!("OKX 策略委托响应缺少 algoId: {}", data))
+    }
+
+    /// 撤销策略委托
+    pub async fn cancel_algo_order(&self, symbol: &str, algo_id: &str) -> Result<()> {
+        let body = serde_json::json!([{
+            "instId": denormalize_symbol(ExchangeId::Okx, symbol),
+            "algoId": algo_id,
+        }]);
+        self.signed(Method::POST, "/api/v5/trade/cancel-algos", Some(&body)).await?;
+        Ok(())
+    }
+
+    /// 撤单
+    pub async fn cancel_order(&self, symbol: &str, order_id: &str) -> Result<()> {
+        let body = serde_json::json!({
+            "instId": denormalize_symbol(ExchangeId::Okx, symbol),
+            "ordId": order_id,
+        });
+        self.signed(Method::POST, "/api/v5/trade/cancel-order", Some(&body)).await?;
+        Ok(())
+    }
+
+    /// 查询订单
+    pub async fn query_order(&self, symbol: &str, order_id: &str) -> Result<OrderResponse> {
+        let path = format!(
+            "/api/v5/trade/order?instId={}&ordId={}",
+            denormalize_symbol(ExchangeId::Okx, symbol),
+            order_id
+        );
+        let started = Instant::now();
+        let data = self.signed(Method::GET, &path, None).await?;
+        let order = data.get(0).with_context(|| format!("OKX 订单查询响应为空: {}", data))?;
+        parse_order(order, symbol, started.elapsed().as_millis() as u64)
+            .with_context(|| format!("OKX 订单查询响应格式错误: {}", order))
+    }
+
+    /// 按客户端订单号查询订单，订单不存在时返回 None (重发超时的下单前确认上一次请求是否已受理)
+    pub async fn query_client_order(&self, symbol: &str, client_order_id: &str) -> Result<Option<OrderResponse>> {
+        let path = format!(
+            "/api/v5/trade/order?instId={}&clOrdId={}",
+            denormalize_symbol(ExchangeId::Okx, symbol),
+            client_order_id
+        );
+        let started = Instant::now();
+        let data = match self.signed(Method::GET, &path, None).await {
+            Ok(data) => data,
+            Err(e) if e.downcast_ref::<ExecutionError>().is_some_and(|e| e.is_rejected_code(UNKNOWN_ORDER)) => {
+                return Ok(None)
+            }
+            Err(e) => return Err(e),
+        };
+        let Some(order) = data.get(0) else {
+            return Ok(None);
+        };
+        parse_order(order, symbol, started.elapsed().as_millis() as u64)
+            .map(Some)
+            .with_context(|| format!("OKX 订单查询响应格式错误: {}", order))
+    }
+
+    /// 发送签名请求，返回 `data` 数组；`code != "0"` 或非 2xx 时映射为结构化执行错误
+    async fn signed(&self, method: Method, request_path: &str, body: Option<&serde_json::Value>) -> Result<serde_json::Value> {
+        let body = body.map(|b| b.to_string()).unwrap_or_default();
+        let timestamp = chrono::Utc::now().format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string();
+        let signature = self.sign(&timestamp, &method, request_path, &body);
+        let mut request = self
+            .client
+            .request(method, format!("{}{}", self.base_url, request_path))
+            .header("OK-ACCESS-KEY", &self.api_key)
+            .header("OK-ACCESS-SIGN", signature)
+            .header("OK-ACCESS-TIMESTAMP", timestamp)
+            .header("OK-ACCESS-PASSPHRASE", &self.passphrase)
+            .header("Content-Type", "application/json");
+        if !body.is_empty() {
+            request = request.body(body);
+        }
+        let response = request.send().await?;
+        let status = response.status().as_u16();
+        let text = response.text().await?;
+        parse_envelope(&self.rejections, status, &text).map_err(Into::into)
+    }
+}
+
+/// 待签名串: timestamp + METHOD + requestPath + body
+pub fn prehash(timestamp: &str, method: &Method, request_path: &str, body: &str) -> String {
+    format!("{}{}{}{}", timestamp, method.as_str(), request_path, body)
+}
+
+/// 解析响应信封: 成功时返回 `data`，否则返回带交易所错误码与原文的执行错误
+pub fn parse_envelope(rejections: &RejectionMap, http_status: u16, text: &str) -> Result<serde_json::Value, ExecutionError> {
+    let json: serde_json::Value = serde_json::from_str(text).unwrap_or_default();
+    let code = json.get("code").and_then(|c| c.as_str()).unwrap_or_default();
+    if (200..300).contains(&http_status) && code == "0" {
+        return Ok(json.get("data").cloned().unwrap_or_else(|| serde_json::json!([])));
+    }
+    // 批量 / 单笔下单失败时顶层只有 "1"，具体原因在 data[0].sCode / sMsg
+    let item = json.get("data").and_then(|d| d.get(0));
+    let item_code = item
+        .and_then(|i| i.get("sCode"))
+        .and_then(|c| c.as_str())
+        .filter(|c| !c.is_empty() && *c != "0");
+    let (code, message) = match item_code {
+        Some(s_code) => (s_code, item.and_then(|i| i.get("sMsg")).and_then(|m| m.as_str()).unwrap_or_default()),
+        None => (code, json.get("msg").and_then(|m| m.as_str()).unwrap_or_default()),
+    };
+    let message = if message.is_empty() { text } else { message };
+    Err(rejections.classify(ExchangeId::Okx, http_status, code, message))
+}
+
+fn side_param(side: OrderSide) -> &'static str {
+    match side {
+        OrderSide::Buy => "buy",
+        OrderSide::Sell => "sell",
+    }
+}
+
+fn first_order_id(data: &serde_json::Value) -> Option<String> {
+    Some(data.get(0)?.get("ordId")?.as_str()?.to_string())
+}
+
+/// 订单状态映射 (订单查询与私有 orders 频道推送的 `state` 共用)
+pub fn order_status(state: &str) -> OrderStatus {
+    match state {
+        "live" => OrderStatus::Pending,
+        "partially_filled" => OrderStatus::PartialFilled,
+        "filled" => OrderStatus::Filled,
+        "canceled" | "mmp_canceled" => OrderStatus::Cancelled,
+        _ => OrderStatus::Failed,
+    }
+}
+
+fn str_f64(value: Option<&serde_json::Value>) -> Option<f64> {
+    value?.as_str()?.parse().ok()
+}
+
+/// 将订单查询结果映射为订单回报:
+/// `{"instId":"BTC-USDT","ordId":"312269865356374016","state":"filled","side":"buy","accFillSz":"0.01","avgPx":"30000",
+///   "fee":"-0.00001","feeCcy":"BTC","px":""}`
+pub fn parse_order(order: &serde_json::Value, symbol: &str, latency_ms: u64) -> Option<OrderResponse> {
+    let filled_amount = str_f64(order.get("accFillSz")).unwrap_or(0.0);
+    let avg_price = str_f64(order.get("avgPx"))
+        .filter(|p| *p > 0.0)
+        .or_else(|| str_f64(order.get("px")))
+        .unwrap_or(0.0);
+    // OKX 手续费为负数表示扣除
+    let fee = -str_f64(order.get("fee")).unwrap_or(0.0);
+    let fee_ccy = order.get("feeCcy").and_then(|c| c.as_str()).unwrap_or_default();
+    let (base, quote) = split_symbol(symbol).unwrap_or_default();
+    let fee = if !base.is_empty() && fee_ccy == base && fee_ccy != quote {
+        fee * avg_price
+    } else {
+        fee
+    };
+    Some(OrderResponse {
+        order_id: order.get("ordId")?.as_str()?.to_string(),
+        exchange: ExchangeId::Okx,
+        symbol: symbol.to_string(),
+        side: match order.get("side")?.as_str()? {
+            "sell" => OrderSide::Sell,
+            _ => OrderSide::Buy,
+        },
+        status: order_status(order.get("state")?.as_str()?),
+        filled_amount,
+        avg_price,
+        fee,
+        latency_ms,
+        other_fees: vec![],
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::extract::{OriginalUri, Query};
+    use axum::http::HeaderMap;
+    use axum::routing::post;
+    use axum::Json;
+    use serde_json::json;
+    use std::collections::HashMap;
+    use std::sync::{Arc, Mutex};
+
+    const SECRET: &str = "22582BD0CFF14C41EDBF1AB98506286D";
+    const TIMESTAMP: &str = "2020-12-08T09:08:57.715Z";
+
+    fn client(base_url: &str) -> OkxRestClient {
+        OkxRestClient::new(base_url, "key", SECRET, "pass").unwrap()
+    }
+
+    #[test]
+    fn prehash_concatenates_timestamp_method_path_and_body() {
+        assert_eq!(
+            prehash(TIMESTAMP, &Method::GET, "/api/v5/account/balance?ccy=BTC", ""),
+            "2020-12-08T09:08:57.715ZGET/api/v5/account/balance?ccy=BTC"
+        );
+        let client = client("http://localhost");
+        // GET 的查询串属于 requestPath，body 为空
+        assert_eq!(
+            client.sign(TIMESTAMP, &Method::GET, "/api/v5/account/balance?ccy=BTC", ""),
+            "HiZhvSfMtWJA3uUIVXV3a/bSXNPCWvYFXoGCVS8V4zY="
+        );
+        assert_eq!(
+            client.sign(TIMESTAMP, &Method::POST, "/api/v5/trade/order", r#"{"instId":"BTC-USDT","side":"buy"}"#),
+            "zmyb6X+C82MRtc7BTVirfIGW6UYRjcmBxRhn68EyGI4="
+        );
+    }
+
+    #[test]
+    fn envelope_errors_map_to_execution_errors() {
+        let rejections = RejectionMap::default();
+        let data = parse_envelope(&rejections, 200, r#"{"code":"0","msg":"","data":[{"ordId":"1","sCode":"0"}]}"#).unwrap();
+        assert_eq!(first_order_id(&data).as_deref(), Some("1"));
+
+        // 顶层 code 为 "1" 时取 data[0].sCode / sMsg
+        let err = parse_envelope(
+            &rejections,
+            200,
+            r#"{"code":"1","msg":"Operation failed.","data":[{"ordId":"","sCode":"51008","sMsg":"Order failed. Insufficient USDT balance"}]}"#,
+        )
+        .unwrap_err();
+        match err {
+            ExecutionError::InsufficientBalance { exchange, message } => {
+                assert_eq!(exchange, ExchangeId::Okx);
+                assert_eq!(message, "Order failed. Insufficient USDT balance");
+            }
+            other => panic!("unexpected error: {:?}", other),
+        }
+
+        // sCode 为 "0" 时回退到顶层 code / msg；未登记的错误码保留原码与原文
+        let err = parse_envelope(&rejections, 200, r#"{"code":"51000","msg":"Parameter px error","data":[{"sCode":"0"}]}"#)
+            .unwrap_err();
+        match err {
+            ExecutionError::Rejected { code, message, .. } => {
+                assert_eq!(code, "51000");
+                assert_eq!(message, "Parameter px error");
+            }
+            other => panic!("unexpected error: {:?}", other),
+        }
+
+        let err = parse_envelope(&rejections, 429, r#"{"code":"50011","msg":"Too Many Requests"}"#).unwrap_err();
+        assert!(matches!(err, ExecutionError::RateLimited { .. }));
+        // 非 JSON 的 5xx 响应按临时故障处理，错误信息保留响应原文
+        match parse_envelope(&rejections, 502, "Bad Gateway").unwrap_err() {
+            ExecutionError::Transient { message, .. } => assert_eq!(message, "Bad Gateway"),
+            other => panic!("unexpected error: {:?}", other),
+        }
+    }
+
+    /// 模拟 OKX 交易接口: 按收到的 OK-ACCESS-* 请求头重新计算签名，签名不符时返回 50113；
+    /// 下单成功后订单查询为以 30000 全部成交、手续费 0.00001 BTC
+    async fn mock_server(signatures: Arc<Mutex<Vec<bool>>>) -> String {
+        let verify = move |headers: &HeaderMap, method: Method, path: &str, body: &str| {
+            let header = |name: &str| headers.get(name).and_then(|v| v.to_str().ok()).unwrap_or_default().to_string();
+            let expected = Signer::new(KeyType::Hmac, SECRET)
+                .unwrap()
+                .sign_base64(&prehash(&header("OK-ACCESS-TIMESTAMP"), &method, path, body));
+            let ok = header("OK-ACCESS-SIGN") == expected && header("OK-ACCESS-PASSPHRASE") == "pass";
+            signatures.lock().unwrap().push(ok);
+            ok
+        };
+        let verify_get = verify.clone();
+        let app = axum::Router::new().route(
+            "/api/v5/trade/order",
+            post(move |headers: HeaderMap, body: String| async move {
+                if !verify(&headers, Method::POST, "/api/v5/trade/order", &body) {
+                    return Json(json!({"code": "50113", "msg": "Invalid Sign", "data": []}));
+                }
+                Json(json!({"code": "0", "msg": "", "data": [{"ordId": "312269865356374016", "sCode": "0", "sMsg": ""}]}))
+            })
+            .get(
+                move |headers: HeaderMap, OriginalUri(uri): OriginalUri, Query(query): Query<HashMap<String, String>>| async move {
+                    let path = uri.path_and_query().map(|p| p.as_str()).unwrap_or_default().to_string();
+                    if !verify_get(&headers, Method::GET, &path, "") {
+                        return Json(json!({"code": "50113", "msg": "Invalid Sign", "data": []}));
+                    }
+                    Json(json!({"code": "0", "msg": "", "data": [{
+                        "instId": query["instId"], "ordId": query["ordId"], "state": "filled", "side": "buy",
+                        "accFillSz": "0.01", "avgPx": "30000", "fee": "-0.00001", "feeCcy": "BTC", "px": ""
+                    }]}))
+                },
+            ),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        format!("http://{}", addr)
+    }
+
+    #[tokio::test]
+    async fn signed_order_is_verified_and_completed_by_query() {
+        let signatures = Arc::new(Mutex::new(vec![]));
+        let client = client(&mock_server(signatures.clone()).await);
+        let request = OrderRequest {
+            exchange: ExchangeId::Okx,
+            symbol: "BTC/USDT".to_string(),
+            side: OrderSide::Buy,
+            order_type: OrderType::Market,
+            amount: 0.01,
+            price: None,
+            client_order_id: None,
+        };
+        let response = client.new_order(&request).await.unwrap();
+        // 下单 (POST body) 与查询 (GET 查询串) 的签名都通过校验
+        assert_eq!(*signatures.lock().unwrap(), vec![true, true]);
+        assert_eq!(response.order_id, "312269865356374016");
+        assert!(matches!(response.status, OrderStatus::Filled));
+        assert!((response.filled_amount - 0.01).abs() < 1e-12);
+        assert!((response.avg_price - 30000.0).abs() < 1e-9);
+        // 以基础币扣除的手续费按成交均价折算为计价币
+        assert!((response.fee - 0.3).abs() < 1e-9);
+    }
+
+    #[tokio::test]
+    async fn rejected_signature_surfaces_exchange_code() {
+        let base_url = mock_server(Arc::new(Mutex::new(vec![]))).await;
+        let client = OkxRestClient::new(base_url, "key", "wrong-secret", "pass").unwrap();
+        let request = OrderRequest {
+            exchange: ExchangeId::Okx,
+            symbol: "BTC/USDT".to_string(),
+            side: OrderSide::Sell,
+            order_type: OrderType::Limit,
+            amount: 0.01,
+            price: Some(30000.0),
+            client_order_id: None,
+        };
+        let err = client.new_order(&request).await.unwrap_err();
+        match err.downcast_ref::<ExecutionError>() {
+            Some(ExecutionError::Rejected { code, message, .. }) => {
+                assert_eq!(code, "50113");
+                assert_eq!(message, "Invalid Sign");
+            }
+            other => panic!("unexpected error: {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,520 @@
+//! 本地订单簿维护 (Binance / Gate / MEXC 快照 + 增量)
+//!
+//! Binance 的全量深度需要 REST 快照 (`/api/v3/depth`) 加增量推送 (`<symbol>@depth@100ms` 的 `depthUpdate`) 维护:
+//! 1. 订阅增量后拉取快照，拉取期间到达的增量先缓存；
+//! 2. 快照到达后丢弃 `u <= lastUpdateId` 的缓存增量，其余按序应用；
+//! 3. 每条应用的增量须满足 `U <= 本地序列号 + 1 < u + 1`，否则视为丢包 / 乱序，
+//!    丢弃本地订单簿并重新拉取快照，而不是在缺口上继续叠加出错误的盘口。
+//!
+//! Gate (`spot.order_book_update`，快照 `/api/v4/spot/order_book?with_id=true` 的 `id`) 的 U / u 语义与 Binance 相同；
+//! MEXC (`spot@public.increase.depth.v3.api`，快照 `/api/v3/depth` 的 `lastUpdateId`) 每条增量只有一个版本号 `r`，
+//! 视为 U = u = r，连续性规则不变。
+//!
+//! 增量中数量为 0 的档位表示删除。`OrderBookManager` 只做状态维护，不做网络请求，
+//! 由连接在需要时拉取快照并把结果交回。
+
+use anyhow::{Context, Result};
+use std::collections::{BTreeMap, HashMap};
+use tracing::warn;
+
+use crate::exchange::{ExchangeId, OrderBook, PriceLevel};
+use crate::symbols::denormalize_symbol;
+
+/// 缓存增量的上限 (快照迟迟未到时丢弃最旧的增量，缺口由快照校验发现)
+const MAX_BUFFERED_UPDATES: usize = 10_000;
+
+/// 增量推送
+#[derive(Debug, Clone)]
+pub struct DepthUpdate {
+    pub symbol: String,
+    /// 本次增量的第一个更新 ID (U)
+    pub first_id: u64,
+    /// 本次增量的最后一个更新 ID (u)
+    pub final_id: u64,
+    pub bids: Vec<PriceLevel>,
+    pub asks: Vec<PriceLevel>,
+    pub timestamp: i64,
+}
+
+/// REST 快照
+#[derive(Debug, Clone)]
+pub struct DepthSnapshot {
+    pub last_update_id: u64,
+    pub bids: Vec<PriceLevel>,
+    pub asks: Vec<PriceLevel>,
+}
+
+/// 维护结果
+#[derive(Debug, Clone)]
+pub enum SyncAction {
+    /// 无需处理 (缓存 / 过期增量 / 未跟踪的交易对)
+    None,
+    /// 订单簿已更新
+    Publish(OrderBook),
+    /// 需要 (重新) 拉取快照
+    Resync,
+}
+
+/// 单侧盘口: 价格 -> 数量。
+/// 键为价格的 IEEE 754 位模式 —— 正浮点数的位模式与数值大小同序，可直接作为有序键
+type BookSide = BTreeMap<u64, f64>;
+
+#[derive(Debug, Default)]
+struct LocalBook {
+    bids: BookSide,
+    asks: BookSide,
+    last_id: u64,
+    timestamp: i64,
+}
+
+impl LocalBook {
+    fn from_snapshot(snapshot: DepthSnapshot) -> Self {
+        let mut book = Self {
+            last_id: snapshot.last_update_id,
+            ..Default::default()
+        };
+        apply_levels(&mut book.bids, &snapshot.bids);
+        apply_levels(&mut book.asks, &snapshot.asks);
+        book
+    }
+
+    /// 增量能否接在本地序列号之后 (允许与已应用部分重叠，档位数量是绝对值，重复应用无副作用)
+    fn continues(&self, update: &DepthUpdate) -> bool {
+        update.first_id <= self.last_id + 1 && update.final_id > self.last_id
+    }
+
+    fn apply(&mut self, update: &DepthUpdate) {
+        apply_levels(&mut self.bids, &update.bids);
+        apply_levels(&mut self.asks, &update.asks);
+        self.last_id = update.final_id;
+        self.timestamp = update.timestamp;
+    }
+
+    fn snapshot(&self, exchange: ExchangeId, symbol: &str) -> OrderBook {
+        let level = |(price, size): (&u64, &f64)| PriceLevel {
+            price: f64::from_bits(*price),
+            size: *size,
+        };
+        OrderBook {
+            exchange,
+            symbol: symbol.to_string(),
+            bids: self.bids.iter().rev().map(level).collect(),
+            asks: self.asks.iter().map(level).collect(),
+            sequence: self.last_id,
+            timestamp: self.timestamp,
+        }
+    }
+}
+
+fn apply_levels(side: &mut BookSide, levels: &[PriceLevel]) {
+    for level in levels {
+        if !level.price.is_finite() || level.price <= 0.0 {
+            continue;
+        }
+        if level.size > 0.0 {
+            side.insert(level.price.to_bits(), level.size);
+        } else {
+            side.remove(&level.price.to_bits());
+        }
+    }
+}
+
+#[derive(Debug)]
+enum SymbolState {
+    /// 等待快照，期间缓存增量
+    Syncing(Vec<DepthUpdate>),
+    Synced(LocalBook),
+}
+
+/// 按交易对维护本地订单簿
+pub struct OrderBookManager {
+    exchange: ExchangeId,
+    states: HashMap<String, SymbolState>,
+    resyncs: u64,
+}
+
+impl OrderBookManager {
+    pub fn new(exchange: ExchangeId) -> Self {
+        Self {
+            exchange,
+            states: HashMap::new(),
+            resyncs: 0,
+        }
+    }
+
+    /// 开始跟踪交易对 (订阅增量后调用，随后拉取快照)；已在跟踪时返回 false
+    pub fn begin_sync(&mut self, symbol: &str) -> bool {
+        let key = book_key(symbol);
+        if self.states.contains_key(&key) {
+            return false;
+        }
+        self.states.insert(key, SymbolState::Syncing(vec![]));
+        true
+    }
+
+    /// 丢弃本地订单簿回到等待快照状态 (连接断开后增量必然出现缺口)；未跟踪时返回 false
+    pub fn restart_sync(&mut self, symbol: &str) -> bool {
+        match self.states.get_mut(&book_key(symbol)) {
+            Some(state) => {
+                *state = SymbolState::Syncing(vec![]);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// 正在跟踪的交易对 (规范形式)
+    pub fn tracked(&self) -> Vec<String> {
+        self.states.keys().cloned().collect()
+    }
+
+    /// 停止跟踪所有交易对
+    pub fn clear(&mut self) {
+        self.states.clear();
+    }
+
+    pub fn is_syncing(&self, symbol: &str) -> bool {
+        matches!(self.states.get(&book_key(symbol)), Some(SymbolState::Syncing(_)))
+    }
+
+    /// 因缺口触发的重新同步次数
+    pub fn resync_count(&self) -> u64 {
+        self.resyncs
+    }
+
+    /// 处理增量推送
+    pub fn on_update(&mut self, update: DepthUpdate) -> SyncAction {
+        let exchange = self.exchange;
+        let Some(state) = self.states.get_mut(&book_key(&update.symbol)) else {
+            return SyncAction::None;
+        };
+        match state {
+            SymbolState::Syncing(buffer) => {
+                if buffer.len() >= MAX_BUFFERED_UPDATES {
+                    buffer.remove(0);
+                }
+                buffer.push(update);
+                SyncAction::None
+            }
+            SymbolState::Synced(book) => {
+                if update.final_id <= book.last_id {
+                    return SyncAction::None;
+                }
+                if !book.continues(&update) {
+                    warn!(
+                        "{:?} {} 订单簿增量不连续 (本地 {}，收到 U={} u={})，重新拉取快照",
+                        exchange, update.symbol, book.last_id, update.first_id, update.final_id
+                    );
+                    self.resyncs += 1;
+                    *state = SymbolState::Syncing(vec![update]);
+                    return SyncAction::Resync;
+                }
+                book.apply(&update);
+                SyncAction::Publish(book.snapshot(exchange, &update.symbol))
+            }
+        }
+    }
+
+    /// 处理拉取到的快照: 应用缓存的增量后进入已同步状态；快照与缓存之间有缺口时要求重新拉取
+    pub fn on_snapshot(&mut self, symbol: &str, snapshot: DepthSnapshot) -> SyncAction {
+        let exchange = self.exchange;
+        let Some(state) = self.states.get_mut(&book_key(symbol)) else {
+            return SyncAction::None;
+        };
+        let SymbolState::Syncing(buffer) = state else {
+            return SyncAction::None;
+        };
+        let mut book = LocalBook::from_snapshot(snapshot);
+        let mut pending = std::mem::take(buffer);
+        pending.retain(|update| update.final_id > book.last_id);
+        pending.sort_by_key(|update| update.first_id);
+        for (i, update) in pending.iter().enumerate() {
+            if !book.continues(update) {
+                warn!(
+                    "{:?} {} 快照 ({}) 与缓存增量 (U={}) 之间有缺口，重新拉取快照",
+                    exchange, symbol, book.last_id, update.first_id
+                );
+                *buffer = pending.split_off(i);
+                return SyncAction::Resync;
+            }
+            book.apply(update);
+        }
+        let out = book.snapshot(exchange, symbol);
+        *state = SymbolState::Synced(book);
+        SyncAction::Publish(out)
+    }
+}
+
+fn book_key(symbol: &str) -> String {
+    symbol.replace(['/', '-', '_'], "").to_uppercase()
+}
+
+/// 解析 `[["价格","数量"], ...]` 格式的档位
+pub fn parse_levels(value: &serde_json::Value) -> Option<Vec<PriceLevel>> {
+    value
+        .as_array()?
+        .iter()
+        .map(|level| {
+            let level = level.as_array()?;
+            Some(PriceLevel {
+                price: level.first()?.as_str()?.parse().ok()?,
+                size: level.get(1)?.as_str()?.parse().ok()?,
+            })
+        })
+        .collect()
+}
+
+/// 解析 Binance 增量推送:
+/// `{"e":"depthUpdate","E":1672515782136,"s":"BNBBTC","U":157,"u":160,"b":[["0.0024","10"]],"a":[["0.0026","100"]]}`
+/// (combined 格式 `{"stream":..,"data":{..}}` 同样支持)
+pub fn parse_binance_diff(msg: &str) -> Option<DepthUpdate> {
+    let json: serde_json::Value = serde_json::from_str(msg).ok()?;
+    let json = match (json.get("stream"), json.get("data")) {
+        (Some(_), Some(data)) => data,
+        _ => &json,
+    };
+    if json.get("e")?.as_str()? != "depthUpdate" {
+        return None;
+    }
+    Some(DepthUpdate {
+        symbol: json.get("s")?.as_str()?.to_string(),
+        first_id: json.get("U")?.as_u64()?,
+        final_id: json.get("u")?.as_u64()?,
+        bids: parse_levels(json.get("b")?)?,
+        asks: parse_levels(json.get("a")?)?,
+        timestamp: json.get("E").and_then(|v| v.as_i64()).unwrap_or_else(|| chrono::Utc::now().timestamp_millis()),
+    })
+}
+
+/// 解析 Gate 增量推送:
+/// `{"time":1606294781,"time_ms":1606294781236,"channel":"spot.order_book_update","event":"update",
+///   "result":{"t":1606294781123,"e":"depthUpdate","E":1606294781,"s":"BTC_USDT","U":48776301,"u":48776306,
+///   "b":[["19137.74","0.0001"]],"a":[["19137.75","0.6135"]]}}`
+pub fn parse_gate_diff(msg: &str) -> Option<DepthUpdate> {
+    let json: serde_json::Value = serde_json::from_str(msg).ok()?;
+    if json.get("channel")?.as_str()? != "spot.order_book_update" || json.get("event")?.as_str()? != "update" {
+        return None;
+    }
+    let result = json.get("result")?;
+    Some(DepthUpdate {
+        symbol: result.get("s")?.as_str()?.to_string(),
+        first_id: result.get("U")?.as_u64()?,
+        final_id: result.get("u")?.as_u64()?,
+        bids: parse_levels(result.get("b")?)?,
+        asks: parse_levels(result.get("a")?)?,
+        timestamp: result
+            .get("t")
+            .or(json.get("time_ms"))
+            .and_then(|v| v.as_i64())
+            .unwrap_or_else(|| chrono::Utc::now().timestamp_millis()),
+    })
+}
+
+/// 解析 MEXC 增量推送 (档位为 `{"p":"价格","v":"数量"}`，版本号 r 为字符串):
+/// `{"c":"spot@public.increase.depth.v3.api@BTCUSDT","d":{"asks":[{"p":"20290.89","v":"0.00000000"}],"bids":[],
+///   "e":"spot@public.increase.depth.v3.api","r":"3407459756"},"s":"BTCUSDT","t":1661932660144}`
+pub fn parse_mexc_diff(msg: &str) -> Option<DepthUpdate> {
+    let json: serde_json::Value = serde_json::from_str(msg).ok()?;
+    if !json.get("c")?.as_str()?.starts_with("spot@public.increase.depth") {
+        return None;
+    }
+    let data = json.get("d")?;
+    let version: u64 = data.get("r")?.as_str()?.parse().ok()?;
+    let levels = |key: &str| parse_object_levels(data.get(key).unwrap_or(&serde_json::Value::Null));
+    Some(DepthUpdate {
+        symbol: json.get("s")?.as_str()?.to_string(),
+        first_id: version,
+        final_id: version,
+        bids: levels("bids")?,
+        asks: levels("asks")?,
+        timestamp: json
+            .get("t")
+            .and_then(|v| v.as_i64())
+            .unwrap_or_else(|| chrono::Utc::now().timestamp_millis()),
+    })
+}
+
+/// 解析 `[{"p":"价格","v":"数量"}, ...]` 格式的档位 (字段缺失视为空)
+fn parse_object_levels(value: &serde_json::Value) -> Option<Vec<PriceLevel>> {
+    let Some(levels) = value.as_array() else {
+        return value.is_null().then(Vec::new);
+    };
+    levels
+        .iter()
+        .map(|level| {
+            Some(PriceLevel {
+                price: level.get("p")?.as_str()?.parse().ok()?,
+                size: level.get("v")?.as_str()?.parse().ok()?,
+            })
+        })
+        .collect()
+}
+
+/// 按交易所解析增量推送 (不支持增量同步的交易所为 None)
+pub fn parse_depth_diff(exchange: ExchangeId, msg: &str) -> Option<DepthUpdate> {
+    match exchange {
+        ExchangeId::Binance => parse_binance_diff(msg),
+        ExchangeId::Gate => parse_gate_diff(msg),
+        ExchangeId::Mexc => parse_mexc_diff(msg),
+        _ => None,
+    }
+}
+
+/// 是否支持快照 + 增量的全量订单簿同步
+pub fn supports_depth_sync(exchange: ExchangeId) -> bool {
+    matches!(exchange, ExchangeId::Binance | ExchangeId::Gate | ExchangeId::Mexc)
+}
+
+/// 拉取 REST 快照 (MEXC 的 `/api/v3/depth` 与 Binance 格式相同)
+pub async fn fetch_depth_snapshot(
+    client: &reqwest::Client,
+    exchange: ExchangeId,
+    base_url: &str,
+    symbol: &str,
+) -> Result<DepthSnapshot> {
+    match exchange {
+        ExchangeId::Gate => fetch_gate_snapshot(client, base_url, symbol).await,
+        _ => fetch_binance_snapshot(client, base_url, symbol).await,
+    }
+}
+
+/// 拉取 Gate REST 快照: `{"id":48776300,"current":1606294781123,"update":1606294781000,"asks":[..],"bids":[..]}`
+async fn fetch_gate_snapshot(client: &reqwest::Client, base_url: &str, symbol: &str) -> Result<DepthSnapshot> {
+    let pair = denormalize_symbol(ExchangeId::Gate, symbol);
+    let body: serde_json::Value = client
+        .get(format!("{}/api/v4/spot/order_book", base_url))
+        .query(&[("currency_pair", pair.as_str()), ("limit", "100"), ("with_id", "true")])
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+    Ok(DepthSnapshot {
+        last_update_id: body.get("id").and_then(|v| v.as_u64()).context("深度快照缺少 id")?,
+        bids: body.get("bids").and_then(parse_levels).context("深度快照 bids 格式错误")?,
+        asks: body.get("asks").and_then(parse_levels).context("深度快照 asks 格式错误")?,
+    })
+}
+
+/// 拉取 Binance REST 快照
+async fn fetch_binance_snapshot(client: &reqwest::Client, base_url: &str, symbol: &str) -> Result<DepthSnapshot> {
+    let body: serde_json::Value = client
+        .get(format!("{}/api/v3/depth", base_url))
+        .query(&[("symbol", book_key(symbol).as_str()), ("limit", "1000")])
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+    Ok(DepthSnapshot {
+        last_update_id: body
+            .get("lastUpdateId")
+            .and_then(|v| v.as_u64())
+            .context("深度快照缺少 lastUpdateId")?,
+        bids: body.get("bids").and_then(parse_levels).context("深度快照 bids 格式错误")?,
+        asks: body.get("asks").and_then(parse_levels).context("深度快照 asks 格式错误")?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn level(price: f64, size: f64) -> PriceLevel {
+        PriceLevel { price, size }
+    }
+
+    fn diff(first_id: u64, final_id: u64, bids: Vec<PriceLevel>) -> DepthUpdate {
+        DepthUpdate {
+            symbol: "BTCUSDT".to_string(),
+            first_id,
+            final_id,
+            bids,
+            asks: vec![],
+            timestamp: final_id as i64,
+        }
+    }
+
+    fn snapshot(last_update_id: u64) -> DepthSnapshot {
+        DepthSnapshot {
+            last_update_id,
+            bids: vec![level(100.0, 1.0), level(99.0, 2.0)],
+            asks: vec![level(101.0, 1.5)],
+        }
+    }
+
+    fn published(action: SyncAction) -> OrderBook {
+        match action {
+            SyncAction::Publish(book) => book,
+            other => panic!("expected publish, got {:?}", other),
+        }
+    }
+
+    fn synced_manager(last_update_id: u64) -> OrderBookManager {
+        let mut manager = OrderBookManager::new(ExchangeId::Binance);
+        assert!(manager.begin_sync("BTC/USDT"));
+        published(manager.on_snapshot("BTC/USDT", snapshot(last_update_id)));
+        manager
+    }
+
+    #[test]
+    fn gapped_diff_triggers_resync() {
+        let mut manager = synced_manager(100);
+        let book = published(manager.on_update(diff(101, 105, vec![level(100.5, 3.0), level(99.0, 0.0)])));
+        assert_eq!(book.sequence, 105);
+        assert_eq!(book.bids.iter().map(|l| l.price).collect::<Vec<_>>(), vec![100.5, 100.0]);
+
+        // U=110 > 本地 105 + 1: 丢包，丢弃本地订单簿重新拉取快照
+        assert!(matches!(manager.on_update(diff(110, 112, vec![])), SyncAction::Resync));
+        assert_eq!(manager.resync_count(), 1);
+        assert!(manager.is_syncing("BTCUSDT"));
+        // 触发重同步的增量已缓存，新快照之后接着应用
+        let book = published(manager.on_snapshot("BTCUSDT", snapshot(111)));
+        assert_eq!(book.sequence, 112);
+        assert!(!manager.is_syncing("BTCUSDT"));
+    }
+
+    #[test]
+    fn out_of_order_diff_already_applied_is_ignored() {
+        let mut manager = synced_manager(100);
+        published(manager.on_update(diff(101, 103, vec![])));
+        assert!(matches!(manager.on_update(diff(99, 101, vec![level(98.0, 1.0)])), SyncAction::None));
+        assert_eq!(manager.resync_count(), 0);
+        // 与已应用部分重叠的增量照常接上
+        let book = published(manager.on_update(diff(102, 104, vec![])));
+        assert_eq!(book.sequence, 104);
+    }
+
+    #[test]
+    fn stale_diffs_are_dropped_before_snapshot_is_applied() {
+        let mut manager = OrderBookManager::new(ExchangeId::Binance);
+        manager.begin_sync("BTCUSDT");
+        // 快照之前的增量: 若被应用会留下 98 的买单
+        for update in [diff(90, 95, vec![level(98.0, 1.0)]), diff(101, 103, vec![level(100.0, 5.0)]), diff(96, 100, vec![])] {
+            assert!(matches!(manager.on_update(update), SyncAction::None));
+        }
+        let book = published(manager.on_snapshot("BTCUSDT", snapshot(100)));
+        assert_eq!(book.sequence, 103);
+        assert_eq!(book.bids, vec![level(100.0, 5.0), level(99.0, 2.0)]);
+        assert_eq!(book.asks, vec![level(101.0, 1.5)]);
+    }
+
+    #[test]
+    fn snapshot_older_than_buffered_diffs_requests_another() {
+        let mut manager = OrderBookManager::new(ExchangeId::Binance);
+        manager.begin_sync("BTCUSDT");
+        manager.on_update(diff(105, 107, vec![]));
+        assert!(matches!(manager.on_snapshot("BTCUSDT", snapshot(100)), SyncAction::Resync));
+        assert!(manager.is_syncing("BTCUSDT"));
+        // 缓存的增量保留，下一份快照跟上后应用
+        assert_eq!(published(manager.on_snapshot("BTCUSDT", snapshot(106))).sequence, 107);
+    }
+
+    #[test]
+    fn untracked_symbols_are_ignored() {
+        let mut manager = OrderBookManager::new(ExchangeId::Binance);
+        assert!(matches!(manager.on_update(diff(1, 2, vec![])), SyncAction::None));
+        assert!(matches!(manager.on_snapshot("BTCUSDT", snapshot(1)), SyncAction::None));
+        assert!(!manager.restart_sync("BTCUSDT"));
+    }
+}
@@ -0,0 +1,270 @@
+//! 本地仓位簿
+//!
+//! 记录引擎已知的持仓与未完成订单，启动时从 OMS 对账恢复，避免重启后重复开仓。
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::exchange::ExchangeId;
+use crate::executor::{OrderResponse, OrderSide};
+use crate::strategies::split_symbol;
+use crate::strategy::{Signal, SignalAction};
+use crate::symbols::normalize_symbol;
+
+/// 持仓
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Position {
+    pub exchange: ExchangeId,
+    pub symbol: String,
+    /// 带方向的数量 (正数为多头，负数为空头)
+    pub quantity: f64,
+    pub avg_price: f64,
+}
+
+/// 未完成订单
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OpenOrder {
+    pub order_id: String,
+    pub exchange: ExchangeId,
+    pub symbol: String,
+    pub side: OrderSide,
+    pub amount: f64,
+    /// 限价 (未知时为 0，不参与重新报价)
+    #[serde(default)]
+    pub price: f64,
+}
+
+/// 引擎开出的一笔方向性持仓: 开仓策略与价格档位 (同一交易对可由多个档位分批持有)
+#[derive(Debug, Clone)]
+struct Lot {
+    strategy_id: String,
+    level: Option<i64>,
+}
+
+/// 仓位簿
+#[derive(Debug, Default)]
+pub struct PositionBook {
+    positions: HashMap<(ExchangeId, String), Position>,
+    open_orders: HashMap<String, OpenOrder>,
+    /// 按交易对记录的开仓批次 (按开仓先后)，持仓归零时一并清除
+    lots: HashMap<(ExchangeId, String), Vec<Lot>>,
+}
+
+impl PositionBook {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 写入对账得到的持仓 (覆盖同一交易对的已有记录)
+    pub fn seed_position(&mut self, position: Position) {
+        let key = (position.exchange, normalize_symbol(position.exchange, &position.symbol));
+        if position.quantity.abs() <= f64::EPSILON {
+            self.lots.remove(&key);
+            self.positions.remove(&key);
+        } else {
+            self.positions.insert(key, position);
+        }
+    }
+
+    /// 写入对账得到的未完成订单
+    pub fn seed_open_order(&mut self, order: OpenOrder) {
+        self.open_orders.insert(order.order_id.clone(), order);
+    }
+
+    /// 记录成交，更新持仓数量与均价
+    pub fn apply_fill(&mut self, exchange: ExchangeId, symbol: &str, side: OrderSide, amount: f64, price: f64) {
+        if amount <= 0.0 {
+            return;
+        }
+        let signed = match side {
+            OrderSide::Buy => amount,
+            OrderSide::Sell => -amount,
+        };
+        let key = (exchange, normalize_symbol(exchange, symbol));
+        let pos = self.positions.entry(key.clone()).or_insert_with(|| Position {
+            exchange,
+            symbol: symbol.to_string(),
+            quantity: 0.0,
+            avg_price: 0.0,
+        });
+        let new_qty = pos.quantity + signed;
+        if pos.quantity == 0.0 || pos.quantity.signum() == signed.signum() {
+            // 加仓: 更新加权均价
+            pos.avg_price = (pos.avg_price * pos.quantity.abs() + price * amount) / new_qty.abs();
+        } else if new_qty != 0.0 && new_qty.signum() != pos.quantity.signum() {
+            // 反手: 剩余部分按成交价开新仓
+            pos.avg_price = price;
+        }
+        pos.quantity = new_qty;
+        if pos.quantity.abs() <= f64::EPSILON {
+            self.lots.remove(&key);
+            self.positions.remove(&key);
+        }
+    }
+
+    /// 记录一次执行的全部成交
+    ///
+    /// 方向性开仓成交后按策略与价格档位记一笔开仓批次 (见 `Engine::is_duplicate_open`)，平仓时释放该档位的批次。
+    /// 无腿平仓信号 (网格卖出等) 只减仓: 按该交易对的批次数平掉等分的反向持仓，最后一批直接平掉剩余持仓，
+    /// 不因买卖价差导致的数量差异留下残仓或反手；没有反向持仓时忽略。
+    pub fn apply_execution(&mut self, signal: &Signal, orders: &[OrderResponse]) {
+        let directional = signal.strategy_type.is_directional();
+        let reduce_only = signal.action == SignalAction::Close && signal.legs.is_empty();
+        for order in orders {
+            let key = (order.exchange, normalize_symbol(order.exchange, &order.symbol));
+            if !reduce_only {
+                if directional && signal.action == SignalAction::Close {
+                    self.release_lot(&key, signal);
+                }
+                self.apply_fill(order.exchange, &order.symbol, order.side, order.filled_amount, order.avg_price);
+                if directional && signal.action == SignalAction::Open && self.positions.contains_key(&key) {
+                    self.lots.entry(key).or_default().push(Lot {
+                        strategy_id: signal.strategy_id.clone(),
+                        level: signal.level,
+                    });
+                }
+                continue;
+            }
+            let held = self.get(order.exchange, &order.symbol).map(|p| p.quantity).unwrap_or(0.0);
+            let closing = match order.side {
+                OrderSide::Buy => held < 0.0,
+                OrderSide::Sell => held > 0.0,
+            };
+            if closing {
+                let lots = self.lots.get(&key).map_or(1, |lots| lots.len().max(1));
+                self.release_lot(&key, signal);
+                self.apply_fill(order.exchange, &order.symbol, order.side, held.abs() / lots as f64, order.avg_price);
+            }
+        }
+    }
+
+    /// 释放平仓信号对应的开仓批次: 优先同策略同档位的批次，否则该策略最早的一批
+    fn release_lot(&mut self, key: &(ExchangeId, String), signal: &Signal) {
+        let Some(lots) = self.lots.get_mut(key) else {
+            return;
+        };
+        let index = lots
+            .iter()
+            .position(|lot| lot.strategy_id == signal.strategy_id && signal.level.is_some() && lot.level == signal.level)
+            .or_else(|| lots.iter().position(|lot| lot.strategy_id == signal.strategy_id));
+        if let Some(index) = index {
+            lots.remove(index);
+        }
+        if lots.is_empty() {
+            self.lots.remove(key);
+        }
+    }
+
+    /// 策略是否已在该交易对的价格档位上持有开仓批次 (`level` 为 None 时该策略的任一批次都算)
+    pub fn has_lot(&self, exchange: ExchangeId, symbol: &str, strategy_id: &str, level: Option<i64>) -> bool {
+        self.lots
+            .get(&(exchange, normalize_symbol(exchange, symbol)))
+            .is_some_and(|lots| {
+                lots.iter()
+                    .any(|lot| lot.strategy_id == strategy_id && (level.is_none() || lot.level == level))
+            })
+    }
+
+    /// 交易对上的敞口是否都不是引擎开出的 (如启动时从 OMS 对账恢复的持仓与挂单)
+    pub fn has_untracked_exposure(&self, exchange: ExchangeId, symbol: &str) -> bool {
+        self.has_exposure(exchange, symbol) && !self.lots.contains_key(&(exchange, normalize_symbol(exchange, symbol)))
+    }
+
+    /// 订单完成或撤销后移除
+    pub fn remove_open_order(&mut self, order_id: &str) {
+        self.open_orders.remove(order_id);
+    }
+
+    /// 指定交易所的交易对是否已有持仓或未完成订单
+    pub fn has_exposure(&self, exchange: ExchangeId, symbol: &str) -> bool {
+        let key = normalize_symbol(exchange, symbol);
+        self.positions.contains_key(&(exchange, key.clone()))
+            || self
+                .open_orders
+                .values()
+                .any(|o| o.exchange == exchange && normalize_symbol(o.exchange, &o.symbol) == key)
+    }
+
+    /// 查询持仓
+    pub fn get(&self, exchange: ExchangeId, symbol: &str) -> Option<&Position> {
+        self.positions.get(&(exchange, normalize_symbol(exchange, symbol)))
+    }
+
+    /// 所有持仓快照
+    pub fn positions(&self) -> Vec<Position> {
+        self.positions.values().cloned().collect()
+    }
+
+    /// 某币种在所有交易所、所有交易对上的净持仓名义金额 (多头为正，按持仓均价计)
+    pub fn net_inventory(&self, asset: &str) -> f64 {
+        self.positions
+            .values()
+            .filter(|p| base_asset(&p.symbol).is_some_and(|base| base == asset))
+            .map(|p| p.quantity * p.avg_price)
+            .sum()
+    }
+
+    /// 某交易所已部署的资金: 所有持仓名义金额 (多空取绝对值，按持仓均价计)
+    pub fn deployed_capital(&self, exchange: ExchangeId) -> f64 {
+        self.positions
+            .values()
+            .filter(|p| p.exchange == exchange)
+            .map(|p| (p.quantity * p.avg_price).abs())
+            .sum()
+    }
+
+    /// 持仓数量
+    pub fn position_count(&self) -> usize {
+        self.positions.len()
+    }
+
+    /// 未完成订单数量
+    pub fn open_order_count(&self) -> usize {
+        self.open_orders.len()
+    }
+
+    /// 指定交易所的交易对上的未完成订单
+    pub fn open_orders_for(&self, exchange: ExchangeId, symbol: &str) -> Vec<OpenOrder> {
+        let key = normalize_symbol(exchange, symbol);
+        self.open_orders
+            .values()
+            .filter(|o| o.exchange == exchange && normalize_symbol(o.exchange, &o.symbol) == key)
+            .cloned()
+            .collect()
+    }
+}
+
+/// 交易对的基础币种 (永续 `BTC/USDT:USDT` 同样归入 BTC)
+pub fn base_asset(symbol: &str) -> Option<String> {
+    let market = symbol.split(':').next()?;
+    split_symbol(market).map(|(base, _)| base)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::risk::InventoryLimits;
+
+    #[test]
+    fn net_inventory_sums_every_venue_and_market_of_the_asset() {
+        let mut book = PositionBook::new();
+        // 网格在 Binance 现货、配对在 OKX 永续、期现在 Bybit 空永续
+        book.apply_fill(ExchangeId::Binance, "BTC/USDT", OrderSide::Buy, 4.0, 100.0);
+        book.apply_fill(ExchangeId::Okx, "BTC/USDT:USDT", OrderSide::Buy, 5.0, 100.0);
+        book.apply_fill(ExchangeId::Bybit, "BTC/USDC:USDC", OrderSide::Sell, 1.0, 100.0);
+        book.apply_fill(ExchangeId::Binance, "ETH/USDT", OrderSide::Buy, 10.0, 10.0);
+        assert_eq!(book.net_inventory("BTC"), 800.0);
+        assert_eq!(book.net_inventory("ETH"), 100.0);
+        assert_eq!(book.net_inventory("SOL"), 0.0);
+
+        // 单个策略各自都在 500 以内，合计 800 再加 300 超过 1000 的组合上限
+        let limits = InventoryLimits::parse("BTC:1000,*:5000");
+        assert!(limits.check("BTC", book.net_inventory("BTC"), 200.0).is_ok());
+        let err = limits.check("BTC", book.net_inventory("BTC"), 300.0).unwrap_err();
+        assert!(err.contains("BTC"), "{}", err);
+        // 减少敞口的变动始终放行；未单独配置的币种用默认上限
+        assert!(limits.check("BTC", 1_200.0, -100.0).is_ok());
+        assert!(limits.check("ETH", book.net_inventory("ETH"), 4_000.0).is_ok());
+        assert!(limits.check("ETH", book.net_inventory("ETH"), 5_000.0).is_err());
+    }
+}
@@ -0,0 +1,305 @@
+//! 存活 / 就绪探针
+//!
+//! 设置 ENGINE_HEALTH_ADDR 时启动独立 HTTP 服务，供编排系统探测引擎状态:
+//! - `/healthz`: PostgreSQL 连接池与 Redis 可达 (未配置的视为正常)，且至少一个交易所在最近
+//!   ENGINE_HEALTH_TICKER_SECS 秒内推送过行情；否则返回 503
+//! - `/readyz`: 在 `/healthz` 的基础上要求至少一个交易所 WebSocket 已连通；交易所连上并收到行情前返回 503
+//!
+//! 两个端点都返回同一份 JSON 报告，便于排查哪一项不满足。
+
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use axum::routing::get;
+use axum::{Json, Router};
+use serde::Serialize;
+use sqlx::PgPool;
+use std::collections::{BTreeMap, HashMap};
+use std::net::SocketAddr;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use tracing::{info, warn};
+
+use crate::db::SharedRedis;
+use crate::exchange::{ExchangeConnection, ExchangeId, TickerClock};
+
+/// 单项依赖检查的超时
+const CHECK_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// 探针服务配置
+#[derive(Debug, Clone)]
+pub struct ProbeConfig {
+    pub addr: SocketAddr,
+    /// 行情新鲜度窗口 (毫秒)
+    pub ticker_window_ms: i64,
+}
+
+impl ProbeConfig {
+    /// ENGINE_HEALTH_ADDR (如 `0.0.0.0:9812`；未设置时不启动)、ENGINE_HEALTH_TICKER_SECS (默认 30)
+    pub fn from_env() -> Option<Self> {
+        let raw = std::env::var("ENGINE_HEALTH_ADDR").ok().filter(|v| !v.trim().is_empty())?;
+        let addr = match raw.trim().parse() {
+            Ok(addr) => addr,
+            Err(e) => {
+                warn!("ENGINE_HEALTH_ADDR 无效 ({}): {}", raw, e);
+                return None;
+            }
+        };
+        let secs = std::env::var("ENGINE_HEALTH_TICKER_SECS")
+            .ok()
+            .and_then(|v| v.parse::<i64>().ok())
+            .filter(|v| *v > 0)
+            .unwrap_or(30);
+        Some(Self {
+            addr,
+            ticker_window_ms: secs * 1_000,
+        })
+    }
+}
+
+/// 探针检查的依赖；服务在交易所连接前启动，连接建立后通过 `set_connections` 填入
+#[derive(Clone)]
+pub struct ProbeState {
+    pool: Option<PgPool>,
+    redis: Option<SharedRedis>,
+    clock: TickerClock,
+    connections: Arc<RwLock<HashMap<ExchangeId, Arc<ExchangeConnection>>>>,
+    ticker_window_ms: i64,
+}
+
+/// 依赖状态: 未配置的依赖为 `disabled`，不影响结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Check {
+    Ok,
+    Down,
+    Disabled,
+}
+
+impl Check {
+    fn passing(self) -> bool {
+        self != Check::Down
+    }
+}
+
+/// 单个交易所的行情状态
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FeedProbe {
+    pub connected: bool,
+    /// 最近一次行情距今 (毫秒)；尚未收到时为 None
+    pub ticker_age_ms: Option<i64>,
+    pub fresh: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProbeReport {
+    pub healthy: bool,
+    pub ready: bool,
+    pub database: Check,
+    pub redis: Check,
+    pub feeds: BTreeMap<String, FeedProbe>,
+    pub timestamp: i64,
+}
+
+impl ProbeState {
+    pub fn new(pool: Option<PgPool>, redis: Option<SharedRedis>, clock: TickerClock, ticker_window_ms: i64) -> Self {
+        Self {
+            pool,
+            redis,
+            clock,
+            connections: Arc::default(),
+            ticker_window_ms,
+        }
+    }
+
+    pub fn set_connections(&self, connections: HashMap<ExchangeId, Arc<ExchangeConnection>>) {
+        if let Ok(mut current) = self.connections.write() {
+            *current = connections;
+        }
+    }
+
+    /// 检查各项依赖
+    pub async fn report(&self) -> ProbeReport {
+        let (database, redis) = tokio::join!(self.check_database(), self.check_redis());
+        let now = chrono::Utc::now().timestamp_millis();
+        let last = self.clock.snapshot();
+        let connected: HashMap<ExchangeId, bool> = self
+            .connections
+            .read()
+            .map(|c| c.iter().map(|(id, conn)| (*id, conn.is_connected())).collect())
+            .unwrap_or_default();
+        let mut ids: Vec<ExchangeId> = connected.keys().copied().collect();
+        ids.extend(last.keys().filter(|id| !connected.contains_key(id)));
+        let feeds: BTreeMap<String, FeedProbe> = ids
+            .into_iter()
+            .map(|id| {
+                let ticker_age_ms = last.get(&id).map(|at| (now - at).max(0));
+                let probe = FeedProbe {
+                    connected: connected.get(&id).copied().unwrap_or(false),
+                    ticker_age_ms,
+                    fresh: ticker_age_ms.is_some_and(|age| age <= self.ticker_window_ms),
+                };
+                (id.key().to_string(), probe)
+            })
+            .collect();
+        let healthy = database.passing() && redis.passing() && feeds.values().any(|f| f.fresh);
+        let ready = healthy && feeds.values().any(|f| f.connected);
+        ProbeReport {
+            healthy,
+            ready,
+            database,
+            redis,
+            feeds,
+            timestamp: now,
+        }
+    }
+
+    async fn check_database(&self) -> Check {
+        let Some(pool) = &self.pool else {
+            return Check::Disabled;
+        };
+        match tokio::time::timeout(CHECK_TIMEOUT, sqlx::query("SELECT 1").execute(pool)).await {
+            Ok(Ok(_)) => Check::Ok,
+            _ => Check::Down,
+        }
+    }
+
+    async fn check_redis(&self) -> Check {
+        let Some(redis) = &self.redis else {
+            return Check::Disabled;
+        };
+        let ping = async {
+            let mut conn = redis.connection().await?;
+            redis::cmd("PING").query_async::<String>(&mut conn).await
+        };
+        match tokio::time::timeout(CHECK_TIMEOUT, ping).await {
+            Ok(Ok(_)) => Check::Ok,
+            _ => Check::Down,
+        }
+    }
+}
+
+pub fn router(state: ProbeState) -> Router {
+    Router::new()
+        .route("/healthz", get(healthz))
+        .route("/readyz", get(readyz))
+        .with_state(state)
+}
+
+/// 启动探针 HTTP 服务
+pub async fn serve(addr: SocketAddr, state: ProbeState) -> anyhow::Result<()> {
+    let listener = tokio::net::TcpListener::bind(addr).await?;
+    info!("健康检查服务监听 {}", addr);
+    axum::serve(listener, router(state)).await?;
+    Ok(())
+}
+
+fn respond(ok: bool, report: ProbeReport) -> impl IntoResponse {
+    let status = if ok { StatusCode::OK } else { StatusCode::SERVICE_UNAVAILABLE };
+    (status, Json(report))
+}
+
+async fn healthz(State(state): State<ProbeState>) -> impl IntoResponse {
+    let report = state.report().await;
+    respond(report.healthy, report)
+}
+
+async fn readyz(State(state): State<ProbeState>) -> impl IntoResponse {
+    let report = state.report().await;
+    respond(report.ready, report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures_util::{SinkExt, StreamExt};
+    use tokio::sync::Notify;
+    use tokio_tungstenite::tungstenite::Message;
+
+    /// 本地 WebSocket 行情源: 收到通知后推送一条 Binance 24hrTicker，之后保持连接
+    async fn ws_feed(push: Arc<Notify>) -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut ws = tokio_tungstenite::accept_async(stream).await.unwrap();
+            push.notified().await;
+            let frame = serde_json::json!({
+                "e": "24hrTicker", "E": chrono::Utc::now().timestamp_millis(), "s": "BTCUSDT",
+                "b": "100.0", "B": "1", "a": "100.1", "A": "1", "c": "100.05", "v": "10",
+            });
+            ws.send(Message::Text(frame.to_string())).await.unwrap();
+            while ws.next().await.is_some() {}
+        });
+        format!("ws://{}", addr)
+    }
+
+    async fn serve_probe(state: ProbeState) -> SocketAddr {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let _ = axum::serve(listener, router(state)).await;
+        });
+        addr
+    }
+
+    async fn get(addr: SocketAddr, path: &str) -> (u16, serde_json::Value) {
+        let response = reqwest::get(format!("http://{}{}", addr, path)).await.unwrap();
+        (response.status().as_u16(), response.json().await.unwrap())
+    }
+
+    #[tokio::test]
+    async fn readyz_is_unavailable_until_the_first_ticker() {
+        let clock = TickerClock::default();
+        let state = ProbeState::new(None, None, clock.clone(), 30_000);
+        let addr = serve_probe(state.clone()).await;
+
+        // 交易所连接前
+        assert_eq!(get(addr, "/healthz").await.0, 503);
+        assert_eq!(get(addr, "/readyz").await.0, 503);
+
+        let push = Arc::new(Notify::new());
+        let conn = Arc::new(
+            ExchangeConnection::new(ExchangeId::Binance)
+                .await
+                .unwrap()
+                .with_ws_url(ws_feed(push.clone()).await)
+                .with_ticker_clock(clock.clone()),
+        );
+        conn.start(vec!["BTC/USDT".to_string()]).await.unwrap();
+        state.set_connections(HashMap::from([(ExchangeId::Binance, conn.clone())]));
+
+        // 已连通但尚未收到行情
+        let (status, body) = get(addr, "/readyz").await;
+        assert_eq!(status, 503);
+        assert_eq!(body["feeds"]["binance"]["connected"], true);
+        assert!(body["feeds"]["binance"]["tickerAgeMs"].is_null());
+
+        push.notify_one();
+        for _ in 0..100 {
+            if !clock.snapshot().is_empty() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        let (status, body) = get(addr, "/healthz").await;
+        assert_eq!(status, 200, "{}", body);
+        assert_eq!(body["database"], "disabled");
+        assert_eq!(body["feeds"]["binance"]["fresh"], true);
+        assert_eq!(get(addr, "/readyz").await.0, 200);
+        conn.stop().await;
+    }
+
+    #[tokio::test]
+    async fn stale_ticker_is_not_healthy() {
+        let clock = TickerClock::default();
+        clock.record(ExchangeId::Okx, chrono::Utc::now().timestamp_millis() - 60_000);
+        let report = ProbeState::new(None, None, clock, 30_000).report().await;
+        assert!(!report.healthy);
+        assert!(!report.ready);
+        assert!(!report.feeds["okx"].fresh);
+        assert!(!report.feeds["okx"].connected);
+    }
+}
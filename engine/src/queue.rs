@@ -0,0 +1,258 @@
+//! 策略与执行之间的有界信号队列
+//!
+//! - 队列满时淘汰优先级最低、最早过期的信号 (可能是新信号本身)
+//! - 出队按优先级、再按预期收益率排序，而不是 FIFO
+//! - 出队前清理已过期信号，过期信号永远不会到达执行器
+
+use std::collections::VecDeque;
+
+use crate::strategy::Signal;
+
+/// 排队等待时长的采样窗口
+const WAIT_SAMPLES: usize = 1024;
+
+/// 入队结果
+#[derive(Debug)]
+pub enum PushOutcome {
+    Queued,
+    /// 队列已满，淘汰了一个已排队的信号
+    Evicted(Signal),
+    /// 队列已满且新信号优先级最低，被直接拒绝
+    Rejected(Signal),
+}
+
+struct QueuedSignal {
+    signal: Signal,
+    enqueued_at: i64,
+}
+
+/// 队列统计
+#[derive(Debug, Clone, Default)]
+pub struct QueueStats {
+    pub depth: usize,
+    pub evicted: u64,
+    pub expired: u64,
+    pub wait_p50_ms: i64,
+    pub wait_p95_ms: i64,
+    pub wait_p99_ms: i64,
+}
+
+/// 有界信号队列
+pub struct SignalQueue {
+    items: Vec<QueuedSignal>,
+    capacity: usize,
+    /// 未设置有效期的信号默认存活时长
+    default_ttl_ms: i64,
+    evicted: u64,
+    expired: u64,
+    waits: VecDeque<i64>,
+}
+
+impl SignalQueue {
+    /// 创建队列 (ENGINE_SIGNAL_QUEUE_CAPACITY 默认 256，ENGINE_SIGNAL_TTL_MS 默认 2000)
+    pub fn from_env() -> Self {
+        let capacity = std::env::var("ENGINE_SIGNAL_QUEUE_CAPACITY")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .filter(|v| *v > 0)
+            .unwrap_or(256);
+        let default_ttl_ms = std::env::var("ENGINE_SIGNAL_TTL_MS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(2000);
+        Self {
+            items: Vec::with_capacity(capacity),
+            capacity,
+            default_ttl_ms,
+            evicted: 0,
+            expired: 0,
+            waits: VecDeque::with_capacity(WAIT_SAMPLES),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// 入队
+    pub fn push(&mut self, mut signal: Signal, now: i64) -> PushOutcome {
+        if signal.valid_until <= 0 {
+            signal.valid_until = now + self.default_ttl_ms;
+        }
+        if self.items.len() < self.capacity {
+            self.items.push(QueuedSignal {
+                signal,
+                enqueued_at: now,
+            });
+            return PushOutcome::Queued;
+        }
+
+        // 已满: 先清理过期，再淘汰最低优先级 / 最早过期
+        self.purge_expired(now);
+        if self.items.len() < self.capacity {
+            self.items.push(QueuedSignal {
+                signal,
+                enqueued_at: now,
+            });
+            return PushOutcome::Queued;
+        }
+        let victim = self
+            .items
+            .iter()
+            .enumerate()
+            .min_by_key(|(_, q)| (q.signal.priority, q.signal.valid_until))
+            .map(|(i, _)| i)
+            .expect("queue is full");
+        let victim_key = (self.items[victim].signal.priority, self.items[victim].signal.valid_until);
+        self.evicted += 1;
+        if (signal.priority, signal.valid_until) <= victim_key {
+            return PushOutcome::Rejected(signal);
+        }
+        let evicted = self.items.swap_remove(victim);
+        self.items.push(QueuedSignal {
+            signal,
+            enqueued_at: now,
+        });
+        PushOutcome::Evicted(evicted.signal)
+    }
+
+    /// 出队: 优先级最高、收益率最高的未过期信号
+    pub fn pop(&mut self, now: i64) -> Option<Signal> {
+        self.purge_expired(now);
+        let best = self
+            .items
+            .iter()
+            .enumerate()
+            .max_by(|(_, a), (_, b)| {
+                a.signal
+                    .priority
+                    .cmp(&b.signal.priority)
+                    .then(a.signal.profit_rate.total_cmp(&b.signal.profit_rate))
+            })
+            .map(|(i, _)| i)?;
+        let item = self.items.swap_remove(best);
+        if self.waits.len() == WAIT_SAMPLES {
+            self.waits.pop_front();
+        }
+        self.waits.push_back(now - item.enqueued_at);
+        Some(item.signal)
+    }
+
+    fn purge_expired(&mut self, now: i64) {
+        let before = self.items.len();
+        self.items.retain(|q| q.signal.valid_until >= now);
+        self.expired += (before - self.items.len()) as u64;
+    }
+
+    /// 当前统计
+    pub fn stats(&self) -> QueueStats {
+        let mut waits: Vec<i64> = self.waits.iter().copied().collect();
+        waits.sort_unstable();
+        let pct = |p: f64| -> i64 {
+            if waits.is_empty() {
+                return 0;
+            }
+            let idx = ((waits.len() as f64 - 1.0) * p).round() as usize;
+            waits[idx]
+        };
+        QueueStats {
+            depth: self.items.len(),
+            evicted: self.evicted,
+            expired: self.expired,
+            wait_p50_ms: pct(0.50),
+            wait_p95_ms: pct(0.95),
+            wait_p99_ms: pct(0.99),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exchange::ExchangeId;
+    use crate::strategy::StrategyType;
+
+    fn queue(capacity: usize) -> SignalQueue {
+        SignalQueue {
+            items: Vec::with_capacity(capacity),
+            capacity,
+            default_ttl_ms: 2000,
+            evicted: 0,
+            expired: 0,
+            waits: VecDeque::new(),
+        }
+    }
+
+    fn signal(id: &str, priority: i32, profit_rate: f64, valid_until: i64) -> Signal {
+        let mut signal = Signal::new(id, StrategyType::Triangular, ExchangeId::Binance, profit_rate, 1.0, 0.5, "BTC/USDT", 0);
+        signal.priority = priority;
+        signal.valid_until = valid_until;
+        signal
+    }
+
+    #[test]
+    fn full_queue_evicts_the_lowest_priority_signal() {
+        let mut queue = queue(2);
+        assert!(matches!(queue.push(signal("low", 1, 0.01, 5_000), 0), PushOutcome::Queued));
+        assert!(matches!(queue.push(signal("high", 5, 0.01, 5_000), 0), PushOutcome::Queued));
+
+        match queue.push(signal("mid", 3, 0.01, 5_000), 0) {
+            PushOutcome::Evicted(victim) => assert_eq!(victim.strategy_id, "low"),
+            other => panic!("expected eviction, got {:?}", other),
+        }
+        assert_eq!(queue.len(), queue.capacity());
+        assert_eq!(queue.stats().evicted, 1);
+    }
+
+    #[test]
+    fn lowest_priority_newcomer_is_rejected_when_full() {
+        let mut queue = queue(2);
+        queue.push(signal("a", 3, 0.01, 5_000), 0);
+        queue.push(signal("b", 3, 0.01, 5_000), 0);
+
+        // 同优先级时先过期的让位: 新信号过期更早，被直接拒绝
+        match queue.push(signal("c", 3, 0.05, 4_000), 0) {
+            PushOutcome::Rejected(rejected) => assert_eq!(rejected.strategy_id, "c"),
+            other => panic!("expected rejection, got {:?}", other),
+        }
+        assert_eq!(queue.len(), 2);
+        assert_eq!(queue.stats().evicted, 1);
+        let kept: Vec<String> = std::iter::from_fn(|| queue.pop(0)).map(|s| s.strategy_id).collect();
+        assert_eq!(kept.len(), 2);
+        assert!(!kept.contains(&"c".to_string()));
+    }
+
+    #[test]
+    fn expired_signals_make_room_and_never_dequeue() {
+        let mut queue = queue(2);
+        queue.push(signal("stale", 9, 0.05, 1_000), 0);
+        // 未设置有效期的按默认存活时长
+        queue.push(signal("fresh", 1, 0.01, 0), 0);
+
+        // 满队列入队时先清理过期信号，不必淘汰
+        assert!(matches!(queue.push(signal("new", 1, 0.02, 5_000), 1_500), PushOutcome::Queued));
+        let stats = queue.stats();
+        assert_eq!((stats.depth, stats.expired, stats.evicted), (2, 1, 0));
+
+        // 默认有效期 2000 内仍可出队，之后过期
+        assert_eq!(queue.pop(1_800).unwrap().strategy_id, "new");
+        assert!(queue.pop(2_500).is_none());
+        assert_eq!(queue.stats().expired, 2);
+    }
+
+    #[test]
+    fn pop_orders_by_priority_then_profit() {
+        let mut queue = queue(4);
+        queue.push(signal("low-rich", 1, 0.05, 5_000), 0);
+        queue.push(signal("high-thin", 2, 0.01, 5_000), 0);
+        queue.push(signal("high-rich", 2, 0.03, 5_000), 0);
+
+        let order: Vec<String> = std::iter::from_fn(|| queue.pop(100)).map(|s| s.strategy_id).collect();
+        assert_eq!(order, vec!["high-rich", "high-thin", "low-rich"]);
+        assert_eq!(queue.stats().wait_p50_ms, 100);
+    }
+}
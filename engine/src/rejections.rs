@@ -0,0 +1,250 @@
+//! 交易所拒单原因映射
+//!
+//! 下单被拒时交易所返回各自的错误码 (余额不足、价格过滤、数量步长、最小名义金额、限频等)，
+//! 统一映射为 [`ExecutionError`]，供重试 / 熔断与告警区分处理: 限频与交易所临时故障 (5xx、超时)
+//! 可退避重试，余额不足与过滤器类错误重试无意义。
+//!
+//! 内置常见错误码，可通过 ENGINE_REJECTION_CODES 追加或覆盖，
+//! 如 `binance:-2010=insufficient_balance,okx:51008=insufficient_balance`。
+
+use std::collections::HashMap;
+use thiserror::Error;
+
+use crate::exchange::ExchangeId;
+
+/// 结构化执行错误
+#[derive(Debug, Clone, PartialEq, Error)]
+pub enum ExecutionError {
+    #[error("{exchange:?} 余额不足: {message}")]
+    InsufficientBalance { exchange: ExchangeId, message: String },
+    #[error("{exchange:?} 价格不满足价格过滤器: {message}")]
+    PriceFilter { exchange: ExchangeId, message: String },
+    #[error("{exchange:?} 数量不满足步长 / 最小下单量: {message}")]
+    LotSize { exchange: ExchangeId, message: String },
+    #[error("{exchange:?} 下单金额低于最小名义金额: {message}")]
+    MinNotional { exchange: ExchangeId, message: String },
+    #[error("{exchange:?} 请求被限频: {message}")]
+    RateLimited { exchange: ExchangeId, message: String },
+    #[error("{exchange:?} 交易所临时故障: {message}")]
+    Transient { exchange: ExchangeId, message: String },
+    #[error("{exchange:?} 拒单 ({code}): {message}")]
+    Rejected {
+        exchange: ExchangeId,
+        code: String,
+        message: String,
+    },
+}
+
+impl ExecutionError {
+    /// 是否值得重试 (限频与临时故障退避后重试；其余拒单原因重试仍会失败)
+    pub fn is_retryable(&self) -> bool {
+        matches!(self, Self::RateLimited { .. } | Self::Transient { .. })
+    }
+
+    /// 是否为未映射原因、错误码为 `code` 的拒单 (如订单不存在)
+    pub fn is_rejected_code(&self, code: &str) -> bool {
+        matches!(self, Self::Rejected { code: actual, .. } if actual == code)
+    }
+
+    /// 告警 / 指标使用的原因标签 (`orders_rejected_total` 的 reason)
+    pub fn reason(&self) -> &'static str {
+        match self {
+            Self::InsufficientBalance { .. } => "insufficient_balance",
+            Self::PriceFilter { .. } => "price_filter",
+            Self::LotSize { .. } => "lot_size",
+            Self::MinNotional { .. } => "min_notional",
+            Self::RateLimited { .. } => "rate_limit",
+            Self::Transient { .. } => "transient",
+            Self::Rejected { .. } => "rejected",
+        }
+    }
+}
+
+/// 拒单原因类别
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Reason {
+    InsufficientBalance,
+    PriceFilter,
+    LotSize,
+    MinNotional,
+    RateLimited,
+    Transient,
+}
+
+impl Reason {
+    fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "insufficient_balance" => Some(Self::InsufficientBalance),
+            "price_filter" => Some(Self::PriceFilter),
+            "lot_size" => Some(Self::LotSize),
+            "min_notional" => Some(Self::MinNotional),
+            "rate_limit" => Some(Self::RateLimited),
+            "transient" => Some(Self::Transient),
+            _ => None,
+        }
+    }
+}
+
+/// 内置错误码 (Binance -1013 涵盖多种过滤器，按错误信息中的过滤器名区分)
+const BUILTIN_CODES: &[(ExchangeId, &str, Reason)] = &[
+    (ExchangeId::Binance, "-2010", Reason::InsufficientBalance),
+    (ExchangeId::Binance, "-1003", Reason::RateLimited),
+    (ExchangeId::Binance, "-1015", Reason::RateLimited),
+    (ExchangeId::Binance, "-1001", Reason::Transient),
+    (ExchangeId::Binance, "-1007", Reason::Transient),
+    (ExchangeId::Binance, "-1111", Reason::PriceFilter),
+    (ExchangeId::Okx, "51008", Reason::InsufficientBalance),
+    (ExchangeId::Okx, "51006", Reason::PriceFilter),
+    (ExchangeId::Okx, "51020", Reason::MinNotional),
+    (ExchangeId::Okx, "51121", Reason::LotSize),
+    (ExchangeId::Okx, "50011", Reason::RateLimited),
+    (ExchangeId::Okx, "50001", Reason::Transient),
+    (ExchangeId::Bybit, "170131", Reason::InsufficientBalance),
+    (ExchangeId::Bybit, "170134", Reason::PriceFilter),
+    (ExchangeId::Bybit, "170137", Reason::LotSize),
+    (ExchangeId::Bybit, "170140", Reason::MinNotional),
+    (ExchangeId::Bybit, "10006", Reason::RateLimited),
+    (ExchangeId::Gate, "BALANCE_NOT_ENOUGH", Reason::InsufficientBalance),
+    (ExchangeId::Gate, "INVALID_PRECISION", Reason::LotSize),
+    (ExchangeId::Gate, "TOO_MANY_REQUESTS", Reason::RateLimited),
+    (ExchangeId::Gate, "SERVER_ERROR", Reason::Transient),
+];
+
+/// 拒单错误码映射表
+#[derive(Debug, Clone)]
+pub struct RejectionMap {
+    codes: HashMap<(ExchangeId, String), Reason>,
+}
+
+impl Default for RejectionMap {
+    fn default() -> Self {
+        Self {
+            codes: BUILTIN_CODES
+                .iter()
+                .map(|(exchange, code, reason)| ((*exchange, code.to_string()), *reason))
+                .collect(),
+        }
+    }
+}
+
+impl RejectionMap {
+    /// 内置错误码叠加 ENGINE_REJECTION_CODES
+    pub fn from_env() -> Self {
+        let mut map = Self::default();
+        if let Ok(raw) = std::env::var("ENGINE_REJECTION_CODES") {
+            map.extend_from_str(&raw);
+        }
+        map
+    }
+
+    /// 解析 `exchange:code=reason` 列表 (逗号分隔)
+    pub fn extend_from_str(&mut self, raw: &str) {
+        for item in raw.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+            let parsed = item.split_once('=').and_then(|(key, reason)| {
+                let (exchange, code) = key.split_once(':')?;
+                let exchange =
+                    serde_json::from_value(serde_json::Value::String(exchange.trim().to_lowercase())).ok()?;
+                Some(((exchange, code.trim().to_string()), Reason::parse(reason)?))
+            });
+            match parsed {
+                Some((key, reason)) => {
+                    self.codes.insert(key, reason);
+                }
+                None => tracing::warn!("ENGINE_REJECTION_CODES 项 {} 无效，已忽略", item),
+            }
+        }
+    }
+
+    /// 将交易所返回的错误码 / 信息映射为结构化错误
+    ///
+    /// 错误码未登记时按错误信息中的关键字 (如 Binance `Filter failure: LOT_SIZE`) 与 HTTP 429 / 5xx 兜底识别。
+    pub fn classify(&self, exchange: ExchangeId, http_status: u16, code: &str, message: &str) -> ExecutionError {
+        let reason = self
+            .codes
+            .get(&(exchange, code.to_string()))
+            .copied()
+            .or_else(|| reason_from_message(message))
+            .or((http_status == 429 || http_status == 418).then_some(Reason::RateLimited))
+            .or((http_status >= 500).then_some(Reason::Transient));
+        let message = message.to_string();
+        match reason {
+            Some(Reason::InsufficientBalance) => ExecutionError::InsufficientBalance { exchange, message },
+            Some(Reason::PriceFilter) => ExecutionError::PriceFilter { exchange, message },
+            Some(Reason::LotSize) => ExecutionError::LotSize { exchange, message },
+            Some(Reason::MinNotional) => ExecutionError::MinNotional { exchange, message },
+            Some(Reason::RateLimited) => ExecutionError::RateLimited { exchange, message },
+            Some(Reason::Transient) => ExecutionError::Transient { exchange, message },
+            None => ExecutionError::Rejected {
+                exchange,
+                code: code.to_string(),
+                message,
+            },
+        }
+    }
+}
+
+fn reason_from_message(message: &str) -> Option<Reason> {
+    let upper = message.to_uppercase();
+    if upper.contains("PRICE_FILTER") || upper.contains("PERCENT_PRICE") {
+        Some(Reason::PriceFilter)
+    } else if upper.contains("MIN_NOTIONAL") || upper.contains("FILTER FAILURE: NOTIONAL") {
+        Some(Reason::MinNotional)
+    } else if upper.contains("LOT_SIZE") {
+        Some(Reason::LotSize)
+    } else if upper.contains("INSUFFICIENT BALANCE") {
+        Some(Reason::InsufficientBalance)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn representative_codes_map_to_variants() {
+        let map = RejectionMap::default();
+        let cases = [
+            (ExchangeId::Binance, 400, "-2010", "Account has insufficient balance for requested action.", "insufficient_balance"),
+            (ExchangeId::Binance, 400, "-1013", "Filter failure: LOT_SIZE", "lot_size"),
+            (ExchangeId::Binance, 400, "-1013", "Filter failure: NOTIONAL", "min_notional"),
+            (ExchangeId::Binance, 400, "-1013", "Filter failure: PRICE_FILTER", "price_filter"),
+            (ExchangeId::Binance, 429, "-1003", "Too much request weight used", "rate_limit"),
+            (ExchangeId::Okx, 200, "51008", "Order failed. Insufficient balance", "insufficient_balance"),
+            (ExchangeId::Okx, 200, "51020", "Order amount should be greater than the min available amount", "min_notional"),
+            (ExchangeId::Gate, 400, "TOO_MANY_REQUESTS", "", "rate_limit"),
+            (ExchangeId::Bybit, 503, "", "service unavailable", "transient"),
+        ];
+        for (exchange, status, code, message, reason) in cases {
+            let error = map.classify(exchange, status, code, message);
+            assert_eq!(error.reason(), reason, "{:?} {} {}", exchange, code, message);
+        }
+    }
+
+    #[test]
+    fn only_rate_limit_and_transient_are_retryable() {
+        let map = RejectionMap::default();
+        assert!(map.classify(ExchangeId::Binance, 429, "-1015", "").is_retryable());
+        assert!(map.classify(ExchangeId::Okx, 200, "50001", "").is_retryable());
+        assert!(!map.classify(ExchangeId::Okx, 200, "51020", "").is_retryable());
+        assert!(!map.classify(ExchangeId::Binance, 400, "-2010", "").is_retryable());
+    }
+
+    #[test]
+    fn unknown_code_is_kept_and_overrides_apply() {
+        let mut map = RejectionMap::default();
+        let error = map.classify(ExchangeId::Okx, 200, "59999", "something else");
+        assert_eq!(
+            error,
+            ExecutionError::Rejected {
+                exchange: ExchangeId::Okx,
+                code: "59999".to_string(),
+                message: "something else".to_string(),
+            }
+        );
+        map.extend_from_str("okx:59999=rate_limit, bogus, binance:-2010=lot_size");
+        assert_eq!(map.classify(ExchangeId::Okx, 200, "59999", "").reason(), "rate_limit");
+        assert_eq!(map.classify(ExchangeId::Binance, 400, "-2010", "").reason(), "lot_size");
+    }
+}
@@ -0,0 +1,290 @@
+//! 模拟 / 回测运行报告
+//!
+//! 运行期间按策略累计信号数、信号预期收益、执行数 (含失败次数)、毛利 / 净利、胜率、最大回撤 (按累计净利曲线) 与平均执行耗时，
+//! 失败的执行同样计入胜率分母、净利曲线与耗时，
+//! 模拟运行结束时写入 ENGINE_SIM_REPORT_FILE。文件扩展名为 `.csv` 时输出 CSV (每个策略一行)，
+//! 否则输出 JSON。
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::path::Path;
+
+use crate::strategy::{Signal, StrategyType};
+
+/// 单个策略的报告行
+#[derive(Debug, Clone, Serialize)]
+pub struct StrategyReport {
+    pub strategy_id: String,
+    pub strategy_type: StrategyType,
+    pub signals: u64,
+    /// 信号预期收益合计
+    pub expected_profit: f64,
+    /// 执行次数 (含失败)
+    pub executions: u64,
+    /// 失败的执行次数
+    pub failures: u64,
+    pub wins: u64,
+    pub gross_profit: f64,
+    pub net_profit: f64,
+    pub win_rate: f64,
+    pub max_drawdown: f64,
+    pub avg_latency_ms: f64,
+}
+
+#[derive(Debug, Clone)]
+struct Accumulator {
+    strategy_type: StrategyType,
+    signals: u64,
+    expected_profit: f64,
+    executions: u64,
+    failures: u64,
+    wins: u64,
+    gross_profit: f64,
+    net_profit: f64,
+    peak: f64,
+    max_drawdown: f64,
+    latency_total_ms: u64,
+}
+
+impl Accumulator {
+    fn new(strategy_type: StrategyType) -> Self {
+        Self {
+            strategy_type,
+            signals: 0,
+            expected_profit: 0.0,
+            executions: 0,
+            failures: 0,
+            wins: 0,
+            gross_profit: 0.0,
+            net_profit: 0.0,
+            peak: 0.0,
+            max_drawdown: 0.0,
+            latency_total_ms: 0,
+        }
+    }
+}
+
+/// 运行报告
+#[derive(Debug, Clone, Serialize)]
+pub struct SimReport {
+    pub started_at: i64,
+    pub finished_at: i64,
+    pub strategies: Vec<StrategyReport>,
+}
+
+/// 报告累计器
+#[derive(Debug, Default)]
+pub struct ReportCollector {
+    started_at: i64,
+    strategies: BTreeMap<String, Accumulator>,
+}
+
+impl ReportCollector {
+    pub fn new(started_at: i64) -> Self {
+        Self {
+            started_at,
+            strategies: BTreeMap::new(),
+        }
+    }
+
+    /// 记录策略产生的信号
+    pub fn record_signal(&mut self, signal: &Signal) {
+        let acc = self.entry(&signal.strategy_id, signal.strategy_type);
+        acc.signals += 1;
+        acc.expected_profit += signal.expected_profit;
+    }
+
+    /// 记录一次执行 (失败的执行不算盈利，部分成交的盈亏照常计入)
+    pub fn record_execution(
+        &mut self,
+        strategy_id: &str,
+        strategy_type: StrategyType,
+        gross_profit: f64,
+        net_profit: f64,
+        latency_ms: u64,
+        success: bool,
+    ) {
+        let acc = self.entry(strategy_id, strategy_type);
+        acc.executions += 1;
+        if !success {
+            acc.failures += 1;
+        } else if net_profit > 0.0 {
+            acc.wins += 1;
+        }
+        acc.gross_profit += gross_profit;
+        acc.net_profit += net_profit;
+        acc.latency_total_ms += latency_ms;
+        acc.peak = acc.peak.max(acc.net_profit);
+        acc.max_drawdown = acc.max_drawdown.max(acc.peak - acc.net_profit);
+    }
+
+    fn entry(&mut self, strategy_id: &str, strategy_type: StrategyType) -> &mut Accumulator {
+        self.strategies
+            .entry(strategy_id.to_string())
+            .or_insert_with(|| Accumulator::new(strategy_type))
+    }
+
+    pub fn report(&self, finished_at: i64) -> SimReport {
+        let strategies = self
+            .strategies
+            .iter()
+            .map(|(id, acc)| {
+                let per_execution = |v: f64| if acc.executions > 0 { v / acc.executions as f64 } else { 0.0 };
+                StrategyReport {
+                    strategy_id: id.clone(),
+                    strategy_type: acc.strategy_type,
+                    signals: acc.signals,
+                    expected_profit: acc.expected_profit,
+                    executions: acc.executions,
+                    failures: acc.failures,
+                    wins: acc.wins,
+                    gross_profit: acc.gross_profit,
+                    net_profit: acc.net_profit,
+                    win_rate: per_execution(acc.wins as f64),
+                    max_drawdown: acc.max_drawdown,
+                    avg_latency_ms: per_execution(acc.latency_total_ms as f64),
+                }
+            })
+            .collect();
+        SimReport {
+            started_at: self.started_at,
+            finished_at,
+            strategies,
+        }
+    }
+}
+
+impl SimReport {
+    /// 按扩展名写入 JSON 或 CSV
+    pub fn write_to(&self, path: &Path) -> Result<()> {
+        let is_csv = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .is_some_and(|e| e.eq_ignore_ascii_case("csv"));
+        let body = if is_csv {
+            self.to_csv()
+        } else {
+            serde_json::to_string_pretty(self)?
+        };
+        if let Some(dir) = path.parent().filter(|d| !d.as_os_str().is_empty()) {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(path, body).with_context(|| format!("写入运行报告 {} 失败", path.display()))
+    }
+
+    fn to_csv(&self) -> String {
+        let mut out = String::from(
+            "strategy_id,strategy_type,signals,expected_profit,executions,failures,wins,gross_profit,net_profit,win_rate,max_drawdown,avg_latency_ms\n",
+        );
+        for row in &self.strategies {
+            let strategy_type = serde_json::to_value(row.strategy_type)
+                .ok()
+                .and_then(|v| v.as_str().map(str::to_string))
+                .unwrap_or_default();
+            let _ = writeln!(
+                out,
+                "{},{},{},{:.8},{},{},{},{:.8},{:.8},{:.4},{:.8},{:.1}",
+                csv_field(&row.strategy_id),
+                strategy_type,
+                row.signals,
+                row.expected_profit,
+                row.executions,
+                row.failures,
+                row.wins,
+                row.gross_profit,
+                row.net_profit,
+                row.win_rate,
+                row.max_drawdown,
+                row.avg_latency_ms
+            );
+        }
+        out
+    }
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// 报告输出路径 (ENGINE_SIM_REPORT_FILE，未设置时不输出)
+pub fn report_path_from_env() -> Option<std::path::PathBuf> {
+    std::env::var("ENGINE_SIM_REPORT_FILE")
+        .ok()
+        .filter(|v| !v.is_empty())
+        .map(std::path::PathBuf::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exchange::ExchangeId;
+
+    fn signal(strategy_id: &str) -> Signal {
+        Signal::new(strategy_id, StrategyType::Grid, ExchangeId::Binance, 0.01, 0.5, 0.8, "BTC/USDT", 0)
+    }
+
+    fn collector() -> ReportCollector {
+        let mut collector = ReportCollector::new(1_000);
+        for _ in 0..3 {
+            collector.record_signal(&signal("btc"));
+        }
+        collector.record_signal(&signal("eth"));
+        collector.record_execution("btc", StrategyType::Grid, 0.3, 0.2, 10, true);
+        collector.record_execution("btc", StrategyType::Grid, 0.1, -0.1, 30, true);
+        collector
+    }
+
+    #[test]
+    fn executions_accumulate_win_rate_and_drawdown() {
+        let report = collector().report(2_000);
+        let btc = &report.strategies[0];
+        assert_eq!((btc.strategy_id.as_str(), btc.signals, btc.executions, btc.wins), ("btc", 3, 2, 1));
+        assert!((btc.expected_profit - 1.5).abs() < 1e-12);
+        assert!((btc.net_profit - 0.1).abs() < 1e-12);
+        assert!((btc.max_drawdown - 0.1).abs() < 1e-12);
+        assert_eq!(btc.win_rate, 0.5);
+        assert_eq!(btc.avg_latency_ms, 20.0);
+        assert_eq!(report.strategies[1].executions, 0);
+    }
+
+    #[test]
+    fn written_report_round_trips_per_strategy_rows() {
+        let report = collector().report(2_000);
+        let signals: Vec<(String, u64)> = report.strategies.iter().map(|s| (s.strategy_id.clone(), s.signals)).collect();
+
+        let dir = std::env::temp_dir().join(format!("inarbit-report-{}", uuid::Uuid::new_v4()));
+        let json_path = dir.join("nested").join("report.json");
+        report.write_to(&json_path).unwrap();
+        let written: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&json_path).unwrap()).unwrap();
+        assert_eq!(written, serde_json::to_value(&report).unwrap());
+        let rows = written["strategies"].as_array().unwrap();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0]["strategy_id"], "btc");
+        assert_eq!(rows[0]["strategy_type"], "grid");
+
+        // 扩展名为 .csv 时每个策略一行，列与 JSON 字段一致
+        let csv_path = dir.join("report.CSV");
+        report.write_to(&csv_path).unwrap();
+        let csv = std::fs::read_to_string(&csv_path).unwrap();
+        let mut lines = csv.lines();
+        let header: Vec<&str> = lines.next().unwrap().split(',').collect();
+        let parsed: Vec<(String, u64)> = lines
+            .map(|line| {
+                let fields: Vec<&str> = line.split(',').collect();
+                assert_eq!(fields.len(), header.len());
+                assert_eq!(fields[1], "grid");
+                (fields[0].to_string(), fields[2].parse().unwrap())
+            })
+            .collect();
+        assert_eq!(header[..3], ["strategy_id", "strategy_type", "signals"]);
+        assert_eq!(parsed, signals);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,48 +1,567 @@
 // risk.rs - Rust 风险管理模块
-use crate::strategy::Signal;
+use crate::exchange::ExchangeId;
+use crate::strategy::{Signal, SignalAction, CORRELATION_HEADER};
+use anyhow::{bail, Context};
 use async_trait::async_trait;
 use reqwest::Client;
-use std::sync::Arc;
-use tracing::warn;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, OnceLock, RwLock};
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use tracing::{debug, info, warn};
+
+/// Redis 紧急停止开关 (值为 "1" 时拦截全部信号)；按用户的开关为 `engine:{user_id}:kill_switch`
+const KILL_SWITCH_KEY: &str = "engine:kill_switch";
 
 #[derive(Debug, Clone)]
 pub struct RiskManager {
-    // 配置可以从 YAML 加载，这里使用占位结构
-    #[allow(dead_code)]
     pub config: Arc<RiskConfig>,
     remote: Option<RiskRemote>,
+    /// 已实现盈亏与权益峰值 (各克隆共享)
+    equity: Arc<RwLock<EquityTracker>>,
+    /// 未平敞口 (各克隆共享)
+    exposure: Arc<RwLock<ExposureBook>>,
+    /// Redis 紧急停止开关的缓存值 (由 `spawn_kill_switch_poller` 定期刷新)
+    kill_switch: Arc<AtomicBool>,
 }
 
-#[derive(Debug, Clone, Default)]
-#[allow(dead_code)]
+/// 本地风控阈值 (ENGINE_RISK_CONFIG 指定的 YAML 为基础，环境变量覆盖；YAML 键与字段名一致)
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
 pub struct RiskConfig {
-    pub max_drawdown: f64, // 如 0.2 表示 20%
+    pub max_drawdown: f64, // 如 0.2 表示 20%，0 为不限
+    /// 未平敞口合计上限 (计价币名义金额)，0 为不限
     pub exposure_limit: f64,
+    /// 无法由预期收益反推名义金额时，单笔信号计入的敞口
+    pub trade_exposure: f64,
+    /// 起始权益 (计价币)，回撤按 起始权益 + 已实现盈亏 相对峰值计算
+    pub initial_equity: f64,
     // 其他阈值
 }
 
+impl RiskConfig {
+    /// 启动时加载: ENGINE_RISK_CONFIG 指定 YAML 时以其为基础 (未设置时全部为 0，即不限)，再应用环境变量
+    pub fn load() -> anyhow::Result<Self> {
+        let mut config = match std::env::var("ENGINE_RISK_CONFIG").ok().filter(|v| !v.trim().is_empty()) {
+            Some(path) => Self::from_yaml(path.trim())?,
+            None => Self::default(),
+        };
+        config.apply_env()?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// 从 YAML 文件读取并校验取值范围
+    pub fn from_yaml(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let config: Self = config::Config::builder()
+            .add_source(config::File::from(path).format(config::FileFormat::Yaml))
+            .build()
+            .and_then(|c| c.try_deserialize())
+            .with_context(|| format!("读取风控配置 {} 失败", path.display()))?;
+        config
+            .validate()
+            .with_context(|| format!("风控配置 {} 无效", path.display()))?;
+        Ok(config)
+    }
+
+    /// 只从环境变量读取 (ENGINE_RISK_MAX_DRAWDOWN / ENGINE_RISK_EXPOSURE_LIMIT / ENGINE_RISK_TRADE_EXPOSURE /
+    /// ENGINE_RISK_INITIAL_EQUITY)
+    pub fn from_env() -> anyhow::Result<Self> {
+        let mut config = Self::default();
+        config.apply_env()?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// 已设置的环境变量覆盖对应字段
+    fn apply_env(&mut self) -> anyhow::Result<()> {
+        self.apply_overrides(|key| std::env::var(key).ok())
+    }
+
+    /// 按键读取覆盖值: 非空即解析 (`0` 关闭 YAML 中的上限)，无法解析时报错；取值范围由 `validate` 检查
+    fn apply_overrides(&mut self, get: impl Fn(&str) -> Option<String>) -> anyhow::Result<()> {
+        for (key, field) in [
+            ("ENGINE_RISK_MAX_DRAWDOWN", &mut self.max_drawdown),
+            ("ENGINE_RISK_EXPOSURE_LIMIT", &mut self.exposure_limit),
+            ("ENGINE_RISK_TRADE_EXPOSURE", &mut self.trade_exposure),
+            ("ENGINE_RISK_INITIAL_EQUITY", &mut self.initial_equity),
+        ] {
+            let Some(value) = get(key).filter(|v| !v.trim().is_empty()) else {
+                continue;
+            };
+            *field = value
+                .trim()
+                .parse()
+                .with_context(|| format!("{} 不是有效数字: {}", key, value))?;
+        }
+        Ok(())
+    }
+
+    /// 校验取值范围: 回撤为 (0, 1] 的比例 (0 为不限)，金额类为非负有限数；回撤上限需要起始权益
+    pub fn validate(&self) -> anyhow::Result<()> {
+        if !self.max_drawdown.is_finite() || self.max_drawdown < 0.0 || self.max_drawdown > 1.0 {
+            bail!("max_drawdown 须在 (0, 1] 之间 (0 为不限)，当前为 {}", self.max_drawdown);
+        }
+        for (name, value) in [
+            ("exposure_limit", self.exposure_limit),
+            ("trade_exposure", self.trade_exposure),
+            ("initial_equity", self.initial_equity),
+        ] {
+            if !value.is_finite() || value < 0.0 {
+                bail!("{} 须为非负数 (0 为不限)，当前为 {}", name, value);
+            }
+        }
+        // 回撤相对 起始权益 + 已实现盈亏 的峰值计算，没有起始权益时回撤上限不会生效
+        if self.max_drawdown > 0.0 && self.initial_equity <= 0.0 {
+            bail!("设置了 max_drawdown 时须同时设置 initial_equity (ENGINE_RISK_INITIAL_EQUITY)");
+        }
+        Ok(())
+    }
+}
+
+/// 未平敞口: 按信号关联 ID 记录名义金额及所属仓位
+/// (套利信号执行完成即释放；方向性策略的开仓按先进先出由同一策略、同一交易所、同一交易对的平仓信号逐笔释放)
+#[derive(Debug, Default)]
+struct ExposureBook {
+    /// 关联 ID -> (仓位, 名义金额, 计入顺序)
+    entries: HashMap<String, (String, f64, u64)>,
+    total: f64,
+    next_seq: u64,
+}
+
+impl ExposureBook {
+    fn add(&mut self, correlation_id: &str, position: String, notional: f64) {
+        self.next_seq += 1;
+        if let Some((_, previous, _)) = self.entries.insert(correlation_id.to_string(), (position, notional, self.next_seq)) {
+            self.total -= previous;
+        }
+        self.total += notional;
+    }
+
+    fn release(&mut self, correlation_id: &str) -> f64 {
+        let released = self.entries.remove(correlation_id).map(|(_, n, _)| n).unwrap_or(0.0);
+        self.total = (self.total - released).max(0.0);
+        released
+    }
+
+    /// 按计入顺序释放仓位的开仓敞口，直到释放满 `amount`；`amount` 未知时只释放最早的一笔
+    fn release_position(&mut self, position: &str, amount: Option<f64>) -> f64 {
+        let mut lots: Vec<(u64, String, f64)> = self
+            .entries
+            .iter()
+            .filter(|(_, (p, _, _))| p == position)
+            .map(|(id, (_, notional, seq))| (*seq, id.clone(), *notional))
+            .collect();
+        lots.sort_by_key(|(seq, _, _)| *seq);
+        let Some(mut remaining) = amount.or_else(|| lots.first().map(|(_, _, n)| *n)) else {
+            return 0.0;
+        };
+        let mut released = 0.0;
+        for (_, id, notional) in lots {
+            if remaining <= f64::EPSILON {
+                break;
+            }
+            if notional <= remaining + f64::EPSILON {
+                released += self.release(&id);
+                remaining -= notional;
+            } else {
+                // 平仓只覆盖该笔的一部分: 保留剩余名义金额
+                if let Some(entry) = self.entries.get_mut(&id) {
+                    entry.1 -= remaining;
+                }
+                self.total = (self.total - remaining).max(0.0);
+                released += remaining;
+                remaining = 0.0;
+            }
+        }
+        released
+    }
+}
+
+/// 方向性仓位的归属 (同一策略在同一交易所、同一交易对上的开仓由其平仓信号释放)
+fn position_key(signal: &Signal) -> String {
+    format!("{}:{:?}:{}", signal.strategy_id, signal.exchange, signal.path)
+}
+
+/// 已实现盈亏与权益峰值
+#[derive(Debug, Default)]
+struct EquityTracker {
+    realized: f64,
+    peak: f64,
+}
+
 impl RiskManager {
     pub fn new(config: RiskConfig) -> Self {
+        let equity = EquityTracker {
+            realized: 0.0,
+            peak: config.initial_equity,
+        };
         Self {
             config: Arc::new(config),
             remote: RiskRemote::from_env(),
+            equity: Arc::new(RwLock::new(equity)),
+            exposure: Arc::default(),
+            kill_switch: Arc::default(),
         }
     }
 
-    pub async fn check(&self, _signal: &Signal) -> bool {
-        if let Some(remote) = &self.remote {
-            match remote.check().await {
-                Ok(allowed) => return allowed,
-                Err(err) => warn!("remote risk check failed: {}", err),
+    /// Redis 紧急停止开关是否开启
+    pub fn kill_switch_engaged(&self) -> bool {
+        self.kill_switch.load(Ordering::Relaxed)
+    }
+
+    /// 更新紧急停止开关的缓存值，状态变化时记录一次日志
+    pub fn set_kill_switch(&self, engaged: bool) {
+        if self.kill_switch.swap(engaged, Ordering::Relaxed) == engaged {
+            return;
+        }
+        if engaged {
+            warn!("紧急停止开关已开启 ({})，拦截全部信号", KILL_SWITCH_KEY);
+        } else {
+            info!("紧急停止开关已关闭，恢复放行信号");
+        }
+    }
+
+    /// 信号计入的敞口: 由预期收益反推的名义金额，无法推算时取单笔配置值
+    pub fn signal_exposure(&self, signal: &Signal) -> f64 {
+        signal.notional().unwrap_or(self.config.trade_exposure)
+    }
+
+    /// 当前未平敞口合计
+    pub fn open_exposure(&self) -> f64 {
+        self.exposure.read().map(|e| e.total).unwrap_or(0.0)
+    }
+
+    /// 计入一笔已放行信号的敞口 (测试用；运行时由 `check` 放行时计入)
+    #[cfg(test)]
+    pub fn add_exposure(&self, signal: &Signal, notional: f64) {
+        if let Ok(mut exposure) = self.exposure.write() {
+            exposure.add(&signal.correlation_id, position_key(signal), notional);
+        }
+    }
+
+    /// 释放一笔信号的敞口，返回释放的名义金额
+    pub fn release_exposure(&self, correlation_id: &str) -> f64 {
+        self.exposure.write().map(|mut e| e.release(correlation_id)).unwrap_or(0.0)
+    }
+
+    /// 执行结束后结算敞口: 套利与未成功的开仓立即释放，方向性平仓成交后按先进先出释放平仓覆盖的开仓敞口
+    pub fn settle_exposure(&self, signal: &Signal, success: bool) {
+        let Ok(mut exposure) = self.exposure.write() else {
+            return;
+        };
+        if signal.action == SignalAction::Close {
+            if success {
+                exposure.release_position(&position_key(signal), signal.notional());
             }
+            return;
+        }
+        if !signal.strategy_type.is_directional() || !success {
+            exposure.release(&signal.correlation_id);
+        }
+    }
+
+    /// 敞口上限检查，通过时在同一把锁内计入敞口 (并发执行时不会同时越过上限)
+    fn reserve_exposure(&self, signal: &Signal) -> bool {
+        if self.config.exposure_limit <= 0.0 || signal.action == SignalAction::Close {
+            return true;
         }
+        let notional = self.signal_exposure(signal);
+        let Ok(mut exposure) = self.exposure.write() else {
+            return false;
+        };
+        if exposure.total + notional > self.config.exposure_limit {
+            warn!(
+                "未平敞口 {:.2} + {:.2} 超过上限 {:.2}，拒绝信号 [{}]",
+                exposure.total, notional, self.config.exposure_limit, signal.correlation_id
+            );
+            return false;
+        }
+        exposure.add(&signal.correlation_id, position_key(signal), notional);
         true
     }
+
+    /// 记录一次执行的已实现盈亏 (计价币)，同时更新权益峰值
+    pub fn record_pnl(&self, delta: f64) {
+        if !delta.is_finite() {
+            return;
+        }
+        let Ok(mut equity) = self.equity.write() else {
+            return;
+        };
+        equity.realized += delta;
+        equity.peak = equity.peak.max(self.config.initial_equity + equity.realized);
+    }
+
+    /// 当前权益相对峰值的回撤比例 (峰值不为正时为 0)
+    pub fn drawdown(&self) -> f64 {
+        let Ok(equity) = self.equity.read() else {
+            return 0.0;
+        };
+        if equity.peak <= 0.0 {
+            return 0.0;
+        }
+        ((equity.peak - (self.config.initial_equity + equity.realized)) / equity.peak).max(0.0)
+    }
+
+    pub async fn check(&self, signal: &Signal) -> bool {
+        if self.kill_switch_engaged() {
+            debug!("紧急停止开关开启，拒绝信号 [{}]", signal.correlation_id);
+            return false;
+        }
+        // 回撤超限时只放行平仓信号
+        if self.config.max_drawdown > 0.0 && signal.action != SignalAction::Close {
+            let drawdown = self.drawdown();
+            if drawdown >= self.config.max_drawdown {
+                warn!(
+                    "回撤 {:.2}% 超过上限 {:.2}%，拒绝信号 [{}]",
+                    drawdown * 100.0,
+                    self.config.max_drawdown * 100.0,
+                    signal.correlation_id
+                );
+                return false;
+            }
+        }
+        if let Some(remote) = &self.remote {
+            match remote.check(&signal.correlation_id).await {
+                Ok(false) => return false,
+                Ok(true) => {}
+                Err(err) => warn!(
+                    "remote risk check failed [{}]: {}",
+                    signal.correlation_id, err
+                ),
+            }
+        }
+        self.reserve_exposure(signal)
+    }
+}
+
+/// 组合层面的单币种净持仓上限 (按计价币名义金额)
+///
+/// 网格、配对、期现等策略各自在限额内，合计仍可能在同一币种上累积大额净多 / 净空。
+/// 从 ENGINE_MAX_NET_INVENTORY 读取，如 `BTC:50000,ETH:20000,*:10000` (`*` 为其他币种的默认上限)。
+#[derive(Debug, Clone, Default)]
+pub struct InventoryLimits {
+    limits: HashMap<String, f64>,
+    default_limit: Option<f64>,
+}
+
+impl InventoryLimits {
+    pub fn from_env() -> Self {
+        std::env::var("ENGINE_MAX_NET_INVENTORY")
+            .map(|raw| Self::parse(&raw))
+            .unwrap_or_default()
+    }
+
+    pub fn parse(raw: &str) -> Self {
+        let mut out = Self::default();
+        for item in raw.split(',') {
+            let Some((asset, limit)) = item.split_once(':') else {
+                continue;
+            };
+            let Ok(limit) = limit.trim().parse::<f64>() else {
+                warn!("ENGINE_MAX_NET_INVENTORY 项 {} 无效，已忽略", item);
+                continue;
+            };
+            match asset.trim() {
+                "*" => out.default_limit = Some(limit.abs()),
+                asset => {
+                    out.limits.insert(asset.to_uppercase(), limit.abs());
+                }
+            }
+        }
+        out
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.limits.is_empty() && self.default_limit.is_none()
+    }
+
+    fn limit(&self, asset: &str) -> Option<f64> {
+        self.limits.get(asset).copied().or(self.default_limit)
+    }
+
+    /// 检查净持仓 `net` 再变动 `delta` 后是否超限；减少敞口的变动始终放行
+    pub fn check(&self, asset: &str, net: f64, delta: f64) -> Result<(), String> {
+        let Some(limit) = self.limit(asset) else {
+            return Ok(());
+        };
+        let after = net + delta;
+        if after.abs() > limit && after.abs() > net.abs() {
+            return Err(format!(
+                "{} 净持仓 {:.2} 变动 {:+.2} 后超过上限 {:.2}",
+                asset, net, delta, limit
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// 单个交易所的资金部署上限 (按计价币名义金额)
+///
+/// 资金集中在一个交易所是对手方风险。按仓位簿统计各交易所已部署的持仓名义金额 (多空取绝对值)，
+/// 开仓信号会使某个交易所超过上限时拦截；平仓信号始终放行。
+/// 从 ENGINE_MAX_EXCHANGE_CAPITAL 读取，如 `binance:50000,okx:20000,*:30000` (`*` 为其他交易所的默认上限)。
+#[derive(Debug, Clone, Default)]
+pub struct ExchangeCapitalCaps {
+    caps: HashMap<ExchangeId, f64>,
+    default_cap: Option<f64>,
+}
+
+impl ExchangeCapitalCaps {
+    pub fn from_env() -> Self {
+        std::env::var("ENGINE_MAX_EXCHANGE_CAPITAL")
+            .map(|raw| Self::parse(&raw))
+            .unwrap_or_default()
+    }
+
+    pub fn parse(raw: &str) -> Self {
+        let mut out = Self::default();
+        for item in raw.split(',').filter(|i| !i.trim().is_empty()) {
+            let Some((exchange, cap)) = item.split_once(':') else {
+                continue;
+            };
+            let Ok(cap) = cap.trim().parse::<f64>() else {
+                warn!("ENGINE_MAX_EXCHANGE_CAPITAL 项 {} 无效，已忽略", item);
+                continue;
+            };
+            match exchange.trim() {
+                "*" => out.default_cap = Some(cap.abs()),
+                exchange => {
+                    match serde_json::from_value(serde_json::Value::String(exchange.to_lowercase())) {
+                        Ok(id) => {
+                            out.caps.insert(id, cap.abs());
+                        }
+                        Err(_) => warn!("ENGINE_MAX_EXCHANGE_CAPITAL 交易所 {} 未知，已忽略", exchange),
+                    }
+                }
+            }
+        }
+        out
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.caps.is_empty() && self.default_cap.is_none()
+    }
+
+    fn cap(&self, exchange: ExchangeId) -> Option<f64> {
+        self.caps.get(&exchange).copied().or(self.default_cap)
+    }
+
+    /// 检查交易所已部署 `deployed` 再投入 `additional` 后是否超过上限
+    pub fn check(&self, exchange: ExchangeId, deployed: f64, additional: f64) -> Result<(), String> {
+        let Some(cap) = self.cap(exchange) else {
+            return Ok(());
+        };
+        if additional > 0.0 && deployed + additional > cap {
+            return Err(format!(
+                "{:?} 已部署 {:.2}，再投入 {:.2} 后超过上限 {:.2}",
+                exchange, deployed, additional, cap
+            ));
+        }
+        Ok(())
+    }
 }
 
-// 为了在 engine 中统一调用，提供一个全局单例（示例）
+/// 启动时加载的风控配置 (见 `init_global`)
+static GLOBAL_RISK_CONFIG: OnceLock<RiskConfig> = OnceLock::new();
+
+/// 设置全局风控管理器的配置；须在首次使用 `GLOBAL_RISK_MANAGER` 之前调用，之后调用不生效
+pub fn init_global(config: RiskConfig) {
+    if GLOBAL_RISK_CONFIG.set(config).is_err() {
+        warn!("全局风控配置已初始化，忽略重复设置");
+    }
+}
+
+// 为了在 engine 中统一调用，提供一个全局单例 (未经 `init_global` 初始化时只读取环境变量，无效时不限)
 lazy_static::lazy_static! {
-    pub static ref GLOBAL_RISK_MANAGER: RiskManager = RiskManager::new(RiskConfig::default());
+    pub static ref GLOBAL_RISK_MANAGER: RiskManager = RiskManager::new(
+        GLOBAL_RISK_CONFIG.get().cloned().unwrap_or_else(|| {
+            RiskConfig::from_env().unwrap_or_else(|e| {
+                warn!("风控环境变量无效，忽略: {:#}", e);
+                RiskConfig::default()
+            })
+        })
+    );
+}
+
+/// 紧急停止开关的轮询间隔 (ENGINE_KILL_SWITCH_POLL_MS，默认 1000)
+pub fn kill_switch_poll_interval() -> Duration {
+    Duration::from_millis(
+        std::env::var("ENGINE_KILL_SWITCH_POLL_MS")
+            .ok()
+            .and_then(|v| v.parse::<u64>().ok())
+            .filter(|v| *v > 0)
+            .unwrap_or(1_000),
+    )
+}
+
+/// 紧急停止开关的 Redis Key: 全局开关，以及指定用户时的 `engine:{user_id}:kill_switch`
+pub fn kill_switch_keys(user_id: Option<&str>) -> Vec<String> {
+    let mut keys = vec![KILL_SWITCH_KEY.to_string()];
+    if let Some(user_id) = user_id.filter(|id| !id.is_empty()) {
+        keys.push(format!("engine:{}:kill_switch", user_id));
+    }
+    keys
+}
+
+/// 写入全局紧急停止开关 (开启写 "1"，关闭删除 Key)，使轮询方与其他引擎实例同步
+pub async fn persist_kill_switch<C: redis::aio::ConnectionLike + Send>(conn: &mut C, engaged: bool) -> redis::RedisResult<()> {
+    if engaged {
+        redis::cmd("SET").arg(KILL_SWITCH_KEY).arg("1").query_async(conn).await
+    } else {
+        redis::cmd("DEL").arg(KILL_SWITCH_KEY).query_async(conn).await
+    }
+}
+
+/// 读取一次紧急停止开关并写入风控管理器的缓存 (任一 Key 为 "1" 即开启)
+async fn poll_kill_switch<C: redis::aio::ConnectionLike + Send>(
+    manager: &RiskManager,
+    conn: &mut C,
+    keys: &[String],
+) -> redis::RedisResult<()> {
+    let values: Vec<Option<String>> = redis::cmd("MGET").arg(keys).query_async(conn).await?;
+    manager.set_kill_switch(values.iter().flatten().any(|v| v.trim() == "1"));
+    Ok(())
+}
+
+/// 按间隔轮询 Redis 紧急停止开关并写入风控管理器的缓存 (任一 Key 为 "1" 即开启；读取失败时保持上次的状态)
+pub fn spawn_kill_switch_poller(
+    manager: RiskManager,
+    redis: redis::Client,
+    user_id: Option<String>,
+    interval: Duration,
+) -> JoinHandle<()> {
+    let keys = kill_switch_keys(user_id.as_deref());
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+        let mut conn = None;
+        let mut failing = false;
+        loop {
+            ticker.tick().await;
+            if conn.is_none() {
+                conn = redis.get_multiplexed_async_connection().await.ok();
+            }
+            let Some(c) = conn.as_mut() else {
+                continue;
+            };
+            match poll_kill_switch(&manager, c, &keys).await {
+                Ok(()) => failing = false,
+                Err(err) => {
+                    if !failing {
+                        warn!("读取紧急停止开关失败，保持当前状态: {}", err);
+                    }
+                    failing = true;
+                    conn = None;
+                }
+            }
+        }
+    })
 }
 
 #[async_trait]
@@ -62,6 +581,7 @@ struct RiskRemote {
     base_url: String,
     token: String,
     http: Client,
+    trace_requests: bool,
 }
 
 impl RiskRemote {
@@ -76,16 +596,19 @@ impl RiskRemote {
             base_url: base.trim_end_matches('/').to_string(),
             token,
             http: Client::new(),
+            trace_requests: trace_requests_from_env(),
         })
     }
 
-    async fn check(&self) -> anyhow::Result<bool> {
-        let resp = self
+    async fn check(&self, correlation_id: &str) -> anyhow::Result<bool> {
+        let mut req = self
             .http
             .get(format!("{}/api/v1/risk/status", self.base_url))
-            .bearer_auth(&self.token)
-            .send()
-            .await?;
+            .bearer_auth(&self.token);
+        if self.trace_requests && !correlation_id.is_empty() {
+            req = req.header(CORRELATION_HEADER, correlation_id);
+        }
+        let resp = req.send().await?;
         let payload: serde_json::Value = resp.json().await?;
         Ok(payload
             .get("trading_allowed")
@@ -93,3 +616,267 @@ impl RiskRemote {
             .unwrap_or(true))
     }
 }
+
+/// 是否在出站请求中携带关联 ID (ENGINE_TRACE_REQUESTS=0 关闭，默认开启)
+pub(crate) fn trace_requests_from_env() -> bool {
+    std::env::var("ENGINE_TRACE_REQUESTS")
+        .map(|v| !matches!(v.as_str(), "0" | "false" | "False"))
+        .unwrap_or(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fake_redis::FakeRedis;
+    use crate::executor::OrderSide;
+    use crate::positions::PositionBook;
+    use crate::strategy::StrategyType;
+
+    #[tokio::test]
+    async fn remote_risk_check_carries_the_correlation_id_header() {
+        use axum::http::HeaderMap;
+        use axum::routing::get;
+        use axum::Json;
+
+        let app = axum::Router::new().route(
+            "/api/v1/risk/status",
+            get(|headers: HeaderMap| async move {
+                // 只放行带关联 ID 的请求
+                let traced = headers.get(CORRELATION_HEADER).is_some_and(|v| v == "corr-1");
+                Json(serde_json::json!({ "trading_allowed": traced }))
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        let mut remote = RiskRemote {
+            base_url,
+            token: "token".to_string(),
+            http: Client::new(),
+            trace_requests: true,
+        };
+        assert!(remote.check("corr-1").await.unwrap());
+        remote.trace_requests = false;
+        assert!(!remote.check("corr-1").await.unwrap());
+    }
+
+    #[test]
+    fn exchange_at_its_capital_cap_blocks_only_that_exchange() {
+        let caps = ExchangeCapitalCaps::parse("binance:1000, okx:5000, *:2000, bogus:1, bybit:x");
+        assert!(!caps.is_empty());
+        let mut book = PositionBook::new();
+        // Binance 多空持仓合计 1000，已到上限
+        book.apply_fill(ExchangeId::Binance, "BTC/USDT", OrderSide::Buy, 6.0, 100.0);
+        book.apply_fill(ExchangeId::Binance, "ETH/USDT", OrderSide::Sell, 40.0, 10.0);
+        book.apply_fill(ExchangeId::Okx, "BTC/USDT", OrderSide::Buy, 10.0, 100.0);
+        let check = |exchange| caps.check(exchange, book.deployed_capital(exchange), 100.0);
+
+        let err = check(ExchangeId::Binance).unwrap_err();
+        assert!(err.contains("超过上限"), "{}", err);
+        assert!(check(ExchangeId::Okx).is_ok());
+        // 未单独配置的交易所使用 `*` 默认上限
+        assert!(check(ExchangeId::Bybit).is_ok());
+        assert!(caps.check(ExchangeId::Bybit, 1_950.0, 100.0).is_err());
+        // 不投入资金 (平仓) 时放行
+        assert!(caps.check(ExchangeId::Binance, 5_000.0, 0.0).is_ok());
+        assert!(ExchangeCapitalCaps::parse("").check(ExchangeId::Binance, 1e9, 1e9).is_ok());
+    }
+
+    /// 名义金额 100 (预期收益 1 / 收益率 1%) 的信号
+    fn signal(strategy_type: StrategyType, action: SignalAction) -> Signal {
+        Signal::new("s1", strategy_type, ExchangeId::Binance, 0.01, 1.0, 0.9, "BTC/USDT", 0).with_action(action)
+    }
+
+    fn manager(exposure_limit: f64) -> RiskManager {
+        RiskManager::new(RiskConfig {
+            exposure_limit,
+            ..Default::default()
+        })
+    }
+
+    #[tokio::test]
+    async fn exposure_limit_blocks_the_next_signal() {
+        let risk = manager(300.0);
+        for _ in 0..3 {
+            let open = signal(StrategyType::Grid, SignalAction::Open);
+            assert!(risk.check(&open).await);
+            risk.settle_exposure(&open, true);
+        }
+        assert!((risk.open_exposure() - 300.0).abs() < 1e-9);
+        assert!(!risk.check(&signal(StrategyType::Grid, SignalAction::Open)).await);
+
+        // 平仓不受限，成交后释放一格开仓敞口
+        let close = signal(StrategyType::Grid, SignalAction::Close);
+        assert!(risk.check(&close).await);
+        risk.settle_exposure(&close, true);
+        assert!((risk.open_exposure() - 200.0).abs() < 1e-9);
+        assert!(risk.check(&signal(StrategyType::Grid, SignalAction::Open)).await);
+    }
+
+    #[tokio::test]
+    async fn closing_one_grid_rung_keeps_the_other_rungs_counted() {
+        let risk = manager(1_000.0);
+        for _ in 0..3 {
+            let open = signal(StrategyType::Grid, SignalAction::Open);
+            assert!(risk.check(&open).await);
+            risk.settle_exposure(&open, true);
+        }
+        // 其他交易对的平仓不释放 BTC/USDT 的格子
+        let mut other = signal(StrategyType::Grid, SignalAction::Close);
+        other.path = "ETH/USDT".to_string();
+        risk.settle_exposure(&other, true);
+        assert!((risk.open_exposure() - 300.0).abs() < 1e-9);
+
+        risk.settle_exposure(&signal(StrategyType::Grid, SignalAction::Close), true);
+        assert!((risk.open_exposure() - 200.0).abs() < 1e-9, "{}", risk.open_exposure());
+
+        // 收益为 0 的强制平仓无法反推金额，释放最早的一格
+        let forced = Signal::new("s1", StrategyType::Grid, ExchangeId::Binance, 0.0, 0.0, 0.5, "BTC/USDT", 0)
+            .with_action(SignalAction::Close);
+        risk.settle_exposure(&forced, true);
+        assert!((risk.open_exposure() - 100.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn partial_close_releases_only_the_covered_amount_oldest_first() {
+        let mut book = ExposureBook::default();
+        book.add("a", "p".to_string(), 100.0);
+        book.add("b", "p".to_string(), 100.0);
+        assert!((book.release_position("p", Some(150.0)) - 150.0).abs() < 1e-9);
+        assert!(!book.entries.contains_key("a"));
+        assert!((book.entries["b"].1 - 50.0).abs() < 1e-9);
+        assert!((book.total - 50.0).abs() < 1e-9);
+    }
+
+    #[tokio::test]
+    async fn arbitrage_exposure_is_released_after_execution() {
+        let risk = manager(150.0);
+        let first = signal(StrategyType::Triangular, SignalAction::Open);
+        assert!(risk.check(&first).await);
+        // 执行未结束时占用敞口
+        assert!(!risk.check(&signal(StrategyType::Triangular, SignalAction::Open)).await);
+        risk.settle_exposure(&first, true);
+        assert_eq!(risk.open_exposure(), 0.0);
+
+        let manual = signal(StrategyType::Triangular, SignalAction::Open);
+        risk.add_exposure(&manual, 120.0);
+        assert_eq!(risk.release_exposure(&manual.correlation_id), 120.0);
+        assert_eq!(risk.open_exposure(), 0.0);
+    }
+
+    #[test]
+    fn drawdown_limit_requires_initial_equity() {
+        let config = RiskConfig {
+            max_drawdown: 0.2,
+            ..Default::default()
+        };
+        assert!(config.validate().is_err());
+        assert!(RiskConfig {
+            initial_equity: 1000.0,
+            ..config
+        }
+        .validate()
+        .is_ok());
+    }
+
+    #[test]
+    fn env_overrides_parse_every_value_and_zero_disables_yaml_limits() {
+        let yaml = RiskConfig {
+            max_drawdown: 0.2,
+            exposure_limit: 500.0,
+            initial_equity: 1000.0,
+            ..Default::default()
+        };
+        let overridden = |vars: &[(&str, &str)]| {
+            let mut config = yaml.clone();
+            config
+                .apply_overrides(|key| vars.iter().find(|(k, _)| *k == key).map(|(_, v)| v.to_string()))
+                .map(|_| config)
+        };
+
+        let config = overridden(&[("ENGINE_RISK_EXPOSURE_LIMIT", "0"), ("ENGINE_RISK_TRADE_EXPOSURE", " 25 ")]).unwrap();
+        assert_eq!(config.exposure_limit, 0.0);
+        assert_eq!(config.trade_exposure, 25.0);
+        assert_eq!((config.max_drawdown, config.initial_equity), (0.2, 1000.0));
+        // 空值视为未设置
+        assert_eq!(overridden(&[("ENGINE_RISK_EXPOSURE_LIMIT", "")]).unwrap().exposure_limit, 500.0);
+        // 无法解析时报错，越界的值交给 validate 拒绝
+        assert!(overridden(&[("ENGINE_RISK_MAX_DRAWDOWN", "20%")]).is_err());
+        assert!(overridden(&[("ENGINE_RISK_MAX_DRAWDOWN", "1.5")]).unwrap().validate().is_err());
+        assert!(overridden(&[("ENGINE_RISK_EXPOSURE_LIMIT", "-1")]).unwrap().validate().is_err());
+    }
+
+    #[tokio::test]
+    async fn drawdown_limit_blocks_opens_but_allows_close() {
+        let risk = RiskManager::new(RiskConfig {
+            max_drawdown: 0.1,
+            initial_equity: 1000.0,
+            ..Default::default()
+        });
+        // 盈利 100 抬高峰值至 1100，随后亏损 100 (回撤约 9.1%) 仍在上限内
+        risk.record_pnl(100.0);
+        risk.record_pnl(-100.0);
+        assert!((risk.drawdown() - 100.0 / 1100.0).abs() < 1e-9);
+        assert!(risk.check(&signal(StrategyType::Grid, SignalAction::Open)).await);
+
+        // 再亏损 20: 权益 980，相对峰值 1100 回撤约 10.9%，拒绝开仓
+        risk.record_pnl(-20.0);
+        assert!(risk.drawdown() >= 0.1);
+        assert!(!risk.check(&signal(StrategyType::Grid, SignalAction::Open)).await);
+        assert!(!risk.check(&signal(StrategyType::Triangular, SignalAction::Open)).await);
+        // 平仓信号仍放行，用于降低风险
+        assert!(risk.check(&signal(StrategyType::Grid, SignalAction::Close)).await);
+
+        // 盈利恢复到峰值附近后重新放行
+        risk.record_pnl(110.0);
+        assert!(risk.check(&signal(StrategyType::Grid, SignalAction::Open)).await);
+    }
+
+    #[tokio::test]
+    async fn zero_limit_is_unlimited() {
+        let risk = manager(0.0);
+        for _ in 0..10 {
+            assert!(risk.check(&signal(StrategyType::Grid, SignalAction::Open)).await);
+        }
+        assert_eq!(risk.open_exposure(), 0.0);
+    }
+
+    #[tokio::test]
+    async fn kill_switch_poll_blocks_signals_while_any_key_is_set() {
+        let redis = FakeRedis::start().await;
+        let mut conn = redis.connection().await;
+        let manager = RiskManager::new(RiskConfig::default());
+        let keys = kill_switch_keys(Some("u1"));
+        let signal = Signal::new("tri", StrategyType::Triangular, ExchangeId::Binance, 0.01, 1.0, 0.5, "BTC/USDT", 0);
+
+        for key in ["engine:kill_switch", "engine:u1:kill_switch"] {
+            let _: () = redis::cmd("SET").arg(key).arg("1").query_async(&mut conn).await.unwrap();
+            poll_kill_switch(&manager, &mut conn, &keys).await.unwrap();
+            assert!(!manager.check(&signal).await, "{} 开启时应拦截信号", key);
+
+            let _: () = redis::cmd("DEL").arg(key).query_async(&mut conn).await.unwrap();
+            poll_kill_switch(&manager, &mut conn, &keys).await.unwrap();
+            assert!(manager.check(&signal).await, "{} 清除后应放行信号", key);
+        }
+    }
+
+    #[test]
+    fn risk_config_loads_the_shipped_yaml() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("../config/engine_risk.yaml");
+        let config = RiskConfig::from_yaml(&path).unwrap();
+        assert_eq!(config.max_drawdown, 0.2);
+        assert_eq!(config.initial_equity, 10_000.0);
+        assert_eq!(config.exposure_limit, 5_000.0);
+        assert_eq!(config.trade_exposure, 100.0);
+    }
+
+    #[test]
+    fn risk_config_rejects_unknown_keys() {
+        let path = std::env::temp_dir().join(format!("inarbit-risk-{}.yaml", uuid::Uuid::new_v4()));
+        std::fs::write(&path, "max_drawdown: 0.2\nmax_draw_down: 0.3\n").unwrap();
+        let err = RiskConfig::from_yaml(&path).unwrap_err();
+        std::fs::remove_file(&path).unwrap();
+        assert!(format!("{:#}", err).contains("max_draw_down"), "{:#}", err);
+    }
+}
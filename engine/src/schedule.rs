@@ -0,0 +1,255 @@
+//! 策略活跃时间窗口
+//!
+//! 配置位于 `StrategyConfig.config["schedule"]`，所有时间均为 UTC（无夏令时），
+//! 时区换算由调用方在代码中完成，配置中不出现时区：
+//!
+//! ```json
+//! {"schedule": {"windows": [{"days": ["mon", "fri"], "start": "22:00", "end": "02:00"}], "close_on_end": true}}
+//! ```
+//!
+//! `start > end` 表示跨越午夜的窗口，`days` 指窗口开始的那一天，省略表示每天。
+//!
+//! 另可通过 `StrategyConfig.active_from` / `active_until` 限定一次性的生效区间 (如某次资金费结算或上新前后)，
+//! 区间外策略照常接收行情但不产生可执行信号；与每周窗口同时配置时两者都满足才活跃。
+
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Datelike, Timelike, Weekday};
+use serde::Deserialize;
+
+use crate::strategy::StrategyConfig;
+
+/// 每周循环的活跃窗口
+#[derive(Debug, Clone)]
+pub struct ActiveWindow {
+    /// 窗口开始的星期 (空表示每天)
+    days: Vec<Weekday>,
+    /// 开始时间 (当天第几分钟)
+    start: u32,
+    /// 结束时间 (当天第几分钟，不含)
+    end: u32,
+}
+
+impl ActiveWindow {
+    fn starts_on(&self, day: Weekday) -> bool {
+        self.days.is_empty() || self.days.contains(&day)
+    }
+
+    /// 判断 UTC 星期 + 分钟是否处于窗口内
+    fn contains(&self, day: Weekday, minute: u32) -> bool {
+        if self.start < self.end {
+            self.starts_on(day) && minute >= self.start && minute < self.end
+        } else if self.start > self.end {
+            // 跨越午夜: 开始当天的后半段 + 次日的前半段
+            (self.starts_on(day) && minute >= self.start)
+                || (self.starts_on(day.pred()) && minute < self.end)
+        } else {
+            // start == end 视为全天
+            self.starts_on(day)
+        }
+    }
+}
+
+/// 策略调度配置
+#[derive(Debug, Clone)]
+pub struct StrategySchedule {
+    /// 每周循环窗口 (空表示不限)
+    windows: Vec<ActiveWindow>,
+    /// 一次性生效区间 [active_from, active_until) (毫秒)
+    active_from: Option<i64>,
+    active_until: Option<i64>,
+    /// 窗口结束时是否要求策略发出平仓信号
+    pub close_on_end: bool,
+}
+
+#[derive(Deserialize)]
+struct RawSchedule {
+    #[serde(default)]
+    windows: Vec<RawWindow>,
+    #[serde(default)]
+    close_on_end: bool,
+}
+
+#[derive(Deserialize)]
+struct RawWindow {
+    #[serde(default)]
+    days: Vec<String>,
+    start: String,
+    end: String,
+}
+
+impl StrategySchedule {
+    /// 从策略配置中解析调度配置，未配置 `schedule` 且未设置生效区间时返回 `None`（始终活跃）
+    pub fn from_config(config: &StrategyConfig) -> Result<Option<Self>> {
+        let (active_from, active_until) = (config.active_from, config.active_until);
+        if let (Some(from), Some(until)) = (active_from, active_until) {
+            if from >= until {
+                return Err(anyhow!("active_from must be earlier than active_until"));
+            }
+        }
+        let raw = config.config.get("schedule").filter(|v| !v.is_null());
+        let Some(raw) = raw else {
+            if active_from.is_none() && active_until.is_none() {
+                return Ok(None);
+            }
+            return Ok(Some(Self {
+                windows: vec![],
+                active_from,
+                active_until,
+                close_on_end: false,
+            }));
+        };
+        let raw: RawSchedule = serde_json::from_value(raw.clone())?;
+        let mut windows = Vec::with_capacity(raw.windows.len());
+        for w in raw.windows {
+            let days = w
+                .days
+                .iter()
+                .map(|d| d.parse::<Weekday>().map_err(|_| anyhow!("invalid weekday: {}", d)))
+                .collect::<Result<Vec<_>>>()?;
+            windows.push(ActiveWindow {
+                days,
+                start: parse_minute_of_day(&w.start)?,
+                end: parse_minute_of_day(&w.end)?,
+            });
+        }
+        if windows.is_empty() {
+            return Err(anyhow!("schedule.windows must not be empty"));
+        }
+        Ok(Some(Self {
+            windows,
+            active_from,
+            active_until,
+            close_on_end: raw.close_on_end,
+        }))
+    }
+
+    /// 判断给定时间戳 (毫秒) 是否处于生效区间且处于任一活跃窗口内
+    pub fn is_active(&self, timestamp_ms: i64) -> bool {
+        if self.active_from.is_some_and(|from| timestamp_ms < from)
+            || self.active_until.is_some_and(|until| timestamp_ms >= until)
+        {
+            return false;
+        }
+        if self.windows.is_empty() {
+            return true;
+        }
+        let Some(dt) = DateTime::from_timestamp_millis(timestamp_ms) else {
+            return false;
+        };
+        let minute = dt.hour() * 60 + dt.minute();
+        let day = dt.weekday();
+        self.windows.iter().any(|w| w.contains(day, minute))
+    }
+}
+
+/// 解析 "HH:MM" 为当天第几分钟 ("24:00" 表示当天结束)
+fn parse_minute_of_day(value: &str) -> Result<u32> {
+    let (h, m) = value
+        .split_once(':')
+        .ok_or_else(|| anyhow!("invalid time (expect HH:MM): {}", value))?;
+    let h: u32 = h.trim().parse()?;
+    let m: u32 = m.trim().parse()?;
+    if m >= 60 || h > 24 || (h == 24 && m != 0) {
+        return Err(anyhow!("time out of range: {}", value));
+    }
+    Ok(h * 60 + m)
+}
+
+/// 解析时间: RFC 3339 字符串 (如 `2026-03-01T08:00:00Z`) 或毫秒时间戳
+pub fn parse_timestamp(value: &serde_json::Value) -> Option<i64> {
+    match value {
+        serde_json::Value::Number(n) => n.as_i64(),
+        serde_json::Value::String(s) => DateTime::parse_from_rfc3339(s.trim())
+            .ok()
+            .map(|t| t.timestamp_millis()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeZone, Utc};
+
+    fn config(params: serde_json::Value, active_from: Option<i64>, active_until: Option<i64>) -> StrategyConfig {
+        serde_json::from_value(serde_json::json!({
+            "id": "s",
+            "strategy_type": "grid",
+            "name": "s",
+            "is_enabled": true,
+            "priority": 1,
+            "config": params,
+            "active_from": active_from,
+            "active_until": active_until,
+        }))
+        .unwrap()
+    }
+
+    fn at(day: u32, hour: u32, minute: u32) -> i64 {
+        // 2026-01-02 为星期五
+        Utc.with_ymd_and_hms(2026, 1, day, hour, minute, 0).unwrap().timestamp_millis()
+    }
+
+    #[test]
+    fn window_spanning_midnight_covers_the_next_morning() {
+        let schedule = StrategySchedule::from_config(&config(
+            serde_json::json!({"schedule": {"windows": [{"days": ["fri"], "start": "22:00", "end": "02:00"}]}}),
+            None,
+            None,
+        ))
+        .unwrap()
+        .unwrap();
+        assert!(!schedule.is_active(at(2, 21, 59)));
+        assert!(schedule.is_active(at(2, 22, 0)));
+        assert!(schedule.is_active(at(2, 23, 59)));
+        assert!(schedule.is_active(at(3, 0, 0)));
+        assert!(schedule.is_active(at(3, 1, 59)));
+        assert!(!schedule.is_active(at(3, 2, 0)));
+        // 星期四开始的那一晚不在窗口内，星期六晚上也不在
+        assert!(!schedule.is_active(at(1, 23, 0)));
+        assert!(!schedule.is_active(at(3, 23, 0)));
+    }
+
+    #[test]
+    fn window_ending_at_midnight_is_exclusive() {
+        let schedule = StrategySchedule::from_config(&config(
+            serde_json::json!({"schedule": {"windows": [{"start": "20:00", "end": "24:00"}], "close_on_end": true}}),
+            None,
+            None,
+        ))
+        .unwrap()
+        .unwrap();
+        assert!(schedule.close_on_end);
+        assert!(schedule.is_active(at(2, 23, 59)));
+        assert!(!schedule.is_active(at(3, 0, 0)));
+    }
+
+    #[test]
+    fn active_range_is_combined_with_windows() {
+        let schedule = StrategySchedule::from_config(&config(
+            serde_json::json!({"schedule": {"windows": [{"start": "00:00", "end": "12:00"}]}}),
+            Some(at(3, 0, 0)),
+            Some(at(4, 0, 0)),
+        ))
+        .unwrap()
+        .unwrap();
+        assert!(!schedule.is_active(at(2, 6, 0)));
+        assert!(schedule.is_active(at(3, 6, 0)));
+        assert!(!schedule.is_active(at(3, 13, 0)));
+        assert!(!schedule.is_active(at(4, 6, 0)));
+    }
+
+    #[test]
+    fn invalid_schedules_are_rejected() {
+        assert!(StrategySchedule::from_config(&config(serde_json::json!({}), None, None)).unwrap().is_none());
+        let bad = [
+            serde_json::json!({"schedule": {"windows": []}}),
+            serde_json::json!({"schedule": {"windows": [{"start": "25:00", "end": "02:00"}]}}),
+            serde_json::json!({"schedule": {"windows": [{"days": ["someday"], "start": "01:00", "end": "02:00"}]}}),
+        ];
+        for params in bad {
+            assert!(StrategySchedule::from_config(&config(params.clone(), None, None)).is_err(), "{}", params);
+        }
+        assert!(StrategySchedule::from_config(&config(serde_json::json!({}), Some(10), Some(10))).is_err());
+    }
+}
@@ -0,0 +1,685 @@
+//! 交易所执行质量评分卡
+//!
+//! 消费执行结果与行情健康度数据 (不新增埋点)，按交易所在多个滚动窗口内统计:
+//! 相对决策价的实际滑点、下单确认延迟分布、拒单率、部分成交率、手续费准确度
+//! (实收 / 按费率预期) 以及行情质量 (行情条数、在线率、因陈旧被抑制的信号)。
+//! 评分卡发布到 Redis `scorecard:{exchange}`，并按天持久化到 `scorecard:{exchange}:{YYYY-MM-DD}`；
+//! 已发布的交易所记在集合 `scorecard:index`，加载时按集合读取，不用 KEYS 扫描整个库。
+//!
+//! 路由与风控据此调整: 最近窗口拒单率超过阈值的交易所信号降级排队，
+//! 超过拦截阈值时不再开新仓 (平仓不受影响)；ENGINE_SCORECARD_OVERRIDE 列出的交易所不做调整。
+//! 按腿选择交易所的策略收到 `rank_venues` 的排序: 不再把腿路由到被拦截的交易所，报价相同时优先排序靠前者。
+
+use anyhow::Result;
+use redis::aio::ConnectionManager;
+use redis::AsyncCommands;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+
+use crate::exchange::{ExchangeId, Ticker};
+use crate::executor::{ExecutionResult, OrderSide, OrderStatus};
+use crate::symbols::same_symbol;
+
+/// Redis key 前缀
+const KEY_PREFIX: &str = "scorecard:";
+/// 已发布评分卡的交易所集合
+const INDEX_KEY: &str = "scorecard:index";
+/// 降级交易所的信号优先级惩罚
+pub const DEPRIORITIZED_PENALTY: i32 = 10;
+
+/// 评分卡配置
+#[derive(Debug, Clone)]
+pub struct ScorecardConfig {
+    /// 统计窗口 (秒)
+    pub windows_secs: Vec<i64>,
+    /// 判定拒单率所用的窗口 (秒)
+    pub reject_window_secs: i64,
+    /// 拒单率超过该值时降级
+    pub deprioritize_reject_rate: f64,
+    /// 拒单率超过该值时拦截开仓
+    pub block_reject_rate: f64,
+    /// 窗口内订单数不足时不做判定
+    pub min_orders: u64,
+    /// 不做降级 / 拦截的交易所
+    pub overrides: HashSet<ExchangeId>,
+    /// 预期手续费率 (按交易所，未配置时使用 default_fee_rate)
+    pub fee_rates: HashMap<ExchangeId, f64>,
+    pub default_fee_rate: f64,
+}
+
+impl Default for ScorecardConfig {
+    fn default() -> Self {
+        Self {
+            windows_secs: vec![300, 3_600, 86_400],
+            reject_window_secs: 3_600,
+            deprioritize_reject_rate: 0.2,
+            block_reject_rate: 0.5,
+            min_orders: 10,
+            overrides: HashSet::new(),
+            fee_rates: HashMap::new(),
+            default_fee_rate: 0.001,
+        }
+    }
+}
+
+impl ScorecardConfig {
+    /// 从环境变量读取:
+    /// ENGINE_SCORECARD_WINDOWS (秒，逗号分隔)、ENGINE_SCORECARD_REJECT_WINDOW_SECS、
+    /// ENGINE_SCORECARD_DEPRIORITIZE_REJECT_RATE、ENGINE_SCORECARD_BLOCK_REJECT_RATE、
+    /// ENGINE_SCORECARD_MIN_ORDERS、ENGINE_SCORECARD_OVERRIDE (交易所，逗号分隔)、
+    /// ENGINE_SCORECARD_FEE_RATES (如 binance:0.001,okx:0.0008)
+    pub fn from_env() -> Self {
+        let mut config = Self::default();
+        let env = |name: &str| std::env::var(name).ok().filter(|v| !v.trim().is_empty());
+        if let Some(raw) = env("ENGINE_SCORECARD_WINDOWS") {
+            let windows: Vec<i64> = raw
+                .split(',')
+                .filter_map(|v| v.trim().parse().ok())
+                .filter(|v| *v > 0)
+                .collect();
+            if !windows.is_empty() {
+                config.windows_secs = windows;
+            }
+        }
+        if let Some(v) = env("ENGINE_SCORECARD_REJECT_WINDOW_SECS").and_then(|v| v.parse().ok()) {
+            config.reject_window_secs = v;
+        }
+        if let Some(v) = env("ENGINE_SCORECARD_DEPRIORITIZE_REJECT_RATE").and_then(|v| v.parse().ok()) {
+            config.deprioritize_reject_rate = v;
+        }
+        if let Some(v) = env("ENGINE_SCORECARD_BLOCK_REJECT_RATE").and_then(|v| v.parse().ok()) {
+            config.block_reject_rate = v;
+        }
+        if let Some(v) = env("ENGINE_SCORECARD_MIN_ORDERS").and_then(|v| v.parse().ok()) {
+            config.min_orders = v;
+        }
+        if let Some(raw) = env("ENGINE_SCORECARD_OVERRIDE") {
+            config.overrides = raw.split(',').filter_map(parse_exchange).collect();
+        }
+        if let Some(raw) = env("ENGINE_SCORECARD_FEE_RATES") {
+            for item in raw.split(',') {
+                let Some((exchange, rate)) = item.split_once(':') else {
+                    continue;
+                };
+                if let (Some(exchange), Ok(rate)) = (parse_exchange(exchange), rate.trim().parse()) {
+                    config.fee_rates.insert(exchange, rate);
+                }
+            }
+        }
+        config
+    }
+
+    fn fee_rate(&self, exchange: ExchangeId) -> f64 {
+        self.fee_rates
+            .get(&exchange)
+            .copied()
+            .unwrap_or(self.default_fee_rate)
+    }
+
+    fn max_window_ms(&self) -> i64 {
+        self.windows_secs
+            .iter()
+            .copied()
+            .chain([self.reject_window_secs])
+            .max()
+            .unwrap_or(0)
+            * 1_000
+    }
+}
+
+/// 单笔订单执行事件
+#[derive(Debug, Clone)]
+pub struct ExecutionEvent {
+    pub exchange: ExchangeId,
+    pub timestamp: i64,
+    pub status: OrderStatus,
+    /// 相对决策价的不利滑点 (基点，正值为不利)
+    pub slippage_bps: Option<f64>,
+    pub latency_ms: u64,
+    pub fee: f64,
+    pub expected_fee: f64,
+}
+
+/// 行情质量采样
+#[derive(Debug, Clone, Copy)]
+enum FeedEvent {
+    /// 同一秒内收到的行情条数
+    Ticks(u64),
+    Stale,
+    /// 每秒一次的连接状态采样
+    Connectivity(bool),
+}
+
+/// 路由 / 风控判定
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VenueVerdict {
+    Preferred,
+    Deprioritized,
+    Blocked,
+}
+
+/// 单个窗口的统计
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct WindowStats {
+    pub window_secs: i64,
+    pub orders: u64,
+    pub rejects: u64,
+    pub partial_fills: u64,
+    pub reject_rate: f64,
+    pub partial_fill_rate: f64,
+    pub slippage_bps_avg: Option<f64>,
+    pub slippage_bps_p95: Option<f64>,
+    pub ack_latency_p50_ms: Option<u64>,
+    pub ack_latency_p95_ms: Option<u64>,
+    pub ack_latency_p99_ms: Option<u64>,
+    /// 实收手续费 / 预期手续费
+    pub fee_accuracy: Option<f64>,
+    pub ticks: u64,
+    pub stale_signals: u64,
+    /// 连接在线比例
+    pub uptime: Option<f64>,
+}
+
+/// 单个交易所的评分卡
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VenueScorecard {
+    pub exchange: ExchangeId,
+    pub updated_at: i64,
+    pub verdict: VenueVerdict,
+    pub overridden: bool,
+    pub windows: Vec<WindowStats>,
+}
+
+#[derive(Default)]
+struct VenueHistory {
+    executions: VecDeque<ExecutionEvent>,
+    feed: VecDeque<(i64, FeedEvent)>,
+}
+
+/// 评分卡聚合器
+pub struct Scorecard {
+    config: ScorecardConfig,
+    venues: HashMap<ExchangeId, VenueHistory>,
+}
+
+impl Scorecard {
+    pub fn new(config: ScorecardConfig) -> Self {
+        Self {
+            config,
+            venues: HashMap::new(),
+        }
+    }
+
+    pub fn from_env() -> Self {
+        Self::new(ScorecardConfig::from_env())
+    }
+
+    /// 记录一次执行结果 (decision 为决策时刻的行情快照，用于计算滑点)
+    pub fn record_execution(&mut self, result: &ExecutionResult, decision: &[Ticker], now: i64) {
+        if result.orders.is_empty() {
+            // 没有订单回报 (如 OMS 托管执行) 时只记录成败
+            self.record_event(ExecutionEvent {
+                exchange: result.signal.exchange,
+                timestamp: now,
+                status: if result.success { OrderStatus::Filled } else { OrderStatus::Failed },
+                slippage_bps: None,
+                latency_ms: 0,
+                fee: 0.0,
+                expected_fee: 0.0,
+            });
+            return;
+        }
+        for order in &result.orders {
+            let decision_price = decision
+                .iter()
+                .find(|t| t.exchange == order.exchange && same_symbol(&t.symbol, &order.symbol))
+                .map(|t| match order.side {
+                    OrderSide::Buy => t.ask,
+                    OrderSide::Sell => t.bid,
+                })
+                .filter(|p| *p > 0.0);
+            let slippage_bps = decision_price
+                .filter(|_| order.avg_price > 0.0 && order.filled_amount > 0.0)
+                .map(|decision| {
+                    let diff = (order.avg_price - decision) / decision * 10_000.0;
+                    match order.side {
+                        OrderSide::Buy => diff,
+                        OrderSide::Sell => -diff,
+                    }
+                });
+            self.record_event(ExecutionEvent {
+                exchange: order.exchange,
+                timestamp: now,
+                status: order.status,
+                slippage_bps,
+                latency_ms: order.latency_ms,
+                fee: order.fee,
+                expected_fee: order.filled_amount * order.avg_price * self.config.fee_rate(order.exchange),
+            });
+        }
+    }
+
+    /// 执行失败 (未拿到订单回报) 计为一次拒单
+    pub fn record_failure(&mut self, exchange: ExchangeId, now: i64) {
+        self.record_event(ExecutionEvent {
+            exchange,
+            timestamp: now,
+            status: OrderStatus::Failed,
+            slippage_bps: None,
+            latency_ms: 0,
+            fee: 0.0,
+            expected_fee: 0.0,
+        });
+    }
+
+    pub fn record_event(&mut self, event: ExecutionEvent) {
+        let now = event.timestamp;
+        let history = self.venues.entry(event.exchange).or_default();
+        history.executions.push_back(event);
+        self.prune(now);
+    }
+
+    pub fn record_tick(&mut self, exchange: ExchangeId, now: i64) {
+        self.record_feed(exchange, FeedEvent::Ticks(1), now);
+    }
+
+    pub fn record_stale(&mut self, exchange: ExchangeId, now: i64) {
+        self.record_feed(exchange, FeedEvent::Stale, now);
+    }
+
+    pub fn record_connectivity(&mut self, exchange: ExchangeId, connected: bool, now: i64) {
+        self.record_feed(exchange, FeedEvent::Connectivity(connected), now);
+    }
+
+    fn record_feed(&mut self, exchange: ExchangeId, event: FeedEvent, now: i64) {
+        let history = self.venues.entry(exchange).or_default();
+        // 行情按秒聚合，避免高频行情撑大缓冲
+        if let FeedEvent::Ticks(_) = event {
+            if let Some((ts, FeedEvent::Ticks(count))) = history.feed.back_mut() {
+                if *ts / 1_000 == now / 1_000 {
+                    *count += 1;
+                    return;
+                }
+            }
+        }
+        history.feed.push_back((now, event));
+    }
+
+    /// 丢弃超出最大窗口的事件
+    pub fn prune(&mut self, now: i64) {
+        let cutoff = now - self.config.max_window_ms();
+        for history in self.venues.values_mut() {
+            while history.executions.front().is_some_and(|e| e.timestamp < cutoff) {
+                history.executions.pop_front();
+            }
+            while history.feed.front().is_some_and(|(ts, _)| *ts < cutoff) {
+                history.feed.pop_front();
+            }
+        }
+    }
+
+    fn window_stats(&self, exchange: ExchangeId, window_secs: i64, now: i64) -> WindowStats {
+        let mut stats = WindowStats {
+            window_secs,
+            ..Default::default()
+        };
+        let Some(history) = self.venues.get(&exchange) else {
+            return stats;
+        };
+        let since = now - window_secs * 1_000;
+        let events: Vec<&ExecutionEvent> = history
+            .executions
+            .iter()
+            .filter(|e| e.timestamp >= since)
+            .collect();
+        stats.orders = events.len() as u64;
+        stats.rejects = events
+            .iter()
+            .filter(|e| matches!(e.status, OrderStatus::Failed | OrderStatus::Cancelled))
+            .count() as u64;
+        stats.partial_fills = events
+            .iter()
+            .filter(|e| matches!(e.status, OrderStatus::PartialFilled))
+            .count() as u64;
+        if stats.orders > 0 {
+            stats.reject_rate = stats.rejects as f64 / stats.orders as f64;
+            stats.partial_fill_rate = stats.partial_fills as f64 / stats.orders as f64;
+        }
+
+        let mut slippage: Vec<f64> = events.iter().filter_map(|e| e.slippage_bps).collect();
+        if !slippage.is_empty() {
+            slippage.sort_by(|a, b| a.total_cmp(b));
+            stats.slippage_bps_avg = Some(slippage.iter().sum::<f64>() / slippage.len() as f64);
+            stats.slippage_bps_p95 = Some(percentile(&slippage, 0.95));
+        }
+        let mut latencies: Vec<u64> = events
+            .iter()
+            .filter(|e| e.latency_ms > 0)
+            .map(|e| e.latency_ms)
+            .collect();
+        if !latencies.is_empty() {
+            latencies.sort_unstable();
+            stats.ack_latency_p50_ms = Some(percentile(&latencies, 0.50));
+            stats.ack_latency_p95_ms = Some(percentile(&latencies, 0.95));
+            stats.ack_latency_p99_ms = Some(percentile(&latencies, 0.99));
+        }
+        let expected: f64 = events.iter().map(|e| e.expected_fee).sum();
+        if expected > 0.0 {
+            stats.fee_accuracy = Some(events.iter().map(|e| e.fee).sum::<f64>() / expected);
+        }
+
+        let (mut online, mut samples) = (0u64, 0u64);
+        for (_, event) in history.feed.iter().filter(|(ts, _)| *ts >= since) {
+            match event {
+                FeedEvent::Ticks(count) => stats.ticks += count,
+                FeedEvent::Stale => stats.stale_signals += 1,
+                FeedEvent::Connectivity(connected) => {
+                    samples += 1;
+                    if *connected {
+                        online += 1;
+                    }
+                }
+            }
+        }
+        if samples > 0 {
+            stats.uptime = Some(online as f64 / samples as f64);
+        }
+        stats
+    }
+
+    /// 路由 / 风控判定
+    pub fn verdict(&self, exchange: ExchangeId, now: i64) -> VenueVerdict {
+        if self.config.overrides.contains(&exchange) {
+            return VenueVerdict::Preferred;
+        }
+        let stats = self.window_stats(exchange, self.config.reject_window_secs, now);
+        if stats.orders < self.config.min_orders {
+            return VenueVerdict::Preferred;
+        }
+        if stats.reject_rate > self.config.block_reject_rate {
+            VenueVerdict::Blocked
+        } else if stats.reject_rate > self.config.deprioritize_reject_rate {
+            VenueVerdict::Deprioritized
+        } else {
+            VenueVerdict::Preferred
+        }
+    }
+
+    /// 按偏好排序候选交易所 (附判定): 判定等级优先，其次拒单率、确认延迟
+    pub fn rank_venues(&self, candidates: &[ExchangeId], now: i64) -> Vec<(ExchangeId, VenueVerdict)> {
+        let mut ranked: Vec<(VenueVerdict, f64, u64, ExchangeId)> = candidates
+            .iter()
+            .map(|&exchange| {
+                let stats = self.window_stats(exchange, self.config.reject_window_secs, now);
+                (
+                    self.verdict(exchange, now),
+                    stats.reject_rate,
+                    stats.ack_latency_p50_ms.unwrap_or(0),
+                    exchange,
+                )
+            })
+            .collect();
+        ranked.sort_by(|a, b| {
+            verdict_rank(a.0)
+                .cmp(&verdict_rank(b.0))
+                .then(a.1.total_cmp(&b.1))
+                .then(a.2.cmp(&b.2))
+        });
+        ranked
+            .into_iter()
+            .map(|(verdict, _, _, exchange)| (exchange, verdict))
+            .collect()
+    }
+
+    /// 有执行或行情记录的交易所
+    pub fn venues(&self) -> Vec<ExchangeId> {
+        self.venues.keys().copied().collect()
+    }
+
+    /// 当前被拦截开仓的交易所
+    pub fn blocked_venues(&self, now: i64) -> Vec<ExchangeId> {
+        self.venues
+            .keys()
+            .copied()
+            .filter(|&exchange| self.verdict(exchange, now) == VenueVerdict::Blocked)
+            .collect()
+    }
+
+    /// 所有交易所的评分卡
+    pub fn snapshot(&self, now: i64) -> Vec<VenueScorecard> {
+        let mut out: Vec<VenueScorecard> = self
+            .venues
+            .keys()
+            .map(|&exchange| VenueScorecard {
+                exchange,
+                updated_at: now,
+                verdict: self.verdict(exchange, now),
+                overridden: self.config.overrides.contains(&exchange),
+                windows: self
+                    .config
+                    .windows_secs
+                    .iter()
+                    .map(|w| self.window_stats(exchange, *w, now))
+                    .collect(),
+            })
+            .collect();
+        out.sort_by_key(|card| card.exchange.key());
+        out
+    }
+
+    /// 发布到 `scorecard:{exchange}`，写入当日快照 `scorecard:{exchange}:{YYYY-MM-DD}`，并登记到 `scorecard:index`
+    pub async fn publish(&self, conn: &mut ConnectionManager, now: i64) -> Result<()> {
+        let day = chrono::DateTime::from_timestamp_millis(now)
+            .map(|t| t.format("%Y-%m-%d").to_string())
+            .unwrap_or_default();
+        for card in self.snapshot(now) {
+            let key = format!("{}{}", KEY_PREFIX, card.exchange.key());
+            let json = serde_json::to_string(&card)?;
+            let _: () = conn.set(&key, &json).await?;
+            let _: () = conn.set(format!("{}:{}", key, day), &json).await?;
+            let _: () = conn.sadd(INDEX_KEY, card.exchange.key()).await?;
+        }
+        Ok(())
+    }
+}
+
+fn verdict_rank(verdict: VenueVerdict) -> u8 {
+    match verdict {
+        VenueVerdict::Preferred => 0,
+        VenueVerdict::Deprioritized => 1,
+        VenueVerdict::Blocked => 2,
+    }
+}
+
+fn percentile<T: Copy>(sorted: &[T], q: f64) -> T {
+    let idx = ((sorted.len() as f64 * q).ceil() as usize).clamp(1, sorted.len()) - 1;
+    sorted[idx]
+}
+
+fn parse_exchange(value: &str) -> Option<ExchangeId> {
+    serde_json::from_value(serde_json::Value::String(value.trim().to_lowercase())).ok()
+}
+
+/// 读取 `scorecard:index` 登记的各交易所最新评分卡 (不含按天快照)
+pub async fn load_scorecards(redis: &redis::Client) -> Result<Vec<VenueScorecard>> {
+    let mut conn = redis.get_multiplexed_async_connection().await?;
+    let exchanges: Vec<String> = conn.smembers(INDEX_KEY).await?;
+    let mut out = vec![];
+    for exchange in exchanges {
+        let raw: Option<String> = conn.get(format!("{}{}", KEY_PREFIX, exchange)).await?;
+        if let Some(card) = raw.and_then(|r| serde_json::from_str::<VenueScorecard>(&r).ok()) {
+            out.push(card);
+        }
+    }
+    out.sort_by_key(|card| card.exchange.key());
+    Ok(out)
+}
+
+/// CLI: 打印评分卡
+pub async fn dump(redis: &redis::Client) -> Result<()> {
+    let cards = load_scorecards(redis).await?;
+    if cards.is_empty() {
+        println!("no venue scorecards found");
+        return Ok(());
+    }
+    let fmt_f = |v: Option<f64>, scale: f64| v.map(|v| format!("{:.2}", v * scale)).unwrap_or_else(|| "-".to_string());
+    let fmt_u = |v: Option<u64>| v.map(|v| v.to_string()).unwrap_or_else(|| "-".to_string());
+    for card in cards {
+        let updated = chrono::DateTime::from_timestamp_millis(card.updated_at)
+            .map(|t| t.format("%Y-%m-%d %H:%M:%S").to_string())
+            .unwrap_or_default();
+        println!(
+            "== {} ({:?}{}, updated {}) ==",
+            card.exchange.key(),
+            card.verdict,
+            if card.overridden { ", override" } else { "" },
+            updated
+        );
+        println!(
+            "{:>8} {:>7} {:>8} {:>9} {:>9} {:>9} {:>8} {:>8} {:>8} {:>8} {:>8} {:>7}",
+            "window", "orders", "reject%", "partial%", "slip_bps", "slip_p95", "lat_p50", "lat_p95", "lat_p99",
+            "fee_acc", "ticks", "uptime%"
+        );
+        let mut rows: BTreeMap<i64, WindowStats> = BTreeMap::new();
+        for stats in card.windows {
+            rows.insert(stats.window_secs, stats);
+        }
+        for (window, s) in rows {
+            println!(
+                "{:>7}s {:>7} {:>8.2} {:>9.2} {:>9} {:>9} {:>8} {:>8} {:>8} {:>8} {:>8} {:>7}",
+                window,
+                s.orders,
+                s.reject_rate * 100.0,
+                s.partial_fill_rate * 100.0,
+                fmt_f(s.slippage_bps_avg, 1.0),
+                fmt_f(s.slippage_bps_p95, 1.0),
+                fmt_u(s.ack_latency_p50_ms),
+                fmt_u(s.ack_latency_p95_ms),
+                fmt_u(s.ack_latency_p99_ms),
+                fmt_f(s.fee_accuracy, 1.0),
+                s.ticks,
+                fmt_f(s.uptime, 100.0),
+            );
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fake_redis::FakeRedis;
+    use crate::exchange::testing::ticker;
+    use crate::executor::OrderResponse;
+    use crate::strategy::{Signal, StrategyType};
+
+    const NOW: i64 = 1_700_000_000_000;
+
+    /// 单笔买单的执行结果 (成交 1 BTC，手续费为预期的两倍)
+    fn execution(exchange: ExchangeId, status: OrderStatus, avg_price: f64, latency_ms: u64) -> ExecutionResult {
+        let filled = if matches!(status, OrderStatus::Failed) { 0.0 } else { 1.0 };
+        let signal = Signal::new("s1", StrategyType::Grid, exchange, 0.01, 1.0, 0.9, "BTC/USDT", NOW);
+        let order = OrderResponse {
+            order_id: "o1".to_string(),
+            exchange,
+            symbol: "BTC/USDT".to_string(),
+            side: OrderSide::Buy,
+            status,
+            filled_amount: filled,
+            avg_price,
+            fee: filled * avg_price * 0.002,
+            latency_ms,
+            other_fees: vec![],
+        };
+        ExecutionResult::from_orders(signal, vec![order], 0.0, false)
+    }
+
+    fn scorecard() -> Scorecard {
+        Scorecard::new(ScorecardConfig {
+            min_orders: 4,
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn execution_stats_roll_up_into_verdict_and_ranking() {
+        let mut card = scorecard();
+        let decision = [ticker("BTC/USDT").exchange(ExchangeId::Binance).quote(99.9, 100.0).at(NOW).build(), ticker("BTC/USDT").exchange(ExchangeId::Okx).quote(99.9, 100.0).at(NOW).build()];
+        for status in [OrderStatus::Filled, OrderStatus::Filled, OrderStatus::PartialFilled, OrderStatus::Failed] {
+            card.record_execution(&execution(ExchangeId::Okx, status, 100.1, 40), &decision, NOW);
+        }
+        for _ in 0..4 {
+            card.record_execution(&execution(ExchangeId::Binance, OrderStatus::Filled, 100.0, 20), &decision, NOW);
+        }
+
+        let okx = card.window_stats(ExchangeId::Okx, 3_600, NOW);
+        assert_eq!((okx.orders, okx.rejects, okx.partial_fills), (4, 1, 1));
+        assert_eq!(okx.reject_rate, 0.25);
+        assert_eq!(okx.partial_fill_rate, 0.25);
+        // 买入均价 100.1 相对决策时的卖一 100 不利 10 个基点
+        assert!((okx.slippage_bps_avg.unwrap() - 10.0).abs() < 1e-6);
+        assert_eq!(okx.ack_latency_p50_ms, Some(40));
+        assert!((okx.fee_accuracy.unwrap() - 2.0).abs() < 1e-9);
+        let binance = card.window_stats(ExchangeId::Binance, 3_600, NOW);
+        assert_eq!(binance.reject_rate, 0.0);
+        assert_eq!(binance.slippage_bps_avg, Some(0.0));
+
+        assert_eq!(card.verdict(ExchangeId::Okx, NOW), VenueVerdict::Deprioritized);
+        assert_eq!(card.verdict(ExchangeId::Binance, NOW), VenueVerdict::Preferred);
+        let venues = [ExchangeId::Okx, ExchangeId::Binance];
+        assert_eq!(
+            card.rank_venues(&venues, NOW),
+            vec![(ExchangeId::Binance, VenueVerdict::Preferred), (ExchangeId::Okx, VenueVerdict::Deprioritized)]
+        );
+
+        // Binance 连续拒单后被拦截，排序翻转
+        for _ in 0..6 {
+            card.record_failure(ExchangeId::Binance, NOW + 1_000);
+        }
+        assert_eq!(card.verdict(ExchangeId::Binance, NOW + 1_000), VenueVerdict::Blocked);
+        assert_eq!(
+            card.rank_venues(&venues, NOW + 1_000),
+            vec![(ExchangeId::Okx, VenueVerdict::Deprioritized), (ExchangeId::Binance, VenueVerdict::Blocked)]
+        );
+        assert_eq!(card.blocked_venues(NOW + 1_000), vec![ExchangeId::Binance]);
+
+        // 拒单移出判定窗口后恢复
+        assert_eq!(card.verdict(ExchangeId::Binance, NOW + 3_602_000), VenueVerdict::Preferred);
+    }
+
+    #[tokio::test]
+    async fn published_scorecards_load_through_the_index_without_keys() {
+        let redis = FakeRedis::start().await;
+        let mut card = scorecard();
+        card.record_failure(ExchangeId::Okx, NOW);
+        card.record_failure(ExchangeId::Binance, NOW);
+        card.publish(&mut redis.connection().await, NOW).await.unwrap();
+
+        let cards = load_scorecards(&redis.client()).await.unwrap();
+        let exchanges: Vec<ExchangeId> = cards.iter().map(|c| c.exchange).collect();
+        assert_eq!(exchanges, vec![ExchangeId::Binance, ExchangeId::Okx]);
+        assert!(redis.get("scorecard:okx:2023-11-14").is_some(), "按天快照照常写入");
+        assert!(redis.commands().iter().all(|c| !c[0].eq_ignore_ascii_case("KEYS")));
+    }
+
+    #[test]
+    fn too_few_orders_or_override_keep_venue_preferred() {
+        let mut card = scorecard();
+        for _ in 0..3 {
+            card.record_failure(ExchangeId::Okx, NOW);
+        }
+        assert_eq!(card.verdict(ExchangeId::Okx, NOW), VenueVerdict::Preferred);
+
+        let mut card = Scorecard::new(ScorecardConfig {
+            min_orders: 4,
+            overrides: HashSet::from([ExchangeId::Okx]),
+            ..Default::default()
+        });
+        for _ in 0..4 {
+            card.record_failure(ExchangeId::Okx, NOW);
+        }
+        assert_eq!(card.verdict(ExchangeId::Okx, NOW), VenueVerdict::Preferred);
+        assert!(card.snapshot(NOW)[0].overridden);
+    }
+}
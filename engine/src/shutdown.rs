@@ -0,0 +1,185 @@
+//! 停机原因
+//!
+//! 引擎停止的原因 (收到停止信号 / 运行中致命错误 / 配置错误 / 正常结束) 统一在退出前记录一次:
+//! 写日志、写入 Redis `engine:shutdown` (JSON)，ENGINE_SHUTDOWN_REPORT_FILE 指定时同时写入文件，
+//! 并映射为进程退出码，供编排系统与运维判断是否需要重启:
+//! 信号 / 正常结束为 0，致命错误为 1 (建议重启)，配置错误为 78 (重启无效，需先修正配置)。
+
+use anyhow::Result;
+use redis::AsyncCommands;
+use serde::Serialize;
+use std::fmt;
+use std::time::Duration;
+use tracing::{error, info};
+
+/// Redis key
+const REDIS_KEY: &str = "engine:shutdown";
+/// 配置错误退出码 (sysexits EX_CONFIG)
+const EXIT_CONFIG: u8 = 78;
+/// 写入 Redis 的超时 (Redis 不可用时不阻塞退出)
+const PUBLISH_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// 配置错误标记: 用 `.map_err(config_error)` 包装后，停机原因记为配置错误而非致命错误
+#[derive(Debug)]
+pub struct ConfigError(pub String);
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+/// 把错误标记为配置错误
+pub fn config_error(err: anyhow::Error) -> anyhow::Error {
+    anyhow::Error::new(ConfigError(format!("{:#}", err)))
+}
+
+/// 停机原因
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "reason", content = "message", rename_all = "snake_case")]
+pub enum ShutdownReason {
+    /// 收到停止信号
+    Signal,
+    /// 正常结束 (子命令完成 / 引擎主循环自行返回)
+    Completed,
+    /// 运行中致命错误 (策略引擎出错、启动对账失败等)
+    FatalError(String),
+    /// 配置错误
+    ConfigError(String),
+}
+
+impl ShutdownReason {
+    /// 按错误类型归类
+    pub fn from_error(err: &anyhow::Error) -> Self {
+        match err.downcast_ref::<ConfigError>() {
+            Some(config) => Self::ConfigError(config.0.clone()),
+            None => Self::FatalError(format!("{:#}", err)),
+        }
+    }
+
+    /// 引擎主循环的返回结果
+    pub fn from_engine_result(res: Result<()>) -> Self {
+        match res {
+            Ok(()) => Self::Completed,
+            Err(err) => Self::from_error(&err),
+        }
+    }
+
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            Self::Signal | Self::Completed => 0,
+            Self::FatalError(_) => 1,
+            Self::ConfigError(_) => EXIT_CONFIG,
+        }
+    }
+
+    /// 是否建议编排系统重启 (配置错误重启也无济于事)
+    pub fn restart_recommended(&self) -> bool {
+        matches!(self, Self::FatalError(_))
+    }
+}
+
+/// 停机报告
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ShutdownReport {
+    #[serde(flatten)]
+    pub reason: ShutdownReason,
+    pub exit_code: u8,
+    pub restart_recommended: bool,
+    pub timestamp: i64,
+}
+
+impl ShutdownReport {
+    pub fn new(reason: ShutdownReason) -> Self {
+        Self {
+            exit_code: reason.exit_code(),
+            restart_recommended: reason.restart_recommended(),
+            reason,
+            timestamp: chrono::Utc::now().timestamp_millis(),
+        }
+    }
+}
+
+/// 记录停机原因 (日志 / Redis / 文件)，返回进程退出码
+pub async fn record(reason: ShutdownReason, redis: Option<&redis::Client>) -> u8 {
+    let report = ShutdownReport::new(reason);
+    match &report.reason {
+        ShutdownReason::Signal | ShutdownReason::Completed => {
+            info!("引擎停止: {:?} (退出码 {})", report.reason, report.exit_code)
+        }
+        reason => error!(
+            "引擎停止: {:?} (退出码 {}，{}重启)",
+            reason,
+            report.exit_code,
+            if report.restart_recommended { "建议" } else { "不建议" }
+        ),
+    }
+    let payload = match serde_json::to_string(&report) {
+        Ok(payload) => payload,
+        Err(_) => return report.exit_code,
+    };
+    if let Some(redis) = redis {
+        match tokio::time::timeout(PUBLISH_TIMEOUT, publish(redis, &payload)).await {
+            Ok(Ok(())) => {}
+            Ok(Err(err)) => error!("停机原因写入 Redis 失败: {}", err),
+            Err(_) => error!("停机原因写入 Redis 超时"),
+        }
+    }
+    if let Ok(path) = std::env::var("ENGINE_SHUTDOWN_REPORT_FILE") {
+        if !path.is_empty() {
+            if let Err(err) = std::fs::write(&path, &payload) {
+                error!("停机原因写入 {} 失败: {}", path, err);
+            }
+        }
+    }
+    report.exit_code
+}
+
+async fn publish(redis: &redis::Client, payload: &str) -> Result<()> {
+    let mut conn = redis.get_multiplexed_async_connection().await?;
+    let _: () = conn.set(REDIS_KEY, payload).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fake_redis::FakeRedis;
+    use anyhow::Context;
+
+    #[test]
+    fn reasons_map_to_exit_codes() {
+        assert_eq!(ShutdownReason::Signal.exit_code(), 0);
+        assert_eq!(ShutdownReason::from_engine_result(Ok(())), ShutdownReason::Completed);
+        assert_eq!(ShutdownReason::Completed.exit_code(), 0);
+
+        let fatal = ShutdownReason::from_engine_result(Err(anyhow::anyhow!("对账失败")));
+        assert_eq!(fatal, ShutdownReason::FatalError("对账失败".to_string()));
+        assert_eq!(fatal.exit_code(), 1);
+        assert!(fatal.restart_recommended());
+
+        // 外层附加上下文后仍能识别为配置错误
+        let err = Err::<(), _>(config_error(anyhow::anyhow!("缺少 DATABASE_URL")))
+            .context("启动失败")
+            .unwrap_err();
+        let config = ShutdownReason::from_error(&err);
+        assert_eq!(config, ShutdownReason::ConfigError("缺少 DATABASE_URL".to_string()));
+        assert_eq!(config.exit_code(), 78);
+        assert!(!config.restart_recommended());
+    }
+
+    #[tokio::test]
+    async fn record_publishes_the_report_and_returns_the_exit_code() {
+        let redis = FakeRedis::start().await;
+        let code = record(ShutdownReason::ConfigError("bad".to_string()), Some(&redis.client())).await;
+        assert_eq!(code, 78);
+        let report: serde_json::Value = serde_json::from_str(&redis.get(REDIS_KEY).unwrap()).unwrap();
+        assert_eq!(report["reason"], "config_error");
+        assert_eq!(report["message"], "bad");
+        assert_eq!(report["exitCode"], 78);
+        assert_eq!(report["restartRecommended"], false);
+    }
+}
@@ -0,0 +1,374 @@
+//! 保护性止损单
+//!
+//! 方向性执行 (网格 / 配对 / 资金费率) 成交后自动为每条腿挂止损:
+//! 交易所支持原生止损单时使用原生类型 (Binance STOP_LOSS_LIMIT、OKX conditional)，
+//! 否则由引擎侧监视器根据行情触发。止损单关联到父仓位，平仓成交后只撤销成交覆盖的交易对与数量。
+
+use serde::Serialize;
+
+use crate::exchange::{ExchangeId, Ticker};
+use crate::executor::{OrderResponse, OrderSide, OrderStatus};
+use crate::strategy::Signal;
+use crate::symbols::denormalize_symbol;
+
+/// 原生止损限价单相对触发价的让价 (保证触发后能成交)
+const STOP_LIMIT_SLIPPAGE: f64 = 0.002;
+
+/// 平仓成交量与止损数量的相对容差 (手续费以基础币扣除时平仓量略小于开仓量)
+const AMOUNT_TOLERANCE: f64 = 0.01;
+
+/// 策略止损配置 (`StrategyConfig.config["stop_loss"]`)
+#[derive(Debug, Clone)]
+pub struct StopConfig {
+    pub enabled: bool,
+    /// 信号未给出止损价时使用的默认止损比例
+    pub default_pct: f64,
+}
+
+impl Default for StopConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            default_pct: 0.02,
+        }
+    }
+}
+
+impl StopConfig {
+    /// 从策略参数解析，缺省时启用并使用 2% 止损
+    pub fn from_config(config: &serde_json::Value) -> Self {
+        let mut cfg = Self::default();
+        if let Some(raw) = config.get("stop_loss") {
+            if let Some(enabled) = raw.get("enabled").and_then(|v| v.as_bool()) {
+                cfg.enabled = enabled;
+            }
+            if let Some(pct) = raw.get("default_pct").and_then(|v| v.as_f64()) {
+                if pct > 0.0 && pct < 1.0 {
+                    cfg.default_pct = pct;
+                }
+            }
+        }
+        cfg
+    }
+}
+
+/// 止损单的托管方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum StopPlacement {
+    /// 交易所原生止损单
+    Native,
+    /// 引擎侧监视器
+    Watcher,
+}
+
+/// 止损单
+#[derive(Debug, Clone, Serialize)]
+pub struct StopOrder {
+    pub id: String,
+    /// 父仓位 (开仓信号的关联 ID)
+    pub position_id: String,
+    pub strategy_id: String,
+    pub exchange: ExchangeId,
+    pub symbol: String,
+    /// 平仓方向 (多头仓位为 Sell)
+    pub side: OrderSide,
+    pub amount: f64,
+    pub entry_price: f64,
+    pub stop_price: f64,
+    pub placement: StopPlacement,
+    /// 原生止损单在交易所的订单 ID
+    pub exchange_order_id: Option<String>,
+}
+
+impl StopOrder {
+    /// 止损触发后的已实现盈亏
+    pub fn realized_pnl(&self, exit_price: f64) -> f64 {
+        match self.side {
+            OrderSide::Sell => (exit_price - self.entry_price) * self.amount,
+            OrderSide::Buy => (self.entry_price - exit_price) * self.amount,
+        }
+    }
+
+    /// 当前行情是否触发止损
+    fn is_triggered(&self, ticker: &Ticker) -> bool {
+        match self.side {
+            // 多头止损: 买一价跌破止损价
+            OrderSide::Sell => ticker.bid > 0.0 && ticker.bid <= self.stop_price,
+            // 空头止损: 卖一价涨破止损价
+            OrderSide::Buy => ticker.ask > 0.0 && ticker.ask >= self.stop_price,
+        }
+    }
+}
+
+/// 交易所是否支持原生止损单
+pub fn supports_native_stop(exchange: ExchangeId) -> bool {
+    matches!(exchange, ExchangeId::Binance | ExchangeId::Okx)
+}
+
+/// 将止损单翻译为交易所原生下单参数
+pub fn native_stop_params(stop: &StopOrder) -> Option<serde_json::Value> {
+    let limit_price = match stop.side {
+        OrderSide::Sell => stop.stop_price * (1.0 - STOP_LIMIT_SLIPPAGE),
+        OrderSide::Buy => stop.stop_price * (1.0 + STOP_LIMIT_SLIPPAGE),
+    };
+    match stop.exchange {
+        ExchangeId::Binance => Some(serde_json::json!({
+            "symbol": denormalize_symbol(ExchangeId::Binance, &stop.symbol),
+            "side": match stop.side { OrderSide::Buy => "BUY", OrderSide::Sell => "SELL" },
+            "type": "STOP_LOSS_LIMIT",
+            "timeInForce": "GTC",
+            "quantity": stop.amount.to_string(),
+            "stopPrice": stop.stop_price.to_string(),
+            "price": limit_price.to_string(),
+            "newClientOrderId": stop.id,
+        })),
+        ExchangeId::Okx => Some(serde_json::json!({
+            "instId": denormalize_symbol(ExchangeId::Okx, &stop.symbol),
+            "tdMode": "cash",
+            "side": match stop.side { OrderSide::Buy => "buy", OrderSide::Sell => "sell" },
+            "ordType": "conditional",
+            "sz": stop.amount.to_string(),
+            "slTriggerPx": stop.stop_price.to_string(),
+            // -1 表示触发后按市价成交
+            "slOrdPx": "-1",
+            "algoClOrdId": stop.id.replace('-', ""),
+        })),
+        _ => None,
+    }
+}
+
+/// 止损单管理器: 关联父仓位、监视器触发 (按登记先后保存)
+#[derive(Default)]
+pub struct StopManager {
+    stops: Vec<StopOrder>,
+}
+
+impl StopManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 为开仓执行的每条成交腿生成止损单；信号的止损价提示只对单一交易对的信号生效，
+    /// 多交易对信号 (如配对) 的各腿价格不同，按各自成交价取默认比例
+    pub fn build_stops(signal: &Signal, orders: &[OrderResponse], config: &StopConfig) -> Vec<StopOrder> {
+        let single_symbol = signal.legs.iter().all(|leg| leg.symbol == signal.legs[0].symbol);
+        let hint = signal.stop_price.filter(|p| *p > 0.0 && single_symbol);
+        orders
+            .iter()
+            .filter(|o| matches!(o.status, OrderStatus::Filled | OrderStatus::PartialFilled))
+            .filter(|o| o.filled_amount > 0.0 && o.avg_price > 0.0)
+            .map(|o| {
+                let side = match o.side {
+                    OrderSide::Buy => OrderSide::Sell,
+                    OrderSide::Sell => OrderSide::Buy,
+                };
+                let stop_price = hint.unwrap_or(match side {
+                    OrderSide::Sell => o.avg_price * (1.0 - config.default_pct),
+                    OrderSide::Buy => o.avg_price * (1.0 + config.default_pct),
+                });
+                StopOrder {
+                    id: uuid::Uuid::new_v4().to_string(),
+                    position_id: signal.correlation_id.clone(),
+                    strategy_id: signal.strategy_id.clone(),
+                    exchange: o.exchange,
+                    symbol: o.symbol.clone(),
+                    side,
+                    amount: o.filled_amount,
+                    entry_price: o.avg_price,
+                    stop_price,
+                    placement: StopPlacement::Watcher,
+                    exchange_order_id: None,
+                }
+            })
+            .collect()
+    }
+
+    /// 登记止损单
+    pub fn register(&mut self, stop: StopOrder) {
+        self.stops.push(stop);
+    }
+
+    /// 平仓成交后撤销被平掉的止损单: 同一策略、交易所、交易对且方向与平仓成交一致，
+    /// 按平仓成交量逐笔撤销 (数量与成交量相同的优先，其余按登记先后)，返回被撤销的止损单
+    pub fn cancel_for_fills(&mut self, strategy_id: &str, orders: &[OrderResponse]) -> Vec<StopOrder> {
+        let mut cancelled = vec![];
+        for order in orders.iter().filter(|o| o.filled_amount > 0.0) {
+            let mut candidates: Vec<&StopOrder> = self
+                .stops
+                .iter()
+                .filter(|s| s.strategy_id == strategy_id && s.exchange == order.exchange)
+                .filter(|s| s.symbol == order.symbol && s.side == order.side)
+                .collect();
+            // 稳定排序: 数量相同的排在前面，其余保持登记先后
+            candidates.sort_by_key(|s| !amount_matches(s.amount, order.filled_amount));
+            let mut remaining = order.filled_amount;
+            let mut ids = vec![];
+            for stop in candidates {
+                if !amount_matches(stop.amount, remaining) && stop.amount > remaining {
+                    continue;
+                }
+                remaining -= stop.amount;
+                ids.push(stop.id.clone());
+                if remaining <= order.filled_amount * AMOUNT_TOLERANCE {
+                    break;
+                }
+            }
+            cancelled.extend(self.take(&ids));
+        }
+        cancelled
+    }
+
+    /// 撤销所有止损单 (紧急平仓时调用)
+    pub fn cancel_all(&mut self) -> Vec<StopOrder> {
+        std::mem::take(&mut self.stops)
+    }
+
+    /// 监视器: 根据最新行情触发引擎侧止损，返回已触发 (并移除) 的止损单
+    pub fn on_ticker(&mut self, ticker: &Ticker) -> Vec<StopOrder> {
+        let ids: Vec<String> = self
+            .stops
+            .iter()
+            .filter(|s| s.placement == StopPlacement::Watcher)
+            .filter(|s| s.exchange == ticker.exchange && s.symbol == ticker.symbol)
+            .filter(|s| s.is_triggered(ticker))
+            .map(|s| s.id.clone())
+            .collect();
+        self.take(&ids)
+    }
+
+    /// 按 ID 移除并返回止损单
+    fn take(&mut self, ids: &[String]) -> Vec<StopOrder> {
+        let (taken, kept) = std::mem::take(&mut self.stops).into_iter().partition(|s| ids.contains(&s.id));
+        self.stops = kept;
+        taken
+    }
+}
+
+/// 数量在容差内是否相同
+fn amount_matches(stop_amount: f64, filled: f64) -> bool {
+    (stop_amount - filled).abs() <= stop_amount * AMOUNT_TOLERANCE
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exchange::testing::ticker;
+    use crate::strategy::{SignalLeg, StrategyType};
+
+    fn fill(side: OrderSide) -> OrderResponse {
+        OrderResponse {
+            order_id: "o1".to_string(),
+            exchange: ExchangeId::Binance,
+            symbol: "BTC/USDT".to_string(),
+            side,
+            status: OrderStatus::Filled,
+            filled_amount: 0.5,
+            avg_price: 100.0,
+            fee: 0.0,
+            latency_ms: 10,
+            other_fees: vec![],
+        }
+    }
+
+    fn stops_for(side: OrderSide, stop_price: Option<f64>) -> Vec<StopOrder> {
+        let mut signal = Signal::new("grid-1", StrategyType::Grid, ExchangeId::Binance, 0.01, 1.0, 0.9, "BTC/USDT", 0);
+        signal.stop_price = stop_price;
+        StopManager::build_stops(&signal, &[fill(side)], &StopConfig::default())
+    }
+
+    #[test]
+    fn watcher_fires_when_price_crosses_the_stop() {
+        let mut manager = StopManager::new();
+        let stop = stops_for(OrderSide::Buy, None).remove(0);
+        // 多头仓位: 卖出止损，默认 2% 止损价
+        assert_eq!(stop.side, OrderSide::Sell);
+        assert!((stop.stop_price - 98.0).abs() < 1e-9);
+        manager.register(stop);
+
+        assert!(manager.on_ticker(&ticker("BTC/USDT").quote(98.5, 98.6).build()).is_empty());
+        let fired = manager.on_ticker(&ticker("BTC/USDT").quote(97.9, 98.0).build());
+        assert_eq!(fired.len(), 1);
+        assert_eq!(fired[0].amount, 0.5);
+        assert!((fired[0].realized_pnl(97.9) + 1.05).abs() < 1e-9);
+        // 触发后移除，不会重复平仓
+        assert!(manager.on_ticker(&ticker("BTC/USDT").quote(97.0, 97.1).build()).is_empty());
+    }
+
+    #[test]
+    fn short_stop_uses_the_ask_and_signal_hint() {
+        let mut manager = StopManager::new();
+        let stop = stops_for(OrderSide::Sell, Some(101.0)).remove(0);
+        assert_eq!(stop.side, OrderSide::Buy);
+        assert_eq!(stop.stop_price, 101.0);
+        manager.register(stop);
+        // 空头止损看卖一: 卖一未到止损价不触发
+        assert!(manager.on_ticker(&ticker("BTC/USDT").quote(100.8, 100.95).build()).is_empty());
+        assert_eq!(manager.on_ticker(&ticker("BTC/USDT").quote(100.9, 101.0).build()).len(), 1);
+    }
+
+    #[test]
+    fn native_stops_are_left_to_the_exchange() {
+        let mut manager = StopManager::new();
+        let mut stop = stops_for(OrderSide::Buy, None).remove(0);
+        stop.placement = StopPlacement::Native;
+        manager.register(stop);
+        assert!(manager.on_ticker(&ticker("BTC/USDT").quote(90.0, 90.1).build()).is_empty());
+        assert_eq!(manager.cancel_for_fills("grid-1", &[fill(OrderSide::Sell)]).len(), 1);
+    }
+
+    #[test]
+    fn close_cancels_only_the_stops_its_fill_covers() {
+        let mut manager = StopManager::new();
+        // 两格 BTC 多头 (0.5 与 0.3) 与一笔 ETH 多头
+        let first = stops_for(OrderSide::Buy, None).remove(0);
+        let mut second = stops_for(OrderSide::Buy, None).remove(0);
+        second.amount = 0.3;
+        let mut eth = stops_for(OrderSide::Buy, None).remove(0);
+        eth.symbol = "ETH/USDT".to_string();
+        let second_id = second.id.clone();
+        for stop in [first, second, eth] {
+            manager.register(stop);
+        }
+
+        // 卖出 0.3 BTC 只平掉数量相同的那一格
+        let close = OrderResponse {
+            filled_amount: 0.3,
+            ..fill(OrderSide::Sell)
+        };
+        let cancelled = manager.cancel_for_fills("grid-1", &[close]);
+        assert_eq!(cancelled.iter().map(|s| s.id.clone()).collect::<Vec<_>>(), vec![second_id]);
+        // 其他策略与买入成交不撤销
+        assert!(manager.cancel_for_fills("grid-2", &[fill(OrderSide::Sell)]).is_empty());
+        assert!(manager.cancel_for_fills("grid-1", &[fill(OrderSide::Buy)]).is_empty());
+
+        // 剩余 BTC 一格平仓后只留下 ETH 的止损
+        assert_eq!(manager.cancel_for_fills("grid-1", &[fill(OrderSide::Sell)]).len(), 1);
+        let remaining = manager.cancel_all();
+        assert_eq!(remaining.len(), 1);
+        assert_eq!(remaining[0].symbol, "ETH/USDT");
+    }
+
+    #[test]
+    fn stop_hint_is_ignored_for_multi_symbol_signals() {
+        let mut signal = Signal::new("pair-1", StrategyType::Pair, ExchangeId::Binance, 0.01, 1.0, 0.9, "BTC/USDT->ETH/USDT", 0);
+        signal.stop_price = Some(101.0);
+        signal.legs = ["BTC/USDT", "ETH/USDT"]
+            .into_iter()
+            .map(|symbol| SignalLeg {
+                exchange: ExchangeId::Binance,
+                symbol: symbol.to_string(),
+                side: OrderSide::Buy,
+                price: 100.0,
+            })
+            .collect();
+        let eth = OrderResponse {
+            symbol: "ETH/USDT".to_string(),
+            avg_price: 10.0,
+            ..fill(OrderSide::Buy)
+        };
+        let stops = StopManager::build_stops(&signal, &[fill(OrderSide::Buy), eth], &StopConfig::default());
+        // 每条腿按自身成交价取默认 2% 止损
+        assert!((stops[0].stop_price - 98.0).abs() < 1e-9);
+        assert!((stops[1].stop_price - 9.8).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,337 @@
+//! 资金费率 (期现) 套利策略
+//!
+//! 现货做多 + 永续做空，赚取正资金费率。永续合约行情使用 `BTC/USDT:USDT` 形式的交易对，
+//! 现货为 `BTC/USDT`。净年化 = 资金费年化 - 基差 - 开平仓手续费 - 现货/合约账户划转成本，
+//! 一次性成本按预计持仓天数摊销。划转成本按交易所配置 (部分交易所内部划转免费)。
+//!
+//! 同一币种常有 USDT 与 USDC 两种保证金的永续合约，资金费率各自独立，因此按完整合约
+//! (币种 + 保证金币种) 分别跟踪，同一币种在多个合约间选净年化最高者发信号。
+//!
+//! 资金费率来自连接的定期轮询 (`on_funding_rate`)；轮询结果带标记价格时同时作为合约价格，
+//! 未订阅永续合约行情时也能计算基差。
+
+use async_trait::async_trait;
+use std::collections::{BTreeMap, HashMap};
+
+use super::{config_f64, config_str_list};
+use crate::exchange::{ExchangeId, FundingRateUpdate, Symbol, Ticker};
+use crate::funding::contract_key;
+use crate::executor::OrderSide;
+use crate::strategy::{Signal, SignalLeg, Strategy, StrategyConfig, StrategyType};
+
+/// 同一币种两次信号的最小间隔 (毫秒)
+const SIGNAL_COOLDOWN_MS: i64 = 60_000;
+
+/// 某币种净年化最高的期现组合
+struct CarryQuote {
+    net_apr: f64,
+    spot_symbol: String,
+    spot: f64,
+    contract: String,
+    perp: f64,
+}
+
+/// 资金费率套利策略
+pub struct FundingRateStrategy {
+    id: String,
+    /// 单期资金费率下限
+    min_funding_rate: f64,
+    /// 净年化下限
+    min_apr: f64,
+    taker_fee: f64,
+    /// 资金费结算间隔 (小时)
+    funding_interval_hours: f64,
+    /// 预计持仓天数 (摊销一次性成本)
+    hold_days: f64,
+    trade_amount: f64,
+    /// 每次现货/合约账户划转成本 (占名义金额比例)
+    transfer_costs: HashMap<ExchangeId, f64>,
+    default_transfer_cost: f64,
+    /// 参与套利的保证金币种
+    margin_assets: Vec<String>,
+    /// (交易所, 合约 BASE/QUOTE:MARGIN) -> 资金费率
+    funding_rates: HashMap<(ExchangeId, String), f64>,
+    /// (交易所, 现货 BASE/QUOTE) -> 中间价
+    spot_prices: HashMap<(ExchangeId, String), f64>,
+    /// (交易所, 合约 BASE/QUOTE:MARGIN) -> 中间价
+    perp_prices: HashMap<(ExchangeId, String), f64>,
+    /// (交易所, 币种) -> 上次信号时间
+    last_signal: HashMap<(ExchangeId, String), i64>,
+}
+
+impl FundingRateStrategy {
+    pub fn new(config: &StrategyConfig) -> Self {
+        let params = &config.config;
+        let mut transfer_costs = HashMap::new();
+        if let Some(map) = params.get("transfer_costs").and_then(|v| v.as_object()) {
+            for (exchange, cost) in map {
+                let (Ok(id), Some(cost)) = (
+                    serde_json::from_value::<ExchangeId>(serde_json::Value::String(exchange.to_lowercase())),
+                    cost.as_f64(),
+                ) else {
+                    continue;
+                };
+                transfer_costs.insert(id, cost.max(0.0));
+            }
+        }
+        Self {
+            id: config.id.clone(),
+            min_funding_rate: config_f64(params, "min_funding_rate", 0.0005),
+            min_apr: config_f64(params, "min_apr", 0.1),
+            taker_fee: config_f64(params, "taker_fee", 0.001),
+            funding_interval_hours: config_f64(params, "funding_interval_hours", 8.0).max(1.0),
+            hold_days: config_f64(params, "hold_days", 7.0).max(1.0),
+            trade_amount: config_f64(params, "trade_amount", 1000.0),
+            transfer_costs,
+            default_transfer_cost: config_f64(params, "default_transfer_cost", 0.0),
+            margin_assets: config_str_list(params, "margin_assets")
+                .unwrap_or_else(|| vec!["USDT".to_string(), "USDC".to_string()]),
+            funding_rates: HashMap::new(),
+            spot_prices: HashMap::new(),
+            perp_prices: HashMap::new(),
+            last_signal: HashMap::new(),
+        }
+    }
+
+    /// 更新资金费率 (symbol 为永续合约；不带保证金后缀时视为以计价币为保证金)
+    pub fn update_funding_rate(&mut self, exchange: ExchangeId, symbol: &str, rate: f64) {
+        let Some(key) = contract_key(symbol) else {
+            return;
+        };
+        self.funding_rates.insert((exchange, key), rate);
+    }
+
+    fn transfer_cost(&self, exchange: ExchangeId) -> f64 {
+        self.transfer_costs
+            .get(&exchange)
+            .copied()
+            .unwrap_or(self.default_transfer_cost)
+    }
+
+    /// 净年化收益率: 资金费年化减去按持仓天数摊销的手续费与划转成本
+    pub fn calculate_apr(&self, exchange: ExchangeId, funding_rate: f64, basis: f64) -> f64 {
+        let periods_per_year = 24.0 / self.funding_interval_hours * 365.0;
+        let gross_apr = funding_rate * periods_per_year;
+        // 开仓两腿 + 平仓两腿的手续费；保证金划入合约账户再划回
+        let one_time_cost = 4.0 * self.taker_fee + 2.0 * self.transfer_cost(exchange) + basis.max(0.0);
+        gross_apr - one_time_cost * 365.0 / self.hold_days
+    }
+
+    /// 合约对应的现货价格: 优先同保证金币种计价的现货，其次任一参与的稳定币计价现货
+    fn spot_price(&self, exchange: ExchangeId, base: &str, margin: &str) -> Option<(String, f64)> {
+        std::iter::once(margin)
+            .chain(self.margin_assets.iter().map(String::as_str))
+            .find_map(|quote| {
+                let symbol = Symbol::new(base, quote).to_string();
+                let price = *self.spot_prices.get(&(exchange, symbol.clone()))?;
+                (price > 0.0).then_some((symbol, price))
+            })
+    }
+
+    /// 评估币种在该交易所所有保证金合约上的净年化，返回净年化最高的组合
+    fn best_contract(&self, exchange: ExchangeId, base: &str) -> Option<CarryQuote> {
+        let mut best: Option<CarryQuote> = None;
+        for ((ex, contract), &rate) in &self.funding_rates {
+            if *ex != exchange || rate < self.min_funding_rate {
+                continue;
+            }
+            let Some((contract_base, margin)) = split_contract(contract) else {
+                continue;
+            };
+            if contract_base != base || !self.margin_assets.contains(&margin) {
+                continue;
+            }
+            let Some(perp) = self.perp_prices.get(&(exchange, contract.clone())).copied().filter(|p| *p > 0.0) else {
+                continue;
+            };
+            let Some((spot_symbol, spot)) = self.spot_price(exchange, base, &margin) else {
+                continue;
+            };
+            let net_apr = self.calculate_apr(exchange, rate, (perp - spot) / spot);
+            if best.as_ref().is_none_or(|b| net_apr > b.net_apr) {
+                best = Some(CarryQuote {
+                    net_apr,
+                    spot_symbol,
+                    spot,
+                    contract: contract.clone(),
+                    perp,
+                });
+            }
+        }
+        best
+    }
+
+    fn evaluate(&mut self, exchange: ExchangeId, base: &str, timestamp: i64) -> Option<Signal> {
+        let CarryQuote {
+            net_apr,
+            spot_symbol,
+            spot,
+            contract,
+            perp,
+        } = self.best_contract(exchange, base)?;
+        if net_apr < self.min_apr {
+            return None;
+        }
+        let map_key = (exchange, base.to_string());
+        if let Some(last) = self.last_signal.get(&map_key) {
+            if timestamp - last < SIGNAL_COOLDOWN_MS {
+                return None;
+            }
+        }
+        self.last_signal.insert(map_key, timestamp);
+
+        // 持仓期内的预期净收益率
+        let profit_rate = net_apr * self.hold_days / 365.0;
+        let mut signal = Signal::new(
+            self.id.clone(),
+            StrategyType::CashCarry,
+            exchange,
+            profit_rate,
+            profit_rate * self.trade_amount,
+            (net_apr / (self.min_apr * 2.0)).min(1.0),
+            format!("{}->{}", spot_symbol, contract),
+            timestamp,
+        )
+        .with_fee_rate(4.0 * self.taker_fee);
+        // 现货买入、永续卖出
+        signal.legs = vec![
+            SignalLeg {
+                exchange,
+                symbol: spot_symbol,
+                side: OrderSide::Buy,
+                price: spot,
+            },
+            SignalLeg {
+                exchange,
+                symbol: contract,
+                side: OrderSide::Sell,
+                price: perp,
+            },
+        ];
+        Some(signal)
+    }
+}
+
+/// 从合约键拆出 (币种, 保证金币种)
+fn split_contract(contract: &str) -> Option<(String, String)> {
+    let (market, margin) = contract.split_once(':')?;
+    let (base, _) = market.split_once('/')?;
+    Some((base.to_string(), margin.to_string()))
+}
+
+#[async_trait]
+impl Strategy for FundingRateStrategy {
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn strategy_type(&self) -> StrategyType {
+        StrategyType::CashCarry
+    }
+
+    fn is_ready(&self) -> bool {
+        !self.funding_rates.is_empty()
+    }
+
+    fn debug_state(&self) -> serde_json::Value {
+        let key = |(exchange, symbol): &(ExchangeId, String)| format!("{:?}:{}", exchange, symbol);
+        let funding: BTreeMap<String, f64> = self.funding_rates.iter().map(|(k, &rate)| (key(k), rate)).collect();
+        let spot: BTreeMap<String, f64> = self.spot_prices.iter().map(|(k, p)| (key(k), *p)).collect();
+        let perp: BTreeMap<String, f64> = self.perp_prices.iter().map(|(k, p)| (key(k), *p)).collect();
+        serde_json::json!({
+            "minFundingRate": self.min_funding_rate,
+            "minApr": self.min_apr,
+            "takerFee": self.taker_fee,
+            "holdDays": self.hold_days,
+            "fundingRates": funding,
+            "spotPrices": spot,
+            "perpPrices": perp,
+        })
+    }
+
+    async fn on_ticker(&mut self, ticker: &Ticker) -> Option<Signal> {
+        let mid = if ticker.bid > 0.0 && ticker.ask > 0.0 {
+            (ticker.bid + ticker.ask) / 2.0
+        } else {
+            ticker.last
+        };
+        let base = if ticker.symbol.contains(':') {
+            let key = contract_key(&ticker.symbol)?;
+            let (base, _) = split_contract(&key)?;
+            self.perp_prices.insert((ticker.exchange, key), mid);
+            base
+        } else {
+            let pair = ticker.pair()?;
+            self.spot_prices.insert((ticker.exchange, pair.to_string()), mid);
+            pair.base
+        };
+        self.evaluate(ticker.exchange, &base, ticker.timestamp)
+    }
+
+    async fn on_funding_rate(&mut self, funding: &FundingRateUpdate) -> Option<Signal> {
+        self.update_funding_rate(funding.exchange, &funding.symbol, funding.rate);
+        let key = contract_key(&funding.symbol)?;
+        let (base, _) = split_contract(&key)?;
+        if let Some(mark) = funding.mark_price.filter(|p| *p > 0.0) {
+            self.perp_prices.insert((funding.exchange, key), mark);
+        }
+        self.evaluate(funding.exchange, &base, funding.timestamp)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exchange::testing::ticker;
+
+    fn strategy(params: serde_json::Value) -> FundingRateStrategy {
+        let config: StrategyConfig = serde_json::from_value(serde_json::json!({
+            "id": "carry", "strategy_type": "funding_rate", "name": "carry", "is_enabled": true, "priority": 1,
+            "config": params,
+        }))
+        .unwrap();
+        FundingRateStrategy::new(&config)
+    }
+
+    #[tokio::test]
+    async fn internal_transfer_cost_lowers_net_apr() {
+        let mut carry = strategy(serde_json::json!({
+            "min_apr": 0.2,
+            "transfer_costs": {"OKX": 0.0, "binance": 0.002},
+        }));
+
+        // 同样的资金费率与基差，收费交易所的净年化少了两次划转的摊销
+        let free = carry.calculate_apr(ExchangeId::Okx, 0.0005, 0.0);
+        let charged = carry.calculate_apr(ExchangeId::Binance, 0.0005, 0.0);
+        assert!((free - charged - 2.0 * 0.002 * 365.0 / 7.0).abs() < 1e-12);
+        // 未配置的交易所用默认划转成本 (0)
+        assert_eq!(carry.calculate_apr(ExchangeId::Bybit, 0.0005, 0.0), free);
+
+        // 免费划转的交易所达到净年化下限而收费的没有
+        for exchange in [ExchangeId::Okx, ExchangeId::Binance] {
+            carry.update_funding_rate(exchange, "BTC/USDT:USDT", 0.0005);
+            carry.on_ticker(&ticker("BTC/USDT:USDT").exchange(exchange).quote(100.0, 100.0).build()).await;
+        }
+        let okx = carry.on_ticker(&ticker("BTC/USDT").exchange(ExchangeId::Okx).quote(100.0, 100.0).build()).await;
+        let binance = carry.on_ticker(&ticker("BTC/USDT").exchange(ExchangeId::Binance).quote(100.0, 100.0).build()).await;
+        assert!((okx.expect("free venue should signal").profit_rate - free * 7.0 / 365.0).abs() < 1e-12);
+        assert!(binance.is_none(), "charged venue net APR {} is below min_apr", charged);
+    }
+
+    #[tokio::test]
+    async fn higher_funding_margin_contract_is_chosen() {
+        let mut carry = strategy(serde_json::json!({"min_apr": 0.1}));
+        for symbol in ["BTC/USDT:USDT", "BTC/USDC:USDC", "BTC/USDC"] {
+            assert!(carry.on_ticker(&ticker(symbol).exchange(ExchangeId::Binance).quote(100.0, 100.0).build()).await.is_none());
+        }
+        carry.update_funding_rate(ExchangeId::Binance, "BTC/USDT:USDT", 0.0006);
+        carry.update_funding_rate(ExchangeId::Binance, "BTC/USDC:USDC", 0.0009);
+
+        // 两个合约的资金费率分别跟踪，USDC 合约净年化更高，且优先配同保证金币种的现货
+        let signal = carry.on_ticker(&ticker("BTC/USDT").exchange(ExchangeId::Binance).quote(100.0, 100.0).build()).await.unwrap();
+        assert_eq!(signal.path, "BTC/USDC->BTC/USDC:USDC");
+        let symbols: Vec<&str> = signal.legs.iter().map(|l| l.symbol.as_str()).collect();
+        assert_eq!(symbols, vec!["BTC/USDC", "BTC/USDC:USDC"]);
+        assert_eq!(carry.funding_rates.len(), 2);
+    }
+}
@@ -0,0 +1,634 @@
+//! 图搜索套利策略
+//!
+//! 以币种为节点、交易对为有向边，边权 `-ln(rate * (1 - fee))`，
+//! 用 Bellman-Ford 寻找负权环 (即获利路径)。
+//! 开启 `depth_weighting` 后，盘口深度不足目标名义金额的边会额外加罚，
+//! 使搜索偏向可实际成交的环路；收益率本身仍按价格计算。
+//!
+//! 同一交易对一买一卖的环路 (如 USDT->BTC->USDT) 只是在吃瞬时的交叉报价，扣费后经济上是对敲，
+//! 默认剔除 (`reject_degenerate_cycles`): 去掉环上重复经过的那条边后重新检测，继续寻找其余获利环路。
+//!
+//! 启动初期图里只有零星几条边，此时的负权环检测没有意义。每个交易所在节点间的有向边数达到
+//! 预期边数的 `min_edge_coverage` 比例之前不做检测；预期边数默认按每个非枢纽节点至少一个交易对
+//! (`2 * (节点数 - 1)`) 计算，可用 `expected_markets` 指定交易对数量。达到后每个 tick 照常检测。
+//!
+//! 按引擎时钟计年龄超过 `max_price_age_ms` (默认 0，不检查) 的边不参与检测与收益计算，
+//! 整个交易所行情冻结时同样生效，避免用旧价格找出虚假的获利环路；环上最旧的报价越接近该上限，信号置信度越低。
+//!
+//! 信号按环路各边给出交易对、方向与价格 (`legs`)，执行与模拟成交据此逐腿下单。
+//!
+//! 检测到的环路被运行时禁用 (见 [`crate::disabled_paths`]) 时，去掉该环路的一条边重新检测，寻找其余获利环路。
+//!
+//! 节点集合以 `nodes` (默认 USDT/BTC/ETH/BNB/SOL/XRP) 为初始值，行情中出现的新币种随边一起加入，
+//! 总数不超过 `max_nodes` (默认 30)，以限制 Bellman-Ford 的 O(V·E) 开销；达到上限后新币种的边照常记录但不参与检测。
+
+use async_trait::async_trait;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::Arc;
+use tracing::{debug, info};
+
+use super::{config_bool, config_f64, config_max_price_age, config_str_list, price_freshness};
+use crate::disabled_paths::DISABLED_PATHS;
+use crate::engine::{Clock, SystemClock};
+use crate::exchange::{ExchangeId, Symbol, Ticker};
+use crate::executor::OrderSide;
+use crate::strategy::{Signal, SignalLeg, Strategy, StrategyConfig, StrategyType};
+
+/// 默认节点集合
+const DEFAULT_NODES: &[&str] = &["USDT", "BTC", "ETH", "BNB", "SOL", "XRP"];
+/// 视为美元计价的稳定币
+const STABLE_ASSETS: &[&str] = &["USDT", "USDC", "FDUSD", "BUSD", "TUSD"];
+/// 跳过禁用 / 退化环路时最多重新检测的次数
+const MAX_CYCLE_SKIPS: usize = 3;
+/// 默认节点数上限
+const DEFAULT_MAX_NODES: usize = 30;
+
+/// 有向边
+#[derive(Debug, Clone)]
+struct Edge {
+    /// 1 单位起点币可换得的终点币数量 (卖出为 bid，买入为 1/ask)
+    rate: f64,
+    /// 盘口可成交数量 (以交易对 base 计)
+    base_size: f64,
+    /// 交易对 base 币种
+    base: String,
+    /// 报价更新时间 (毫秒)
+    timestamp: i64,
+}
+
+/// 图搜索套利策略
+pub struct GraphStrategy {
+    id: String,
+    nodes: Vec<String>,
+    /// 节点数上限 (行情中的新币种只在未达上限时加入)
+    max_nodes: usize,
+    edges: HashMap<ExchangeId, HashMap<(String, String), Edge>>,
+    min_profit_rate: f64,
+    fee_rate: f64,
+    max_path_length: usize,
+    trade_amount: f64,
+    depth_weighting: bool,
+    /// 深度加权的目标名义金额 (USDT)
+    depth_target_notional: f64,
+    /// 剔除同一交易对正反各走一次的环路
+    reject_degenerate_cycles: bool,
+    /// 开始检测所需的有向边数
+    min_ready_edges: usize,
+    /// 报价最大年龄 (毫秒，0 为不检查)
+    max_price_age_ms: i64,
+    /// 引擎时钟，报价年龄以此为准 (加载时由引擎注入)
+    clock: Arc<dyn Clock>,
+    /// 边覆盖已达标的交易所
+    ready: HashSet<ExchangeId>,
+    last_signal: Option<(String, i64)>,
+}
+
+impl GraphStrategy {
+    pub fn new(config: &StrategyConfig) -> Self {
+        let params = &config.config;
+        let max_nodes = (config_f64(params, "max_nodes", DEFAULT_MAX_NODES as f64) as usize).max(2);
+        let mut nodes: Vec<String> = vec![];
+        for node in config_str_list(params, "nodes")
+            .unwrap_or_else(|| DEFAULT_NODES.iter().map(|s| s.to_string()).collect())
+        {
+            if !nodes.contains(&node) {
+                nodes.push(node);
+            }
+        }
+        nodes.truncate(max_nodes);
+        let expected_markets = config_f64(params, "expected_markets", nodes.len().saturating_sub(1) as f64);
+        let coverage = config_f64(params, "min_edge_coverage", 0.5).clamp(0.0, 1.0);
+        Self {
+            id: config.id.clone(),
+            min_ready_edges: (2.0 * expected_markets.max(0.0) * coverage).ceil() as usize,
+            nodes,
+            max_nodes,
+            edges: HashMap::new(),
+            min_profit_rate: config_f64(params, "min_profit_rate", 0.002),
+            fee_rate: config_f64(params, "taker_fee", config_f64(params, "fee_rate", 0.001)),
+            max_path_length: config_f64(params, "max_path_length", 5.0) as usize,
+            trade_amount: config_f64(params, "trade_amount", 100.0),
+            depth_weighting: config_bool(params, "depth_weighting", false),
+            depth_target_notional: config_f64(params, "depth_target_notional", 1000.0),
+            reject_degenerate_cycles: config_bool(params, "reject_degenerate_cycles", true),
+            max_price_age_ms: config_max_price_age(params),
+            clock: Arc::new(SystemClock),
+            ready: HashSet::new(),
+            last_signal: None,
+        }
+    }
+
+    /// 新币种加入节点集合 (已达上限时忽略)
+    fn add_node(&mut self, asset: &str) {
+        if self.nodes.iter().any(|n| n == asset) {
+            return;
+        }
+        if self.nodes.len() >= self.max_nodes {
+            debug!("图策略 {} 节点数已达上限 {}，忽略新币种 {}", self.id, self.max_nodes, asset);
+            return;
+        }
+        self.nodes.push(asset.to_string());
+    }
+
+    fn update_edges(&mut self, ticker: &Ticker) {
+        let Some(Symbol { base, quote }) = ticker.pair() else {
+            return;
+        };
+        self.add_node(&quote);
+        self.add_node(&base);
+        let timestamp = ticker.timestamp;
+        let edges = self.edges.entry(ticker.exchange).or_default();
+        if ticker.bid > 0.0 {
+            edges.insert(
+                (base.clone(), quote.clone()),
+                Edge {
+                    rate: ticker.bid,
+                    base_size: ticker.bid_size,
+                    base: base.clone(),
+                    timestamp,
+                },
+            );
+        }
+        if ticker.ask > 0.0 {
+            edges.insert(
+                (quote, base.clone()),
+                Edge {
+                    rate: 1.0 / ticker.ask,
+                    base_size: ticker.ask_size,
+                    base,
+                    timestamp,
+                },
+            );
+        }
+    }
+
+    /// 交易所的图是否已有足够的边做环检测 (达标后不再回退)
+    fn graph_ready(&mut self, exchange: ExchangeId) -> bool {
+        if self.ready.contains(&exchange) {
+            return true;
+        }
+        let Some(edges) = self.edges.get(&exchange) else {
+            return false;
+        };
+        let populated = edges
+            .keys()
+            .filter(|(from, to)| self.nodes.contains(from) && self.nodes.contains(to))
+            .count();
+        if populated < self.min_ready_edges.max(1) {
+            return false;
+        }
+        info!(
+            "图策略 {} {:?} 已有 {} 条有向边 (需要 {})，开始环检测",
+            self.id, exchange, populated, self.min_ready_edges
+        );
+        self.ready.insert(exchange);
+        true
+    }
+
+    /// 资产的 USDT 估值价格
+    fn usd_price(edges: &HashMap<(String, String), Edge>, asset: &str) -> Option<f64> {
+        if STABLE_ASSETS.contains(&asset) {
+            return Some(1.0);
+        }
+        STABLE_ASSETS
+            .iter()
+            .find_map(|stable| edges.get(&(asset.to_string(), stable.to_string())))
+            .map(|e| e.rate)
+    }
+
+    /// 边权: 价格项 + 深度罚项
+    fn edge_weight(&self, edges: &HashMap<(String, String), Edge>, edge: &Edge) -> f64 {
+        let price_weight = -(edge.rate * (1.0 - self.fee_rate)).ln();
+        if !self.depth_weighting || edge.base_size <= 0.0 {
+            return price_weight;
+        }
+        let Some(price) = Self::usd_price(edges, &edge.base) else {
+            return price_weight;
+        };
+        let fill_ratio = (edge.base_size * price / self.depth_target_notional).min(1.0);
+        if fill_ratio <= 0.0 {
+            return price_weight;
+        }
+        // 可成交比例越低罚项越大，满足目标金额时无罚项
+        price_weight - fill_ratio.ln()
+    }
+
+    /// Bellman-Ford 负权环检测，返回环上的节点序列 (首尾相同)
+    fn detect_negative_cycle(&self, exchange: ExchangeId, excluded: &HashSet<(String, String)>) -> Option<Vec<String>> {
+        let edges = self.edges.get(&exchange)?;
+        let index: HashMap<&str, usize> = self
+            .nodes
+            .iter()
+            .enumerate()
+            .map(|(i, n)| (n.as_str(), i))
+            .collect();
+        let graph: Vec<(usize, usize, f64)> = edges
+            .iter()
+            .filter(|(key, edge)| !excluded.contains(*key) && self.fresh(edge))
+            .filter_map(|((from, to), edge)| {
+                let u = *index.get(from.as_str())?;
+                let v = *index.get(to.as_str())?;
+                Some((u, v, self.edge_weight(edges, edge)))
+            })
+            .collect();
+        if graph.is_empty() {
+            return None;
+        }
+
+        let n = self.nodes.len();
+        let mut dist = vec![0.0_f64; n];
+        let mut pred: Vec<Option<usize>> = vec![None; n];
+        let mut relaxed = None;
+        for _ in 0..n {
+            relaxed = None;
+            for &(u, v, w) in &graph {
+                if dist[u] + w < dist[v] - 1e-12 {
+                    dist[v] = dist[u] + w;
+                    pred[v] = Some(u);
+                    relaxed = Some(v);
+                }
+            }
+            relaxed?;
+        }
+
+        // 第 n 轮仍可松弛: 回溯 n 步确保落在环上
+        let mut x = relaxed?;
+        for _ in 0..n {
+            x = pred[x]?;
+        }
+        let mut cycle = vec![x];
+        let mut cur = pred[x]?;
+        while cur != x {
+            cycle.push(cur);
+            cur = pred[cur]?;
+            if cycle.len() > n {
+                return None;
+            }
+        }
+        cycle.push(x);
+        cycle.reverse();
+
+        // 尽量从稳定币开始，便于阅读与执行
+        cycle.pop();
+        if let Some(pos) = cycle
+            .iter()
+            .position(|i| STABLE_ASSETS.contains(&self.nodes[*i].as_str()))
+        {
+            cycle.rotate_left(pos);
+        }
+        cycle.push(cycle[0]);
+        Some(cycle.into_iter().map(|i| self.nodes[i].clone()).collect())
+    }
+
+    fn fresh(&self, edge: &Edge) -> bool {
+        price_freshness(edge.timestamp, self.clock.now_ms(), self.max_price_age_ms).is_some()
+    }
+
+    /// 按价格计算环路收益率 (含手续费，不含深度罚项) 与报价新鲜度系数；环上任一条边过旧时为 None
+    fn cycle_profit(&self, exchange: ExchangeId, cycle: &[String]) -> Option<(f64, f64)> {
+        let edges = self.edges.get(&exchange)?;
+        let mut amount = 1.0;
+        let mut oldest = i64::MAX;
+        for pair in cycle.windows(2) {
+            let edge = edges.get(&(pair[0].clone(), pair[1].clone()))?;
+            amount *= edge.rate * (1.0 - self.fee_rate);
+            oldest = oldest.min(edge.timestamp);
+        }
+        let freshness = price_freshness(oldest, self.clock.now_ms(), self.max_price_age_ms)?;
+        Some((amount - 1.0, freshness))
+    }
+
+    /// 环路各腿的交易对、方向与价格: 起点币为交易对 base 时卖出 (价格为 bid)，否则买入 (价格为 ask)
+    fn cycle_legs(&self, exchange: ExchangeId, cycle: &[String]) -> Option<Vec<SignalLeg>> {
+        let edges = self.edges.get(&exchange)?;
+        cycle
+            .windows(2)
+            .map(|pair| {
+                let edge = edges.get(&(pair[0].clone(), pair[1].clone()))?;
+                let (symbol, side, price) = if edge.base == pair[0] {
+                    (Symbol::new(&pair[0], &pair[1]), OrderSide::Sell, edge.rate)
+                } else {
+                    (Symbol::new(&pair[1], &pair[0]), OrderSide::Buy, 1.0 / edge.rate)
+                };
+                Some(SignalLeg {
+                    exchange,
+                    symbol: symbol.to_string(),
+                    side,
+                    price,
+                })
+            })
+            .collect()
+    }
+}
+
+/// 环路退化时返回第二次经过同一交易对 (无向边) 的那条有向边: 同一交易对被正反两个方向各经过一次，
+/// 净效果只是买入后又卖出同一币对
+fn degenerate_edge(cycle: &[String]) -> Option<(String, String)> {
+    let mut markets = HashSet::new();
+    for pair in cycle.windows(2) {
+        let market = if pair[0] < pair[1] {
+            (&pair[0], &pair[1])
+        } else {
+            (&pair[1], &pair[0])
+        };
+        if !markets.insert(market) {
+            return Some((pair[0].clone(), pair[1].clone()));
+        }
+    }
+    None
+}
+
+#[async_trait]
+impl Strategy for GraphStrategy {
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn strategy_type(&self) -> StrategyType {
+        StrategyType::Graph
+    }
+
+    fn set_clock(&mut self, clock: Arc<dyn Clock>) {
+        self.clock = clock;
+    }
+
+    fn debug_state(&self) -> serde_json::Value {
+        let edges: BTreeMap<String, BTreeMap<String, f64>> = self
+            .edges
+            .iter()
+            .map(|(exchange, edges)| {
+                let edges = edges
+                    .iter()
+                    .map(|((from, to), edge)| (format!("{}->{}", from, to), edge.rate))
+                    .collect();
+                (format!("{:?}", exchange), edges)
+            })
+            .collect();
+        serde_json::json!({
+            "minProfitRate": self.min_profit_rate,
+            "feeRate": self.fee_rate,
+            "maxPathLength": self.max_path_length,
+            "maxNodes": self.max_nodes,
+            "minReadyEdges": self.min_ready_edges,
+            "maxPriceAgeMs": self.max_price_age_ms,
+            "ready": self.ready.iter().map(|e| format!("{:?}", e)).collect::<Vec<_>>(),
+            "nodes": self.nodes,
+            "edges": edges,
+        })
+    }
+
+    fn is_ready(&self) -> bool {
+        !self.ready.is_empty()
+    }
+
+    async fn on_ticker(&mut self, ticker: &Ticker) -> Option<Signal> {
+        self.update_edges(ticker);
+        if !self.graph_ready(ticker.exchange) {
+            return None;
+        }
+
+        let mut excluded = HashSet::new();
+        let cycle = loop {
+            let cycle = self.detect_negative_cycle(ticker.exchange, &excluded)?;
+            let skip = if DISABLED_PATHS.is_disabled(&cycle.join("->")) {
+                debug!("{:?} 跳过已禁用环路 {}", ticker.exchange, cycle.join("->"));
+                (cycle[0].clone(), cycle[1].clone())
+            } else if let Some(edge) = degenerate_edge(&cycle).filter(|_| self.reject_degenerate_cycles) {
+                debug!("{:?} 剔除退化环路 {}", ticker.exchange, cycle.join("->"));
+                edge
+            } else {
+                break cycle;
+            };
+            if excluded.len() >= MAX_CYCLE_SKIPS {
+                return None;
+            }
+            excluded.insert(skip);
+        };
+        if cycle.len() - 1 > self.max_path_length {
+            return None;
+        }
+        let (profit_rate, freshness) = self.cycle_profit(ticker.exchange, &cycle)?;
+        if profit_rate < self.min_profit_rate {
+            return None;
+        }
+        let legs = self.cycle_legs(ticker.exchange, &cycle)?;
+
+        let path = cycle.join("->");
+        // 同一路径 1 秒内不重复发信号
+        if let Some((last_path, last_ts)) = &self.last_signal {
+            if *last_path == path && ticker.timestamp - last_ts < 1000 {
+                return None;
+            }
+        }
+        self.last_signal = Some((path.clone(), ticker.timestamp));
+
+        let mut signal = Signal::new(
+            self.id.clone(),
+            StrategyType::Graph,
+            ticker.exchange,
+            profit_rate,
+            profit_rate * self.trade_amount,
+            (profit_rate / (self.min_profit_rate * 2.0)).min(1.0) * freshness,
+            path,
+            ticker.timestamp,
+        )
+        .with_fee_rate((1.0 + profit_rate) / (1.0 - self.fee_rate).powi(legs.len() as i32) - 1.0 - profit_rate);
+        signal.legs = legs;
+        Some(signal)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backtest::ReplayClock;
+    use crate::exchange::testing::ticker;
+
+    fn strategy() -> GraphStrategy {
+        let config: StrategyConfig = serde_json::from_value(serde_json::json!({
+            "id": "graph",
+            "strategy_type": "graph",
+            "name": "graph",
+            "is_enabled": true,
+            "priority": 1,
+            "config": {"nodes": ["USDT", "BTC", "ETH"]},
+        }))
+        .unwrap();
+        GraphStrategy::new(&config)
+    }
+
+    fn depth_weighted_strategy() -> GraphStrategy {
+        let config: StrategyConfig = serde_json::from_value(serde_json::json!({
+            "id": "graph",
+            "strategy_type": "graph",
+            "name": "graph",
+            "is_enabled": true,
+            "priority": 1,
+            "config": {
+                "nodes": ["USDT", "BTC", "ETH"],
+                "depth_weighting": true,
+                "depth_target_notional": 1000.0,
+            },
+        }))
+        .unwrap();
+        GraphStrategy::new(&config)
+    }
+
+    #[tokio::test]
+    async fn thin_book_increases_edge_weight() {
+        let mut graph = depth_weighted_strategy();
+        let weight = |graph: &GraphStrategy| {
+            let edges = &graph.edges[&ExchangeId::Binance];
+            graph.edge_weight(edges, &edges[&("BTC".to_string(), "USDT".to_string())])
+        };
+
+        // 买一 10 BTC * 100 = 1000 USDT，满足目标金额，只有价格项
+        graph.on_ticker(&ticker("BTC/USDT").quote(100.0, 100.1).size(10.0).build()).await;
+        let deep = weight(&graph);
+        assert!((deep - -(100.0f64 * 0.999).ln()).abs() < 1e-12);
+
+        // 只剩 1 BTC (目标金额的 10%)，罚项 -ln(0.1)
+        graph.on_ticker(&ticker("BTC/USDT").quote(100.0, 100.1).size(1.0).build()).await;
+        let thin = weight(&graph);
+        assert!((thin - deep - 10f64.ln()).abs() < 1e-9, "deep {} thin {}", deep, thin);
+
+        // 未推送挂单量时不加罚
+        graph.on_ticker(&ticker("BTC/USDT").quote(100.0, 100.1).build()).await;
+        assert!((weight(&graph) - deep).abs() < 1e-12);
+    }
+
+    #[tokio::test]
+    async fn thin_book_suppresses_otherwise_profitable_cycle() {
+        for (size, expect_signal) in [(1_000.0, true), (1.0, false)] {
+            let mut graph = depth_weighted_strategy();
+            graph.on_ticker(&ticker("ETH/USDT").quote(10.1, 10.11).size(size).build()).await;
+            graph.on_ticker(&ticker("ETH/BTC").quote(0.0989, 0.099).size(1_000.0).build()).await;
+            let signal = graph.on_ticker(&ticker("BTC/USDT").quote(99.9, 100.0).size(1_000.0).build()).await;
+            assert_eq!(signal.is_some(), expect_signal, "ETH/USDT size {}", size);
+        }
+    }
+
+    #[tokio::test]
+    async fn equal_price_cycles_prefer_the_deeper_book() {
+        let config: StrategyConfig = serde_json::from_value(serde_json::json!({
+            "id": "graph",
+            "strategy_type": "graph",
+            "name": "graph",
+            "is_enabled": true,
+            "priority": 1,
+            "config": {
+                "nodes": ["USDT", "BTC", "ETH", "SOL"],
+                "depth_weighting": true,
+                "depth_target_notional": 1000.0,
+            },
+        }))
+        .unwrap();
+        // USDT -> BTC -> X -> USDT 两条环路报价完全相同，只有 X/USDT 的挂单量不同
+        for (deep, thin) in [("SOL", "ETH"), ("ETH", "SOL")] {
+            let mut graph = GraphStrategy::new(&config);
+            for (asset, size) in [(deep, 1_000.0), (thin, 60.0)] {
+                graph.on_ticker(&ticker(&format!("{}/USDT", asset)).quote(10.1, 10.11).size(size).build()).await;
+                graph.on_ticker(&ticker(&format!("{}/BTC", asset)).quote(0.0989, 0.099).size(1_000.0).build()).await;
+            }
+            let signal = graph
+                .on_ticker(&ticker("BTC/USDT").quote(99.9, 100.0).size(1_000.0).build())
+                .await
+                .expect("深度足够的环路应被检测到");
+            assert_eq!(signal.path, format!("USDT->BTC->{}->USDT", deep), "深 {} 浅 {}", deep, thin);
+        }
+    }
+
+    #[tokio::test]
+    async fn no_cycle_is_emitted_before_every_market_has_a_quote() {
+        let config: StrategyConfig = serde_json::from_value(serde_json::json!({
+            "id": "graph",
+            "strategy_type": "graph",
+            "name": "graph",
+            "is_enabled": true,
+            "priority": 1,
+            "config": {
+                "nodes": ["USDT", "BTC", "ETH"],
+                "expected_markets": 3,
+                "min_edge_coverage": 1.0,
+                "reject_degenerate_cycles": false,
+            },
+        }))
+        .unwrap();
+        let mut graph = GraphStrategy::new(&config);
+        assert_eq!(graph.min_ready_edges, 6);
+
+        // BTC/USDT 买卖倒挂本身就构成获利环路，但图里只有 2 / 4 条边，不做检测
+        assert!(graph.on_ticker(&ticker("BTC/USDT").quote(110.0, 100.0).build()).await.is_none());
+        assert!(graph.on_ticker(&ticker("ETH/USDT").quote(10.1, 10.11).build()).await.is_none());
+        assert!(graph.ready.is_empty());
+
+        // 三个交易对都有报价后开始检测
+        assert!(graph.on_ticker(&ticker("ETH/BTC").quote(0.0989, 0.099).build()).await.is_some());
+        assert!(graph.ready.contains(&ExchangeId::Binance));
+    }
+
+    #[test]
+    fn degenerate_edge_is_the_second_traversal() {
+        let cycle: Vec<String> = ["USDT", "BTC", "USDT"].iter().map(|s| s.to_string()).collect();
+        assert_eq!(degenerate_edge(&cycle), Some(("BTC".to_string(), "USDT".to_string())));
+        let cycle: Vec<String> = ["USDT", "BTC", "ETH", "USDT"].iter().map(|s| s.to_string()).collect();
+        assert_eq!(degenerate_edge(&cycle), None);
+    }
+
+    #[tokio::test]
+    async fn degenerate_cycle_does_not_stop_detection() {
+        let mut graph = strategy();
+        // BTC/USDT 买卖倒挂 (退化环路)，同时存在获利的三角环路
+        graph.on_ticker(&ticker("ETH/USDT").quote(10.1, 10.11).build()).await;
+        graph.on_ticker(&ticker("ETH/BTC").quote(0.0989, 0.099).build()).await;
+        let signal = graph.on_ticker(&ticker("BTC/USDT").quote(110.0, 100.0).build()).await.expect("应找到非退化环路");
+        let cycle: Vec<String> = signal.path.split("->").map(str::to_string).collect();
+        assert_eq!(degenerate_edge(&cycle), None, "path {}", signal.path);
+        assert_eq!(signal.legs.len(), 3);
+    }
+
+    #[tokio::test]
+    async fn venue_wide_freeze_is_stale_by_engine_clock() {
+        let config: StrategyConfig = serde_json::from_value(serde_json::json!({
+            "id": "graph",
+            "strategy_type": "graph",
+            "name": "graph",
+            "is_enabled": true,
+            "priority": 1,
+            "config": {"nodes": ["USDT", "BTC", "ETH"], "max_price_age_ms": 5000},
+        }))
+        .unwrap();
+        let clock = Arc::new(ReplayClock::new(1_000));
+        for (now, expect_signal) in [(1_000, true), (7_000, false)] {
+            clock.advance_to(now);
+            let mut graph = GraphStrategy::new(&config);
+            graph.set_clock(clock.clone());
+            // 三条行情时间戳相同 (整个交易所冻结在 1000)，只有引擎时钟前进
+            graph.on_ticker(&ticker("ETH/USDT").quote(10.1, 10.11).build()).await;
+            graph.on_ticker(&ticker("ETH/BTC").quote(0.0989, 0.099).build()).await;
+            let signal = graph.on_ticker(&ticker("BTC/USDT").quote(99.9, 100.0).build()).await;
+            assert_eq!(signal.is_some(), expect_signal, "now {}", now);
+        }
+    }
+
+    #[tokio::test]
+    async fn asset_outside_the_default_nodes_joins_a_detectable_cycle() {
+        let config: StrategyConfig = serde_json::from_value(serde_json::json!({
+            "id": "graph", "strategy_type": "graph", "name": "graph", "is_enabled": true, "priority": 1, "config": {},
+        }))
+        .unwrap();
+        let mut graph = GraphStrategy::new(&config);
+        assert!(!graph.nodes.iter().any(|n| n == "DOGE"));
+
+        // USDT -> BTC -> DOGE -> USDT: 1 / 100 / 0.000001 * 0.0102 = 1.02
+        graph.on_ticker(&ticker("BTC/USDT").quote(99.9, 100.0).build()).await;
+        graph.on_ticker(&ticker("DOGE/BTC").quote(0.00000099, 0.000001).build()).await;
+        let signal = graph
+            .on_ticker(&ticker("DOGE/USDT").quote(0.0102, 0.0103).build())
+            .await
+            .expect("新币种加入图后应检测到获利环路");
+        assert!(graph.nodes.iter().any(|n| n == "DOGE"));
+        assert!(signal.path.split("->").any(|asset| asset == "DOGE"), "path {}", signal.path);
+        assert_eq!(signal.legs.len(), 3);
+    }
+}
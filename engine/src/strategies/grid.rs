@@ -0,0 +1,417 @@
+//! 网格策略
+//!
+//! 在 [lower_price, upper_price] 区间内均分 grid_count 格。价格向下穿越格线时发出开仓 (买入) 信号，
+//! 向上穿越时发出平仓 (卖出) 信号；区间外不发信号。
+//! 交易对 24 小时成交额或盘口挂单金额低于配置下限时，梯度挂单实际无法成交，网格暂停发信号。
+//! 永续合约网格的买入信号扣除 `expected_hold_hours` 内按资金费率缓存估算的多头资金费
+//! (`include_funding`，默认开启)，扣除后不高于 `min_edge` 时不发信号。
+//! 配置 `max_hold_hours` 后，买入超过该时长仍未卖出的一格 (价格单边下行、迟迟不回到上方格线) 强制发出平仓信号。
+
+use async_trait::async_trait;
+use std::collections::{HashMap, VecDeque};
+use tracing::{debug, info, warn};
+
+use super::{config_bool, config_f64};
+use crate::exchange::{ExchangeId, Ticker};
+use crate::executor::OrderSide;
+use crate::funding::FUNDING_CACHE;
+use crate::strategy::{Signal, SignalAction, Strategy, StrategyConfig, StrategyType};
+use crate::symbols::normalize_symbol;
+
+/// 单个网格
+#[derive(Debug, Clone)]
+struct Grid {
+    symbol: String,
+    /// 限定交易所 (为空时对所有交易所生效)
+    exchange: Option<ExchangeId>,
+    lower_price: f64,
+    upper_price: f64,
+    grid_count: usize,
+    amount_per_grid: f64,
+    /// 24 小时成交额下限 (计价币)
+    min_volume_24h: f64,
+    /// 买一 / 卖一挂单金额下限 (计价币)
+    min_book_notional: f64,
+}
+
+impl Grid {
+    fn from_config(value: &serde_json::Value, defaults: &serde_json::Value) -> Option<Self> {
+        let symbol = value.get("symbol")?.as_str()?.to_string();
+        let lower_price = config_f64(value, "lower_price", 0.0);
+        let upper_price = config_f64(value, "upper_price", 0.0);
+        let grid_count = config_f64(value, "grid_count", 10.0) as usize;
+        if lower_price <= 0.0 || upper_price <= lower_price || grid_count == 0 {
+            return None;
+        }
+        let exchange = value
+            .get("exchange")
+            .and_then(|v| v.as_str())
+            .and_then(|v| serde_json::from_value(serde_json::Value::String(v.to_lowercase())).ok());
+        // 单个网格未配置时使用策略级参数
+        let param = |key: &str, default: f64| config_f64(value, key, config_f64(defaults, key, default));
+        Some(Self {
+            symbol,
+            exchange,
+            lower_price,
+            upper_price,
+            grid_count,
+            amount_per_grid: param("amount_per_grid", 100.0),
+            min_volume_24h: param("min_volume_24h", 0.0),
+            min_book_notional: param("min_book_notional", 0.0),
+        })
+    }
+
+    fn step(&self) -> f64 {
+        (self.upper_price - self.lower_price) / self.grid_count as f64
+    }
+
+    /// 价格所在格 (区间外为 None)
+    fn level(&self, price: f64) -> Option<usize> {
+        if price < self.lower_price || price > self.upper_price {
+            return None;
+        }
+        Some((((price - self.lower_price) / self.step()) as usize).min(self.grid_count - 1))
+    }
+
+    fn matches(&self, ticker: &Ticker) -> bool {
+        self.exchange.is_none_or(|e| e == ticker.exchange)
+            && normalize_symbol(ticker.exchange, &self.symbol) == normalize_symbol(ticker.exchange, &ticker.symbol)
+    }
+
+    /// 流动性不足的原因 (满足下限时为 None)
+    fn illiquidity(&self, ticker: &Ticker, price: f64) -> Option<String> {
+        let volume = ticker.volume * price;
+        if self.min_volume_24h > 0.0 && volume < self.min_volume_24h {
+            return Some(format!("24h 成交额 {:.0} < {:.0}", volume, self.min_volume_24h));
+        }
+        // 行情不带挂单量时不做盘口检查
+        if self.min_book_notional > 0.0 && (ticker.bid_size > 0.0 || ticker.ask_size > 0.0) {
+            let book = (ticker.bid_size * ticker.bid).min(ticker.ask_size * ticker.ask);
+            if book < self.min_book_notional {
+                return Some(format!("盘口挂单金额 {:.0} < {:.0}", book, self.min_book_notional));
+            }
+        }
+        None
+    }
+}
+
+/// 单个网格在某个交易所的运行状态
+#[derive(Debug, Default)]
+struct GridState {
+    level: Option<usize>,
+    /// 因流动性不足暂停
+    paused: bool,
+    /// 尚未卖出的买入时间 (先买先卖)
+    open_lots: VecDeque<i64>,
+}
+
+/// 网格策略
+pub struct GridStrategy {
+    id: String,
+    grids: Vec<Grid>,
+    fee_rate: f64,
+    /// 买入信号是否扣除持仓期资金费
+    include_funding: bool,
+    /// 预计持仓时长 (毫秒)
+    hold_ms: i64,
+    /// 扣除资金费后的最低收益率
+    min_edge: f64,
+    /// 最长持仓时长 (毫秒，0 为不限)
+    max_hold_ms: i64,
+    states: HashMap<(usize, ExchangeId), GridState>,
+}
+
+impl GridStrategy {
+    pub fn new(config: &StrategyConfig) -> Self {
+        let params = &config.config;
+        // 兼容单网格写法 ({"symbol": ..., "upper_price": ...})
+        let grids: Vec<Grid> = match params.get("grids").and_then(|v| v.as_array()) {
+            Some(list) => list
+                .iter()
+                .filter_map(|g| {
+                    let grid = Grid::from_config(g, params);
+                    if grid.is_none() {
+                        warn!("网格配置无效 (需要 symbol 且 0 < lower_price < upper_price、grid_count > 0)，已跳过: {}", g);
+                    }
+                    grid
+                })
+                .collect(),
+            None => Grid::from_config(params, params).into_iter().collect(),
+        };
+        if grids.is_empty() {
+            warn!("网格策略 {} 没有有效的网格配置，不会产生信号", config.id);
+        }
+        Self {
+            id: config.id.clone(),
+            grids,
+            fee_rate: config_f64(params, "taker_fee", config_f64(params, "fee_rate", 0.001)),
+            include_funding: config_bool(params, "include_funding", true),
+            hold_ms: (config_f64(params, "expected_hold_hours", 24.0).max(0.0) * 3_600_000.0) as i64,
+            min_edge: config_f64(params, "min_edge", 0.0),
+            max_hold_ms: (config_f64(params, "max_hold_hours", 0.0).max(0.0) * 3_600_000.0) as i64,
+            states: HashMap::new(),
+        }
+    }
+}
+
+#[async_trait]
+impl Strategy for GridStrategy {
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn strategy_type(&self) -> StrategyType {
+        StrategyType::Grid
+    }
+
+    fn is_ready(&self) -> bool {
+        !self.states.is_empty()
+    }
+
+    fn symbols(&self) -> Vec<String> {
+        self.grids.iter().map(|g| g.symbol.clone()).collect()
+    }
+
+    fn debug_state(&self) -> serde_json::Value {
+        let grids: Vec<serde_json::Value> = self
+            .grids
+            .iter()
+            .map(|g| {
+                serde_json::json!({
+                    "symbol": g.symbol,
+                    "exchange": g.exchange,
+                    "lowerPrice": g.lower_price,
+                    "upperPrice": g.upper_price,
+                    "gridCount": g.grid_count,
+                })
+            })
+            .collect();
+        let states: Vec<serde_json::Value> = self
+            .states
+            .iter()
+            .map(|((i, exchange), state)| {
+                serde_json::json!({
+                    "grid": i,
+                    "exchange": exchange,
+                    "level": state.level,
+                    "paused": state.paused,
+                    "openLots": state.open_lots.len(),
+                })
+            })
+            .collect();
+        serde_json::json!({
+            "feeRate": self.fee_rate,
+            "minEdge": self.min_edge,
+            "maxHoldMs": self.max_hold_ms,
+            "grids": grids,
+            "states": states,
+        })
+    }
+
+    async fn on_ticker(&mut self, ticker: &Ticker) -> Option<Signal> {
+        let price = if ticker.last > 0.0 {
+            ticker.last
+        } else {
+            (ticker.bid + ticker.ask) / 2.0
+        };
+        if price <= 0.0 {
+            return None;
+        }
+        let mut signal = None;
+        for (i, grid) in self.grids.iter().enumerate() {
+            if !grid.matches(ticker) {
+                continue;
+            }
+            let state = self.states.entry((i, ticker.exchange)).or_default();
+            let level = grid.level(price);
+            let previous = std::mem::replace(&mut state.level, level);
+
+            match grid.illiquidity(ticker, price) {
+                Some(reason) => {
+                    if !state.paused {
+                        info!("网格 {} {:?} {} 流动性不足，暂停: {}", self.id, ticker.exchange, grid.symbol, reason);
+                        state.paused = true;
+                    }
+                    continue;
+                }
+                None if state.paused => {
+                    info!("网格 {} {:?} {} 流动性恢复", self.id, ticker.exchange, grid.symbol);
+                    state.paused = false;
+                }
+                None => {}
+            }
+
+            // 最早一格持仓超时: 不等价格回到上方格线，直接卖出平仓
+            if signal.is_none()
+                && self.max_hold_ms > 0
+                && state
+                    .open_lots
+                    .front()
+                    .is_some_and(|opened| ticker.timestamp - opened >= self.max_hold_ms)
+            {
+                state.open_lots.pop_front();
+                warn!(
+                    "网格 {} {:?} {} 持仓超过 {} 小时未卖出，强制平仓",
+                    self.id,
+                    ticker.exchange,
+                    grid.symbol,
+                    self.max_hold_ms as f64 / 3_600_000.0
+                );
+                signal = Some(
+                    Signal::new(
+                        self.id.clone(),
+                        StrategyType::Grid,
+                        ticker.exchange,
+                        0.0,
+                        0.0,
+                        0.5,
+                        grid.symbol.clone(),
+                        ticker.timestamp,
+                    )
+                    .with_action(SignalAction::Close),
+                );
+                continue;
+            }
+
+            let (Some(previous), Some(level)) = (previous, level) else {
+                continue;
+            };
+            if level == previous || signal.is_some() {
+                continue;
+            }
+            // 向下穿越格线买入，向上穿越卖出
+            let action = if level < previous {
+                SignalAction::Open
+            } else {
+                SignalAction::Close
+            };
+            let mut profit_rate = grid.step() / price - 2.0 * self.fee_rate;
+            if action == SignalAction::Open && self.include_funding {
+                let funding =
+                    FUNDING_CACHE.projected_cost(ticker.exchange, &grid.symbol, OrderSide::Buy, ticker.timestamp, self.hold_ms);
+                if funding != 0.0 {
+                    profit_rate -= funding;
+                    if profit_rate <= self.min_edge {
+                        debug!(
+                            "网格 {} {:?} {} 扣除预计资金费 {:.4}% 后收益不足，不发买入信号",
+                            self.id,
+                            ticker.exchange,
+                            grid.symbol,
+                            funding * 100.0
+                        );
+                        continue;
+                    }
+                }
+            }
+            // 买入记在新进入的格，卖出平掉离开的格 (即当初买入的那一格)
+            let rung = match action {
+                SignalAction::Open => {
+                    state.open_lots.push_back(ticker.timestamp);
+                    level
+                }
+                SignalAction::Close => {
+                    state.open_lots.pop_front();
+                    previous
+                }
+            };
+            signal = Some(
+                Signal::new(
+                    self.id.clone(),
+                    StrategyType::Grid,
+                    ticker.exchange,
+                    profit_rate,
+                    grid.amount_per_grid * profit_rate,
+                    0.5,
+                    grid.symbol.clone(),
+                    ticker.timestamp,
+                )
+                .with_action(action)
+                .with_level(rung as i64)
+                .with_fee_rate(2.0 * self.fee_rate),
+            );
+        }
+        signal
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exchange::testing::ticker;
+
+    fn strategy() -> GridStrategy {
+        let config: StrategyConfig = serde_json::from_value(serde_json::json!({
+            "id": "grid", "strategy_type": "grid", "name": "grid", "is_enabled": true, "priority": 1,
+            "config": {
+                "symbol": "BTC/USDT", "lower_price": 90.0, "upper_price": 110.0, "grid_count": 20,
+                "min_volume_24h": 10_000.0, "min_book_notional": 500.0,
+            },
+        }))
+        .unwrap();
+        GridStrategy::new(&config)
+    }
+
+    #[tokio::test]
+    async fn crossing_a_grid_line_signals_buy_below_and_sell_above() {
+        let mut grid = strategy();
+        // 第一笔行情只记录所在格
+        assert!(grid.on_ticker(&ticker("BTC/USDT").price(100.5).at(1).build()).await.is_none());
+        assert!(grid.on_ticker(&ticker("BTC/USDT").price(100.7).at(2).build()).await.is_none(), "同一格内不发信号");
+
+        let buy = grid.on_ticker(&ticker("BTC/USDT").price(99.5).at(3).build()).await.expect("向下穿越");
+        assert_eq!(buy.action, SignalAction::Open);
+        assert_eq!(buy.path, "BTC/USDT");
+        // 每格 1 USDT，扣两次手续费
+        assert!((buy.profit_rate - (1.0 / 99.5 - 0.002)).abs() < 1e-12);
+
+        let sell = grid.on_ticker(&ticker("BTC/USDT").price(100.5).at(4).build()).await.expect("向上穿越");
+        assert_eq!(sell.action, SignalAction::Close);
+        // 买入记在进入的第 9 格，卖出平掉的也是第 9 格
+        assert_eq!((buy.level, sell.level), (Some(9), Some(9)));
+    }
+
+    #[tokio::test]
+    async fn prices_outside_the_range_do_not_signal() {
+        let mut grid = strategy();
+        grid.on_ticker(&ticker("BTC/USDT").price(100.5).at(1).build()).await;
+        assert!(grid.on_ticker(&ticker("BTC/USDT").price(120.0).at(2).build()).await.is_none());
+        // 从区间外回到区间内也不算穿越
+        assert!(grid.on_ticker(&ticker("BTC/USDT").price(109.5).at(3).build()).await.is_none());
+        assert!(grid.on_ticker(&ticker("BTC/USDT").price(108.5).at(4).build()).await.is_some());
+    }
+
+    fn paused(grid: &GridStrategy) -> bool {
+        grid.states[&(0, ExchangeId::Binance)].paused
+    }
+
+    #[tokio::test]
+    async fn low_volume_pauses_grid_until_liquidity_returns() {
+        let mut grid = strategy();
+        assert!(grid.on_ticker(&ticker("BTC/USDT").price(100.5).volume(1_000.0).size(0.0).at(1).build()).await.is_none());
+
+        // 24h 成交额 50 * 101.5 < 10000: 穿越格线也不发信号
+        assert!(grid.on_ticker(&ticker("BTC/USDT").price(101.5).volume(50.0).size(0.0).at(2).build()).await.is_none());
+        assert!(paused(&grid));
+
+        // 流动性恢复后只对恢复之后的穿越发信号，暂停期间的穿越不补发
+        assert!(grid.on_ticker(&ticker("BTC/USDT").price(101.5).volume(1_000.0).size(0.0).at(3).build()).await.is_none());
+        assert!(!paused(&grid));
+        let signal = grid.on_ticker(&ticker("BTC/USDT").price(100.5).volume(1_000.0).size(0.0).at(4).build()).await.expect("crossing after recovery");
+        assert_eq!(signal.action, SignalAction::Open);
+    }
+
+    #[tokio::test]
+    async fn thin_book_pauses_grid() {
+        let mut grid = strategy();
+        grid.on_ticker(&ticker("BTC/USDT").price(100.5).volume(1_000.0).size(10.0).at(1).build()).await;
+
+        // 盘口只有 1 个 (约 101.5 USDT) 低于 500 下限
+        assert!(grid.on_ticker(&ticker("BTC/USDT").price(101.5).volume(1_000.0).size(1.0).at(2).build()).await.is_none());
+        assert!(paused(&grid));
+
+        // 行情不带挂单量时不做盘口检查
+        let signal = grid.on_ticker(&ticker("BTC/USDT").price(100.5).volume(1_000.0).size(0.0).at(3).build()).await.expect("no size, no book check");
+        assert_eq!(signal.action, SignalAction::Open);
+        assert!(grid.on_ticker(&ticker("BTC/USDT").price(101.5).volume(1_000.0).size(10.0).at(4).build()).await.is_some());
+    }
+}
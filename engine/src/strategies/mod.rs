@@ -0,0 +1,211 @@
+//! 具体策略实现
+
+mod funding_rate;
+mod graph;
+mod grid;
+mod pair;
+mod triangular;
+
+pub use funding_rate::FundingRateStrategy;
+pub use graph::GraphStrategy;
+pub use grid::GridStrategy;
+pub use pair::PairStrategy;
+pub use triangular::TriangularStrategy;
+
+use tracing::warn;
+
+pub(crate) use crate::symbols::split_symbol;
+use crate::strategy::{Strategy, StrategyConfig, StrategyType};
+
+/// 根据策略配置构建策略实例，不支持的类型返回 `None`
+pub fn build_strategy(config: &StrategyConfig) -> Option<Box<dyn Strategy>> {
+    // 参数解析期间的告警带上策略 ID
+    let _span = tracing::warn_span!("strategy_config", id = %config.id).entered();
+    if !config.config.is_object() && !config.config.is_null() {
+        warn!("策略参数应为 JSON 对象，实际为 {}，全部使用默认值", config.config);
+    }
+    match config.strategy_type {
+        StrategyType::Triangular => Some(Box::new(TriangularStrategy::new(config))),
+        StrategyType::Graph => Some(Box::new(GraphStrategy::new(config))),
+        StrategyType::CashCarry => Some(Box::new(FundingRateStrategy::new(config))),
+        StrategyType::Grid => Some(Box::new(GridStrategy::new(config))),
+        StrategyType::Pair => Some(Box::new(PairStrategy::new(config))),
+    }
+}
+
+/// 读取数值参数，缺省时使用默认值；类型错误时告警并使用默认值
+pub(crate) fn config_f64(config: &serde_json::Value, key: &str, default: f64) -> f64 {
+    match config.get(key) {
+        None | Some(serde_json::Value::Null) => default,
+        Some(value) => value.as_f64().unwrap_or_else(|| {
+            ignored(key, "数字", value, &format!("使用默认值 {}", default));
+            default
+        }),
+    }
+}
+
+/// 读取布尔参数，缺省时使用默认值；类型错误时告警并使用默认值
+pub(crate) fn config_bool(config: &serde_json::Value, key: &str, default: bool) -> bool {
+    match config.get(key) {
+        None | Some(serde_json::Value::Null) => default,
+        Some(value) => value.as_bool().unwrap_or_else(|| {
+            ignored(key, "布尔值", value, &format!("使用默认值 {}", default));
+            default
+        }),
+    }
+}
+
+/// 读取字符串参数；类型错误时告警并视为未配置
+pub(crate) fn config_str(config: &serde_json::Value, key: &str) -> Option<String> {
+    match config.get(key)? {
+        serde_json::Value::Null => None,
+        serde_json::Value::String(s) => Some(s.clone()),
+        value => {
+            ignored(key, "字符串", value, "按未配置处理");
+            None
+        }
+    }
+}
+
+/// 读取字符串数组参数 (统一转大写)；非数组时告警并视为未配置，非字符串元素告警后跳过
+pub(crate) fn config_str_list(config: &serde_json::Value, key: &str) -> Option<Vec<String>> {
+    let list = match config.get(key)? {
+        serde_json::Value::Null => return None,
+        serde_json::Value::Array(list) => list,
+        value => {
+            ignored(key, "字符串数组", value, "按未配置处理");
+            return None;
+        }
+    };
+    Some(
+        list.iter()
+            .filter_map(|item| match item.as_str() {
+                Some(s) => Some(s.to_uppercase()),
+                None => {
+                    warn!("策略参数 `{}` 的元素应为字符串，实际为 {}，已跳过", key, item);
+                    None
+                }
+            })
+            .collect(),
+    )
+}
+
+/// 读取 `max_price_age_ms` 参数 (默认 0，不检查)
+pub(crate) fn config_max_price_age(config: &serde_json::Value) -> i64 {
+    config_f64(config, "max_price_age_ms", 0.0).max(0.0) as i64
+}
+
+/// 报价新鲜度系数: 参与计算的最旧报价 (`oldest`) 距 `now` 超过 `max_age_ms` 时为 None (拒绝计算)，
+/// 否则随报价变旧从 1 线性衰减到 0，用于折减信号置信度；`max_age_ms` 为 0 时恒为 1
+pub(crate) fn price_freshness(oldest: i64, now: i64, max_age_ms: i64) -> Option<f64> {
+    if max_age_ms <= 0 {
+        return Some(1.0);
+    }
+    let age = (now - oldest).max(0);
+    (age <= max_age_ms).then(|| 1.0 - age as f64 / max_age_ms as f64)
+}
+
+fn ignored(key: &str, expected: &str, value: &serde_json::Value, fallback: &str) {
+    warn!(
+        "策略参数 `{}` 应为{}，实际为 {}，已忽略并{}",
+        key, expected, value, fallback
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backtest::ReplayClock;
+    use crate::engine::Engine;
+    use crate::executor::OrderExecutor;
+    use std::sync::{Arc, Mutex};
+
+    /// 收集日志输出
+    #[derive(Clone, Default)]
+    struct LogBuffer(Arc<Mutex<Vec<u8>>>);
+
+    impl std::io::Write for LogBuffer {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    /// 在捕获日志的订阅者下执行，返回结果与日志文本
+    fn with_logs<T>(f: impl FnOnce() -> T) -> (T, String) {
+        let logs = LogBuffer::default();
+        let writer = logs.clone();
+        let subscriber = tracing_subscriber::fmt()
+            .with_writer(move || writer.clone())
+            .with_ansi(false)
+            .finish();
+        let out = tracing::subscriber::with_default(subscriber, f);
+        let text = String::from_utf8(logs.0.lock().unwrap().clone()).unwrap();
+        (out, text)
+    }
+
+    fn config(id: &str, strategy_type: &str, params: serde_json::Value) -> StrategyConfig {
+        serde_json::from_value(serde_json::json!({
+            "id": id, "strategy_type": strategy_type, "name": id, "is_enabled": true, "priority": 1,
+            "config": params,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn string_typed_numeric_field_is_reported_not_silently_defaulted() {
+        let (strategy, logs) = with_logs(|| {
+            build_strategy(&config("tri-1", "triangular", serde_json::json!({"min_profit_rate": "0.002"})))
+        });
+        assert!(strategy.is_some());
+        assert!(logs.contains("WARN"), "{}", logs);
+        assert!(logs.contains("`min_profit_rate` 应为数字，实际为 \"0.002\"，已忽略并使用默认值 0.001"), "{}", logs);
+        // 告警带上策略 ID
+        assert!(logs.contains("id=tri-1"), "{}", logs);
+    }
+
+    #[test]
+    fn malformed_config_falls_back_without_panicking() {
+        // 参数整体不是对象 (如数据库里存了字符串)
+        let (strategy, logs) = with_logs(|| build_strategy(&config("graph-1", "graph", serde_json::json!("{not json"))));
+        assert!(strategy.is_some());
+        assert!(logs.contains("策略参数应为 JSON 对象"), "{}", logs);
+
+        // 单个网格配置无效时跳过该网格并告警
+        let (strategy, logs) = with_logs(|| {
+            build_strategy(&config(
+                "grid-1",
+                "grid",
+                serde_json::json!({"grids": [{"symbol": "BTC/USDT", "lower_price": 110.0, "upper_price": 90.0}]}),
+            ))
+        });
+        assert!(strategy.is_some());
+        assert!(logs.contains("网格配置无效"), "{}", logs);
+        assert!(logs.contains("没有有效的网格配置"), "{}", logs);
+    }
+
+    #[test]
+    fn strategy_that_fails_to_load_is_skipped_with_an_error() {
+        let mut executor = OrderExecutor::new(Default::default(), None);
+        executor.set_simulation_mode(true);
+        let mut engine = Engine::new(executor, None, Arc::new(ReplayClock::new(0)));
+        let bad_schedule = serde_json::json!({
+            "symbol": "BTC/USDT", "lower_price": 90.0, "upper_price": 110.0,
+            "schedule": {"windows": [{"days": ["Funday"], "start": "09:00", "end": "10:00"}]},
+        });
+        let (_, logs) = with_logs(|| {
+            engine.load_strategies(vec![
+                config("grid-1", "grid", bad_schedule),
+                config("tri-1", "triangular", serde_json::json!({})),
+            ])
+        });
+        assert!(logs.contains("ERROR"), "{}", logs);
+        assert!(logs.contains("加载策略 grid-1 失败"), "{}", logs);
+        assert!(!engine.has_strategy_type(StrategyType::Grid));
+        assert!(engine.has_strategy_type(StrategyType::Triangular));
+    }
+}
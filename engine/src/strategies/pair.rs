@@ -0,0 +1,667 @@
+//! 配对 (统计套利) 策略
+//!
+//! 跟踪同一交易所两个交易对的价格比 A/B，比值的 z-score 超过阈值时做空偏贵的一侧、做多偏便宜的一侧，
+//! 回归到 `exit_zscore` 以内时平仓。
+//!
+//! 比值均值 / 方差默认按最近 `window_size` 个样本的简单均值计算；`mean_mode: "ema"` 时改用
+//! 指数移动平均与 EWMA 方差 (按 `half_life` 个样本衰减一半)，对趋势切换反应更快。两种方式每个 tick 均为 O(1)。
+//!
+//! 任一腿为永续合约时，开仓预期收益扣除 `expected_hold_hours` 内按资金费率缓存估算的资金费
+//! (`include_funding`，默认开启)；扣除后不高于 `min_edge` 的开仓信号被抑制。
+//!
+//! 另一腿的价格按引擎时钟计已超过 `max_price_age_ms` (默认 0，不检查) 以上时，该次比值不计入统计也不发信号，
+//! 避免一侧行情冻结时用旧价格算出虚假的偏离；另一腿越接近该上限，信号置信度越低。
+//!
+//! 单腿名义金额 `trade_amount` 低于任一交易对最小名义金额 (交易所元数据) 时不开仓。
+//!
+//! 配置 `max_hold_hours` 后，持仓超过该时长仍未回归的配对 (价差永久偏离) 强制发出平仓信号。
+
+use async_trait::async_trait;
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+use tracing::{debug, warn};
+
+use super::{config_bool, config_f64, config_max_price_age, config_str, price_freshness};
+use crate::engine::{Clock, SystemClock};
+use crate::exchange::{ExchangeId, Ticker};
+use crate::executor::OrderSide;
+use crate::funding::FUNDING_CACHE;
+use crate::instruments::min_notional;
+use crate::strategy::{Signal, SignalAction, SignalLeg, Strategy, StrategyConfig, StrategyType};
+use crate::symbols::same_symbol;
+
+/// 比值统计量
+#[derive(Debug, Clone)]
+enum RatioStats {
+    /// 固定窗口简单均值 / 方差 (滚动累加和)
+    Simple {
+        window: usize,
+        samples: VecDeque<f64>,
+        sum: f64,
+        sum_sq: f64,
+    },
+    /// 指数移动平均 / EWMA 方差
+    Ema { alpha: f64, mean: f64, var: f64, count: usize },
+}
+
+impl RatioStats {
+    fn simple(window: usize) -> Self {
+        Self::Simple {
+            window: window.max(2),
+            samples: VecDeque::new(),
+            sum: 0.0,
+            sum_sq: 0.0,
+        }
+    }
+
+    fn ema(half_life: f64) -> Self {
+        Self::Ema {
+            alpha: 1.0 - 0.5f64.powf(1.0 / half_life.max(1.0)),
+            mean: 0.0,
+            var: 0.0,
+            count: 0,
+        }
+    }
+
+    fn push(&mut self, x: f64) {
+        match self {
+            Self::Simple {
+                window,
+                samples,
+                sum,
+                sum_sq,
+            } => {
+                samples.push_back(x);
+                *sum += x;
+                *sum_sq += x * x;
+                if samples.len() > *window {
+                    let old = samples.pop_front().unwrap_or_default();
+                    *sum -= old;
+                    *sum_sq -= old * old;
+                }
+            }
+            Self::Ema { alpha, mean, var, count } => {
+                if *count == 0 {
+                    *mean = x;
+                } else {
+                    let diff = x - *mean;
+                    let incr = *alpha * diff;
+                    *mean += incr;
+                    *var = (1.0 - *alpha) * (*var + diff * incr);
+                }
+                *count += 1;
+            }
+        }
+    }
+
+    fn count(&self) -> usize {
+        match self {
+            Self::Simple { samples, .. } => samples.len(),
+            Self::Ema { count, .. } => *count,
+        }
+    }
+
+    fn mean_std(&self) -> (f64, f64) {
+        match self {
+            Self::Simple { samples, sum, sum_sq, .. } => {
+                let n = samples.len().max(1) as f64;
+                let mean = sum / n;
+                (mean, (sum_sq / n - mean * mean).max(0.0).sqrt())
+            }
+            Self::Ema { mean, var, .. } => (*mean, var.max(0.0).sqrt()),
+        }
+    }
+
+    /// 当前样本相对统计量的 z-score
+    fn zscore(&self, x: f64) -> Option<f64> {
+        let (mean, std) = self.mean_std();
+        (std > f64::EPSILON).then(|| (x - mean) / std)
+    }
+}
+
+/// 单个配对在某个交易所上的状态
+#[derive(Debug)]
+struct PairState {
+    stats: RatioStats,
+    price_a: f64,
+    price_b: f64,
+    /// 两腿价格的更新时间 (毫秒)
+    updated_a: i64,
+    updated_b: i64,
+    /// 持仓方向: 1 表示做空 A / 做多 B，-1 表示做多 A / 做空 B
+    position: i8,
+    /// 开仓时间 (毫秒)
+    opened_at: i64,
+}
+
+/// 配对策略
+pub struct PairStrategy {
+    id: String,
+    pairs: Vec<(String, String)>,
+    zscore_threshold: f64,
+    exit_zscore: f64,
+    /// 发信号前所需的最少样本数
+    min_samples: usize,
+    trade_amount: f64,
+    fee_rate: f64,
+    /// 开仓预期收益是否扣除持仓期资金费
+    include_funding: bool,
+    /// 预计持仓时长 (毫秒)
+    hold_ms: i64,
+    /// 扣除资金费后的最低收益率
+    min_edge: f64,
+    /// 最长持仓时长 (毫秒，0 为不限)
+    max_hold_ms: i64,
+    /// 价格最大年龄 (毫秒，0 为不检查)
+    max_price_age_ms: i64,
+    /// 引擎时钟，报价年龄以此为准 (加载时由引擎注入)
+    clock: Arc<dyn Clock>,
+    new_stats: RatioStats,
+    states: HashMap<(usize, ExchangeId), PairState>,
+}
+
+impl PairStrategy {
+    pub fn new(config: &StrategyConfig) -> Self {
+        let params = &config.config;
+        let pairs = params
+            .get("pairs")
+            .and_then(|v| v.as_array())
+            .map(|list| {
+                list.iter()
+                    .filter_map(|p| {
+                        let pair = p.as_array().and_then(|a| match a.as_slice() {
+                            [a, b] => Some((a.as_str()?.to_string(), b.as_str()?.to_string())),
+                            _ => None,
+                        });
+                        if pair.is_none() {
+                            warn!("策略参数 `pairs` 的元素应为 2 个交易对组成的数组，实际为 {}，已跳过", p);
+                        }
+                        pair
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        let window_size = config_f64(params, "window_size", 100.0) as usize;
+        let new_stats = match config_str(params, "mean_mode").as_deref() {
+            Some("ema") => RatioStats::ema(config_f64(params, "half_life", window_size as f64 / 2.0)),
+            Some("simple") | None => RatioStats::simple(window_size),
+            Some(other) => {
+                warn!("策略参数 `mean_mode` 不支持 {}，使用简单均值", other);
+                RatioStats::simple(window_size)
+            }
+        };
+        Self {
+            id: config.id.clone(),
+            pairs,
+            zscore_threshold: config_f64(params, "zscore_threshold", 2.0),
+            exit_zscore: config_f64(params, "exit_zscore", 0.5),
+            min_samples: config_f64(params, "min_samples", window_size as f64) as usize,
+            trade_amount: config_f64(params, "trade_amount", 100.0),
+            fee_rate: config_f64(params, "taker_fee", config_f64(params, "fee_rate", 0.001)),
+            include_funding: config_bool(params, "include_funding", true),
+            hold_ms: (config_f64(params, "expected_hold_hours", 24.0).max(0.0) * 3_600_000.0) as i64,
+            min_edge: config_f64(params, "min_edge", 0.0),
+            max_hold_ms: (config_f64(params, "max_hold_hours", 0.0).max(0.0) * 3_600_000.0) as i64,
+            max_price_age_ms: config_max_price_age(params),
+            clock: Arc::new(SystemClock),
+            new_stats,
+            states: HashMap::new(),
+        }
+    }
+
+    fn signal(
+        &self,
+        exchange: ExchangeId,
+        pair: &(String, String),
+        state: &PairState,
+        sell_a: bool,
+        zscore: f64,
+        timestamp: i64,
+    ) -> Signal {
+        let (mean, _) = state.stats.mean_std();
+        let ratio = state.price_a / state.price_b;
+        let freshness =
+            price_freshness(state.updated_a.min(state.updated_b), self.clock.now_ms(), self.max_price_age_ms).unwrap_or(0.0);
+        // 比值回归均值的预期收益 (开平两腿共四次手续费)
+        let profit_rate = ((ratio - mean) / mean).abs() - 4.0 * self.fee_rate;
+        let (side_a, side_b) = if sell_a {
+            (OrderSide::Sell, OrderSide::Buy)
+        } else {
+            (OrderSide::Buy, OrderSide::Sell)
+        };
+        let mut signal = Signal::new(
+            self.id.clone(),
+            StrategyType::Pair,
+            exchange,
+            profit_rate,
+            profit_rate * self.trade_amount,
+            (zscore.abs() / (self.zscore_threshold * 2.0)).min(1.0) * freshness,
+            format!("{}->{}", pair.0, pair.1),
+            timestamp,
+        )
+        .with_fee_rate(4.0 * self.fee_rate);
+        signal.legs = vec![
+            SignalLeg {
+                exchange,
+                symbol: pair.0.clone(),
+                side: side_a,
+                price: state.price_a,
+            },
+            SignalLeg {
+                exchange,
+                symbol: pair.1.clone(),
+                side: side_b,
+                price: state.price_b,
+            },
+        ];
+        signal
+    }
+
+    /// 单腿名义金额是否满足两腿交易对的最小名义金额
+    fn meets_min_notional(&self, signal: &Signal) -> bool {
+        signal.legs.iter().all(|leg| {
+            let min = min_notional(leg.exchange, &leg.symbol);
+            if min > 0.0 && self.trade_amount < min {
+                debug!(
+                    "配对 {} {:?} {} 单腿金额 {} 低于最小名义金额 {}，抑制开仓",
+                    signal.path, leg.exchange, leg.symbol, self.trade_amount, min
+                );
+                return false;
+            }
+            true
+        })
+    }
+
+    /// 开仓信号扣除持仓期内两腿的预计资金费；扣除后收益不足时返回 None
+    fn apply_funding(&self, mut signal: Signal) -> Option<Signal> {
+        if !self.include_funding {
+            return Some(signal);
+        }
+        // 两腿名义金额相同，资金费按单腿名义金额比例直接相加
+        let funding: f64 = signal
+            .legs
+            .iter()
+            .map(|leg| FUNDING_CACHE.projected_cost(leg.exchange, &leg.symbol, leg.side, signal.timestamp, self.hold_ms))
+            .sum();
+        if funding == 0.0 {
+            return Some(signal);
+        }
+        signal.set_profit_rate(signal.profit_rate - funding);
+        signal.expected_profit = signal.profit_rate * self.trade_amount;
+        if signal.profit_rate <= self.min_edge {
+            debug!(
+                "配对 {} 扣除预计资金费 {:.4}% 后收益 {:.4}% 不足，抑制开仓",
+                signal.path,
+                funding * 100.0,
+                signal.profit_rate * 100.0
+            );
+            return None;
+        }
+        Some(signal)
+    }
+}
+
+#[async_trait]
+impl Strategy for PairStrategy {
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn strategy_type(&self) -> StrategyType {
+        StrategyType::Pair
+    }
+
+    fn set_clock(&mut self, clock: Arc<dyn Clock>) {
+        self.clock = clock;
+    }
+
+    fn symbols(&self) -> Vec<String> {
+        self.pairs.iter().flat_map(|(a, b)| [a.clone(), b.clone()]).collect()
+    }
+
+    fn debug_state(&self) -> serde_json::Value {
+        let states: Vec<serde_json::Value> = self
+            .states
+            .iter()
+            .map(|((i, exchange), state)| {
+                let (mean, std) = state.stats.mean_std();
+                serde_json::json!({
+                    "pair": self.pairs.get(*i),
+                    "exchange": exchange,
+                    "priceA": state.price_a,
+                    "priceB": state.price_b,
+                    "samples": state.stats.count(),
+                    "mean": mean,
+                    "std": std,
+                    "position": state.position,
+                    "openedAt": state.opened_at,
+                })
+            })
+            .collect();
+        serde_json::json!({
+            "zscoreThreshold": self.zscore_threshold,
+            "exitZscore": self.exit_zscore,
+            "minSamples": self.min_samples,
+            "feeRate": self.fee_rate,
+            "minEdge": self.min_edge,
+            "maxHoldMs": self.max_hold_ms,
+            "maxPriceAgeMs": self.max_price_age_ms,
+            "states": states,
+        })
+    }
+
+    fn path_edges(&self) -> Vec<(String, Option<f64>)> {
+        let mut edges = vec![];
+        for (i, pair) in self.pairs.iter().enumerate() {
+            let path = format!("{}->{}", pair.0, pair.1);
+            let mut states: Vec<_> = self.states.iter().filter(|((j, _), _)| *j == i).collect();
+            if states.is_empty() {
+                edges.push((path, None));
+                continue;
+            }
+            states.sort_by_key(|((_, exchange), _)| format!("{:?}", exchange));
+            for ((_, exchange), state) in states {
+                // 比值回归均值的收益 (与开仓信号同口径，未扣资金费)
+                let edge = (state.stats.count() >= self.min_samples && state.price_a > 0.0 && state.price_b > 0.0)
+                    .then(|| {
+                        let (mean, _) = state.stats.mean_std();
+                        ((state.price_a / state.price_b - mean) / mean).abs() - 4.0 * self.fee_rate
+                    });
+                edges.push((format!("{:?}:{}", exchange, path), edge));
+            }
+        }
+        edges
+    }
+
+    async fn on_ticker(&mut self, ticker: &Ticker) -> Option<Signal> {
+        let price = if ticker.bid > 0.0 && ticker.ask > 0.0 {
+            (ticker.bid + ticker.ask) / 2.0
+        } else {
+            ticker.last
+        };
+        if price <= 0.0 {
+            return None;
+        }
+        let mut out = None;
+        for i in 0..self.pairs.len() {
+            let pair = &self.pairs[i];
+            let is_a = same_symbol(&pair.0, &ticker.symbol);
+            if !is_a && !same_symbol(&pair.1, &ticker.symbol) {
+                continue;
+            }
+            let state = self.states.entry((i, ticker.exchange)).or_insert_with(|| PairState {
+                stats: self.new_stats.clone(),
+                price_a: 0.0,
+                price_b: 0.0,
+                updated_a: 0,
+                updated_b: 0,
+                position: 0,
+                opened_at: 0,
+            });
+            if is_a {
+                state.price_a = price;
+                state.updated_a = ticker.timestamp;
+            } else {
+                state.price_b = price;
+                state.updated_b = ticker.timestamp;
+            }
+            if state.price_a <= 0.0 || state.price_b <= 0.0 {
+                continue;
+            }
+            if price_freshness(state.updated_a.min(state.updated_b), self.clock.now_ms(), self.max_price_age_ms).is_none() {
+                debug!("配对 {}->{} {:?} 另一腿价格过旧，跳过", pair.0, pair.1, ticker.exchange);
+                continue;
+            }
+            let ratio = state.price_a / state.price_b;
+            state.stats.push(ratio);
+            if out.is_some() {
+                continue;
+            }
+            let held_too_long = state.position != 0
+                && self.max_hold_ms > 0
+                && ticker.timestamp - state.opened_at >= self.max_hold_ms;
+            if state.stats.count() < self.min_samples && !held_too_long {
+                continue;
+            }
+            let zscore = state.stats.zscore(ratio);
+            // 开仓: 比值偏高时卖 A 买 B；平仓: 与开仓方向相反；持仓超时强制平仓
+            let (action, sell_a) = match (state.position, zscore) {
+                (p, _) if held_too_long => {
+                    warn!(
+                        "配对 {}->{} {:?} 持仓超过 {} 小时未回归，强制平仓",
+                        pair.0,
+                        pair.1,
+                        ticker.exchange,
+                        self.max_hold_ms as f64 / 3_600_000.0
+                    );
+                    (SignalAction::Close, p < 0)
+                }
+                (0, Some(z)) if z.abs() >= self.zscore_threshold => (SignalAction::Open, z > 0.0),
+                (p, Some(z)) if p != 0 && z.abs() <= self.exit_zscore => (SignalAction::Close, p < 0),
+                _ => continue,
+            };
+            let zscore = zscore.unwrap_or(0.0);
+            let state = &self.states[&(i, ticker.exchange)];
+            let signal = self
+                .signal(ticker.exchange, &self.pairs[i], state, sell_a, zscore, ticker.timestamp)
+                .with_action(action);
+            let signal = match action {
+                SignalAction::Open if !self.meets_min_notional(&signal) => continue,
+                SignalAction::Open => match self.apply_funding(signal) {
+                    Some(signal) => signal,
+                    None => continue,
+                },
+                SignalAction::Close => signal,
+            };
+            if let Some(state) = self.states.get_mut(&(i, ticker.exchange)) {
+                state.position = match action {
+                    SignalAction::Open => if sell_a { 1 } else { -1 },
+                    SignalAction::Close => 0,
+                };
+                state.opened_at = ticker.timestamp;
+            }
+            out = Some(signal);
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backtest::ReplayClock;
+    use crate::exchange::testing::ticker;
+    use crate::funding::FundingRate;
+
+    fn fed(mut stats: RatioStats, samples: impl IntoIterator<Item = f64>) -> RatioStats {
+        for x in samples {
+            stats.push(x);
+        }
+        stats
+    }
+
+    #[test]
+    fn simple_mean_matches_the_trailing_window() {
+        let samples: Vec<f64> = (0..50).map(|i| 1.0 + (i % 7) as f64 * 0.01).collect();
+        let stats = fed(RatioStats::simple(20), samples.iter().copied());
+        assert_eq!(stats.count(), 20);
+
+        let window = &samples[30..];
+        let mean = window.iter().sum::<f64>() / 20.0;
+        let std = (window.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / 20.0).sqrt();
+        let (got_mean, got_std) = stats.mean_std();
+        assert!((got_mean - mean).abs() < 1e-9);
+        assert!((got_std - std).abs() < 1e-9);
+        assert!(fed(RatioStats::simple(20), [1.0; 5]).zscore(1.1).is_none(), "零方差时没有 z-score");
+    }
+
+    #[test]
+    fn ema_tracks_a_regime_shift_faster_than_the_simple_mean() {
+        // 比值长期在 1.0 附近，之后跳到 2.0 并保持 10 个样本
+        let history = (0..100).map(|i| 1.0 + if i % 2 == 0 { 0.01 } else { -0.01 });
+        let shifted = std::iter::repeat_n(2.0, 10);
+        let simple = fed(RatioStats::simple(100), history.clone().chain(shifted.clone()));
+        let ema = fed(RatioStats::ema(10.0), history.chain(shifted));
+
+        // 半衰期 10 个样本: 均值恰好走完一半；简单均值只移动了 1/10
+        let (ema_mean, _) = ema.mean_std();
+        let (simple_mean, _) = simple.mean_std();
+        assert!((ema_mean - 1.5).abs() < 0.01, "ema mean {}", ema_mean);
+        assert!((simple_mean - 1.1).abs() < 0.01, "simple mean {}", simple_mean);
+        // 新水平在 EMA 下已不算极端偏离
+        assert!(ema.zscore(2.0).unwrap() < simple.zscore(2.0).unwrap());
+    }
+
+    #[tokio::test]
+    async fn ratio_deviation_opens_and_reversion_closes() {
+        let mut strategy = PairStrategy::new(
+            &serde_json::from_value(serde_json::json!({
+                "id": "pair", "strategy_type": "pair", "name": "pair", "is_enabled": true, "priority": 1,
+                "config": {"pairs": [["FIL/USDT", "ATOM/USDT"]], "window_size": 20},
+            }))
+            .unwrap(),
+        );
+        strategy.on_ticker(&ticker("ATOM/USDT").quote(10.0, 10.0).at(0).build()).await;
+        // 比值在 1 附近小幅波动，样本未满或 z-score 未达阈值时不发信号
+        for i in 0..20 {
+            let a = if i % 2 == 0 { 10.001 } else { 9.999 };
+            assert!(strategy.on_ticker(&ticker("FIL/USDT").quote(a, a).at(i + 1).build()).await.is_none());
+        }
+
+        // A 腿上涨约 1%: 比值偏高，卖 A 买 B
+        let open = strategy.on_ticker(&ticker("FIL/USDT").quote(10.1, 10.1).at(21).build()).await.expect("比值偏离应开仓");
+        assert_eq!(open.action, SignalAction::Open);
+        assert!(matches!(open.legs[0].side, OrderSide::Sell));
+        assert!(matches!(open.legs[1].side, OrderSide::Buy));
+
+        // 回到均值附近平仓，方向与开仓相反
+        let close = strategy.on_ticker(&ticker("FIL/USDT").quote(10.0, 10.0).at(22).build()).await.expect("比值回归应平仓");
+        assert_eq!(close.action, SignalAction::Close);
+        assert!(matches!(close.legs[0].side, OrderSide::Buy));
+        assert!(matches!(close.legs[1].side, OrderSide::Sell));
+    }
+
+    fn perp_strategy(include_funding: bool) -> PairStrategy {
+        PairStrategy::new(
+            &serde_json::from_value(serde_json::json!({
+                "id": "pair-perp",
+                "strategy_type": "pair",
+                "name": "pair-perp",
+                "is_enabled": true,
+                "priority": 1,
+                "config": {
+                    "pairs": [["FIL/USDT:USDT", "ATOM/USDT:USDT"]],
+                    "window_size": 20,
+                    "fee_rate": 0.001,
+                    "expected_hold_hours": 24,
+                    "include_funding": include_funding,
+                },
+            }))
+            .unwrap(),
+        )
+    }
+
+    /// 比值在 1 附近小幅波动 20 个样本后，A 腿上涨约 1%，返回最后一笔行情产生的信号
+    async fn spike(strategy: &mut PairStrategy) -> Option<Signal> {
+        strategy.on_ticker(&ticker("ATOM/USDT:USDT").quote(10.0, 10.0).at(0).build()).await;
+        for i in 0..20 {
+            let a = if i % 2 == 0 { 10.001 } else { 9.999 };
+            assert!(strategy.on_ticker(&ticker("FIL/USDT:USDT").quote(a, a).at(i + 1).build()).await.is_none());
+        }
+        strategy.on_ticker(&ticker("FIL/USDT:USDT").quote(10.1, 10.1).at(21).build()).await
+    }
+
+    #[tokio::test]
+    async fn projected_funding_on_the_long_leg_suppresses_a_marginal_open() {
+        // 比值偏高时卖 A 买 B: 多头 B 每 8 小时支付 0.25%，24 小时共 0.75%
+        FUNDING_CACHE.update(
+            ExchangeId::Binance,
+            "ATOM/USDT:USDT",
+            FundingRate {
+                rate: 0.0025,
+                ..Default::default()
+            },
+        );
+
+        let signal = spike(&mut perp_strategy(false)).await.expect("未扣资金费时应开仓");
+        assert_eq!(signal.action, SignalAction::Open);
+        assert!(signal.profit_rate > 0.0 && signal.profit_rate < 0.0075, "{}", signal.profit_rate);
+        assert_eq!(signal.legs[1].side, OrderSide::Buy);
+
+        assert!(spike(&mut perp_strategy(true)).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn position_held_past_max_hold_is_force_closed() {
+        let mut strategy = PairStrategy::new(
+            &serde_json::from_value(serde_json::json!({
+                "id": "pair-hold",
+                "strategy_type": "pair",
+                "name": "pair-hold",
+                "is_enabled": true,
+                "priority": 1,
+                "config": {
+                    "pairs": [["FIL/USDT:USDT", "ATOM/USDT:USDT"]],
+                    "window_size": 20,
+                    "fee_rate": 0.001,
+                    "include_funding": false,
+                    "max_hold_hours": 1,
+                },
+            }))
+            .unwrap(),
+        );
+        let open = spike(&mut strategy).await.expect("比值偏离应开仓");
+        assert_eq!(open.action, SignalAction::Open);
+        assert_eq!(open.legs[0].side, OrderSide::Sell);
+
+        // 价差仍未回归: 持仓不足 1 小时不平仓，满 1 小时强制平仓 (方向与开仓相反)
+        assert!(strategy.on_ticker(&ticker("FIL/USDT:USDT").quote(10.1, 10.1).at(1_800_000).build()).await.is_none());
+        let close = strategy
+            .on_ticker(&ticker("FIL/USDT:USDT").quote(10.1, 10.1).at(21 + 3_600_000).build())
+            .await
+            .expect("持仓超时应强制平仓");
+        assert_eq!(close.action, SignalAction::Close);
+        assert_eq!(close.legs[0].side, OrderSide::Buy);
+        assert_eq!(close.legs[1].side, OrderSide::Sell);
+        // 平仓后不再重复发信号
+        assert!(strategy.on_ticker(&ticker("FIL/USDT:USDT").quote(10.1, 10.1).at(7_300_000).build()).await.is_none());
+    }
+
+    fn fresh_only_strategy(now: i64) -> PairStrategy {
+        let mut strategy = PairStrategy::new(
+            &serde_json::from_value(serde_json::json!({
+                "id": "pair-fresh",
+                "strategy_type": "pair",
+                "name": "pair-fresh",
+                "is_enabled": true,
+                "priority": 1,
+                "config": {
+                    "pairs": [["FIL/USDT:USDT", "ATOM/USDT:USDT"]],
+                    "window_size": 20,
+                    "fee_rate": 0.001,
+                    "include_funding": false,
+                    "max_price_age_ms": 5000,
+                },
+            }))
+            .unwrap(),
+        );
+        strategy.set_clock(Arc::new(ReplayClock::new(now)));
+        strategy
+    }
+
+    #[tokio::test]
+    async fn stale_leg_is_neither_sampled_nor_traded() {
+        let signal = spike(&mut fresh_only_strategy(21)).await.expect("两腿都新鲜时应开仓");
+        assert_eq!(signal.action, SignalAction::Open);
+
+        // B 腿停在 0 毫秒，引擎时钟已到 10 秒后: 比值不进统计，偏离也不发信号
+        let mut strategy = fresh_only_strategy(10_021);
+        strategy.on_ticker(&ticker("ATOM/USDT:USDT").quote(10.0, 10.0).at(0).build()).await;
+        for i in 0..20 {
+            let a = if i % 2 == 0 { 10.001 } else { 9.999 };
+            assert!(strategy.on_ticker(&ticker("FIL/USDT:USDT").quote(a, a).at(10_000 + i).build()).await.is_none());
+        }
+        assert!(strategy.on_ticker(&ticker("FIL/USDT:USDT").quote(10.1, 10.1).at(10_021).build()).await.is_none());
+        assert_eq!(strategy.states[&(0, ExchangeId::Binance)].stats.count(), 0);
+    }
+}
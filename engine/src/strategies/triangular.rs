@@ -0,0 +1,708 @@
+//! 三角套利策略
+//!
+//! 以计价币 (默认 USDT) 出发，经两个中间币种换回计价币: USDT -> A -> B -> USDT。
+//! 每条腿按方向取盘口对应一侧 (买入用 ask，卖出用 bid)，扣除每腿手续费后计算收益率。
+//!
+//! 开启 `cross_venue` 后每条腿可在不同交易所成交: 逐条腿在已连接的交易所中选择价格最优者，
+//! 但换了交易所的腿需要在该交易所预先持有输入币种 (`inventory` 配置)，否则不能跨所路由。
+//! 所选交易所按腿写入信号的 `legs`。评分卡判定为拦截的交易所不参与选择，收益相同时优先评分卡排序靠前的交易所。
+//!
+//! 开启 `report_near_misses` 后，扣费前有利可图但未达阈值的机会附带盈亏平衡费率上报。
+//!
+//! 设置 `corroboration_window_ms` 后，三条腿的报价更新时间相差超过该窗口的组合不参与计算:
+//! 收益可能只来自某条腿的旧报价，等三条腿在窗口内先后更新后再判断。
+//!
+//! 任一条腿的报价按引擎时钟计年龄超过 `max_price_age_ms` (默认 0，不检查) 时组合同样不参与计算，
+//! 整个交易所行情冻结时同样生效，避免用旧价格算出虚假的套利机会；报价越接近该上限，信号置信度越低。
+//!
+//! 按 `trade_amount` 逐腿推算的成交金额低于交易对最小名义金额 (交易所元数据) 的组合不发信号，
+//! 否则执行时会被交易所拒单。
+//!
+//! 运行时禁用的三角形 (见 [`crate::disabled_paths`]) 直接跳过，其余三角形照常评估。
+
+use async_trait::async_trait;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::Arc;
+use tracing::{debug, warn};
+
+use super::{config_bool, config_f64, config_max_price_age, config_str, config_str_list, price_freshness};
+use crate::disabled_paths::DISABLED_PATHS;
+use crate::engine::{Clock, SystemClock};
+use crate::exchange::{ExchangeId, Symbol, Ticker};
+use crate::executor::OrderSide;
+use crate::instruments::min_notional;
+use crate::scorecard::VenueVerdict;
+use crate::strategy::{breakeven_fee_rate, NearMiss, Signal, SignalLeg, Strategy, StrategyConfig, StrategyType};
+
+/// 默认中间币种
+const DEFAULT_BASES: &[&str] = &["BTC", "ETH", "BNB", "SOL", "XRP"];
+/// 同一三角形两次信号的最小间隔 (毫秒)
+const SIGNAL_COOLDOWN_MS: i64 = 1_000;
+/// 两次取出之间最多缓存的接近成交机会
+const MAX_NEAR_MISSES: usize = 100;
+
+/// 盘口报价
+#[derive(Debug, Clone, Copy)]
+struct Quote {
+    bid: f64,
+    ask: f64,
+    /// 报价更新时间 (毫秒)
+    timestamp: i64,
+}
+
+/// 三角形: [计价币, A, B]，依次兑换后回到计价币
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct Triangle([String; 3]);
+
+impl Triangle {
+    fn key(&self) -> String {
+        format!("{}->{}->{}->{}", self.0[0], self.0[1], self.0[2], self.0[0])
+    }
+
+    /// 三条腿的 (输入币, 输出币)
+    fn legs(&self) -> [(&str, &str); 3] {
+        [
+            (&self.0[0], &self.0[1]),
+            (&self.0[1], &self.0[2]),
+            (&self.0[2], &self.0[0]),
+        ]
+    }
+
+    fn involves(&self, base: &str, quote: &str) -> bool {
+        self.0.iter().any(|a| a == base) && self.0.iter().any(|a| a == quote)
+    }
+}
+
+/// 单条腿的成交方案
+#[derive(Debug, Clone)]
+struct LegFill {
+    exchange: ExchangeId,
+    symbol: String,
+    side: OrderSide,
+    price: f64,
+    /// 1 单位输入币可换得的输出币 (已扣手续费)
+    rate: f64,
+    /// 所用报价的更新时间 (毫秒)
+    timestamp: i64,
+}
+
+/// 三角套利策略
+pub struct TriangularStrategy {
+    id: String,
+    triangles: Vec<Triangle>,
+    min_profit_rate: f64,
+    fee_rate: f64,
+    /// 每次套利投入的计价币数量
+    trade_amount: f64,
+    /// 三条腿报价更新时间的最大跨度 (毫秒，0 为不检查)
+    corroboration_window_ms: i64,
+    /// 报价最大年龄 (毫秒，0 为不检查)
+    max_price_age_ms: i64,
+    /// 引擎时钟，报价年龄以此为准 (加载时由引擎注入)
+    clock: Arc<dyn Clock>,
+    cross_venue: bool,
+    /// 允许路由的交易所 (为空表示不限)
+    venues: HashSet<ExchangeId>,
+    /// 评分卡排序 (由引擎更新，较优者在前)
+    venue_ranking: Vec<(ExchangeId, VenueVerdict)>,
+    /// 各交易所预先持有的币种数量 (跨所路由时使用)
+    inventory: HashMap<ExchangeId, HashMap<String, f64>>,
+    /// (base, quote) -> 交易所 -> 报价
+    quotes: HashMap<(String, String), HashMap<ExchangeId, Quote>>,
+    last_signal: HashMap<String, i64>,
+    /// 记录扣费前有正收益但未达阈值的机会及其盈亏平衡费率
+    report_near_misses: bool,
+    near_misses: Vec<NearMiss>,
+    last_near_miss: HashMap<String, i64>,
+}
+
+impl TriangularStrategy {
+    pub fn new(config: &StrategyConfig) -> Self {
+        let params = &config.config;
+        let quote = config_str(params, "quote_currency")
+            .unwrap_or_else(|| "USDT".to_string())
+            .to_uppercase();
+        let bases = config_str_list(params, "base_currencies")
+            .unwrap_or_else(|| DEFAULT_BASES.iter().map(|s| s.to_string()).collect());
+        let triangles = match params.get("triangles").and_then(|v| v.as_array()) {
+            Some(list) => list
+                .iter()
+                .filter_map(|t| {
+                    let assets: Option<[String; 3]> = t
+                        .as_array()
+                        .and_then(|a| a.iter().map(|v| v.as_str().map(str::to_uppercase)).collect::<Option<Vec<_>>>())
+                        .and_then(|a| a.try_into().ok());
+                    if assets.is_none() {
+                        warn!("策略参数 `triangles` 的元素应为 3 个币种组成的数组，实际为 {}，已跳过", t);
+                    }
+                    Some(Triangle(assets?))
+                })
+                .collect(),
+            None => build_triangles(&quote, &bases),
+        };
+        let venues = config_str_list(params, "venues")
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|v| parse_exchange(v))
+            .collect();
+        let mut inventory: HashMap<ExchangeId, HashMap<String, f64>> = HashMap::new();
+        if let Some(map) = params.get("inventory").and_then(|v| v.as_object()) {
+            for (exchange, assets) in map {
+                let (Some(exchange), Some(assets)) = (parse_exchange(exchange), assets.as_object()) else {
+                    continue;
+                };
+                let held = inventory.entry(exchange).or_default();
+                for (asset, amount) in assets {
+                    if let Some(amount) = amount.as_f64() {
+                        held.insert(asset.to_uppercase(), amount);
+                    }
+                }
+            }
+        }
+        Self {
+            id: config.id.clone(),
+            triangles,
+            min_profit_rate: config_f64(params, "min_profit_rate", 0.001),
+            fee_rate: config_f64(params, "taker_fee", config_f64(params, "fee_rate", 0.001)),
+            trade_amount: config_f64(params, "trade_amount", 100.0),
+            corroboration_window_ms: config_f64(params, "corroboration_window_ms", 0.0).max(0.0) as i64,
+            max_price_age_ms: config_max_price_age(params),
+            clock: Arc::new(SystemClock),
+            cross_venue: config_bool(params, "cross_venue", false),
+            venues,
+            venue_ranking: vec![],
+            inventory,
+            quotes: HashMap::new(),
+            last_signal: HashMap::new(),
+            report_near_misses: config_bool(params, "report_near_misses", false),
+            near_misses: vec![],
+            last_near_miss: HashMap::new(),
+        }
+    }
+
+    fn record_near_miss(&mut self, path: &str, exchange: ExchangeId, net_rate: f64, timestamp: i64) {
+        let gross_growth = (1.0 + net_rate) / (1.0 - self.fee_rate).powi(3);
+        if gross_growth <= 1.0 || self.near_misses.len() >= MAX_NEAR_MISSES {
+            return;
+        }
+        if self
+            .last_near_miss
+            .get(path)
+            .is_some_and(|last| timestamp - last < SIGNAL_COOLDOWN_MS)
+        {
+            return;
+        }
+        self.last_near_miss.insert(path.to_string(), timestamp);
+        self.near_misses.push(NearMiss {
+            strategy_id: self.id.clone(),
+            exchange,
+            path: path.to_string(),
+            gross_rate: gross_growth - 1.0,
+            net_rate,
+            breakeven_fee_rate: breakeven_fee_rate(gross_growth, 3),
+            timestamp,
+        });
+    }
+
+    fn update_quote(&mut self, ticker: &Ticker) -> Option<(String, String)> {
+        let Symbol { base, quote } = ticker.pair()?;
+        if ticker.bid <= 0.0 || ticker.ask <= 0.0 {
+            return None;
+        }
+        self.quotes.entry((base.clone(), quote.clone())).or_default().insert(
+            ticker.exchange,
+            Quote {
+                bid: ticker.bid,
+                ask: ticker.ask,
+                timestamp: ticker.timestamp,
+            },
+        );
+        Some((base, quote))
+    }
+
+    /// 在指定交易所把 from 兑换为 to 的方案 (to/from 市场买入用 ask，from/to 市场卖出用 bid)
+    fn leg_on(&self, exchange: ExchangeId, from: &str, to: &str) -> Option<LegFill> {
+        let keep = 1.0 - self.fee_rate;
+        if let Some(q) = self.quote_on(exchange, to, from) {
+            return Some(LegFill {
+                exchange,
+                symbol: Symbol::new(to, from).to_string(),
+                side: OrderSide::Buy,
+                price: q.ask,
+                rate: keep / q.ask,
+                timestamp: q.timestamp,
+            });
+        }
+        let q = self.quote_on(exchange, from, to)?;
+        Some(LegFill {
+            exchange,
+            symbol: Symbol::new(from, to).to_string(),
+            side: OrderSide::Sell,
+            price: q.bid,
+            rate: keep * q.bid,
+            timestamp: q.timestamp,
+        })
+    }
+
+    fn quote_on(&self, exchange: ExchangeId, base: &str, quote: &str) -> Option<Quote> {
+        self.quotes
+            .get(&(base.to_string(), quote.to_string()))?
+            .get(&exchange)
+            .copied()
+    }
+
+    fn has_inventory(&self, exchange: ExchangeId, asset: &str, amount: f64) -> bool {
+        self.inventory
+            .get(&exchange)
+            .and_then(|held| held.get(asset))
+            .is_some_and(|held| *held >= amount)
+    }
+
+    /// 有报价且未被评分卡拦截的交易所，按评分卡排序 (未排序的交易所在后，按名称)
+    fn candidate_venues(&self) -> Vec<ExchangeId> {
+        let blocked = |v: &ExchangeId| {
+            self.venue_ranking
+                .iter()
+                .any(|(venue, verdict)| venue == v && *verdict == VenueVerdict::Blocked)
+        };
+        let mut venues: Vec<ExchangeId> = self
+            .quotes
+            .values()
+            .flat_map(|by_venue| by_venue.keys().copied())
+            .filter(|v| self.venues.is_empty() || self.venues.contains(v))
+            .filter(|v| !blocked(v))
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .collect();
+        let rank = |v: &ExchangeId| {
+            self.venue_ranking
+                .iter()
+                .position(|(venue, _)| venue == v)
+                .unwrap_or(usize::MAX)
+        };
+        venues.sort_by_key(|v| (rank(v), format!("{:?}", v)));
+        venues
+    }
+
+    /// 各腿报价是否在印证窗口内先后更新
+    fn corroborated(&self, fills: &[LegFill]) -> bool {
+        if self.corroboration_window_ms <= 0 {
+            return true;
+        }
+        let newest = fills.iter().map(|f| f.timestamp).max().unwrap_or(0);
+        let oldest = fills.iter().map(|f| f.timestamp).min().unwrap_or(0);
+        newest - oldest <= self.corroboration_window_ms
+    }
+
+    /// 各腿报价的新鲜度系数；任一条腿过旧时为 None
+    fn freshness(&self, fills: &[LegFill]) -> Option<f64> {
+        let oldest = fills.iter().map(|f| f.timestamp).min()?;
+        price_freshness(oldest, self.clock.now_ms(), self.max_price_age_ms)
+    }
+
+    /// 按 `trade_amount` 逐腿推算的成交金额 (以各交易对计价币计) 是否都不低于最小名义金额
+    fn meets_min_notional(&self, fills: &[LegFill]) -> bool {
+        let mut amount = self.trade_amount;
+        for fill in fills {
+            // 买入腿的输入币就是计价币；卖出腿按卖出数量乘价格
+            let notional = match fill.side {
+                OrderSide::Buy => amount,
+                OrderSide::Sell => amount * fill.price,
+            };
+            let min = min_notional(fill.exchange, &fill.symbol);
+            if min > 0.0 && notional < min {
+                debug!(
+                    "{:?} {} 成交金额 {:.4} 低于最小名义金额 {}，跳过",
+                    fill.exchange, fill.symbol, notional, min
+                );
+                return false;
+            }
+            amount *= fill.rate;
+        }
+        true
+    }
+
+    /// 计算三角形的最优成交方案，返回 (收益率, 各腿方案)；报价未相互印证或过旧的组合不计入
+    fn calculate_profit(&self, triangle: &Triangle) -> Option<(f64, Vec<LegFill>)> {
+        let venues = self.candidate_venues();
+        let legs = triangle.legs();
+        let mut best: Option<(f64, Vec<LegFill>)> = None;
+        let mut consider = |fills: Vec<LegFill>| {
+            if !self.corroborated(&fills) || self.freshness(&fills).is_none() {
+                return;
+            }
+            let profit = fills.iter().map(|f| f.rate).product::<f64>() - 1.0;
+            if best.as_ref().is_none_or(|(p, _)| profit > *p) {
+                best = Some((profit, fills));
+            }
+        };
+
+        if !self.cross_venue {
+            for &venue in &venues {
+                let fills: Option<Vec<LegFill>> = legs.iter().map(|(from, to)| self.leg_on(venue, from, to)).collect();
+                if let Some(fills) = fills {
+                    consider(fills);
+                }
+            }
+            return best;
+        }
+
+        // 跨所: 枚举每条腿的交易所；换所的腿要求目标交易所持有足够的输入币
+        let options: Vec<Vec<LegFill>> = legs
+            .iter()
+            .map(|(from, to)| venues.iter().filter_map(|v| self.leg_on(*v, from, to)).collect())
+            .collect();
+        for first in &options[0] {
+            let amount1 = self.trade_amount * first.rate;
+            for second in &options[1] {
+                if second.exchange != first.exchange && !self.has_inventory(second.exchange, legs[1].0, amount1) {
+                    continue;
+                }
+                let amount2 = amount1 * second.rate;
+                for third in &options[2] {
+                    if third.exchange != second.exchange && !self.has_inventory(third.exchange, legs[2].0, amount2) {
+                        continue;
+                    }
+                    consider(vec![first.clone(), second.clone(), third.clone()]);
+                }
+            }
+        }
+        best
+    }
+}
+
+/// 由计价币与中间币种生成三角形 (每对币种两个方向)
+fn build_triangles(quote: &str, bases: &[String]) -> Vec<Triangle> {
+    let mut out = vec![];
+    for a in bases {
+        for b in bases {
+            if a != b && a != quote && b != quote {
+                out.push(Triangle([quote.to_string(), a.clone(), b.clone()]));
+            }
+        }
+    }
+    out
+}
+
+fn parse_exchange(value: &str) -> Option<ExchangeId> {
+    serde_json::from_value(serde_json::Value::String(value.to_lowercase())).ok()
+}
+
+#[async_trait]
+impl Strategy for TriangularStrategy {
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn strategy_type(&self) -> StrategyType {
+        StrategyType::Triangular
+    }
+
+    fn set_clock(&mut self, clock: Arc<dyn Clock>) {
+        self.clock = clock;
+    }
+
+    fn set_venue_ranking(&mut self, ranking: &[(ExchangeId, VenueVerdict)]) {
+        self.venue_ranking = ranking.to_vec();
+    }
+
+    fn debug_state(&self) -> serde_json::Value {
+        let quotes: BTreeMap<String, BTreeMap<String, [f64; 2]>> = self
+            .quotes
+            .iter()
+            .map(|((base, quote), venues)| {
+                let venues = venues
+                    .iter()
+                    .map(|(exchange, q)| (format!("{:?}", exchange), [q.bid, q.ask]))
+                    .collect();
+                (format!("{}/{}", base, quote), venues)
+            })
+            .collect();
+        serde_json::json!({
+            "minProfitRate": self.min_profit_rate,
+            "feeRate": self.fee_rate,
+            "tradeAmount": self.trade_amount,
+            "corroborationWindowMs": self.corroboration_window_ms,
+            "maxPriceAgeMs": self.max_price_age_ms,
+            "crossVenue": self.cross_venue,
+            "triangles": self.triangles.iter().map(Triangle::key).collect::<Vec<_>>(),
+            "quotes": quotes,
+        })
+    }
+
+    fn path_edges(&self) -> Vec<(String, Option<f64>)> {
+        self.triangles
+            .iter()
+            .map(|t| (t.key(), self.calculate_profit(t).map(|(profit, _)| profit)))
+            .collect()
+    }
+
+    async fn on_ticker(&mut self, ticker: &Ticker) -> Option<Signal> {
+        let (base, quote) = self.update_quote(ticker)?;
+
+        let mut best: Option<(f64, Vec<LegFill>, String)> = None;
+        let triangles: Vec<Triangle> = self
+            .triangles
+            .iter()
+            .filter(|t| t.involves(&base, &quote))
+            .cloned()
+            .collect();
+        for triangle in &triangles {
+            let key = triangle.key();
+            if DISABLED_PATHS.is_disabled(&key) {
+                continue;
+            }
+            let Some((profit, fills)) = self.calculate_profit(triangle) else {
+                continue;
+            };
+            if profit < self.min_profit_rate {
+                if self.report_near_misses {
+                    self.record_near_miss(&key, fills[0].exchange, profit, ticker.timestamp);
+                }
+                continue;
+            }
+            if self
+                .last_signal
+                .get(&key)
+                .is_some_and(|last| ticker.timestamp - last < SIGNAL_COOLDOWN_MS)
+            {
+                continue;
+            }
+            if !self.meets_min_notional(&fills) {
+                continue;
+            }
+            if best.as_ref().is_none_or(|(p, _, _)| profit > *p) {
+                best = Some((profit, fills, key));
+            }
+        }
+        let (profit_rate, fills, path) = best?;
+        self.last_signal.insert(path.clone(), ticker.timestamp);
+        let freshness = self.freshness(&fills).unwrap_or(0.0);
+
+        let mut signal = Signal::new(
+            self.id.clone(),
+            StrategyType::Triangular,
+            fills[0].exchange,
+            profit_rate,
+            profit_rate * self.trade_amount,
+            (profit_rate / (self.min_profit_rate * 2.0)).min(1.0) * freshness,
+            path,
+            ticker.timestamp,
+        )
+        .with_fee_rate((1.0 + profit_rate) / (1.0 - self.fee_rate).powi(3) - 1.0 - profit_rate);
+        signal.legs = fills
+            .into_iter()
+            .map(|f| SignalLeg {
+                exchange: f.exchange,
+                symbol: f.symbol,
+                side: f.side,
+                price: f.price,
+            })
+            .collect();
+        Some(signal)
+    }
+
+    fn take_near_misses(&mut self) -> Vec<NearMiss> {
+        std::mem::take(&mut self.near_misses)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backtest::ReplayClock;
+    use crate::exchange::testing::ticker;
+
+    fn strategy() -> TriangularStrategy {
+        let config: StrategyConfig = serde_json::from_value(serde_json::json!({
+            "id": "tri", "strategy_type": "triangular", "name": "tri", "is_enabled": true, "priority": 1,
+            "config": {"triangles": [["USDT", "BTC", "ETH"]]},
+        }))
+        .unwrap();
+        TriangularStrategy::new(&config)
+    }
+
+    fn cross_venue_strategy(inventory: serde_json::Value) -> TriangularStrategy {
+        let config: StrategyConfig = serde_json::from_value(serde_json::json!({
+            "id": "tri", "strategy_type": "triangular", "name": "tri", "is_enabled": true, "priority": 1,
+            "config": {
+                "triangles": [["USDT", "BTC", "ETH"]],
+                "cross_venue": true, "inventory": inventory,
+            },
+        }))
+        .unwrap();
+        TriangularStrategy::new(&config)
+    }
+
+    /// Binance 上三条腿扣费后亏损；OKX 只有更便宜的 ETH/BTC，返回最后一笔行情产生的信号
+    async fn quote_split_triangle(strategy: &mut TriangularStrategy) -> Option<Signal> {
+        strategy.on_ticker(&ticker("BTC/USDT").exchange(ExchangeId::Binance).quote(99.9, 100.0).build()).await;
+        strategy.on_ticker(&ticker("ETH/BTC").exchange(ExchangeId::Binance).quote(0.0499, 0.05).build()).await;
+        strategy.on_ticker(&ticker("ETH/USDT").exchange(ExchangeId::Binance).quote(5.0, 5.01).build()).await;
+        strategy.on_ticker(&ticker("ETH/BTC").exchange(ExchangeId::Okx).quote(0.0489, 0.049).build()).await
+    }
+
+    /// 在交易所上写入 USDT -> BTC -> ETH -> USDT 有利可图的报价 (BTC 卖一为 `btc_ask`)
+    async fn quote_triangle(strategy: &mut TriangularStrategy, exchange: ExchangeId, btc_ask: f64) {
+        strategy.on_ticker(&ticker("BTC/USDT").exchange(exchange).quote(btc_ask - 0.1, btc_ask).build()).await;
+        strategy.on_ticker(&ticker("ETH/BTC").exchange(exchange).quote(0.0499, 0.05).build()).await;
+        strategy.on_ticker(&ticker("ETH/USDT").exchange(exchange).quote(5.1, 5.11).build()).await;
+    }
+
+    #[tokio::test]
+    async fn cheaper_second_leg_venue_makes_triangle_profitable() {
+        let mut strategy = cross_venue_strategy(serde_json::json!({"okx": {"BTC": 1.0}, "binance": {"eth": 25.0}}));
+        let signal = quote_split_triangle(&mut strategy).await.expect("跨所路由后应有利可图");
+
+        let venues: Vec<ExchangeId> = signal.legs.iter().map(|l| l.exchange).collect();
+        assert_eq!(venues, vec![ExchangeId::Binance, ExchangeId::Okx, ExchangeId::Binance]);
+        assert_eq!(signal.legs[1].symbol, "ETH/BTC");
+        assert_eq!(signal.legs[1].price, 0.049);
+        let expected = 0.999f64.powi(3) / 100.0 / 0.049 * 5.0 - 1.0;
+        assert!((signal.profit_rate - expected).abs() < 1e-12);
+
+        // 单交易所时同一组报价不赚钱
+        let binance_only = strategy.triangles[0]
+            .legs()
+            .iter()
+            .map(|(from, to)| strategy.leg_on(ExchangeId::Binance, from, to).unwrap().rate)
+            .product::<f64>();
+        assert!(binance_only < 1.0);
+    }
+
+    #[tokio::test]
+    async fn cross_venue_leg_requires_inventory_on_the_target_venue() {
+        // OKX 没有 BTC 可卖，不能把第二条腿路由过去
+        let mut short = cross_venue_strategy(serde_json::json!({"okx": {"BTC": 0.5}, "binance": {"ETH": 25.0}}));
+        assert!(quote_split_triangle(&mut short).await.is_none());
+
+        // 未开启 cross_venue 时只在单个交易所内成交
+        let mut single = strategy();
+        assert!(quote_split_triangle(&mut single).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn near_miss_reports_the_breakeven_fee_rate() {
+        let config: StrategyConfig = serde_json::from_value(serde_json::json!({
+            "id": "tri", "strategy_type": "triangular", "name": "tri", "is_enabled": true, "priority": 1,
+            "config": {
+                "triangles": [["USDT", "BTC", "ETH"]],
+                "min_profit_rate": 0.05, "report_near_misses": true,
+            },
+        }))
+        .unwrap();
+        let mut strategy = TriangularStrategy::new(&config);
+        quote_triangle(&mut strategy, ExchangeId::Binance, 100.0).await;
+
+        // 扣费前 1/100/0.05*5.1 = 1.02，扣费后约 1.69%，未达 5% 阈值
+        let misses = strategy.take_near_misses();
+        assert_eq!(misses.len(), 1);
+        let miss = &misses[0];
+        assert_eq!(miss.path, "USDT->BTC->ETH->USDT");
+        assert!((miss.gross_rate - 0.02).abs() < 1e-9);
+        assert!((miss.net_rate - (1.02 * 0.999f64.powi(3) - 1.0)).abs() < 1e-9);
+        // 1.02 * (1 - f)^3 = 1 => f = 1 - 1.02^(-1/3) ≈ 0.006579
+        assert!((miss.breakeven_fee_rate - (1.0 - 1.02f64.powf(-1.0 / 3.0))).abs() < 1e-9);
+        assert!((miss.breakeven_fee_rate - 0.006579).abs() < 1e-6);
+        assert!(strategy.take_near_misses().is_empty());
+    }
+
+    #[tokio::test]
+    async fn runtime_disabled_triangle_is_skipped() {
+        let config: StrategyConfig = serde_json::from_value(serde_json::json!({
+            "id": "tri-sol", "strategy_type": "triangular", "name": "tri-sol", "is_enabled": true, "priority": 1,
+            "config": {"triangles": [["USDT", "SOL", "BNB"]]},
+        }))
+        .unwrap();
+        let mut strategy = TriangularStrategy::new(&config);
+        // USDT -> SOL -> BNB -> USDT: 1 USDT 换回约 1.025 USDT
+        strategy.on_ticker(&ticker("SOL/USDT").exchange(ExchangeId::Binance).quote(9.99, 10.0).build()).await;
+        strategy.on_ticker(&ticker("BNB/SOL").exchange(ExchangeId::Binance).quote(1.99, 2.0).build()).await;
+
+        // 运维以另一起点写入同一环路
+        DISABLED_PATHS.replace(["SOL->BNB->USDT->SOL"]);
+        let suppressed = strategy.on_ticker(&ticker("BNB/USDT").exchange(ExchangeId::Binance).quote(20.5, 20.51).build()).await;
+        DISABLED_PATHS.replace(Vec::<String>::new());
+        assert!(suppressed.is_none());
+
+        let signal = strategy.on_ticker(&ticker("BNB/USDT").exchange(ExchangeId::Binance).quote(20.5, 20.51).build()).await.expect("解除禁用后应发信号");
+        assert_eq!(signal.path, "USDT->SOL->BNB->USDT");
+    }
+
+    #[tokio::test]
+    async fn legs_outside_the_corroboration_window_are_not_combined() {
+        async fn quote_with_btc_lag(lag_ms: i64) -> Option<Signal> {
+            let config: StrategyConfig = serde_json::from_value(serde_json::json!({
+                "id": "tri", "strategy_type": "triangular", "name": "tri", "is_enabled": true, "priority": 1,
+                "config": {"triangles": [["USDT", "BTC", "ETH"]], "corroboration_window_ms": 500},
+            }))
+            .unwrap();
+            let mut strategy = TriangularStrategy::new(&config);
+            strategy.on_ticker(&ticker("BTC/USDT").quote(99.9, 100.0).at(1_000).build()).await;
+            strategy.on_ticker(&ticker("ETH/BTC").quote(0.0499, 0.05).at(1_000 + lag_ms).build()).await;
+            strategy.on_ticker(&ticker("ETH/USDT").quote(5.1, 5.11).at(1_000 + lag_ms).build()).await
+        }
+
+        assert!(quote_with_btc_lag(501).await.is_none());
+        assert!(quote_with_btc_lag(500).await.is_some());
+    }
+
+    #[tokio::test]
+    async fn stale_leg_suppresses_the_triangle() {
+        async fn quote_with_btc_at(btc_at: i64) -> Option<Signal> {
+            let config: StrategyConfig = serde_json::from_value(serde_json::json!({
+                "id": "tri", "strategy_type": "triangular", "name": "tri", "is_enabled": true, "priority": 1,
+                "config": {"triangles": [["USDT", "BTC", "ETH"]], "max_price_age_ms": 5000},
+            }))
+            .unwrap();
+            let mut strategy = TriangularStrategy::new(&config);
+            strategy.set_clock(Arc::new(ReplayClock::new(6_000)));
+            strategy.on_ticker(&ticker("BTC/USDT").quote(99.9, 100.0).at(btc_at).build()).await;
+            strategy.on_ticker(&ticker("ETH/BTC").quote(0.0499, 0.05).at(6_000).build()).await;
+            strategy.on_ticker(&ticker("ETH/USDT").quote(5.1, 5.11).at(6_000).build()).await
+        }
+
+        // 引擎时钟在 6000: BTC/USDT 报价 2 秒前更新，仍在 5 秒上限内
+        assert!(quote_with_btc_at(4_000).await.is_some());
+        // 5.5 秒前的报价已过旧，整个三角形不参与计算
+        assert!(quote_with_btc_at(500).await.is_none());
+    }
+
+    fn routed_venue(strategy: &TriangularStrategy) -> ExchangeId {
+        let triangle = strategy.triangles[0].clone();
+        let (_, fills) = strategy.calculate_profit(&triangle).unwrap();
+        assert!(fills.iter().all(|f| f.exchange == fills[0].exchange));
+        fills[0].exchange
+    }
+
+    #[tokio::test]
+    async fn scorecard_ranking_breaks_ties_between_venues() {
+        let mut strategy = strategy();
+        for exchange in [ExchangeId::Binance, ExchangeId::Okx] {
+            quote_triangle(&mut strategy, exchange, 100.0).await;
+        }
+        assert_eq!(routed_venue(&strategy), ExchangeId::Binance);
+
+        strategy.set_venue_ranking(&[
+            (ExchangeId::Okx, VenueVerdict::Preferred),
+            (ExchangeId::Binance, VenueVerdict::Deprioritized),
+        ]);
+        assert_eq!(routed_venue(&strategy), ExchangeId::Okx);
+    }
+
+    #[tokio::test]
+    async fn blocked_venue_is_not_routed_even_when_cheaper() {
+        let mut strategy = strategy();
+        quote_triangle(&mut strategy, ExchangeId::Binance, 99.0).await;
+        quote_triangle(&mut strategy, ExchangeId::Okx, 100.0).await;
+        assert_eq!(routed_venue(&strategy), ExchangeId::Binance);
+
+        strategy.set_venue_ranking(&[
+            (ExchangeId::Okx, VenueVerdict::Preferred),
+            (ExchangeId::Binance, VenueVerdict::Blocked),
+        ]);
+        assert_eq!(routed_venue(&strategy), ExchangeId::Okx);
+        strategy.set_venue_ranking(&[(ExchangeId::Okx, VenueVerdict::Blocked), (ExchangeId::Binance, VenueVerdict::Blocked)]);
+        assert!(strategy.calculate_profit(&strategy.triangles[0].clone()).is_none());
+    }
+}
@@ -1,30 +1,99 @@
+use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
 
-use crate::exchange::ExchangeId;
+use std::sync::Arc;
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+use crate::engine::Clock;
+use crate::exchange::{ExchangeId, FundingRateUpdate, Kline, OrderBook, Ticker, Trade};
+use crate::executor::OrderSide;
+use crate::scorecard::VenueVerdict;
+use crate::telemetry::SignalTrace;
+
+/// 关联 ID 请求头 (OMS / 风控请求携带，用于串联引擎与下游服务日志)
+pub const CORRELATION_HEADER: &str = "X-Correlation-Id";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
 pub enum StrategyType {
     Triangular,
+    #[serde(alias = "funding_rate")]
     CashCarry,
     Pair,
     Grid,
     Graph,
 }
 
+impl StrategyType {
+    /// 执行后是否产生方向性敞口 (需要保护性止损)
+    pub fn is_directional(&self) -> bool {
+        matches!(self, StrategyType::Grid | StrategyType::Pair | StrategyType::CashCarry)
+    }
+}
+
+/// 信号动作: 开仓 / 平仓
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SignalAction {
+    #[default]
+    Open,
+    Close,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Signal {
     pub strategy_id: String,
     pub strategy_type: StrategyType,
     pub exchange: ExchangeId,
     pub profit_rate: f64,
+    /// 策略已从 `profit_rate` 中扣除的手续费率 (各腿合计)，扣费前的毛收益率为两者之和
+    #[serde(default)]
+    pub fee_rate: f64,
+    /// 统一口径的单笔预期优势: 占成交名义金额的基点数 (年化类策略已折算为单次持仓期)，各策略可直接比较
+    #[serde(default)]
+    pub edge_bps: f64,
     pub expected_profit: f64,
     pub confidence: f64,
+    /// 校准前的原始置信度
+    #[serde(default)]
+    pub raw_confidence: f64,
     pub path: String,
     pub timestamp: i64,
+    #[serde(default)]
+    pub action: SignalAction,
+    /// 关联 ID (每个信号唯一)
+    #[serde(default)]
+    pub correlation_id: String,
+    /// 策略给出的止损价提示 (如 ATR 止损)
+    #[serde(default)]
+    pub stop_price: Option<f64>,
+    /// 队列优先级 (1-10，越大越优先)
+    #[serde(default)]
+    pub priority: i32,
+    /// 有效期截止时间 (毫秒，0 表示使用队列默认有效期)
+    #[serde(default)]
+    pub valid_until: i64,
+    /// 按腿指定的成交交易所 (跨交易所三角套利)，为空时所有腿在 exchange 上成交
+    #[serde(default)]
+    pub legs: Vec<SignalLeg>,
+    /// 价格档位 (如网格格线序号): 方向性开仓按策略、交易对与档位判断是否重复
+    #[serde(default)]
+    pub level: Option<i64>,
+    /// 生命周期链路追踪 (开启 OTLP 导出时由引擎记录)
+    #[serde(skip)]
+    pub trace: Option<SignalTrace>,
+}
+
+/// 多腿信号中的单条腿
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SignalLeg {
+    pub exchange: ExchangeId,
+    pub symbol: String,
+    pub side: OrderSide,
+    pub price: f64,
 }
 
 impl Signal {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         strategy_id: impl Into<String>,
         strategy_type: StrategyType,
@@ -40,10 +109,231 @@ impl Signal {
             strategy_type,
             exchange,
             profit_rate,
+            fee_rate: 0.0,
+            edge_bps: profit_rate * 10_000.0,
             expected_profit,
             confidence,
+            raw_confidence: confidence,
             path: path.into(),
             timestamp,
+            action: SignalAction::Open,
+            correlation_id: uuid::Uuid::new_v4().to_string(),
+            stop_price: None,
+            priority: 5,
+            valid_until: 0,
+            legs: vec![],
+            level: None,
+            trace: None,
+        }
+    }
+
+    /// 修改预期收益率 (同步更新 edge_bps)
+    pub fn set_profit_rate(&mut self, profit_rate: f64) {
+        self.profit_rate = profit_rate;
+        self.edge_bps = profit_rate * 10_000.0;
+    }
+
+    /// 设置信号动作
+    pub fn with_action(mut self, action: SignalAction) -> Self {
+        self.action = action;
+        self
+    }
+
+    /// 设置价格档位
+    pub fn with_level(mut self, level: i64) -> Self {
+        self.level = Some(level);
+        self
+    }
+
+    /// 设置策略已扣除的手续费率
+    pub fn with_fee_rate(mut self, fee_rate: f64) -> Self {
+        self.fee_rate = fee_rate;
+        self
+    }
+
+    /// 扣除手续费前的毛收益率
+    pub fn gross_profit_rate(&self) -> f64 {
+        self.profit_rate + self.fee_rate
+    }
+
+    /// 由预期收益与收益率反推的成交名义金额 (无法推算时为 None)
+    pub fn notional(&self) -> Option<f64> {
+        if self.profit_rate.abs() <= f64::EPSILON {
+            return None;
+        }
+        let notional = self.expected_profit / self.profit_rate;
+        (notional.is_finite() && notional > 0.0).then_some(notional)
+    }
+}
+
+/// 未达收益阈值但扣费前有正收益的机会 (用于评估手续费等级)
+#[derive(Debug, Clone, Serialize)]
+pub struct NearMiss {
+    pub strategy_id: String,
+    pub exchange: ExchangeId,
+    pub path: String,
+    /// 扣费前收益率
+    pub gross_rate: f64,
+    /// 按当前费率扣费后的收益率
+    pub net_rate: f64,
+    /// 收益恰好为 0 时的单腿费率
+    pub breakeven_fee_rate: f64,
+    pub timestamp: i64,
+}
+
+/// 盈亏平衡费率: 扣费前总换算倍数 `gross_growth` 经 `legs` 次各收 f 的手续费后恰为 1，
+/// 即 gross_growth * (1 - f)^legs = 1
+pub fn breakeven_fee_rate(gross_growth: f64, legs: usize) -> f64 {
+    if gross_growth <= 0.0 || legs == 0 {
+        return 0.0;
+    }
+    (1.0 - gross_growth.powf(-1.0 / legs as f64)).max(0.0)
+}
+
+/// 策略配置 (对应 strategy_configs 表)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StrategyConfig {
+    pub id: String,
+    pub strategy_type: StrategyType,
+    pub name: String,
+    pub is_enabled: bool,
+    pub priority: i32,
+    /// 策略参数 (JSON 格式，不同策略有不同参数)
+    pub config: serde_json::Value,
+    /// 生效时间 (毫秒)，之前只接收行情、不产生可执行信号
+    #[serde(default)]
+    pub active_from: Option<i64>,
+    /// 失效时间 (毫秒，不含)，之后不再产生可执行信号
+    #[serde(default)]
+    pub active_until: Option<i64>,
+}
+
+/// 策略接口
+#[async_trait]
+pub trait Strategy: Send + Sync {
+    /// 策略 ID
+    fn id(&self) -> &str;
+
+    /// 策略类型
+    fn strategy_type(&self) -> StrategyType;
+
+    /// 注入引擎时钟 (加载时调用；按报价年龄过滤的策略以此为当前时间，回测时为回放时钟；默认忽略)
+    fn set_clock(&mut self, _clock: Arc<dyn Clock>) {}
+
+    /// 交易所评分卡的排序 (较优者在前，附判定)；按腿选择交易所的策略据此避开被拦截的交易所 (默认忽略)
+    fn set_venue_ranking(&mut self, _ranking: &[(ExchangeId, VenueVerdict)]) {}
+
+    /// 处理 Ticker，可能产生信号
+    async fn on_ticker(&mut self, ticker: &Ticker) -> Option<Signal>;
+
+    /// 处理订单簿快照 (默认忽略；需要深度的策略据此更新内部状态，信号仍由 on_ticker 产生)
+    fn on_orderbook(&mut self, _book: &OrderBook) {}
+
+    /// 处理逐笔成交 (默认忽略；动量、订单流失衡等需要逐笔成交的策略据此产生信号)
+    async fn on_trade(&mut self, _trade: &Trade) -> Option<Signal> {
+        None
+    }
+
+    /// 处理 K 线 (默认忽略；默认只收到已收盘的 K 线，策略参数 `partial_klines: true` 时未收盘的 K 线也会送达)
+    async fn on_kline(&mut self, _kline: &Kline) -> Option<Signal> {
+        None
+    }
+
+    /// 处理永续合约资金费率 (默认忽略；期现套利据此产生信号)
+    async fn on_funding_rate(&mut self, _funding: &FundingRateUpdate) -> Option<Signal> {
+        None
+    }
+
+    /// 策略依赖的交易对，加载时向各交易所连接追加订阅 (默认为空，只使用启动时配置的交易对)
+    fn symbols(&self) -> Vec<String> {
+        vec![]
+    }
+
+    /// 取出最近记录的接近成交的机会 (默认不记录)
+    fn take_near_misses(&mut self) -> Vec<NearMiss> {
+        vec![]
+    }
+
+    /// 活跃窗口结束时为未平仓位生成平仓信号 (默认无持仓)
+    fn close_signals(&mut self, _timestamp: i64) -> Vec<Signal> {
+        vec![]
+    }
+
+    /// 各监控路径当前的扣费后收益率，供机会热力图使用 (行情不全的路径为 None；默认不导出)
+    fn path_edges(&self) -> Vec<(String, Option<f64>)> {
+        vec![]
+    }
+
+    /// 调试快照: 阈值参数与内部缓存 (默认不导出)
+    fn debug_state(&self) -> serde_json::Value {
+        serde_json::Value::Null
+    }
+
+    /// 生效中的阈值参数: 默认取调试快照中的标量字段 (内部缓存均为对象 / 数组)
+    fn thresholds(&self) -> serde_json::Value {
+        match self.debug_state() {
+            serde_json::Value::Object(state) => state
+                .into_iter()
+                .filter(|(_, v)| !v.is_object() && !v.is_array())
+                .collect(),
+            _ => serde_json::json!({}),
+        }
+    }
+
+    /// 是否已收到足够行情、可以产生信号 (默认: 任一监控路径可计算收益；不导出路径的策略视为就绪)
+    fn is_ready(&self) -> bool {
+        let edges = self.path_edges();
+        edges.is_empty() || edges.iter().any(|(_, edge)| edge.is_some())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn breakeven_fee_rate_zeroes_the_net_profit() {
+        for (gross, legs) in [(1.02, 3), (1.005, 2), (1.3, 5)] {
+            let fee = breakeven_fee_rate(gross, legs);
+            assert!(fee > 0.0);
+            assert!((gross * (1.0 - fee).powi(legs as i32) - 1.0).abs() < 1e-12, "gross {} legs {}", gross, legs);
+        }
+        // 扣费前已不赚钱、或参数无效时为 0
+        assert_eq!(breakeven_fee_rate(0.99, 3), 0.0);
+        assert_eq!(breakeven_fee_rate(1.0, 3), 0.0);
+        assert_eq!(breakeven_fee_rate(1.02, 0), 0.0);
+        assert_eq!(breakeven_fee_rate(-1.0, 3), 0.0);
+    }
+
+    #[tokio::test]
+    async fn edge_bps_tracks_the_profit_rate() {
+        let mut signal = Signal::new("s", StrategyType::Triangular, ExchangeId::Binance, 0.0012, 0.12, 0.5, "BTC/USDT", 0);
+        assert!((signal.edge_bps - 12.0).abs() < 1e-9);
+        signal.set_profit_rate(-0.0003);
+        assert!((signal.edge_bps - -3.0).abs() < 1e-9);
+
+        // 策略产生的信号同口径
+        let config: StrategyConfig = serde_json::from_value(serde_json::json!({
+            "id": "grid",
+            "strategy_type": "grid",
+            "name": "grid",
+            "is_enabled": true,
+            "priority": 1,
+            "config": {"symbol": "BTC/USDT", "lower_price": 90.0, "upper_price": 110.0, "grid_count": 20, "include_funding": false},
+        }))
+        .unwrap();
+        let mut grid = crate::strategies::build_strategy(&config).unwrap();
+        let mut produced = None;
+        for (i, price) in [100.5, 101.5, 100.5].into_iter().enumerate() {
+            let ticker: Ticker = serde_json::from_value(serde_json::json!({
+                "exchange": "binance", "symbol": "BTC/USDT", "bid": price - 0.01, "ask": price + 0.01,
+                "last": price, "volume": 1000.0, "timestamp": i,
+            }))
+            .unwrap();
+            produced = produced.or(grid.on_ticker(&ticker).await);
         }
+        let produced = produced.expect("穿越格线应产生信号");
+        assert!(produced.profit_rate > 0.0);
+        assert!((produced.edge_bps - produced.profit_rate * 10_000.0).abs() < 1e-9);
     }
 }
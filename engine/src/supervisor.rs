@@ -0,0 +1,144 @@
+//! 任务监管
+//!
+//! tokio 任务 panic 后只会静默退出，进程仍在运行，对应的交易所 / 策略 / 执行悄然停止。
+//! 这里统一捕获后台任务与策略回调中的 panic，记录上下文后交给引擎发出严重告警，
+//! 执行任务 panic 时由引擎按配置市价平掉所有持仓。
+
+use futures_util::FutureExt;
+use serde::Serialize;
+use std::any::Any;
+use std::future::Future;
+use std::panic::AssertUnwindSafe;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tracing::error;
+
+/// 任务类别
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TaskKind {
+    /// 行情转发
+    Feed,
+    /// 策略回调
+    Strategy,
+    /// 信号执行
+    Execution,
+}
+
+/// panic 事件
+#[derive(Debug, Clone, Serialize)]
+pub struct PanicEvent {
+    pub task: String,
+    pub kind: TaskKind,
+    pub message: String,
+    pub timestamp: i64,
+}
+
+/// 任务监管器: 启动受监管的任务，并把 panic 上报到引擎
+#[derive(Clone)]
+pub struct Supervisor {
+    tx: mpsc::UnboundedSender<PanicEvent>,
+}
+
+impl Supervisor {
+    pub fn new() -> (Self, mpsc::UnboundedReceiver<PanicEvent>) {
+        let (tx, rx) = mpsc::unbounded_channel();
+        (Self { tx }, rx)
+    }
+
+    /// 启动受监管的任务
+    pub fn spawn<F>(&self, task: impl Into<String>, kind: TaskKind, fut: F) -> JoinHandle<()>
+    where
+        F: Future<Output = ()> + Send + 'static,
+    {
+        let task = task.into();
+        let supervisor = self.clone();
+        tokio::spawn(async move {
+            if let Err(payload) = AssertUnwindSafe(fut).catch_unwind().await {
+                supervisor.report(task, kind, payload.as_ref());
+            }
+        })
+    }
+
+    /// 上报已捕获的 panic
+    pub fn report(&self, task: impl Into<String>, kind: TaskKind, payload: &(dyn Any + Send)) {
+        let event = PanicEvent {
+            task: task.into(),
+            kind,
+            message: panic_message(payload),
+            timestamp: chrono::Utc::now().timestamp_millis(),
+        };
+        error!(
+            "任务 {} ({:?}) panic: {}",
+            event.task, event.kind, event.message
+        );
+        let _ = self.tx.send(event);
+    }
+}
+
+/// 提取 panic 信息
+pub fn panic_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(s) = payload.downcast_ref::<&str>() {
+        s.to_string()
+    } else if let Some(s) = payload.downcast_ref::<String>() {
+        s.clone()
+    } else {
+        "unknown panic payload".to_string()
+    }
+}
+
+/// 安装全局 panic hook，把 panic 位置与线程写入日志 (替代默认的 stderr 输出)
+pub fn install_panic_hook() {
+    std::panic::set_hook(Box::new(|info| {
+        let location = info
+            .location()
+            .map(|l| format!("{}:{}", l.file(), l.line()))
+            .unwrap_or_else(|| "unknown".to_string());
+        let thread = std::thread::current();
+        error!(
+            "panic 于线程 {} ({}): {}",
+            thread.name().unwrap_or("unnamed"),
+            location,
+            panic_message(info.payload())
+        );
+    }));
+}
+
+/// 执行任务 panic 后是否市价平掉所有持仓 (ENGINE_PANIC_FLATTEN=0 关闭，默认开启)
+pub fn flatten_on_panic_enabled() -> bool {
+    std::env::var("ENGINE_PANIC_FLATTEN")
+        .map(|v| !matches!(v.as_str(), "0" | "false" | "False"))
+        .unwrap_or(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn panicking_task_is_reported_with_context() {
+        let (supervisor, mut rx) = Supervisor::new();
+        supervisor
+            .spawn("execution:c1", TaskKind::Execution, async {
+                panic!("leg {} failed", 2);
+            })
+            .await
+            .unwrap();
+        supervisor.spawn("feed:binance", TaskKind::Feed, async {}).await.unwrap();
+
+        let event = rx.try_recv().unwrap();
+        assert_eq!(event.task, "execution:c1");
+        assert_eq!(event.kind, TaskKind::Execution);
+        assert_eq!(event.message, "leg 2 failed");
+        assert!(event.timestamp > 0);
+        // 正常结束的任务不上报
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[test]
+    fn panic_message_reads_str_and_string_payloads() {
+        assert_eq!(panic_message(&"boom"), "boom");
+        assert_eq!(panic_message(&"boom".to_string()), "boom");
+        assert_eq!(panic_message(&42), "unknown panic payload");
+    }
+}
@@ -0,0 +1,168 @@
+//! 交易对规范化
+//!
+//! 各交易所的交易对写法不同: Binance / Bybit / Bitget / MEXC 为 `BTCUSDT`，OKX 为 `BTC-USDT`，Gate 为 `BTC_USDT`。
+//! 行情在进入引擎前统一转换为 `BASE/QUOTE` (如 `BTC/USDT`)，策略缓存、信号路径与指标均使用该形式；
+//! 构建订阅消息与下单参数时再按交易所转换回原始写法。
+//!
+//! 无分隔符的写法按已知计价币后缀拆分 (`1000SHIBUSDT` → `1000SHIB/USDT`、`BTCTUSD` → `BTC/TUSD`)，
+//! 无法识别计价币时原样 (大写) 保留。
+//!
+//! [`Symbol`] 是拆分后的结构化形式: `Symbol::parse` 解析任一交易所写法，`to_exchange_format` 转回交易所写法，
+//! `Display` 输出规范形式 (同时用作 Redis 键中的交易对)，拼接交易对名时应使用它而不是手写字符串。
+
+use std::fmt;
+
+use crate::exchange::ExchangeId;
+
+/// 常见计价币 (长后缀在前，避免 FDUSD 被拆为 FD + USD、TUSD 被拆为 T + USD)
+const QUOTE_ASSETS: &[&str] = &[
+    "FDUSD", "USDT", "USDC", "BUSD", "TUSD", "DAI", "BTC", "ETH", "BNB", "EUR", "TRY", "BRL", "USD",
+];
+
+/// Bitget v1 现货交易对后缀 (`BTCUSDT_SPBL`)
+const BITGET_SPOT_SUFFIX: &str = "_SPBL";
+
+/// 拆分交易对为 (base, quote)，兼容 BTC/USDT、BTC-USDT、BTC_USDT、BTCUSDT
+pub fn split_symbol(symbol: &str) -> Option<(String, String)> {
+    let upper = symbol.trim().to_uppercase();
+    if let Some((base, quote)) = upper.split_once(['/', '-', '_']) {
+        if base.is_empty() || quote.is_empty() {
+            return None;
+        }
+        return Some((base.to_string(), quote.to_string()));
+    }
+    QUOTE_ASSETS.iter().find_map(|quote| {
+        let base = upper.strip_suffix(quote)?;
+        (!base.is_empty()).then(|| (base.to_string(), quote.to_string()))
+    })
+}
+
+/// 结构化交易对 (`BASE/QUOTE`，均为大写)
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Symbol {
+    pub base: String,
+    pub quote: String,
+}
+
+impl Symbol {
+    pub fn new(base: impl AsRef<str>, quote: impl AsRef<str>) -> Self {
+        Self {
+            base: base.as_ref().trim().to_uppercase(),
+            quote: quote.as_ref().trim().to_uppercase(),
+        }
+    }
+
+    /// 解析交易所原始写法 (也接受规范形式与其他交易所的写法)；无法识别计价币时返回 None
+    pub fn parse(exchange: ExchangeId, raw: &str) -> Option<Self> {
+        split_symbol(&strip_venue_suffix(exchange, raw)).map(|(base, quote)| Self { base, quote })
+    }
+
+    /// 交易所写法 (订阅消息 / 下单参数使用；Binance 订阅流名另需转小写)
+    pub fn to_exchange_format(&self, exchange: ExchangeId) -> String {
+        match exchange {
+            ExchangeId::Okx => format!("{}-{}", self.base, self.quote),
+            ExchangeId::Gate => format!("{}_{}", self.base, self.quote),
+            ExchangeId::Binance | ExchangeId::Bybit | ExchangeId::Bitget | ExchangeId::Mexc => {
+                format!("{}{}", self.base, self.quote)
+            }
+        }
+    }
+}
+
+impl fmt::Display for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.base, self.quote)
+    }
+}
+
+/// 两种写法是否为同一交易对 (`BTC/USDT`、`BTC-USDT`、`btcusdt` 视为相同)；无法拆分时按忽略大小写比较
+pub fn same_symbol(a: &str, b: &str) -> bool {
+    match (split_symbol(a), split_symbol(b)) {
+        (Some(a), Some(b)) => a == b,
+        _ => a.trim().eq_ignore_ascii_case(b.trim()),
+    }
+}
+
+/// 交易所原始写法 → 规范形式 `BASE/QUOTE`
+pub fn normalize_symbol(exchange: ExchangeId, raw: &str) -> String {
+    match Symbol::parse(exchange, raw) {
+        Some(symbol) => symbol.to_string(),
+        None => strip_venue_suffix(exchange, raw),
+    }
+}
+
+/// 大写并去掉交易所特有的后缀
+fn strip_venue_suffix(exchange: ExchangeId, raw: &str) -> String {
+    let raw = raw.trim().to_uppercase();
+    match exchange {
+        ExchangeId::Bitget => raw.strip_suffix(BITGET_SPOT_SUFFIX).map(str::to_string).unwrap_or(raw),
+        _ => raw,
+    }
+}
+
+/// 规范形式 (或任意写法) → 交易所写法 (订阅消息 / 下单参数使用；Binance 订阅流名另需转小写)
+pub fn denormalize_symbol(exchange: ExchangeId, symbol: &str) -> String {
+    match split_symbol(symbol) {
+        Some((base, quote)) => Symbol { base, quote }.to_exchange_format(exchange),
+        None => symbol.replace(['/', '-', '_'], "").to_uppercase(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXCHANGES: [ExchangeId; 6] = [
+        ExchangeId::Binance,
+        ExchangeId::Okx,
+        ExchangeId::Bybit,
+        ExchangeId::Gate,
+        ExchangeId::Bitget,
+        ExchangeId::Mexc,
+    ];
+
+    #[test]
+    fn raw_symbols_normalize_to_base_quote() {
+        let cases = [
+            (ExchangeId::Binance, "BTCUSDT", "BTC/USDT"),
+            (ExchangeId::Binance, "btcusdt", "BTC/USDT"),
+            (ExchangeId::Binance, "1000SHIBUSDT", "1000SHIB/USDT"),
+            (ExchangeId::Binance, "BTCTUSD", "BTC/TUSD"),
+            (ExchangeId::Binance, "ETHUSDC", "ETH/USDC"),
+            (ExchangeId::Binance, "BTCFDUSD", "BTC/FDUSD"),
+            (ExchangeId::Binance, "ETHBTC", "ETH/BTC"),
+            (ExchangeId::Okx, "BTC-USDT", "BTC/USDT"),
+            (ExchangeId::Gate, "BTC_USDT", "BTC/USDT"),
+            (ExchangeId::Bitget, "BTCUSDT_SPBL", "BTC/USDT"),
+            (ExchangeId::Mexc, "1000SHIBUSDT", "1000SHIB/USDT"),
+        ];
+        for (exchange, raw, canonical) in cases {
+            assert_eq!(normalize_symbol(exchange, raw), canonical, "{:?} {}", exchange, raw);
+        }
+        // 无法识别计价币时原样 (大写) 保留
+        assert_eq!(normalize_symbol(ExchangeId::Binance, "foobar"), "FOOBAR");
+        assert_eq!(Symbol::parse(ExchangeId::Binance, "FOOBAR"), None);
+    }
+
+    #[test]
+    fn canonical_symbols_round_trip_on_every_exchange() {
+        for canonical in ["BTC/USDT", "1000SHIB/USDT", "BTC/TUSD", "ETH/USDC", "ETH/BTC", "TUSD/USDT"] {
+            for exchange in EXCHANGES {
+                let raw = denormalize_symbol(exchange, canonical);
+                assert_eq!(normalize_symbol(exchange, &raw), canonical, "{:?} {}", exchange, raw);
+            }
+        }
+        assert_eq!(denormalize_symbol(ExchangeId::Okx, "BTC/USDT"), "BTC-USDT");
+        assert_eq!(denormalize_symbol(ExchangeId::Gate, "BTC-USDT"), "BTC_USDT");
+        assert_eq!(denormalize_symbol(ExchangeId::Binance, "btc_usdt"), "BTCUSDT");
+    }
+
+    #[test]
+    fn same_symbol_ignores_venue_format() {
+        assert!(same_symbol("BTC/USDT", "btc-usdt"));
+        assert!(same_symbol("BTCUSDT", "BTC_USDT"));
+        assert!(!same_symbol("BTC/USDT", "BTC/USDC"));
+        assert!(same_symbol("foobar", "FOOBAR"));
+        assert_eq!(Symbol::new(" btc ", "usdt").to_string(), "BTC/USDT");
+    }
+}
@@ -0,0 +1,319 @@
+//! 链路追踪 (OpenTelemetry)
+//!
+//! 每个进入执行阶段的信号导出一条链路，根 span `opportunity` 下依次为:
+//! `ticker` (引擎收到行情 → 分发给策略)、`strategy.detect` (策略计算出信号)、
+//! `risk_check` (出队后的本地检查与全局风控)、`execution` (执行器下单) 及其下每笔订单的 `fill`，
+//! 各 span 携带策略类型 / 路径 / 收益等属性。阶段时间点随信号记录，结束后按实际起止时间一次性导出，
+//! 因此不进入执行 (被抑制 / 淘汰) 的信号不产生链路，不会因逐条行情建 span 造成开销。
+//! 引擎中 `tracing` 的 span 也经 tracing-opentelemetry 导出到同一后端。
+//!
+//! ENGINE_OTLP_ENDPOINT 指定 OTLP gRPC 地址 (如 `http://localhost:4317`) 后开启；
+//! ENGINE_OTLP_SERVICE_NAME 指定服务名 (默认 `inarbit-engine`)，ENGINE_OTLP_SAMPLE_RATIO 指定采样比例 (默认 1.0)。
+
+use anyhow::Result;
+use opentelemetry::trace::{Span, TraceContextExt, Tracer as _};
+use opentelemetry::{Context, KeyValue};
+use opentelemetry_sdk::trace::{Sampler, Tracer, TracerProvider};
+use opentelemetry_sdk::{runtime, Resource};
+use std::time::{Duration, SystemTime};
+
+use crate::executor::OrderResponse;
+use crate::strategy::Signal;
+
+/// tracer 名称
+pub const TRACER_NAME: &str = "inarbit-engine";
+
+/// OTLP 导出配置
+#[derive(Debug, Clone)]
+pub struct TelemetryConfig {
+    pub endpoint: String,
+    pub service_name: String,
+    pub sample_ratio: f64,
+}
+
+impl TelemetryConfig {
+    /// 未设置 ENGINE_OTLP_ENDPOINT 时不开启
+    pub fn from_env() -> Option<Self> {
+        let endpoint = std::env::var("ENGINE_OTLP_ENDPOINT").ok().filter(|v| !v.trim().is_empty())?;
+        let service_name = std::env::var("ENGINE_OTLP_SERVICE_NAME")
+            .ok()
+            .filter(|v| !v.trim().is_empty())
+            .unwrap_or_else(|| TRACER_NAME.to_string());
+        let sample_ratio = std::env::var("ENGINE_OTLP_SAMPLE_RATIO")
+            .ok()
+            .and_then(|v| v.parse::<f64>().ok())
+            .unwrap_or(1.0)
+            .clamp(0.0, 1.0);
+        Some(Self {
+            endpoint: endpoint.trim().to_string(),
+            service_name,
+            sample_ratio,
+        })
+    }
+}
+
+/// 创建批量导出到 OTLP 的 TracerProvider (需在 tokio 运行时内调用)
+pub fn init_provider(config: &TelemetryConfig) -> Result<TracerProvider> {
+    use opentelemetry_otlp::WithExportConfig;
+
+    let exporter = opentelemetry_otlp::SpanExporter::builder()
+        .with_tonic()
+        .with_endpoint(config.endpoint.clone())
+        .build()?;
+    Ok(TracerProvider::builder()
+        .with_batch_exporter(exporter, runtime::Tokio)
+        .with_sampler(Sampler::ParentBased(Box::new(Sampler::TraceIdRatioBased(
+            config.sample_ratio,
+        ))))
+        .with_resource(Resource::new(vec![KeyValue::new(
+            "service.name",
+            config.service_name.clone(),
+        )]))
+        .build())
+}
+
+/// 信号生命周期各阶段的时间点
+#[derive(Debug, Clone)]
+pub struct SignalTrace {
+    /// 信号属性 (策略类型 / 路径 / 收益等)
+    attributes: Vec<KeyValue>,
+    exchange: String,
+    symbol: String,
+    received_at: SystemTime,
+    detect_start: SystemTime,
+    detect_end: SystemTime,
+    risk_start: Option<SystemTime>,
+    risk_end: Option<SystemTime>,
+    execution_start: Option<SystemTime>,
+    execution_end: Option<SystemTime>,
+}
+
+impl SignalTrace {
+    /// 策略产出信号时记录: 行情到达时间、策略开始计算时间，当前时间为检测完成
+    pub fn detected(signal: &Signal, symbol: &str, received_at: SystemTime, detect_start: SystemTime) -> Self {
+        let strategy_type = serde_json::to_value(signal.strategy_type)
+            .ok()
+            .and_then(|v| v.as_str().map(str::to_string))
+            .unwrap_or_default();
+        let exchange = format!("{:?}", signal.exchange).to_lowercase();
+        Self {
+            attributes: vec![
+                KeyValue::new("correlation_id", signal.correlation_id.clone()),
+                KeyValue::new("strategy_id", signal.strategy_id.clone()),
+                KeyValue::new("strategy_type", strategy_type),
+                KeyValue::new("exchange", exchange.clone()),
+                KeyValue::new("path", signal.path.clone()),
+                KeyValue::new("profit_rate", signal.profit_rate),
+                KeyValue::new("edge_bps", signal.edge_bps),
+                KeyValue::new("expected_profit", signal.expected_profit),
+            ],
+            exchange,
+            symbol: symbol.to_string(),
+            received_at,
+            detect_start,
+            detect_end: SystemTime::now(),
+            risk_start: None,
+            risk_end: None,
+            execution_start: None,
+            execution_end: None,
+        }
+    }
+
+    pub fn risk_started(&mut self) {
+        self.risk_start = Some(SystemTime::now());
+    }
+
+    pub fn risk_finished(&mut self) {
+        self.risk_end = Some(SystemTime::now());
+    }
+
+    pub fn execution_started(&mut self) {
+        self.execution_start = Some(SystemTime::now());
+    }
+
+    pub fn execution_finished(&mut self) {
+        self.execution_end = Some(SystemTime::now());
+    }
+
+    /// 按记录的起止时间导出整条链路；verdict 为信号最终结果 (executed / failed / risk_blocked 等)
+    pub fn export(&self, tracer: &Tracer, verdict: &str, orders: &[OrderResponse]) {
+        let end = self
+            .execution_end
+            .or(self.risk_end)
+            .or(self.risk_start)
+            .unwrap_or(self.detect_end);
+        let mut root_attributes = self.attributes.clone();
+        root_attributes.push(KeyValue::new("verdict", verdict.to_string()));
+        let root = tracer
+            .span_builder("opportunity")
+            .with_start_time(self.received_at)
+            .with_attributes(root_attributes)
+            .start(tracer);
+        let cx = Context::current_with_span(root);
+
+        let stage = |cx: &Context, name: &'static str, start: SystemTime, end: SystemTime, attributes: Vec<KeyValue>| {
+            let mut span = tracer
+                .span_builder(name)
+                .with_start_time(start)
+                .with_attributes(attributes)
+                .start_with_context(tracer, cx);
+            span.end_with_timestamp(end.max(start));
+        };
+
+        stage(
+            &cx,
+            "ticker",
+            self.received_at,
+            self.detect_start,
+            vec![
+                KeyValue::new("exchange", self.exchange.clone()),
+                KeyValue::new("symbol", self.symbol.clone()),
+            ],
+        );
+        stage(&cx, "strategy.detect", self.detect_start, self.detect_end, self.attributes.clone());
+        if let Some(start) = self.risk_start {
+            let passed = self.execution_start.is_some();
+            stage(
+                &cx,
+                "risk_check",
+                start,
+                self.risk_end.unwrap_or(start),
+                vec![KeyValue::new("passed", passed)],
+            );
+        }
+        if let Some(start) = self.execution_start {
+            let exec_end = self.execution_end.unwrap_or(start);
+            let span = tracer
+                .span_builder("execution")
+                .with_start_time(start)
+                .with_attributes(vec![
+                    KeyValue::new("verdict", verdict.to_string()),
+                    KeyValue::new("orders", orders.len() as i64),
+                ])
+                .start_with_context(tracer, &cx);
+            let exec_cx = cx.with_span(span);
+            for order in orders {
+                let fill_end = (start + Duration::from_millis(order.latency_ms)).min(exec_end.max(start));
+                stage(
+                    &exec_cx,
+                    "fill",
+                    start,
+                    fill_end,
+                    vec![
+                        KeyValue::new("exchange", format!("{:?}", order.exchange).to_lowercase()),
+                        KeyValue::new("symbol", order.symbol.clone()),
+                        KeyValue::new("side", format!("{:?}", order.side).to_lowercase()),
+                        KeyValue::new("status", format!("{:?}", order.status).to_lowercase()),
+                        KeyValue::new("filled_amount", order.filled_amount),
+                        KeyValue::new("avg_price", order.avg_price),
+                        KeyValue::new("fee", order.fee),
+                    ],
+                );
+            }
+            exec_cx.span().end_with_timestamp(exec_end.max(start));
+        }
+        cx.span().end_with_timestamp(end.max(self.received_at));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exchange::ExchangeId;
+    use crate::executor::{OrderSide, OrderStatus};
+    use crate::strategy::StrategyType;
+    use futures_util::future::BoxFuture;
+    use opentelemetry::trace::{SpanId, TracerProvider as _};
+    use opentelemetry_sdk::export::trace::{ExportResult, SpanData, SpanExporter};
+    use std::sync::{Arc, Mutex};
+
+    /// 内存导出器: 收集导出的 span
+    #[derive(Debug, Clone, Default)]
+    struct MemoryExporter(Arc<Mutex<Vec<SpanData>>>);
+
+    impl SpanExporter for MemoryExporter {
+        fn export(&mut self, batch: Vec<SpanData>) -> BoxFuture<'static, ExportResult> {
+            self.0.lock().unwrap().extend(batch);
+            Box::pin(std::future::ready(Ok(())))
+        }
+    }
+
+    /// 导出一条链路，返回全部 span
+    fn export(trace: &SignalTrace, verdict: &str, orders: &[OrderResponse]) -> Vec<SpanData> {
+        let exporter = MemoryExporter::default();
+        let provider = TracerProvider::builder().with_simple_exporter(exporter.clone()).build();
+        trace.export(&provider.tracer(TRACER_NAME), verdict, orders);
+        let spans = exporter.0.lock().unwrap().clone();
+        spans
+    }
+
+    fn order(symbol: &str, side: OrderSide) -> OrderResponse {
+        OrderResponse {
+            order_id: format!("{}-{:?}", symbol, side),
+            exchange: ExchangeId::Binance,
+            symbol: symbol.to_string(),
+            side,
+            status: OrderStatus::Filled,
+            filled_amount: 1.0,
+            avg_price: 100.0,
+            fee: 0.1,
+            latency_ms: 5,
+            other_fees: vec![],
+        }
+    }
+
+    fn attribute<'a>(span: &'a SpanData, key: &str) -> Option<&'a opentelemetry::Value> {
+        span.attributes.iter().find(|kv| kv.key.as_str() == key).map(|kv| &kv.value)
+    }
+
+    #[test]
+    fn executed_signal_exports_nested_stage_spans() {
+        let signal = Signal::new("tri", StrategyType::Triangular, ExchangeId::Binance, 0.002, 0.2, 0.8, "USDT->BTC->ETH->USDT", 0);
+        let received_at = SystemTime::now() - Duration::from_millis(10);
+        let mut trace = SignalTrace::detected(&signal, "BTC/USDT", received_at, received_at + Duration::from_millis(1));
+        trace.risk_started();
+        trace.risk_finished();
+        trace.execution_started();
+        trace.execution_finished();
+        let spans = export(&trace, "executed", &[order("BTC/USDT", OrderSide::Buy), order("ETH/USDT", OrderSide::Sell)]);
+
+        let named = |name: &str| spans.iter().filter(|s| s.name == name).collect::<Vec<_>>();
+        let root = named("opportunity")[0];
+        assert_eq!(root.parent_span_id, SpanId::INVALID);
+        assert_eq!(attribute(root, "verdict").map(|v| v.as_str().into_owned()), Some("executed".to_string()));
+        assert_eq!(
+            attribute(root, "correlation_id").map(|v| v.as_str().into_owned()),
+            Some(signal.correlation_id.clone())
+        );
+        let root_id = root.span_context.span_id();
+        for name in ["ticker", "strategy.detect", "risk_check", "execution"] {
+            let stage = named(name);
+            assert_eq!(stage.len(), 1, "{}", name);
+            assert_eq!(stage[0].parent_span_id, root_id, "{} 应挂在根 span 下", name);
+        }
+        // 每笔订单一个 fill，挂在 execution 下
+        let execution_id = named("execution")[0].span_context.span_id();
+        let fills = named("fill");
+        assert_eq!(fills.len(), 2);
+        assert!(fills.iter().all(|f| f.parent_span_id == execution_id));
+        // 同一条链路
+        let trace_id = root.span_context.trace_id();
+        assert!(spans.iter().all(|s| s.span_context.trace_id() == trace_id));
+        assert_eq!(spans.len(), 7);
+    }
+
+    #[test]
+    fn risk_blocked_signal_has_no_execution_span() {
+        let signal = Signal::new("grid", StrategyType::Grid, ExchangeId::Okx, 0.001, 0.1, 0.5, "BTC/USDT", 0);
+        let now = SystemTime::now();
+        let mut trace = SignalTrace::detected(&signal, "BTC/USDT", now, now);
+        trace.risk_started();
+        trace.risk_finished();
+        let spans = export(&trace, "risk_blocked", &[]);
+
+        let names: Vec<&str> = spans.iter().map(|s| s.name.as_ref()).collect();
+        assert!(!names.contains(&"execution") && !names.contains(&"fill"), "{:?}", names);
+        let risk = spans.iter().find(|s| s.name == "risk_check").unwrap();
+        assert_eq!(attribute(risk, "passed"), Some(&opentelemetry::Value::Bool(false)));
+    }
+}
@@ -0,0 +1,293 @@
+//! 交易对行情频率统计
+//!
+//! 按 (交易所, 交易对) 统计固定时间桶内的行情条数，保留最近若干个桶作为基线。
+//! 最近一个完整桶的频率低于基线的一定比例时标记为退化 (常见于单个交易对推送变慢而整体行情正常)。
+//! 统计结果写入状态接口 `/tick-rates`，开启持久化时同时写入 Redis `metrics:tick_rate` (字段为 `交易所:BASE/QUOTE`)。
+
+use anyhow::Result;
+use redis::aio::ConnectionManager;
+use redis::AsyncCommands;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet, VecDeque};
+use tracing::{info, warn};
+
+use crate::exchange::ExchangeId;
+use crate::symbols::normalize_symbol;
+
+/// Redis Hash (field 为 `exchange:SYMBOL`)
+const REDIS_KEY: &str = "metrics:tick_rate";
+
+/// 行情频率统计配置
+#[derive(Debug, Clone)]
+pub struct TickRateConfig {
+    /// 统计桶长度 (毫秒)
+    pub bucket_ms: i64,
+    /// 基线使用的历史桶数
+    pub baseline_buckets: usize,
+    /// 最近频率低于基线的该比例时视为退化
+    pub degraded_ratio: f64,
+    /// 基线频率 (条/秒) 低于该值的交易对不做判定，避免冷门交易对误报
+    pub min_baseline_rate: f64,
+    /// 是否写入 Redis
+    pub persist: bool,
+}
+
+impl Default for TickRateConfig {
+    fn default() -> Self {
+        Self {
+            bucket_ms: 10_000,
+            baseline_buckets: 30,
+            degraded_ratio: 0.3,
+            min_baseline_rate: 0.2,
+            persist: true,
+        }
+    }
+}
+
+impl TickRateConfig {
+    /// 从环境变量读取:
+    /// ENGINE_TICK_RATE_BUCKET_SECS、ENGINE_TICK_RATE_BASELINE_BUCKETS、ENGINE_TICK_RATE_DEGRADED_RATIO、
+    /// ENGINE_TICK_RATE_MIN_BASELINE、ENGINE_TICK_RATE_PERSIST
+    pub fn from_env() -> Self {
+        let mut config = Self::default();
+        let env = |name: &str| std::env::var(name).ok().filter(|v| !v.trim().is_empty());
+        if let Some(v) = env("ENGINE_TICK_RATE_BUCKET_SECS").and_then(|v| v.parse::<i64>().ok()) {
+            config.bucket_ms = v.max(1) * 1_000;
+        }
+        if let Some(v) = env("ENGINE_TICK_RATE_BASELINE_BUCKETS").and_then(|v| v.parse::<usize>().ok()) {
+            config.baseline_buckets = v.max(2);
+        }
+        if let Some(v) = env("ENGINE_TICK_RATE_DEGRADED_RATIO").and_then(|v| v.parse().ok()) {
+            config.degraded_ratio = v;
+        }
+        if let Some(v) = env("ENGINE_TICK_RATE_MIN_BASELINE").and_then(|v| v.parse().ok()) {
+            config.min_baseline_rate = v;
+        }
+        if let Some(v) = env("ENGINE_TICK_RATE_PERSIST") {
+            config.persist = !matches!(v.as_str(), "0" | "false" | "False");
+        }
+        config
+    }
+}
+
+/// 单个交易对的频率统计
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TickRateStat {
+    pub exchange: ExchangeId,
+    pub symbol: String,
+    /// 最近一个完整桶的频率 (条/秒)
+    pub rate: f64,
+    /// 之前各桶的平均频率 (条/秒)
+    pub baseline: f64,
+    pub degraded: bool,
+}
+
+#[derive(Debug, Default)]
+struct SymbolRate {
+    /// 当前桶起始时间
+    bucket_start: i64,
+    count: u32,
+    /// 已完成的桶 (旧 -> 新)
+    history: VecDeque<u32>,
+}
+
+/// 行情频率统计
+pub struct TickRateTracker {
+    config: TickRateConfig,
+    symbols: HashMap<(ExchangeId, String), SymbolRate>,
+    /// 当前处于退化状态的交易对
+    flagged: HashSet<(ExchangeId, String)>,
+}
+
+impl TickRateTracker {
+    pub fn new(config: TickRateConfig) -> Self {
+        Self {
+            config,
+            symbols: HashMap::new(),
+            flagged: HashSet::new(),
+        }
+    }
+
+    pub fn from_env() -> Self {
+        Self::new(TickRateConfig::from_env())
+    }
+
+    /// 记录一条行情 (使用本地接收时间)
+    pub fn record(&mut self, exchange: ExchangeId, symbol: &str, now: i64) {
+        let bucket_ms = self.config.bucket_ms;
+        let keep = self.config.baseline_buckets + 1;
+        let entry = self
+            .symbols
+            .entry((exchange, normalize_symbol(exchange, symbol)))
+            .or_insert_with(|| SymbolRate {
+                bucket_start: now - now.rem_euclid(bucket_ms),
+                ..Default::default()
+            });
+        roll(entry, now, bucket_ms, keep);
+        entry.count += 1;
+    }
+
+    /// 推进所有交易对的时间桶 (长时间无行情的交易对也能被判定为退化)
+    pub fn advance(&mut self, now: i64) {
+        let bucket_ms = self.config.bucket_ms;
+        let keep = self.config.baseline_buckets + 1;
+        for entry in self.symbols.values_mut() {
+            roll(entry, now, bucket_ms, keep);
+        }
+    }
+
+    /// 推进时间桶并返回最新统计，退化状态变化时记录日志
+    pub fn update(&mut self, now: i64) -> Vec<TickRateStat> {
+        self.advance(now);
+        let stats = self.snapshot();
+        let mut flagged = HashSet::new();
+        for stat in stats.iter().filter(|s| s.degraded) {
+            let key = (stat.exchange, stat.symbol.clone());
+            if !self.flagged.contains(&key) {
+                warn!(
+                    "{:?} {} 行情频率退化: {:.2} 条/秒 (基线 {:.2} 条/秒)",
+                    stat.exchange, stat.symbol, stat.rate, stat.baseline
+                );
+            }
+            flagged.insert(key);
+        }
+        for (exchange, symbol) in self.flagged.difference(&flagged) {
+            info!("{:?} {} 行情频率恢复", exchange, symbol);
+        }
+        self.flagged = flagged;
+        stats
+    }
+
+    /// 各交易对的频率统计 (退化的排在前面)
+    pub fn snapshot(&self) -> Vec<TickRateStat> {
+        let per_sec = 1_000.0 / self.config.bucket_ms as f64;
+        let mut out: Vec<TickRateStat> = self
+            .symbols
+            .iter()
+            .filter_map(|((exchange, symbol), entry)| {
+                let last = *entry.history.back()?;
+                let earlier = entry.history.len() - 1;
+                let rate = last as f64 * per_sec;
+                let baseline = if earlier == 0 {
+                    rate
+                } else {
+                    (entry.history.iter().sum::<u32>() - last) as f64 / earlier as f64 * per_sec
+                };
+                // 至少积累两个基线桶再判定，刚订阅的交易对不报退化
+                let degraded = earlier >= 2
+                    && baseline >= self.config.min_baseline_rate
+                    && rate < baseline * self.config.degraded_ratio;
+                Some(TickRateStat {
+                    exchange: *exchange,
+                    symbol: symbol.clone(),
+                    rate,
+                    baseline,
+                    degraded,
+                })
+            })
+            .collect();
+        out.sort_by(|a, b| b.degraded.cmp(&a.degraded).then_with(|| a.symbol.cmp(&b.symbol)));
+        out
+    }
+
+    /// 写入 Redis (未开启持久化时跳过)
+    pub async fn publish(&self, conn: &mut ConnectionManager, stats: &[TickRateStat]) -> Result<()> {
+        if !self.config.persist || stats.is_empty() {
+            return Ok(());
+        }
+        let mut fields = Vec::with_capacity(stats.len());
+        for stat in stats {
+            let field = format!("{}:{}", stat.exchange.key(), stat.symbol);
+            fields.push((field, serde_json::to_string(stat)?));
+        }
+        let _: () = conn.hset_multiple(REDIS_KEY, &fields).await?;
+        Ok(())
+    }
+}
+
+/// 把当前桶之前已结束的桶 (含中间没有行情的空桶) 移入历史
+fn roll(entry: &mut SymbolRate, now: i64, bucket_ms: i64, keep: usize) {
+    let elapsed = (now - entry.bucket_start) / bucket_ms;
+    if elapsed <= 0 {
+        return;
+    }
+    entry.history.push_back(entry.count);
+    for _ in 1..elapsed.min(keep as i64) {
+        entry.history.push_back(0);
+    }
+    while entry.history.len() > keep {
+        entry.history.pop_front();
+    }
+    entry.count = 0;
+    entry.bucket_start += elapsed * bucket_ms;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tracker() -> TickRateTracker {
+        TickRateTracker::new(TickRateConfig {
+            bucket_ms: 1_000,
+            baseline_buckets: 5,
+            degraded_ratio: 0.3,
+            min_baseline_rate: 1.0,
+            persist: false,
+        })
+    }
+
+    /// 在 [from_s, to_s) 的每个桶内记录 per_bucket 条行情
+    fn feed(tracker: &mut TickRateTracker, symbol: &str, from_s: i64, to_s: i64, per_bucket: u32) {
+        for s in from_s..to_s {
+            for i in 0..per_bucket {
+                tracker.record(ExchangeId::Binance, symbol, s * 1_000 + i as i64);
+            }
+        }
+    }
+
+    #[test]
+    fn slowed_symbol_is_flagged_while_healthy_one_is_not() {
+        let mut tracker = tracker();
+        feed(&mut tracker, "BTC/USDT", 0, 6, 10);
+        feed(&mut tracker, "ETH/USDT", 0, 6, 10);
+        // 第 6 秒 BTC 只剩 1 条，ETH 保持不变
+        feed(&mut tracker, "BTC/USDT", 6, 7, 1);
+        feed(&mut tracker, "ETH/USDT", 6, 7, 10);
+
+        let stats = tracker.update(7_000);
+        assert_eq!(stats.len(), 2);
+        // 退化的排在前面
+        assert_eq!(stats[0].symbol, "BTC/USDT");
+        assert!(stats[0].degraded);
+        assert!((stats[0].rate - 1.0).abs() < 1e-9);
+        assert!((stats[0].baseline - 10.0).abs() < 1e-9);
+        assert!(!stats[1].degraded);
+        assert!((stats[1].rate - 10.0).abs() < 1e-9);
+
+        // 恢复后不再标记
+        feed(&mut tracker, "BTC/USDT", 7, 8, 10);
+        feed(&mut tracker, "ETH/USDT", 7, 8, 10);
+        let stats = tracker.update(8_000);
+        assert!(stats.iter().all(|s| !s.degraded));
+    }
+
+    #[test]
+    fn silent_symbol_degrades_but_new_and_quiet_symbols_do_not() {
+        let mut tracker = tracker();
+        feed(&mut tracker, "BTC/USDT", 0, 4, 10);
+        // 冷门交易对: 基线低于 min_baseline_rate
+        feed(&mut tracker, "DOGE/USDT", 0, 4, 0);
+        tracker.record(ExchangeId::Binance, "DOGE/USDT", 0);
+        // 刚订阅的交易对只有一个完整桶
+        feed(&mut tracker, "ETH/USDT", 3, 4, 1);
+
+        // 之后没有任何行情，advance 仍把空桶计入历史
+        let stats = tracker.update(5_000);
+        let by_symbol = |symbol: &str| stats.iter().find(|s| s.symbol == symbol).unwrap();
+        assert!(by_symbol("BTC/USDT").degraded);
+        assert_eq!(by_symbol("BTC/USDT").rate, 0.0);
+        assert!(!by_symbol("DOGE/USDT").degraded);
+        assert!(!by_symbol("ETH/USDT").degraded);
+    }
+}
@@ -0,0 +1,161 @@
+//! 交易所时间戳归一化
+//!
+//! 各交易所 (以及同一交易所的不同频道) 的时间字段可能是毫秒字符串、秒 / 微秒数字、
+//! 带小数的秒或 ISO-8601 字符串。统一转换为 epoch 毫秒，避免混用单位破坏陈旧判断与延迟统计。
+//!
+//! 纯数字默认按数量级推断单位 (秒 / 毫秒 / 微秒 / 纳秒)，带小数的数字 (含数字字符串) 视为秒；
+//! 可通过 ENGINE_TIMESTAMP_UNITS 为交易所固定单位 (如 `gate:s,okx:ms`，单位 s / ms / us / ns / auto)。
+
+use std::collections::HashMap;
+
+use crate::exchange::ExchangeId;
+
+/// 数字时间戳的单位
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimestampUnit {
+    Auto,
+    Seconds,
+    Millis,
+    Micros,
+    Nanos,
+}
+
+impl TimestampUnit {
+    fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "auto" => Some(Self::Auto),
+            "s" | "sec" | "secs" | "seconds" => Some(Self::Seconds),
+            "ms" | "millis" => Some(Self::Millis),
+            "us" | "micros" => Some(Self::Micros),
+            "ns" | "nanos" => Some(Self::Nanos),
+            _ => None,
+        }
+    }
+
+    /// 按数量级推断单位 (2001 年之后的时间戳)
+    fn infer(value: f64) -> Self {
+        let magnitude = value.abs();
+        if magnitude < 1e11 {
+            Self::Seconds
+        } else if magnitude < 1e14 {
+            Self::Millis
+        } else if magnitude < 1e17 {
+            Self::Micros
+        } else {
+            Self::Nanos
+        }
+    }
+
+    fn to_millis(self, value: f64) -> i64 {
+        let unit = match self {
+            Self::Auto => Self::infer(value),
+            unit => unit,
+        };
+        let millis = match unit {
+            Self::Seconds => value * 1_000.0,
+            Self::Micros => value / 1_000.0,
+            Self::Nanos => value / 1_000_000.0,
+            _ => value,
+        };
+        millis.round() as i64
+    }
+}
+
+lazy_static::lazy_static! {
+    static ref UNIT_OVERRIDES: HashMap<ExchangeId, TimestampUnit> = std::env::var("ENGINE_TIMESTAMP_UNITS")
+        .map(|raw| parse_overrides(&raw))
+        .unwrap_or_default();
+}
+
+fn parse_overrides(raw: &str) -> HashMap<ExchangeId, TimestampUnit> {
+    raw.split(',')
+        .filter_map(|item| {
+            let (exchange, unit) = item.split_once(':')?;
+            let exchange =
+                serde_json::from_value(serde_json::Value::String(exchange.trim().to_lowercase())).ok()?;
+            Some((exchange, TimestampUnit::parse(unit)?))
+        })
+        .collect()
+}
+
+fn unit_for(exchange: ExchangeId) -> TimestampUnit {
+    UNIT_OVERRIDES
+        .get(&exchange)
+        .copied()
+        .unwrap_or(TimestampUnit::Auto)
+}
+
+/// 将交易所时间字段转换为 epoch 毫秒
+pub fn normalize_timestamp(exchange: ExchangeId, value: &serde_json::Value) -> Option<i64> {
+    normalize_with_unit(unit_for(exchange), value)
+}
+
+fn normalize_with_unit(unit: TimestampUnit, value: &serde_json::Value) -> Option<i64> {
+    match value {
+        serde_json::Value::Number(n) => {
+            let v = n.as_f64()?;
+            // 带小数的数字只可能是秒
+            if n.is_f64() && v.fract() != 0.0 && unit == TimestampUnit::Auto {
+                return Some(TimestampUnit::Seconds.to_millis(v));
+            }
+            Some(unit.to_millis(v))
+        }
+        serde_json::Value::String(s) => normalize_str(unit, s.trim()),
+        _ => None,
+    }
+}
+
+fn normalize_str(unit: TimestampUnit, s: &str) -> Option<i64> {
+    if let Ok(v) = s.parse::<i64>() {
+        return Some(unit.to_millis(v as f64));
+    }
+    if let Ok(v) = s.parse::<f64>() {
+        // "1700000000.123" 形式为带小数的秒
+        let unit = if unit == TimestampUnit::Auto { TimestampUnit::Seconds } else { unit };
+        return Some(unit.to_millis(v));
+    }
+    if let Ok(t) = chrono::DateTime::parse_from_rfc3339(s) {
+        return Some(t.timestamp_millis());
+    }
+    // 不带时区的 ISO-8601 视为 UTC
+    chrono::NaiveDateTime::parse_from_str(s, "%Y-%m-%dT%H:%M:%S%.f")
+        .ok()
+        .map(|t| t.and_utc().timestamp_millis())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const MS: i64 = 1_700_000_000_123;
+
+    #[test]
+    fn okx_millisecond_and_second_ts_agree() {
+        let auto = |v: serde_json::Value| normalize_with_unit(TimestampUnit::Auto, &v);
+        // OKX 行情 `ts` 为毫秒字符串；部分频道为秒 (整数或带小数)
+        assert_eq!(auto(json!("1700000000123")), Some(MS));
+        assert_eq!(auto(json!(1_700_000_000_123i64)), Some(MS));
+        assert_eq!(auto(json!("1700000000")), Some(1_700_000_000_000));
+        assert_eq!(auto(json!(1_700_000_000)), Some(1_700_000_000_000));
+        assert_eq!(auto(json!("1700000000.123")), Some(MS));
+        assert_eq!(auto(json!(1_700_000_000.123)), Some(MS));
+        assert_eq!(auto(json!(1_700_000_000_123_000i64)), Some(MS));
+        assert_eq!(auto(json!("2023-11-14T22:13:20.123Z")), Some(MS));
+        assert_eq!(auto(json!("2023-11-14T22:13:20.123")), Some(MS));
+        assert_eq!(auto(json!(null)), None);
+        assert_eq!(auto(json!("n/a")), None);
+    }
+
+    #[test]
+    fn configured_unit_overrides_magnitude_inference() {
+        let overrides = parse_overrides("OKX:ms, gate:s,bogus:ms,binance:years");
+        assert_eq!(overrides.len(), 2);
+        assert_eq!(overrides[&ExchangeId::Okx], TimestampUnit::Millis);
+        assert_eq!(overrides[&ExchangeId::Gate], TimestampUnit::Seconds);
+
+        // 固定为毫秒时，数量级像秒的值也按毫秒解释
+        assert_eq!(normalize_with_unit(TimestampUnit::Millis, &json!("1700000000")), Some(1_700_000_000));
+        assert_eq!(normalize_with_unit(TimestampUnit::Seconds, &json!(1_700_000_000.5)), Some(1_700_000_000_500));
+    }
+}
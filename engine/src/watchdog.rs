@@ -0,0 +1,175 @@
+//! 行情连接看门狗
+//!
+//! 连接仍在但行情停止推送时 (交易所侧静默卡住)，策略会继续使用缓存中的旧价格并可能发出错误信号。
+//! 这里定期读取各交易所连接的健康状态 (`ExchangeConnection::health`)，写入 Redis `metrics:exchange:<id>`；
+//! 行情静默超过阈值时强制断开重连，并向 `engine:status` 发布告警。
+
+use anyhow::Result;
+use redis::AsyncCommands;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use tracing::warn;
+
+use crate::db::SharedRedis;
+use crate::engine::STATUS_CHANNEL;
+use crate::exchange::{ExchangeConnection, ExchangeId, FeedHealth};
+
+/// Redis Key 前缀 (后接交易所 ID)
+const KEY_PREFIX: &str = "metrics:exchange:";
+
+/// 看门狗配置
+#[derive(Debug, Clone)]
+pub struct WatchdogConfig {
+    /// 检查 / 写入健康状态的间隔
+    pub interval: Duration,
+    /// 行情静默超过该时长 (毫秒) 时强制重连
+    pub stale_ms: i64,
+    /// 是否强制重连 (关闭时只写入健康状态)
+    pub reconnect: bool,
+}
+
+impl Default for WatchdogConfig {
+    fn default() -> Self {
+        Self {
+            interval: Duration::from_secs(5),
+            stale_ms: 30_000,
+            reconnect: true,
+        }
+    }
+}
+
+impl WatchdogConfig {
+    /// 从环境变量读取: ENGINE_FEED_HEALTH_INTERVAL_SECS、ENGINE_FEED_STALE_SECS、ENGINE_FEED_WATCHDOG
+    pub fn from_env() -> Self {
+        let mut config = Self::default();
+        let env = |name: &str| std::env::var(name).ok().filter(|v| !v.trim().is_empty());
+        if let Some(v) = env("ENGINE_FEED_HEALTH_INTERVAL_SECS").and_then(|v| v.parse::<u64>().ok()) {
+            config.interval = Duration::from_secs(v.max(1));
+        }
+        if let Some(v) = env("ENGINE_FEED_STALE_SECS").and_then(|v| v.parse::<i64>().ok()) {
+            config.stale_ms = v.max(1) * 1_000;
+        }
+        if let Some(v) = env("ENGINE_FEED_WATCHDOG") {
+            config.reconnect = !matches!(v.as_str(), "0" | "false" | "False");
+        }
+        config
+    }
+}
+
+/// 行情连接看门狗
+pub struct FeedWatchdog {
+    config: WatchdogConfig,
+    connections: HashMap<ExchangeId, Arc<ExchangeConnection>>,
+    redis: Option<SharedRedis>,
+}
+
+impl FeedWatchdog {
+    pub fn new(
+        config: WatchdogConfig,
+        connections: HashMap<ExchangeId, Arc<ExchangeConnection>>,
+        redis: Option<SharedRedis>,
+    ) -> Self {
+        Self {
+            config,
+            connections,
+            redis,
+        }
+    }
+
+    /// 后台定期检查
+    pub fn spawn(self) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(self.config.interval);
+            ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+            loop {
+                ticker.tick().await;
+                self.check().await;
+            }
+        })
+    }
+
+    /// 检查一次: 写入健康状态，对静默超时的连接强制重连，返回触发重连的交易所
+    pub async fn check(&self) -> Vec<ExchangeId> {
+        let mut stalled = Vec::new();
+        let mut reports = Vec::with_capacity(self.connections.len());
+        for (id, conn) in &self.connections {
+            let health = conn.health();
+            // 已停止或正在断线重连的连接交给重连逻辑处理
+            if self.config.reconnect && health.active && health.connected && health.silent_ms >= self.config.stale_ms {
+                warn!("{:?} 行情已静默 {}ms (阈值 {}ms)，强制重连", id, health.silent_ms, self.config.stale_ms);
+                conn.force_reconnect();
+                stalled.push(*id);
+                if let Err(e) = self.publish_alert(&health).await {
+                    warn!("发布行情静默告警失败: {}", e);
+                }
+            }
+            reports.push(health);
+        }
+        if let Err(e) = self.publish_health(&reports).await {
+            warn!("写入交易所连接健康状态失败: {}", e);
+        }
+        stalled
+    }
+
+    async fn publish_health(&self, reports: &[FeedHealth]) -> Result<()> {
+        let Some(redis) = &self.redis else {
+            return Ok(());
+        };
+        let mut conn = redis.connection().await?;
+        for health in reports {
+            let key = format!("{}{}", KEY_PREFIX, health.exchange.key());
+            let _: () = conn.set(key, serde_json::to_string(health)?).await?;
+        }
+        Ok(())
+    }
+
+    async fn publish_alert(&self, health: &FeedHealth) -> Result<()> {
+        let Some(redis) = &self.redis else {
+            return Ok(());
+        };
+        let payload = serde_json::json!({
+            "event": "alert",
+            "level": "warning",
+            "reason": "feed_stalled",
+            "exchange": health.exchange,
+            "silentMs": health.silent_ms,
+            "lastMessageAt": health.last_message_at,
+            "timestamp": health.timestamp,
+        });
+        let mut conn = redis.connection().await?;
+        let _: () = conn.publish(STATUS_CHANNEL, payload.to_string()).await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exchange::testing::recording_server;
+
+    #[tokio::test]
+    async fn silent_feed_is_forced_to_reconnect() {
+        let (url, mut frames) = recording_server(vec![]).await;
+        let conn = Arc::new(ExchangeConnection::new(ExchangeId::Okx).await.unwrap().with_ws_url(url));
+        conn.start(vec!["BTC/USDT".to_string()]).await.unwrap();
+        let (index, _) = tokio::time::timeout(Duration::from_secs(2), frames.recv()).await.unwrap().unwrap();
+        assert_eq!(index, 0);
+
+        let config = WatchdogConfig {
+            interval: Duration::from_secs(1),
+            stale_ms: 50,
+            reconnect: true,
+        };
+        let watchdog = FeedWatchdog::new(config, HashMap::from([(ExchangeId::Okx, conn.clone())]), None);
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert!(conn.health().silent_ms > 50);
+        assert_eq!(watchdog.check().await, vec![ExchangeId::Okx]);
+
+        // 强制重连后在新连接上重新订阅
+        let (index, _) = tokio::time::timeout(Duration::from_secs(2), frames.recv()).await.unwrap().unwrap();
+        assert_eq!(index, 1);
+        conn.stop().await;
+    }
+}
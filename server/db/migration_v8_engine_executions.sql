@@ -0,0 +1,28 @@
+-- ============================================
+-- 引擎执行审计: 每次信号执行 (模拟与实盘) 一行
+-- 由 Rust 引擎 db::record_execution 写入
+-- ============================================
+
+CREATE TABLE IF NOT EXISTS executions (
+    id BIGSERIAL PRIMARY KEY,
+    correlation_id VARCHAR(64) NOT NULL,
+    strategy_id VARCHAR(64) NOT NULL,
+    strategy_type VARCHAR(30) NOT NULL,
+    exchange_id VARCHAR(50) NOT NULL,
+    path TEXT,
+    action VARCHAR(10),
+    profit_rate DECIMAL(20, 10),
+    expected_profit DECIMAL(20, 8),
+    confidence DECIMAL(10, 6),
+    signal_at TIMESTAMP WITH TIME ZONE,
+    orders JSONB NOT NULL DEFAULT '[]'::jsonb, -- 逐笔订单回报 (订单号、方向、成交量、均价、手续费、延迟)
+    total_fee DECIMAL(20, 8) NOT NULL DEFAULT 0,
+    net_profit DECIMAL(20, 8) NOT NULL DEFAULT 0,
+    success BOOLEAN NOT NULL,
+    error TEXT, -- 执行失败的原因 (下单前被拒或下单出错时无订单回报)
+    executed_at TIMESTAMP WITH TIME ZONE DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_executions_strategy ON executions(strategy_id, executed_at DESC);
+CREATE INDEX IF NOT EXISTS idx_executions_correlation ON executions(correlation_id);
+CREATE INDEX IF NOT EXISTS idx_executions_executed ON executions(executed_at DESC);
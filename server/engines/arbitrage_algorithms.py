@@ -213,17 +213,49 @@ class TriangularArbitrage:
     """
     三角套利
     A -> B -> C -> A 的循环交易
+
+    use_full_spread 开启且三个交易对都有买一/卖一 (update_quote) 时，每条腿按方向吃对应一侧盘口
+    (买入用 ask，卖出用 bid)，两个方向分别计算并取较优者，收益为扣除三条腿价差后的净值；
+    否则退回单一价格 (update_price) 的简化计算。
     """
     
-    def __init__(self):
+    def __init__(self, use_full_spread: bool = True):
         self.min_profit_rate = 0.001  # 最小收益率 (0.1%)
+        self.use_full_spread = use_full_spread
         self.prices: Dict[str, float] = {}
+        self.quotes: Dict[str, Tuple[float, float]] = {}  # symbol -> (bid, ask)
         self.fees: Dict[str, float] = {}  # symbol -> 交易费率
     
     def update_price(self, symbol: str, price: float, fee: float = 0.001) -> None:
         """更新交易对价格"""
         self.prices[symbol] = price
         self.fees[symbol] = fee
+
+    def update_quote(self, symbol: str, bid: float, ask: float, fee: float = 0.001) -> None:
+        """更新交易对买一/卖一 (同时以中间价更新单一价格)"""
+        self.quotes[symbol] = (bid, ask)
+        self.update_price(symbol, (bid + ask) / 2.0, fee)
+
+    def _full_spread_route(self, symbol_a: str, symbol_b: str, symbol_c: str,
+                           initial_amount: float) -> Tuple[float, List[str], Dict[str, float]]:
+        """
+        按盘口两侧计算三角形 (A/Q, B/Q, B/A) 两个方向的最终金额，返回较优方向的
+        (最终金额, 成交顺序, 各交易对成交价)
+        - 正向: Q 买 A (A/Q ask) -> A 买 B (B/A ask) -> 卖 B 得 Q (B/Q bid)
+        - 反向: Q 买 B (B/Q ask) -> 卖 B 得 A (B/A bid) -> 卖 A 得 Q (A/Q bid)
+        """
+        bid_a, ask_a = self.quotes[symbol_a]
+        bid_b, ask_b = self.quotes[symbol_b]
+        bid_c, ask_c = self.quotes[symbol_c]
+        keep_a = 1 - self.fees.get(symbol_a, 0.001)
+        keep_b = 1 - self.fees.get(symbol_b, 0.001)
+        keep_c = 1 - self.fees.get(symbol_c, 0.001)
+
+        forward = initial_amount / ask_a * keep_a / ask_c * keep_c * bid_b * keep_b
+        reverse = initial_amount / ask_b * keep_b * bid_c * keep_c * bid_a * keep_a
+        if forward >= reverse:
+            return forward, [symbol_a, symbol_c, symbol_b], {symbol_a: ask_a, symbol_c: ask_c, symbol_b: bid_b}
+        return reverse, [symbol_b, symbol_c, symbol_a], {symbol_b: ask_b, symbol_c: bid_c, symbol_a: bid_a}
     
     def find_triangular_opportunities(self, 
                                       symbol_a: str, symbol_b: str, symbol_c: str,
@@ -245,6 +277,30 @@ class TriangularArbitrage:
         
         if symbol_a not in self.prices or symbol_b not in self.prices or symbol_c not in self.prices:
             return None
+
+        if self.use_full_spread and all(s in self.quotes for s in (symbol_a, symbol_b, symbol_c)):
+            try:
+                final_amount, order, used_prices = self._full_spread_route(
+                    symbol_a, symbol_b, symbol_c, initial_amount
+                )
+            except ZeroDivisionError:
+                return None
+            profit = final_amount - initial_amount
+            profit_rate = profit / initial_amount
+            if profit_rate > self.min_profit_rate:
+                return ArbitrageOpportunity(
+                    type='triangular',
+                    symbols=order,
+                    path=f"{order[0]} -> {order[1]} -> {order[2]} -> {order[0]}",
+                    entry_prices=used_prices,
+                    exit_prices=used_prices,
+                    expected_profit=profit,
+                    expected_profit_rate=profit_rate * 100,
+                    confidence=0.85,
+                    execution_time_ms=1000,
+                    timestamp=datetime.now()
+                )
+            return None
         
         try:
             # 路径1: A -> B -> C -> A
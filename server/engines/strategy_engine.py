@@ -52,6 +52,7 @@ class StrategyEngine:
         self.user_id: Optional[UUID] = None
         self._scan_interval_cache: Dict[str, tuple[float, float]] = {}
         self._scan_interval_ttl_seconds = 30.0
+        self._base_refresh_at: Dict[str, float] = {}
     
     @classmethod
     def get_instance(cls) -> 'StrategyEngine':
@@ -305,9 +306,14 @@ class StrategyEngine:
                 return
             if not base_currencies:
                 base_currencies = await self._get_top_base_currencies(exchange_id, limit=3)
+            elif config.get("auto_refresh_bases"):
+                base_currencies = await self._refresh_base_currencies(
+                    strategy_id, config, exchange_id, base_currencies
+                )
 
             repo = MarketDataRepository()
-            triangular = TriangularArbitrage()
+            # 默认按每条腿的买一/卖一计算 (扣除三条腿的价差)，关闭后退回中间价简化计算
+            triangular = TriangularArbitrage(use_full_spread=bool(config.get("use_full_spread", True)))
             triangular.min_profit_rate = min_profit_rate
 
             semaphore = asyncio.Semaphore(60)
@@ -315,39 +321,28 @@ class StrategyEngine:
             async def _fetch_price(pair):
                 async with semaphore:
                     tob = await repo.get_orderbook_tob(exchange_id, pair.symbol)
-                    bid = tob.best_bid_price
-                    ask = tob.best_ask_price
-                    if bid and ask:
-                        price = (float(bid) + float(ask)) / 2.0
-                    else:
-                        price = float(bid or ask or 0.0)
-                    return pair.symbol, price
+                    bid = float(tob.best_bid_price or 0.0)
+                    ask = float(tob.best_ask_price or 0.0)
+                    return pair.symbol, bid, ask
 
             results = await asyncio.gather(*[_fetch_price(p) for p in pairs], return_exceptions=True)
             for item in results:
                 if isinstance(item, Exception):
                     continue
-                symbol, price = item
-                if price and price > 0:
-                    triangular.update_price(symbol, price, fee_rate)
+                symbol, bid, ask = item
+                if bid > 0 and ask > 0:
+                    triangular.update_quote(symbol, bid, ask, fee_rate)
+                elif bid > 0 or ask > 0:
+                    triangular.update_price(symbol, bid or ask, fee_rate)
 
-            pairs_by_symbol = {p.symbol: p for p in pairs}
             opportunities = []
 
-            for base in base_currencies:
-                base_pairs = [p for p in pairs if p.quote == base]
-                for p1 in base_pairs:
-                    for p2 in base_pairs:
-                        if p1.base == p2.base:
-                            continue
-                        symbol_c = f"{p2.base}/{p1.base}"
-                        if symbol_c not in pairs_by_symbol:
-                            continue
-                        opp = triangular.find_triangular_opportunities(
-                            p1.symbol, p2.symbol, symbol_c, float(config.get("initial_amount", 1000.0))
-                        )
-                        if opp and (opp.expected_profit_rate / 100.0) >= min_profit_rate:
-                            opportunities.append(opp)
+            for symbol_a, symbol_b, symbol_c in _derive_triangles(pairs, base_currencies):
+                opp = triangular.find_triangular_opportunities(
+                    symbol_a, symbol_b, symbol_c, float(config.get("initial_amount", 1000.0))
+                )
+                if opp and (opp.expected_profit_rate / 100.0) >= min_profit_rate:
+                    opportunities.append(opp)
 
             if opportunities:
                 best = max(opportunities, key=lambda o: o.expected_profit_rate)
@@ -393,6 +388,7 @@ class StrategyEngine:
                         cfg = {}
                 if not cfg.get("base_currencies"):
                     cfg["base_currencies"] = await self._get_top_base_currencies(default_exchange_id, limit=3)
+                    cfg.setdefault("auto_refresh_bases", True)
                 if "regime_weights" not in cfg:
                     cfg["regime_weights"] = _default_regime_weights()
                 if "allow_short" not in cfg:
@@ -414,6 +410,7 @@ class StrategyEngine:
                 "min_profit_rate": 0.001,
                 "fee_rate": 0.0004,
                 "base_currencies": await self._get_top_base_currencies(default_exchange_id, limit=3),
+                "auto_refresh_bases": True,
                 "regime_weights": _default_regime_weights(),
                 "allow_short": False,
                 "max_leverage": 1.0,
@@ -479,6 +476,11 @@ class StrategyEngine:
             return []
 
     async def _get_top_base_currencies(self, exchange_id: str, limit: int = 3) -> List[str]:
+        top = await self._rank_base_currencies(exchange_id, limit=limit)
+        return top or ["USDT", "BTC", "ETH"]
+
+    async def _rank_base_currencies(self, exchange_id: str, limit: int = 3) -> List[str]:
+        """按 Redis 行情成交量排名 base 币种，无数据时返回空列表"""
         try:
             redis = await get_redis()
             symbols = await redis.smembers(f"symbols:ticker:{exchange_id}")
@@ -498,12 +500,48 @@ class StrategyEngine:
                     continue
                 volume_by_base[base] = volume_by_base.get(base, 0.0) + float(vol)
             ranked = sorted(volume_by_base.items(), key=lambda x: x[1], reverse=True)
-            top = [b for b, _ in ranked[: max(1, limit)]]
-            if top:
-                return top
+            return [b for b, _ in ranked[: max(1, limit)]]
         except Exception:
-            pass
-        return ["USDT", "BTC", "ETH"]
+            return []
+
+    async def _refresh_base_currencies(
+        self, strategy_id: str, config: dict, exchange_id: str, current: List[str]
+    ) -> List[str]:
+        """
+        定期按成交量重新排名 base 币种 (auto_refresh_bases 开启的策略，默认三角套利策略即是)
+        头部变化达到 base_refresh_min_changes 个币种时写回策略配置，三角形集合随之重建
+        """
+        interval = float(config.get("base_refresh_interval_seconds", 600))
+        if interval <= 0:
+            return current
+        now = asyncio.get_event_loop().time()
+        last = self._base_refresh_at.get(strategy_id)
+        if last is not None and (now - last) < interval:
+            return current
+        self._base_refresh_at[strategy_id] = now
+
+        ranked = await self._rank_base_currencies(exchange_id, limit=len(current) or 3)
+        min_changes = int(config.get("base_refresh_min_changes", 1))
+        if not _base_leaders_changed(current, ranked, min_changes):
+            return current
+
+        try:
+            pool = await get_pg_pool()
+            async with pool.acquire() as conn:
+                await conn.execute(
+                    """
+                    UPDATE strategy_configs
+                    SET config = jsonb_set(config, '{base_currencies}', $3::jsonb), updated_at = NOW()
+                    WHERE id = $1 AND user_id = $2
+                    """,
+                    strategy_id,
+                    self.user_id,
+                    json.dumps(ranked, ensure_ascii=False),
+                )
+        except Exception as e:
+            logger.warning(f"写回 base 币种失败: {e}")
+        logger.info(f"三角套利 base 币种已刷新: exchange={exchange_id} {current} -> {ranked}")
+        return ranked
 
     def _split_symbol(self, symbol: str) -> Tuple[Optional[str], Optional[str]]:
         if not symbol:
@@ -899,6 +937,31 @@ def _regime_allows_strategy(config: dict, regime: str) -> bool:
     return value > 0
 
 
+def _base_leaders_changed(current: List[str], ranked: List[str], min_changes: int = 1) -> bool:
+    """成交量头部是否明显变化: 新进入头部的币种数达到 min_changes (仅排名顺序变化不算)"""
+    if not ranked:
+        return False
+    entered = set(ranked) - set(current)
+    return len(entered) >= max(1, min_changes)
+
+
+def _derive_triangles(pairs: List[TradingPair], base_currencies: List[str]) -> List[Tuple[str, str, str]]:
+    """按 base 币种从交易对中推导三角形 (A/base, B/base, B/A)"""
+    pairs_by_symbol = {p.symbol: p for p in pairs}
+    triangles: List[Tuple[str, str, str]] = []
+    for base in base_currencies:
+        base_pairs = [p for p in pairs if p.quote == base]
+        for p1 in base_pairs:
+            for p2 in base_pairs:
+                if p1.base == p2.base:
+                    continue
+                symbol_c = f"{p2.base}/{p1.base}"
+                if symbol_c not in pairs_by_symbol:
+                    continue
+                triangles.append((p1.symbol, p2.symbol, symbol_c))
+    return triangles
+
+
 def _default_regime_weights() -> dict:
     return {
         "RANGE": 1.0,
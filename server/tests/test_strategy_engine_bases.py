@@ -0,0 +1,92 @@
+"""
+默认三角套利 base 币种刷新测试
+成交量排名变化后，刷新得到的 base 币种与三角形集合应反映新的头部币种
+"""
+import pytest
+from unittest.mock import patch, MagicMock, AsyncMock
+
+from server.services.config_service import TradingPair
+from server.engines import strategy_engine
+from server.engines.strategy_engine import StrategyEngine, _base_leaders_changed, _derive_triangles
+
+
+def _pair(symbol: str) -> TradingPair:
+    base, quote = symbol.split("/")
+    return TradingPair(symbol=symbol, base=base, quote=quote, supported_exchanges=["binance"])
+
+
+PAIRS = [
+    _pair(s)
+    for s in (
+        "ETH/USDT", "SOL/USDT", "ETH/BTC", "SOL/BTC",
+        "BTC/ETH", "SOL/ETH", "BTC/SOL", "ETH/SOL",
+    )
+]
+
+
+def _mock_pool():
+    conn = MagicMock()
+    conn.execute = AsyncMock()
+    acquire = MagicMock()
+    acquire.__aenter__ = AsyncMock(return_value=conn)
+    acquire.__aexit__ = AsyncMock(return_value=False)
+    pool = MagicMock()
+    pool.acquire.return_value = acquire
+    return pool, conn
+
+
+def test_base_leaders_changed():
+    """仅排名顺序变化不算变化，新币种进入头部才算"""
+    assert _base_leaders_changed(["BTC", "ETH", "SOL"], ["ETH", "BTC", "SOL"]) is False
+    assert _base_leaders_changed(["BTC", "ETH", "SOL"], ["BTC", "ETH", "DOGE"]) is True
+    assert _base_leaders_changed(["BTC", "ETH", "SOL"], ["BTC", "ETH", "DOGE"], min_changes=2) is False
+    assert _base_leaders_changed(["BTC", "ETH", "SOL"], []) is False
+
+
+class TestRefreshBaseCurrencies:
+    """测试 base 币种定期刷新"""
+
+    @pytest.mark.asyncio
+    async def test_ranking_change_rebuilds_triangles(self):
+        """成交量排名变化后写回配置，三角形集合随新头部重建"""
+        engine = StrategyEngine()
+        engine.user_id = "user-1"
+        config = {"auto_refresh_bases": True, "base_refresh_interval_seconds": 600}
+        current = ["USDT", "BTC"]
+        pool, conn = _mock_pool()
+
+        with patch.object(strategy_engine, "get_pg_pool", new_callable=AsyncMock, return_value=pool), \
+                patch.object(engine, "_rank_base_currencies", new_callable=AsyncMock) as rank:
+            rank.return_value = ["BTC", "USDT"]
+            bases = await engine._refresh_base_currencies("s1", config, "binance", current)
+            assert bases == current
+            conn.execute.assert_not_called()
+
+            # 刷新间隔内不重新排名
+            rank.return_value = ["ETH", "SOL"]
+            bases = await engine._refresh_base_currencies("s1", config, "binance", current)
+            assert bases == current
+            assert rank.await_count == 1
+
+            engine._base_refresh_at.clear()
+            bases = await engine._refresh_base_currencies("s1", config, "binance", current)
+
+        assert bases == ["ETH", "SOL"]
+        conn.execute.assert_awaited_once()
+        assert '["ETH", "SOL"]' in conn.execute.await_args.args
+
+        before = set(_derive_triangles(PAIRS, current))
+        after = set(_derive_triangles(PAIRS, bases))
+        assert ("ETH/USDT", "SOL/USDT", "SOL/ETH") in before
+        assert ("BTC/ETH", "SOL/ETH", "SOL/BTC") in after
+        assert all(t[0].split("/")[1] in ("ETH", "SOL") for t in after)
+        assert before != after
+
+    @pytest.mark.asyncio
+    async def test_no_ranking_data_keeps_current(self):
+        """没有成交量数据时保留当前 base 币种"""
+        engine = StrategyEngine()
+        config = {"auto_refresh_bases": True}
+        with patch.object(engine, "_rank_base_currencies", new_callable=AsyncMock, return_value=[]):
+            bases = await engine._refresh_base_currencies("s1", config, "binance", ["USDT", "BTC"])
+        assert bases == ["USDT", "BTC"]
@@ -0,0 +1,48 @@
+"""
+三角套利全价差计算测试
+单一价格计算有利可图、但三条腿都跨越宽价差后无利可图的三角形不应上报机会
+"""
+from server.engines.arbitrage_algorithms import TriangularArbitrage
+
+MIDS = {"ETH/USDT": 2000.0, "SOL/USDT": 100.0, "SOL/ETH": 0.0495}
+WIDE = {"ETH/USDT": (1990.0, 2010.0), "SOL/USDT": (99.5, 100.5), "SOL/ETH": (0.049, 0.050)}
+
+
+def _arb(quotes) -> TriangularArbitrage:
+    arb = TriangularArbitrage()
+    for symbol, (bid, ask) in quotes.items():
+        arb.update_quote(symbol, bid, ask, fee=0.0)
+    return arb
+
+
+def test_single_price_overstates_profit():
+    """按中间价计算约 1% 收益，按买一/卖一计算两个方向均亏损"""
+    naive = _arb({s: (p, p) for s, p in MIDS.items()})
+    opp = naive.find_triangular_opportunities("ETH/USDT", "SOL/USDT", "SOL/ETH", 1000.0)
+    assert opp is not None
+    assert opp.path == "ETH/USDT -> SOL/ETH -> SOL/USDT -> ETH/USDT"
+    assert abs(opp.expected_profit_rate - 1.0101) < 0.01
+
+    full = _arb(WIDE)
+    assert full.find_triangular_opportunities("ETH/USDT", "SOL/USDT", "SOL/ETH", 1000.0) is None
+    final, order, prices = full._full_spread_route("ETH/USDT", "SOL/USDT", "SOL/ETH", 1000.0)
+    assert final < 1000.0
+    assert prices == {"ETH/USDT": 2010.0, "SOL/ETH": 0.050, "SOL/USDT": 99.5}
+
+
+def test_full_spread_picks_profitable_direction():
+    """反向 (先买 B) 有利时按反向成交顺序上报，成交价取对应一侧"""
+    arb = _arb({"ETH/USDT": (2000.0, 2001.0), "SOL/USDT": (99.9, 100.0), "SOL/ETH": (0.0510, 0.0511)})
+    opp = arb.find_triangular_opportunities("ETH/USDT", "SOL/USDT", "SOL/ETH", 1000.0)
+    assert opp is not None
+    assert opp.symbols == ["SOL/USDT", "SOL/ETH", "ETH/USDT"]
+    assert opp.entry_prices == {"SOL/USDT": 100.0, "SOL/ETH": 0.0510, "ETH/USDT": 2000.0}
+
+
+def test_full_spread_can_be_disabled():
+    """关闭后退回单一价格计算"""
+    arb = TriangularArbitrage(use_full_spread=False)
+    for symbol, (bid, ask) in WIDE.items():
+        arb.update_quote(symbol, bid, ask, fee=0.0)
+    assert arb.prices["ETH/USDT"] == 2000.0
+    assert arb.use_full_spread is False